@@ -14,6 +14,7 @@ mod message;
 #[allow(dead_code)]
 mod numeric_reply;
 mod parser;
+mod tls;
 use gtk::{
     prelude::{ApplicationExt, ApplicationExtManual},
     Application,
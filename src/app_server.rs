@@ -15,6 +15,7 @@ mod message;
 mod numeric_reply;
 mod parser;
 mod server_utils;
+mod tls;
 use std::{env, result::Result, string::String};
 
 use crate::server_utils::server::Server;
@@ -1,18 +1,40 @@
 use crate::client_utils::client::gtk_methods::find_user_by_current_buffer;
+use crate::client_utils::client::gtk_methods::get_object;
+use crate::client_utils::client::gtk_methods::is_scrolled_to_bottom;
+use crate::client_utils::client::gtk_methods::scroll_chat_to_bottom;
 use crate::client_utils::client::gtk_methods::send_privmsg;
+use crate::client_utils::client::gtk_methods::should_notify;
+use crate::client_utils::client::gtk_methods::show_typing_label;
 use crate::client_utils::client::gtk_methods::wait_connection_dcc_file;
+use crate::client_utils::client::gtk_methods::ChannelMemory;
+use crate::client_utils::client::gtk_methods::CONFIRMABLE_COMMANDS;
+use crate::client_utils::dcc_checksum;
+use crate::client_utils::dcc_cipher::{self, DccCipher};
+use crate::client_utils::dcc_connection::{
+    self, DCC_FILE_CONNECT_TIMEOUT, DCC_FILE_TRANSFER_TIMEOUT, DEFAULT_DCC_CHAT_TIMEOUT,
+};
+use crate::client_utils::dcc_download::{self, RequestedResumes, DEFAULT_DOWNLOAD_DIRECTORY};
+use crate::client_utils::dcc_offers::{PendingDccOffers, DCC_OFFER_EXPIRATION};
+use crate::client_utils::dcc_progress;
+use crate::client_utils::typing::{typing_notice, TypingNotifier, TypingUsers, TYPING_DONE};
 use crate::commands::DCC_ACCEPT;
+use crate::commands::DCC_ACCEPT_OFFER;
 use crate::commands::DCC_CLOSE;
+use crate::commands::DCC_ENCRYPTED;
+use crate::commands::DCC_TIMEOUT;
 use crate::commands::PAUSE;
-use crate::parser;
+use crate::commands::TLS_ON;
+use crate::commands::{TYPING, TYPING_NOTIFICATION};
+use crate::parser::{self, irc_lowercase};
+use crate::tls;
 use gtk::glib;
 use gtk::prelude::*;
 use gtk::Builder;
 use gtk::TextBuffer;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fs::File;
 use std::fs::OpenOptions;
-use std::io::BufRead;
 use std::io::BufReader;
 use std::io::Read;
 use std::io::Seek;
@@ -22,10 +44,10 @@ use std::net::Shutdown;
 use std::net::TcpListener;
 use std::net::TcpStream;
 use std::path::PathBuf;
-use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::mpsc::{self, Receiver, SendError, Sender, TryRecvError};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 #[path = "gtk_methods.rs"]
 mod gtk_methods;
@@ -36,26 +58,40 @@ mod gtk_login;
 #[path = "gtk_connect.rs"]
 mod gtk_connect;
 
-#[path = "ui_updater.rs"]
-mod ui_updater;
-
-#[path = "message_types.rs"]
-mod message_types;
+use crate::client_utils::frontend::Frontend;
+use crate::client_utils::irc_formatting::{self, FormattedSpan};
+use crate::client_utils::message_types;
+use crate::client_utils::nick_completion::ChannelMembers;
+use crate::client_utils::ui_listener::ui_listener;
+use crate::client_utils::ui_updater;
 
 use crate::commands::{
-    ADD_LIST_CHATS, CONNECTION_ATTEMPT, CORRECT_LOGIN, CORRECT_REGISTRATION, DCC_CHAT, DCC_RESUME,
-    DCC_SEND, ERROR_CHANNEL, INVALID_LOGIN, INVALID_REGISTRATION, KICK_CHANNEL, LIST_CHANNELS,
-    PART_CHANNEL, QUIT, RECEIVED_MESSAGE, SEARCH_USERS,
+    ADD_LIST_CHATS, ANNOUNCE_TOPICS, CONFIRM, CONNECTION_ATTEMPT, CONNECTION_PROGRESS,
+    CORRECT_LOGIN, CORRECT_REGISTRATION, DCC_CHAT, DCC_RESUME, DCC_SEND, ERROR_CHANNEL, EVENTS,
+    EVENTS_COLLAPSED, EVENTS_HIDDEN, EVENTS_INLINE, IGNORE, INVALID_LOGIN, INVALID_REGISTRATION,
+    KICK_CHANNEL, LIST_CHANNELS, MAX_FILE_SIZE, MAX_FILE_SIZE_OFF, MUTE, NOTICE, PART_CHANNEL,
+    QUIT, RAW, RECEIVED_MESSAGE, RECONNECT, SEARCH_USERS, SQUIT, UNIGNORE, UNMUTE,
 };
 use crate::custom_errors::client_error::ClientError;
 use crate::custom_errors::errors::LOCK_DCC;
-use crate::custom_errors::errors::{CRITICAL, NONCRITICAL, RECEIVE_MESSAGE, SEND_MESSAGE};
+use crate::custom_errors::errors::{CRITICAL, NONCRITICAL, SEND_MESSAGE};
 use crate::message::Message;
 
 use self::gtk_connect::WindowConnect;
-use self::gtk_login::WindowLogin;
+use self::gtk_login::{Credentials, WindowLogin};
+use self::message_types::NOTICE as NOTICE_MESSAGE;
+use self::message_types::RAW as RAW_MESSAGE;
 use self::message_types::{ERROR, INFO, PRIVATE_MESSAGE};
 
+// Commands that ask for confirmation unless the user turns it off
+pub const DEFAULT_CONFIRM_COMMANDS: [&str; 2] = [QUIT, SQUIT];
+
+// Files offered bigger than this, in bytes, are warned before being accepted (1 GB)
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 1024 * 1024 * 1024;
+
+// Style class used to highlight the chats with unread messages
+pub const UNREAD_CHAT_CLASS: &str = "suggested-action";
+
 // This is the main struct of the client
 pub struct Client {
     pub application: gtk::Application,
@@ -69,6 +105,44 @@ pub struct Client {
     pub dcc_chats: Arc<Mutex<HashMap<String, Sender<Message>>>>,
     // Hashmap of files being sent, this is to keep track of the file path if a file transfer is not completed
     pub dcc_file_paths: Arc<Mutex<HashMap<String, PathBuf>>>,
+    // Resumes asked with the resume button, only their DCC ACCEPTs continue a download
+    pub requested_resumes: Arc<Mutex<RequestedResumes>>,
+    // Files offered that weren't answered yet, they can be accepted with /dcc_accept
+    pub pending_dcc_offers: Arc<Mutex<PendingDccOffers>>,
+    // Directory where the files received are saved, chosen in the chat window
+    pub download_directory: Arc<Mutex<PathBuf>>,
+    // Set of muted channels, they still receive messages but don't notify the user
+    pub muted_channels: Arc<Mutex<HashSet<String>>>,
+    // Nicknames in lowercase of the users whose messages are not shown, kept until the client is closed
+    pub ignored_users: Arc<Mutex<HashSet<String>>>,
+    // If true, every message received from the server is also printed as it was received
+    pub raw_mode: Arc<Mutex<bool>>,
+    // Commands that ask for confirmation before being sent
+    pub confirm_commands: Arc<Mutex<HashSet<String>>>,
+    // Keys of the channels joined and the last channel parted, used by /rejoin
+    pub channel_memory: Arc<Mutex<ChannelMemory>>,
+    // If true, topic changes in channels are shown in the channel chat
+    pub announce_topic_changes: Arc<Mutex<bool>>,
+    // How joins, parts and quits are shown: inline, hidden or collapsed in a summary
+    pub membership_events: Arc<Mutex<String>>,
+    // Files offered bigger than this size are warned before accepting them, None to never warn
+    pub max_file_size: Arc<Mutex<Option<u64>>>,
+    // Time a DCC chat offered waits for the other user, and the accepted one waits to connect
+    pub dcc_chat_timeout: Arc<Mutex<Duration>>,
+    // If true, messages are shown with the time they were received
+    pub show_timestamps: Arc<Mutex<bool>>,
+    // If true, colors and other formatting codes are shown, if not they are removed
+    pub show_formatting: Arc<Mutex<bool>>,
+    // If true, the users of private chats are told when the user is typing, and shown when they are
+    pub typing_notifications: Arc<Mutex<bool>>,
+    // Users that said they are typing a message to the user
+    pub typing_users: Arc<Mutex<TypingUsers>>,
+    // Chat told that the user is typing in the message entry
+    pub typing_notifier: Arc<Mutex<TypingNotifier>>,
+    // Users of each channel, kept by the UI updater to complete their nicknames
+    pub channel_members: Arc<Mutex<ChannelMembers>>,
+    // Credentials of the user logged in, used to log in again after reconnecting
+    pub session: Arc<Mutex<Option<Credentials>>>,
     // Gtk builder.
     pub builder: Builder,
     // Gtk login window
@@ -101,6 +175,29 @@ impl Client {
             channels: Arc::new(Mutex::new(Vec::new())),
             dcc_chats: Arc::new(Mutex::new(HashMap::new())),
             dcc_file_paths: Arc::new(Mutex::new(HashMap::new())),
+            requested_resumes: Arc::new(Mutex::new(RequestedResumes::default())),
+            pending_dcc_offers: Arc::new(Mutex::new(PendingDccOffers::default())),
+            download_directory: Arc::new(Mutex::new(PathBuf::from(DEFAULT_DOWNLOAD_DIRECTORY))),
+            muted_channels: Arc::new(Mutex::new(HashSet::new())),
+            ignored_users: Arc::new(Mutex::new(HashSet::new())),
+            raw_mode: Arc::new(Mutex::new(false)),
+            confirm_commands: Arc::new(Mutex::new(HashSet::from_iter(
+                DEFAULT_CONFIRM_COMMANDS
+                    .iter()
+                    .map(|command| command.to_string()),
+            ))),
+            channel_memory: Arc::new(Mutex::new(ChannelMemory::default())),
+            announce_topic_changes: Arc::new(Mutex::new(true)),
+            membership_events: Arc::new(Mutex::new(EVENTS_INLINE.to_string())),
+            max_file_size: Arc::new(Mutex::new(Some(DEFAULT_MAX_FILE_SIZE))),
+            dcc_chat_timeout: Arc::new(Mutex::new(DEFAULT_DCC_CHAT_TIMEOUT)),
+            show_timestamps: Arc::new(Mutex::new(true)),
+            show_formatting: Arc::new(Mutex::new(true)),
+            typing_notifications: Arc::new(Mutex::new(true)),
+            typing_users: Arc::new(Mutex::new(TypingUsers::default())),
+            typing_notifier: Arc::new(Mutex::new(TypingNotifier::default())),
+            channel_members: Arc::new(Mutex::new(ChannelMembers::default())),
+            session: Arc::new(Mutex::new(None)),
             builder,
             window_login,
             window_connect,
@@ -127,13 +224,33 @@ impl Client {
         let (tx_stream_1, rx_stream_1): (Sender<TcpStream>, Receiver<TcpStream>) = mpsc::channel();
         // Communication channel for sending the stream to the UI Listener after a successful connection
         let (tx_stream_2, rx_stream_2): (Sender<TcpStream>, Receiver<TcpStream>) = mpsc::channel();
+        // Communication channel for the UI Updater to tell the UI Listener the labels acknowledged
+        let (tx_acknowledgements, rx_acknowledgements): (Sender<u64>, Receiver<u64>) =
+            mpsc::channel();
 
         let tx_backend_clone = tx_backend.clone();
 
         // Create thread that listens to TCP messages and updates the UI through the channel
         let online_users_names_clone = self.online_chats_names.clone();
+        let raw_mode_clone = self.raw_mode.clone();
+        let announce_topic_changes_clone = self.announce_topic_changes.clone();
+        let membership_events_clone = self.membership_events.clone();
+        let channel_members_clone = self.channel_members.clone();
+        let ignored_users_clone = self.ignored_users.clone();
+        let typing_notifications_clone = self.typing_notifications.clone();
         let _ = thread::spawn(move || {
-            match ui_updater::ui_updater(tx_backend, rx_stream_1, &online_users_names_clone) {
+            match ui_updater::ui_updater(
+                tx_backend,
+                rx_stream_1,
+                tx_acknowledgements,
+                &online_users_names_clone,
+                &raw_mode_clone,
+                &announce_topic_changes_clone,
+                &membership_events_clone,
+                &channel_members_clone,
+                &ignored_users_clone,
+                &typing_notifications_clone,
+            ) {
                 Ok(_) => {}
                 Err(err_message) => {
                     println!("Error in ui_updater {}", err_message)
@@ -142,10 +259,12 @@ impl Client {
         });
 
         // Create thread that listens to UI changes and writes to the socket through the channel
-        let _ = thread::spawn(move || match ui_listener(rx_backend, rx_stream_2) {
-            Ok(_) => {}
-            Err(err_message) => {
-                println!("Error in ui_listener {}", err_message)
+        let _ = thread::spawn(move || {
+            match ui_listener(rx_backend, rx_stream_2, rx_acknowledgements) {
+                Ok(_) => {}
+                Err(err_message) => {
+                    println!("Error in ui_listener {}", err_message)
+                }
             }
         });
 
@@ -195,8 +314,13 @@ impl Client {
                     tx_backend.clone(),
                     &mut users,
                     &mut buffers,
+                    &channels,
                 ),
-                INVALID_LOGIN => self.window_login.invalid_login(),
+                INVALID_LOGIN => self.invalid_login(&mut buffers),
+                RECONNECT => self.connection_lost(&mut buffers),
+                CONNECTION_PROGRESS => self
+                    .window_login
+                    .connection_progress(message.params[0][0].clone()),
                 CORRECT_REGISTRATION => self.correct_registration(
                     &message,
                     tx_frontend.clone(),
@@ -211,12 +335,36 @@ impl Client {
                     self.add_list_chats(&message, &mut users, &mut buffers, &mut channels)
                 }
                 RECEIVED_MESSAGE => self.received_message(&message, &mut buffers, &mut channels),
-                LIST_CHANNELS => self.list_channels(message),
+                LIST_CHANNELS => self.list_channels(message, &tx_frontend),
                 SEARCH_USERS => self.search_users(message, &tx_backend),
                 PART_CHANNEL => self.delete_chat(&message, &mut users, &mut buffers, &mut channels),
                 KICK_CHANNEL => self.delete_chat(&message, &mut users, &mut buffers, &mut channels),
+                MUTE => self.mute_channel(&message, &mut buffers, &channels, true),
+                UNMUTE => self.mute_channel(&message, &mut buffers, &channels, false),
+                IGNORE => self.ignore_user(&message, &mut buffers, true),
+                UNIGNORE => self.ignore_user(&message, &mut buffers, false),
+                RAW => self.toggle_raw_mode(&mut buffers),
+                CONFIRM => self.toggle_confirmation(&message, &mut buffers),
+                ANNOUNCE_TOPICS => self.toggle_topic_announcements(&mut buffers),
+                TYPING => self.toggle_typing_notifications(&tx_frontend, &mut buffers),
+                TYPING_NOTIFICATION => self.typing_notification(&message, &current_name_chat),
+                EVENTS => self.set_membership_events(&message, &mut buffers),
+                MAX_FILE_SIZE => self.set_max_file_size(&message, &mut buffers),
+                DCC_TIMEOUT => self.set_dcc_chat_timeout(&message, &mut buffers),
                 ERROR_CHANNEL => self.error_channel(&message),
                 QUIT => {
+                    // If the server closed the connection the user is told why before closing
+                    if let Some(reason) = message.params.first().and_then(|param| param.first()) {
+                        let dialog = gtk::MessageDialog::builder()
+                            .transient_for(&self.window)
+                            .modal(true)
+                            .message_type(gtk::MessageType::Info)
+                            .buttons(gtk::ButtonsType::Ok)
+                            .text(reason)
+                            .build();
+                        dialog.run();
+                        dialog.close();
+                    }
                     gtk::main_quit();
                     return glib::Continue(false);
                 }
@@ -242,7 +390,8 @@ impl Client {
                     current_name_chat,
                 ),
                 DCC_CLOSE => self.close_dcc(message, &tx_backend),
-                DCC_ACCEPT => self.join_dcc(message, &tx_backend),
+                DCC_ACCEPT => self.accept_requested_resume(message, &tx_backend),
+                DCC_ACCEPT_OFFER => self.accept_pending_dcc(&message, &tx_backend),
 
                 _ => println!("Undefined message received by the client"),
             }
@@ -266,6 +415,7 @@ impl Client {
         let server_name = message.params[0][0].clone();
         let server_ip = message.params[0][1].clone();
         let server_port = message.params[0][2].clone();
+        let use_tls = message.params[0][3] == TLS_ON;
 
         let address = format!("{}:{}", server_ip, server_port);
 
@@ -277,12 +427,29 @@ impl Client {
             Err(_) => {
                 println!("Couldn't connect to server");
                 // Show error message in the connect window
-                self.window_connect.connection_error();
+                self.window_connect
+                    .connection_error("Couldn't connect to server");
                 return;
             }
         };
+
+        // If the encrypted connection fails it is not retried without encryption,
+        // the user must choose it in the connect window
+        let stream = match use_tls {
+            true => match tls::client_config()
+                .and_then(|config| tls::connect(stream, &server_ip, config))
+            {
+                Ok(stream) => stream,
+                Err(error) => {
+                    println!("Couldn't start TLS: {}", error);
+                    self.window_connect
+                        .connection_error("Couldn't establish an encrypted connection");
+                    return;
+                }
+            },
+            false => stream,
+        };
         self.window_connect.hide();
-        self.window_login.show(tx_frontend, server_ip, server_name);
 
         let stream_clone = stream.try_clone().expect("Couldn't clone the stream");
         tx_stream_1
@@ -303,6 +470,79 @@ impl Client {
                 }
             })
             .ok();
+
+        // After reconnecting the user logs in again with the same credentials, the streams
+        // are sent first so the UI listener sends the login through the new connection
+        let session = self.session.lock().expect("Couldn't lock session").clone();
+        match session {
+            Some(credentials) => {
+                for message in gtk_login::login_messages(&credentials, &server_ip, &server_name) {
+                    tx_frontend
+                        .send(message)
+                        .map_err(|_| -> ClientError {
+                            ClientError {
+                                kind: NONCRITICAL.to_string(),
+                                message: SEND_MESSAGE.to_string(),
+                            }
+                        })
+                        .ok();
+                }
+            }
+            None => self.window_login.show(tx_frontend, server_ip, server_name),
+        }
+    }
+
+    ///
+    /// Handles the connection with the server being closed. If the user was logged in the connect
+    /// window is shown again, the chats are kept to continue them after reconnecting
+    ///
+    fn connection_lost(&self, buffers: &mut HashMap<String, TextBuffer>) {
+        if self
+            .session
+            .lock()
+            .expect("Couldn't lock session")
+            .is_none()
+        {
+            self.window_login
+                .connection_progress("The connection with the server was lost".to_string());
+            return;
+        }
+
+        if let Some(buffer) = buffers.get("You") {
+            let message_to_print = format!(
+                "{} {}\r\n",
+                "@ERROR", "The connection with the server was lost, connect again to continue"
+            );
+            buffer.insert(&mut buffer.end_iter(), &message_to_print);
+        }
+        self.window_connect
+            .show_again("The connection was lost, connect again");
+    }
+
+    ///
+    /// Handles an invalid login. If it happened logging in again after reconnecting the
+    /// connect window is shown again, as the login window isn't
+    ///
+    fn invalid_login(&self, buffers: &mut HashMap<String, TextBuffer>) {
+        if self
+            .session
+            .lock()
+            .expect("Couldn't lock session")
+            .is_none()
+        {
+            self.window_login.invalid_login();
+            return;
+        }
+
+        if let Some(buffer) = buffers.get("You") {
+            let message_to_print = format!(
+                "{} {}\r\n",
+                "@ERROR", "Couldn't log in again, the nickname or password is no longer valid"
+            );
+            buffer.insert(&mut buffer.end_iter(), &message_to_print);
+        }
+        self.window_connect
+            .show_again("Couldn't log in again after reconnecting");
     }
 
     ///
@@ -337,10 +577,21 @@ impl Client {
         tx_backend: gtk::glib::Sender<Message>,
         users: &mut Vec<String>,
         buffers: &mut HashMap<String, TextBuffer>,
+        channels: &[String],
     ) {
         // Hide login window
         self.window_login.hide();
-        // Initialize and show main window
+
+        let mut session = self.session.lock().expect("Couldn't lock session");
+        if session.is_some() {
+            // The main window is already initialized, the open chats are continued
+            self.reconnected(&tx_frontend, buffers, channels);
+            return;
+        }
+        *session = Some(gtk_login::entered_credentials(&self.window_login.builder));
+
+        // Initialize and show main window. The server joins the user to its channels again
+        // right after the login, their chats are added when their topics arrive
         gtk_methods::initialize_gtk_window(
             self,
             tx_frontend,
@@ -367,6 +618,9 @@ impl Client {
         self.window_login.hide();
         // Hide registration window
         self.window_login.hide_registration();
+        *self.session.lock().expect("Couldn't lock session") = Some(
+            gtk_login::entered_credentials(&self.window_login.window_register.builder),
+        );
         // Initialize and show main window
         gtk_methods::initialize_gtk_window(
             self,
@@ -378,6 +632,37 @@ impl Client {
         );
     }
 
+    ///
+    /// Joins again the channels that were open before the connection was lost
+    ///
+    fn reconnected(
+        &self,
+        tx_frontend: &Sender<Message>,
+        buffers: &mut HashMap<String, TextBuffer>,
+        channels: &[String],
+    ) {
+        let channel_memory = self
+            .channel_memory
+            .lock()
+            .expect("Couldn't lock channel memory");
+        for channel in channels {
+            tx_frontend
+                .send(channel_memory.join_message(channel))
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+        }
+
+        if let Some(buffer) = buffers.get("You") {
+            let message_to_print = format!("{} {} \r\n", "@INFO", "Reconnected to the server");
+            buffer.insert(&mut buffer.end_iter(), &message_to_print);
+        }
+    }
+
     ///
     /// Adds a new chat to the list of chats, creating a new buffer and button for it
     ///
@@ -461,9 +746,21 @@ impl Client {
         if (name.starts_with('#') || name.starts_with('&')) && !channels.contains(&name) {
             return;
         }
+        // The message the user was typing arrived
+        if message.params[0][1] == PRIVATE_MESSAGE {
+            self.update_typing_user(&name, None, &self.current_chat_name(buffers));
+        }
         let buffer = buffers.get(&name).expect("Couldn't get buffer");
         let mut end = buffer.end_iter();
 
+        // Only follow the new message if the user wasn't reading older ones
+        let text_view: gtk::TextView = self
+            .builder
+            .object("chat_text")
+            .expect("Couldn't get chat_text");
+        let is_shown = text_view.buffer().as_ref() == Some(buffer);
+        let follow_new_messages = is_shown && self.is_chat_scrolled_to_bottom();
+
         let message_type = &*message.params[0][1];
 
         // Adds a tag to the message depending on the type
@@ -471,31 +768,475 @@ impl Client {
             PRIVATE_MESSAGE => format!("{}\r\n", message.params[0][0].clone()),
             INFO => format!("{} {} \r\n", "@INFO", message.params[0][0].clone()),
             ERROR => format!("{} {}\r\n", "@ERROR", message.params[0][0].clone()),
+            RAW_MESSAGE => format!("{} {}\r\n", "@RAW", message.params[0][0].clone()),
+            NOTICE_MESSAGE => format!("{} {}\r\n", "@NOTICE", message.params[0][0].clone()),
             _ => format!("{} {}\r\n", "@UNDEFINED", message.params[0][0].clone()),
         };
+        let message_to_print = self.with_timestamp(message_to_print, message.time());
+
+        self.insert_formatted(buffer, &mut end, &message_to_print); // Add the new message to the buffer
 
-        buffer.insert(&mut end, &message_to_print); // Add the new message to the buffer
+        if follow_new_messages {
+            scroll_chat_to_bottom(&text_view);
+        } else if is_shown {
+            if let Ok(jump_to_latest_button) =
+                get_object::<gtk::Button>(&self.builder, "jump_to_latest_button")
+            {
+                jump_to_latest_button.show();
+            }
+        }
+
+        // Only messages from other users notify, and only if the chat isn't muted
+        if message_type != PRIVATE_MESSAGE {
+            return;
+        }
+        let current_name_chat = self.current_chat_name(buffers);
+        let muted_channels = self
+            .muted_channels
+            .lock()
+            .expect("Couldn't lock muted channels");
+        if should_notify(&name, &current_name_chat, &muted_channels) {
+            self.mark_chat_as_unread(&name);
+            if !self.window.is_active() {
+                self.window.set_urgency_hint(true);
+            }
+        }
     }
 
     ///
-    /// Adds the channel names to the list of visible channels
+    /// Returns true if the chat shown is scrolled to its last message
     ///
-    fn list_channels(&self, message: Message) {
-        let list_channels: gtk::TextView = self
+    fn is_chat_scrolled_to_bottom(&self) -> bool {
+        let scrolled_window: gtk::ScrolledWindow = self
             .builder
-            .object("channels_list_text")
-            .expect("Couldn't get list_channels");
-        let buffer = list_channels.buffer().expect("Couldn't get buffer");
-        let mut end = buffer.end_iter();
-        buffer.delete(&mut buffer.start_iter(), &mut end);
+            .object("chat_scrolled_window")
+            .expect("Couldn't get chat_scrolled_window");
+        let adjustment = scrolled_window.vadjustment();
+        is_scrolled_to_bottom(
+            adjustment.value(),
+            adjustment.upper(),
+            adjustment.page_size(),
+        )
+    }
+
+    ///
+    /// Highlights the button of a chat that received messages while it wasn't selected
+    ///
+    fn mark_chat_as_unread(&self, name: &str) {
+        let list_box: gtk::ListBox = self
+            .builder
+            .object("chats_list")
+            .expect("Couldn't get chats_list");
+        for widget in list_box.children() {
+            let row = widget
+                .clone()
+                .downcast::<gtk::ListBoxRow>()
+                .expect("Couldn't downcast to listboxrow");
+            let button = row
+                .child()
+                .expect("Couldn't get row")
+                .downcast::<gtk::Button>()
+                .expect("Couldn't downcast to button");
+            let button_name = button.label().expect("Couldn't get button label");
+            if button_name == name {
+                button.style_context().add_class(UNREAD_CHAT_CLASS);
+                break;
+            }
+        }
+    }
+
+    ///
+    /// Mutes or unmutes a channel. Muted channels keep receiving and storing messages,
+    /// but they don't highlight the chat nor notify the user
+    ///
+    fn mute_channel(
+        &self,
+        message: &Message,
+        buffers: &mut HashMap<String, TextBuffer>,
+        channels: &[String],
+        mute: bool,
+    ) {
+        let channel = message.params[0][0].clone();
+        let mut muted_channels = self
+            .muted_channels
+            .lock()
+            .expect("Couldn't lock muted channels");
+
+        let text_to_print = if !(channel.starts_with('#') || channel.starts_with('&')) {
+            format!("{} is not a channel", channel)
+        } else if mute {
+            muted_channels.insert(channel.clone());
+            format!("Channel {} muted", channel)
+        } else if muted_channels.remove(&channel) {
+            format!("Channel {} unmuted", channel)
+        } else {
+            format!("Channel {} wasn't muted", channel)
+        };
+        drop(muted_channels);
+
+        // Print the result in the channel if it's open, else in the current chat
+        let name = match channels.contains(&channel) {
+            true => channel,
+            false => self.current_chat_name(buffers),
+        };
+        if let Some(buffer) = buffers.get(&name) {
+            let message_to_print = format!("{} {} \r\n", "@INFO", text_to_print);
+            buffer.insert(&mut buffer.end_iter(), &message_to_print);
+        }
+    }
+
+    ///
+    /// Adds or removes a user from the ignored users. The messages of ignored users are dropped
+    /// by the UI updater, so they don't open a chat nor notify the user. Without a nickname the
+    /// users ignored are listed
+    ///
+    fn ignore_user(
+        &self,
+        message: &Message,
+        buffers: &mut HashMap<String, TextBuffer>,
+        ignore: bool,
+    ) {
+        let mut ignored_users = self
+            .ignored_users
+            .lock()
+            .expect("Couldn't lock ignored users");
+
+        let text_to_print = match message.params.first().and_then(|param| param.first()) {
+            None => ui_updater::ignored_users_text(&ignored_users),
+            Some(nickname) if ignore => {
+                ignored_users.insert(irc_lowercase(nickname));
+                format!("{} ignored", nickname)
+            }
+            Some(nickname) if ignored_users.remove(&irc_lowercase(nickname)) => {
+                format!("{} unignored", nickname)
+            }
+            Some(nickname) => format!("{} wasn't ignored", nickname),
+        };
+        drop(ignored_users);
+
+        let name = self.current_chat_name(buffers);
+        if let Some(buffer) = buffers.get(&name) {
+            let message_to_print = format!("{} {} \r\n", "@INFO", text_to_print);
+            buffer.insert(&mut buffer.end_iter(), &message_to_print);
+        }
+    }
+
+    ///
+    /// Inserts the text of a message with its colors, bold, italic and underline. If the user
+    /// turned formatting off the text is inserted without the formatting codes
+    ///
+    fn insert_formatted(&self, buffer: &TextBuffer, end: &mut gtk::TextIter, text: &str) {
+        let show_formatting = *self
+            .show_formatting
+            .lock()
+            .expect("Couldn't lock show formatting");
+        if !show_formatting {
+            buffer.insert(end, &irc_formatting::strip_formatting(text));
+            return;
+        }
+
+        for span in irc_formatting::parse_formatting(text) {
+            let start_offset = end.offset();
+            buffer.insert(end, &span.text);
+            if span.is_plain() {
+                continue;
+            }
+            let start = buffer.iter_at_offset(start_offset);
+            for tag in formatting_tags(buffer, &span) {
+                buffer.apply_tag(&tag, &start, end);
+            }
+        }
+    }
+
+    ///
+    /// Adds the local time to the text of a message, unless the user turned timestamps off.
+    /// If the server told when it received the message, like for the backlog, that time is shown
+    ///
+    fn with_timestamp(&self, text: String, time: Option<&str>) -> String {
+        let show_timestamps = *self
+            .show_timestamps
+            .lock()
+            .expect("Couldn't lock show timestamps");
+        if !show_timestamps {
+            return text;
+        }
+        let received_at = match time {
+            Some(time) => glib::DateTime::from_iso8601(time, None).and_then(|time| time.to_local()),
+            None => glib::DateTime::now_local(),
+        };
+        match received_at.or_else(|_| glib::DateTime::now_local()) {
+            Ok(time) => ui_updater::timestamped_text(time.hour(), time.minute(), &text),
+            Err(_) => text,
+        }
+    }
+
+    ///
+    /// Turns raw mode on or off. While it's on, every message received from the server
+    /// is also printed in the own chat without any formatting
+    ///
+    fn toggle_raw_mode(&self, buffers: &mut HashMap<String, TextBuffer>) {
+        let mut raw_mode = self.raw_mode.lock().expect("Couldn't lock raw mode");
+        *raw_mode = !*raw_mode;
+
+        let text_to_print = match *raw_mode {
+            true => "Raw mode on, server messages will be shown as received",
+            false => "Raw mode off",
+        };
+        if let Some(buffer) = buffers.get("You") {
+            let message_to_print = format!("{} {} \r\n", "@INFO", text_to_print);
+            buffer.insert(&mut buffer.end_iter(), &message_to_print);
+        }
+    }
+
+    ///
+    /// Turns on or off showing the topic changes in the channels chats
+    ///
+    fn toggle_topic_announcements(&self, buffers: &mut HashMap<String, TextBuffer>) {
+        let mut announce_topic_changes = self
+            .announce_topic_changes
+            .lock()
+            .expect("Couldn't lock topic announcements");
+        *announce_topic_changes = !*announce_topic_changes;
+
+        let text_to_print = match *announce_topic_changes {
+            true => "Topic changes will be shown in the channels",
+            false => "Topic changes will no longer be shown in the channels",
+        };
+        if let Some(buffer) = buffers.get("You") {
+            let message_to_print = format!("{} {} \r\n", "@INFO", text_to_print);
+            buffer.insert(&mut buffer.end_iter(), &message_to_print);
+        }
+    }
+
+    ///
+    /// Turns on or off telling the users of private chats when the user is typing, and showing
+    /// when they are. Turning it off also hides the users that were typing, and tells the chat
+    /// the user was typing in that it stopped
+    ///
+    fn toggle_typing_notifications(
+        &self,
+        tx_frontend: &Sender<Message>,
+        buffers: &mut HashMap<String, TextBuffer>,
+    ) {
+        let mut typing_notifications = self
+            .typing_notifications
+            .lock()
+            .expect("Couldn't lock typing notifications");
+        *typing_notifications = !*typing_notifications;
+
+        let text_to_print = match *typing_notifications {
+            true => {
+                "The users of private chats will see when you are typing, and you when they are"
+            }
+            false => "Nobody will be told when you are typing, or shown when they are",
+        };
+        if !*typing_notifications {
+            *self
+                .typing_users
+                .lock()
+                .expect("Couldn't lock typing users") = TypingUsers::default();
+            let chat = self
+                .typing_notifier
+                .lock()
+                .expect("Couldn't lock typing notifier")
+                .stopped();
+            if let Some(chat) = chat {
+                tx_frontend
+                    .send(Message {
+                        tags: None,
+                        prefix: None,
+                        command: NOTICE.to_string(),
+                        params: vec![vec![chat], vec![typing_notice(TYPING_DONE)]],
+                    })
+                    .map_err(|_| -> ClientError {
+                        ClientError {
+                            kind: NONCRITICAL.to_string(),
+                            message: SEND_MESSAGE.to_string(),
+                        }
+                    })
+                    .ok();
+            }
+        }
+        if let Some(buffer) = buffers.get("You") {
+            let message_to_print = format!("{} {} \r\n", "@INFO", text_to_print);
+            buffer.insert(&mut buffer.end_iter(), &message_to_print);
+        }
+    }
+
+    ///
+    /// Handles a user telling that it started or stopped typing a message to the user
+    ///
+    fn typing_notification(&self, message: &Message, current_name_chat: &str) {
+        if let (Some(nickname), Some(state)) = (
+            &message.prefix,
+            message.params.first().and_then(|param| param.first()),
+        ) {
+            self.update_typing_user(nickname, Some(state.as_str()), current_name_chat);
+        }
+    }
+
+    ///
+    /// Saves the typing state of the user, None when it stopped, and refreshes the label below
+    /// the chat if it is the one shown
+    ///
+    fn update_typing_user(&self, nickname: &str, state: Option<&str>, current_name_chat: &str) {
+        let mut typing_users = self
+            .typing_users
+            .lock()
+            .expect("Couldn't lock typing users");
+        let now = Instant::now();
+        match state {
+            Some(state) => typing_users.notified(nickname, state, now),
+            None => typing_users.stopped(nickname),
+        }
+        if !parser::irc_eq(nickname, current_name_chat) {
+            return;
+        }
+        if let Ok(typing_label) = get_object::<gtk::Label>(&self.builder, "typing_label") {
+            show_typing_label(
+                &typing_label,
+                current_name_chat,
+                typing_users.is_typing(nickname, now),
+            );
+        }
+    }
+
+    ///
+    /// Sets how joins, parts and quits are shown in the chats: inline, hidden, or
+    /// collapsed into a summary every minute
+    ///
+    fn set_membership_events(&self, message: &Message, buffers: &mut HashMap<String, TextBuffer>) {
+        let mode = message.params[0][0].to_uppercase();
+        let mut membership_events = self
+            .membership_events
+            .lock()
+            .expect("Couldn't lock membership events");
+
+        let text_to_print = match mode.as_str() {
+            EVENTS_INLINE => "Joins, parts and quits will be shown in the chats",
+            EVENTS_HIDDEN => "Joins, parts and quits will no longer be shown",
+            EVENTS_COLLAPSED => "Joins, parts and quits will be shown as a summary every minute",
+            _ => "Events can be shown inline, hidden or collapsed",
+        };
+        if [EVENTS_INLINE, EVENTS_HIDDEN, EVENTS_COLLAPSED].contains(&mode.as_str()) {
+            *membership_events = mode;
+        }
+        if let Some(buffer) = buffers.get("You") {
+            let message_to_print = format!("{} {} \r\n", "@INFO", text_to_print);
+            buffer.insert(&mut buffer.end_iter(), &message_to_print);
+        }
+    }
+
+    ///
+    /// Sets the size, in megabytes, from which the files offered are warned before accepting them.
+    /// With "off" files are never warned
+    ///
+    fn set_max_file_size(&self, message: &Message, buffers: &mut HashMap<String, TextBuffer>) {
+        let size = message.params[0][0].to_uppercase();
+        let mut max_file_size = self
+            .max_file_size
+            .lock()
+            .expect("Couldn't lock max file size");
+
+        let text_to_print = if size == MAX_FILE_SIZE_OFF {
+            *max_file_size = None;
+            "Files offered will no longer be warned, whatever their size".to_string()
+        } else {
+            match size.parse::<u64>() {
+                Ok(megabytes) if megabytes > 0 => {
+                    let bytes = megabytes.saturating_mul(1024 * 1024);
+                    *max_file_size = Some(bytes);
+                    format!(
+                        "Files offered bigger than {} will be warned before accepting them",
+                        ui_updater::file_size_text(bytes)
+                    )
+                }
+                _ => "The maximum file size must be a number of megabytes or off".to_string(),
+            }
+        };
+
+        if let Some(buffer) = buffers.get("You") {
+            let message_to_print = format!("{} {} \r\n", "@INFO", text_to_print);
+            buffer.insert(&mut buffer.end_iter(), &message_to_print);
+        }
+    }
+
+    ///
+    /// Sets the seconds a DCC chat offered waits for the other user to connect
+    ///
+    fn set_dcc_chat_timeout(&self, message: &Message, buffers: &mut HashMap<String, TextBuffer>) {
+        let text_to_print = match message.params[0][0].parse::<u64>() {
+            Ok(seconds) if seconds > 0 => {
+                *self
+                    .dcc_chat_timeout
+                    .lock()
+                    .expect("Couldn't lock dcc chat timeout") = Duration::from_secs(seconds);
+                format!(
+                    "Private chats offered will wait {} seconds for the other user",
+                    seconds
+                )
+            }
+            _ => "The time to wait must be a number of seconds".to_string(),
+        };
+
+        if let Some(buffer) = buffers.get("You") {
+            let message_to_print = format!("{} {} \r\n", "@INFO", text_to_print);
+            buffer.insert(&mut buffer.end_iter(), &message_to_print);
+        }
+    }
+
+    ///
+    /// Turns on or off the confirmation before sending the command given
+    ///
+    fn toggle_confirmation(&self, message: &Message, buffers: &mut HashMap<String, TextBuffer>) {
+        let command = message.params[0][0].to_uppercase();
+        let mut confirm_commands = self
+            .confirm_commands
+            .lock()
+            .expect("Couldn't lock confirm commands");
+
+        let text_to_print = if !CONFIRMABLE_COMMANDS.contains(&command.as_str()) {
+            format!(
+                "'{}' can't ask for confirmation, allowed: {}",
+                command,
+                CONFIRMABLE_COMMANDS.join(", ")
+            )
+        } else if confirm_commands.remove(&command) {
+            format!("'{}' will no longer ask for confirmation", command)
+        } else {
+            confirm_commands.insert(command.clone());
+            format!("'{}' will ask for confirmation", command)
+        };
+
+        if let Some(buffer) = buffers.get("You") {
+            let message_to_print = format!("{} {} \r\n", "@INFO", text_to_print);
+            buffer.insert(&mut buffer.end_iter(), &message_to_print);
+        }
+    }
+
+    ///
+    /// Adds a button for each visible channel, clicking it joins the channel
+    ///
+    fn list_channels(&self, message: Message, tx_frontend: &Sender<Message>) {
+        let list_box: gtk::ListBox = self
+            .builder
+            .object("channels_list")
+            .expect("Couldn't get channels_list");
+
+        // Empty the list
+        for row in list_box.children() {
+            list_box.remove(&row);
+        }
+
         if message.params[0].is_empty() {
-            buffer.insert(&mut end, "No channels available");
+            list_box.add(&gtk::Label::new(Some("No channels available")));
         } else {
             for channel in message.params[0].clone() {
-                buffer.insert(&mut end, &channel);
-                buffer.insert(&mut end, "\r\n");
+                let channel_button = gtk_methods::new_channel_list_button(channel, tx_frontend);
+                list_box.add(&channel_button);
             }
         }
+        list_box.show_all();
     }
 
     ///
@@ -513,12 +1254,19 @@ impl Client {
             list_box.remove(&button);
         }
 
-        for user_nickname in message.params[0].clone() {
+        // The labels have the flags of the users when they are the members of a channel
+        let labels = message.params.get(1).cloned().unwrap_or_default();
+        for (index, user_nickname) in message.params[0].clone().into_iter().enumerate() {
             // If the user is me, don't add it to the list
             if user_nickname == self_nickname {
                 continue;
             }
-            let user_button = gtk_methods::new_user_search_button(user_nickname, tx_backend); // Create the new button
+            let label = labels
+                .get(index)
+                .cloned()
+                .unwrap_or_else(|| user_nickname.clone());
+            let user_button =
+                gtk_methods::new_user_search_button(user_nickname, &label, tx_backend); // Create the new button
 
             list_box.add(&user_button);
             list_box.show_all();
@@ -570,6 +1318,9 @@ impl Client {
     ///
     /// Handle the dcc request
     /// Show a dialog to the user to accept or reject the request
+    /// Files bigger than the maximum size set are warned in the dialog
+    /// Encrypted chats ask for the passphrase in the dialog
+    /// Files offered are pending until answered, closing the dialog leaves them for /dcc_accept
     ///
     fn accept_or_reject_dcc(
         &self,
@@ -579,27 +1330,186 @@ impl Client {
         text_show: &str,
         chat_name: String,
     ) {
+        let mut text_show = text_show.to_string();
+        if message.command == DCC_SEND {
+            self.pending_dcc_offers
+                .lock()
+                .expect(LOCK_DCC)
+                .offered(&message, Instant::now());
+            let max_file_size = *self
+                .max_file_size
+                .lock()
+                .expect("Couldn't lock max file size");
+            let size = message.params[3][0].parse::<u64>().unwrap_or_default();
+            if let Some(max_file_size) = max_file_size.filter(|max| size > *max) {
+                text_show.push_str(&format!(
+                    "\r\n Warning! The file is bigger than {}, check you have enough space",
+                    ui_updater::file_size_text(max_file_size)
+                ));
+            }
+        }
+
+        let encrypted = message.command == DCC_CHAT
+            && matches!(
+                message.params.get(4).and_then(|param| param.first()),
+                Some(param) if param == DCC_ENCRYPTED
+            );
+
         let dialog = gtk::MessageDialog::builder()
             .transient_for(&self.window)
             .modal(false)
             .buttons(gtk::ButtonsType::YesNo)
-            .text(text_show)
+            .text(&text_show)
+            .build();
+        let passphrase_entry = gtk::Entry::builder()
+            .visibility(false)
+            .placeholder_text("Passphrase")
             .build();
+        if encrypted {
+            dialog.content_area().add(&passphrase_entry);
+            dialog.show_all();
+        }
         let response = dialog.run();
+        let passphrase = passphrase_entry.text().to_string();
         dialog.close();
-        if response == gtk::ResponseType::Yes {
-            if message.command == DCC_RESUME {
-                self.resume_dcc(message, tx_backend, tx_frontend, chat_name);
-            } else {
-                self.join_dcc(message, tx_backend);
+        if message.command == DCC_SEND {
+            self.file_offer_answered(&message, response, tx_backend);
+        }
+        if response != gtk::ResponseType::Yes {
+            return;
+        }
+
+        if message.command == DCC_RESUME {
+            self.resume_dcc(message, tx_backend, tx_frontend, chat_name);
+        } else if encrypted {
+            if passphrase.is_empty() {
+                let _ = tx_backend.send(Message {
+                    tags: None,
+                    prefix: message.prefix,
+                    command: RECEIVED_MESSAGE.to_string(),
+                    params: vec![vec![
+                        "The passphrase is needed to start an encrypted private chat".to_string(),
+                        ERROR.to_string(),
+                    ]],
+                });
+                return;
+            }
+            self.join_dcc(message, tx_backend, Some(DccCipher::new(&passphrase)));
+        } else {
+            self.join_dcc(message, tx_backend, None);
+        }
+    }
+
+    ///
+    /// Continues the download accepted with a DCC ACCEPT, if the user asked to resume it.
+    /// Accepts nobody asked for or with a position that is not a number are shown as errors
+    ///
+    fn accept_requested_resume(&self, message: Message, tx_backend: &gtk::glib::Sender<Message>) {
+        let nickname = message.prefix.clone().unwrap_or_default();
+        let file_name = message.params.first().and_then(|param| param.first());
+        let position = message
+            .params
+            .get(3)
+            .and_then(|param| param.first())
+            .and_then(|position| position.parse::<u64>().ok());
+        let text_to_print = match (file_name, position) {
+            (Some(file_name), Some(position)) => {
+                let mut resumes = self.requested_resumes.lock().expect(LOCK_DCC);
+                if resumes.accepted(&nickname, file_name, position) {
+                    drop(resumes);
+                    self.join_dcc(message, tx_backend, None);
+                    return;
+                }
+                "Ignored a file transfer resumed without asking for it"
+            }
+            _ => "Couldn't resume the file transfer, the position received is not valid",
+        };
+        let _res = tx_backend.send(Message {
+            tags: None,
+            prefix: message.prefix,
+            command: RECEIVED_MESSAGE.to_string(),
+            params: vec![vec![text_to_print.to_string(), ERROR.to_string()]],
+        });
+    }
+
+    ///
+    /// The file offered is no longer pending once the user accepts or rejects it. If the dialog
+    /// was closed without answering, the user is told it can still be accepted
+    ///
+    fn file_offer_answered(
+        &self,
+        offer: &Message,
+        response: gtk::ResponseType,
+        tx_backend: &gtk::glib::Sender<Message>,
+    ) {
+        match response {
+            gtk::ResponseType::Yes | gtk::ResponseType::No => self
+                .pending_dcc_offers
+                .lock()
+                .expect(LOCK_DCC)
+                .answered(offer),
+            _ => {
+                if let Some(nickname) = &offer.prefix {
+                    let _ = tx_backend.send(Message {
+                        tags: None,
+                        prefix: Some(nickname.clone()),
+                        command: RECEIVED_MESSAGE.to_string(),
+                        params: vec![vec![
+                            format!(
+                                "The file can still be accepted with /dcc_accept {}",
+                                nickname
+                            ),
+                            INFO.to_string(),
+                        ]],
+                    });
+                }
+            }
+        }
+    }
+
+    ///
+    /// Accepts the file offered by the user, typed as /dcc_accept, without showing the dialog
+    ///
+    fn accept_pending_dcc(&self, message: &Message, tx_backend: &gtk::glib::Sender<Message>) {
+        let nickname = match &message.prefix {
+            Some(nickname) => nickname,
+            None => return,
+        };
+        let offer = self
+            .pending_dcc_offers
+            .lock()
+            .expect(LOCK_DCC)
+            .take(nickname, Instant::now());
+        match offer {
+            Some(offer) => self.join_dcc(offer, tx_backend, None),
+            None => {
+                let _ = tx_backend.send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: RECEIVED_MESSAGE.to_string(),
+                    params: vec![vec![
+                        format!(
+                            "{} has no file offered, offers expire after {} seconds",
+                            nickname,
+                            DCC_OFFER_EXPIRATION.as_secs()
+                        ),
+                        ERROR.to_string(),
+                    ]],
+                });
             }
         }
     }
 
     ///
     /// Joins a dcc connection with the user
+    /// The messages of a chat are encrypted with the cipher given, if any
     ///
-    fn join_dcc(&self, message: Message, tx_backend: &gtk::glib::Sender<Message>) {
+    fn join_dcc(
+        &self,
+        message: Message,
+        tx_backend: &gtk::glib::Sender<Message>,
+        cipher: Option<DccCipher>,
+    ) {
         // Communication channel from frontend to backend (Client to UI Listener)
         let (dcc_sender, dcc_receiver): (Sender<Message>, Receiver<Message>) = mpsc::channel();
 
@@ -607,20 +1517,35 @@ impl Client {
 
         println!("Connecting to {}", address);
 
-        //Create the TCP connection
-        let stream = match TcpStream::connect(address) {
+        //Create the TCP connection, the one who offered it stops waiting after its timeout
+        let timeout = match message.command == DCC_CHAT {
+            true => *self
+                .dcc_chat_timeout
+                .lock()
+                .expect("Couldn't lock dcc chat timeout"),
+            false => DCC_FILE_CONNECT_TIMEOUT,
+        };
+        let stream = dcc_connection::connect(&address, timeout).and_then(|stream| {
+            if message.command != DCC_CHAT {
+                dcc_connection::set_file_transfer_timeouts(&stream, DCC_FILE_TRANSFER_TIMEOUT)?;
+            }
+            Ok(stream)
+        });
+        let stream = match stream {
             Ok(stream) => stream,
-            Err(_) => {
+            Err(error) => {
+                let nickname = message.prefix.unwrap();
+                let text_to_print = match message.command == DCC_CHAT {
+                    true => dcc_connection::failed_connection_text(&nickname, &error),
+                    false => "Couldn't start private chat due to error or time to connect expired"
+                        .to_string(),
+                };
                 // Send start private chat message to the UI
                 let _res = tx_backend.send(Message {
-                    prefix: Some(message.prefix.unwrap()),
+                    tags: None,
+                    prefix: Some(nickname),
                     command: RECEIVED_MESSAGE.to_string(),
-                    params: vec![vec![
-                        format!(
-                            "Couldn't start private chat due to error or time to connect expired"
-                        ),
-                        ERROR.to_string(),
-                    ]],
+                    params: vec![vec![text_to_print, ERROR.to_string()]],
                 });
                 return;
             }
@@ -628,18 +1553,54 @@ impl Client {
 
         let tx_backend_clone = tx_backend.clone();
         let user_to_send = message.prefix.clone().unwrap();
+        // Offers from clients that don't send the checksum are still accepted, without checking it
+        let checksum_index = match message.command == DCC_SEND {
+            true => 5,
+            false => 4,
+        };
+        let checksum = message
+            .params
+            .get(checksum_index)
+            .and_then(|param| param.first())
+            .cloned();
 
         if message.command == DCC_CHAT {
             let mut chats = self.dcc_chats.lock().expect(LOCK_DCC);
             chats.insert(message.prefix.expect("No prefix in message"), dcc_sender);
             // Spawn new thread for the client
             let _ = thread::spawn(move || {
-                match handle_dcc_chat(stream, tx_backend_clone, dcc_receiver, &user_to_send) {
+                match handle_dcc_chat(
+                    stream,
+                    tx_backend_clone,
+                    dcc_receiver,
+                    &user_to_send,
+                    cipher,
+                ) {
                     Ok(_) => println!("Client disconnected: dcc_chat"),
                     Err(err) => println!("Error: {}", err),
                 }
             });
         } else if message.command == DCC_SEND {
+            let directory = self
+                .download_directory
+                .lock()
+                .expect("Couldn't lock download directory")
+                .clone();
+            let file_path = match dcc_download::new_file_path(&directory, &message.params[0][0]) {
+                Ok(file_path) => file_path,
+                Err(_) => {
+                    let _res = tx_backend.send(Message {
+                        tags: None,
+                        prefix: Some(user_to_send),
+                        command: RECEIVED_MESSAGE.to_string(),
+                        params: vec![vec![
+                            format!("Couldn't create the directory {}", directory.display()),
+                            ERROR.to_string(),
+                        ]],
+                    });
+                    return;
+                }
+            };
             let mut chats = self.dcc_chats.lock().expect(LOCK_DCC);
             let name = message.prefix.expect("No prefix in message");
             chats.insert(format!("{}_f", name), dcc_sender);
@@ -651,18 +1612,26 @@ impl Client {
                     tx_backend_clone,
                     dcc_receiver,
                     &user_to_send,
-                    message.params[0][0].clone(),
+                    file_path,
                     0,
+                    checksum,
                 ) {
                     Ok(_) => println!("Client disconnected: dcc_chat"),
                     Err(err) => println!("Error: {}", err),
                 }
             });
         } else if message.command == DCC_ACCEPT {
+            let directory = self
+                .download_directory
+                .lock()
+                .expect("Couldn't lock download directory")
+                .clone();
+            let file_path = dcc_download::resumed_file_path(&directory, &message.params[0][0]);
             let mut chats = self.dcc_chats.lock().expect(LOCK_DCC);
             let name = message.prefix.expect("No prefix in message");
             chats.insert(format!("{}_f", name), dcc_sender);
-            let position = message.params[3][0].clone().parse().unwrap();
+            // The position was already checked by accept_requested_resume
+            let position = message.params[3][0].parse().unwrap_or_default();
             // Spawn new thread for the client
             let _ = thread::spawn(move || {
                 // connection succeeded
@@ -671,8 +1640,9 @@ impl Client {
                     tx_backend_clone,
                     dcc_receiver,
                     &user_to_send,
-                    message.params[0][0].clone(),
+                    file_path,
                     position,
+                    checksum,
                 ) {
                     Ok(_) => println!("Client disconnected: dcc_chat"),
                     Err(err) => println!("Error: {}", err),
@@ -695,6 +1665,7 @@ impl Client {
 
         tx_backend
             .send(Message {
+                tags: None,
                 prefix: Some(other_user_nickname.clone()),
                 command: RECEIVED_MESSAGE.to_string(),
                 params: vec![vec![format!("CLOSED PRIVATE CONNECTION"), INFO.to_string()]],
@@ -744,6 +1715,14 @@ impl Client {
         let start_position = message.params[3][0]
             .parse::<u64>()
             .expect("Position not valid");
+        // The checksum is of the whole file, so the receiver can check it when it finishes
+        let mut message_accept = format!(
+            "DCC_ACCEPT {} {} {} {}",
+            message.params[0][0], ip, port, message.params[3][0]
+        );
+        if let Ok(checksum) = dcc_checksum::file_checksum(&file_path) {
+            message_accept.push_str(&format!(" {}", checksum));
+        }
 
         let _ = thread::spawn(move || {
             // connection succeeded
@@ -759,23 +1738,21 @@ impl Client {
             }
         });
 
-        let message = format!(
-            "DCC_ACCEPT {} {} {} {}",
-            message.params[0][0], ip, port, message.params[3][0]
-        );
-        println!("Sending message: {}", message);
+        println!("Sending message: {}", message_accept);
 
         send_privmsg(
             &tx_frontend, //start chat and thread to send file
-            &message,
+            &message_accept,
             None,
             text_view,
             current_name_chat,
             false,
+            &self.get_current_nickname(),
         );
 
         tx_backend
             .send(Message {
+                tags: None,
                 prefix: None,
                 command: RECEIVED_MESSAGE.to_string(),
                 params: vec![vec!["Resuming file transfer".to_string(), INFO.to_string()]],
@@ -798,70 +1775,43 @@ impl Client {
 ///
 /// Creates the client and runs it
 ///
+impl Frontend for gtk::glib::Sender<Message> {
+    fn send(&self, message: Message) -> Result<(), SendError<Message>> {
+        gtk::glib::Sender::send(self, message)
+    }
+}
+
 pub fn init_chat(application: &gtk::Application) {
     let client = Client::new(application);
 
     client.run();
 }
 
-///
-/// When the user performs an action, this method sends it through TCP to the server
-///
-fn ui_listener(
-    rx_backend: Receiver<Message>,
-    rx_stream: Receiver<TcpStream>,
-) -> Result<(), ClientError> {
-    let mut stream = rx_stream.recv().map_err(|_| -> ClientError {
-        ClientError {
-            kind: CRITICAL.to_string(),
-            message: RECEIVE_MESSAGE.to_string(),
-        }
-    })?;
-
-    while match rx_backend.try_recv() {
-        Ok(message) => {
-            println!("Sending message: {:?}", message);
-            stream
-                .write_all(message.as_string().as_bytes())
-                .map_err(|_| -> ClientError {
-                    ClientError {
-                        kind: CRITICAL.to_string(),
-                        message: SEND_MESSAGE.to_string(),
-                    }
-                })?;
-            true
-        }
-        Err(TryRecvError::Empty) => true,
-        Err(TryRecvError::Disconnected) => {
-            println!("Client disconnected from server");
-            false
-        }
-    } {}
-
-    Ok(())
-}
-
 ///
 /// Handles the dcc chat
 /// Send messages to the UI
 /// and call the function to receive messages from the UI
+/// With a cipher, the messages received are decrypted and the ones sent are encrypted
 ///
 fn handle_dcc_chat(
     stream: TcpStream,
     tx_backend: gtk::glib::Sender<Message>,
     dcc_receiver: Receiver<Message>,
     user_to_send: &String,
+    cipher: Option<DccCipher>,
 ) -> Result<(), ClientError> {
     println!("I'm in a new thread");
+    let started_text = match cipher {
+        Some(_) => format!("STARTED ENCRYPTED PRIVATE CONNECTION WITH {}", user_to_send),
+        None => format!("STARTED PRIVATE CONNECTION WITH {}", user_to_send),
+    };
     // Send start private chat message to the UI
     tx_backend
         .send(Message {
+            tags: None,
             prefix: Some(user_to_send.clone()),
             command: RECEIVED_MESSAGE.to_string(),
-            params: vec![vec![
-                format!("STARTED PRIVATE CONNECTION WITH {}", user_to_send),
-                INFO.to_string(),
-            ]],
+            params: vec![vec![started_text, INFO.to_string()]],
         })
         .map_err(|_| -> ClientError {
             ClientError {
@@ -881,21 +1831,42 @@ fn handle_dcc_chat(
         })?;
 
     let mut line = String::new();
+    // Bytes of a line cut by the timeout
+    let mut pending = Vec::new();
 
     println!("Waiting for messages");
-    while match reader.read_line(&mut line) {
+    while match parser::read_line_lossy(&mut reader, &mut pending, &mut line) {
         Ok(_bytes_read) => {
             // If data was read then handle it
             if !line.is_empty() {
                 let message = parser::parse(line.clone()).unwrap();
-                let message_to_print =
-                    format!("{}: {}", user_to_send, message.params[1][0].clone());
+                let text = message.params[1][0].clone();
+                let params = match &cipher {
+                    None => vec![
+                        format!("{}: {}", user_to_send, text),
+                        PRIVATE_MESSAGE.to_string(),
+                    ],
+                    Some(cipher) => match cipher.decrypt(&text) {
+                        Some(text) => vec![
+                            format!("{}: {}", user_to_send, text),
+                            PRIVATE_MESSAGE.to_string(),
+                        ],
+                        None => vec![
+                            format!(
+                                "Couldn't decrypt a message from {}, the passphrases may be different",
+                                user_to_send
+                            ),
+                            ERROR.to_string(),
+                        ],
+                    },
+                };
                 println!("Received message: {:?}", line);
                 tx_backend
                     .send(Message {
+                        tags: None,
                         prefix: Some(user_to_send.to_string()),
                         command: RECEIVED_MESSAGE.to_string(),
-                        params: vec![vec![message_to_print, PRIVATE_MESSAGE.to_string()]],
+                        params: vec![params],
                     })
                     .map_err(|_| -> ClientError {
                         ClientError {
@@ -912,7 +1883,7 @@ fn handle_dcc_chat(
         }
     } {
         // In every execution execute this block
-        read_to_send_private_chat(&stream, &dcc_receiver, &tx_backend)?;
+        read_to_send_private_chat(&stream, &dcc_receiver, &tx_backend, &cipher)?;
     }
     Ok(())
 }
@@ -920,14 +1891,16 @@ fn handle_dcc_chat(
 ///
 /// Reads the messages from the UI and sends them to the stream
 /// If the message is a close message, it closes the connection
+/// With a cipher, the text is encrypted before it is sent
 ///
 fn read_to_send_private_chat(
     mut stream: &TcpStream,
     dcc_receiver: &Receiver<Message>,
     tx_backend: &gtk::glib::Sender<Message>,
+    cipher: &Option<DccCipher>,
 ) -> Result<(), ClientError> {
     match dcc_receiver.try_recv() {
-        Ok(message) => {
+        Ok(mut message) => {
             if message.command == *DCC_CLOSE {
                 println!("Closing private chat");
                 stream
@@ -940,6 +1913,7 @@ fn read_to_send_private_chat(
                     })?;
                 tx_backend
                     .send(Message {
+                        tags: None,
                         prefix: None,
                         command: RECEIVED_MESSAGE.to_string(),
                         params: vec![vec![format!("CLOSED PRIVATE CONNECTION"), INFO.to_string()]],
@@ -952,6 +1926,15 @@ fn read_to_send_private_chat(
                     })?;
             } else {
                 println!("Sending message by private chat: {:?}", message);
+                if let Some(cipher) = cipher {
+                    if let Some(text) = message
+                        .params
+                        .get_mut(1)
+                        .and_then(|param| param.first_mut())
+                    {
+                        *text = cipher.encrypt(text, dcc_cipher::new_nonce());
+                    }
+                }
                 stream
                     .write_all(message.as_string().as_bytes())
                     .map_err(|_| -> ClientError {
@@ -978,19 +1961,24 @@ fn read_to_send_private_chat(
 /// Handles the dcc file transfer RECEIVER
 /// Creates a new file and writes the data received from the stream
 /// If the message is a pause message, it kill the connection
+/// The bytes written are saved in the .part index of the file, so the transfer can be
+/// resumed from there even if the client is closed
+/// If the sender sent the checksum of the file, the file received is checked with it
 ///
 fn handle_dcc_file_received(
     mut stream: TcpStream,
     tx_backend: gtk::glib::Sender<Message>,
     dcc_receiver: Receiver<Message>,
     user_to_send: &str,
-    file_name: String,
+    file_path: PathBuf,
     start_position: u64,
+    checksum: Option<String>,
 ) -> Result<(), ClientError> {
     println!("I'm in a new thread waiting for a file");
 
     tx_backend
         .send(Message {
+            tags: None,
             prefix: Some(user_to_send.to_string()),
             command: RECEIVED_MESSAGE.to_string(),
             params: vec![vec!["Started receiving file".to_string(), INFO.to_string()]],
@@ -1002,24 +1990,36 @@ fn handle_dcc_file_received(
             }
         })?;
 
+    let write_error = |_| -> ClientError {
+        ClientError {
+            kind: CRITICAL.to_string(),
+            message: SEND_MESSAGE.to_string(),
+        }
+    };
     let mut my_file = match start_position {
-        0 => {
-            File::create("received_files/".to_owned() + &file_name).map_err(|_| -> ClientError {
-                ClientError {
-                    kind: CRITICAL.to_string(),
-                    message: SEND_MESSAGE.to_string(),
-                }
-            })?
+        0 => File::create(&file_path).map_err(write_error)?,
+        _ => {
+            let file = OpenOptions::new()
+                .write(true)
+                .append(true)
+                .open(&file_path)
+                .map_err(write_error)?;
+            // Bytes after the position saved could be incomplete, they are received again
+            file.set_len(start_position).map_err(write_error)?;
+            file
         }
-        _ => OpenOptions::new()
-            .write(true)
-            .append(true)
-            .open("received_files/".to_owned() + &file_name)
-            .unwrap(),
     };
+    let mut bytes_written = start_position;
     let mut buffer = [0; 1024];
 
-    while let Ok(bytes_read) = stream.read(&mut buffer) {
+    loop {
+        // If the connection is lost the index is kept to resume the transfer later
+        let bytes_read = stream.read(&mut buffer).map_err(|_| -> ClientError {
+            ClientError {
+                kind: NONCRITICAL.to_string(),
+                message: "The file transfer was interrupted".to_string(),
+            }
+        })?;
         if bytes_read == 0 {
             break;
         }
@@ -1058,32 +2058,46 @@ fn handle_dcc_file_received(
                 }
             })?;
 
+        my_file
+            .write_all(&buffer[0..bytes_read])
+            .map_err(write_error)?;
+        bytes_written += bytes_read as u64;
+        dcc_progress::save_progress(&file_path, bytes_written).map_err(write_error)?;
+
         if bytes_read < 1024 {
-            my_file
-                .write_all(&buffer[0..bytes_read as usize])
-                .map_err(|_| -> ClientError {
-                    ClientError {
-                        kind: CRITICAL.to_string(),
-                        message: SEND_MESSAGE.to_string(),
-                    }
-                })?;
             break;
         }
-        my_file.write_all(&buffer).map_err(|_| -> ClientError {
-            ClientError {
-                kind: CRITICAL.to_string(),
-                message: SEND_MESSAGE.to_string(),
-            }
-        })?;
     }
+    dcc_progress::finish(&file_path).map_err(write_error)?;
+
+    let (text_to_print, message_type) = match checksum {
+        Some(checksum) => match dcc_checksum::checksum_matches(&file_path, &checksum) {
+            Ok(true) => (
+                format!(
+                    "File received completely from {}, its checksum matches",
+                    user_to_send
+                ),
+                INFO,
+            ),
+            _ => (
+                format!(
+                    "The file received from {} doesn't match its checksum, it could be incomplete or corrupted",
+                    user_to_send
+                ),
+                ERROR,
+            ),
+        },
+        None => (
+            format!("File received completely from {}", user_to_send),
+            INFO,
+        ),
+    };
     tx_backend
         .send(Message {
+            tags: None,
             prefix: Some(user_to_send.to_string()),
             command: RECEIVED_MESSAGE.to_string(),
-            params: vec![vec![
-                format!("File received completely from {}", user_to_send),
-                INFO.to_string(),
-            ]],
+            params: vec![vec![text_to_print, message_type.to_string()]],
         })
         .map_err(|_| -> ClientError {
             ClientError {
@@ -1109,6 +2123,7 @@ pub fn handle_dcc_file_send(
 
     tx_backend
         .send(Message {
+            tags: None,
             prefix: None,
             command: RECEIVED_MESSAGE.to_string(),
             params: vec![vec!["Started file transfer".to_string(), INFO.to_string()]],
@@ -1125,7 +2140,11 @@ pub fn handle_dcc_file_send(
             break;
         }
         let mut buf = [0; 4];
-        stream.write_all(&buffer[0..bytes_read]).unwrap();
+        // The stream has a write timeout, a receiver that stopped reading ends the transfer
+        if stream.write_all(&buffer[0..bytes_read]).is_err() {
+            println!("Error sending the file");
+            return;
+        }
 
         match stream.read_exact(&mut buf) {
             Ok(_) => {}
@@ -1144,6 +2163,7 @@ pub fn handle_dcc_file_send(
     }
     tx_backend
         .send(Message {
+            tags: None,
             prefix: None,
             command: RECEIVED_MESSAGE.to_string(),
             params: vec![vec![
@@ -1156,6 +2176,43 @@ pub fn handle_dcc_file_send(
     stream.shutdown(std::net::Shutdown::Both).unwrap();
 }
 
+///
+/// Returns the tags of the buffer that show the format of the span, creating the ones the
+/// buffer doesn't have yet
+///
+fn formatting_tags(buffer: &TextBuffer, span: &FormattedSpan) -> Vec<gtk::TextTag> {
+    let tag_table = buffer.tag_table();
+    let tag = |name: &str, setup: &dyn Fn(&gtk::TextTag)| -> gtk::TextTag {
+        tag_table.lookup(name).unwrap_or_else(|| {
+            let tag = gtk::TextTag::new(Some(name));
+            setup(&tag);
+            tag_table.add(&tag);
+            tag
+        })
+    };
+
+    let mut tags = vec![];
+    if span.bold {
+        tags.push(tag("irc_bold", &|tag| tag.set_weight(700)));
+    }
+    if span.italic {
+        tags.push(tag("irc_italic", &|tag| {
+            tag.set_style(gtk::pango::Style::Italic)
+        }));
+    }
+    if span.underline {
+        tags.push(tag("irc_underline", &|tag| {
+            tag.set_underline(gtk::pango::Underline::Single)
+        }));
+    }
+    if let Some(color) = span.foreground {
+        tags.push(tag(&format!("irc_color_{}", color), &|tag| {
+            tag.set_foreground(Some(color))
+        }));
+    }
+    tags
+}
+
 // These tests are commented, when using applications and gtk they don't work if they are all run together.
 // If you run them individually, they work perfectly.
 
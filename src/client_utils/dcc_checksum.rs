@@ -0,0 +1,85 @@
+//!
+//! The sender of a DCC file transfer sends the CRC-32 checksum of the file with the offer, so the
+//! receiver can check that the file it wrote is the same and wasn't truncated or corrupted
+//!
+
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+// Reversed polynomial of the CRC-32 used by zip and ethernet
+const CRC32_POLYNOMIAL: u32 = 0xEDB8_8320;
+// Size of the chunks read from the file
+const READ_BUFFER_SIZE: usize = 4096;
+
+///
+/// Returns the CRC-32 of the bytes given, continuing the one of the bytes that came before.
+/// The checksum of no bytes is 0
+///
+pub fn crc32(previous: u32, bytes: &[u8]) -> u32 {
+    let mut crc = !previous;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ CRC32_POLYNOMIAL,
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
+
+///
+/// Returns the checksum of the whole file, as the hexadecimal text sent in the DCC messages
+///
+pub fn file_checksum(file_path: &Path) -> Result<String, io::Error> {
+    let mut file = File::open(file_path)?;
+    let mut buffer = [0; READ_BUFFER_SIZE];
+    let mut crc = 0;
+    loop {
+        let bytes_read = file.read(&mut buffer)?;
+        if bytes_read == 0 {
+            return Ok(format!("{:08x}", crc));
+        }
+        crc = crc32(crc, &buffer[..bytes_read]);
+    }
+}
+
+///
+/// Returns true if the checksum of the file is the one expected
+///
+pub fn checksum_matches(file_path: &Path, expected: &str) -> Result<bool, io::Error> {
+    Ok(file_checksum(file_path)?.eq_ignore_ascii_case(expected))
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{checksum_matches, crc32, file_checksum};
+
+    #[test]
+    fn crc32_is_the_standard_one_and_can_be_computed_by_parts() {
+        assert_eq!(crc32(0, b""), 0);
+        assert_eq!(crc32(0, b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(crc32(0, b"1234"), b"56789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn file_checksum_detects_a_truncated_file() {
+        let file_path = std::env::temp_dir().join("dcc_checksum_test_file.txt");
+        fs::write(&file_path, "123456789").unwrap();
+        assert_eq!(file_checksum(&file_path).unwrap(), "cbf43926");
+        assert!(checksum_matches(&file_path, "CBF43926").unwrap());
+
+        fs::write(&file_path, "12345678").unwrap();
+        assert!(!checksum_matches(&file_path, "cbf43926").unwrap());
+
+        fs::remove_file(&file_path).unwrap();
+    }
+}
@@ -0,0 +1,157 @@
+//!
+//! Two users can encrypt a DCC chat with a passphrase they agreed on. The text of each message is
+//! mixed with a keystream made from the passphrase and a nonce sent with the message, and it is
+//! written in hexadecimal so it still fits in a line. The keystream is not a standard cipher, it
+//! only keeps the text from being read at a glance and doesn't protect it from an attacker
+//!
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Added before the text so a message encrypted with another passphrase is detected
+const PLAINTEXT_MARKER: &str = "DCC:";
+// Separates the nonce from the encrypted text, a colon would not be accepted by the parser
+const NONCE_SEPARATOR: char = '.';
+
+// Constants of the FNV-1a hash used to turn the passphrase into the seed of the keystream
+const FNV_OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+// Mixes the nonce with the seed and the state of the keystream
+const NONCE_MULTIPLIER: u64 = 0x9E37_79B9_7F4A_7C15;
+const KEYSTREAM_MULTIPLIER: u64 = 0x2545_F491_4F6C_DD1D;
+
+///
+/// Encrypts and decrypts the messages of a DCC chat with the passphrase given
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DccCipher {
+    seed: u64,
+}
+
+impl DccCipher {
+    pub fn new(passphrase: &str) -> Self {
+        let seed = passphrase.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+        });
+        DccCipher { seed }
+    }
+
+    ///
+    /// Returns the line sent for the text given, with the nonce followed by the encrypted text.
+    /// Every message must use a different nonce
+    ///
+    pub fn encrypt(&self, text: &str, nonce: u64) -> String {
+        let plaintext = format!("{}{}", PLAINTEXT_MARKER, text);
+        let encrypted: String = self
+            .apply_keystream(plaintext.as_bytes(), nonce)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        format!("{:016x}{}{}", nonce, NONCE_SEPARATOR, encrypted)
+    }
+
+    ///
+    /// Returns the text of a line received. None if the line is not encrypted or it was
+    /// encrypted with another passphrase
+    ///
+    pub fn decrypt(&self, line: &str) -> Option<String> {
+        let (nonce, encrypted) = line.trim().split_once(NONCE_SEPARATOR)?;
+        let nonce = u64::from_str_radix(nonce, 16).ok()?;
+        if encrypted.len() % 2 != 0 {
+            return None;
+        }
+        let bytes = (0..encrypted.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(encrypted.get(index..index + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+
+        let plaintext = String::from_utf8(self.apply_keystream(&bytes, nonce)).ok()?;
+        plaintext.strip_prefix(PLAINTEXT_MARKER).map(str::to_string)
+    }
+
+    ///
+    /// Mixes the bytes given with the keystream of the nonce, doing it twice returns the
+    /// same bytes
+    ///
+    fn apply_keystream(&self, bytes: &[u8], nonce: u64) -> Vec<u8> {
+        // The state of a xorshift can't be 0
+        let mut state = (self.seed ^ nonce.wrapping_mul(NONCE_MULTIPLIER)).max(1);
+        let mut keystream = Vec::with_capacity(bytes.len() + 8);
+        while keystream.len() < bytes.len() {
+            state ^= state >> 12;
+            state ^= state << 25;
+            state ^= state >> 27;
+            keystream.extend_from_slice(&state.wrapping_mul(KEYSTREAM_MULTIPLIER).to_le_bytes());
+        }
+
+        bytes
+            .iter()
+            .zip(keystream)
+            .map(|(byte, key)| byte ^ key)
+            .collect()
+    }
+}
+
+///
+/// Returns a nonce for a new message, from the current time
+///
+pub fn new_nonce() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or(1)
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::DccCipher;
+    use crate::parser;
+
+    #[test]
+    fn messages_are_decrypted_only_with_the_same_passphrase() {
+        let cipher = DccCipher::new("secreto");
+        let line = cipher.encrypt("hola, ¿cómo estás?", 42);
+
+        assert!(!line.contains("hola"));
+        assert!(!line.contains(' '));
+        assert_eq!(
+            DccCipher::new("secreto").decrypt(&line),
+            Some("hola, ¿cómo estás?".to_string())
+        );
+        assert_eq!(DccCipher::new("otro").decrypt(&line), None);
+    }
+
+    #[test]
+    fn encrypted_messages_can_be_parsed() {
+        let cipher = DccCipher::new("secreto");
+        let line = format!("PRIVMSG ari {}\r\n", cipher.encrypt("hola grupo", 7));
+
+        let message = parser::parse(line).unwrap();
+        assert_eq!(
+            cipher.decrypt(&message.params[1][0]),
+            Some("hola grupo".to_string())
+        );
+    }
+
+    #[test]
+    fn the_same_text_is_encrypted_differently_with_each_nonce() {
+        let cipher = DccCipher::new("secreto");
+        let first = cipher.encrypt("hola", 1);
+        let second = cipher.encrypt("hola", 2);
+
+        assert_ne!(
+            first.split_once('.').unwrap().1,
+            second.split_once('.').unwrap().1
+        );
+        assert_eq!(cipher.decrypt(&second), Some("hola".to_string()));
+    }
+
+    #[test]
+    fn lines_that_are_not_encrypted_are_not_decrypted() {
+        let cipher = DccCipher::new("secreto");
+        assert_eq!(cipher.decrypt("hola"), None);
+        assert_eq!(cipher.decrypt("zz.0102"), None);
+        assert_eq!(cipher.decrypt("0000000000000001.abc"), None);
+    }
+}
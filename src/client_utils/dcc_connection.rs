@@ -0,0 +1,165 @@
+//!
+//! The user that offers a DCC CHAT listens on a port until the other user connects to it.
+//! Both wait the same time, the one who offered for the connection and the one who accepted
+//! to connect, so the invitation expires at the same time on both ends.
+//! Files have their own times, a transfer that stops answering is interrupted instead of
+//! blocking the thread that sends or receives it
+//!
+
+use std::{
+    io::{self, ErrorKind},
+    net::{SocketAddr, TcpListener, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+
+// Time the user that offers a DCC CHAT waits for the other one until it is changed with /dcc_timeout
+pub const DEFAULT_DCC_CHAT_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Time the user that accepted a file tries to connect, the one who offered it waits 10 seconds
+pub const DCC_FILE_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// Time a file transfer waits to read or write its next bytes before it is interrupted
+pub const DCC_FILE_TRANSFER_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Time between the tries to accept the connection
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+
+///
+/// Waits until a user connects to the listener or the timeout passes, then the error is
+/// TimedOut. The listener is dropped when it returns, so the port is free again
+///
+pub fn wait_connection(listener: TcpListener, timeout: Duration) -> Result<TcpStream, io::Error> {
+    listener.set_nonblocking(true)?;
+    let started = Instant::now();
+
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                // Some systems give the connection the mode of the listener
+                stream.set_nonblocking(false)?;
+                return Ok(stream);
+            }
+            Err(error) if error.kind() == ErrorKind::WouldBlock => {
+                let waited = started.elapsed();
+                if waited >= timeout {
+                    return Err(io::Error::new(
+                        ErrorKind::TimedOut,
+                        "nobody connected in time",
+                    ));
+                }
+                thread::sleep(ACCEPT_INTERVAL.min(timeout - waited));
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+///
+/// Connects to the address offered in a DCC CHAT, waiting at most the timeout
+///
+pub fn connect(address: &str, timeout: Duration) -> Result<TcpStream, io::Error> {
+    let address: SocketAddr = address
+        .parse()
+        .map_err(|_| io::Error::new(ErrorKind::InvalidInput, "invalid address"))?;
+    TcpStream::connect_timeout(&address, timeout)
+}
+
+///
+/// Sets how long the stream of a file transfer waits for the other user to read or write
+///
+pub fn set_file_transfer_timeouts(stream: &TcpStream, timeout: Duration) -> Result<(), io::Error> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))
+}
+
+///
+/// Returns the text shown to the user that offered the chat when the other one didn't connect
+///
+pub fn expired_offer_text(nickname: &str, timeout: Duration) -> String {
+    format!(
+        "{} didn't accept the private chat in {} seconds, the invitation expired. Use /dcc_chat to send it again",
+        nickname,
+        timeout.as_secs()
+    )
+}
+
+///
+/// Returns the text shown to the user that accepted the chat when the connection failed
+///
+pub fn failed_connection_text(nickname: &str, error: &io::Error) -> String {
+    match error.kind() {
+        ErrorKind::ConnectionRefused | ErrorKind::TimedOut => format!(
+            "Couldn't start the private chat with {}, the invitation expired. Ask them to send it again",
+            nickname
+        ),
+        _ => format!(
+            "Couldn't start the private chat with {}: {}",
+            nickname, error
+        ),
+    }
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{ErrorKind, Read, Write},
+        net::{TcpListener, TcpStream},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use super::{connect, failed_connection_text, set_file_transfer_timeouts, wait_connection};
+
+    #[test]
+    fn nobody_connecting_times_out_and_frees_the_port() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let started = Instant::now();
+        let error = wait_connection(listener, Duration::from_millis(300)).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::TimedOut);
+        assert!(started.elapsed() >= Duration::from_millis(300));
+
+        // The one who accepted late is told the invitation expired
+        let error = connect(&address.to_string(), Duration::from_secs(1)).unwrap_err();
+        assert!(failed_connection_text("ari", &error).contains("the invitation expired"));
+        assert!(TcpListener::bind(address).is_ok());
+    }
+
+    #[test]
+    fn user_connecting_in_time_gets_the_chat() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            let mut stream: TcpStream = connect(&address, Duration::from_secs(1)).unwrap();
+            stream.write_all(b"hola").unwrap();
+        });
+
+        let mut stream = wait_connection(listener, Duration::from_secs(5)).unwrap();
+        let mut text = String::new();
+        stream.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "hola");
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn file_transfer_whose_peer_stops_answering_is_interrupted() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let _peer = connect(&address, Duration::from_secs(1)).unwrap();
+        let mut stream = wait_connection(listener, Duration::from_secs(1)).unwrap();
+        set_file_transfer_timeouts(&stream, Duration::from_millis(200)).unwrap();
+
+        let started = Instant::now();
+        let error = stream.read(&mut [0; 4]).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::WouldBlock | ErrorKind::TimedOut
+        ));
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+}
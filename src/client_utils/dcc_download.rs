@@ -0,0 +1,185 @@
+//!
+//! Where the files received with DCC SEND are saved. The name offered by the other user is
+//! reduced to the name of a file, so it can't be written outside the download directory, and
+//! files that already exist there are not overwritten. The resumes asked by the user are also
+//! kept, so only the DCC ACCEPTs that answer them continue a download
+//!
+
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::parser::irc_lowercase;
+
+// Directory where the files received are saved until the user chooses another one
+pub const DEFAULT_DOWNLOAD_DIRECTORY: &str = "received_files";
+
+// Name used when nothing is left of the name offered after removing its directories
+const UNNAMED_FILE: &str = "received_file";
+
+///
+/// Returns the name offered without its directories and control characters, like "passwd"
+/// for "../../etc/passwd"
+///
+pub fn sanitize_file_name(file_name: &str) -> String {
+    let name: String = file_name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|character| !character.is_control())
+        .collect();
+
+    match name.trim() {
+        "" | "." | ".." => UNNAMED_FILE.to_string(),
+        name => name.to_string(),
+    }
+}
+
+///
+/// Returns the path where a new file offered is saved, creating the directory if it doesn't
+/// exist. If there is already a file with the same name, " (1)", " (2)"... is added before the
+/// extension, like "photo (1).png"
+///
+pub fn new_file_path(directory: &Path, file_name: &str) -> Result<PathBuf, io::Error> {
+    fs::create_dir_all(directory)?;
+
+    let file_name = sanitize_file_name(file_name);
+    let path = directory.join(&file_name);
+    if !path.exists() {
+        return Ok(path);
+    }
+
+    // Hidden files, like ".bashrc", have no extension
+    let (stem, extension) = match file_name.rfind('.') {
+        Some(index) if index > 0 => file_name.split_at(index),
+        _ => (file_name.as_str(), ""),
+    };
+    let mut copy = 1;
+    loop {
+        let path = directory.join(format!("{} ({}){}", stem, copy, extension));
+        if !path.exists() {
+            return Ok(path);
+        }
+        copy += 1;
+    }
+}
+
+///
+/// Returns the path of a file whose transfer is resumed, it was saved with the name given
+///
+pub fn resumed_file_path(directory: &Path, file_name: &str) -> PathBuf {
+    directory.join(sanitize_file_name(file_name))
+}
+
+///
+/// Returns true if the file is in the directory given, so it can be a file being received
+///
+pub fn is_in_directory(file_path: &Path, directory: &Path) -> bool {
+    match (
+        file_path.parent().map(fs::canonicalize),
+        fs::canonicalize(directory),
+    ) {
+        (Some(Ok(parent)), Ok(directory)) => parent == directory,
+        _ => false,
+    }
+}
+
+///
+/// Resumes of downloads asked by the user that weren't accepted yet
+///
+#[derive(Debug, Default)]
+pub struct RequestedResumes {
+    // Nickname in lowercase of who sends the file, name of the file and position asked
+    resumes: HashSet<(String, String, u64)>,
+}
+
+impl RequestedResumes {
+    ///
+    /// The user asked the sender to continue the file from the position
+    ///
+    pub fn requested(&mut self, nickname: &str, file_name: &str, position: u64) {
+        self.resumes
+            .insert((irc_lowercase(nickname), file_name.to_string(), position));
+    }
+
+    ///
+    /// Returns true if the user asked for the resume accepted, which is no longer requested.
+    /// Resumes nobody asked for must not be followed, they would write over a file received
+    ///
+    pub fn accepted(&mut self, nickname: &str, file_name: &str, position: u64) -> bool {
+        self.resumes
+            .remove(&(irc_lowercase(nickname), file_name.to_string(), position))
+    }
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::{
+        is_in_directory, new_file_path, resumed_file_path, sanitize_file_name, RequestedResumes,
+    };
+
+    #[test]
+    fn directories_are_removed_from_the_name_offered() {
+        assert_eq!(sanitize_file_name("photo.png"), "photo.png");
+        assert_eq!(sanitize_file_name("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_file_name("..\\windows\\win.ini"), "win.ini");
+        assert_eq!(sanitize_file_name("/tmp/\x07notes.txt"), "notes.txt");
+        assert_eq!(sanitize_file_name("docs/.."), "received_file");
+        assert_eq!(sanitize_file_name("docs/"), "received_file");
+        assert_eq!(
+            resumed_file_path(Path::new("received_files"), "../photo.png"),
+            Path::new("received_files/photo.png")
+        );
+    }
+
+    #[test]
+    fn files_received_with_the_same_name_are_not_overwritten() {
+        let directory = std::env::temp_dir().join("dcc_download_test_directory");
+        let _ = fs::remove_dir_all(&directory);
+
+        // The directory is created if it doesn't exist
+        let path = new_file_path(&directory, "photo.png").unwrap();
+        assert_eq!(path, directory.join("photo.png"));
+        assert!(directory.is_dir());
+
+        fs::write(&path, "first").unwrap();
+        let path = new_file_path(&directory, "../photo.png").unwrap();
+        assert_eq!(path, directory.join("photo (1).png"));
+
+        fs::write(&path, "second").unwrap();
+        assert_eq!(
+            new_file_path(&directory, "photo.png").unwrap(),
+            directory.join("photo (2).png")
+        );
+
+        fs::write(directory.join(".bashrc"), "hidden").unwrap();
+        assert_eq!(
+            new_file_path(&directory, ".bashrc").unwrap(),
+            directory.join(".bashrc (1)")
+        );
+
+        assert!(is_in_directory(&directory.join("photo.png"), &directory));
+        assert!(!is_in_directory(&directory, &directory));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn only_the_resumes_requested_are_accepted_once() {
+        let mut resumes = RequestedResumes::default();
+        resumes.requested("Ari", "photo.png", 10);
+
+        assert!(!resumes.accepted("marce", "photo.png", 10));
+        assert!(!resumes.accepted("ari", "other.png", 10));
+        assert!(!resumes.accepted("ari", "photo.png", 0));
+        assert!(resumes.accepted("ARI", "photo.png", 10));
+        assert!(!resumes.accepted("ari", "photo.png", 10));
+    }
+}
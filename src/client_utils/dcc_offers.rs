@@ -0,0 +1,138 @@
+//!
+//! The files offered with a DCC SEND are kept until the user answers them, so they can be
+//! accepted from the dialog or by typing /dcc_accept with the nickname of who offered them.
+//! Each user has one offer pending, a new one replaces the one it had. Offers expire when the
+//! one who sent them stops waiting for the connection
+//!
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{message::Message, parser::irc_lowercase};
+
+// The user that offers a file waits 10 seconds for the connection, later it can't be accepted
+pub const DCC_OFFER_EXPIRATION: Duration = Duration::from_secs(10);
+
+///
+/// Files offered to the user that weren't accepted nor rejected yet
+///
+#[derive(Debug, Default)]
+pub struct PendingDccOffers {
+    // Nickname in lowercase of who offered the file, the DCC SEND received and when
+    offers: HashMap<String, (Message, Instant)>,
+}
+
+impl PendingDccOffers {
+    ///
+    /// Keeps the offer until it is answered. Offers without the nickname of who sent them
+    /// can't be accepted later, so they are not kept. The offers that expired are forgotten
+    ///
+    pub fn offered(&mut self, offer: &Message, now: Instant) {
+        self.offers
+            .retain(|_, (_, offered_at)| !is_expired(*offered_at, now));
+        if let Some(nickname) = &offer.prefix {
+            self.offers
+                .insert(irc_lowercase(nickname), (offer.clone(), now));
+        }
+    }
+
+    ///
+    /// Returns the offer of the user, which is no longer pending, or None if it has none or it
+    /// expired
+    ///
+    pub fn take(&mut self, nickname: &str, now: Instant) -> Option<Message> {
+        match self.offers.remove(&irc_lowercase(nickname)) {
+            Some((offer, offered_at)) if !is_expired(offered_at, now) => Some(offer),
+            _ => None,
+        }
+    }
+
+    ///
+    /// The user answered the offer in the dialog. If the user offered another file since then,
+    /// that one is still pending
+    ///
+    pub fn answered(&mut self, offer: &Message) {
+        if let Some(nickname) = &offer.prefix {
+            let nickname = irc_lowercase(nickname);
+            if matches!(self.offers.get(&nickname), Some((pending, _)) if pending == offer) {
+                self.offers.remove(&nickname);
+            }
+        }
+    }
+}
+
+///
+/// Returns true if the one who offered the file stopped waiting for the connection
+///
+fn is_expired(offered_at: Instant, now: Instant) -> bool {
+    now.saturating_duration_since(offered_at) >= DCC_OFFER_EXPIRATION
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::{PendingDccOffers, DCC_OFFER_EXPIRATION};
+    use crate::{commands::DCC_SEND, message::Message};
+
+    fn offer(nickname: &str, file_name: &str) -> Message {
+        Message {
+            tags: None,
+            prefix: Some(nickname.to_string()),
+            command: DCC_SEND.to_string(),
+            params: vec![
+                vec![file_name.to_string()],
+                vec!["127.0.0.1".to_string()],
+                vec!["5000".to_string()],
+                vec!["10".to_string()],
+            ],
+        }
+    }
+
+    #[test]
+    fn offers_are_taken_once_by_the_nickname_in_any_case() {
+        let mut pending = PendingDccOffers::default();
+        let now = Instant::now();
+        pending.offered(&offer("Ari", "photo.png"), now);
+
+        assert_eq!(pending.take("marce", now), None);
+        assert_eq!(pending.take("ari", now), Some(offer("Ari", "photo.png")));
+        assert_eq!(pending.take("ari", now), None);
+    }
+
+    #[test]
+    fn answering_an_offer_keeps_the_newer_one_of_the_user() {
+        let mut pending = PendingDccOffers::default();
+        let now = Instant::now();
+        pending.offered(&offer("ari", "photo.png"), now);
+        pending.answered(&offer("ari", "photo.png"));
+        assert_eq!(pending.take("ari", now), None);
+
+        pending.offered(&offer("ari", "photo.png"), now);
+        pending.offered(&offer("ari", "notes.txt"), now);
+        pending.answered(&offer("ari", "photo.png"));
+        assert_eq!(pending.take("ari", now), Some(offer("ari", "notes.txt")));
+    }
+
+    #[test]
+    fn offers_expire_when_the_sender_stops_waiting() {
+        let mut pending = PendingDccOffers::default();
+        let now = Instant::now();
+        pending.offered(&offer("ari", "photo.png"), now);
+
+        assert_eq!(pending.take("ari", now + DCC_OFFER_EXPIRATION), None);
+
+        // The expired offers of other users are dropped when a new one arrives
+        pending.offered(&offer("marce", "notes.txt"), now);
+        pending.offered(&offer("ari", "photo.png"), now + DCC_OFFER_EXPIRATION);
+        assert_eq!(pending.offers.len(), 1);
+        assert_eq!(
+            pending.take("ari", now + DCC_OFFER_EXPIRATION),
+            Some(offer("ari", "photo.png"))
+        );
+    }
+}
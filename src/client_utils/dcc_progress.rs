@@ -0,0 +1,105 @@
+//!
+//! The receiver of a DCC file transfer keeps how many bytes of the file it already wrote in a
+//! sidecar .part file, so the transfer can be resumed even after the client was closed
+//!
+
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+// Extension added to the name of a file being received to get the name of its index
+pub const PART_EXTENSION: &str = ".part";
+
+///
+/// Returns the path of the index of the file received, like "received_files/photo.png.part"
+///
+pub fn part_path(file_path: &Path) -> PathBuf {
+    let mut path = file_path.as_os_str().to_owned();
+    path.push(PART_EXTENSION);
+    PathBuf::from(path)
+}
+
+///
+/// Saves the amount of bytes of the file that were written
+///
+pub fn save_progress(file_path: &Path, bytes_written: u64) -> Result<(), io::Error> {
+    fs::write(part_path(file_path), bytes_written.to_string())
+}
+
+///
+/// Returns the amount of bytes saved in the index of the file, or None if there is no index
+/// or it can't be read
+///
+pub fn saved_progress(file_path: &Path) -> Option<u64> {
+    fs::read_to_string(part_path(file_path))
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+}
+
+///
+/// Returns the position where the transfer of the file has to be resumed. It is the one saved
+/// in its index, as the file could have more bytes than the ones written completely.
+/// Files without index are resumed from their end
+///
+pub fn resume_position(file_path: &Path) -> Result<u64, io::Error> {
+    let file_size = fs::metadata(file_path)?.len();
+    match saved_progress(file_path) {
+        Some(bytes_written) => Ok(bytes_written.min(file_size)),
+        None => Ok(file_size),
+    }
+}
+
+///
+/// Removes the index of a file that was received completely
+///
+pub fn finish(file_path: &Path) -> Result<(), io::Error> {
+    match fs::remove_file(part_path(file_path)) {
+        Err(error) if error.kind() != ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::{finish, part_path, resume_position, save_progress, saved_progress};
+
+    #[test]
+    fn index_is_next_to_the_file_received() {
+        assert_eq!(
+            part_path(Path::new("received_files/photo.png")),
+            Path::new("received_files/photo.png.part")
+        );
+    }
+
+    #[test]
+    fn transfer_is_resumed_from_the_bytes_saved_after_a_restart() {
+        let file_path = std::env::temp_dir().join("dcc_progress_test_file.txt");
+        fs::write(&file_path, "0123456789").unwrap();
+
+        // Without index the whole file is considered written
+        assert_eq!(resume_position(&file_path).unwrap(), 10);
+
+        // Bytes after the ones saved could be incomplete, so they are sent again
+        save_progress(&file_path, 6).unwrap();
+        assert_eq!(saved_progress(&file_path), Some(6));
+        assert_eq!(resume_position(&file_path).unwrap(), 6);
+
+        // An index bigger than the file can't be trusted further than the file
+        save_progress(&file_path, 20).unwrap();
+        assert_eq!(resume_position(&file_path).unwrap(), 10);
+
+        finish(&file_path).unwrap();
+        assert_eq!(saved_progress(&file_path), None);
+        assert!(finish(&file_path).is_ok());
+
+        fs::remove_file(&file_path).unwrap();
+    }
+}
@@ -0,0 +1,95 @@
+//!
+//! A file can be offered to every member of a channel. Each member gets its own DCC SEND with
+//! its own listener, so the transfers are independent and a member that doesn't accept the file
+//! doesn't stop the others. The chat of the channel shows how the offer to each member went
+//!
+
+use std::io;
+
+use crate::parser::irc_eq;
+
+///
+/// Returns the members of the channel the file is offered to, every member known but the user
+/// sending it, once each
+///
+pub fn channel_recipients(members: &[String], own_nickname: &str) -> Vec<String> {
+    let mut recipients: Vec<String> = vec![];
+    for member in members {
+        if irc_eq(member, own_nickname)
+            || recipients.iter().any(|recipient| irc_eq(recipient, member))
+        {
+            continue;
+        }
+        recipients.push(member.clone());
+    }
+    recipients
+}
+
+///
+/// Returns the text shown when the file was offered to the member
+///
+pub fn offered_text(nickname: &str, file_name: &str) -> String {
+    format!(
+        "{} offered to {}, waiting for them to accept it",
+        file_name, nickname
+    )
+}
+
+///
+/// Returns the text shown when the file couldn't be offered to the member
+///
+pub fn not_offered_text(nickname: &str, file_name: &str, error: &io::Error) -> String {
+    format!("Couldn't offer {} to {}: {}", file_name, nickname, error)
+}
+
+///
+/// Returns the text shown when the user the file was offered to didn't accept it in time
+///
+pub fn not_accepted_text(nickname: &str) -> String {
+    format!("{} didn't accept the transfer", nickname)
+}
+
+///
+/// Returns the text shown when no other member of the channel is known
+///
+pub fn no_recipients_text(channel: &str) -> String {
+    format!(
+        "No one else is known in {}, use /names to get its users",
+        channel
+    )
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, ErrorKind};
+
+    use super::{channel_recipients, not_offered_text, offered_text};
+
+    #[test]
+    fn recipients_are_the_other_members_once_each() {
+        let members = vec![
+            "ari".to_string(),
+            "Marce".to_string(),
+            "lucas".to_string(),
+            "ARI".to_string(),
+        ];
+
+        assert_eq!(channel_recipients(&members, "marce"), vec!["ari", "lucas"]);
+        assert!(channel_recipients(&["marce".to_string()], "Marce").is_empty());
+    }
+
+    #[test]
+    fn each_recipient_has_its_own_status() {
+        assert_eq!(
+            offered_text("ari", "photo.png"),
+            "photo.png offered to ari, waiting for them to accept it"
+        );
+        let error = io::Error::new(ErrorKind::AddrInUse, "no ports left");
+        assert_eq!(
+            not_offered_text("lucas", "photo.png", &error),
+            "Couldn't offer photo.png to lucas: no ports left"
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//!
+//! The messages sent to the server are numbered in their label tag and the server answers each
+//! one with an ACK that has the same label. The messages that are not acknowledged in time are
+//! sent again, a few times at most. The server acknowledges again the numbers it already
+//! received without handling their messages twice
+//!
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+use crate::message::{Message, LABEL_TAG};
+
+// Time waited for the ACK of a message before sending it again
+pub const ACKNOWLEDGEMENT_TIMEOUT: Duration = Duration::from_secs(5);
+// Times a message is sent before it is given up as lost
+pub const DELIVERY_ATTEMPTS: usize = 3;
+
+///
+/// Messages sent to the server that weren't acknowledged yet
+///
+#[derive(Debug, Default)]
+pub struct PendingDeliveries {
+    // Label given to the last message sent
+    last_label: u64,
+    // Label of each message, with the line sent, when it was last sent and the times it was sent
+    pending: BTreeMap<u64, (String, Instant, usize)>,
+}
+
+impl PendingDeliveries {
+    ///
+    /// Returns the line to send with the message and the next label, which is kept until it
+    /// is acknowledged
+    ///
+    pub fn label(&mut self, message: &Message, now: Instant) -> String {
+        self.last_label += 1;
+        let mut message = message.clone();
+        message
+            .tags
+            .get_or_insert_with(HashMap::new)
+            .insert(LABEL_TAG.to_string(), self.last_label.to_string());
+        let line = message.as_string();
+        self.pending.insert(self.last_label, (line.clone(), now, 1));
+        line
+    }
+
+    ///
+    /// The server received the message with the label, it is not sent again
+    ///
+    pub fn acknowledged(&mut self, label: u64) {
+        self.pending.remove(&label);
+    }
+
+    ///
+    /// Returns the messages whose ACK didn't arrive in time, oldest first, to send them again.
+    /// Messages already sent every attempt are forgotten and returned as lost
+    ///
+    pub fn take_due(&mut self, now: Instant) -> (Vec<String>, Vec<String>) {
+        let mut due = Vec::new();
+        let mut lost = Vec::new();
+        self.pending.retain(|_, (line, sent_at, attempts)| {
+            if now.saturating_duration_since(*sent_at) < ACKNOWLEDGEMENT_TIMEOUT {
+                return true;
+            }
+            if *attempts >= DELIVERY_ATTEMPTS {
+                lost.push(line.clone());
+                return false;
+            }
+            *sent_at = now;
+            *attempts += 1;
+            due.push(line.clone());
+            true
+        });
+        (due, lost)
+    }
+
+    ///
+    /// Forgets the messages of a connection that was lost, the server of the new connection
+    /// knows nothing about them
+    ///
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::{PendingDeliveries, ACKNOWLEDGEMENT_TIMEOUT};
+    use crate::{commands::PRIVMSG, message::Message};
+
+    fn message(text: &str) -> Message {
+        Message {
+            tags: None,
+            prefix: None,
+            command: PRIVMSG.to_string(),
+            params: vec![vec!["ari".to_string()], vec![text.to_string()]],
+        }
+    }
+
+    #[test]
+    fn messages_are_labeled_in_order_and_written_with_their_label() {
+        let mut pending = PendingDeliveries::default();
+        let now = Instant::now();
+
+        assert_eq!(
+            pending.label(&message("hola"), now),
+            "@label=1 PRIVMSG ari hola\r\n"
+        );
+        assert_eq!(
+            pending.label(&message("chau"), now),
+            "@label=2 PRIVMSG ari chau\r\n"
+        );
+    }
+
+    #[test]
+    fn messages_not_acknowledged_are_sent_again_until_they_are_lost() {
+        let mut pending = PendingDeliveries::default();
+        let now = Instant::now();
+        pending.label(&message("hola"), now);
+        let second = pending.label(&message("chau"), now);
+        pending.acknowledged(1);
+
+        assert_eq!(pending.take_due(now), (vec![], vec![]));
+        let later = now + ACKNOWLEDGEMENT_TIMEOUT;
+        assert_eq!(pending.take_due(later), (vec![second.clone()], vec![]));
+        // The time is counted again from the retransmission
+        assert_eq!(pending.take_due(later), (vec![], vec![]));
+
+        let later = later + ACKNOWLEDGEMENT_TIMEOUT;
+        assert_eq!(pending.take_due(later), (vec![second.clone()], vec![]));
+        let later = later + ACKNOWLEDGEMENT_TIMEOUT;
+        assert_eq!(pending.take_due(later), (vec![], vec![second]));
+        assert_eq!(
+            pending.take_due(later + ACKNOWLEDGEMENT_TIMEOUT),
+            (vec![], vec![])
+        );
+    }
+
+    #[test]
+    fn messages_of_a_lost_connection_are_not_sent_again() {
+        let mut pending = PendingDeliveries::default();
+        let now = Instant::now();
+        pending.label(&message("hola"), now);
+        pending.clear();
+
+        assert_eq!(
+            pending.take_due(now + ACKNOWLEDGEMENT_TIMEOUT),
+            (vec![], vec![])
+        );
+        // Labels keep growing in the new connection
+        assert_eq!(
+            pending.label(&message("chau"), now),
+            "@label=2 PRIVMSG ari chau\r\n"
+        );
+    }
+}
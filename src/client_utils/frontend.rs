@@ -0,0 +1,27 @@
+//!
+//! The frontend receives the updates of the threads that talk with the server.
+//! The GTK client and the headless client are both frontends
+//!
+
+use std::sync::mpsc::{SendError, Sender};
+
+use crate::message::Message;
+
+pub trait Frontend {
+    ///
+    /// Sends an update to the frontend
+    ///
+    fn send(&self, message: Message) -> Result<(), SendError<Message>>;
+}
+
+impl Frontend for Sender<Message> {
+    fn send(&self, message: Message) -> Result<(), SendError<Message>> {
+        Sender::send(self, message)
+    }
+}
+
+impl<T: Frontend> Frontend for &T {
+    fn send(&self, message: Message) -> Result<(), SendError<Message>> {
+        (*self).send(message)
+    }
+}
@@ -1,5 +1,6 @@
 use super::gtk_login;
 use crate::{
+    commands::{TLS_OFF, TLS_ON},
     custom_errors::{
         client_error::ClientError,
         errors::{CRITICAL, SEND_MESSAGE},
@@ -68,17 +69,37 @@ impl WindowConnect {
             .builder
             .object("entry_port")
             .expect("Couldn't get entry_password");
+        let tls_check: gtk::CheckButton = self
+            .builder
+            .object("check_tls")
+            .expect("Couldn't get check_tls");
+        let error_label: gtk::Label = self
+            .builder
+            .object("label_connection_error")
+            .expect("Couldn't get label_connection_error");
+
+        // Connecting without encryption must be chosen by the user, so it is warned about it
+        tls_check.connect_toggled(move |tls_check| match tls_check.is_active() {
+            true => error_label.set_text(""),
+            false => error_label.set_text("Passwords will be sent without encryption"),
+        });
 
         // Sends a connection attempt to the Client
         login_button.connect_clicked(move |_| {
+            let tls = match tls_check.is_active() {
+                true => TLS_ON,
+                false => TLS_OFF,
+            };
             tx_backend
                 .send(Message {
+                    tags: None,
                     prefix: None,
                     command: "CONNECTION_ATTEMPT".to_string(),
                     params: vec![vec![
                         server_name_entry.text().to_string(),
                         server_ip_entry.text().to_string(),
                         port_entry.text().to_string(),
+                        tls.to_string(),
                     ]],
                 })
                 .map_err(|_| -> ClientError {
@@ -92,14 +113,22 @@ impl WindowConnect {
     }
 
     ///
-    /// Updates the error label
+    /// Updates the error label with the reason why the connection failed
     ///
-    pub fn connection_error(&self) {
+    pub fn connection_error(&self, reason: &str) {
         let error_label: gtk::Label = self
             .builder
             .object("label_connection_error")
             .expect("Couldn't get entry_password");
-        error_label.set_text("Couldn't connect to server");
+        error_label.set_text(reason);
+    }
+
+    ///
+    /// Shows the connection window again after the connection was lost, with the reason
+    ///
+    pub fn show_again(&self, reason: &str) {
+        self.connection_error(reason);
+        self.window.show_all();
     }
 
     // Hides the connection window
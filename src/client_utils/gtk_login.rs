@@ -9,6 +9,14 @@ use std::sync::mpsc::Sender;
 #[path = "gtk_register.rs"]
 mod gtk_register;
 
+// Nickname, password and real name the user logged in with, kept to log in again after reconnecting
+#[derive(Clone)]
+pub struct Credentials {
+    pub nickname: String,
+    pub password: String,
+    pub realname: String,
+}
+
 #[derive(Clone)]
 pub struct WindowLogin {
     pub window: gtk::ApplicationWindow,
@@ -76,11 +84,6 @@ impl WindowLogin {
             .builder
             .object("label_login_error")
             .expect("Couldn't get entry_password");
-        let user_env = match env::var("USER") {
-            // Search username por environment variable
-            Ok(val) => val,
-            Err(_e) => "Anonymous".to_string(),
-        };
 
         login_button.connect_clicked(move |_| {
             if password_entry.text().is_empty()
@@ -89,62 +92,22 @@ impl WindowLogin {
             {
                 error_label.set_text("Please fill in all values");
             } else {
-                tx_frontend
-                    .send(Message {
-                        prefix: None,
-                        command: LOGIN.to_string(),
-                        params: vec![],
-                    })
-                    .map_err(|_| -> ClientError {
-                        ClientError {
-                            kind: CRITICAL.to_string(),
-                            message: SEND_MESSAGE.to_string(),
-                        }
-                    })
-                    .ok();
-                tx_frontend
-                    .send(Message {
-                        prefix: None,
-                        command: PASS.to_string(),
-                        params: vec![vec![password_entry.text().to_string()]],
-                    })
-                    .map_err(|_| -> ClientError {
-                        ClientError {
-                            kind: CRITICAL.to_string(),
-                            message: SEND_MESSAGE.to_string(),
-                        }
-                    })
-                    .ok();
-                tx_frontend
-                    .send(Message {
-                        prefix: None,
-                        command: NICK.to_string(),
-                        params: vec![vec![nickname_entry.text().to_string()]],
-                    })
-                    .map_err(|_| -> ClientError {
-                        ClientError {
-                            kind: CRITICAL.to_string(),
-                            message: SEND_MESSAGE.to_string(),
-                        }
-                    })
-                    .ok();
-
-                tx_frontend
-                    .send(Message {
-                        prefix: None,
-                        command: USER.to_string(),
-                        params: vec![
-                            vec![user_env.clone(), server_ip.clone(), server_name.clone()],
-                            vec![realname_entry.text().to_string()],
-                        ],
-                    })
-                    .map_err(|_| -> ClientError {
-                        ClientError {
-                            kind: CRITICAL.to_string(),
-                            message: SEND_MESSAGE.to_string(),
-                        }
-                    })
-                    .ok();
+                let credentials = Credentials {
+                    nickname: nickname_entry.text().to_string(),
+                    password: password_entry.text().to_string(),
+                    realname: realname_entry.text().to_string(),
+                };
+                for message in login_messages(&credentials, &server_ip, &server_name) {
+                    tx_frontend
+                        .send(message)
+                        .map_err(|_| -> ClientError {
+                            ClientError {
+                                kind: CRITICAL.to_string(),
+                                message: SEND_MESSAGE.to_string(),
+                            }
+                        })
+                        .ok();
+                }
             }
         });
     }
@@ -181,6 +144,19 @@ impl WindowLogin {
         login_error_label.set_text("Invalid nickname or password");
     }
 
+    ///
+    /// Shows how the connection is progressing in the login and register error labels,
+    /// so the user knows the server is handling the request
+    ///
+    pub fn connection_progress(&self, progress: String) {
+        let login_error_label: gtk::Label = self
+            .builder
+            .object("label_login_error")
+            .expect("Couldn't get login_error_label");
+        login_error_label.set_text(progress.as_str());
+        self.window_register.invalid_registration(progress);
+    }
+
     // Hides the login window
     pub fn hide(&self) {
         self.window.hide();
@@ -196,3 +172,64 @@ impl WindowLogin {
         self.window_register.invalid_registration(error);
     }
 }
+
+///
+/// Reads the credentials typed in the entries of a login or register window
+///
+pub fn entered_credentials(builder: &Builder) -> Credentials {
+    let entry_text = |name: &str| -> String {
+        builder
+            .object::<gtk::Entry>(name)
+            .map(|entry| entry.text().to_string())
+            .unwrap_or_default()
+    };
+    Credentials {
+        nickname: entry_text("entry_nickname"),
+        password: entry_text("entry_password"),
+        realname: entry_text("entry_realname"),
+    }
+}
+
+///
+/// Returns the messages sent to the server to log in with the credentials given
+///
+pub fn login_messages(
+    credentials: &Credentials,
+    server_ip: &str,
+    server_name: &str,
+) -> Vec<Message> {
+    let user_env = match env::var("USER") {
+        // Search username por environment variable
+        Ok(val) => val,
+        Err(_e) => "Anonymous".to_string(),
+    };
+    vec![
+        Message {
+            tags: None,
+            prefix: None,
+            command: LOGIN.to_string(),
+            params: vec![],
+        },
+        Message {
+            tags: None,
+            prefix: None,
+            command: PASS.to_string(),
+            params: vec![vec![credentials.password.clone()]],
+        },
+        Message {
+            tags: None,
+            prefix: None,
+            command: NICK.to_string(),
+            params: vec![vec![credentials.nickname.clone()]],
+        },
+        Message {
+            tags: None,
+            prefix: None,
+            command: USER.to_string(),
+            params: vec![
+                vec![user_env, server_ip.to_string(), server_name.to_string()],
+                vec![credentials.realname.clone()],
+            ],
+        },
+    ]
+}
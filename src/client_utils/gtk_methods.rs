@@ -1,33 +1,172 @@
 use gtk::prelude::*;
 use gtk::TextBuffer;
 
-use super::message_types::{ERROR, INFO};
+use super::message_types::NOTICE as NOTICE_MESSAGE;
+use super::message_types::{ERROR, INFO, PRIVATE_MESSAGE};
+use super::ui_updater::format_action;
 use super::Client;
+use super::UNREAD_CHAT_CLASS;
 use crate::client_utils::client::handle_dcc_chat;
 use crate::client_utils::client::handle_dcc_file_send;
+use crate::client_utils::dcc_checksum;
+use crate::client_utils::dcc_cipher::DccCipher;
+use crate::client_utils::dcc_connection::{self, DCC_FILE_TRANSFER_TIMEOUT};
+use crate::client_utils::dcc_download;
+use crate::client_utils::dcc_progress;
+use crate::client_utils::dcc_recipients;
+use crate::client_utils::nick_completion::NickCompletion;
+use crate::client_utils::typing::{typing_notice, typing_text};
 use crate::commands::DCC_CLOSE;
+use crate::commands::DCC_ENCRYPTED;
+use crate::commands::DCC_TIMEOUT;
+use crate::commands::GLOBOPS;
+use crate::commands::MODE_SET_BAN;
 use crate::commands::PAUSE;
+use crate::commands::SAVE;
+use crate::commands::TYPING;
 use crate::commands::{
-    ADD_LIST_CHATS, AWAY, DCC_CHAT, INVITE, JOIN, KICK, LIST, MODE, NAMES, OPER, OPERATOR, PART,
-    PART_CHANNEL, PRIVMSG, QUIT, RECEIVED_MESSAGE, SQUIT, TOPIC, UNAWAY, WHO, WHOIS,
+    ACTION, ADD_LIST_CHATS, ANNOUNCE_TOPICS, AWAY, BANS, CONFIRM, DCC_CHAT, EVENTS, HELP, IGNORE,
+    INVITE, ISON, JOIN, KICK, KNOCK, LIST, LUSERS, MAX_FILE_SIZE, MODE, MOTD, MSG, MUTE, NAMES,
+    NOTICE, OPER, OPERATOR, PART, PART_CHANNEL, PRIVMSG, QUIT, RAW, RECEIVED_MESSAGE, REGISTER,
+    REHASH, REJOIN, SQUIT, TOPIC, UNAWAY, UNIGNORE, UNMUTE, WHO, WHOIS, WHOWAS,
 };
+use crate::commands::{CTCP_ACTION, CTCP_DELIMITER};
+use crate::commands::{DCC_ACCEPT, DCC_ACCEPT_OFFER};
 use crate::custom_errors::client_error::ClientError;
 use crate::custom_errors::errors::LOCK_DCC;
+use crate::custom_errors::errors::MISSING_WIDGET;
 use crate::custom_errors::errors::{LOCK_USERS, NONCRITICAL, SEND_MESSAGE};
 use crate::message::Message;
 use crate::parser;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::collections::HashSet;
-use std::fs::File;
+use std::fs;
+use std::io;
 use std::io::ErrorKind;
 use std::net::TcpListener;
 use std::net::TcpStream;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::channel;
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::thread;
+use std::time::{Duration, Instant};
+
+// Commands that can ask for confirmation before being sent
+pub const CONFIRMABLE_COMMANDS: [&str; 3] = [QUIT, SQUIT, KICK];
+
+// Syntax of the commands typed in the message entry, in the order they are shown
+const COMMANDS_HELP: &[(&str, &str)] = &[
+    (AWAY, "/away [message] - Set your away status"),
+    (UNAWAY, "/unaway - Remove your away status"),
+    (WHOIS, "/whois [nickname] - Get information about a user"),
+    (WHOWAS, "/whowas [nickname] - Get information about a user that quit"),
+    (LUSERS, "/lusers - Get how many users, channels and servers there are"),
+    (ISON, "/ison [nicknames] - Check which of the users are online"),
+    (MOTD, "/motd - Get the message of the day of the server"),
+    (OPER, "/oper [name] [password] - Become an IRC operator, the name is your nickname if it is left out"),
+    (QUIT, "/quit [message] - Quit the IRC server"),
+    (SQUIT, "/squit [server] [comment] - Disconnect a server from the IRC network"),
+    (REHASH, "/rehash - Make the server read its server data file again, only for operators"),
+    (GLOBOPS, "/globops [message] - Send a message to the operators of every server, only for operators"),
+    (NAMES, "/names - Get a list of users in current channel"),
+    (TOPIC, "/topic [topic] - Set the topic of the current channel"),
+    (PART, "/part - Leave current channel"),
+    (REGISTER, "/register - Keep the current channel when it is left empty, only for its operators"),
+    (REJOIN, "/rejoin - Join again the last channel you left"),
+    (MSG, "/msg [nickname] [message] - Send a message to a user, opening the chat with them"),
+    (NOTICE, "/notice [nickname] [message] - Send a notice to a user, it doesn't open a chat"),
+    (INVITE, "/invite [nickname] - Invite a user to the current channel"),
+    (KNOCK, "/knock [channel] [message] - Ask the operators of an invite only channel for an invitation"),
+    (KICK, "/kick [nickname] [comment] - Kick a user from the current channel"),
+    (MODE, "/mode [mode] - Set the mode of the current channel, without a mode it shows the modes set"),
+    (MODE, "/mode [your nickname] [+i/-i] - Hide/Show yourself in the /who of users that don't share a channel with you"),
+    (BANS, "/bans [channel] - List the bans of a channel, the current one if it is left out"),
+    (DCC_CHAT, "/dcc_chat [passphrase] - Send a DCC chat request to a user, encrypted if a passphrase is given"),
+    (DCC_CLOSE, "/dcc_close - Close a DCC chat"),
+    (MUTE, "/mute [channel] - Stop notifications from a channel"),
+    (UNMUTE, "/unmute [channel] - Resume notifications from a channel"),
+    (IGNORE, "/ignore [nickname] - Hide the messages of a user until the client is closed, without a nickname it lists the users ignored"),
+    (UNIGNORE, "/unignore [nickname] - Show again the messages of a user"),
+    (RAW, "/raw - Show/Hide the messages from the server as they are received"),
+    (CONFIRM, "/confirm [command] - Turn on/off the confirmation before /quit, /squit or /kick"),
+    (ANNOUNCE_TOPICS, "/announce_topics - Show/Hide the topic changes in the channels"),
+    (TYPING, "/typing - Turn on/off telling and seeing who is typing in the private chats"),
+    (EVENTS, "/events [inline/hidden/collapsed] - Choose how joins, parts and quits are shown"),
+    (MAX_FILE_SIZE, "/max_file_size [megabytes/off] - Warn before accepting files bigger than this size"),
+    (DCC_TIMEOUT, "/dcc_timeout [seconds] - Choose how long a DCC chat request waits for the other user"),
+    (DCC_ACCEPT, "/dcc_accept [nickname] - Accept the file offered by a user without the dialog"),
+    (ACTION, "/me [action] - Describe an action in the current chat, like /me waves"),
+    (SAVE, "/save [filename] - Save the current chat to a text file, named after the chat and the time if left out"),
+    (HELP, "/help [command] - Show the commands, or the syntax of one of them"),
+];
+
+// Max distance in pixels from the bottom of the chat to still follow new messages
+pub const SCROLL_BOTTOM_MARGIN: f64 = 10.0;
+
+// Name of the mark used to scroll to the end of a chat
+const CHAT_END_MARK: &str = "chat_end";
+
+// Time between the checks of who is typing in the chat shown
+const TYPING_LABEL_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+///
+/// Keeps the keys of the channels joined and the last channel parted, so it can be joined again with /rejoin
+///
+#[derive(Debug, Default)]
+pub struct ChannelMemory {
+    keys: HashMap<String, String>,
+    last_parted: Option<(String, Option<String>)>,
+}
+
+impl ChannelMemory {
+    ///
+    /// Saves the key used to join a channel
+    ///
+    pub fn remember_key(&mut self, channel: &str, key: &str) {
+        self.keys.insert(channel.to_string(), key.to_string());
+    }
+
+    ///
+    /// Saves the channel as the last one parted, with its key if it had one
+    ///
+    pub fn record_part(&mut self, channel: &str) {
+        let key = self.keys.remove(channel);
+        self.last_parted = Some((channel.to_string(), key));
+    }
+
+    ///
+    /// Returns the JOIN message for the last channel parted and forgets it.
+    /// Returns None if no channel was parted since the last rejoin
+    ///
+    pub fn take_rejoin(&mut self) -> Option<Message> {
+        let (channel, key) = self.last_parted.take()?;
+        Some(join_with_key(channel, key))
+    }
+
+    ///
+    /// Returns the JOIN message for a channel joined, with its key if it had one.
+    /// Used to join the open channels again after reconnecting
+    ///
+    pub fn join_message(&self, channel: &str) -> Message {
+        join_with_key(channel.to_string(), self.keys.get(channel).cloned())
+    }
+}
+
+fn join_with_key(channel: String, key: Option<String>) -> Message {
+    let params = match key {
+        Some(key) => vec![vec![channel], vec![key]],
+        None => vec![vec![channel]],
+    };
+    Message {
+        tags: None,
+        prefix: None,
+        command: JOIN.to_string(),
+        params,
+    }
+}
 
 ///
 /// Initializes main chat window
@@ -47,22 +186,41 @@ pub fn initialize_gtk_window(
     window.set_application(Some(&client.application));
 
     // Setup all of the UI components
-    setup_nickname_label(client, &nickname.to_owned());
     setup_own_chat(client, users, buffers);
     setup_channel_join(client, tx_frontend.clone());
     setup_send_button(client, tx_frontend.clone(), tx_backend.clone(), nickname);
-    setup_channel_refresh_button(client, tx_frontend.clone());
-    setup_search_user_button(client, tx_frontend.clone());
-    setup_send_file_button(client, tx_frontend.clone(), tx_backend.clone());
-    setup_pause_transfer_button(client);
-    setup_resume_transfer_button(client, tx_frontend.clone());
+
+    // The client can still be used without these, so a missing widget only shows an error
+    let optional_setups = vec![
+        setup_nickname_label(client, &nickname.to_owned()),
+        setup_channel_refresh_button(client, tx_frontend.clone()),
+        setup_search_user_button(client, tx_frontend.clone()),
+        setup_send_file_button(client, tx_frontend.clone(), tx_backend.clone(), nickname),
+        setup_pause_transfer_button(client),
+        setup_resume_transfer_button(client, tx_frontend.clone(), nickname),
+        setup_download_directory_chooser(client),
+        setup_jump_to_latest_button(client),
+        setup_timestamps_check(client),
+        setup_formatting_check(client),
+        setup_nick_completion(client, nickname),
+        setup_typing_notifications(client, tx_frontend.clone()),
+    ];
 
     window.resize(1000, 600);
     window.show_all();
+    for error in optional_setups.into_iter().filter_map(Result::err) {
+        show_error_dialog(client, &error);
+    }
+    // Stop notifying once the user comes back to the window
+    window.connect_focus_in_event(|window, _| {
+        window.set_urgency_hint(false);
+        gtk::Inhibit(false)
+    });
     window.connect_delete_event(move |_, _| {
         println!("Finished application.");
         tx_frontend
             .send(Message {
+                tags: None,
                 prefix: None,
                 command: QUIT.to_string(),
                 params: vec![],
@@ -76,6 +234,7 @@ pub fn initialize_gtk_window(
             .ok();
         tx_backend
             .send(Message {
+                tags: None,
                 prefix: None,
                 command: QUIT.to_string(),
                 params: vec![],
@@ -92,22 +251,56 @@ pub fn initialize_gtk_window(
     hide_dummy_entry(client);
 }
 
+///
+/// Gets the widget with the given id from the builder.
+/// Returns an error instead of panicking if the UI definition doesn't have it
+///
+pub fn get_object<T: IsA<gtk::glib::Object>>(
+    builder: &gtk::Builder,
+    id: &str,
+) -> Result<T, ClientError> {
+    widget_or_error(builder.object(id), id)
+}
+
+///
+/// Returns the widget found, or an error with the id of the missing widget
+///
+pub fn widget_or_error<T>(widget: Option<T>, id: &str) -> Result<T, ClientError> {
+    widget.ok_or_else(|| ClientError {
+        kind: NONCRITICAL.to_string(),
+        message: format!("{} {}", MISSING_WIDGET, id),
+    })
+}
+
+///
+/// Shows a dialog with the error, so the user knows that part of the window won't work
+///
+pub fn show_error_dialog(client: &Client, error: &ClientError) {
+    let mut dialog_builder = gtk::MessageDialog::builder()
+        .modal(true)
+        .message_type(gtk::MessageType::Error)
+        .buttons(gtk::ButtonsType::Ok)
+        .text(error.message.as_str());
+    if let Ok(window) = get_object::<gtk::ApplicationWindow>(&client.builder, "window") {
+        dialog_builder = dialog_builder.transient_for(&window);
+    }
+    let dialog = dialog_builder.build();
+    dialog.run();
+    dialog.close();
+}
+
 // This entry is used to get the focus on the chat window
 pub fn hide_dummy_entry(client: &Client) {
-    let dummy_entry: gtk::Entry = client
-        .builder
-        .object("dummy_no_focus")
-        .expect("Couldn't get dummy entry");
-    dummy_entry.hide();
+    if let Ok(dummy_entry) = get_object::<gtk::Entry>(&client.builder, "dummy_no_focus") {
+        dummy_entry.hide();
+    }
 }
 
 // Sets up the nickname label with the nickname of the user
-fn setup_nickname_label(client: &Client, nickname: &String) {
-    let nickname_label: gtk::Label = client
-        .builder
-        .object("nickname_label")
-        .expect("Couldn't get nickname label");
+fn setup_nickname_label(client: &Client, nickname: &String) -> Result<(), ClientError> {
+    let nickname_label: gtk::Label = get_object(&client.builder, "nickname_label")?;
     nickname_label.set_text(&format!("Logged in as {}", nickname));
+    Ok(())
 }
 
 ///
@@ -151,23 +344,11 @@ fn setup_own_chat(
 /// Changes the text of the chat to the tutorial
 ///
 fn setup_own_chat_tutorial(buffer: &TextBuffer) {
-    let tutorial_text = "✉️ Welcome to Panicked at pensar Nombre's IRC client! ✉️\n
+    let tutorial_text = format!(
+        "✉️ Welcome to Panicked at pensar Nombre's IRC client! ✉️\n
 This is your own chat.\n
 Apart from the UI, you can also use the following shortcuts:
-♦️ /away [message] - Set your away status
-♦️ /unaway - Remove your away status
-♦️ /whois [nickname] - Get information about a user
-♦️ /oper [password] - Become an IRC operator
-♦️ /quit [message] - Quit the IRC server
-♦️ /squit [server] [comment] - Disconnect a server from the IRC network
-♦️ /names - Get a list of users in current channel
-♦️ /topic [topic] - Set the topic of the current channel
-♦️ /part - Leave current channel
-♦️ /invite [nickname] - Invite a user to the current channel
-♦️ /kick [nickname] - Kick a user from the current channel
-♦️ /mode [mode] - Set the mode of the current channel
-♦️ /dcc_chat - Send a DCC chat request to a user
-♦️ /dcc_close - Close a DCC chat
+{}
 Possible modes are:
 ⚪️ +k [key] - Set a channel key
 ⚪️ -k - Remove the channel key
@@ -177,10 +358,17 @@ Possible modes are:
 ⚪️ (+/-)o [nickname] - Give/Remove a user operator status
 ⚪️ (+/-)t - Set/Remove the topic operator-only mode
 ⚪️ (+/-)s - Set/Remove the secret mode
-⚪️ (+/-)b [nickname] - Ban/Unban a user from the channel
-
-✉️ Have fun! ✉️\n\n";
-    buffer.insert(&mut buffer.end_iter(), tutorial_text);
+⚪️ (+/-)b [nickname or mask] - Ban/Unban a user from the channel, masks look like nick!user@host and can use * and ?
+⚪️ +b - List the bans of the channel
+⚪️ (+/-)e [nickname or mask] - Let a user join even if banned, or remove the exception
+⚪️ +e - List the ban exceptions of the channel
+⚪️ +f [events:seconds:cooldown] - For cooldown seconds (60 if not given), make the channel invite only if more users join in that time, or let only operators and voiced users talk if more messages are sent
+⚪️ -f - Remove the flood protection
+
+✉️ Have fun! ✉️\n\n",
+        help_text(None).unwrap_or_default()
+    );
+    buffer.insert(&mut buffer.end_iter(), &tutorial_text);
 }
 
 ///
@@ -206,70 +394,66 @@ fn setup_channel_join(client: &Client, tx_frontend: Sender<Message>) {
         .object("label_channel_name_error")
         .expect("Couldn't get label_channel_name_error");
     let channels_clone = client.channels.clone();
+    let channel_memory_clone = client.channel_memory.clone();
     channel_button.connect_clicked(move |_| {
-        // If entry is empty, show an error message
-        if channel_entry.text().is_empty() {
-            label_channel_name_error.set_text("Please choose a channel name");
-            return;
-        }
-
-        let channel_name = channel_entry.text().to_string();
-
-        // If channel name doesn't start with # or &, show an error message
-        if !(channel_name.starts_with('#') || channel_name.starts_with('&')) {
-            label_channel_name_error.set_text("Must start with # or &");
-            channel_entry.delete_text(0, -1);
-            return;
-        }
-
-        // If starts with # or &, but is only 1 character long, show an error message
-        if channel_entry.text().len() < 2 {
-            label_channel_name_error.set_text("Channel name can't be empty");
-            return;
-        }
+        let entries = match parse_join_entries(
+            channel_entry.text().as_str(),
+            channel_password_entry.text().as_str(),
+        ) {
+            Ok(entries) => entries,
+            Err(error) => {
+                label_channel_name_error.set_text(&error);
+                return;
+            }
+        };
 
         let channels_clone = channels_clone.lock().expect("Couldn't lock channels");
-        // If channel is already in the list of channels, don't send messages
-        if channels_clone.contains(&channel_name) {
+        // Channels already in the list of channels are not joined again
+        let entries: Vec<(String, Option<String>)> = entries
+            .into_iter()
+            .filter(|(channel_name, _)| !channels_clone.contains(channel_name))
+            .collect();
+        if entries.is_empty() {
             label_channel_name_error.set_text("You're already in this channel");
             return;
         }
 
-        let channel_password = channel_password_entry.text().to_string();
+        let mut channels_names = vec![];
+        let mut channels_keys = vec![];
+        for (channel_name, key) in entries {
+            if let Some(key) = key {
+                channel_memory_clone
+                    .lock()
+                    .expect("Couldn't lock channel memory")
+                    .remember_key(&channel_name, &key);
+                channels_keys.push(key);
+            }
+            channels_names.push(channel_name);
+        }
 
-        // If password is empty, send a JOIN command without password, else, send a JOIN command with password
-        if channel_password.is_empty() {
-            tx_frontend
-                .send(Message {
-                    prefix: None,
-                    command: JOIN.to_string(),
-                    params: vec![vec![channel_name.clone()]],
-                })
-                .map_err(|_| -> ClientError {
-                    ClientError {
-                        kind: NONCRITICAL.to_string(),
-                        message: SEND_MESSAGE.to_string(),
-                    }
-                })
-                .ok();
-        } else {
-            tx_frontend
-                .send(Message {
-                    prefix: None,
-                    command: JOIN.to_string(),
-                    params: vec![vec![channel_name.clone()], vec![channel_password]],
-                })
-                .map_err(|_| -> ClientError {
-                    ClientError {
-                        kind: NONCRITICAL.to_string(),
-                        message: SEND_MESSAGE.to_string(),
-                    }
-                })
-                .ok();
+        // The i-th key is sent for the i-th channel
+        let mut params = vec![channels_names.clone()];
+        if !channels_keys.is_empty() {
+            params.push(channels_keys);
         }
+        tx_frontend
+            .send(Message {
+                tags: None,
+                prefix: None,
+                command: JOIN.to_string(),
+                params,
+            })
+            .map_err(|_| -> ClientError {
+                ClientError {
+                    kind: NONCRITICAL.to_string(),
+                    message: SEND_MESSAGE.to_string(),
+                }
+            })
+            .ok();
 
         tx_frontend
             .send(Message {
+                tags: None,
                 prefix: None,
                 command: LIST.to_string(),
                 params: vec![],
@@ -282,12 +466,13 @@ fn setup_channel_join(client: &Client, tx_frontend: Sender<Message>) {
             })
             .ok();
 
-        // Get names of users in channel
+        // Get names of users in channels
         tx_frontend
             .send(Message {
+                tags: None,
                 prefix: None,
                 command: NAMES.to_string(),
-                params: vec![vec![channel_name]],
+                params: vec![channels_names],
             })
             .map_err(|_| -> ClientError {
                 ClientError {
@@ -303,18 +488,57 @@ fn setup_channel_join(client: &Client, tx_frontend: Sender<Message>) {
     });
 }
 
+///
+/// Splits the channels and keys written in the join submenu, both separated by spaces.
+/// The i-th key belongs to the i-th channel, channels left without key are joined without one.
+/// If a channel name is not valid the error shown to the user is returned
+///
+pub fn parse_join_entries(
+    channels: &str,
+    keys: &str,
+) -> Result<Vec<(String, Option<String>)>, String> {
+    let channels_names: Vec<&str> = channels.split_whitespace().collect();
+    if channels_names.is_empty() {
+        return Err("Please choose a channel name".to_string());
+    }
+
+    let mut keys = keys.split_whitespace();
+    let mut entries = vec![];
+    for channel_name in channels_names {
+        // Channel names must start with # or &
+        if !(channel_name.starts_with('#') || channel_name.starts_with('&')) {
+            return Err("Must start with # or &".to_string());
+        }
+        // If starts with # or &, but is only 1 character long the name is empty
+        if channel_name.len() < 2 {
+            return Err("Channel name can't be empty".to_string());
+        }
+        if channel_name.contains(',') {
+            return Err("Separate channels with spaces".to_string());
+        }
+        entries.push((
+            channel_name.to_string(),
+            keys.next().map(|key| key.to_string()),
+        ));
+    }
+
+    Ok(entries)
+}
+
 ///
 /// Setup button "Refresh List" in the channel submenu.
 /// When clicked, it sends the corresponding command to the thread, for the client to send to the server
 ///
-fn setup_channel_refresh_button(client: &Client, tx_frontend: Sender<Message>) {
-    let channel_refresh_button = client
-        .builder
-        .object::<gtk::Button>("channel_refresh_button")
-        .expect("Couldn't get channel_refresh_button");
+fn setup_channel_refresh_button(
+    client: &Client,
+    tx_frontend: Sender<Message>,
+) -> Result<(), ClientError> {
+    let channel_refresh_button =
+        get_object::<gtk::Button>(&client.builder, "channel_refresh_button")?;
     channel_refresh_button.connect_clicked(move |_| {
         tx_frontend
             .send(Message {
+                tags: None,
                 prefix: None,
                 command: LIST.to_string(),
                 params: vec![],
@@ -327,6 +551,7 @@ fn setup_channel_refresh_button(client: &Client, tx_frontend: Sender<Message>) {
             })
             .ok();
     });
+    Ok(())
 }
 
 ///
@@ -355,23 +580,34 @@ fn setup_send_button(
         .expect("Couldn't get chat_text");
     let users_clone = client.online_chats_buffers.clone();
     let dcc_chats_clone = client.dcc_chats.clone();
+    let dcc_chat_timeout_clone = client.dcc_chat_timeout.clone();
+    let confirm_commands_clone = client.confirm_commands.clone();
+    let channel_memory_clone = client.channel_memory.clone();
+    let window = client.window.clone();
     let nickname_clone = nickname.to_owned();
     send_button.connect_clicked(move |_| {
         let message = message_entry.text().to_string();
         let users = users_clone.lock().expect(LOCK_USERS).clone();
 
+        let chats: Vec<String> = users.keys().cloned().collect();
         let current_name_chat = find_user_by_current_buffer(users, &text_view); // Can be user or channel
-        if !message.is_empty() {
+        if has_text_to_send(&message) {
             println!("Current name chat: {}", current_name_chat);
             if message.starts_with('/') {
                 send_command(
                     dcc_chats_clone.clone(),
+                    &dcc_chat_timeout_clone,
+                    confirm_commands_clone.clone(),
+                    channel_memory_clone.clone(),
+                    &window,
                     message.as_str(),
                     &tx_frontend,
                     current_name_chat,
                     &tx_backend,
                     &message_entry,
+                    &text_view,
                     &nickname_clone,
+                    &chats,
                 );
                 return;
             }
@@ -390,6 +626,7 @@ fn setup_send_button(
                 text_view.clone(),
                 current_name_chat,
                 true,
+                &nickname_clone,
             );
         }
     });
@@ -413,8 +650,17 @@ pub fn find_user_by_current_buffer(
     user.to_string()
 }
 
+///
+/// Returns false for the messages that are empty or only have whitespace, they are not sent
+///
+pub fn has_text_to_send(message: &str) -> bool {
+    !message.trim().is_empty()
+}
+
 ///
 /// This function sends a PRIVMSG command to the client.
+/// Messages to the own nickname are not sent, the server wouldn't deliver them, and neither
+/// are the ones without text
 ///
 pub fn send_privmsg(
     tx_frontend: &Sender<Message>,
@@ -423,13 +669,29 @@ pub fn send_privmsg(
     text_view: gtk::TextView,
     user_to_send: String,
     print_to_self_buffer: bool,
+    nickname: &str,
 ) {
     println!("User to send PRIVMSG: {}", user_to_send);
+    if !has_text_to_send(message) {
+        return;
+    }
+    if parser::irc_eq(&user_to_send, nickname) {
+        let buffer = text_view.buffer().expect("Couldn't get buffer");
+        buffer.insert(
+            &mut buffer.end_iter(),
+            "@ERROR You can't send messages to yourself\r\n",
+        );
+        if let Some(entry) = message_entry {
+            entry.delete_text(0, -1)
+        }
+        return;
+    }
     // If the user is not in the self chat, send it
     if user_to_send != *"You".to_string() && !user_to_send.is_empty() {
         println!("Sending PRIVMSG");
         tx_frontend
             .send(Message {
+                tags: None,
                 prefix: None,
                 command: PRIVMSG.to_string(),
                 params: vec![vec![user_to_send], vec![message.clone()]],
@@ -459,35 +721,57 @@ pub fn send_privmsg(
 ///
 /// This function parses and sends a command to the client.
 ///
+#[allow(clippy::too_many_arguments)]
 pub fn send_command(
     dcc_chats: Arc<Mutex<HashMap<String, Sender<Message>>>>,
+    dcc_chat_timeout: &Arc<Mutex<Duration>>,
+    confirm_commands: Arc<Mutex<HashSet<String>>>,
+    channel_memory: Arc<Mutex<ChannelMemory>>,
+    window: &gtk::ApplicationWindow,
     message: &str,
     tx_frontend: &Sender<Message>,
     current_chat_name: String,
     tx_backend: &gtk::glib::Sender<Message>,
     message_entry: &gtk::Entry,
+    chat_text: &gtk::TextView,
     nickname: &str,
+    chats: &[String],
 ) {
-    let general_purpose_commands: HashSet<&str> =
-        HashSet::from_iter(vec![AWAY, UNAWAY, WHOIS, OPER, QUIT, SQUIT]);
+    let general_purpose_commands: HashSet<&str> = HashSet::from_iter(vec![
+        AWAY,
+        UNAWAY,
+        WHOIS,
+        WHOWAS,
+        LUSERS,
+        ISON,
+        MOTD,
+        OPER,
+        QUIT,
+        SQUIT,
+        REHASH,
+        GLOBOPS,
+        MUTE,
+        UNMUTE,
+        IGNORE,
+        UNIGNORE,
+        RAW,
+        CONFIRM,
+        ANNOUNCE_TOPICS,
+        TYPING,
+        EVENTS,
+        MAX_FILE_SIZE,
+        DCC_TIMEOUT,
+        DCC_ACCEPT,
+        SAVE,
+        REJOIN,
+        NOTICE,
+        KNOCK,
+    ]);
     let user_only_commands: HashSet<&str> = HashSet::from_iter(vec![DCC_CHAT, DCC_CLOSE]);
-    let commands_with_messages: HashSet<&str> = HashSet::from_iter(vec![TOPIC, AWAY, SQUIT, QUIT]);
-    let max_amount_params: HashMap<&str, usize> = HashMap::from_iter(vec![
-        (AWAY, 1),
-        (UNAWAY, 0),
-        (WHOIS, 1),
-        (OPER, 1),
-        (QUIT, 1),
-        (SQUIT, 2),
-        (NAMES, 0),
-        (TOPIC, 1),
-        (PART, 0),
-        (INVITE, 1),
-        (KICK, 1),
-        (MODE, 2),
-        (DCC_CHAT, 0),
-        (DCC_CLOSE, 0),
+    let commands_with_messages: HashSet<&str> = HashSet::from_iter(vec![
+        TOPIC, AWAY, SQUIT, QUIT, KICK, NOTICE, ISON, GLOBOPS, KNOCK,
     ]);
+    let max_amount_params = max_amount_params();
 
     let space_index = parser::next_whitespace(message);
 
@@ -502,6 +786,7 @@ pub fn send_command(
         let text_to_print = format!("'{}' is not a valid command", command);
         tx_backend
             .send(Message {
+                tags: None,
                 prefix: None,
                 command: RECEIVED_MESSAGE.to_string(),
                 params: vec![vec![text_to_print, ERROR.to_string()]],
@@ -522,10 +807,31 @@ pub fn send_command(
         None => "",
     };
 
+    // Actions are a single text sent to the current chat, either a channel or a user
+    if command == ACTION {
+        message_entry.delete_text(0, -1);
+        send_action(
+            params_str,
+            tx_frontend,
+            tx_backend,
+            current_chat_name,
+            nickname,
+        );
+        return;
+    }
+
+    // The text of a private message can have spaces and commas, it is sent as it was written
+    if command == MSG {
+        message_entry.delete_text(0, -1);
+        send_msg(params_str, tx_frontend, tx_backend, chats, nickname);
+        return;
+    }
+
     // Check if the user tried to separate the params with a comma, which is not allowed
     if params_str.contains(',') {
         tx_backend
             .send(Message {
+                tags: None,
                 prefix: None,
                 command: RECEIVED_MESSAGE.to_string(),
                 params: vec![vec![
@@ -581,6 +887,7 @@ pub fn send_command(
         };
         tx_backend
             .send(Message {
+                tags: None,
                 prefix: None,
                 command: RECEIVED_MESSAGE.to_string(),
                 params: vec![vec![text_to_print, ERROR.to_string()]],
@@ -595,6 +902,53 @@ pub fn send_command(
         return;
     }
 
+    // Destructive commands could ask the user for confirmation before being sent
+    let needs_confirmation = requires_confirmation(
+        &command,
+        &confirm_commands
+            .lock()
+            .expect("Couldn't lock confirm commands"),
+    );
+    if needs_confirmation
+        && !confirm_command(
+            window,
+            &confirmation_text(&command, &params, &current_chat_name),
+        )
+    {
+        return;
+    }
+
+    // The help is shown in any chat
+    if command == HELP {
+        show_help(params.first(), tx_backend);
+        return;
+    }
+
+    // The chat shown is saved by the client only, nothing is sent to the server
+    if command == SAVE {
+        save_chat(chat_text, &current_chat_name, params.first(), tx_backend);
+        return;
+    }
+
+    // Rejoining needs the memory of the last channel parted
+    if command == REJOIN {
+        rejoin_channel(&channel_memory, tx_frontend, tx_backend);
+        return;
+    }
+
+    // The bans can be asked from any chat if the channel is given
+    if command == BANS {
+        ask_bans(&params, &current_chat_name, tx_frontend, tx_backend);
+        return;
+    }
+
+    // The modes of the user can be set from any chat, like /mode ari +i
+    let is_user_mode = matches!(params.first(), Some(target) if parser::irc_eq(target, nickname));
+    if command == MODE && is_user_mode {
+        send_user_mode(&params, tx_frontend);
+        return;
+    }
+
     // General purpose commands
     send_general_purpose_commands(&command, &params, tx_frontend, tx_backend, nickname);
 
@@ -606,6 +960,7 @@ pub fn send_command(
             let text_to_print = format!("'{}' can only be used in channels", command);
             tx_backend
                 .send(Message {
+                    tags: None,
                     prefix: None,
                     command: RECEIVED_MESSAGE.to_string(),
                     params: vec![vec![text_to_print, ERROR.to_string()]],
@@ -622,7 +977,11 @@ pub fn send_command(
             // Send user only commands
             send_user_only_commands(
                 dcc_chats,
+                *dcc_chat_timeout
+                    .lock()
+                    .expect("Couldn't lock dcc chat timeout"),
                 &command,
+                &params,
                 tx_frontend,
                 tx_backend,
                 current_chat_name,
@@ -639,47 +998,62 @@ pub fn send_command(
         tx_backend,
         current_chat_name,
         nickname,
+        &channel_memory,
     );
 }
 
 ///
-/// For commands that can only be used by users
-/// Builds the message to send depending on the command and sends it to the client
+/// Sends a MODE for the user, with the mode given or without one to ask for the modes set
 ///
-pub fn send_user_only_commands(
-    dcc_chats: Arc<Mutex<HashMap<String, Sender<Message>>>>,
-    command: &str,
+fn send_user_mode(params: &[String], tx_frontend: &Sender<Message>) {
+    tx_frontend
+        .send(Message {
+            tags: None,
+            prefix: None,
+            command: MODE.to_string(),
+            params: params.iter().map(|param| vec![param.clone()]).collect(),
+        })
+        .map_err(|_| -> ClientError {
+            ClientError {
+                kind: NONCRITICAL.to_string(),
+                message: SEND_MESSAGE.to_string(),
+            }
+        })
+        .ok();
+}
+
+///
+/// Returns the channel whose bans /bans asks for, the one given or the current chat if it is a
+/// channel
+///
+pub fn bans_channel(params: &[String], current_chat_name: &str) -> Option<String> {
+    match params.first() {
+        Some(channel) => Some(channel.clone()),
+        None if current_chat_name.starts_with('#') || current_chat_name.starts_with('&') => {
+            Some(current_chat_name.to_string())
+        }
+        None => None,
+    }
+}
+
+///
+/// Sends a MODE +b without masks, which the server answers with the bans of the channel.
+/// If no channel is given outside of a channel chat, an error is shown instead
+///
+fn ask_bans(
+    params: &[String],
+    current_chat_name: &str,
     tx_frontend: &Sender<Message>,
     tx_backend: &gtk::glib::Sender<Message>,
-    user_to_send: String,
 ) {
-    match command {
-        DCC_CHAT => {
-            let listener = TcpListener::bind("0.0.0.0:0").unwrap();
-            // Spawn new thread for the client
-            let addres = listener.local_addr().unwrap();
-            println!("Listening on {}", addres);
-            let tx_backend_clone = tx_backend.clone();
-            let user_to_send_clone = user_to_send.clone();
-            let _ = thread::spawn(move || {
-                // connection succeeded
-                match wait_connection_dcc_chat(
-                    listener,
-                    tx_backend_clone,
-                    dcc_chats,
-                    user_to_send_clone,
-                ) {
-                    Ok(_) => println!("Private connection ended"),
-                    Err(err) => println!("Error: {}", err),
-                }
-            });
-            let message = format!("DCC_CHAT chat {} {}", addres.ip(), addres.port());
-            println!("Sending message DCC_CHAT");
+    match bans_channel(params, current_chat_name) {
+        Some(channel) => {
             tx_frontend
                 .send(Message {
+                    tags: None,
                     prefix: None,
-                    command: PRIVMSG.to_string(),
-                    params: vec![vec![user_to_send], vec![message]],
+                    command: MODE.to_string(),
+                    params: vec![vec![channel], vec![MODE_SET_BAN.to_string()]],
                 })
                 .map_err(|_| -> ClientError {
                     ClientError {
@@ -689,22 +1063,16 @@ pub fn send_user_only_commands(
                 })
                 .ok();
         }
-        DCC_CLOSE => {
-            let mut dcc_chats = dcc_chats.lock().unwrap();
-            let sender = dcc_chats.remove(&user_to_send);
-            drop(dcc_chats);
-            if let Some(sender) = sender {
-                let _ = sender.send(Message {
-                    prefix: None,
-                    command: DCC_CLOSE.to_string(),
-                    params: vec![],
-                });
-            }
-            tx_frontend
+        None => {
+            tx_backend
                 .send(Message {
+                    tags: None,
                     prefix: None,
-                    command: PRIVMSG.to_string(),
-                    params: vec![vec![user_to_send], vec![DCC_CLOSE.to_string()]],
+                    command: RECEIVED_MESSAGE.to_string(),
+                    params: vec![vec![
+                        "You must specify a channel".to_string(),
+                        ERROR.to_string(),
+                    ]],
                 })
                 .map_err(|_| -> ClientError {
                     ClientError {
@@ -714,60 +1082,212 @@ pub fn send_user_only_commands(
                 })
                 .ok();
         }
-        _ => {}
-    };
+    }
 }
 
 ///
-/// Waits for 10 seconds the acceptance of dcc chat connection. If the dcc chat connection is
-/// accepted whitin 10 seconds, then handle_dcc_chat is called. Whether the connection is accepted
-/// or not, the client is notified so the ui is updated.
+/// Sends a JOIN for the last channel parted, with its key if it had one.
+/// If no channel was parted, an error is shown instead
 ///
-pub fn wait_connection_dcc_chat(
-    listener: TcpListener,
-    tx_backend: gtk::glib::Sender<Message>,
-    dcc_chats: Arc<Mutex<HashMap<String, Sender<Message>>>>,
-    user_to_send: String,
-) -> Result<(), ClientError> {
-    listener.set_nonblocking(true).map_err(|_| -> ClientError {
-        ClientError {
-            kind: NONCRITICAL.to_string(),
-            message: SEND_MESSAGE.to_string(),
-        }
-    })?;
-
-    let mut i = 0;
-    let one_second = std::time::Duration::from_millis(1000);
-    let mut stream: Option<TcpStream> = None;
-
-    // Try to accept connection for 10 seconds
-    while i < 10 {
-        match listener.accept() {
-            Ok(result) => {
-                stream = Some(result.0);
-                break;
-            }
-            Err(error) => {
-                if error.kind() == ErrorKind::WouldBlock {
-                    i += 1;
-                    thread::sleep(one_second);
-                    continue;
-                } else {
-                    break;
-                }
+fn rejoin_channel(
+    channel_memory: &Arc<Mutex<ChannelMemory>>,
+    tx_frontend: &Sender<Message>,
+    tx_backend: &gtk::glib::Sender<Message>,
+) {
+    let join = channel_memory
+        .lock()
+        .expect("Couldn't lock channel memory")
+        .take_rejoin();
+    let join = match join {
+        Some(join) => join,
+        None => {
+            tx_backend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: RECEIVED_MESSAGE.to_string(),
+                    params: vec![vec![
+                        "There is no channel to rejoin".to_string(),
+                        ERROR.to_string(),
+                    ]],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+            return;
+        }
+    };
+    let channel_name = join.params[0].clone();
+
+    tx_frontend
+        .send(join)
+        .map_err(|_| -> ClientError {
+            ClientError {
+                kind: NONCRITICAL.to_string(),
+                message: SEND_MESSAGE.to_string(),
             }
-        };
+        })
+        .ok();
+
+    tx_frontend
+        .send(Message {
+            tags: None,
+            prefix: None,
+            command: LIST.to_string(),
+            params: vec![],
+        })
+        .map_err(|_| -> ClientError {
+            ClientError {
+                kind: NONCRITICAL.to_string(),
+                message: SEND_MESSAGE.to_string(),
+            }
+        })
+        .ok();
+
+    // Get names of users in channel
+    tx_frontend
+        .send(Message {
+            tags: None,
+            prefix: None,
+            command: NAMES.to_string(),
+            params: vec![channel_name],
+        })
+        .map_err(|_| -> ClientError {
+            ClientError {
+                kind: NONCRITICAL.to_string(),
+                message: SEND_MESSAGE.to_string(),
+            }
+        })
+        .ok();
+}
+
+///
+/// Returns the nickname and the text of a /msg, the text is everything after the nickname.
+/// If the message can't be sent the error shown is returned instead
+///
+pub fn msg_receiver_and_text<'a>(
+    params: &'a str,
+    nickname: &str,
+) -> Result<(&'a str, &'a str), &'static str> {
+    let (receiver, text) = params
+        .trim_start()
+        .split_once(' ')
+        .ok_or("You must specify a nickname and a message")?;
+    let text = text.trim();
+    if receiver.is_empty() || text.is_empty() {
+        return Err("You must specify a nickname and a message");
+    }
+    // Like in the chats, the nickname of the user is checked in any case
+    if parser::irc_eq(receiver, nickname) {
+        return Err("You can't send messages to yourself");
+    }
+    Ok((receiver, text))
+}
+
+///
+/// Sends the text to the user given and shows it in their chat, which is opened if it wasn't.
+/// If there is a chat with the user, written with other case, that one is used
+///
+fn send_msg(
+    params: &str,
+    tx_frontend: &Sender<Message>,
+    tx_backend: &gtk::glib::Sender<Message>,
+    chats: &[String],
+    nickname: &str,
+) {
+    let (receiver, text) = match msg_receiver_and_text(params, nickname) {
+        Ok(msg) => msg,
+        Err(error) => {
+            tx_backend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: RECEIVED_MESSAGE.to_string(),
+                    params: vec![vec![error.to_string(), ERROR.to_string()]],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+            return;
+        }
+    };
+    let chat = chats
+        .iter()
+        .find(|chat| parser::irc_eq(chat, receiver))
+        .cloned()
+        .unwrap_or_else(|| receiver.to_string());
+
+    // The chat is only added if it doesn't exist yet
+    let messages = [
+        Message {
+            tags: None,
+            prefix: Some(chat.clone()),
+            command: ADD_LIST_CHATS.to_string(),
+            params: vec![vec![]],
+        },
+        Message {
+            tags: None,
+            prefix: Some(chat.clone()),
+            command: RECEIVED_MESSAGE.to_string(),
+            params: vec![vec![format!("You: {}", text), PRIVATE_MESSAGE.to_string()]],
+        },
+    ];
+    for message in messages {
+        tx_backend
+            .send(message)
+            .map_err(|_| -> ClientError {
+                ClientError {
+                    kind: NONCRITICAL.to_string(),
+                    message: SEND_MESSAGE.to_string(),
+                }
+            })
+            .ok();
     }
 
-    // If stream is None then the connection was rejected
-    if stream.is_none() {
+    tx_frontend
+        .send(Message {
+            tags: None,
+            prefix: None,
+            command: PRIVMSG.to_string(),
+            params: vec![vec![chat], vec![text.to_string()]],
+        })
+        .map_err(|_| -> ClientError {
+            ClientError {
+                kind: NONCRITICAL.to_string(),
+                message: SEND_MESSAGE.to_string(),
+            }
+        })
+        .ok();
+}
+
+///
+/// Sends the text as a CTCP ACTION to the current chat and shows it as "* nick text"
+///
+fn send_action(
+    text: &str,
+    tx_frontend: &Sender<Message>,
+    tx_backend: &gtk::glib::Sender<Message>,
+    current_chat_name: String,
+    nickname: &str,
+) {
+    let text = text.trim();
+    if text.is_empty() {
         tx_backend
             .send(Message {
+                tags: None,
                 prefix: None,
                 command: RECEIVED_MESSAGE.to_string(),
                 params: vec![vec![
-                    format!("The connection wasn't accepted"),
-                    INFO.to_string(),
+                    format!("'{}' needs a text, like /me waves", ACTION),
+                    ERROR.to_string(),
                 ]],
             })
             .map_err(|_| -> ClientError {
@@ -775,20 +1295,203 @@ pub fn wait_connection_dcc_chat(
                     kind: NONCRITICAL.to_string(),
                     message: SEND_MESSAGE.to_string(),
                 }
-            })?;
-    } else {
-        println!("Connection accepted");
-        // If stream exists then the connection was accepted
-        let mut dcc_chats = dcc_chats.lock().unwrap();
-        let (tx, rx) = channel();
-        dcc_chats.insert(user_to_send.clone(), tx);
-        println!("DCC CHAT {:?}", dcc_chats);
-        drop(dcc_chats);
+            })
+            .ok();
+        return;
+    }
 
-        // Start reading and writing messages in dcc_chat
-        handle_dcc_chat(stream.unwrap(), tx_backend, rx, &user_to_send)?;
+    if current_chat_name != "You" && !current_chat_name.is_empty() {
+        tx_frontend
+            .send(Message {
+                tags: None,
+                prefix: None,
+                command: PRIVMSG.to_string(),
+                params: vec![vec![current_chat_name], vec![ctcp_action(text)]],
+            })
+            .map_err(|_| -> ClientError {
+                ClientError {
+                    kind: NONCRITICAL.to_string(),
+                    message: SEND_MESSAGE.to_string(),
+                }
+            })
+            .ok();
     }
 
+    // Without prefix it's shown in the current chat
+    tx_backend
+        .send(Message {
+            tags: None,
+            prefix: None,
+            command: RECEIVED_MESSAGE.to_string(),
+            params: vec![vec![
+                format_action(nickname, text),
+                PRIVATE_MESSAGE.to_string(),
+            ]],
+        })
+        .map_err(|_| -> ClientError {
+            ClientError {
+                kind: NONCRITICAL.to_string(),
+                message: SEND_MESSAGE.to_string(),
+            }
+        })
+        .ok();
+}
+
+///
+/// Returns the text wrapped as a CTCP ACTION, to be sent in a PRIVMSG
+///
+pub fn ctcp_action(text: &str) -> String {
+    format!(
+        "{}{} {}{}",
+        CTCP_DELIMITER, CTCP_ACTION, text, CTCP_DELIMITER
+    )
+}
+
+///
+/// For commands that can only be used by users
+/// Builds the message to send depending on the command and sends it to the client
+/// A DCC chat with a passphrase is encrypted with it, and waits for the other user until the timeout
+///
+pub fn send_user_only_commands(
+    dcc_chats: Arc<Mutex<HashMap<String, Sender<Message>>>>,
+    dcc_chat_timeout: Duration,
+    command: &str,
+    params: &Vec<String>,
+    tx_frontend: &Sender<Message>,
+    tx_backend: &gtk::glib::Sender<Message>,
+    user_to_send: String,
+) {
+    match command {
+        DCC_CHAT => {
+            let listener = TcpListener::bind("0.0.0.0:0").unwrap();
+            // Spawn new thread for the client
+            let addres = listener.local_addr().unwrap();
+            println!("Listening on {}", addres);
+            let tx_backend_clone = tx_backend.clone();
+            let user_to_send_clone = user_to_send.clone();
+            let cipher = params.first().map(|passphrase| DccCipher::new(passphrase));
+            let encrypted = cipher.is_some();
+            let _ = thread::spawn(move || {
+                // connection succeeded
+                match wait_connection_dcc_chat(
+                    listener,
+                    tx_backend_clone,
+                    dcc_chats,
+                    user_to_send_clone,
+                    cipher,
+                    dcc_chat_timeout,
+                ) {
+                    Ok(_) => println!("Private connection ended"),
+                    Err(err) => println!("Error: {}", err),
+                }
+            });
+            let mut message = format!("DCC_CHAT chat {} {}", addres.ip(), addres.port());
+            if encrypted {
+                message.push_str(&format!(" {}", DCC_ENCRYPTED));
+            }
+            println!("Sending message DCC_CHAT");
+            tx_frontend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: PRIVMSG.to_string(),
+                    params: vec![vec![user_to_send], vec![message]],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+        }
+        DCC_CLOSE => {
+            let mut dcc_chats = dcc_chats.lock().unwrap();
+            let sender = dcc_chats.remove(&user_to_send);
+            drop(dcc_chats);
+            if let Some(sender) = sender {
+                let _ = sender.send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: DCC_CLOSE.to_string(),
+                    params: vec![],
+                });
+            }
+            tx_frontend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: PRIVMSG.to_string(),
+                    params: vec![vec![user_to_send], vec![DCC_CLOSE.to_string()]],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+        }
+        _ => {}
+    };
+}
+
+///
+/// Waits the timeout for the other user to accept the dcc chat connection. If it is accepted
+/// in time handle_dcc_chat is called, if not the listener is closed so the port is free and
+/// the user is told the invitation expired
+///
+pub fn wait_connection_dcc_chat(
+    listener: TcpListener,
+    tx_backend: gtk::glib::Sender<Message>,
+    dcc_chats: Arc<Mutex<HashMap<String, Sender<Message>>>>,
+    user_to_send: String,
+    cipher: Option<DccCipher>,
+    timeout: Duration,
+) -> Result<(), ClientError> {
+    let stream = match dcc_connection::wait_connection(listener, timeout) {
+        Ok(stream) => stream,
+        Err(error) => {
+            let (text_to_print, message_type) = match error.kind() {
+                ErrorKind::TimedOut => (
+                    dcc_connection::expired_offer_text(&user_to_send, timeout),
+                    INFO,
+                ),
+                _ => (
+                    format!(
+                        "Couldn't wait for the private chat with {}: {}",
+                        user_to_send, error
+                    ),
+                    ERROR,
+                ),
+            };
+            tx_backend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: RECEIVED_MESSAGE.to_string(),
+                    params: vec![vec![text_to_print, message_type.to_string()]],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })?;
+            return Ok(());
+        }
+    };
+
+    println!("Connection accepted");
+    let mut dcc_chats = dcc_chats.lock().unwrap();
+    let (tx, rx) = channel();
+    dcc_chats.insert(user_to_send.clone(), tx);
+    println!("DCC CHAT {:?}", dcc_chats);
+    drop(dcc_chats);
+
+    // Start reading and writing messages in dcc_chat
+    handle_dcc_chat(stream, tx_backend, rx, &user_to_send, cipher)?;
+
     Ok(())
 }
 
@@ -807,6 +1510,7 @@ pub fn send_general_purpose_commands(
         AWAY => {
             tx_frontend
                 .send(Message {
+                    tags: None,
                     prefix: None,
                     command: AWAY.to_string(),
                     params: vec![params.clone()],
@@ -822,6 +1526,7 @@ pub fn send_general_purpose_commands(
         UNAWAY => {
             tx_frontend
                 .send(Message {
+                    tags: None,
                     prefix: None,
                     command: AWAY.to_string(),
                     params: vec![],
@@ -842,6 +1547,7 @@ pub fn send_general_purpose_commands(
             };
             tx_frontend
                 .send(Message {
+                    tags: None,
                     prefix: None,
                     command: WHOIS.to_string(),
                     params: params_to_send,
@@ -854,14 +1560,137 @@ pub fn send_general_purpose_commands(
                 })
                 .ok();
         }
+        WHOWAS => {
+            tx_frontend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: WHOWAS.to_string(),
+                    params: vec![params.clone()],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+        }
+        ISON => {
+            // The nicknames are sent in a single parameter, the server splits them
+            tx_frontend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: ISON.to_string(),
+                    params: vec![params.clone()],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+        }
+        LUSERS => {
+            tx_frontend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: LUSERS.to_string(),
+                    params: vec![],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+        }
+        MOTD => {
+            tx_frontend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: MOTD.to_string(),
+                    params: vec![],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+        }
+        REHASH => {
+            tx_frontend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: REHASH.to_string(),
+                    params: vec![],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+        }
+        KNOCK => {
+            // The channel can be followed by the message for its operators
+            let params_to_send = match params.first().map(|param| param.split_once(' ')) {
+                Some(Some((channel, text))) => {
+                    vec![vec![channel.to_string()], vec![text.to_string()]]
+                }
+                Some(None) => vec![params.clone()],
+                None => vec![],
+            };
+            tx_frontend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: KNOCK.to_string(),
+                    params: params_to_send,
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+        }
+        GLOBOPS => {
+            tx_frontend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: GLOBOPS.to_string(),
+                    params: vec![params.clone()],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+        }
         OPER => {
             let params_to_send = match params.len() {
                 0 => vec![vec![nickname.to_string()]], // If it has no parameters, send only the nickname
                 1 => vec![vec![nickname.to_string()], vec![params[0].clone()]], // If it has one parameter, send the nickname and the password
-                _ => vec![vec![]], // If it has more than one parameters, it's an error
+                2 => vec![vec![params[0].clone()], vec![params[1].clone()]], // If it has two parameters, send the name of the operator and the password
+                _ => vec![vec![]], // If it has more than two parameters, it's an error
             };
             tx_frontend
                 .send(Message {
+                    tags: None,
                     prefix: None,
                     command: OPERATOR.to_string(),
                     params: params_to_send,
@@ -877,8 +1706,39 @@ pub fn send_general_purpose_commands(
         QUIT => {
             tx_frontend
                 .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: QUIT.to_string(),
+                    params: vec![params.clone()],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+            tx_backend
+                .send(Message {
+                    tags: None,
                     prefix: None,
                     command: QUIT.to_string(),
+                    params: vec![],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+        }
+        SQUIT => {
+            tx_frontend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: SQUIT.to_string(),
                     params: vec![params.clone()],
                 })
                 .map_err(|_| -> ClientError {
@@ -888,10 +1748,318 @@ pub fn send_general_purpose_commands(
                     }
                 })
                 .ok();
+        }
+        NOTICE => {
+            // The nickname is followed by the text of the notice
+            let notice = match params.len() {
+                1 => params[0].split_once(' '),
+                2 => Some((params[0].as_str(), params[1].as_str())),
+                _ => None,
+            };
+            let (receiver, text) = match notice {
+                Some(notice) => notice,
+                None => {
+                    tx_backend
+                        .send(Message {
+                            tags: None,
+                            prefix: None,
+                            command: RECEIVED_MESSAGE.to_string(),
+                            params: vec![vec![
+                                "You must specify a nickname and a message".to_string(),
+                                ERROR.to_string(),
+                            ]],
+                        })
+                        .map_err(|_| -> ClientError {
+                            ClientError {
+                                kind: NONCRITICAL.to_string(),
+                                message: SEND_MESSAGE.to_string(),
+                            }
+                        })
+                        .ok();
+                    return;
+                }
+            };
+            tx_frontend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: NOTICE.to_string(),
+                    params: vec![vec![receiver.to_string()], vec![text.to_string()]],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+            let text_to_print = format!("You -> {}: {}", receiver, text);
+            tx_backend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: RECEIVED_MESSAGE.to_string(),
+                    params: vec![vec![text_to_print, NOTICE_MESSAGE.to_string()]],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+        }
+        MUTE | UNMUTE => {
+            if params.is_empty() {
+                tx_backend
+                    .send(Message {
+                        tags: None,
+                        prefix: None,
+                        command: RECEIVED_MESSAGE.to_string(),
+                        params: vec![vec![
+                            "You must specify a channel".to_string(),
+                            ERROR.to_string(),
+                        ]],
+                    })
+                    .map_err(|_| -> ClientError {
+                        ClientError {
+                            kind: NONCRITICAL.to_string(),
+                            message: SEND_MESSAGE.to_string(),
+                        }
+                    })
+                    .ok();
+                return;
+            }
+            // Muting is handled by the client only, nothing is sent to the server
+            tx_backend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: command.to_string(),
+                    params: vec![vec![params[0].clone()]],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+        }
+        IGNORE | UNIGNORE => {
+            // Without a nickname /ignore lists the users ignored
+            if params.is_empty() && command == UNIGNORE {
+                tx_backend
+                    .send(Message {
+                        tags: None,
+                        prefix: None,
+                        command: RECEIVED_MESSAGE.to_string(),
+                        params: vec![vec![
+                            "You must specify a nickname".to_string(),
+                            ERROR.to_string(),
+                        ]],
+                    })
+                    .map_err(|_| -> ClientError {
+                        ClientError {
+                            kind: NONCRITICAL.to_string(),
+                            message: SEND_MESSAGE.to_string(),
+                        }
+                    })
+                    .ok();
+                return;
+            }
+            // The ignored users are kept by the client only, the server keeps sending their messages
+            tx_backend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: command.to_string(),
+                    params: params.iter().map(|param| vec![param.clone()]).collect(),
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+        }
+        CONFIRM => {
+            if params.is_empty() {
+                tx_backend
+                    .send(Message {
+                        tags: None,
+                        prefix: None,
+                        command: RECEIVED_MESSAGE.to_string(),
+                        params: vec![vec![
+                            "You must specify a command".to_string(),
+                            ERROR.to_string(),
+                        ]],
+                    })
+                    .map_err(|_| -> ClientError {
+                        ClientError {
+                            kind: NONCRITICAL.to_string(),
+                            message: SEND_MESSAGE.to_string(),
+                        }
+                    })
+                    .ok();
+                return;
+            }
+            // Confirmations are handled by the client only, nothing is sent to the server
+            tx_backend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: CONFIRM.to_string(),
+                    params: vec![vec![params[0].trim_start_matches('/').to_string()]],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+        }
+        EVENTS => {
+            if params.is_empty() {
+                tx_backend
+                    .send(Message {
+                        tags: None,
+                        prefix: None,
+                        command: RECEIVED_MESSAGE.to_string(),
+                        params: vec![vec![
+                            "You must specify inline, hidden or collapsed".to_string(),
+                            ERROR.to_string(),
+                        ]],
+                    })
+                    .map_err(|_| -> ClientError {
+                        ClientError {
+                            kind: NONCRITICAL.to_string(),
+                            message: SEND_MESSAGE.to_string(),
+                        }
+                    })
+                    .ok();
+                return;
+            }
+            // This setting is handled by the client only, nothing is sent to the server
+            tx_backend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: EVENTS.to_string(),
+                    params: vec![vec![params[0].clone()]],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+        }
+        MAX_FILE_SIZE => {
+            if params.is_empty() {
+                tx_backend
+                    .send(Message {
+                        tags: None,
+                        prefix: None,
+                        command: RECEIVED_MESSAGE.to_string(),
+                        params: vec![vec![
+                            "You must specify a size in megabytes or off".to_string(),
+                            ERROR.to_string(),
+                        ]],
+                    })
+                    .map_err(|_| -> ClientError {
+                        ClientError {
+                            kind: NONCRITICAL.to_string(),
+                            message: SEND_MESSAGE.to_string(),
+                        }
+                    })
+                    .ok();
+                return;
+            }
+            // This setting is handled by the client only, nothing is sent to the server
+            tx_backend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: MAX_FILE_SIZE.to_string(),
+                    params: vec![vec![params[0].clone()]],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+        }
+        DCC_TIMEOUT => {
+            if params.is_empty() {
+                tx_backend
+                    .send(Message {
+                        tags: None,
+                        prefix: None,
+                        command: RECEIVED_MESSAGE.to_string(),
+                        params: vec![vec![
+                            "You must specify the seconds to wait".to_string(),
+                            ERROR.to_string(),
+                        ]],
+                    })
+                    .map_err(|_| -> ClientError {
+                        ClientError {
+                            kind: NONCRITICAL.to_string(),
+                            message: SEND_MESSAGE.to_string(),
+                        }
+                    })
+                    .ok();
+                return;
+            }
+            // This setting is handled by the client only, nothing is sent to the server
+            tx_backend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: DCC_TIMEOUT.to_string(),
+                    params: vec![vec![params[0].clone()]],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+        }
+        DCC_ACCEPT => {
+            if params.is_empty() {
+                tx_backend
+                    .send(Message {
+                        tags: None,
+                        prefix: None,
+                        command: RECEIVED_MESSAGE.to_string(),
+                        params: vec![vec![
+                            "You must specify the nickname of the user that offered the file"
+                                .to_string(),
+                            ERROR.to_string(),
+                        ]],
+                    })
+                    .map_err(|_| -> ClientError {
+                        ClientError {
+                            kind: NONCRITICAL.to_string(),
+                            message: SEND_MESSAGE.to_string(),
+                        }
+                    })
+                    .ok();
+                return;
+            }
+            // The client connects to the user that offered the file, nothing is sent to the server
             tx_backend
                 .send(Message {
-                    prefix: None,
-                    command: QUIT.to_string(),
+                    tags: None,
+                    prefix: Some(params[0].clone()),
+                    command: DCC_ACCEPT_OFFER.to_string(),
                     params: vec![],
                 })
                 .map_err(|_| -> ClientError {
@@ -902,12 +2070,14 @@ pub fn send_general_purpose_commands(
                 })
                 .ok();
         }
-        SQUIT => {
-            tx_frontend
+        RAW | ANNOUNCE_TOPICS | TYPING => {
+            // These settings are handled by the client only, nothing is sent to the server
+            tx_backend
                 .send(Message {
+                    tags: None,
                     prefix: None,
-                    command: SQUIT.to_string(),
-                    params: vec![params.clone()],
+                    command: command.to_string(),
+                    params: vec![],
                 })
                 .map_err(|_| -> ClientError {
                     ClientError {
@@ -921,6 +2091,265 @@ pub fn send_general_purpose_commands(
     }
 }
 
+///
+/// Returns true if the command must be confirmed by the user before being sent.
+/// Only the destructive commands can ask for confirmation, and only if the user
+/// didn't turn it off.
+///
+pub fn requires_confirmation(command: &str, confirm_commands: &HashSet<String>) -> bool {
+    CONFIRMABLE_COMMANDS.contains(&command) && confirm_commands.contains(command)
+}
+
+///
+/// Returns the most parameters each command typed in the message entry takes.
+/// Commands that are not here are not valid
+///
+fn max_amount_params() -> HashMap<&'static str, usize> {
+    HashMap::from_iter(vec![
+        (AWAY, 1),
+        (UNAWAY, 0),
+        (WHOIS, 1),
+        (WHOWAS, 1),
+        (LUSERS, 0),
+        (ISON, 1),
+        (MOTD, 0),
+        (OPER, 2),
+        (QUIT, 1),
+        (SQUIT, 2),
+        (REHASH, 0),
+        (GLOBOPS, 1),
+        (NAMES, 0),
+        (TOPIC, 1),
+        (PART, 0),
+        (REGISTER, 0),
+        (INVITE, 1),
+        (KNOCK, 1),
+        (KICK, 1),
+        (MODE, 2),
+        (DCC_CHAT, 1),
+        (DCC_CLOSE, 0),
+        (MUTE, 1),
+        (UNMUTE, 1),
+        (IGNORE, 1),
+        (UNIGNORE, 1),
+        (BANS, 1),
+        (RAW, 0),
+        (CONFIRM, 1),
+        (ANNOUNCE_TOPICS, 0),
+        (TYPING, 0),
+        (EVENTS, 1),
+        (MAX_FILE_SIZE, 1),
+        (DCC_TIMEOUT, 1),
+        (DCC_ACCEPT, 1),
+        (SAVE, 1),
+        (ACTION, 1),
+        (REJOIN, 0),
+        (NOTICE, 2),
+        (MSG, 2),
+        (HELP, 1),
+    ])
+}
+
+///
+/// Returns the commands with their syntax, or only the syntax of the command given with the
+/// parameters it takes. The commands are the ones in max_amount_params, the ones without a
+/// line in COMMANDS_HELP are shown by their name
+///
+pub fn help_text(command: Option<&str>) -> Result<String, String> {
+    let max_amount_params = max_amount_params();
+    let command = match command {
+        Some(command) => command.trim_start_matches('/').to_uppercase(),
+        None => {
+            let mut lines: Vec<String> = COMMANDS_HELP
+                .iter()
+                .filter(|(command, _)| max_amount_params.contains_key(command))
+                .map(|(_, syntax)| format!("♦️ {}", syntax))
+                .collect();
+            let mut without_help: Vec<&str> = max_amount_params
+                .keys()
+                .filter(|command| !COMMANDS_HELP.iter().any(|(name, _)| name == *command))
+                .copied()
+                .collect();
+            without_help.sort_unstable();
+            for command in without_help {
+                lines.push(format!("♦️ /{}", command.to_lowercase()));
+            }
+            return Ok(lines.join("\n"));
+        }
+    };
+
+    let max_amount = match max_amount_params.get(command.as_str()) {
+        Some(max_amount) => max_amount,
+        None => return Err(format!("'{}' is not a valid command", command)),
+    };
+    let mut lines: Vec<String> = COMMANDS_HELP
+        .iter()
+        .filter(|(name, _)| *name == command)
+        .map(|(_, syntax)| syntax.to_string())
+        .collect();
+    if lines.is_empty() {
+        lines.push(format!("/{}", command.to_lowercase()));
+    }
+    lines.push(match max_amount {
+        0 => "It doesn't take any parameters".to_string(),
+        1 => "It takes one parameter at max".to_string(),
+        _ => format!("It takes {} parameters at max", max_amount),
+    });
+    Ok(lines.join("\n"))
+}
+
+///
+/// Shows the help in the current chat, the server is not asked for anything
+///
+fn show_help(command: Option<&String>, tx_backend: &gtk::glib::Sender<Message>) {
+    let (text_to_print, message_type) = match help_text(command.map(String::as_str)) {
+        Ok(text) => (text, INFO),
+        Err(text) => (text, ERROR),
+    };
+    tx_backend
+        .send(Message {
+            tags: None,
+            prefix: None,
+            command: RECEIVED_MESSAGE.to_string(),
+            params: vec![vec![text_to_print, message_type.to_string()]],
+        })
+        .map_err(|_| -> ClientError {
+            ClientError {
+                kind: NONCRITICAL.to_string(),
+                message: SEND_MESSAGE.to_string(),
+            }
+        })
+        .ok();
+}
+
+///
+/// Writes the text of the chat shown to the file given, or to one named after the chat and
+/// the time if none is given. The user is told where it was saved or why it couldn't be
+///
+fn save_chat(
+    chat_text: &gtk::TextView,
+    chat_name: &str,
+    file_name: Option<&String>,
+    tx_backend: &gtk::glib::Sender<Message>,
+) {
+    let text = chat_text
+        .buffer()
+        .and_then(|buffer| buffer.text(&buffer.start_iter(), &buffer.end_iter(), false))
+        .map(|text| text.to_string())
+        .unwrap_or_default();
+    let file_name = match file_name {
+        Some(file_name) => file_name.clone(),
+        None => {
+            let time = gtk::glib::DateTime::now_local()
+                .and_then(|now| now.format("%Y-%m-%d_%H-%M-%S"))
+                .map(|time| time.to_string())
+                .unwrap_or_default();
+            saved_chat_file_name(chat_name, &time)
+        }
+    };
+
+    let (text_to_print, message_type) = match fs::write(&file_name, text) {
+        Ok(_) => (format!("The chat was saved in {}", file_name), INFO),
+        Err(error) => (
+            format!("Couldn't save the chat in {}: {}", file_name, error),
+            ERROR,
+        ),
+    };
+    tx_backend
+        .send(Message {
+            tags: None,
+            prefix: None,
+            command: RECEIVED_MESSAGE.to_string(),
+            params: vec![vec![text_to_print, message_type.to_string()]],
+        })
+        .map_err(|_| -> ClientError {
+            ClientError {
+                kind: NONCRITICAL.to_string(),
+                message: SEND_MESSAGE.to_string(),
+            }
+        })
+        .ok();
+}
+
+///
+/// Returns the name of the file a chat is saved in when the user doesn't give one, like
+/// "rust_2023-05-20_18-30-00.txt" for the channel #rust. The characters that can't be in
+/// the name of a file in every system are replaced
+///
+pub fn saved_chat_file_name(chat_name: &str, time: &str) -> String {
+    let chat_name: String = chat_name
+        .trim_start_matches(['#', '&'])
+        .chars()
+        .map(
+            |character| match character.is_alphanumeric() || character == '-' {
+                true => character,
+                false => '_',
+            },
+        )
+        .collect();
+    match time.is_empty() {
+        true => format!("{}.txt", chat_name),
+        false => format!("{}_{}.txt", chat_name, time),
+    }
+}
+
+///
+/// Returns the question shown to the user before sending a destructive command,
+/// naming the action and its target
+///
+pub fn confirmation_text(command: &str, params: &[String], current_chat_name: &str) -> String {
+    match command {
+        QUIT => "Are you sure you want to quit the IRC server?".to_string(),
+        SQUIT => match params.first() {
+            Some(server) => format!(
+                "Are you sure you want to disconnect the server '{}' from the network?",
+                server.split(' ').next().unwrap_or(server)
+            ),
+            None => "Are you sure you want to disconnect the server from the network?".to_string(),
+        },
+        KICK => match params.first() {
+            Some(nickname) => format!(
+                "Are you sure you want to kick {} from the channel {}?",
+                nickname.split(' ').next().unwrap_or(nickname),
+                current_chat_name
+            ),
+            None => format!(
+                "Are you sure you want to kick a user from the channel {}?",
+                current_chat_name
+            ),
+        },
+        _ => format!("Are you sure you want to use '{}'?", command),
+    }
+}
+
+///
+/// Shows a modal dialog asking the user to confirm the action.
+/// Returns true if the user accepted.
+///
+fn confirm_command(window: &gtk::ApplicationWindow, text_show: &str) -> bool {
+    let dialog = gtk::MessageDialog::builder()
+        .transient_for(window)
+        .modal(true)
+        .buttons(gtk::ButtonsType::YesNo)
+        .text(text_show)
+        .build();
+    let response = dialog.run();
+    dialog.close();
+    response == gtk::ResponseType::Yes
+}
+
+///
+/// Returns true if a message received in the chat should notify the user.
+/// Messages from the chat currently open or from a muted channel don't notify.
+///
+pub fn should_notify(
+    chat_name: &str,
+    current_chat_name: &str,
+    muted_channels: &HashSet<String>,
+) -> bool {
+    chat_name != current_chat_name && !muted_channels.contains(chat_name)
+}
+
 ///
 /// For commands that can only be used in channels
 /// Builds the message to send depending on the command and sends it to the client
@@ -932,11 +2361,13 @@ pub fn send_channel_only_commands(
     tx_backend: &gtk::glib::Sender<Message>,
     current_chat_name: String,
     nickname: &str,
+    channel_memory: &Arc<Mutex<ChannelMemory>>,
 ) {
     match command {
         NAMES => {
             tx_frontend
                 .send(Message {
+                    tags: None,
                     prefix: None,
                     command: NAMES.to_string(),
                     params: vec![vec![current_chat_name]],
@@ -953,6 +2384,7 @@ pub fn send_channel_only_commands(
             true => {
                 tx_frontend
                     .send(Message {
+                        tags: None,
                         prefix: None,
                         command: TOPIC.to_string(),
                         params: vec![vec![current_chat_name]],
@@ -968,6 +2400,7 @@ pub fn send_channel_only_commands(
             false => {
                 tx_frontend
                     .send(Message {
+                        tags: None,
                         prefix: None,
                         command: TOPIC.to_string(),
                         params: vec![vec![current_chat_name], params],
@@ -981,10 +2414,33 @@ pub fn send_channel_only_commands(
                     .ok();
             }
         },
-        PART => part_channel(tx_frontend, current_chat_name, tx_backend),
+        REGISTER => {
+            tx_frontend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: REGISTER.to_string(),
+                    params: vec![vec![current_chat_name]],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+        }
+        PART => {
+            channel_memory
+                .lock()
+                .expect("Couldn't lock channel memory")
+                .record_part(&current_chat_name);
+            part_channel(tx_frontend, current_chat_name, tx_backend)
+        }
         INVITE => {
             tx_frontend
                 .send(Message {
+                    tags: None,
                     prefix: None,
                     command: INVITE.to_string(),
                     params: vec![params, vec![current_chat_name]],
@@ -998,12 +2454,18 @@ pub fn send_channel_only_commands(
                 .ok();
         }
         KICK => {
-            if params[0] == nickname {
+            // The nickname can be followed by the comment of the kick
+            let (nickname_kicked, comment) = match params[0].split_once(' ') {
+                Some((nickname_kicked, comment)) => (nickname_kicked, Some(comment)),
+                None => (params[0].as_str(), None),
+            };
+            if nickname_kicked == nickname {
                 let text_to_print =
                     "You can't kick yourself from a channel, consider using '/part' instead"
                         .to_string();
                 tx_backend
                     .send(Message {
+                        tags: None,
                         prefix: None,
                         command: RECEIVED_MESSAGE.to_string(),
                         params: vec![vec![text_to_print, ERROR.to_string()]],
@@ -1019,9 +2481,17 @@ pub fn send_channel_only_commands(
             }
             tx_frontend
                 .send(Message {
+                    tags: None,
                     prefix: None,
                     command: KICK.to_string(),
-                    params: vec![vec![current_chat_name], params],
+                    params: match comment {
+                        Some(comment) => vec![
+                            vec![current_chat_name],
+                            vec![nickname_kicked.to_string()],
+                            vec![comment.to_string()],
+                        ],
+                        None => vec![vec![current_chat_name], vec![nickname_kicked.to_string()]],
+                    },
                 })
                 .map_err(|_| -> ClientError {
                     ClientError {
@@ -1032,15 +2502,21 @@ pub fn send_channel_only_commands(
                 .ok();
         }
         MODE => {
-            if params.is_empty() {
-                tx_backend
+            // Without a mode the modes of the channel are asked, of the current one or the one given
+            let channel_queried = match params.as_slice() {
+                [] => Some(current_chat_name.clone()),
+                [channel] if channel.starts_with('#') || channel.starts_with('&') => {
+                    Some(channel.clone())
+                }
+                _ => None,
+            };
+            if let Some(channel) = channel_queried {
+                tx_frontend
                     .send(Message {
+                        tags: None,
                         prefix: None,
-                        command: RECEIVED_MESSAGE.to_string(),
-                        params: vec![vec![
-                            "You must specify a mode".to_string(),
-                            ERROR.to_string(),
-                        ]],
+                        command: MODE.to_string(),
+                        params: vec![vec![channel]],
                     })
                     .map_err(|_| -> ClientError {
                         ClientError {
@@ -1055,6 +2531,7 @@ pub fn send_channel_only_commands(
                     true => {
                         tx_frontend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: MODE.to_string(),
                                 params: vec![
@@ -1074,6 +2551,7 @@ pub fn send_channel_only_commands(
                     false => {
                         tx_frontend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: MODE.to_string(),
                                 params: vec![vec![current_chat_name], vec![params[0].clone()]],
@@ -1097,19 +2575,17 @@ pub fn send_channel_only_commands(
 /// Setup button "Search" in the user submenu
 /// When clicked, it sends a WHO command to the client
 ///
-pub fn setup_search_user_button(client: &Client, tx_frontend: Sender<Message>) {
-    let search_button: gtk::Button = client
-        .builder
-        .object("search_user_button")
-        .expect("Couldn't get send_button");
-    let search_entry: gtk::Entry = client
-        .builder
-        .object("search_user_entry")
-        .expect("Couldn't get search_entry");
+pub fn setup_search_user_button(
+    client: &Client,
+    tx_frontend: Sender<Message>,
+) -> Result<(), ClientError> {
+    let search_button: gtk::Button = get_object(&client.builder, "search_user_button")?;
+    let search_entry: gtk::Entry = get_object(&client.builder, "search_user_entry")?;
 
     search_button.connect_clicked(move |_| {
         tx_frontend
             .send(Message {
+                tags: None,
                 prefix: None,
                 command: WHO.to_string(),
                 params: vec![vec![search_entry.text().to_string()]],
@@ -1122,6 +2598,7 @@ pub fn setup_search_user_button(client: &Client, tx_frontend: Sender<Message>) {
             })
             .ok();
     });
+    Ok(())
 }
 
 ///
@@ -1139,6 +2616,10 @@ pub fn new_user_chat_button(client: &Client, name: &str, buffer: TextBuffer) ->
         .builder
         .object("message_entry")
         .expect("Couldn't get message_entry");
+    let jump_to_latest_button: gtk::Button = client
+        .builder
+        .object("jump_to_latest_button")
+        .expect("Couldn't get jump_to_latest_button");
 
     let user_button_clicked = move |button: &gtk::Button| {
         // Obtain user nickname looking at buttons label
@@ -1149,29 +2630,36 @@ pub fn new_user_chat_button(client: &Client, name: &str, buffer: TextBuffer) ->
         // Set placeholer text of entry
         message_entry
             .set_placeholder_text(Some(&format!("Type your message to {} here", chat_name)));
+        // The chat is being read, so it's no longer unread
+        button.style_context().remove_class(UNREAD_CHAT_CLASS);
         //Set the text_view buffer to that
         text_view.set_buffer(Some(&buffer));
+        // The new chat starts showing its latest messages
+        jump_to_latest_button.hide();
+        scroll_chat_to_bottom(&text_view);
     };
     button.connect_clicked(user_button_clicked);
     button
 }
 
 ///
-/// This function creates a new button with the nickname of the user
+/// This function creates a new button with the label of the user, its nickname when it has no flags
 /// Returns the new button
 /// When the button is clicked, it sends a message to create a new chat with the corresponding user
 ///
 pub fn new_user_search_button(
     name: String,
+    label: &str,
     tx_backend: &gtk::glib::Sender<Message>,
 ) -> gtk::Button {
-    let button = gtk::Button::with_label(&name);
+    let button = gtk::Button::with_label(label);
 
     let tx_backend_clone = tx_backend.clone();
     let user_button_clicked = move |_: &gtk::Button| {
         let name_string = name.clone();
         tx_backend_clone
             .send(Message {
+                tags: None,
                 prefix: Some(name_string),
                 command: ADD_LIST_CHATS.to_string(),
                 params: vec![vec![]],
@@ -1188,6 +2676,35 @@ pub fn new_user_search_button(
     button
 }
 
+///
+/// This function creates a new button with the name of the channel
+/// Returns the new button
+/// When the button is clicked, it sends a JOIN for the channel
+///
+pub fn new_channel_list_button(name: String, tx_frontend: &Sender<Message>) -> gtk::Button {
+    let button = gtk::Button::with_label(&name);
+
+    let tx_frontend_clone = tx_frontend.clone();
+    let channel_button_clicked = move |_: &gtk::Button| {
+        tx_frontend_clone
+            .send(Message {
+                tags: None,
+                prefix: None,
+                command: JOIN.to_string(),
+                params: vec![vec![name.clone()]],
+            })
+            .map_err(|_| -> ClientError {
+                ClientError {
+                    kind: NONCRITICAL.to_string(),
+                    message: SEND_MESSAGE.to_string(),
+                }
+            })
+            .ok();
+    };
+    button.connect_clicked(channel_button_clicked);
+    button
+}
+
 ///
 /// Prints a message in the chat window and sends the PART or PART_CHANNEL command to the client
 ///
@@ -1199,6 +2716,7 @@ pub fn part_channel(
     let text_to_print = "left the channel.".to_string();
     tx_frontend
         .send(Message {
+            tags: None,
             prefix: None,
             command: PRIVMSG.to_string(),
             params: vec![vec![channel.clone()], vec![text_to_print]],
@@ -1212,6 +2730,7 @@ pub fn part_channel(
         .ok();
     tx_frontend
         .send(Message {
+            tags: None,
             prefix: None,
             command: PART.to_string(),
             params: vec![vec![channel.clone()]],
@@ -1227,6 +2746,7 @@ pub fn part_channel(
     let text_to_print = format!("You left the channel {}", channel);
     tx_backend
         .send(Message {
+            tags: None,
             prefix: Some("You".to_string()),
             command: RECEIVED_MESSAGE.to_string(),
             params: vec![vec![text_to_print, INFO.to_string()]],
@@ -1240,6 +2760,7 @@ pub fn part_channel(
         .ok();
     tx_backend
         .send(Message {
+            tags: None,
             prefix: None,
             command: PART_CHANNEL.to_string(),
             params: vec![vec![channel]],
@@ -1261,21 +2782,16 @@ pub fn setup_send_file_button(
     client: &Client,
     tx_frontend: Sender<Message>,
     tx_backend: gtk::glib::Sender<Message>,
-) {
-    let file_chooser: gtk::FileChooserButton = client
-        .builder
-        .object("file_chooser")
-        .expect("Couldn't get file_chooser");
-    let send_file_button: gtk::Button = client
-        .builder
-        .object("send_file_button")
-        .expect("Couldn't get send_file_button");
-    let text_view: gtk::TextView = client
-        .builder
-        .object("chat_text")
-        .expect("Couldn't get chat_text");
+    nickname: &str,
+) -> Result<(), ClientError> {
+    let nickname = nickname.to_owned();
+    let file_chooser: gtk::FileChooserButton = get_object(&client.builder, "file_chooser")?;
+    let send_file_button: gtk::Button = get_object(&client.builder, "send_file_button")?;
+    let send_to_members: gtk::CheckButton = get_object(&client.builder, "check_send_to_members")?;
+    let text_view: gtk::TextView = get_object(&client.builder, "chat_text")?;
     let users_clone = client.online_chats_buffers.clone();
     let file_paths = client.dcc_file_paths.clone();
+    let channel_members = client.channel_members.clone();
     send_file_button.connect_clicked(move |_| {
         println!("Send file button clicked");
         let file_path = match file_chooser.filename() {
@@ -1283,11 +2799,85 @@ pub fn setup_send_file_button(
             None => {
                 tx_backend
                     .send(Message {
+                        tags: None,
                         prefix: None,
                         command: RECEIVED_MESSAGE.to_string(),
                         params: vec![vec!["Please select a file".to_string(), INFO.to_string()]],
                     })
-                    .unwrap();
+                    .map_err(|_| -> ClientError {
+                        ClientError {
+                            kind: NONCRITICAL.to_string(),
+                            message: SEND_MESSAGE.to_string(),
+                        }
+                    })
+                    .ok();
+                return;
+            }
+        };
+        // The file may have been removed or moved after it was chosen
+        let file_size = match file_path.metadata() {
+            Ok(metadata) => metadata.len(),
+            Err(_) => {
+                tx_backend
+                    .send(Message {
+                        tags: None,
+                        prefix: None,
+                        command: RECEIVED_MESSAGE.to_string(),
+                        params: vec![vec![
+                            "Couldn't read the file".to_string(),
+                            ERROR.to_string(),
+                        ]],
+                    })
+                    .map_err(|_| -> ClientError {
+                        ClientError {
+                            kind: NONCRITICAL.to_string(),
+                            message: SEND_MESSAGE.to_string(),
+                        }
+                    })
+                    .ok();
+                return;
+            }
+        };
+        if file_size == 0 {
+            tx_backend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: RECEIVED_MESSAGE.to_string(),
+                    params: vec![vec![
+                        "The file is empty, there is nothing to send".to_string(),
+                        INFO.to_string(),
+                    ]],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+            return;
+        }
+        let checksum = match dcc_checksum::file_checksum(&file_path) {
+            Ok(checksum) => checksum,
+            Err(_) => {
+                tx_backend
+                    .send(Message {
+                        tags: None,
+                        prefix: None,
+                        command: RECEIVED_MESSAGE.to_string(),
+                        params: vec![vec![
+                            "Couldn't read the file".to_string(),
+                            ERROR.to_string(),
+                        ]],
+                    })
+                    .map_err(|_| -> ClientError {
+                        ClientError {
+                            kind: NONCRITICAL.to_string(),
+                            message: SEND_MESSAGE.to_string(),
+                        }
+                    })
+                    .ok();
                 return;
             }
         };
@@ -1306,34 +2896,55 @@ pub fn setup_send_file_button(
             .insert(name.to_string(), file_path.clone());
 
         println!("Current name chat: {}", current_name_chat);
-        let listener = TcpListener::bind("0.0.0.0:0").unwrap();
-        // Spawn new thread for the client
-        let address = listener.local_addr().unwrap();
-        println!("Listening on {}", address);
-        let tx_backend_clone = tx_backend.clone();
-        let current_name_chat_clone = current_name_chat.clone();
-        let file_size = file_path.metadata().unwrap().len();
-        let _ = thread::spawn(move || {
-            // connection succeeded
-            match wait_connection_dcc_file(
-                listener,
-                tx_backend_clone,
-                current_name_chat_clone,
-                file_path,
-                0,
-            ) {
-                Ok(_) => println!("Private connection ended"),
-                Err(err) => println!("Error: {}", err),
+        let is_channel = current_name_chat.starts_with('#') || current_name_chat.starts_with('&');
+        if is_channel && send_to_members.is_active() {
+            let members = channel_members
+                .lock()
+                .expect("Couldn't lock channel members")
+                .get(&current_name_chat);
+            send_file_to_members(
+                &tx_frontend,
+                &tx_backend,
+                &current_name_chat,
+                dcc_recipients::channel_recipients(&members, &nickname),
+                &file_path,
+                name,
+                file_size,
+                &checksum,
+            );
+            return;
+        }
+
+        let message = match offer_file(
+            &tx_backend,
+            current_name_chat.clone(),
+            file_path,
+            name,
+            file_size,
+            &checksum,
+        ) {
+            Ok(message) => message,
+            Err(_) => {
+                tx_backend
+                    .send(Message {
+                        tags: None,
+                        prefix: None,
+                        command: RECEIVED_MESSAGE.to_string(),
+                        params: vec![vec![
+                            "Couldn't open a port to send the file".to_string(),
+                            ERROR.to_string(),
+                        ]],
+                    })
+                    .map_err(|_| -> ClientError {
+                        ClientError {
+                            kind: NONCRITICAL.to_string(),
+                            message: SEND_MESSAGE.to_string(),
+                        }
+                    })
+                    .ok();
+                return;
             }
-        });
-
-        let message = format!(
-            "DCC_SEND {} {} {} {}",
-            name,
-            address.ip(),
-            address.port(),
-            file_size
-        );
+        };
         println!("Sending message: {}", message);
         send_privmsg(
             &tx_frontend, //start chat and thread to send file
@@ -1342,8 +2953,114 @@ pub fn setup_send_file_button(
             text_view.clone(),
             current_name_chat,
             false,
+            &nickname,
         );
     });
+    Ok(())
+}
+
+///
+/// Listens on a new port for the user the file is offered to, waiting in another thread
+/// for them to accept it. Returns the DCC_SEND that has to be sent to the user
+///
+fn offer_file(
+    tx_backend: &gtk::glib::Sender<Message>,
+    user_to_send: String,
+    file_path: PathBuf,
+    file_name: &str,
+    file_size: u64,
+    checksum: &str,
+) -> Result<String, io::Error> {
+    let listener = TcpListener::bind("0.0.0.0:0")?;
+    let address = listener.local_addr()?;
+    println!("Listening on {}", address);
+    let tx_backend_clone = tx_backend.clone();
+    let _ = thread::spawn(move || {
+        // connection succeeded
+        match wait_connection_dcc_file(listener, tx_backend_clone, user_to_send, file_path, 0) {
+            Ok(_) => println!("Private connection ended"),
+            Err(err) => println!("Error: {}", err),
+        }
+    });
+
+    // The checksum lets the receiver check the file arrived complete
+    Ok(format!(
+        "DCC_SEND {} {} {} {} {}",
+        file_name,
+        address.ip(),
+        address.port(),
+        file_size,
+        checksum
+    ))
+}
+
+///
+/// Offers the file to every member of the channel, each one in a private message with its own
+/// port. The chat of the channel shows whether the file could be offered to each member
+///
+#[allow(clippy::too_many_arguments)]
+fn send_file_to_members(
+    tx_frontend: &Sender<Message>,
+    tx_backend: &gtk::glib::Sender<Message>,
+    channel: &str,
+    recipients: Vec<String>,
+    file_path: &Path,
+    file_name: &str,
+    file_size: u64,
+    checksum: &str,
+) {
+    let show_status = |text: String| {
+        tx_backend
+            .send(Message {
+                tags: None,
+                prefix: Some(channel.to_string()),
+                command: RECEIVED_MESSAGE.to_string(),
+                params: vec![vec![text, INFO.to_string()]],
+            })
+            .map_err(|_| -> ClientError {
+                ClientError {
+                    kind: NONCRITICAL.to_string(),
+                    message: SEND_MESSAGE.to_string(),
+                }
+            })
+            .ok();
+    };
+    if recipients.is_empty() {
+        show_status(dcc_recipients::no_recipients_text(channel));
+        return;
+    }
+
+    for recipient in recipients {
+        let offer = offer_file(
+            tx_backend,
+            recipient.clone(),
+            file_path.to_path_buf(),
+            file_name,
+            file_size,
+            checksum,
+        );
+        let status = match offer {
+            Ok(message) => {
+                tx_frontend
+                    .send(Message {
+                        tags: None,
+                        prefix: None,
+                        command: PRIVMSG.to_string(),
+                        params: vec![vec![recipient.clone()], vec![message]],
+                    })
+                    .map_err(|_| -> ClientError {
+                        ClientError {
+                            kind: NONCRITICAL.to_string(),
+                            message: SEND_MESSAGE.to_string(),
+                        }
+                    })
+                    .ok();
+                dcc_recipients::offered_text(&recipient, file_name)
+            }
+            Err(error) => dcc_recipients::not_offered_text(&recipient, file_name, &error),
+        };
+        show_status(status);
+    }
 }
 
 ///
@@ -1389,24 +3106,21 @@ pub fn wait_connection_dcc_file(
         None => {
             tx_backend
                 .send(Message {
+                    tags: None,
                     prefix: None,
                     command: RECEIVED_MESSAGE.to_string(),
                     params: vec![vec![
-                        "The other user rejected the transfer".to_string(),
+                        dcc_recipients::not_accepted_text(&user_to_send),
                         INFO.to_string(),
                     ]],
                 })
                 .unwrap();
             Err("Connection rejected".to_string())
         }
-        Some(_) => {
-            handle_dcc_file_send(
-                stream.unwrap(),
-                tx_backend,
-                &user_to_send,
-                file_path,
-                start_position,
-            );
+        Some(stream) => {
+            dcc_connection::set_file_transfer_timeouts(&stream, DCC_FILE_TRANSFER_TIMEOUT)
+                .map_err(|error| error.to_string())?;
+            handle_dcc_file_send(stream, tx_backend, &user_to_send, file_path, start_position);
             Ok(())
         }
     }
@@ -1414,22 +3128,21 @@ pub fn wait_connection_dcc_file(
 
 ///
 /// Setup the button to resume a file transfer
-/// If the button is clicked, send a DCC_RESUME message to the user
+/// If the button is clicked, send a DCC_RESUME message to the user with the position
+/// where the transfer has to continue
 ///
-pub fn setup_resume_transfer_button(client: &Client, tx_frontend: Sender<Message>) {
-    let file_chooser: gtk::FileChooserButton = client
-        .builder
-        .object("file_chooser")
-        .expect("Couldn't get file_chooser");
-    let resume_file_button: gtk::Button = client
-        .builder
-        .object("resume_file_button")
-        .expect("Couldn't get resume_file_button");
-    let text_view: gtk::TextView = client
-        .builder
-        .object("chat_text")
-        .expect("Couldn't get chat_text");
+pub fn setup_resume_transfer_button(
+    client: &Client,
+    tx_frontend: Sender<Message>,
+    nickname: &str,
+) -> Result<(), ClientError> {
+    let nickname = nickname.to_owned();
+    let file_chooser: gtk::FileChooserButton = get_object(&client.builder, "file_chooser")?;
+    let resume_file_button: gtk::Button = get_object(&client.builder, "resume_file_button")?;
+    let text_view: gtk::TextView = get_object(&client.builder, "chat_text")?;
     let users_clone = client.online_chats_buffers.clone();
+    let download_directory = client.download_directory.clone();
+    let requested_resumes = client.requested_resumes.clone();
     resume_file_button.connect_clicked(move |_| {
         println!("Resume file button clicked");
         let file_path = match file_chooser.filename() {
@@ -1440,24 +3153,33 @@ pub fn setup_resume_transfer_button(client: &Client, tx_frontend: Sender<Message
         let file_path_clone = file_path.clone();
         let file_path_str = file_path_clone.to_str().expect("Couldn't get file name");
 
-        let mut file_path_splitted = file_path_str.split('/');
-
-        let file_name = file_path_splitted
-            .clone()
+        let file_name = file_path_str
+            .split('/')
             .last()
             .expect("Couldn't get file name");
 
-        let directory = file_path_splitted.nth_back(1).unwrap();
-        if directory == "received_files" {
-            println!("File already in received_files");
+        let directory = download_directory
+            .lock()
+            .expect("Couldn't lock download directory")
+            .clone();
+        if dcc_download::is_in_directory(&file_path, &directory) {
+            println!("File already in the download directory");
             let users = users_clone.lock().expect(LOCK_USERS).clone();
             let current_name_chat = find_user_by_current_buffer(users, &text_view);
 
-            let file = File::open(file_path).unwrap();
-            let file_size = file.metadata().unwrap().len();
+            // The progress saved is used, so it also works after the client was closed
+            let start_position = match dcc_progress::resume_position(&file_path) {
+                Ok(position) => position,
+                Err(_) => return,
+            };
 
-            let message = format!("DCC_RESUME {} 0.0.0.0 0 {}", file_name, file_size);
+            let message = format!("DCC_RESUME {} 0.0.0.0 0 {}", file_name, start_position);
             println!("Sending message: {}", message);
+            requested_resumes.lock().expect(LOCK_DCC).requested(
+                &current_name_chat,
+                file_name,
+                start_position,
+            );
 
             send_privmsg(
                 &tx_frontend, //start chat and thread to send file
@@ -1466,24 +3188,47 @@ pub fn setup_resume_transfer_button(client: &Client, tx_frontend: Sender<Message
                 text_view.clone(),
                 current_name_chat,
                 false,
+                &nickname,
             );
         }
     });
+    Ok(())
+}
+
+///
+/// Sets up the chooser of the folder where the files received are saved. It starts in the
+/// default folder, and the folders chosen are used in the transfers started after it changes
+///
+fn setup_download_directory_chooser(client: &Client) -> Result<(), ClientError> {
+    let directory_chooser: gtk::FileChooserButton =
+        get_object(&client.builder, "download_directory_chooser")?;
+    let download_directory = client.download_directory.clone();
+
+    let directory = download_directory
+        .lock()
+        .expect("Couldn't lock download directory")
+        .clone();
+    if fs::create_dir_all(&directory).is_ok() {
+        directory_chooser.set_current_folder(&directory);
+    }
+
+    directory_chooser.connect_file_set(move |directory_chooser| {
+        if let Some(directory) = directory_chooser.filename() {
+            *download_directory
+                .lock()
+                .expect("Couldn't lock download directory") = directory;
+        }
+    });
+    Ok(())
 }
 
 ///
 /// Setup the button to pause a file transfer
 /// If the button is clicked, send a DCC_PAUSE message to the user
 ///
-pub fn setup_pause_transfer_button(client: &Client) {
-    let pause_file_button: gtk::Button = client
-        .builder
-        .object("pause_file_button")
-        .expect("Couldn't get pause_file_button");
-    let text_view: gtk::TextView = client
-        .builder
-        .object("chat_text")
-        .expect("Couldn't get chat_text");
+pub fn setup_pause_transfer_button(client: &Client) -> Result<(), ClientError> {
+    let pause_file_button: gtk::Button = get_object(&client.builder, "pause_file_button")?;
+    let text_view: gtk::TextView = get_object(&client.builder, "chat_text")?;
     let users_clone = client.online_chats_buffers.clone();
     let dcc_chats_clone = client.dcc_chats.clone();
     pause_file_button.connect_clicked(move |_| {
@@ -1500,6 +3245,7 @@ pub fn setup_pause_transfer_button(client: &Client) {
             }
         };
         let message = Message {
+            tags: None,
             prefix: None,
             command: PAUSE.to_string(),
             params: vec![vec![]],
@@ -1507,4 +3253,508 @@ pub fn setup_pause_transfer_button(client: &Client) {
         sender.send(message).unwrap();
         println!("Pause sent");
     });
+    Ok(())
+}
+
+///
+/// Sets up the button shown when new messages arrive while the user is reading older ones.
+/// Clicking it scrolls the chat to the latest message, and it hides by itself once the
+/// user scrolls back to the bottom
+///
+fn setup_jump_to_latest_button(client: &Client) -> Result<(), ClientError> {
+    let jump_to_latest_button: gtk::Button = get_object(&client.builder, "jump_to_latest_button")?;
+    let scrolled_window: gtk::ScrolledWindow = get_object(&client.builder, "chat_scrolled_window")?;
+    let text_view: gtk::TextView = get_object(&client.builder, "chat_text")?;
+
+    let jump_to_latest_button_clone = jump_to_latest_button.clone();
+    scrolled_window
+        .vadjustment()
+        .connect_value_changed(move |adjustment| {
+            if is_scrolled_to_bottom(
+                adjustment.value(),
+                adjustment.upper(),
+                adjustment.page_size(),
+            ) {
+                jump_to_latest_button_clone.hide();
+            }
+        });
+
+    jump_to_latest_button.connect_clicked(move |button| {
+        scroll_chat_to_bottom(&text_view);
+        button.hide();
+    });
+    Ok(())
+}
+
+///
+/// Sets up the checkbox that shows or hides the time of the messages. Only the messages
+/// received after it changes are affected
+///
+fn setup_timestamps_check(client: &Client) -> Result<(), ClientError> {
+    let timestamps_check: gtk::CheckButton = get_object(&client.builder, "check_timestamps")?;
+    let show_timestamps = client.show_timestamps.clone();
+
+    timestamps_check.connect_toggled(move |timestamps_check| {
+        *show_timestamps
+            .lock()
+            .expect("Couldn't lock show timestamps") = timestamps_check.is_active();
+    });
+    Ok(())
+}
+
+///
+/// Sets up the checkbox that shows the colors and formatting of the messages or removes them.
+/// Only the messages received after it changes are affected
+///
+fn setup_formatting_check(client: &Client) -> Result<(), ClientError> {
+    let formatting_check: gtk::CheckButton = get_object(&client.builder, "check_formatting")?;
+    let show_formatting = client.show_formatting.clone();
+
+    formatting_check.connect_toggled(move |formatting_check| {
+        *show_formatting
+            .lock()
+            .expect("Couldn't lock show formatting") = formatting_check.is_active();
+    });
+    Ok(())
+}
+
+///
+/// Sets up the completion of nicknames in the message entry. Tab completes the word before the
+/// cursor with the users of the current channel, or the user of the private chat, and pressing
+/// it again goes to the next one. Nothing is sent until the message is sent
+///
+fn setup_nick_completion(client: &Client, nickname: &str) -> Result<(), ClientError> {
+    let message_entry: gtk::Entry = get_object(&client.builder, "message_entry")?;
+    let text_view: gtk::TextView = get_object(&client.builder, "chat_text")?;
+    let buffers = client.online_chats_buffers.clone();
+    let channel_members = client.channel_members.clone();
+    let nickname = nickname.to_owned();
+    let completion = RefCell::new(NickCompletion::default());
+
+    message_entry.connect_key_press_event(move |message_entry, event| {
+        if event.keyval() != gtk::gdk::keys::constants::Tab {
+            return gtk::Inhibit(false);
+        }
+
+        let buffers = buffers.lock().expect(LOCK_USERS).clone();
+        let current_chat_name = find_user_by_current_buffer(buffers, &text_view);
+        let nicknames =
+            match current_chat_name.starts_with('#') || current_chat_name.starts_with('&') {
+                true => channel_members
+                    .lock()
+                    .expect("Couldn't lock channel members")
+                    .get(&current_chat_name),
+                false => vec![current_chat_name],
+            };
+        let nicknames: Vec<String> = nicknames
+            .into_iter()
+            .filter(|member| !parser::irc_eq(member, &nickname) && member != "You")
+            .collect();
+
+        let text = message_entry.text().to_string();
+        let cursor = message_entry.position().max(0) as usize;
+        if let Some((text, cursor)) = completion.borrow_mut().complete(&text, cursor, &nicknames) {
+            message_entry.set_text(&text);
+            message_entry.set_position(cursor as i32);
+        }
+        // Tab doesn't move the focus out of the entry
+        gtk::Inhibit(true)
+    });
+    Ok(())
+}
+
+///
+/// Sets up the typing notifications. While the user writes in a private chat the other user is
+/// told with a NOTICE, and below the chat it is shown when the user of the chat shown is typing
+///
+fn setup_typing_notifications(
+    client: &Client,
+    tx_frontend: Sender<Message>,
+) -> Result<(), ClientError> {
+    let message_entry: gtk::Entry = get_object(&client.builder, "message_entry")?;
+    let typing_label: gtk::Label = get_object(&client.builder, "typing_label")?;
+    let text_view: gtk::TextView = get_object(&client.builder, "chat_text")?;
+    let buffers = client.online_chats_buffers.clone();
+    let typing_notifications = client.typing_notifications.clone();
+    let notifier = client.typing_notifier.clone();
+
+    let buffers_clone = buffers.clone();
+    let text_view_clone = text_view.clone();
+    message_entry.connect_changed(move |message_entry| {
+        if !*typing_notifications
+            .lock()
+            .expect("Couldn't lock typing notifications")
+        {
+            return;
+        }
+        let buffers = buffers_clone.lock().expect(LOCK_USERS).clone();
+        let current_chat_name = find_user_by_current_buffer(buffers, &text_view_clone);
+        let text = message_entry.text().to_string();
+        for (chat, state) in notifier
+            .lock()
+            .expect("Couldn't lock typing notifier")
+            .entry_changed(&current_chat_name, &text, Instant::now())
+        {
+            tx_frontend
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: NOTICE.to_string(),
+                    params: vec![vec![chat], vec![typing_notice(state)]],
+                })
+                .map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: NONCRITICAL.to_string(),
+                        message: SEND_MESSAGE.to_string(),
+                    }
+                })
+                .ok();
+        }
+    });
+
+    // The label changes with the chat shown and hides by itself when the notification expires
+    let typing_users = client.typing_users.clone();
+    gtk::glib::timeout_add_local(TYPING_LABEL_REFRESH_INTERVAL, move || {
+        let buffers = buffers.lock().expect(LOCK_USERS).clone();
+        let current_chat_name = find_user_by_current_buffer(buffers, &text_view);
+        let is_typing = typing_users
+            .lock()
+            .expect("Couldn't lock typing users")
+            .is_typing(&current_chat_name, Instant::now());
+        show_typing_label(&typing_label, &current_chat_name, is_typing);
+        gtk::glib::Continue(true)
+    });
+    Ok(())
+}
+
+///
+/// Shows below the chat that its user is typing, or hides it
+///
+pub fn show_typing_label(typing_label: &gtk::Label, chat: &str, is_typing: bool) {
+    match is_typing {
+        true => {
+            typing_label.set_text(&typing_text(chat));
+            typing_label.show();
+        }
+        false => typing_label.hide(),
+    }
+}
+
+///
+/// Scrolls the chat to its last message
+///
+pub fn scroll_chat_to_bottom(text_view: &gtk::TextView) {
+    let buffer = text_view.buffer().expect("Couldn't get buffer");
+    // The mark keeps the scroll target even if the view isn't drawn yet
+    let mark = match buffer.mark(CHAT_END_MARK) {
+        Some(mark) => mark,
+        None => buffer.create_mark(Some(CHAT_END_MARK), &buffer.end_iter(), false),
+    };
+    buffer.move_mark(&mark, &buffer.end_iter());
+    text_view.scroll_to_mark(&mark, 0.0, false, 0.0, 1.0);
+}
+
+///
+/// Returns true if the chat is showing its last line, so new messages should scroll it.
+/// A small margin is allowed for the spacing below the last line.
+///
+pub fn is_scrolled_to_bottom(value: f64, upper: f64, page_size: f64) -> bool {
+    value + page_size >= upper - SCROLL_BOTTOM_MARGIN
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        bans_channel, confirmation_text, ctcp_action, has_text_to_send, help_text,
+        is_scrolled_to_bottom, msg_receiver_and_text, parse_join_entries, requires_confirmation,
+        saved_chat_file_name, should_notify, widget_or_error, ChannelMemory, SCROLL_BOTTOM_MARGIN,
+    };
+    use crate::client_utils::client::DEFAULT_CONFIRM_COMMANDS;
+    use crate::commands::{AWAY, JOIN, KICK, QUIT, SQUIT};
+    use crate::custom_errors::errors::{MISSING_WIDGET, NONCRITICAL};
+    use std::collections::HashSet;
+
+    fn default_confirm_commands() -> HashSet<String> {
+        DEFAULT_CONFIRM_COMMANDS
+            .iter()
+            .map(|command| command.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn quit_and_squit_require_confirmation_by_default() {
+        let confirm_commands = default_confirm_commands();
+        assert!(requires_confirmation(QUIT, &confirm_commands));
+        assert!(requires_confirmation(SQUIT, &confirm_commands));
+        assert!(!requires_confirmation(KICK, &confirm_commands));
+        assert!(!requires_confirmation(AWAY, &confirm_commands));
+    }
+
+    #[test]
+    fn join_entries_pair_each_key_with_its_channel() {
+        assert_eq!(
+            parse_join_entries("#a #b", "clave"),
+            Ok(vec![
+                ("#a".to_string(), Some("clave".to_string())),
+                ("#b".to_string(), None),
+            ])
+        );
+        assert_eq!(
+            parse_join_entries(" &local  #rust ", ""),
+            Ok(vec![
+                ("&local".to_string(), None),
+                ("#rust".to_string(), None)
+            ])
+        );
+        assert_eq!(
+            parse_join_entries("", ""),
+            Err("Please choose a channel name".to_string())
+        );
+        assert_eq!(
+            parse_join_entries("#a b", ""),
+            Err("Must start with # or &".to_string())
+        );
+        assert_eq!(
+            parse_join_entries("#a,#b", ""),
+            Err("Separate channels with spaces".to_string())
+        );
+    }
+
+    #[test]
+    fn bans_are_asked_for_the_channel_given_or_the_current_one() {
+        assert_eq!(
+            bans_channel(&["#rust".to_string()], "ari"),
+            Some("#rust".to_string())
+        );
+        assert_eq!(bans_channel(&[], "&canal"), Some("&canal".to_string()));
+        assert_eq!(bans_channel(&[], "ari"), None);
+        assert_eq!(bans_channel(&[], "You"), None);
+    }
+
+    #[test]
+    fn msg_text_is_everything_after_the_nickname() {
+        assert_eq!(
+            msg_receiver_and_text("ari hola, como estas?", "marce"),
+            Ok(("ari", "hola, como estas?"))
+        );
+        assert_eq!(
+            msg_receiver_and_text(" lucas  chau ", "marce"),
+            Ok(("lucas", "chau"))
+        );
+        let error = Err("You must specify a nickname and a message");
+        assert_eq!(msg_receiver_and_text("ari", "marce"), error);
+        assert_eq!(msg_receiver_and_text("ari   ", "marce"), error);
+        assert_eq!(msg_receiver_and_text("", "marce"), error);
+    }
+
+    #[test]
+    fn msg_to_the_own_nickname_is_not_sent() {
+        let error = Err("You can't send messages to yourself");
+        assert_eq!(msg_receiver_and_text("marce hola", "marce"), error);
+        assert_eq!(msg_receiver_and_text("Marce hola", "marce"), error);
+        assert_eq!(msg_receiver_and_text("marce[ hola", "marce{"), error);
+    }
+
+    #[test]
+    fn empty_and_whitespace_only_messages_are_not_sent() {
+        assert!(!has_text_to_send(""));
+        assert!(!has_text_to_send("   "));
+        assert!(!has_text_to_send(" \t "));
+        assert!(has_text_to_send(" hola "));
+        assert!(has_text_to_send("/away"));
+    }
+
+    #[test]
+    fn confirmation_follows_user_setting() {
+        let mut confirm_commands = default_confirm_commands();
+        confirm_commands.remove(QUIT);
+        confirm_commands.insert(KICK.to_string());
+        assert!(!requires_confirmation(QUIT, &confirm_commands));
+        assert!(requires_confirmation(KICK, &confirm_commands));
+
+        // Non destructive commands never ask for confirmation
+        confirm_commands.insert(AWAY.to_string());
+        assert!(!requires_confirmation(AWAY, &confirm_commands));
+    }
+
+    #[test]
+    fn confirmation_text_names_action_and_target() {
+        assert_eq!(
+            confirmation_text(KICK, &["nick".to_string()], "#channel"),
+            "Are you sure you want to kick nick from the channel #channel?"
+        );
+        assert_eq!(
+            confirmation_text(KICK, &["nick too much spam".to_string()], "#channel"),
+            "Are you sure you want to kick nick from the channel #channel?"
+        );
+        assert_eq!(
+            confirmation_text(SQUIT, &["server".to_string(), "bye".to_string()], "You"),
+            "Are you sure you want to disconnect the server 'server' from the network?"
+        );
+        assert_eq!(
+            confirmation_text(QUIT, &[], "You"),
+            "Are you sure you want to quit the IRC server?"
+        );
+    }
+
+    #[test]
+    fn help_lists_every_command_or_the_syntax_of_one() {
+        let help = help_text(None).unwrap();
+        assert!(help.starts_with("♦️ /away [message] - Set your away status\n"));
+        assert!(help.contains("♦️ /rehash - Make the server read its server data file again"));
+        assert!(
+            help.ends_with("♦️ /help [command] - Show the commands, or the syntax of one of them")
+        );
+
+        assert_eq!(
+            help_text(Some("/Motd")),
+            Ok("/motd - Get the message of the day of the server\n\
+                It doesn't take any parameters"
+                .to_string())
+        );
+        let mode_help = help_text(Some("mode")).unwrap();
+        assert_eq!(mode_help.lines().count(), 3);
+        assert!(mode_help.ends_with("It takes 2 parameters at max"));
+        assert_eq!(
+            help_text(Some("nope")),
+            Err("'NOPE' is not a valid command".to_string())
+        );
+    }
+
+    #[test]
+    fn saved_chats_are_named_after_the_chat_and_the_time() {
+        assert_eq!(
+            saved_chat_file_name("#rust", "2023-05-20_18-30-00"),
+            "rust_2023-05-20_18-30-00.txt"
+        );
+        assert_eq!(saved_chat_file_name("ari|away", ""), "ari_away.txt");
+        assert_eq!(saved_chat_file_name("&../etc", "now"), "___etc_now.txt");
+    }
+
+    #[test]
+    fn message_in_other_chat_notifies() {
+        let muted_channels = HashSet::new();
+        assert!(should_notify("#channel", "You", &muted_channels));
+        assert!(should_notify("nick", "#channel", &muted_channels));
+    }
+
+    #[test]
+    fn message_in_current_chat_does_not_notify() {
+        let muted_channels = HashSet::new();
+        assert!(!should_notify("#channel", "#channel", &muted_channels));
+    }
+
+    #[test]
+    fn message_in_muted_channel_does_not_notify() {
+        let mut muted_channels = HashSet::new();
+        muted_channels.insert("#channel".to_string());
+        assert!(!should_notify("#channel", "You", &muted_channels));
+        assert!(should_notify("#other_channel", "You", &muted_channels));
+
+        muted_channels.remove("#channel");
+        assert!(should_notify("#channel", "You", &muted_channels));
+    }
+
+    #[test]
+    fn chat_showing_last_line_is_at_bottom() {
+        assert!(is_scrolled_to_bottom(400.0, 600.0, 200.0));
+    }
+
+    #[test]
+    fn chat_shorter_than_view_is_at_bottom() {
+        assert!(is_scrolled_to_bottom(0.0, 150.0, 200.0));
+    }
+
+    #[test]
+    fn chat_within_margin_is_at_bottom() {
+        assert!(is_scrolled_to_bottom(
+            400.0 - SCROLL_BOTTOM_MARGIN,
+            600.0,
+            200.0
+        ));
+    }
+
+    #[test]
+    fn chat_scrolled_up_is_not_at_bottom() {
+        assert!(!is_scrolled_to_bottom(100.0, 600.0, 200.0));
+        assert!(!is_scrolled_to_bottom(
+            400.0 - SCROLL_BOTTOM_MARGIN - 1.0,
+            600.0,
+            200.0
+        ));
+    }
+
+    #[test]
+    fn missing_widget_id_returns_error_instead_of_panicking() {
+        let result = widget_or_error::<String>(None, "jump_to_latest_button");
+
+        let error = result.unwrap_err();
+        assert_eq!(error.kind, NONCRITICAL);
+        assert_eq!(
+            error.message,
+            format!("{} jump_to_latest_button", MISSING_WIDGET)
+        );
+    }
+
+    #[test]
+    fn found_widget_is_returned() {
+        let result = widget_or_error(Some("chat_text".to_string()), "chat_text");
+
+        assert_eq!(result.unwrap(), "chat_text");
+    }
+
+    #[test]
+    fn action_text_is_wrapped_as_ctcp_action() {
+        assert_eq!(ctcp_action("waves"), "\x01ACTION waves\x01");
+        assert_eq!(
+            ctcp_action("waves at everyone"),
+            "\x01ACTION waves at everyone\x01"
+        );
+    }
+
+    #[test]
+    fn parting_records_channel_and_rejoin_uses_stored_key() {
+        let mut channel_memory = ChannelMemory::default();
+        channel_memory.remember_key("#canal", "clave");
+
+        channel_memory.record_part("#canal");
+        let join = channel_memory.take_rejoin().unwrap();
+
+        assert_eq!(join.command, JOIN);
+        assert_eq!(
+            join.params,
+            vec![vec!["#canal".to_string()], vec!["clave".to_string()]]
+        );
+
+        // The memory is cleared after rejoining
+        assert!(channel_memory.take_rejoin().is_none());
+    }
+
+    #[test]
+    fn rejoin_channel_without_key_sends_only_the_name() {
+        let mut channel_memory = ChannelMemory::default();
+        assert!(channel_memory.take_rejoin().is_none());
+
+        channel_memory.record_part("#otro");
+        let join = channel_memory.take_rejoin().unwrap();
+
+        assert_eq!(join.params, vec![vec!["#otro".to_string()]]);
+    }
+
+    #[test]
+    fn joining_again_after_reconnecting_keeps_the_keys() {
+        let mut channel_memory = ChannelMemory::default();
+        channel_memory.remember_key("#canal", "clave");
+
+        assert_eq!(
+            channel_memory.join_message("#canal").params,
+            vec![vec!["#canal".to_string()], vec!["clave".to_string()]]
+        );
+        assert_eq!(
+            channel_memory.join_message("#otro").params,
+            vec![vec!["#otro".to_string()]]
+        );
+    }
 }
@@ -80,6 +80,7 @@ impl WindowRegister {
             } else {
                 tx_frontend
                     .send(Message {
+                        tags: None,
                         prefix: None,
                         command: REGISTRATION.to_string(),
                         params: vec![],
@@ -93,6 +94,7 @@ impl WindowRegister {
                     .ok();
                 tx_frontend
                     .send(Message {
+                        tags: None,
                         prefix: None,
                         command: PASS.to_string(),
                         params: vec![vec![password_entry.text().to_string()]],
@@ -106,6 +108,7 @@ impl WindowRegister {
                     .ok();
                 tx_frontend
                     .send(Message {
+                        tags: None,
                         prefix: None,
                         command: NICK.to_string(),
                         params: vec![vec![nickname_entry.text().to_string()]],
@@ -119,6 +122,7 @@ impl WindowRegister {
                     .ok();
                 tx_frontend
                     .send(Message {
+                        tags: None,
                         prefix: None,
                         command: USER.to_string(),
                         params: vec![
@@ -0,0 +1,336 @@
+//!
+//! The headless client talks with the server without GTK, so it can be used from scripts and tests.
+//! It runs the same UI updater and UI listener than the GTK client, and the updates that the
+//! GTK client would show in its windows are returned by its methods instead
+//!
+
+use std::collections::HashSet;
+use std::net::{Shutdown, TcpStream};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::client_utils::nick_completion::ChannelMembers;
+use crate::client_utils::ui_listener::ui_listener;
+use crate::client_utils::ui_updater::ui_updater;
+use crate::commands::{
+    ADD_LIST_CHATS, CORRECT_LOGIN, EVENTS_INLINE, INVALID_LOGIN, JOIN, KICK_CHANNEL, LOGIN, NICK,
+    PART_CHANNEL, PASS, PRIVMSG, QUIT, RECEIVED_MESSAGE, USER,
+};
+use crate::custom_errors::client_error::ClientError;
+use crate::custom_errors::errors::{CRITICAL, NONCRITICAL, SEND_MESSAGE};
+use crate::message::Message;
+use crate::parser::irc_lowercase;
+
+pub struct HeadlessClient {
+    // Stream connected to the server, kept to close the connection
+    stream: TcpStream,
+    // Messages for the UI listener to send to the server
+    tx_frontend: Sender<Message>,
+    // Updates from the UI updater
+    rx_frontend: Receiver<Message>,
+    // Users and channels with an open chat, shared with the UI updater like in the GTK client
+    online_chats_names: Arc<Mutex<Vec<String>>>,
+    // Users of the channels joined, used by the GTK client to complete nicknames
+    channel_members: Arc<Mutex<ChannelMembers>>,
+    // Users whose messages are dropped by the UI updater, like /ignore in the GTK client
+    ignored_users: Arc<Mutex<HashSet<String>>>,
+}
+
+impl HeadlessClient {
+    ///
+    /// Connects to the server in address and starts the UI updater and UI listener
+    ///
+    pub fn connect(address: &str) -> Result<Self, ClientError> {
+        let stream = TcpStream::connect(address).map_err(|_| -> ClientError {
+            ClientError {
+                kind: CRITICAL.to_string(),
+                message: format!("Couldn't connect to {}", address),
+            }
+        })?;
+        let clone_stream = || -> Result<TcpStream, ClientError> {
+            stream.try_clone().map_err(|_| -> ClientError {
+                ClientError {
+                    kind: CRITICAL.to_string(),
+                    message: "Couldn't clone the stream".to_string(),
+                }
+            })
+        };
+        let stream_updater = clone_stream()?;
+        let stream_listener = clone_stream()?;
+
+        let (tx_backend, rx_frontend): (Sender<Message>, Receiver<Message>) = mpsc::channel();
+        let (tx_frontend, rx_backend): (Sender<Message>, Receiver<Message>) = mpsc::channel();
+        let (tx_stream_1, rx_stream_1): (Sender<TcpStream>, Receiver<TcpStream>) = mpsc::channel();
+        let (tx_stream_2, rx_stream_2): (Sender<TcpStream>, Receiver<TcpStream>) = mpsc::channel();
+        let (tx_acknowledgements, rx_acknowledgements): (Sender<u64>, Receiver<u64>) =
+            mpsc::channel();
+
+        let online_chats_names = Arc::new(Mutex::new(Vec::new()));
+        let online_chats_names_clone = online_chats_names.clone();
+        let channel_members = Arc::new(Mutex::new(ChannelMembers::default()));
+        let channel_members_clone = channel_members.clone();
+        let ignored_users = Arc::new(Mutex::new(HashSet::new()));
+        let ignored_users_clone = ignored_users.clone();
+        let _ = thread::spawn(move || {
+            let raw_mode = Arc::new(Mutex::new(false));
+            let announce_topic_changes = Arc::new(Mutex::new(true));
+            let membership_events = Arc::new(Mutex::new(EVENTS_INLINE.to_string()));
+            // The headless client has no chat window to show who is typing
+            let typing_notifications = Arc::new(Mutex::new(false));
+            if let Err(err_message) = ui_updater(
+                tx_backend,
+                rx_stream_1,
+                tx_acknowledgements,
+                &online_chats_names_clone,
+                &raw_mode,
+                &announce_topic_changes,
+                &membership_events,
+                &channel_members_clone,
+                &ignored_users_clone,
+                &typing_notifications,
+            ) {
+                println!("Error in ui_updater {}", err_message)
+            }
+        });
+        let _ = thread::spawn(move || {
+            if let Err(err_message) = ui_listener(rx_backend, rx_stream_2, rx_acknowledgements) {
+                println!("Error in ui_listener {}", err_message)
+            }
+        });
+
+        let send_error = |_| -> ClientError {
+            ClientError {
+                kind: CRITICAL.to_string(),
+                message: SEND_MESSAGE.to_string(),
+            }
+        };
+        tx_stream_1.send(stream_updater).map_err(send_error)?;
+        tx_stream_2.send(stream_listener).map_err(send_error)?;
+
+        Ok(HeadlessClient {
+            stream,
+            tx_frontend,
+            rx_frontend,
+            online_chats_names,
+            channel_members,
+            ignored_users,
+        })
+    }
+
+    ///
+    /// Sends a message to the server, like the GTK client does when the user performs an action
+    ///
+    pub fn send(&self, message: Message) -> Result<(), ClientError> {
+        self.tx_frontend.send(message).map_err(|_| -> ClientError {
+            ClientError {
+                kind: CRITICAL.to_string(),
+                message: SEND_MESSAGE.to_string(),
+            }
+        })
+    }
+
+    ///
+    /// Logs in with the same messages than the login window and waits for the answer of the
+    /// server. Returns a NONCRITICAL error if the login was invalid or no answer arrived in time
+    ///
+    pub fn login(
+        &self,
+        nickname: &str,
+        password: &str,
+        timeout: Duration,
+    ) -> Result<(), ClientError> {
+        self.send(Message {
+            tags: None,
+            prefix: None,
+            command: LOGIN.to_string(),
+            params: vec![],
+        })?;
+        self.send(Message {
+            tags: None,
+            prefix: None,
+            command: PASS.to_string(),
+            params: vec![vec![password.to_string()]],
+        })?;
+        self.send(Message {
+            tags: None,
+            prefix: None,
+            command: NICK.to_string(),
+            params: vec![vec![nickname.to_string()]],
+        })?;
+        self.send(Message {
+            tags: None,
+            prefix: None,
+            command: USER.to_string(),
+            params: vec![
+                vec![
+                    nickname.to_string(),
+                    "headless".to_string(),
+                    "headless".to_string(),
+                ],
+                vec![nickname.to_string()],
+            ],
+        })?;
+
+        let answer = self.wait_for(timeout, |update| {
+            update.command == *CORRECT_LOGIN || update.command == *INVALID_LOGIN
+        });
+        match answer {
+            Some(update) if update.command == *CORRECT_LOGIN => Ok(()),
+            _ => Err(ClientError {
+                kind: NONCRITICAL.to_string(),
+                message: format!("Couldn't login as {}", nickname),
+            }),
+        }
+    }
+
+    ///
+    /// Joins a channel. Its chat is opened before the JOIN is sent, so the messages the server
+    /// sends right after joining, like the backlog, are not dropped
+    ///
+    pub fn join(&self, channel: &str) -> Result<(), ClientError> {
+        if let Ok(mut chats) = self.online_chats_names.lock() {
+            if !chats.iter().any(|chat| chat == channel) {
+                chats.push(channel.to_string());
+            }
+        }
+        self.send(Message {
+            tags: None,
+            prefix: None,
+            command: JOIN.to_string(),
+            params: vec![vec![channel.to_string()]],
+        })
+    }
+
+    ///
+    /// Sends a private message to a user or a channel
+    ///
+    pub fn privmsg(&self, receiver: &str, text: &str) -> Result<(), ClientError> {
+        self.send(Message {
+            tags: None,
+            prefix: None,
+            command: PRIVMSG.to_string(),
+            params: vec![vec![receiver.to_string()], vec![text.to_string()]],
+        })
+    }
+
+    ///
+    /// Stops showing the messages of a user, or shows them again if ignore is false
+    ///
+    pub fn ignore(&self, nickname: &str, ignore: bool) {
+        if let Ok(mut ignored_users) = self.ignored_users.lock() {
+            match ignore {
+                true => ignored_users.insert(irc_lowercase(nickname)),
+                false => ignored_users.remove(&irc_lowercase(nickname)),
+            };
+        }
+    }
+
+    ///
+    /// Leaves the server
+    ///
+    pub fn quit(&self) -> Result<(), ClientError> {
+        self.send(Message {
+            tags: None,
+            prefix: None,
+            command: QUIT.to_string(),
+            params: vec![],
+        })
+    }
+
+    ///
+    /// Returns the names of the users and channels that have an open chat
+    ///
+    pub fn chats(&self) -> Vec<String> {
+        match self.online_chats_names.lock() {
+            Ok(chats) => chats.clone(),
+            Err(_) => vec![],
+        }
+    }
+
+    ///
+    /// Returns the users known in a channel
+    ///
+    pub fn channel_members(&self, channel: &str) -> Vec<String> {
+        match self.channel_members.lock() {
+            Ok(channel_members) => channel_members.get(channel),
+            Err(_) => vec![],
+        }
+    }
+
+    ///
+    /// Returns the next update from the UI updater, or None if nothing arrived in time.
+    /// The list of chats is kept like the GTK client does it
+    ///
+    pub fn next_update(&self, timeout: Duration) -> Option<Message> {
+        let update = match self.rx_frontend.recv_timeout(timeout) {
+            Ok(update) => update,
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => return None,
+        };
+
+        if let Ok(mut chats) = self.online_chats_names.lock() {
+            match update.command.as_str() {
+                ADD_LIST_CHATS => {
+                    if let Some(name) = &update.prefix {
+                        if !chats.contains(name) {
+                            chats.push(name.clone());
+                        }
+                    }
+                }
+                PART_CHANNEL | KICK_CHANNEL => {
+                    if let Some(name) = update.params.first().and_then(|param| param.first()) {
+                        chats.retain(|chat| chat != name);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Some(update)
+    }
+
+    ///
+    /// Waits until an update that matches the condition arrives, skipping the other ones.
+    /// Returns None if it didn't arrive in time
+    ///
+    pub fn wait_for(
+        &self,
+        timeout: Duration,
+        condition: impl Fn(&Message) -> bool,
+    ) -> Option<Message> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.checked_duration_since(Instant::now())?;
+            let update = self.next_update(remaining)?;
+            if condition(&update) {
+                return Some(update);
+            }
+        }
+    }
+
+    ///
+    /// Waits until a text that contains the one given is shown in chat. Returns the name of the chat
+    /// and the whole text, or None if it didn't arrive in time
+    ///
+    pub fn wait_for_text(&self, text: &str, timeout: Duration) -> Option<(String, String)> {
+        let update = self.wait_for(timeout, |update| {
+            update.command == *RECEIVED_MESSAGE
+                && update
+                    .params
+                    .first()
+                    .and_then(|param| param.first())
+                    .is_some_and(|shown| shown.contains(text))
+        })?;
+        Some((
+            update.prefix.unwrap_or_default(),
+            update.params[0][0].clone(),
+        ))
+    }
+}
+
+impl Drop for HeadlessClient {
+    fn drop(&mut self) {
+        // Closing the stream ends the UI updater, and dropping the sender ends the UI listener
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
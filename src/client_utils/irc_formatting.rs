@@ -0,0 +1,199 @@
+//!
+//! Formatting codes of the messages, as sent by mIRC and most IRC clients. The text is split in
+//! spans with the same format, so the chat can show them with colors, bold, italic or underline
+//! instead of the control characters. Codes that are not shown are removed
+//!
+
+// Codes that turn a format on or off
+const BOLD: char = '\x02';
+const COLOR: char = '\x03';
+const RESET: char = '\x0F';
+const ITALIC: char = '\x1D';
+const UNDERLINE: char = '\x1F';
+
+// Colors of the mIRC palette, by their number
+const COLORS: [&str; 16] = [
+    "#FFFFFF", "#000000", "#00007F", "#009300", "#FF0000", "#7F0000", "#9C009C", "#FC7F00",
+    "#FFFF00", "#00FC00", "#009393", "#00FFFF", "#0000FC", "#FF00FF", "#7F7F7F", "#D2D2D2",
+];
+
+///
+/// Part of a message with the same format
+///
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FormattedSpan {
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    // Color of the text, like "#FF0000", None to use the one of the chat
+    pub foreground: Option<&'static str>,
+}
+
+impl FormattedSpan {
+    ///
+    /// Returns true if the span is shown as the rest of the chat
+    ///
+    pub fn is_plain(&self) -> bool {
+        !self.bold && !self.italic && !self.underline && self.foreground.is_none()
+    }
+}
+
+///
+/// Splits the text in spans with the same format. The spans don't have formatting codes, and
+/// spans without text are left out
+///
+pub fn parse_formatting(text: &str) -> Vec<FormattedSpan> {
+    let mut spans = vec![];
+    let mut current = FormattedSpan::default();
+    let mut chars = text.chars().peekable();
+
+    while let Some(character) = chars.next() {
+        if !character.is_control() || character.is_whitespace() {
+            current.text.push(character);
+            continue;
+        }
+
+        let mut next = FormattedSpan {
+            text: String::new(),
+            ..current.clone()
+        };
+        match character {
+            BOLD => next.bold = !next.bold,
+            ITALIC => next.italic = !next.italic,
+            UNDERLINE => next.underline = !next.underline,
+            RESET => next = FormattedSpan::default(),
+            COLOR => {
+                // Up to two digits for the text and, after a comma, two for the background
+                let foreground = read_color(&mut chars);
+                next.foreground = foreground.and_then(color);
+                let mut after_comma = chars.clone();
+                if foreground.is_some()
+                    && after_comma.next() == Some(',')
+                    && matches!(after_comma.peek(), Some(digit) if digit.is_ascii_digit())
+                {
+                    chars = after_comma;
+                    read_color(&mut chars);
+                }
+            }
+            // Reverse colors and unknown codes are not shown
+            _ => continue,
+        }
+
+        if !current.text.is_empty() {
+            spans.push(current);
+        }
+        current = next;
+    }
+
+    if !current.text.is_empty() {
+        spans.push(current);
+    }
+    spans
+}
+
+///
+/// Returns the text without any formatting code
+///
+pub fn strip_formatting(text: &str) -> String {
+    parse_formatting(text)
+        .into_iter()
+        .map(|span| span.text)
+        .collect()
+}
+
+///
+/// Reads the number of a color, of one or two digits
+///
+fn read_color(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<usize> {
+    let mut number = String::new();
+    while number.len() < 2 {
+        match chars.peek() {
+            Some(digit) if digit.is_ascii_digit() => number.push(*digit),
+            _ => break,
+        }
+        chars.next();
+    }
+    number.parse().ok()
+}
+
+///
+/// Returns the color of the palette with the number given, None if it is not in the palette
+///
+fn color(number: usize) -> Option<&'static str> {
+    COLORS.get(number).copied()
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_formatting, strip_formatting, FormattedSpan};
+
+    fn span(text: &str) -> FormattedSpan {
+        FormattedSpan {
+            text: text.to_string(),
+            ..FormattedSpan::default()
+        }
+    }
+
+    #[test]
+    fn bold_underline_and_italic_are_turned_on_and_off() {
+        let spans = parse_formatting("hola \x02grupo\x02 \x1Fde\x1D rust\x0F!");
+
+        assert_eq!(
+            spans,
+            vec![
+                span("hola "),
+                FormattedSpan {
+                    bold: true,
+                    ..span("grupo")
+                },
+                span(" "),
+                FormattedSpan {
+                    underline: true,
+                    ..span("de")
+                },
+                FormattedSpan {
+                    underline: true,
+                    italic: true,
+                    ..span(" rust")
+                },
+                span("!"),
+            ]
+        );
+        assert!(spans[0].is_plain());
+    }
+
+    #[test]
+    fn colors_use_the_mirc_palette_and_ignore_the_background() {
+        let spans = parse_formatting("\x034rojo\x0312,1azul\x03 normal\x0399 fuera, si");
+
+        assert_eq!(
+            spans,
+            vec![
+                FormattedSpan {
+                    foreground: Some("#FF0000"),
+                    ..span("rojo")
+                },
+                FormattedSpan {
+                    foreground: Some("#0000FC"),
+                    ..span("azul")
+                },
+                span(" normal"),
+                // 99 is not in the palette, the comma is part of the text
+                span(" fuera, si"),
+            ]
+        );
+    }
+
+    #[test]
+    fn formatting_and_unknown_codes_are_stripped() {
+        assert_eq!(
+            strip_formatting("\x02\x0304,08hola\x0F \x16ari\x07\x1D\r\n"),
+            "hola ari\r\n"
+        );
+        assert_eq!(strip_formatting("sin formato"), "sin formato");
+        assert_eq!(strip_formatting("\x03,5coma"), ",5coma");
+    }
+}
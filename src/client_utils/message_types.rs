@@ -4,3 +4,5 @@
 pub const PRIVATE_MESSAGE: &str = "MESSAGE";
 pub const INFO: &str = "INFO";
 pub const ERROR: &str = "ERROR";
+pub const RAW: &str = "RAW";
+pub const NOTICE: &str = "NOTICE";
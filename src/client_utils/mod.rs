@@ -1 +1,18 @@
+#[cfg(feature = "client")]
 pub mod client;
+pub mod dcc_checksum;
+pub mod dcc_cipher;
+pub mod dcc_connection;
+pub mod dcc_download;
+pub mod dcc_offers;
+pub mod dcc_progress;
+pub mod dcc_recipients;
+pub mod delivery;
+pub mod frontend;
+pub mod headless;
+pub mod irc_formatting;
+pub mod message_types;
+pub mod nick_completion;
+pub mod typing;
+pub mod ui_listener;
+pub mod ui_updater;
@@ -0,0 +1,268 @@
+//!
+//! Completion of nicknames in the message entry. The UI updater keeps the users of every channel
+//! from the NAMES replies, the messages and the joins, parts and quits received, and pressing Tab
+//! completes the word being written with one of them. Pressing Tab again goes to the next user
+//! that matches
+//!
+
+use std::collections::HashMap;
+
+use crate::parser::irc_lowercase;
+
+// Written before a nickname to mention the user, it is kept when completing
+const MENTION_MARKER: char = '@';
+
+///
+/// Users of each channel, by the name of the channel in lowercase
+///
+#[derive(Debug, Default, Clone)]
+pub struct ChannelMembers {
+    members: HashMap<String, Vec<String>>,
+    // Name of each channel as the server sent it, by the name in lowercase
+    names: HashMap<String, String>,
+}
+
+impl ChannelMembers {
+    ///
+    /// Replaces the users of the channel, like when a NAMES reply arrives
+    ///
+    pub fn set(&mut self, channel: &str, nicknames: Vec<String>) {
+        self.members.insert(irc_lowercase(channel), nicknames);
+        self.names
+            .insert(irc_lowercase(channel), channel.to_string());
+    }
+
+    ///
+    /// Adds a user that joined the channel
+    ///
+    pub fn add(&mut self, channel: &str, nickname: &str) {
+        self.names
+            .entry(irc_lowercase(channel))
+            .or_insert_with(|| channel.to_string());
+        let members = self.members.entry(irc_lowercase(channel)).or_default();
+        if !members
+            .iter()
+            .any(|member| irc_lowercase(member) == irc_lowercase(nickname))
+        {
+            members.push(nickname.to_string());
+        }
+    }
+
+    ///
+    /// Removes a user that left the channel
+    ///
+    pub fn remove(&mut self, channel: &str, nickname: &str) {
+        if let Some(members) = self.members.get_mut(&irc_lowercase(channel)) {
+            members.retain(|member| irc_lowercase(member) != irc_lowercase(nickname));
+        }
+    }
+
+    ///
+    /// Removes a user that quit from every channel. Returns the channels the user was in,
+    /// sorted by their name
+    ///
+    pub fn remove_from_all(&mut self, nickname: &str) -> Vec<String> {
+        let mut channels = vec![];
+        for (channel, members) in self.members.iter_mut() {
+            let before = members.len();
+            members.retain(|member| irc_lowercase(member) != irc_lowercase(nickname));
+            if members.len() != before {
+                channels.push(self.names.get(channel).unwrap_or(channel).clone());
+            }
+        }
+        channels.sort();
+        channels
+    }
+
+    ///
+    /// Returns the users of the channel, empty if the channel is not known
+    ///
+    pub fn get(&self, channel: &str) -> Vec<String> {
+        self.members
+            .get(&irc_lowercase(channel))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+///
+/// Remembers the last completion, so pressing Tab again replaces it with the next match
+///
+#[derive(Debug, Default)]
+pub struct NickCompletion {
+    last: Option<LastCompletion>,
+}
+
+#[derive(Debug)]
+struct LastCompletion {
+    before: String,
+    after: String,
+    matches: Vec<String>,
+    index: usize,
+    text: String,
+    cursor: usize,
+}
+
+impl LastCompletion {
+    ///
+    /// Returns the text with the current match between the text before and after the word
+    /// completed, and the position of the cursor after the match
+    ///
+    fn completed_text(&self) -> (String, usize) {
+        let completed = format!("{}{} ", self.before, self.matches[self.index]);
+        let cursor = completed.chars().count();
+        (format!("{}{}", completed, self.after), cursor)
+    }
+}
+
+impl NickCompletion {
+    ///
+    /// Completes the word before the cursor with the nicknames that start with it, ignoring the
+    /// case. The cursor is counted in characters, like in the GTK entries. Returns the new text
+    /// and the new position of the cursor, or None if no nickname matches. If the text is the
+    /// one of the last completion the next match is used
+    ///
+    pub fn complete(
+        &mut self,
+        text: &str,
+        cursor: usize,
+        nicknames: &[String],
+    ) -> Option<(String, usize)> {
+        if let Some(last) = self.last.as_mut() {
+            if last.text == text && last.cursor == cursor {
+                last.index = (last.index + 1) % last.matches.len();
+                let (text, cursor) = last.completed_text();
+                last.text = text.clone();
+                last.cursor = cursor;
+                return Some((text, cursor));
+            }
+        }
+        self.last = None;
+
+        let cursor_byte = text
+            .char_indices()
+            .nth(cursor)
+            .map_or(text.len(), |(index, _)| index);
+        let (before_cursor, after) = text.split_at(cursor_byte);
+        // Some spaces take more than one byte, the word starts after the whole character
+        let word_start = before_cursor
+            .char_indices()
+            .rev()
+            .find(|(_, character)| character.is_whitespace())
+            .map_or(0, |(index, character)| index + character.len_utf8());
+        let (before, word) = before_cursor.split_at(word_start);
+        let (marker, partial) = match word.strip_prefix(MENTION_MARKER) {
+            Some(partial) => (MENTION_MARKER.to_string(), partial),
+            None => (String::new(), word),
+        };
+        if partial.is_empty() {
+            return None;
+        }
+
+        let mut matches: Vec<String> = nicknames
+            .iter()
+            .filter(|nickname| irc_lowercase(nickname).starts_with(&irc_lowercase(partial)))
+            .cloned()
+            .collect();
+        if matches.is_empty() {
+            return None;
+        }
+        matches.sort_by_key(|nickname| irc_lowercase(nickname));
+        matches.dedup();
+
+        let mut last = LastCompletion {
+            before: format!("{}{}", before, marker),
+            after: after.trim_start().to_string(),
+            matches,
+            index: 0,
+            text: String::new(),
+            cursor: 0,
+        };
+        let (text, cursor) = last.completed_text();
+        last.text = text.clone();
+        last.cursor = cursor;
+        self.last = Some(last);
+        Some((text, cursor))
+    }
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::{ChannelMembers, NickCompletion};
+
+    fn nicknames(nicknames: &[&str]) -> Vec<String> {
+        nicknames
+            .iter()
+            .map(|nickname| nickname.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn the_word_before_the_cursor_is_completed() {
+        let mut completion = NickCompletion::default();
+        let users = nicknames(&["juanireil", "Ariana", "lucas"]);
+
+        assert_eq!(
+            completion.complete("hola @ar", 8, &users),
+            Some(("hola @Ariana ".to_string(), 13))
+        );
+        assert_eq!(
+            completion.complete("LU", 2, &users),
+            Some(("lucas ".to_string(), 6))
+        );
+        assert_eq!(
+            completion.complete("juan dijo algo", 4, &users),
+            Some(("juanireil dijo algo".to_string(), 10))
+        );
+        assert_eq!(completion.complete("hola @ma", 8, &users), None);
+        assert_eq!(completion.complete("hola ", 5, &users), None);
+        // The ideographic space takes three bytes
+        assert_eq!(
+            completion.complete("hola\u{3000}ar", 7, &users),
+            Some(("hola\u{3000}Ariana ".to_string(), 12))
+        );
+    }
+
+    #[test]
+    fn pressing_tab_again_cycles_through_the_matches() {
+        let mut completion = NickCompletion::default();
+        let users = nicknames(&["marce", "mari", "lucas", "Mario"]);
+
+        let (text, cursor) = completion.complete("chau @ma", 8, &users).unwrap();
+        assert_eq!(text, "chau @marce ");
+        let (text, cursor) = completion.complete(&text, cursor, &users).unwrap();
+        assert_eq!(text, "chau @mari ");
+        let (text, cursor) = completion.complete(&text, cursor, &users).unwrap();
+        assert_eq!(text, "chau @Mario ");
+        let (text, _) = completion.complete(&text, cursor, &users).unwrap();
+        assert_eq!(text, "chau @marce ");
+
+        // A text written after the completion starts a new one
+        assert_eq!(
+            completion.complete("chau @marce y lu", 16, &users),
+            Some(("chau @marce y lucas ".to_string(), 20))
+        );
+    }
+
+    #[test]
+    fn members_follow_the_joins_parts_and_quits() {
+        let mut members = ChannelMembers::default();
+        members.set("#Canal", nicknames(&["ari", "lucas"]));
+        members.set("#otro", nicknames(&["lucas"]));
+
+        members.add("#canal", "juanireil");
+        members.add("#canal", "ARI");
+        members.remove("#CANAL", "ari");
+        assert_eq!(members.get("#canal"), nicknames(&["lucas", "juanireil"]));
+
+        assert_eq!(
+            members.remove_from_all("Lucas"),
+            nicknames(&["#Canal", "#otro"])
+        );
+        assert_eq!(members.get("#canal"), nicknames(&["juanireil"]));
+        assert!(members.get("#otro").is_empty());
+        assert!(members.get("#desconocido").is_empty());
+    }
+}
@@ -0,0 +1,258 @@
+//!
+//! Typing notifications tell the other user of a private chat that a message is being written.
+//! They are CTCP TYPING notices: "active" is sent while the user types, at most every few
+//! seconds, and "done" when the message entry is emptied, which also happens when the message is
+//! sent. The user notified stops showing it when "done" or the message arrives, or when nothing
+//! is received for a while, so a user that stops typing without sending isn't shown forever
+//!
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    commands::{CTCP_DELIMITER, CTCP_TYPING},
+    parser::{irc_eq, irc_lowercase},
+};
+
+pub const TYPING_ACTIVE: &str = "active";
+pub const TYPING_DONE: &str = "done";
+
+// Time between the "active" notices sent while the user keeps typing
+pub const TYPING_RENEWAL_INTERVAL: Duration = Duration::from_secs(3);
+// Time a user is shown typing after the last "active" received
+pub const TYPING_EXPIRATION: Duration = Duration::from_secs(6);
+
+///
+/// Returns the text of the NOTICE that tells the state, wrapped as a CTCP TYPING
+///
+pub fn typing_notice(state: &str) -> String {
+    format!(
+        "{}{} {}{}",
+        CTCP_DELIMITER, CTCP_TYPING, state, CTCP_DELIMITER
+    )
+}
+
+///
+/// Returns the state of a CTCP TYPING, or None if the text is not one
+///
+pub fn typing_state(text: &str) -> Option<&str> {
+    let ctcp = text
+        .strip_prefix(CTCP_DELIMITER)?
+        .strip_suffix(CTCP_DELIMITER)?;
+    match ctcp.split_once(' ') {
+        Some((CTCP_TYPING, state)) if state == TYPING_ACTIVE || state == TYPING_DONE => Some(state),
+        _ => None,
+    }
+}
+
+///
+/// Returns the text shown below the chat while the user is typing
+///
+pub fn typing_text(nickname: &str) -> String {
+    format!("{} is typing…", nickname)
+}
+
+///
+/// Returns true for the chats with another user, only they are told when the user types
+///
+fn is_private_chat(chat: &str) -> bool {
+    !chat.is_empty() && chat != "You" && !chat.starts_with('#') && !chat.starts_with('&')
+}
+
+///
+/// Decides the notices sent while the user writes in the message entry
+///
+#[derive(Debug, Default)]
+pub struct TypingNotifier {
+    // Chat told that the user is typing and when the last "active" was sent to it
+    active: Option<(String, Instant)>,
+}
+
+impl TypingNotifier {
+    ///
+    /// Called when the text of the message entry changes. Returns the chats that must be
+    /// notified, with the state sent to each. Commands are not messages, so they are not notified
+    ///
+    pub fn entry_changed(
+        &mut self,
+        chat: &str,
+        text: &str,
+        now: Instant,
+    ) -> Vec<(String, &'static str)> {
+        let mut notices = Vec::new();
+        let typing = is_private_chat(chat) && !text.is_empty() && !text.starts_with('/');
+
+        if let Some((active_chat, last_sent)) = &self.active {
+            if typing
+                && irc_eq(active_chat, chat)
+                && now.saturating_duration_since(*last_sent) < TYPING_RENEWAL_INTERVAL
+            {
+                return notices;
+            }
+            if !typing || !irc_eq(active_chat, chat) {
+                notices.push((active_chat.clone(), TYPING_DONE));
+            }
+        }
+
+        self.active = None;
+        if typing {
+            notices.push((chat.to_string(), TYPING_ACTIVE));
+            self.active = Some((chat.to_string(), now));
+        }
+        notices
+    }
+
+    ///
+    /// Called when the notifications are turned off. Returns the chat that was told the user
+    /// is typing, it must be told "done" because nothing else will be sent to it
+    ///
+    pub fn stopped(&mut self) -> Option<String> {
+        self.active.take().map(|(chat, _)| chat)
+    }
+}
+
+///
+/// Users that are typing a message to this user
+///
+#[derive(Debug, Default)]
+pub struct TypingUsers {
+    // Nickname in lowercase and when its last "active" was received
+    typing: HashMap<String, Instant>,
+}
+
+impl TypingUsers {
+    ///
+    /// Saves the state received from the user
+    ///
+    pub fn notified(&mut self, nickname: &str, state: &str, now: Instant) {
+        match state {
+            TYPING_ACTIVE => {
+                self.typing.insert(irc_lowercase(nickname), now);
+            }
+            _ => self.stopped(nickname),
+        }
+    }
+
+    ///
+    /// The user is no longer typing, because the message arrived
+    ///
+    pub fn stopped(&mut self, nickname: &str) {
+        self.typing.remove(&irc_lowercase(nickname));
+    }
+
+    ///
+    /// Returns true if the user said it is typing and it didn't expire
+    ///
+    pub fn is_typing(&self, nickname: &str, now: Instant) -> bool {
+        match self.typing.get(&irc_lowercase(nickname)) {
+            Some(since) => now.saturating_duration_since(*since) < TYPING_EXPIRATION,
+            None => false,
+        }
+    }
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{
+        typing_notice, typing_state, TypingNotifier, TypingUsers, TYPING_ACTIVE, TYPING_DONE,
+        TYPING_EXPIRATION, TYPING_RENEWAL_INTERVAL,
+    };
+
+    #[test]
+    fn typing_notices_are_ctcp_and_other_texts_are_not() {
+        assert_eq!(typing_notice(TYPING_ACTIVE), "\x01TYPING active\x01");
+        assert_eq!(typing_state("\x01TYPING active\x01"), Some(TYPING_ACTIVE));
+        assert_eq!(typing_state("\x01TYPING done\x01"), Some(TYPING_DONE));
+        assert_eq!(typing_state("\x01TYPING maybe\x01"), None);
+        assert_eq!(typing_state("\x01ACTION waves\x01"), None);
+        assert_eq!(typing_state("TYPING active"), None);
+    }
+
+    #[test]
+    fn typing_is_notified_again_only_after_the_interval_and_done_when_emptied() {
+        let mut notifier = TypingNotifier::default();
+        let now = Instant::now();
+
+        assert_eq!(
+            notifier.entry_changed("ari", "h", now),
+            vec![("ari".to_string(), TYPING_ACTIVE)]
+        );
+        assert!(notifier.entry_changed("ari", "ho", now).is_empty());
+        assert_eq!(
+            notifier.entry_changed("ari", "hol", now + TYPING_RENEWAL_INTERVAL),
+            vec![("ari".to_string(), TYPING_ACTIVE)]
+        );
+
+        // Sending the message empties the entry
+        let later = now + TYPING_RENEWAL_INTERVAL;
+        assert_eq!(
+            notifier.entry_changed("ari", "", later),
+            vec![("ari".to_string(), TYPING_DONE)]
+        );
+        assert!(notifier.entry_changed("ari", "", later).is_empty());
+    }
+
+    #[test]
+    fn channels_own_chat_and_commands_are_not_notified() {
+        let mut notifier = TypingNotifier::default();
+        let now = Instant::now();
+
+        assert!(notifier.entry_changed("#canal", "hola", now).is_empty());
+        assert!(notifier.entry_changed("You", "hola", now).is_empty());
+        assert!(notifier.entry_changed("ari", "/away", now).is_empty());
+
+        // Changing to another chat tells the first one that the user stopped
+        notifier.entry_changed("ari", "hola", now);
+        assert_eq!(
+            notifier.entry_changed("marce", "hola", now),
+            vec![
+                ("ari".to_string(), TYPING_DONE),
+                ("marce".to_string(), TYPING_ACTIVE)
+            ]
+        );
+        assert_eq!(
+            notifier.entry_changed("#canal", "hola", now),
+            vec![("marce".to_string(), TYPING_DONE)]
+        );
+    }
+
+    #[test]
+    fn turning_the_notifications_off_while_typing_returns_the_chat_to_tell() {
+        let mut notifier = TypingNotifier::default();
+        let now = Instant::now();
+
+        assert_eq!(notifier.stopped(), None);
+        notifier.entry_changed("ari", "hola", now);
+        assert_eq!(notifier.stopped(), Some("ari".to_string()));
+        assert_eq!(notifier.stopped(), None);
+        // The chat is told it is typing again when the notifications are turned on
+        assert_eq!(
+            notifier.entry_changed("ari", "hola!", now),
+            vec![("ari".to_string(), TYPING_ACTIVE)]
+        );
+    }
+
+    #[test]
+    fn users_typing_stop_when_done_the_message_arrives_or_it_expires() {
+        let mut typing_users = TypingUsers::default();
+        let now = Instant::now();
+
+        typing_users.notified("Ari", TYPING_ACTIVE, now);
+        assert!(typing_users.is_typing("ari", now));
+        assert!(!typing_users.is_typing("marce", now));
+        assert!(!typing_users.is_typing("ari", now + TYPING_EXPIRATION));
+
+        typing_users.notified("ari", TYPING_DONE, now);
+        assert!(!typing_users.is_typing("ari", now));
+
+        typing_users.notified("ari", TYPING_ACTIVE, now);
+        typing_users.stopped("ari");
+        assert!(!typing_users.is_typing("ari", now + Duration::from_secs(1)));
+    }
+}
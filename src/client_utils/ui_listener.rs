@@ -0,0 +1,90 @@
+//!
+//! The UI listener receives the actions of the user from the frontend and sends them to the server
+//!
+
+use std::io::Write;
+use std::net::TcpStream;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::Instant;
+
+use crate::client_utils::delivery::PendingDeliveries;
+use crate::custom_errors::client_error::ClientError;
+use crate::custom_errors::errors::{CRITICAL, RECEIVE_MESSAGE};
+use crate::message::Message;
+
+///
+/// When the user performs an action, this method sends it through TCP to the server.
+/// If the connection is lost the actions are dropped until the client sends a new stream.
+/// Every message is sent with a label, the UI updater receives the ACK of the server with it
+/// and the messages whose ACK doesn't arrive in time are sent again
+///
+pub fn ui_listener(
+    rx_backend: Receiver<Message>,
+    rx_stream: Receiver<TcpStream>,
+    rx_acknowledgements: Receiver<u64>,
+) -> Result<(), ClientError> {
+    let mut stream = Some(rx_stream.recv().map_err(|_| -> ClientError {
+        ClientError {
+            kind: CRITICAL.to_string(),
+            message: RECEIVE_MESSAGE.to_string(),
+        }
+    })?);
+    let mut pending = PendingDeliveries::default();
+
+    while match rx_backend.try_recv() {
+        Ok(message) => {
+            // The stream of a new connection replaces the one lost
+            if let Ok(new_stream) = rx_stream.try_recv() {
+                stream = Some(new_stream);
+                pending.clear();
+            }
+            match stream.as_mut() {
+                Some(connected_stream) => {
+                    println!("Sending message: {:?}", message);
+                    let line = pending.label(&message, Instant::now());
+                    if !write_line(connected_stream, &line) {
+                        stream = None;
+                    }
+                }
+                None => println!("Not connected, message dropped: {:?}", message),
+            }
+            true
+        }
+        Err(TryRecvError::Empty) => {
+            while let Ok(label) = rx_acknowledgements.try_recv() {
+                pending.acknowledged(label);
+            }
+            let (due, lost) = pending.take_due(Instant::now());
+            for line in lost {
+                println!("No ACK arrived, message dropped: {:?}", line);
+            }
+            if let Some(connected_stream) = stream.as_mut() {
+                for line in due {
+                    println!("Sending again message: {:?}", line);
+                    if !write_line(connected_stream, &line) {
+                        stream = None;
+                        break;
+                    }
+                }
+            }
+            true
+        }
+        Err(TryRecvError::Disconnected) => {
+            println!("Client disconnected from server");
+            false
+        }
+    } {}
+
+    Ok(())
+}
+
+///
+/// Writes the line of a message to the server. Returns false if the connection was lost
+///
+fn write_line(stream: &mut TcpStream, line: &str) -> bool {
+    if stream.write_all(line.as_bytes()).is_err() {
+        println!("Couldn't send message, the connection was lost");
+        return false;
+    }
+    true
+}
@@ -1,8 +1,14 @@
-use crate::client_utils::client::message_types::{ERROR, INFO, PRIVATE_MESSAGE};
+use crate::client_utils::frontend::Frontend;
+use crate::client_utils::message_types::NOTICE as NOTICE_MESSAGE;
+use crate::client_utils::message_types::{ERROR, INFO, PRIVATE_MESSAGE, RAW};
+use crate::client_utils::nick_completion::ChannelMembers;
+use crate::client_utils::typing::typing_state;
 use crate::commands::{
-    ADD_LIST_CHATS, CORRECT_LOGIN, CORRECT_REGISTRATION, DCC_ACCEPT, DCC_CHAT, DCC_CLOSE,
-    DCC_RESUME, DCC_SEND, ERROR_CHANNEL, INVALID_LOGIN, INVALID_REGISTRATION, INVITE, KICK,
-    KICK_CHANNEL, LIST_CHANNELS, NAMES, PRIVMSG, QUIT, RECEIVED_MESSAGE, SEARCH_USERS,
+    ACK, ADD_LIST_CHATS, CLOSE, CONNECTION_PROGRESS, CORRECT_LOGIN, CORRECT_REGISTRATION,
+    CTCP_ACTION, CTCP_DELIMITER, DCC_ACCEPT, DCC_CHAT, DCC_CLOSE, DCC_ENCRYPTED, DCC_RESUME,
+    DCC_SEND, ERROR_CHANNEL, EVENTS_COLLAPSED, EVENTS_HIDDEN, INVALID_LOGIN, INVALID_REGISTRATION,
+    INVITE, JOIN, KICK, KICK_CHANNEL, LIST_CHANNELS, NAMES, NOTICE, NOTICE_AUTH, PART, PING, PONG,
+    PRIVMSG, QUIT, RECEIVED_MESSAGE, RECONNECT, SEARCH_USERS, TOPIC, TYPING_NOTIFICATION, WALLOPS,
 };
 use crate::custom_errors::client_error::ClientError;
 use crate::custom_errors::errors::{
@@ -10,48 +16,164 @@ use crate::custom_errors::errors::{
 };
 use crate::message::Message;
 use crate::numeric_reply::{
-    ERR_BADCHANNELKEY_NUM, ERR_BANNEDFROMCHAN_NUM, ERR_CHANNELHASKEY_MSG, ERR_CHANNELHASKEY_NUM,
-    ERR_CHANNELISFULL_NUM, ERR_CHANOPRIVSNEEDED_MSG, ERR_CHANOPRIVSNEEDED_NUM,
+    ERR_ALREADYFOUNDED_MSG, ERR_ALREADYFOUNDED_NUM, ERR_BADCHANNELKEY_NUM, ERR_BANLISTFULL_MSG,
+    ERR_BANLISTFULL_NUM, ERR_BANNEDFROMCHAN_NUM, ERR_CANNOTSENDTOCHAN_MSG,
+    ERR_CANNOTSENDTOCHAN_NUM, ERR_CHANNELHASKEY_MSG, ERR_CHANNELHASKEY_NUM, ERR_CHANNELISFULL_NUM,
+    ERR_CHANOPEN_MSG, ERR_CHANOPEN_NUM, ERR_CHANOPRIVSNEEDED_MSG, ERR_CHANOPRIVSNEEDED_NUM,
     ERR_ERRONEUSNICKNAME_NUM, ERR_INVALIDLOGIN_NUM, ERR_INVITEONLYCHAN_NUM, ERR_KEYSET_MSG,
-    ERR_KEYSET_NUM, ERR_NEEDMOREPARAMS_MSG, ERR_NEEDMOREPARAMS_NUM, ERR_NICKCOLLISION_NUM,
-    ERR_NONICKNAMEGIVEN_MSG, ERR_NONICKNAMEGIVEN_NUM, ERR_NOPRIVILEGES_MSG, ERR_NOPRIVILEGES_NUM,
-    ERR_NOSUCHCHANNEL_MSG, ERR_NOSUCHCHANNEL_NUM, ERR_NOSUCHNICK_MSG, ERR_NOSUCHNICK_NUM,
-    ERR_NOSUCHSERVER_MSG, ERR_NOSUCHSERVER_NUM, ERR_NOTONCHANNEL_MSG, ERR_NOTONCHANNEL_NUM,
-    ERR_PASSWDMISMATCH_MSG, ERR_PASSWDMISMATCH_NUM, ERR_TOOMANYCHANNELS_MSG,
-    ERR_TOOMANYCHANNELS_NUM, ERR_UNKNOWNMODE_NUM, ERR_USERONCHANNEL_MSG, ERR_USERONCHANNEL_NUM,
-    RPL_AWAY_NUM, RPL_CORRECTLOGIN_NUM, RPL_CORRECTREGISTRATION_NUM, RPL_ENDOFNAMES_NUM,
-    RPL_ENDOFWHOIS_NUM, RPL_ENDOFWHO_NUM, RPL_INVITING_NUM, RPL_LISTEND_NUM, RPL_LISTSTART_NUM,
-    RPL_LIST_NUM, RPL_MODESET_MSG, RPL_MODESET_NUM, RPL_NAMEREPLY_NUM, RPL_NOTOPIC_NUM,
-    RPL_NOWAWAY_MSG, RPL_NOWAWAY_NUM, RPL_TOPIC_NUM, RPL_UNAWAY_MSG, RPL_UNAWAY_NUM,
-    RPL_WHOISCHANNELS_NUM, RPL_WHOISOPERATOR_NUM, RPL_WHOISSERVER_NUM, RPL_WHOISUSER_NUM,
-    RPL_WHOREPLY_NUM, RPL_YOUREOPER_MSG, RPL_YOUREOPER_NUM,
+    ERR_KEYSET_NUM, ERR_KNOCKONCHAN_MSG, ERR_KNOCKONCHAN_NUM, ERR_MESSAGETOOLONG_NUM,
+    ERR_NEEDMOREPARAMS_MSG, ERR_NEEDMOREPARAMS_NUM, ERR_NICKCOLLISION_NUM, ERR_NOMOTD_MSG,
+    ERR_NOMOTD_NUM, ERR_NONICKNAMEGIVEN_MSG, ERR_NONICKNAMEGIVEN_NUM, ERR_NOOPERHOST_MSG,
+    ERR_NOOPERHOST_NUM, ERR_NOPRIVILEGES_MSG, ERR_NOPRIVILEGES_NUM, ERR_NOSUCHCHANNEL_MSG,
+    ERR_NOSUCHCHANNEL_NUM, ERR_NOSUCHNICK_MSG, ERR_NOSUCHNICK_NUM, ERR_NOSUCHSERVER_MSG,
+    ERR_NOSUCHSERVER_NUM, ERR_NOTONCHANNEL_MSG, ERR_NOTONCHANNEL_NUM, ERR_PASSWDMISMATCH_MSG,
+    ERR_PASSWDMISMATCH_NUM, ERR_TOOMANYCHANNELS_MSG, ERR_TOOMANYCHANNELS_NUM,
+    ERR_TOOMANYTARGETS_MSG, ERR_TOOMANYTARGETS_NUM, ERR_UMODEUNKNOWNFLAG_NUM, ERR_UNKNOWNMODE_NUM,
+    ERR_USERONCHANNEL_MSG, ERR_USERONCHANNEL_NUM, ERR_USERSDONTMATCH_MSG, ERR_USERSDONTMATCH_NUM,
+    ERR_WEAKPASSWORD_NUM, RPL_AWAY_NUM, RPL_BANLIST_NUM, RPL_CHANNELMODEIS_NUM,
+    RPL_CHANNELREGISTERED_NUM, RPL_CORRECTLOGIN_NUM, RPL_CORRECTREGISTRATION_NUM, RPL_CREATED_NUM,
+    RPL_CREATIONTIME_NUM, RPL_ENDOFBANLIST_NUM, RPL_ENDOFEXCEPTLIST_NUM, RPL_ENDOFMOTD_NUM,
+    RPL_ENDOFNAMES_NUM, RPL_ENDOFWHOIS_NUM, RPL_ENDOFWHOWAS_NUM, RPL_ENDOFWHO_NUM,
+    RPL_EXCEPTLIST_NUM, RPL_INVITING_NUM, RPL_ISON_NUM, RPL_KNOCKDLVR_NUM, RPL_LISTEND_NUM,
+    RPL_LISTSTART_NUM, RPL_LIST_NUM, RPL_LUSERCHANNELS_NUM, RPL_LUSERCLIENT_NUM, RPL_LUSERME_NUM,
+    RPL_MODESET_MSG, RPL_MODESET_NUM, RPL_MOTDSTART_NUM, RPL_MOTD_NUM, RPL_MYINFO_NUM,
+    RPL_NAMEREPLY_NUM, RPL_NAMEREPLY_OPERATOR, RPL_NAMEREPLY_VOICED, RPL_NOTOPIC_NUM,
+    RPL_NOWAWAY_MSG, RPL_NOWAWAY_NUM, RPL_TOPICWHOTIME_NUM, RPL_TOPIC_NUM, RPL_UMODEIS_NUM,
+    RPL_UNAWAY_MSG, RPL_UNAWAY_NUM, RPL_WELCOME_NUM, RPL_WHOISCHANNELS_NUM, RPL_WHOISIDLE_NUM,
+    RPL_WHOISOPERATOR_NUM, RPL_WHOISSERVER_NUM, RPL_WHOISUSER_NUM, RPL_WHOREPLY_GONE,
+    RPL_WHOREPLY_NUM, RPL_WHOWASUSER_NUM, RPL_YOUREOPER_MSG, RPL_YOUREOPER_NUM, RPL_YOURHOST_NUM,
 };
-use crate::parser;
-use std::io::{BufRead, BufReader};
+use crate::parser::{self, irc_eq, irc_lowercase};
+use crate::server_utils::logger::timestamp_text;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufReader, ErrorKind, Write};
 use std::net::TcpStream;
-use std::sync::mpsc::Receiver;
+use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Time during which collapsed joins, parts and quits are counted before showing their summary
+pub const EVENTS_SUMMARY_INTERVAL: Duration = Duration::from_secs(60);
+// Time the server is waited for before checking if the summary of the events is due
+const EVENTS_SUMMARY_CHECK_INTERVAL: Duration = Duration::from_secs(1);
 
 ///
 /// This function is responsible for receiving messages from the server and updating the UI accordingly.
+/// When the server closes the connection the frontend is told to reconnect, and the messages of the
+/// next stream received are handled the same way. The labels of the ACKs received are passed to
+/// the UI listener, so it doesn't send those messages again
 ///
+#[allow(clippy::too_many_arguments)]
 pub fn ui_updater(
-    tx_backend: gtk::glib::Sender<Message>,
+    tx_backend: impl Frontend,
     rx_stream: Receiver<TcpStream>,
+    tx_acknowledgements: Sender<u64>,
     users_online: &Arc<Mutex<Vec<String>>>,
+    raw_mode: &Arc<Mutex<bool>>,
+    announce_topic_changes: &Arc<Mutex<bool>>,
+    membership_events: &Arc<Mutex<String>>,
+    channel_members: &Arc<Mutex<ChannelMembers>>,
+    ignored_users: &Arc<Mutex<HashSet<String>>>,
+    typing_notifications: &Arc<Mutex<bool>>,
 ) -> Result<(), ClientError> {
-    let stream = rx_stream.recv().map_err(|_| -> ClientError {
+    let mut stream = rx_stream.recv().map_err(|_| -> ClientError {
         ClientError {
             kind: CRITICAL.to_string(),
             message: RECEIVE_MESSAGE.to_string(),
         }
     })?;
 
+    loop {
+        read_server_messages(
+            &tx_backend,
+            stream,
+            &tx_acknowledgements,
+            users_online,
+            raw_mode,
+            announce_topic_changes,
+            membership_events,
+            channel_members,
+            ignored_users,
+            typing_notifications,
+        )?;
+
+        tx_backend
+            .send(Message {
+                tags: None,
+                prefix: None,
+                command: RECONNECT.to_string(),
+                params: vec![],
+            })
+            .map_err(|_| -> ClientError {
+                ClientError {
+                    kind: NONCRITICAL.to_string(),
+                    message: SEND_MESSAGE.to_string(),
+                }
+            })?;
+
+        // Without a new stream the client was closed
+        stream = match rx_stream.recv() {
+            Ok(stream) => stream,
+            Err(_) => return Ok(()),
+        };
+    }
+}
+
+///
+/// Handles the messages received from the server until it closes the connection
+///
+#[allow(clippy::too_many_arguments)]
+fn read_server_messages(
+    tx_backend: &impl Frontend,
+    stream: TcpStream,
+    tx_acknowledgements: &Sender<u64>,
+    users_online: &Arc<Mutex<Vec<String>>>,
+    raw_mode: &Arc<Mutex<bool>>,
+    announce_topic_changes: &Arc<Mutex<bool>>,
+    membership_events: &Arc<Mutex<String>>,
+    channel_members: &Arc<Mutex<ChannelMembers>>,
+    ignored_users: &Arc<Mutex<HashSet<String>>>,
+    typing_notifications: &Arc<Mutex<bool>>,
+) -> Result<(), ClientError> {
+    // The stream is cloned to answer PINGs from the server without involving the user
+    let mut writer = stream.try_clone().map_err(|_| -> ClientError {
+        ClientError {
+            kind: CRITICAL.to_string(),
+            message: "Couldn't clone stream".to_string(),
+        }
+    })?;
+
+    // The reads time out so the summary of the events is shown even if nothing else arrives
+    stream
+        .set_read_timeout(Some(EVENTS_SUMMARY_CHECK_INTERVAL))
+        .map_err(|_| -> ClientError {
+            ClientError {
+                kind: CRITICAL.to_string(),
+                message: "Couldn't set the read timeout".to_string(),
+            }
+        })?;
+
     let mut data = String::new();
+    // Bytes of a line that is not complete yet
+    let mut pending = Vec::new();
     let mut reader = BufReader::new(stream);
     let mut channels: Vec<String> = Vec::new();
     let mut search_users: Vec<String> = Vec::new();
-    while match reader.read_line(&mut data) {
+    // Texts shown for the users found, with their flags when the WHO was of a channel
+    let mut search_labels: Vec<String> = Vec::new();
+    let mut events_summary = EventsSummary::new();
+    let mut motd_lines: Vec<String> = Vec::new();
+    // Channels listed by the RPL_NAMEREPLY received since the last RPL_ENDOFNAMES
+    let mut channels_named: Vec<String> = Vec::new();
+    // Channels with a RPL_BANLIST received since the last RPL_ENDOFBANLIST
+    let mut channels_banning: Vec<String> = Vec::new();
+    // Nickname of this user, known once the login or registration is answered
+    let mut own_nickname: Option<String> = None;
+    while match parser::read_line_lossy(&mut reader, &mut pending, &mut data) {
+        Ok(0) => {
+            println!("Server closed the connection");
+            false
+        }
         Ok(bytes_read) => {
             if bytes_read > 0 && !data.is_empty() {
                 let users_online = users_online.lock().map_err(|_| -> ClientError {
@@ -63,10 +185,69 @@ pub fn ui_updater(
                 let message = parser::parse(data.clone()).expect("Couldn't parse message");
                 println!("Received from server: {:?}", message);
 
+                // If raw mode is on, print the message as it was received before handling it
+                let raw_mode_on = *raw_mode.lock().map_err(|_| -> ClientError {
+                    ClientError {
+                        kind: CRITICAL.to_string(),
+                        message: "Couldn't lock raw mode".to_string(),
+                    }
+                })?;
+                // ACKs only matter to the UI listener, they are not shown even in raw mode
+                if raw_mode_on && message.command != ACK {
+                    tx_backend
+                        .send(Message {
+                            tags: None,
+                            prefix: Some("You".to_string()),
+                            command: RECEIVED_MESSAGE.to_string(),
+                            params: vec![vec![raw_message_line(&message), RAW.to_string()]],
+                        })
+                        .map_err(|_| -> ClientError {
+                            ClientError {
+                                kind: NONCRITICAL.to_string(),
+                                message: SEND_MESSAGE.to_string(),
+                            }
+                        })?;
+                }
+
+                // The users of the channels are kept to complete their nicknames
+                let channels_left = track_channel_members(
+                    &message,
+                    &mut *channel_members.lock().map_err(|_| -> ClientError {
+                        ClientError {
+                            kind: CRITICAL.to_string(),
+                            message: "Couldn't lock channel members".to_string(),
+                        }
+                    })?,
+                );
+
+                // Collapsed events are summarized once the interval is over
+                for (chat, text_to_print) in events_summary.take_if_due(Instant::now()) {
+                    send_membership_event(chat, text_to_print, &tx_backend);
+                }
+
                 match message.command.clone().as_str() {
                     // Commands
+                    PING => {
+                        answer_ping(message, &mut writer)?;
+                    }
+                    ACK => {
+                        // The UI listener may have stopped with the client, then nobody waits
+                        if let Some(label) = message.label() {
+                            let _ = tx_acknowledgements.send(label);
+                        }
+                    }
                     PRIVMSG => {
-                        parse_message(message, &tx_backend, &users_online)?;
+                        // Messages of ignored users don't reach any chat, not even a new one
+                        let ignored = ignored_users.lock().map_err(|_| -> ClientError {
+                            ClientError {
+                                kind: CRITICAL.to_string(),
+                                message: "Couldn't lock ignored users".to_string(),
+                            }
+                        })?;
+                        if !is_from_ignored_user(&message, &ignored) {
+                            drop(ignored);
+                            parse_message(message, &tx_backend, &users_online)?;
+                        }
                     }
                     NAMES => {
                         names(message, &tx_backend, &users_online);
@@ -74,6 +255,9 @@ pub fn ui_updater(
                     KICK => {
                         kick(message, &tx_backend);
                     }
+                    CLOSE => {
+                        close(message, &tx_backend);
+                    }
                     INVITE => {
                         let text_to_print = format!(
                             "{} invited you to the channel: {}",
@@ -82,6 +266,7 @@ pub fn ui_updater(
                         );
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: Some("You".to_string()),
                                 command: RECEIVED_MESSAGE.to_string(),
                                 params: vec![vec![text_to_print, INFO.to_string()]],
@@ -93,12 +278,44 @@ pub fn ui_updater(
                                 }
                             })?;
                     }
+                    JOIN | PART => {
+                        let mode = membership_events.lock().map_err(|_| -> ClientError {
+                            ClientError {
+                                kind: CRITICAL.to_string(),
+                                message: "Couldn't lock membership events".to_string(),
+                            }
+                        })?;
+                        membership_event(message, &mode, &mut events_summary, &tx_backend);
+                    }
+                    // Another user quitting, the server quitting doesn't have a prefix
+                    QUIT if message.prefix.is_some() => {
+                        let mode = membership_events.lock().map_err(|_| -> ClientError {
+                            ClientError {
+                                kind: CRITICAL.to_string(),
+                                message: "Couldn't lock membership events".to_string(),
+                            }
+                        })?;
+                        for (chat, text_to_print) in
+                            quit_events(&message, &channels_left, own_nickname.as_deref())
+                        {
+                            show_membership_event(
+                                chat,
+                                text_to_print,
+                                QUIT,
+                                &mode,
+                                &mut events_summary,
+                                &tx_backend,
+                            );
+                        }
+                    }
                     QUIT => {
+                        // The reason of the server is shown before closing
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: QUIT.to_string(),
-                                params: vec![vec![]],
+                                params: vec![server_quit_text(&message).into_iter().collect()],
                             })
                             .map_err(|_| -> ClientError {
                                 ClientError {
@@ -117,6 +334,7 @@ pub fn ui_updater(
                     RPL_LISTEND_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: LIST_CHANNELS.to_string(),
                                 params: vec![channels.clone()],
@@ -128,21 +346,92 @@ pub fn ui_updater(
                                 }
                             })?;
                     }
-                    RPL_TOPIC_NUM | RPL_NOTOPIC_NUM => {
+                    RPL_TOPIC_NUM | RPL_NOTOPIC_NUM | RPL_TOPICWHOTIME_NUM
+                    | RPL_CREATIONTIME_NUM => {
                         topic_message(message, &tx_backend);
                     }
+                    TOPIC => {
+                        let announce =
+                            *announce_topic_changes.lock().map_err(|_| -> ClientError {
+                                ClientError {
+                                    kind: CRITICAL.to_string(),
+                                    message: "Couldn't lock topic announcements".to_string(),
+                                }
+                            })?;
+                        if announce {
+                            topic_message(message, &tx_backend);
+                        }
+                    }
                     RPL_INVITING_NUM => {
                         invite_success(message, &tx_backend);
                     }
+                    RPL_KNOCKDLVR_NUM => {
+                        let text_to_print = format!(
+                            "Your knock on {} was delivered to its operators",
+                            message.params[0][0]
+                        );
+                        tx_backend
+                            .send(Message {
+                                tags: None,
+                                prefix: None,
+                                command: RECEIVED_MESSAGE.to_string(),
+                                params: vec![vec![text_to_print, INFO.to_string()]],
+                            })
+                            .map_err(|_| -> ClientError {
+                                ClientError {
+                                    kind: NONCRITICAL.to_string(),
+                                    message: SEND_MESSAGE.to_string(),
+                                }
+                            })?;
+                    }
+                    RPL_CHANNELREGISTERED_NUM => {
+                        let text_to_print = format!(
+                            "{} is registered, it is kept when it is left empty",
+                            message.params[0][0]
+                        );
+                        tx_backend
+                            .send(Message {
+                                tags: None,
+                                prefix: None,
+                                command: RECEIVED_MESSAGE.to_string(),
+                                params: vec![vec![text_to_print, INFO.to_string()]],
+                            })
+                            .map_err(|_| -> ClientError {
+                                ClientError {
+                                    kind: NONCRITICAL.to_string(),
+                                    message: SEND_MESSAGE.to_string(),
+                                }
+                            })?;
+                    }
+                    WALLOPS => {
+                        tx_backend
+                            .send(Message {
+                                tags: None,
+                                prefix: None,
+                                command: RECEIVED_MESSAGE.to_string(),
+                                params: vec![vec![
+                                    operators_message_text(&message),
+                                    NOTICE_MESSAGE.to_string(),
+                                ]],
+                            })
+                            .map_err(|_| -> ClientError {
+                                ClientError {
+                                    kind: NONCRITICAL.to_string(),
+                                    message: SEND_MESSAGE.to_string(),
+                                }
+                            })?;
+                    }
                     RPL_WHOREPLY_NUM => {
                         search_users.push(message.params[0][0].clone());
+                        search_labels.push(who_reply_label(&message));
                     }
                     RPL_ENDOFWHO_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: SEARCH_USERS.to_string(),
-                                params: vec![search_users.clone()],
+                                params: vec![search_users.clone(), search_labels.clone()],
                             })
                             .map_err(|_| -> ClientError {
                                 ClientError {
@@ -151,10 +440,84 @@ pub fn ui_updater(
                                 }
                             })?;
                         search_users.clear();
+                        search_labels.clear();
+                    }
+                    // Typing notifications are not shown as messages and never open a chat
+                    NOTICE if typing_notification(&message).is_some() => {
+                        let typing_notifications_on =
+                            *typing_notifications.lock().map_err(|_| -> ClientError {
+                                ClientError {
+                                    kind: CRITICAL.to_string(),
+                                    message: "Couldn't lock typing notifications".to_string(),
+                                }
+                            })?;
+                        let ignored = ignored_users.lock().map_err(|_| -> ClientError {
+                            ClientError {
+                                kind: CRITICAL.to_string(),
+                                message: "Couldn't lock ignored users".to_string(),
+                            }
+                        })?;
+                        match typing_notification(&message) {
+                            Some(state)
+                                if typing_notifications_on
+                                    && !is_from_ignored_user(&message, &ignored) =>
+                            {
+                                tx_backend
+                                    .send(Message {
+                                        tags: None,
+                                        prefix: message.prefix.clone(),
+                                        command: TYPING_NOTIFICATION.to_string(),
+                                        params: vec![vec![state.to_string()]],
+                                    })
+                                    .map_err(|_| -> ClientError {
+                                        ClientError {
+                                            kind: NONCRITICAL.to_string(),
+                                            message: SEND_MESSAGE.to_string(),
+                                        }
+                                    })?;
+                            }
+                            _ => {}
+                        }
                     }
+                    NOTICE => match auth_notice_text(&message) {
+                        Some(progress) => {
+                            tx_backend
+                                .send(Message {
+                                    tags: None,
+                                    prefix: None,
+                                    command: CONNECTION_PROGRESS.to_string(),
+                                    params: vec![vec![progress]],
+                                })
+                                .map_err(|_| -> ClientError {
+                                    ClientError {
+                                        kind: NONCRITICAL.to_string(),
+                                        message: SEND_MESSAGE.to_string(),
+                                    }
+                                })?;
+                        }
+                        None => {
+                            let (chat, text_to_print) =
+                                notice_chat_and_text(&message, &users_online);
+                            tx_backend
+                                .send(Message {
+                                    tags: None,
+                                    prefix: Some(chat),
+                                    command: RECEIVED_MESSAGE.to_string(),
+                                    params: vec![vec![text_to_print, NOTICE_MESSAGE.to_string()]],
+                                })
+                                .map_err(|_| -> ClientError {
+                                    ClientError {
+                                        kind: NONCRITICAL.to_string(),
+                                        message: SEND_MESSAGE.to_string(),
+                                    }
+                                })?;
+                        }
+                    },
                     RPL_CORRECTLOGIN_NUM => {
+                        own_nickname = message.params[0].first().cloned();
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: CORRECT_LOGIN.to_string(),
                                 params: vec![message.params[0].clone()],
@@ -167,8 +530,10 @@ pub fn ui_updater(
                             })?;
                     }
                     RPL_CORRECTREGISTRATION_NUM => {
+                        own_nickname = message.params[0].first().cloned();
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: CORRECT_REGISTRATION.to_string(),
                                 params: vec![message.params[0].clone()],
@@ -180,12 +545,34 @@ pub fn ui_updater(
                                 }
                             })?;
                     }
+                    RPL_WELCOME_NUM | RPL_YOURHOST_NUM | RPL_CREATED_NUM | RPL_MYINFO_NUM => {
+                        if let Some(text_to_print) = welcome_text(&message) {
+                            tx_backend
+                                .send(Message {
+                                    tags: None,
+                                    prefix: Some("You".to_string()),
+                                    command: RECEIVED_MESSAGE.to_string(),
+                                    params: vec![vec![text_to_print, INFO.to_string()]],
+                                })
+                                .map_err(|_| -> ClientError {
+                                    ClientError {
+                                        kind: NONCRITICAL.to_string(),
+                                        message: SEND_MESSAGE.to_string(),
+                                    }
+                                })?;
+                        }
+                    }
                     RPL_NAMEREPLY_NUM => {
+                        if let Some(channel) = message.params.get(2).and_then(|param| param.first())
+                        {
+                            channels_named.push(channel.clone());
+                        }
                         show_participants(message, &tx_backend);
                     }
                     RPL_NOWAWAY_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: RECEIVED_MESSAGE.to_string(),
                                 params: vec![vec![RPL_NOWAWAY_MSG.to_string(), INFO.to_string()]],
@@ -200,6 +587,7 @@ pub fn ui_updater(
                     RPL_UNAWAY_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: RECEIVED_MESSAGE.to_string(),
                                 params: vec![vec![RPL_UNAWAY_MSG.to_string(), INFO.to_string()]],
@@ -217,6 +605,7 @@ pub fn ui_updater(
                     RPL_YOUREOPER_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: RECEIVED_MESSAGE.to_string(),
                                 params: vec![vec![RPL_YOUREOPER_MSG.to_string(), INFO.to_string()]],
@@ -239,6 +628,7 @@ pub fn ui_updater(
                         );
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: RECEIVED_MESSAGE.to_string(),
                                 params: vec![vec![text_to_print, INFO.to_string()]],
@@ -258,6 +648,7 @@ pub fn ui_updater(
                         );
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: RECEIVED_MESSAGE.to_string(),
                                 params: vec![vec![text_to_print, INFO.to_string()]],
@@ -269,6 +660,9 @@ pub fn ui_updater(
                                 }
                             })?;
                     }
+                    RPL_WHOISIDLE_NUM => {
+                        whoisidle(message, &tx_backend);
+                    }
                     RPL_WHOISOPERATOR_NUM => {
                         let text_to_print = format!(
                             "{} {}",
@@ -277,6 +671,7 @@ pub fn ui_updater(
                         );
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: RECEIVED_MESSAGE.to_string(),
                                 params: vec![vec![text_to_print, INFO.to_string()]],
@@ -291,6 +686,7 @@ pub fn ui_updater(
                     RPL_MODESET_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: RECEIVED_MESSAGE.to_string(),
                                 params: vec![vec![RPL_MODESET_MSG.to_string(), INFO.to_string()]],
@@ -302,13 +698,196 @@ pub fn ui_updater(
                                 }
                             })?;
                     }
+                    RPL_CHANNELMODEIS_NUM => {
+                        tx_backend
+                            .send(Message {
+                                tags: None,
+                                prefix: Some("You".to_string()),
+                                command: RECEIVED_MESSAGE.to_string(),
+                                params: vec![vec![channel_modes_text(&message), INFO.to_string()]],
+                            })
+                            .map_err(|_| -> ClientError {
+                                ClientError {
+                                    kind: NONCRITICAL.to_string(),
+                                    message: SEND_MESSAGE.to_string(),
+                                }
+                            })?;
+                    }
+                    RPL_UMODEIS_NUM => {
+                        tx_backend
+                            .send(Message {
+                                tags: None,
+                                prefix: Some("You".to_string()),
+                                command: RECEIVED_MESSAGE.to_string(),
+                                params: vec![vec![user_modes_text(&message), INFO.to_string()]],
+                            })
+                            .map_err(|_| -> ClientError {
+                                ClientError {
+                                    kind: NONCRITICAL.to_string(),
+                                    message: SEND_MESSAGE.to_string(),
+                                }
+                            })?;
+                    }
+                    RPL_LUSERCLIENT_NUM | RPL_LUSERCHANNELS_NUM | RPL_LUSERME_NUM => {
+                        tx_backend
+                            .send(Message {
+                                tags: None,
+                                prefix: Some("You".to_string()),
+                                command: RECEIVED_MESSAGE.to_string(),
+                                params: vec![vec![lusers_text(&message), INFO.to_string()]],
+                            })
+                            .map_err(|_| -> ClientError {
+                                ClientError {
+                                    kind: NONCRITICAL.to_string(),
+                                    message: SEND_MESSAGE.to_string(),
+                                }
+                            })?;
+                    }
+                    RPL_WHOWASUSER_NUM => {
+                        tx_backend
+                            .send(Message {
+                                tags: None,
+                                prefix: None,
+                                command: RECEIVED_MESSAGE.to_string(),
+                                params: vec![vec![whowas_text(&message), INFO.to_string()]],
+                            })
+                            .map_err(|_| -> ClientError {
+                                ClientError {
+                                    kind: NONCRITICAL.to_string(),
+                                    message: SEND_MESSAGE.to_string(),
+                                }
+                            })?;
+                    }
+                    RPL_ISON_NUM => {
+                        tx_backend
+                            .send(Message {
+                                tags: None,
+                                prefix: Some("You".to_string()),
+                                command: RECEIVED_MESSAGE.to_string(),
+                                params: vec![vec![ison_text(&message), INFO.to_string()]],
+                            })
+                            .map_err(|_| -> ClientError {
+                                ClientError {
+                                    kind: NONCRITICAL.to_string(),
+                                    message: SEND_MESSAGE.to_string(),
+                                }
+                            })?;
+                    }
+                    RPL_MOTDSTART_NUM | RPL_MOTD_NUM => {
+                        motd_lines.push(motd_text(&message));
+                    }
+                    RPL_ENDOFMOTD_NUM => {
+                        for line in motd_lines.drain(..) {
+                            tx_backend
+                                .send(Message {
+                                    tags: None,
+                                    prefix: Some("You".to_string()),
+                                    command: RECEIVED_MESSAGE.to_string(),
+                                    params: vec![vec![line, INFO.to_string()]],
+                                })
+                                .map_err(|_| -> ClientError {
+                                    ClientError {
+                                        kind: NONCRITICAL.to_string(),
+                                        message: SEND_MESSAGE.to_string(),
+                                    }
+                                })?;
+                        }
+                    }
+                    RPL_BANLIST_NUM => {
+                        channels_banning.push(message.params[0][0].clone());
+                        let text_to_print = format!(
+                            "{} bans {}",
+                            message.params[0][0],
+                            message
+                                .params
+                                .get(1)
+                                .map(|mask| mask.join(" "))
+                                .unwrap_or_default()
+                        );
+                        tx_backend
+                            .send(Message {
+                                tags: None,
+                                prefix: Some("You".to_string()),
+                                command: RECEIVED_MESSAGE.to_string(),
+                                params: vec![vec![text_to_print, INFO.to_string()]],
+                            })
+                            .map_err(|_| -> ClientError {
+                                ClientError {
+                                    kind: NONCRITICAL.to_string(),
+                                    message: SEND_MESSAGE.to_string(),
+                                }
+                            })?;
+                    }
+                    RPL_EXCEPTLIST_NUM => {
+                        let text_to_print = format!(
+                            "{} lets {} join even if banned",
+                            message.params[0][0],
+                            message
+                                .params
+                                .get(1)
+                                .map(|mask| mask.join(" "))
+                                .unwrap_or_default()
+                        );
+                        tx_backend
+                            .send(Message {
+                                tags: None,
+                                prefix: Some("You".to_string()),
+                                command: RECEIVED_MESSAGE.to_string(),
+                                params: vec![vec![text_to_print, INFO.to_string()]],
+                            })
+                            .map_err(|_| -> ClientError {
+                                ClientError {
+                                    kind: NONCRITICAL.to_string(),
+                                    message: SEND_MESSAGE.to_string(),
+                                }
+                            })?;
+                    }
+                    RPL_ENDOFBANLIST_NUM => {
+                        if let Some(text_to_print) = no_bans_text(&message, &channels_banning) {
+                            tx_backend
+                                .send(Message {
+                                    tags: None,
+                                    prefix: Some("You".to_string()),
+                                    command: RECEIVED_MESSAGE.to_string(),
+                                    params: vec![vec![text_to_print, INFO.to_string()]],
+                                })
+                                .map_err(|_| -> ClientError {
+                                    ClientError {
+                                        kind: NONCRITICAL.to_string(),
+                                        message: SEND_MESSAGE.to_string(),
+                                    }
+                                })?;
+                        }
+                        channels_banning.clear();
+                    }
+                    RPL_ENDOFEXCEPTLIST_NUM => {}
                     RPL_ENDOFWHOIS_NUM => {}
-                    RPL_ENDOFNAMES_NUM => {}
+                    RPL_ENDOFWHOWAS_NUM => {}
+                    RPL_ENDOFNAMES_NUM => {
+                        if let Some(text_to_print) = names_not_found_text(&message, &channels_named)
+                        {
+                            tx_backend
+                                .send(Message {
+                                    tags: None,
+                                    prefix: None,
+                                    command: RECEIVED_MESSAGE.to_string(),
+                                    params: vec![vec![text_to_print, INFO.to_string()]],
+                                })
+                                .map_err(|_| -> ClientError {
+                                    ClientError {
+                                        kind: NONCRITICAL.to_string(),
+                                        message: SEND_MESSAGE.to_string(),
+                                    }
+                                })?;
+                        }
+                        channels_named.clear();
+                    }
 
                     // Errors
                     ERR_INVALIDLOGIN_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: INVALID_LOGIN.to_string(),
                                 params: vec![],
@@ -323,6 +902,7 @@ pub fn ui_updater(
                     ERR_NICKCOLLISION_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: INVALID_REGISTRATION.to_string(),
                                 params: vec![vec!["Nickname is already in use".to_string()]],
@@ -334,14 +914,39 @@ pub fn ui_updater(
                                 }
                             })?;
                     }
+                    ERR_WEAKPASSWORD_NUM => {
+                        let reason = message
+                            .params
+                            .last()
+                            .map(|reason| reason.join(" "))
+                            .unwrap_or_default();
+                        tx_backend
+                            .send(Message {
+                                tags: None,
+                                prefix: None,
+                                command: INVALID_REGISTRATION.to_string(),
+                                params: vec![vec![reason]],
+                            })
+                            .map_err(|_| -> ClientError {
+                                ClientError {
+                                    kind: NONCRITICAL.to_string(),
+                                    message: SEND_MESSAGE.to_string(),
+                                }
+                            })?;
+                    }
                     ERR_ERRONEUSNICKNAME_NUM => {
+                        // The server says why the nickname can't be used
+                        let reason = message
+                            .params
+                            .last()
+                            .map(|reason| reason.join(" "))
+                            .unwrap_or_default();
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: INVALID_REGISTRATION.to_string(),
-                                params: vec![vec![
-                                    "Nickname cannot start with #, & or :".to_string()
-                                ]],
+                                params: vec![vec![reason]],
                             })
                             .map_err(|_| -> ClientError {
                                 ClientError {
@@ -353,6 +958,7 @@ pub fn ui_updater(
                     ERR_NEEDMOREPARAMS_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: RECEIVED_MESSAGE.to_string(),
                                 params: vec![vec![
@@ -370,6 +976,7 @@ pub fn ui_updater(
                     ERR_PASSWDMISMATCH_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: RECEIVED_MESSAGE.to_string(),
                                 params: vec![vec![
@@ -384,9 +991,28 @@ pub fn ui_updater(
                                 }
                             })?;
                     }
+                    ERR_NOOPERHOST_NUM => {
+                        tx_backend
+                            .send(Message {
+                                tags: None,
+                                prefix: None,
+                                command: RECEIVED_MESSAGE.to_string(),
+                                params: vec![vec![
+                                    ERR_NOOPERHOST_MSG.to_string(),
+                                    ERROR.to_string(),
+                                ]],
+                            })
+                            .map_err(|_| -> ClientError {
+                                ClientError {
+                                    kind: NONCRITICAL.to_string(),
+                                    message: SEND_MESSAGE.to_string(),
+                                }
+                            })?;
+                    }
                     ERR_NOPRIVILEGES_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: RECEIVED_MESSAGE.to_string(),
                                 params: vec![vec![
@@ -401,9 +1027,25 @@ pub fn ui_updater(
                                 }
                             })?;
                     }
+                    ERR_NOMOTD_NUM => {
+                        tx_backend
+                            .send(Message {
+                                tags: None,
+                                prefix: Some("You".to_string()),
+                                command: RECEIVED_MESSAGE.to_string(),
+                                params: vec![vec![ERR_NOMOTD_MSG.to_string(), INFO.to_string()]],
+                            })
+                            .map_err(|_| -> ClientError {
+                                ClientError {
+                                    kind: NONCRITICAL.to_string(),
+                                    message: SEND_MESSAGE.to_string(),
+                                }
+                            })?;
+                    }
                     ERR_NOSUCHSERVER_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: RECEIVED_MESSAGE.to_string(),
                                 params: vec![vec![
@@ -421,6 +1063,7 @@ pub fn ui_updater(
                     ERR_NONICKNAMEGIVEN_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: RECEIVED_MESSAGE.to_string(),
                                 params: vec![vec![
@@ -438,6 +1081,7 @@ pub fn ui_updater(
                     ERR_NOSUCHNICK_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: RECEIVED_MESSAGE.to_string(),
                                 params: vec![vec![
@@ -457,6 +1101,7 @@ pub fn ui_updater(
                     ERR_INVITEONLYCHAN_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: ERROR_CHANNEL.to_string(),
                                 params: vec![vec!["You aren't invited to the channel".to_string()]],
@@ -471,6 +1116,7 @@ pub fn ui_updater(
                     ERR_BADCHANNELKEY_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: ERROR_CHANNEL.to_string(),
                                 params: vec![vec!["The key entered is not valid".to_string()]],
@@ -485,6 +1131,7 @@ pub fn ui_updater(
                     ERR_CHANNELHASKEY_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: ERROR_CHANNEL.to_string(),
                                 params: vec![vec![ERR_CHANNELHASKEY_MSG.to_string()]],
@@ -499,6 +1146,7 @@ pub fn ui_updater(
                     ERR_BANNEDFROMCHAN_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: ERROR_CHANNEL.to_string(),
                                 params: vec![vec!["You are banned from channel".to_string()]],
@@ -513,6 +1161,7 @@ pub fn ui_updater(
                     ERR_CHANNELISFULL_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: ERROR_CHANNEL.to_string(),
                                 params: vec![vec!["Channel is full".to_string()]],
@@ -527,6 +1176,7 @@ pub fn ui_updater(
                     ERR_TOOMANYCHANNELS_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: ERROR_CHANNEL.to_string(),
                                 params: vec![vec![ERR_TOOMANYCHANNELS_MSG.to_string()]],
@@ -538,12 +1188,18 @@ pub fn ui_updater(
                                 }
                             })?;
                     }
-                    ERR_NOSUCHCHANNEL_NUM => {
+                    ERR_TOOMANYTARGETS_NUM => {
+                        let text_to_print = format!(
+                            "Couldn't join {}: {}",
+                            message.params[0][0].clone(),
+                            ERR_TOOMANYTARGETS_MSG
+                        );
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
-                                command: ERROR_CHANNEL.to_string(),
-                                params: vec![vec![ERR_NOSUCHCHANNEL_MSG.to_string()]],
+                                command: RECEIVED_MESSAGE.to_string(),
+                                params: vec![vec![text_to_print, ERROR.to_string()]],
                             })
                             .map_err(|_| -> ClientError {
                                 ClientError {
@@ -552,18 +1208,43 @@ pub fn ui_updater(
                                 }
                             })?;
                     }
-                    ERR_USERONCHANNEL_NUM => {
-                        send_custom_error_to_channel(
-                            message,
-                            &tx_backend,
-                            ERR_USERONCHANNEL_MSG.to_string(),
-                        );
+                    ERR_NOSUCHCHANNEL_NUM => {
+                        tx_backend
+                            .send(Message {
+                                tags: None,
+                                prefix: None,
+                                command: ERROR_CHANNEL.to_string(),
+                                params: vec![vec![ERR_NOSUCHCHANNEL_MSG.to_string()]],
+                            })
+                            .map_err(|_| -> ClientError {
+                                ClientError {
+                                    kind: NONCRITICAL.to_string(),
+                                    message: SEND_MESSAGE.to_string(),
+                                }
+                            })?;
+                    }
+                    ERR_USERONCHANNEL_NUM => {
+                        send_custom_error_to_channel(
+                            message,
+                            &tx_backend,
+                            ERR_USERONCHANNEL_MSG.to_string(),
+                            &users_online,
+                        );
                     }
                     ERR_CHANOPRIVSNEEDED_NUM => {
                         send_custom_error_to_channel(
                             message,
                             &tx_backend,
                             ERR_CHANOPRIVSNEEDED_MSG.to_string(),
+                            &users_online,
+                        );
+                    }
+                    ERR_ALREADYFOUNDED_NUM => {
+                        send_custom_error_to_channel(
+                            message,
+                            &tx_backend,
+                            ERR_ALREADYFOUNDED_MSG.to_string(),
+                            &users_online,
                         );
                     }
                     ERR_UNKNOWNMODE_NUM => {
@@ -573,6 +1254,7 @@ pub fn ui_updater(
                         );
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: RECEIVED_MESSAGE.to_string(),
                                 params: vec![vec![text_to_print, ERROR.to_string()]],
@@ -584,9 +1266,31 @@ pub fn ui_updater(
                                 }
                             })?;
                     }
+                    ERR_UMODEUNKNOWNFLAG_NUM | ERR_USERSDONTMATCH_NUM => {
+                        let text_to_print = match message.command.as_str() {
+                            ERR_UMODEUNKNOWNFLAG_NUM => {
+                                "That is not a valid mode for users, allowed: +/-i"
+                            }
+                            _ => ERR_USERSDONTMATCH_MSG,
+                        };
+                        tx_backend
+                            .send(Message {
+                                tags: None,
+                                prefix: None,
+                                command: RECEIVED_MESSAGE.to_string(),
+                                params: vec![vec![text_to_print.to_string(), ERROR.to_string()]],
+                            })
+                            .map_err(|_| -> ClientError {
+                                ClientError {
+                                    kind: NONCRITICAL.to_string(),
+                                    message: SEND_MESSAGE.to_string(),
+                                }
+                            })?;
+                    }
                     ERR_KEYSET_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: RECEIVED_MESSAGE.to_string(),
                                 params: vec![vec![ERR_KEYSET_MSG.to_string(), ERROR.to_string()]],
@@ -598,13 +1302,14 @@ pub fn ui_updater(
                                 }
                             })?;
                     }
-                    ERR_NOTONCHANNEL_NUM => {
+                    ERR_BANLISTFULL_NUM => {
                         tx_backend
                             .send(Message {
+                                tags: None,
                                 prefix: None,
                                 command: RECEIVED_MESSAGE.to_string(),
                                 params: vec![vec![
-                                    ERR_NOTONCHANNEL_MSG.to_string(),
+                                    ERR_BANLISTFULL_MSG.to_string(),
                                     ERROR.to_string(),
                                 ]],
                             })
@@ -615,13 +1320,89 @@ pub fn ui_updater(
                                 }
                             })?;
                     }
+                    ERR_NOTONCHANNEL_NUM => {
+                        send_custom_error_to_channel(
+                            message,
+                            &tx_backend,
+                            ERR_NOTONCHANNEL_MSG.to_string(),
+                            &users_online,
+                        );
+                    }
+                    ERR_CANNOTSENDTOCHAN_NUM => {
+                        send_custom_error_to_channel(
+                            message,
+                            &tx_backend,
+                            ERR_CANNOTSENDTOCHAN_MSG.to_string(),
+                            &users_online,
+                        );
+                    }
+                    ERR_CHANOPEN_NUM => {
+                        send_custom_error_to_channel(
+                            message,
+                            &tx_backend,
+                            format!("{}, you can join it without knocking", ERR_CHANOPEN_MSG),
+                            &users_online,
+                        );
+                    }
+                    ERR_KNOCKONCHAN_NUM => {
+                        send_custom_error_to_channel(
+                            message,
+                            &tx_backend,
+                            ERR_KNOCKONCHAN_MSG.to_string(),
+                            &users_online,
+                        );
+                    }
+                    ERR_MESSAGETOOLONG_NUM => {
+                        let text_to_print = format!(
+                            "The message wasn't sent, the channel allows up to {} characters",
+                            message.params[1][0]
+                        );
+                        tx_backend
+                            .send(Message {
+                                tags: None,
+                                prefix: Some(message.params[0][0].clone()),
+                                command: RECEIVED_MESSAGE.to_string(),
+                                params: vec![vec![text_to_print, ERROR.to_string()]],
+                            })
+                            .map_err(|_| -> ClientError {
+                                ClientError {
+                                    kind: NONCRITICAL.to_string(),
+                                    message: SEND_MESSAGE.to_string(),
+                                }
+                            })?;
+                    }
 
-                    &_ => println!("Undefined message received by the server"),
+                    // Numerics without a handler are shown as they come, so they aren't lost
+                    &_ => match unhandled_numeric_text(&message) {
+                        Some((text_to_print, message_type)) => {
+                            tx_backend
+                                .send(Message {
+                                    tags: None,
+                                    prefix: None,
+                                    command: RECEIVED_MESSAGE.to_string(),
+                                    params: vec![vec![text_to_print, message_type.to_string()]],
+                                })
+                                .map_err(|_| -> ClientError {
+                                    ClientError {
+                                        kind: NONCRITICAL.to_string(),
+                                        message: SEND_MESSAGE.to_string(),
+                                    }
+                                })?;
+                        }
+                        None => println!("Undefined message received by the server"),
+                    },
                 }
                 data.clear();
             }
             true
         }
+        // Nothing arrived in time, the part of a line already read is kept in data
+        Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {
+            for (chat, text_to_print) in events_summary.take_if_due(Instant::now()) {
+                send_membership_event(chat, text_to_print, &tx_backend);
+            }
+            true
+        }
         Err(e) => {
             println!("Error reading {}", e);
             false
@@ -631,9 +1412,90 @@ pub fn ui_updater(
     Ok(())
 }
 
+///
+/// Returns the text of the replies that welcome the user after the login or the registration,
+/// the creation time of the server is shown in UTC. None if the reply is incomplete
+///
+pub fn welcome_text(message: &Message) -> Option<String> {
+    match message.command.as_str() {
+        RPL_CREATED_NUM => {
+            let created_at = message.params.get(1)?.first()?.parse::<u64>().ok()?;
+            Some(format!(
+                "{} {} UTC",
+                message.params.last()?.first()?,
+                timestamp_text(created_at)
+            ))
+        }
+        RPL_MYINFO_NUM => Some(format!(
+            "{} runs {}, user modes {}, channel modes {}",
+            message.params.get(1)?.first()?,
+            message.params.get(2)?.first()?,
+            message.params.get(3)?.first()?,
+            message.params.get(4)?.first()?
+        )),
+        _ => message.params.last()?.first().cloned(),
+    }
+}
+
+///
+/// Returns the text to show for a numeric reply the client doesn't handle, and the type
+/// of message to show it with. Replies in the 400-599 range are errors.
+/// Returns None if the message is not a numeric reply.
+///
+pub fn unhandled_numeric_text(message: &Message) -> Option<(String, &'static str)> {
+    let number = message.command.parse::<u16>().ok()?;
+    let params: Vec<String> = message.params.concat();
+
+    let mut text = format!("Server: {}", message.command);
+    if !params.is_empty() {
+        text = format!("{} {}", text, params.join(" "));
+    }
+    let message_type = match number {
+        400..=599 => ERROR,
+        _ => INFO,
+    };
+    Some((text, message_type))
+}
+
+///
+/// Returns the message received from the server as a single line, showing
+/// the prefix, the command and every group of parameters between brackets
+///
+pub fn raw_message_line(message: &Message) -> String {
+    let mut line = match &message.prefix {
+        Some(prefix) => format!(":{} {}", prefix, message.command),
+        None => message.command.clone(),
+    };
+    for param_list in &message.params {
+        line.push_str(&format!(" [{}]", param_list.join(", ")));
+    }
+    line
+}
+
+///
+/// Answers a PING from the server with a PONG that has the same parameters,
+/// so the server knows the client is still connected
+///
+fn answer_ping(message: Message, stream: &mut TcpStream) -> Result<(), ClientError> {
+    let pong = Message {
+        tags: None,
+        prefix: None,
+        command: PONG.to_string(),
+        params: message.params,
+    };
+    stream
+        .write_all(pong.as_string().as_bytes())
+        .map_err(|_| -> ClientError {
+            ClientError {
+                kind: NONCRITICAL.to_string(),
+                message: SEND_MESSAGE.to_string(),
+            }
+        })
+}
+
 fn parse_message(
     message: Message,
-    tx_backend: &gtk::glib::Sender<Message>,
+    tx_backend: &impl Frontend,
     users: &[String],
 ) -> Result<(), ClientError> {
     println!("Parseo mensaje -> {:?}", message);
@@ -657,6 +1519,7 @@ fn parse_message(
                     // If the user is not in the list of users, we add it
                     tx_backend
                         .send(Message {
+                            tags: None,
                             prefix: Some(user_nick),
                             command: ADD_LIST_CHATS.to_string(),
                             params: vec![vec![]],
@@ -670,11 +1533,28 @@ fn parse_message(
                         .ok();
                 }
                 if message.command == *DCC_CHAT {
-                    let aux = format!("{} Sent you an invitation to start a DCC chat.\r\n Do you accept? \r\n (Time to accept: 10 seconds)", prefix.expect("No prefix in message"));
-                    message.params.push(vec![aux]);
+                    let encrypted = matches!(
+                        message.params.get(3).and_then(|param| param.first()),
+                        Some(param) if param == DCC_ENCRYPTED
+                    );
+                    let aux = match encrypted {
+                        true => format!("{} Sent you an invitation to start an encrypted DCC chat.\r\n Write the passphrase you agreed on to accept. \r\n (Time to accept: 10 seconds)", prefix.expect("No prefix in message")),
+                        false => format!("{} Sent you an invitation to start a DCC chat.\r\n Do you accept? \r\n (Time to accept: 10 seconds)", prefix.expect("No prefix in message")),
+                    };
+                    // The encryption mark, if the offer has it, is left after the text
+                    message
+                        .params
+                        .insert(3.min(message.params.len()), vec![aux]);
                 } else if message.command == *DCC_SEND {
-                    let aux = format!("{} wants to send you the file {} with a total weight of {} bytes.\r\n Do you accept?", prefix.expect("No prefix in message"), message.params[0][0], message.params[3][0]);
-                    message.params.push(vec![aux]);
+                    let size = match message.params[3][0].parse::<u64>() {
+                        Ok(size) => file_size_text(size),
+                        Err(_) => format!("{} bytes", message.params[3][0]),
+                    };
+                    let aux = format!("{} wants to send you the file {} with a total weight of {}.\r\n Do you accept?", prefix.expect("No prefix in message"), message.params[0][0], size);
+                    // The checksum, if the sender sent it, is left after the text
+                    message
+                        .params
+                        .insert(4.min(message.params.len()), vec![aux]);
                 } else if message.command == *DCC_RESUME {
                     let aux = format!(
                         "{} wants to continue the transfer of the file {}.\r\n Do you accept?",
@@ -706,7 +1586,7 @@ fn parse_message(
 /// If a user is not in the list of users online, it sends a ADD_LIST_CHATS command to the main thread to add it to the list
 /// If the user is in the list of users online or is a message from channel, it sends a RECEIVED_MESSAGE command to the main thread to update the UI
 ///
-fn private_message(message: Message, tx_backend: &gtk::glib::Sender<Message>, users: &[String]) {
+fn private_message(message: Message, tx_backend: &impl Frontend, users: &[String]) {
     let user_nick = message.prefix.expect("No prefix in message");
     let message_receiver = message.params[0][0].clone();
 
@@ -726,6 +1606,7 @@ fn private_message(message: Message, tx_backend: &gtk::glib::Sender<Message>, us
         if !users.contains(&user_nick) {
             tx_backend
                 .send(Message {
+                    tags: None,
                     prefix: Some(user_nick.clone()),
                     command: ADD_LIST_CHATS.to_string(),
                     params: vec![vec![]],
@@ -741,9 +1622,15 @@ fn private_message(message: Message, tx_backend: &gtk::glib::Sender<Message>, us
         user_nick.clone()
     };
 
-    let message_to_print = format!("{}: {}", user_nick, message.params[1][0].clone());
+    let text = &message.params[1][0];
+    let message_to_print = match action_text(text) {
+        Some(action) => format_action(&user_nick, action),
+        None => format!("{}: {}", user_nick, text),
+    };
+    // The tags go with the text, so the time the server received it is shown if it was told
     tx_backend
         .send(Message {
+            tags: message.tags.clone(),
             prefix: Some(prefix),
             command: RECEIVED_MESSAGE.to_string(),
             params: vec![vec![message_to_print, PRIVATE_MESSAGE.to_string()]],
@@ -757,11 +1644,82 @@ fn private_message(message: Message, tx_backend: &gtk::glib::Sender<Message>, us
         .ok();
 }
 
+///
+/// Returns true if the message was sent by a user of the ignored list, which has the nicknames
+/// in lowercase
+///
+pub fn is_from_ignored_user(message: &Message, ignored_users: &HashSet<String>) -> bool {
+    match &message.prefix {
+        Some(nickname) => ignored_users.contains(&irc_lowercase(nickname)),
+        None => false,
+    }
+}
+
+///
+/// Returns the text shown by /ignore without a nickname, with the users ignored in order
+///
+pub fn ignored_users_text(ignored_users: &HashSet<String>) -> String {
+    let mut nicknames: Vec<&String> = ignored_users.iter().collect();
+    nicknames.sort();
+    match nicknames.is_empty() {
+        true => "You are not ignoring anyone".to_string(),
+        false => format!(
+            "Ignoring: {}",
+            nicknames
+                .iter()
+                .map(|nickname| nickname.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        ),
+    }
+}
+
+///
+/// Returns the text shown when the server closes the connection, or None if it didn't say why
+///
+pub fn server_quit_text(message: &Message) -> Option<String> {
+    match message.params.first().and_then(|param| param.first()) {
+        Some(reason) if !reason.is_empty() => {
+            Some(format!("The server closed the connection: {}", reason))
+        }
+        _ => None,
+    }
+}
+
+///
+/// Returns the text of a CTCP ACTION, or None if the message is not an action
+///
+pub fn action_text(text: &str) -> Option<&str> {
+    let ctcp = text
+        .strip_prefix(CTCP_DELIMITER)?
+        .strip_suffix(CTCP_DELIMITER)?;
+    match ctcp.split_once(' ') {
+        Some((CTCP_ACTION, action)) => Some(action),
+        _ => None,
+    }
+}
+
+///
+/// Returns how an action is shown in the chat, like "* ari waves"
+///
+pub fn format_action(nickname: &str, action: &str) -> String {
+    format!("* {} {}", nickname, action)
+}
+
+///
+/// Returns the state of a CTCP TYPING sent by a user in a NOTICE, or None if it is not one
+///
+pub fn typing_notification(message: &Message) -> Option<&str> {
+    message.prefix.as_ref()?;
+    let text = message.params.get(1)?.first()?;
+    typing_state(text)
+}
+
 ///
 /// The client receives a NAMES from the server
 /// It sends a ADD_LIST_CHATS command to the main thread to add the users to the list
 ///
-fn names(message: Message, tx_backend: &gtk::glib::Sender<Message>, users: &[String]) {
+fn names(message: Message, tx_backend: &impl Frontend, users: &[String]) {
     if !message.params.is_empty() {
         for user in message.params[0].clone() {
             if users.contains(&user) {
@@ -769,6 +1727,7 @@ fn names(message: Message, tx_backend: &gtk::glib::Sender<Message>, users: &[Str
             }
             tx_backend
                 .send(Message {
+                    tags: None,
                     prefix: Some(user.clone()),
                     command: ADD_LIST_CHATS.to_string(),
                     params: vec![vec![]],
@@ -788,7 +1747,7 @@ fn names(message: Message, tx_backend: &gtk::glib::Sender<Message>, users: &[Str
 /// The client receives a RPL_INVITING from the server
 /// Sends a RECEIVED_MESSAGE command to the main thread to let the user know that it has been invited to a channel
 ///
-fn invite_success(message: Message, tx_backend: &gtk::glib::Sender<Message>) {
+fn invite_success(message: Message, tx_backend: &impl Frontend) {
     let channel = message.params[0][0].clone();
     let user = message.params[1][0].clone();
     let text_to_print = format!(
@@ -797,6 +1756,7 @@ fn invite_success(message: Message, tx_backend: &gtk::glib::Sender<Message>) {
     );
     tx_backend
         .send(Message {
+            tags: None,
             prefix: Some(channel),
             command: RECEIVED_MESSAGE.to_string(),
             params: vec![vec![text_to_print, INFO.to_string()]],
@@ -811,17 +1771,62 @@ fn invite_success(message: Message, tx_backend: &gtk::glib::Sender<Message>) {
 }
 
 ///
-/// The client receives a RPL_TOPIC from the server
+/// Format used when a user changes the topic of a channel
+///
+pub const TOPIC_CHANGE_FORMAT: &str = "{nickname} changed the topic to: {topic}";
+
+///
+/// Format used when the topic is received after joining or asking for it
+///
+pub const TOPIC_QUERY_FORMAT: &str = "Topic is: {topic}";
+
+///
+/// Returns the text to show for a topic. If the nickname of the user that set it is
+/// given then it's a topic change, else it's the reply to a join or a query.
+///
+pub fn topic_text(topic: &str, nickname_setter: Option<&str>) -> String {
+    match nickname_setter {
+        Some(nickname) => TOPIC_CHANGE_FORMAT
+            .replace("{nickname}", nickname)
+            .replace("{topic}", topic),
+        None => TOPIC_QUERY_FORMAT.replace("{topic}", topic),
+    }
+}
+
+///
+/// Returns the text of a RPL_TOPICWHOTIME, like "Topic set by ari at 2022-12-16 23:49:27 UTC",
+/// or of a RPL_CREATIONTIME. None if the time is not a number
+///
+pub fn topic_details_text(message: &Message) -> Option<String> {
+    let time = message.params.last()?.first()?.parse::<u64>().ok()?;
+    let time = format!("{} UTC", timestamp_text(time));
+    match message.command.as_str() {
+        RPL_TOPICWHOTIME_NUM => Some(format!(
+            "Topic set by {} at {}",
+            message.params.get(1)?.first()?,
+            time
+        )),
+        _ => Some(format!("Channel created at {}", time)),
+    }
+}
+
+///
+/// The client receives a RPL_TOPIC, RPL_NOTOPIC, RPL_TOPICWHOTIME, RPL_CREATIONTIME or a
+/// TOPIC change from the server
 /// Try to add the channel to the list of chats, if it already exists, it will not be added
 /// Sends a RECEIVED_MESSAGE command to the main thread to update the topic of the channel
 ///
-fn topic_message(message: Message, tx_backend: &gtk::glib::Sender<Message>) {
+fn topic_message(message: Message, tx_backend: &impl Frontend) {
     let channel = message.params[0][0].clone();
-    let topic = message.params[1][0].clone();
+    let topic = match message.params.last() {
+        Some(topic) => topic.join(","),
+        None => return,
+    };
 
     // We don't know if the channel is in the list of channels, so we try to add it
     tx_backend
         .send(Message {
+            tags: None,
             prefix: Some(channel.clone()),
             command: ADD_LIST_CHATS.to_string(),
             params: vec![vec![]],
@@ -834,9 +1839,18 @@ fn topic_message(message: Message, tx_backend: &gtk::glib::Sender<Message>) {
         })
         .ok();
 
-    let text_to_print = format!("Channel topic: {}", topic);
+    let text_to_print = match message.command.as_str() {
+        RPL_NOTOPIC_NUM => topic,
+        RPL_TOPICWHOTIME_NUM | RPL_CREATIONTIME_NUM => match topic_details_text(&message) {
+            Some(text) => text,
+            None => return,
+        },
+        TOPIC => topic_text(&topic, message.prefix.as_deref()),
+        _ => topic_text(&topic, None),
+    };
     tx_backend
         .send(Message {
+            tags: None,
             prefix: Some(channel),
             command: RECEIVED_MESSAGE.to_string(),
             params: vec![vec![text_to_print, INFO.to_string()]],
@@ -851,28 +1865,17 @@ fn topic_message(message: Message, tx_backend: &gtk::glib::Sender<Message>) {
 }
 
 ///
-/// The client receives a RPL_NAMEREPLY from the server
+/// The client receives a RPL_NAMEREPLY from the server, with the visibility of the channel before its name.
 /// It filters the current user and sends a RECEIVED_MESSAGE command to the main thread to print the list of users online
 ///
-fn show_participants(message: Message, tx_backend: &gtk::glib::Sender<Message>) {
+fn show_participants(message: Message, tx_backend: &impl Frontend) {
     let this_user = message.params[0][0].clone();
-    let channel = message.params[1][0].clone();
-    let users = message.params[2][0].split(' ').collect::<Vec<&str>>();
-
-    // Filter this user
-    let users_without_this_user = users
-        .iter()
-        .filter(|user| **user != this_user)
-        .copied()
-        .collect::<Vec<&str>>();
-
-    let text_to_print = match users_without_this_user.is_empty() {
-        true => "No one is here yet!".to_string(),
-        false => format!("Users in channel: {}", users_without_this_user.join(", ")),
-    };
+    let channel = message.params[2][0].clone();
+    let text_to_print = participants_text(&this_user, &message.params[3][0]);
 
     tx_backend
         .send(Message {
+            tags: None,
             prefix: Some(channel),
             command: RECEIVED_MESSAGE.to_string(),
             params: vec![vec![text_to_print, INFO.to_string()]],
@@ -886,15 +1889,160 @@ fn show_participants(message: Message, tx_backend: &gtk::glib::Sender<Message>)
         .ok();
 }
 
+///
+/// Updates the users of the channels with the NAMES replies, joins, parts and quits received.
+/// The users that send messages to a channel are known to be in it too. Returns the channels
+/// a user that quit was in
+///
+pub fn track_channel_members(
+    message: &Message,
+    channel_members: &mut ChannelMembers,
+) -> Vec<String> {
+    let param = |index: usize| message.params.get(index).and_then(|param| param.first());
+
+    match (message.command.as_str(), message.prefix.as_deref()) {
+        (RPL_NAMEREPLY_NUM, _) => {
+            if let (Some(channel), Some(names)) = (param(2), param(3)) {
+                let nicknames = names
+                    .split(' ')
+                    .filter(|name| !name.is_empty())
+                    .map(|name| {
+                        name.trim_start_matches([RPL_NAMEREPLY_OPERATOR, RPL_NAMEREPLY_VOICED])
+                            .to_string()
+                    })
+                    .collect();
+                channel_members.set(channel, nicknames);
+            }
+        }
+        (JOIN, Some(nickname)) => {
+            if let Some(channel) = param(0) {
+                channel_members.add(channel, nickname);
+            }
+        }
+        (PRIVMSG, Some(nickname)) => {
+            if let Some(channel) =
+                param(0).filter(|receiver| receiver.starts_with('#') || receiver.starts_with('&'))
+            {
+                channel_members.add(channel, nickname);
+            }
+        }
+        (PART, Some(nickname)) => {
+            if let Some(channel) = param(0) {
+                channel_members.remove(channel, nickname);
+            }
+        }
+        (QUIT, Some(nickname)) => return channel_members.remove_from_all(nickname),
+        _ => {}
+    }
+    vec![]
+}
+
+///
+/// Returns the text that lists the users in a channel, except the user of the client.
+/// Operators and users with voice come with their prefix in the reply and are shown as such
+///
+pub fn participants_text(this_user: &str, names: &str) -> String {
+    let participants = names
+        .split(' ')
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            if let Some(operator) = name.strip_prefix(RPL_NAMEREPLY_OPERATOR) {
+                (operator, format!("{} (operator)", operator))
+            } else if let Some(voiced) = name.strip_prefix(RPL_NAMEREPLY_VOICED) {
+                (voiced, format!("{} (voice)", voiced))
+            } else {
+                (name, name.to_string())
+            }
+        })
+        .filter(|(nickname, _)| *nickname != this_user)
+        .map(|(_, text)| text)
+        .collect::<Vec<String>>();
+
+    match participants.is_empty() {
+        true => "No one is here yet!".to_string(),
+        false => format!("Users in channel: {}", participants.join(", ")),
+    }
+}
+
+///
+/// Returns the text shown when the NAMES of a channel end without any RPL_NAMEREPLY of it,
+/// because the channel doesn't exist or no one can be seen in it. The end of the NAMES of
+/// every channel, without the name of a channel, is not shown
+///
+pub fn names_not_found_text(message: &Message, channels_named: &[String]) -> Option<String> {
+    if message.params.len() < 2 {
+        return None;
+    }
+    let channel = message.params.first()?.first()?;
+
+    match channels_named
+        .iter()
+        .any(|named| parser::irc_eq(named, channel))
+    {
+        true => None,
+        false => Some("Channel not found or empty".to_string()),
+    }
+}
+
+///
+/// Returns the text shown when the bans of a channel end without any RPL_BANLIST of it, so
+/// asking for the bans always has an answer
+///
+pub fn no_bans_text(message: &Message, channels_banning: &[String]) -> Option<String> {
+    let channel = message.params.first()?.first()?;
+
+    match channels_banning
+        .iter()
+        .any(|banning| parser::irc_eq(banning, channel))
+    {
+        true => None,
+        false => Some(format!("{} has no bans", channel)),
+    }
+}
+
+///
+/// Returns the text shown for a user found with WHO. The members of a channel have its prefix
+/// in the channel, and it is told if they are away or connected to another server
+///
+pub fn who_reply_label(message: &Message) -> String {
+    let nickname = message.params[0][0].clone();
+    if message.params.len() < 7 {
+        return nickname;
+    }
+    let flags = &message.params[5][0];
+    let hopcount = message.params[6][0].split(' ').next().unwrap_or("0");
+
+    let mut label = String::new();
+    if flags.contains(RPL_NAMEREPLY_OPERATOR) {
+        label.push(RPL_NAMEREPLY_OPERATOR);
+    } else if flags.contains(RPL_NAMEREPLY_VOICED) {
+        label.push(RPL_NAMEREPLY_VOICED);
+    }
+    label.push_str(&nickname);
+
+    let mut details = vec![];
+    if flags.starts_with(RPL_WHOREPLY_GONE) {
+        details.push("away");
+    }
+    if hopcount != "0" {
+        details.push("on another server");
+    }
+    match details.is_empty() {
+        true => label,
+        false => format!("{} ({})", label, details.join(", ")),
+    }
+}
+
 ///
 /// The client received a message from user in away mode
 ///
-fn user_away(message: Message, tx_backend: &gtk::glib::Sender<Message>) {
+fn user_away(message: Message, tx_backend: &impl Frontend) {
     let user_away = message.params[0][0].clone();
     let message_away = message.params[1][0].clone();
     let message_to_print = format!("{} is away '{}'", user_away, message_away);
     tx_backend
         .send(Message {
+            tags: None,
             prefix: Some(user_away),
             command: RECEIVED_MESSAGE.to_string(),
             params: vec![vec![message_to_print, INFO.to_string()]],
@@ -913,18 +2061,15 @@ fn user_away(message: Message, tx_backend: &gtk::glib::Sender<Message>) {
 ///
 fn send_custom_error_to_channel(
     message: Message,
-    tx_backend: &gtk::glib::Sender<Message>,
+    tx_backend: &impl Frontend,
     message_to_print: String,
+    chats: &[String],
 ) {
-    let channel = message.params[0][0].clone();
-    let mut text_to_print = message_to_print.to_string();
-    if message.params_total_count() >= 3 {
-        let user = message.params[1][0].clone();
-        text_to_print = format!("{} {}", user, message_to_print);
-    }
+    let (chat, text_to_print) = channel_error_chat_and_text(&message, &message_to_print, chats);
     tx_backend
         .send(Message {
-            prefix: Some(channel),
+            tags: None,
+            prefix: chat,
             command: RECEIVED_MESSAGE.to_string(),
             params: vec![vec![text_to_print, ERROR.to_string()]],
         })
@@ -937,15 +2082,44 @@ fn send_custom_error_to_channel(
         .ok();
 }
 
+///
+/// Returns the chat where an error about a channel is shown and its text, with the name of the
+/// channel. The chat is the one of the channel, or None to show it in the current chat if the
+/// user doesn't have the channel open, like when writing to a channel without joining it
+///
+pub fn channel_error_chat_and_text(
+    message: &Message,
+    error: &str,
+    chats: &[String],
+) -> (Option<String>, String) {
+    let params: Vec<&String> = message.params.iter().flatten().collect();
+    let channel = match params
+        .iter()
+        .find(|param| param.starts_with('#') || param.starts_with('&'))
+    {
+        Some(channel) => channel.to_string(),
+        None => return (None, error.to_string()),
+    };
+
+    // The errors about another user of the channel have its nickname after the channel
+    let text_to_print = match params.get(1) {
+        Some(user) if params.len() >= 3 && **user != channel => format!("{} {}", user, error),
+        _ => error.to_string(),
+    };
+    let chat = chats.iter().find(|chat| irc_eq(chat, &channel)).cloned();
+    (chat, format!("{}: {}", channel, text_to_print))
+}
+
 ///
 /// Sends a RECEIVED_MESSAGE command to the main thread to let the user know taht it has been kicked
 /// Sends a KICK_CHANNEL command to the main thread to remove the channel from the list of channels
 ///
-fn kick(message: Message, tx_backend: &gtk::glib::Sender<Message>) {
+fn kick(message: Message, tx_backend: &impl Frontend) {
     let channel = message.params[0][0].clone();
-    let text_to_print = format!("You have been kicked from the channel {}", channel);
+    let text_to_print = kick_text(&message);
     tx_backend
         .send(Message {
+            tags: None,
             prefix: Some("You".to_string()),
             command: RECEIVED_MESSAGE.to_string(),
             params: vec![vec![text_to_print, INFO.to_string()]],
@@ -959,6 +2133,7 @@ fn kick(message: Message, tx_backend: &gtk::glib::Sender<Message>) {
         .ok();
     tx_backend
         .send(Message {
+            tags: None,
             prefix: None,
             command: KICK_CHANNEL.to_string(),
             params: vec![vec![channel]],
@@ -973,21 +2148,23 @@ fn kick(message: Message, tx_backend: &gtk::glib::Sender<Message>) {
 }
 
 ///
-/// The client receives a RPL_WHOISUSER from the server
-/// Creates the text and sends a RECEIVED_MESSAGE command to the main thread to print it
+/// Sends a RECEIVED_MESSAGE command to the main thread to let the user know that a channel was closed
+/// Sends a KICK_CHANNEL command to the main thread to remove the channel from the list of channels
 ///
-fn whoisuser(message: Message, tx_backend: &gtk::glib::Sender<Message>) {
-    let nickname = message.params[0][0].clone();
-    let username = message.params[1][0].clone();
-    let server_ip = message.params[2][0].clone();
-    let realname = message.params[4][0].clone();
-    let text_to_print = format!(
-        "{}, has username '{}' and realname '{}'.",
-        nickname, username, realname
+fn close(message: Message, tx_backend: &impl Frontend) {
+    let channel = message.params[0][0].clone();
+    let mut text_to_print = format!(
+        "The channel {} was closed by {}",
+        channel,
+        message.prefix.unwrap_or_default()
     );
+    if let Some(comment) = message.params.get(1).and_then(|param| param.first()) {
+        text_to_print = format!("{}: {}", text_to_print, comment);
+    }
     tx_backend
         .send(Message {
-            prefix: None,
+            tags: None,
+            prefix: Some("You".to_string()),
             command: RECEIVED_MESSAGE.to_string(),
             params: vec![vec![text_to_print, INFO.to_string()]],
         })
@@ -998,12 +2175,12 @@ fn whoisuser(message: Message, tx_backend: &gtk::glib::Sender<Message>) {
             }
         })
         .ok();
-    let text_to_print = format!("The host is: {}.", server_ip);
     tx_backend
         .send(Message {
+            tags: None,
             prefix: None,
-            command: RECEIVED_MESSAGE.to_string(),
-            params: vec![vec![text_to_print, INFO.to_string()]],
+            command: KICK_CHANNEL.to_string(),
+            params: vec![vec![channel]],
         })
         .map_err(|_| -> ClientError {
             ClientError {
@@ -1013,3 +2190,1486 @@ fn whoisuser(message: Message, tx_backend: &gtk::glib::Sender<Message>) {
         })
         .ok();
 }
+
+///
+/// The client receives a RPL_WHOISUSER from the server
+/// Creates the text and sends a RECEIVED_MESSAGE command to the main thread to print it
+///
+fn whoisuser(message: Message, tx_backend: &impl Frontend) {
+    let nickname = message.params[0][0].clone();
+    let username = message.params[1][0].clone();
+    let server_ip = message.params[2][0].clone();
+    let realname = message.params[4][0].clone();
+    let text_to_print = format!(
+        "{}, has username '{}' and realname '{}'.",
+        nickname, username, realname
+    );
+    tx_backend
+        .send(Message {
+            tags: None,
+            prefix: None,
+            command: RECEIVED_MESSAGE.to_string(),
+            params: vec![vec![text_to_print, INFO.to_string()]],
+        })
+        .map_err(|_| -> ClientError {
+            ClientError {
+                kind: NONCRITICAL.to_string(),
+                message: SEND_MESSAGE.to_string(),
+            }
+        })
+        .ok();
+    let text_to_print = format!("The host is: {}.", server_ip);
+    tx_backend
+        .send(Message {
+            tags: None,
+            prefix: None,
+            command: RECEIVED_MESSAGE.to_string(),
+            params: vec![vec![text_to_print, INFO.to_string()]],
+        })
+        .map_err(|_| -> ClientError {
+            ClientError {
+                kind: NONCRITICAL.to_string(),
+                message: SEND_MESSAGE.to_string(),
+            }
+        })
+        .ok();
+}
+
+///
+/// The client receives a RPL_WHOISIDLE from the server
+/// Shows for how long the user has been idle and connected
+///
+fn whoisidle(message: Message, tx_backend: &impl Frontend) {
+    let time = |index: usize| {
+        message
+            .params
+            .get(index)
+            .and_then(|param| param.first())
+            .and_then(|time| time.parse::<u64>().ok())
+    };
+    let (idle, signon) = match (time(1), time(2)) {
+        (Some(idle), Some(signon)) => (idle, signon),
+        _ => return,
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0);
+    let nickname = &message.params[0][0];
+    for text_to_print in [
+        idle_text(nickname, idle),
+        connected_for_text(nickname, signon, now),
+    ] {
+        tx_backend
+            .send(Message {
+                tags: None,
+                prefix: None,
+                command: RECEIVED_MESSAGE.to_string(),
+                params: vec![vec![text_to_print, INFO.to_string()]],
+            })
+            .map_err(|_| -> ClientError {
+                ClientError {
+                    kind: NONCRITICAL.to_string(),
+                    message: SEND_MESSAGE.to_string(),
+                }
+            })
+            .ok();
+    }
+}
+
+///
+/// Returns the text showing for how long a user hasn't sent messages, like "idle for 5m 3s"
+///
+pub fn idle_text(nickname: &str, idle_seconds: u64) -> String {
+    format!(
+        "{} has been idle for {}m {}s.",
+        nickname,
+        idle_seconds / 60,
+        idle_seconds % 60
+    )
+}
+
+///
+/// Returns the text showing for how long a user has been connected, like "connected for 2h 5m".
+/// Both times are seconds since epoch.
+///
+pub fn connected_for_text(nickname: &str, signon: u64, now: u64) -> String {
+    let connected_minutes = now.saturating_sub(signon) / 60;
+    format!(
+        "{} has been connected for {}h {}m.",
+        nickname,
+        connected_minutes / 60,
+        connected_minutes % 60
+    )
+}
+
+///
+/// The client receives a JOIN, PART or QUIT from another user. Depending on the mode
+/// chosen it is shown in the chat, ignored, or counted to be shown later in a summary
+///
+fn membership_event(
+    message: Message,
+    mode: &str,
+    events_summary: &mut EventsSummary,
+    tx_backend: &impl Frontend,
+) {
+    if let Some((chat, text_to_print)) = membership_event_text(&message) {
+        show_membership_event(
+            chat,
+            text_to_print,
+            &message.command,
+            mode,
+            events_summary,
+            tx_backend,
+        );
+    }
+}
+
+///
+/// Shows the event in the chat, ignores it, or counts it for the summary, depending on the mode
+///
+fn show_membership_event(
+    chat: String,
+    text_to_print: String,
+    command: &str,
+    mode: &str,
+    events_summary: &mut EventsSummary,
+    tx_backend: &impl Frontend,
+) {
+    match mode {
+        EVENTS_HIDDEN => {}
+        EVENTS_COLLAPSED => events_summary.add(&chat, command, Instant::now()),
+        _ => send_membership_event(chat, text_to_print, tx_backend),
+    }
+}
+
+///
+/// Sends a RECEIVED_MESSAGE command to the main thread to show the event in the chat
+///
+fn send_membership_event(chat: String, text_to_print: String, tx_backend: &impl Frontend) {
+    tx_backend
+        .send(Message {
+            tags: None,
+            prefix: Some(chat),
+            command: RECEIVED_MESSAGE.to_string(),
+            params: vec![vec![text_to_print, INFO.to_string()]],
+        })
+        .map_err(|_| -> ClientError {
+            ClientError {
+                kind: NONCRITICAL.to_string(),
+                message: SEND_MESSAGE.to_string(),
+            }
+        })
+        .ok();
+}
+
+///
+/// Returns the chat where the event must be shown and its text. Joins and parts are
+/// shown in their channel, quits don't say the channel so they are shown in the own chat
+///
+pub fn membership_event_text(message: &Message) -> Option<(String, String)> {
+    let nickname = message.prefix.clone()?;
+
+    match message.command.as_str() {
+        JOIN | PART => {
+            let channel = message.params.first()?.first()?.clone();
+            let text_to_print = match message.command.as_str() {
+                JOIN => format!("{} joined the channel", nickname),
+                _ => format!("{} left the channel", nickname),
+            };
+            Some((channel, text_to_print))
+        }
+        QUIT => {
+            let text_to_print = match message.params.first().and_then(|param| param.first()) {
+                Some(reason) if !reason.is_empty() => {
+                    format!("{} has quit ({})", nickname, reason)
+                }
+                _ => format!("{} has quit", nickname),
+            };
+            Some(("You".to_string(), text_to_print))
+        }
+        _ => None,
+    }
+}
+
+///
+/// Returns the chats where the quit of another user is shown and its text: every channel this
+/// user shared with them, or the own chat if no channel is known. The own quit is not shown
+///
+pub fn quit_events(
+    message: &Message,
+    channels_left: &[String],
+    own_nickname: Option<&str>,
+) -> Vec<(String, String)> {
+    let is_own_quit = match (message.prefix.as_deref(), own_nickname) {
+        (Some(nickname), Some(own_nickname)) => irc_eq(nickname, own_nickname),
+        _ => false,
+    };
+    let (chat, text_to_print) = match membership_event_text(message) {
+        Some(event) if !is_own_quit => event,
+        _ => return vec![],
+    };
+
+    if channels_left.is_empty() {
+        return vec![(chat, text_to_print)];
+    }
+    channels_left
+        .iter()
+        .map(|channel| (channel.clone(), text_to_print.clone()))
+        .collect()
+}
+
+///
+/// Amount of each event received in a chat while they are collapsed
+///
+#[derive(Debug, Default, PartialEq, Eq)]
+struct EventCounts {
+    joins: usize,
+    parts: usize,
+    quits: usize,
+}
+
+///
+/// Joins, parts and quits counted for every chat since the first one arrived. Once
+/// EVENTS_SUMMARY_INTERVAL passed they are taken as a summary, like
+/// "5 joins, 2 parts in the last minute", and counting starts again
+///
+#[derive(Debug, Default)]
+pub struct EventsSummary {
+    counts: HashMap<String, EventCounts>,
+    started_at: Option<Instant>,
+}
+
+impl EventsSummary {
+    ///
+    /// Returns an EventsSummary without events
+    ///
+    pub fn new() -> Self {
+        EventsSummary::default()
+    }
+
+    ///
+    /// Counts an event of the chat, the command must be JOIN, PART or QUIT
+    ///
+    pub fn add(&mut self, chat: &str, command: &str, now: Instant) {
+        let counts = self.counts.entry(chat.to_string()).or_default();
+        match command {
+            JOIN => counts.joins += 1,
+            PART => counts.parts += 1,
+            QUIT => counts.quits += 1,
+            _ => return,
+        }
+        self.started_at.get_or_insert(now);
+    }
+
+    ///
+    /// If EVENTS_SUMMARY_INTERVAL passed since the first event counted, returns the
+    /// summary of every chat sorted by chat name and starts counting again
+    ///
+    pub fn take_if_due(&mut self, now: Instant) -> Vec<(String, String)> {
+        match self.started_at {
+            Some(started_at) if now.duration_since(started_at) >= EVENTS_SUMMARY_INTERVAL => {}
+            _ => return vec![],
+        }
+        self.started_at = None;
+
+        let mut summaries = self
+            .counts
+            .drain()
+            .map(|(chat, counts)| (chat, summary_text(&counts)))
+            .collect::<Vec<(String, String)>>();
+        summaries.sort();
+        summaries
+    }
+}
+
+///
+/// Returns the text of the summary, leaving out the events that didn't happen
+///
+fn summary_text(counts: &EventCounts) -> String {
+    let events = [
+        (counts.joins, "join"),
+        (counts.parts, "part"),
+        (counts.quits, "quit"),
+    ]
+    .iter()
+    .filter(|(amount, _)| *amount > 0)
+    .map(|(amount, event)| match amount {
+        1 => format!("1 {}", event),
+        _ => format!("{} {}s", amount, event),
+    })
+    .collect::<Vec<String>>();
+
+    format!("{} in the last minute", events.join(", "))
+}
+
+///
+/// Returns the text of a LUSERS reply, like "3 channels formed"
+///
+pub fn lusers_text(message: &Message) -> String {
+    message
+        .params
+        .iter()
+        .flatten()
+        .cloned()
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+///
+/// Returns the size of a file in the biggest unit that fits, like "2.5 MB"
+///
+pub fn file_size_text(bytes: u64) -> String {
+    let units = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} bytes", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, units[unit])
+}
+
+///
+/// Returns the text of an ISON reply, with the nicknames that are online
+///
+pub fn ison_text(message: &Message) -> String {
+    // The nicknames are in the trailing parameter, separated by spaces
+    let online = message
+        .params
+        .last()
+        .and_then(|param| param.first())
+        .map(|nicknames| nicknames.split_whitespace().collect::<Vec<&str>>())
+        .unwrap_or_default();
+    match online.is_empty() {
+        true => "None of them is online".to_string(),
+        false => format!("Online: {}", online.join(", ")),
+    }
+}
+
+///
+/// Returns the text of a WHOWAS reply, with the user that had the nickname
+///
+pub fn whowas_text(message: &Message) -> String {
+    let param = |index: usize| {
+        message
+            .params
+            .get(index)
+            .map(|param| param.join(","))
+            .unwrap_or_default()
+    };
+    format!(
+        "{} was online with username '{}' and realname '{}', from {}.",
+        param(0),
+        param(1),
+        param(4),
+        param(2)
+    )
+}
+
+///
+/// Returns the text of a line of the message of the day, without the "- " it starts with
+///
+pub fn motd_text(message: &Message) -> String {
+    // The line is the trailing parameter, it is never split
+    let text = message
+        .params
+        .last()
+        .and_then(|param| param.first())
+        .cloned()
+        .unwrap_or_default();
+    match text.strip_prefix("- ") {
+        Some(text) => text.to_string(),
+        None => text,
+    }
+}
+
+///
+/// Returns the text of a RPL_CHANNELMODEIS, with the modes of the channel and their parameters
+///
+pub fn channel_modes_text(message: &Message) -> String {
+    let channel = &message.params[0][0];
+    match message.params.get(1).map(|modes| modes.join(" ")) {
+        Some(modes) if modes != "+" => format!("Modes of {}: {}", channel, modes),
+        _ => format!("{} has no modes set", channel),
+    }
+}
+
+///
+/// Returns the text of a RPL_UMODEIS, with the modes of the user
+///
+pub fn user_modes_text(message: &Message) -> String {
+    match message.params.first().map(|modes| modes.join(" ")) {
+        Some(modes) if modes != "+" => format!("Your modes: {}", modes),
+        _ => "You have no modes set".to_string(),
+    }
+}
+
+///
+/// Returns the text shown to the user kicked, with the reason of the kick. If the reason
+/// is missing the nickname of the user kicking is shown, as it is the default reason
+///
+pub fn kick_text(message: &Message) -> String {
+    let channel = &message.params[0][0];
+    let reason = match message.params.get(2) {
+        Some(comment) => comment.join(" "),
+        None => message.prefix.clone().unwrap_or_default(),
+    };
+    format!("You have been kicked from {} ({})", channel, reason)
+}
+
+///
+/// Returns the text of a NOTICE AUTH, sent by the server while logging in or registering
+///
+pub fn auth_notice_text(message: &Message) -> Option<String> {
+    if message.params.len() < 2 || message.params[0].first()? != NOTICE_AUTH {
+        return None;
+    }
+    Some(message.params[1].join(" "))
+}
+
+///
+/// Returns the chat where a notice is shown and its text. Notices never open a new chat,
+/// if there isn't a chat with the sender it is shown in the own chat of the user
+///
+pub fn notice_chat_and_text(message: &Message, users: &[String]) -> (String, String) {
+    let text = message
+        .params
+        .get(1)
+        .map(|text| text.join(" "))
+        .unwrap_or_default();
+    match &message.prefix {
+        Some(nickname) if users.contains(nickname) => {
+            (nickname.clone(), format!("{}: {}", nickname, text))
+        }
+        Some(nickname) => ("You".to_string(), format!("{}: {}", nickname, text)),
+        None => ("You".to_string(), text),
+    }
+}
+
+///
+/// Returns the text of a WALLOPS, sent by an operator to the operators of every server, like
+/// "WALLOPS from marce: Reinicio en 5 minutos"
+///
+pub fn operators_message_text(message: &Message) -> String {
+    let text = message
+        .params
+        .iter()
+        .map(|param| param.join(" "))
+        .collect::<Vec<String>>()
+        .join(" ");
+    match &message.prefix {
+        Some(nickname) => format!("{} from {}: {}", message.command, nickname, text),
+        None => format!("{}: {}", message.command, text),
+    }
+}
+
+///
+/// Returns the line of a chat with the time the message was received, like "[09:05] ari: hola"
+///
+pub fn timestamped_text(hour: i32, minute: i32, text: &str) -> String {
+    format!("[{:02}:{:02}] {}", hour, minute, text)
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        action_text, auth_notice_text, channel_error_chat_and_text, channel_modes_text,
+        connected_for_text, file_size_text, format_action, idle_text, ignored_users_text,
+        is_from_ignored_user, ison_text, kick_text, lusers_text, membership_event_text, motd_text,
+        names_not_found_text, no_bans_text, notice_chat_and_text, operators_message_text,
+        participants_text, quit_events, raw_message_line, server_quit_text, timestamped_text,
+        topic_details_text, topic_text, track_channel_members, ui_updater, unhandled_numeric_text,
+        user_modes_text, welcome_text, who_reply_label, whowas_text, EventsSummary,
+        EVENTS_SUMMARY_INTERVAL,
+    };
+    use crate::client_utils::message_types::{ERROR, INFO};
+    use crate::client_utils::nick_completion::ChannelMembers;
+    use crate::client_utils::typing::TYPING_ACTIVE;
+    use crate::commands::{
+        ADD_LIST_CHATS, EVENTS_INLINE, JOIN, KICK, NOTICE, NOTICE_AUTH, PART, PRIVMSG, QUIT,
+        RECEIVED_MESSAGE, RECONNECT, TYPING_NOTIFICATION, WALLOPS,
+    };
+    use crate::message::Message;
+    use crate::numeric_reply::{
+        ERR_CANNOTSENDTOCHAN_MSG, ERR_CANNOTSENDTOCHAN_NUM, ERR_NOTONCHANNEL_MSG,
+        ERR_NOTONCHANNEL_NUM, ERR_USERONCHANNEL_MSG, ERR_USERONCHANNEL_NUM, RPL_ISON_NUM,
+        RPL_LUSERCHANNELS_NUM, RPL_MOTD_NUM, RPL_NAMEREPLY_NUM, RPL_TOPIC_NUM, RPL_WHOWASUSER_NUM,
+    };
+    use crate::parser::parse;
+    use std::collections::HashSet;
+    use std::io::Write;
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn raw_line_of_numeric_reply_shows_number_and_params() {
+        let message = Message {
+            tags: None,
+            prefix: None,
+            command: RPL_TOPIC_NUM.to_string(),
+            params: vec![
+                vec!["#channel".to_string()],
+                vec!["a new topic".to_string()],
+            ],
+        };
+
+        assert_eq!(raw_message_line(&message), "332 [#channel] [a new topic]");
+    }
+
+    #[test]
+    fn raw_line_shows_prefix_and_grouped_params() {
+        let message = Message {
+            tags: None,
+            prefix: Some("nick".to_string()),
+            command: "PRIVMSG".to_string(),
+            params: vec![
+                vec!["user1".to_string(), "user2".to_string()],
+                vec!["hello".to_string()],
+            ],
+        };
+
+        assert_eq!(
+            raw_message_line(&message),
+            ":nick PRIVMSG [user1, user2] [hello]"
+        );
+    }
+
+    #[test]
+    fn raw_line_of_message_without_params_is_the_command() {
+        let message = Message {
+            tags: None,
+            prefix: None,
+            command: "QUIT".to_string(),
+            params: vec![],
+        };
+
+        assert_eq!(raw_message_line(&message), "QUIT");
+    }
+
+    #[test]
+    fn topic_change_names_who_changed_it() {
+        assert_eq!(
+            topic_text("new topic", Some("nick")),
+            "nick changed the topic to: new topic"
+        );
+    }
+
+    #[test]
+    fn topic_query_reply_shows_only_topic() {
+        assert_eq!(topic_text("current topic", None), "Topic is: current topic");
+    }
+
+    #[test]
+    fn topic_details_show_who_set_the_topic_and_when_the_channel_was_created() {
+        let reply = |number: &str, params: &[&str]| Message {
+            tags: None,
+            prefix: None,
+            command: number.to_string(),
+            params: params.iter().map(|param| vec![param.to_string()]).collect(),
+        };
+
+        assert_eq!(
+            topic_details_text(&reply("333", &["#canal", "ari", "1671234567"])),
+            Some("Topic set by ari at 2022-12-16 23:49:27 UTC".to_string())
+        );
+        assert_eq!(
+            topic_details_text(&reply("329", &["#canal", "1671234567"])),
+            Some("Channel created at 2022-12-16 23:49:27 UTC".to_string())
+        );
+        assert_eq!(topic_details_text(&reply("329", &["#canal", "ayer"])), None);
+    }
+
+    #[test]
+    fn unhandled_error_numeric_is_shown_as_error() {
+        let message = Message {
+            tags: None,
+            prefix: None,
+            command: "437".to_string(),
+            params: vec![
+                vec!["#channel".to_string()],
+                vec!["Channel is temporarily unavailable".to_string()],
+            ],
+        };
+
+        assert_eq!(
+            unhandled_numeric_text(&message),
+            Some((
+                "Server: 437 #channel Channel is temporarily unavailable".to_string(),
+                ERROR
+            ))
+        );
+    }
+
+    #[test]
+    fn unhandled_reply_numeric_is_shown_as_info() {
+        let message = Message {
+            tags: None,
+            prefix: None,
+            command: "251".to_string(),
+            params: vec![],
+        };
+
+        assert_eq!(
+            unhandled_numeric_text(&message),
+            Some(("Server: 251".to_string(), INFO))
+        );
+    }
+
+    #[test]
+    fn unhandled_command_is_not_a_numeric() {
+        let message = Message {
+            tags: None,
+            prefix: Some("nickname".to_string()),
+            command: PRIVMSG.to_string(),
+            params: vec![vec!["#channel".to_string()], vec!["hi".to_string()]],
+        };
+
+        assert_eq!(unhandled_numeric_text(&message), None);
+    }
+
+    #[test]
+    fn ctcp_action_payload_returns_the_action() {
+        assert_eq!(action_text("\x01ACTION waves\x01"), Some("waves"));
+        assert_eq!(
+            action_text("\x01ACTION waves at everyone\x01"),
+            Some("waves at everyone")
+        );
+    }
+
+    #[test]
+    fn regular_text_is_not_an_action() {
+        assert_eq!(action_text("Hola"), None);
+        assert_eq!(action_text("ACTION waves"), None);
+        assert_eq!(action_text("\x01VERSION client\x01"), None);
+    }
+
+    #[test]
+    fn action_is_shown_with_the_nickname() {
+        assert_eq!(format_action("ari", "waves"), "* ari waves");
+    }
+
+    #[test]
+    fn channel_modes_are_shown_with_their_parameters() {
+        let reply = |modes: &str| Message {
+            tags: None,
+            prefix: None,
+            command: "324".to_string(),
+            params: vec![vec!["#canal".to_string()], vec![modes.to_string()]],
+        };
+
+        assert_eq!(
+            channel_modes_text(&reply("+klt clave 10")),
+            "Modes of #canal: +klt clave 10"
+        );
+        assert_eq!(channel_modes_text(&reply("+")), "#canal has no modes set");
+    }
+
+    #[test]
+    fn user_modes_are_shown() {
+        let reply = |modes: &str| Message {
+            tags: None,
+            prefix: None,
+            command: "221".to_string(),
+            params: vec![vec![modes.to_string()]],
+        };
+
+        assert_eq!(user_modes_text(&reply("+i")), "Your modes: +i");
+        assert_eq!(user_modes_text(&reply("+")), "You have no modes set");
+    }
+
+    #[test]
+    fn quit_of_the_server_shows_its_reason() {
+        let quit = |reason: &str| Message {
+            tags: None,
+            prefix: None,
+            command: QUIT.to_string(),
+            params: vec![vec![reason.to_string()]],
+        };
+
+        assert_eq!(
+            server_quit_text(&quit("Server shutting down")),
+            Some("The server closed the connection: Server shutting down".to_string())
+        );
+        assert_eq!(server_quit_text(&quit("")), None);
+    }
+
+    #[test]
+    fn messages_of_ignored_users_are_dropped() {
+        let mut ignored_users = HashSet::new();
+        ignored_users.insert("lucas".to_string());
+        let message = |prefix: Option<&str>| Message {
+            tags: None,
+            prefix: prefix.map(|nickname| nickname.to_string()),
+            command: PRIVMSG.to_string(),
+            params: vec![vec!["ari".to_string()], vec!["hola".to_string()]],
+        };
+
+        assert!(is_from_ignored_user(
+            &message(Some("Lucas")),
+            &ignored_users
+        ));
+        assert!(!is_from_ignored_user(
+            &message(Some("marce")),
+            &ignored_users
+        ));
+        assert!(!is_from_ignored_user(&message(None), &ignored_users));
+    }
+
+    #[test]
+    fn ignored_users_are_listed_in_order() {
+        let mut ignored_users = HashSet::new();
+        assert_eq!(
+            ignored_users_text(&ignored_users),
+            "You are not ignoring anyone"
+        );
+
+        ignored_users.insert("marce".to_string());
+        ignored_users.insert("lucas".to_string());
+        assert_eq!(ignored_users_text(&ignored_users), "Ignoring: lucas, marce");
+    }
+
+    #[test]
+    fn ignored_users_dont_open_a_chat() {
+        let listener = TcpListener::bind("127.0.0.1:5011").unwrap();
+        let (tx_backend, rx_frontend): (Sender<Message>, Receiver<Message>) = mpsc::channel();
+        let (tx_stream, rx_stream): (Sender<TcpStream>, Receiver<TcpStream>) = mpsc::channel();
+        let _ = thread::spawn(move || {
+            let users_online = Arc::new(Mutex::new(Vec::new()));
+            let raw_mode = Arc::new(Mutex::new(false));
+            let announce_topic_changes = Arc::new(Mutex::new(true));
+            let membership_events = Arc::new(Mutex::new(EVENTS_INLINE.to_string()));
+            let channel_members = Arc::new(Mutex::new(ChannelMembers::default()));
+            let ignored_users = Arc::new(Mutex::new(HashSet::from(["lucas".to_string()])));
+            let typing_notifications = Arc::new(Mutex::new(true));
+            ui_updater(
+                tx_backend,
+                rx_stream,
+                mpsc::channel().0,
+                &users_online,
+                &raw_mode,
+                &announce_topic_changes,
+                &membership_events,
+                &channel_members,
+                &ignored_users,
+                &typing_notifications,
+            )
+        });
+
+        tx_stream
+            .send(TcpStream::connect("127.0.0.1:5011").unwrap())
+            .unwrap();
+        let mut server_side = listener.accept().unwrap().0;
+        server_side
+            .write_all(b":Lucas PRIVMSG ari :spam\r\n:marce PRIVMSG ari :hola\r\n")
+            .unwrap();
+
+        // The first update is the chat of marce, nothing was sent for lucas
+        let update = rx_frontend.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(update.command, ADD_LIST_CHATS);
+        assert_eq!(update.prefix, Some("marce".to_string()));
+        let update = rx_frontend.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(update.command, RECEIVED_MESSAGE);
+        assert_eq!(update.params[0][0], "marce: hola");
+    }
+
+    #[test]
+    fn messages_keep_the_time_the_server_received_them() {
+        let listener = TcpListener::bind("127.0.0.1:5019").unwrap();
+        let (tx_backend, rx_frontend): (Sender<Message>, Receiver<Message>) = mpsc::channel();
+        let (tx_stream, rx_stream): (Sender<TcpStream>, Receiver<TcpStream>) = mpsc::channel();
+        let _ = thread::spawn(move || {
+            let users_online = Arc::new(Mutex::new(vec!["#rust".to_string()]));
+            let raw_mode = Arc::new(Mutex::new(false));
+            let announce_topic_changes = Arc::new(Mutex::new(true));
+            let membership_events = Arc::new(Mutex::new(EVENTS_INLINE.to_string()));
+            let channel_members = Arc::new(Mutex::new(ChannelMembers::default()));
+            let ignored_users = Arc::new(Mutex::new(HashSet::new()));
+            let typing_notifications = Arc::new(Mutex::new(true));
+            ui_updater(
+                tx_backend,
+                rx_stream,
+                mpsc::channel().0,
+                &users_online,
+                &raw_mode,
+                &announce_topic_changes,
+                &membership_events,
+                &channel_members,
+                &ignored_users,
+                &typing_notifications,
+            )
+        });
+
+        tx_stream
+            .send(TcpStream::connect("127.0.0.1:5019").unwrap())
+            .unwrap();
+        let mut server_side = listener.accept().unwrap().0;
+        server_side
+            .write_all(
+                b"@time=2022-12-16T23:49:27.000Z :marce PRIVMSG #rust :(backlog) hola\r\n\
+                  :marce PRIVMSG #rust :chau\r\n",
+            )
+            .unwrap();
+
+        let update = rx_frontend.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(update.command, RECEIVED_MESSAGE);
+        assert_eq!(update.params[0][0], "marce: (backlog) hola");
+        assert_eq!(update.time(), Some("2022-12-16T23:49:27.000Z"));
+        let update = rx_frontend.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(update.params[0][0], "marce: chau");
+        assert_eq!(update.time(), None);
+    }
+
+    #[test]
+    fn connected_time_is_shown_in_hours_and_minutes() {
+        let now = 1_700_000_000;
+        let signon = now - (3 * 60 * 60 + 25 * 60 + 40);
+
+        assert_eq!(
+            connected_for_text("ari", signon, now),
+            "ari has been connected for 3h 25m."
+        );
+    }
+
+    #[test]
+    fn idle_time_is_shown_in_minutes_and_seconds() {
+        assert_eq!(idle_text("ari", 0), "ari has been idle for 0m 0s.");
+        assert_eq!(idle_text("ari", 303), "ari has been idle for 5m 3s.");
+        assert_eq!(idle_text("ari", 7200), "ari has been idle for 120m 0s.");
+    }
+
+    #[test]
+    fn recently_connected_user_shows_zero_hours() {
+        let now = 1_700_000_000;
+
+        assert_eq!(
+            connected_for_text("ari", now - 59, now),
+            "ari has been connected for 0h 0m."
+        );
+        assert_eq!(
+            connected_for_text("ari", now + 10, now),
+            "ari has been connected for 0h 0m."
+        );
+    }
+
+    #[test]
+    fn collapsed_events_are_summarized_after_the_interval() {
+        let start = Instant::now();
+        let mut events_summary = EventsSummary::new();
+
+        for _ in 0..5 {
+            events_summary.add("#canal", JOIN, start);
+        }
+        events_summary.add("#canal", PART, start);
+        events_summary.add("#canal", PART, start + Duration::from_secs(10));
+        events_summary.add("You", QUIT, start + Duration::from_secs(20));
+
+        // Nothing is shown until the interval is over
+        assert!(events_summary
+            .take_if_due(start + Duration::from_secs(30))
+            .is_empty());
+
+        assert_eq!(
+            events_summary.take_if_due(start + EVENTS_SUMMARY_INTERVAL),
+            vec![
+                (
+                    "#canal".to_string(),
+                    "5 joins, 2 parts in the last minute".to_string()
+                ),
+                ("You".to_string(), "1 quit in the last minute".to_string()),
+            ]
+        );
+
+        // Events already summarized are not shown again
+        assert!(events_summary
+            .take_if_due(start + EVENTS_SUMMARY_INTERVAL * 2)
+            .is_empty());
+    }
+
+    #[test]
+    fn interval_starts_with_the_first_event_counted() {
+        let start = Instant::now();
+        let mut events_summary = EventsSummary::new();
+
+        events_summary.add("#canal", JOIN, start + EVENTS_SUMMARY_INTERVAL);
+
+        assert!(events_summary
+            .take_if_due(start + EVENTS_SUMMARY_INTERVAL)
+            .is_empty());
+        assert_eq!(
+            events_summary.take_if_due(start + EVENTS_SUMMARY_INTERVAL * 2),
+            vec![(
+                "#canal".to_string(),
+                "1 join in the last minute".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn joins_and_parts_are_shown_in_their_channel() {
+        let join = Message {
+            tags: None,
+            prefix: Some("ari".to_string()),
+            command: JOIN.to_string(),
+            params: vec![vec!["#canal".to_string()]],
+        };
+        let part = Message {
+            tags: None,
+            prefix: Some("ari".to_string()),
+            command: PART.to_string(),
+            params: vec![vec!["#canal".to_string()]],
+        };
+
+        assert_eq!(
+            membership_event_text(&join),
+            Some(("#canal".to_string(), "ari joined the channel".to_string()))
+        );
+        assert_eq!(
+            membership_event_text(&part),
+            Some(("#canal".to_string(), "ari left the channel".to_string()))
+        );
+    }
+
+    #[test]
+    fn quits_are_shown_in_own_chat_with_the_reason() {
+        let quit = Message {
+            tags: None,
+            prefix: Some("ari".to_string()),
+            command: QUIT.to_string(),
+            params: vec![vec!["Chau".to_string()]],
+        };
+
+        assert_eq!(
+            membership_event_text(&quit),
+            Some(("You".to_string(), "ari has quit (Chau)".to_string()))
+        );
+    }
+
+    #[test]
+    fn quits_are_shown_in_the_channels_shared_but_not_the_own_quit() {
+        let quit = Message {
+            tags: None,
+            prefix: Some("ari".to_string()),
+            command: QUIT.to_string(),
+            params: vec![],
+        };
+        let channels_left = vec!["#Canal".to_string(), "#rust".to_string()];
+
+        assert_eq!(
+            quit_events(&quit, &channels_left, Some("lucas")),
+            vec![
+                ("#Canal".to_string(), "ari has quit".to_string()),
+                ("#rust".to_string(), "ari has quit".to_string())
+            ]
+        );
+        assert_eq!(
+            quit_events(&quit, &[], None),
+            vec![("You".to_string(), "ari has quit".to_string())]
+        );
+        assert!(quit_events(&quit, &channels_left, Some("ARI")).is_empty());
+    }
+
+    #[test]
+    fn lusers_channels_reply_shows_the_amount_of_channels() {
+        let message = Message {
+            tags: None,
+            prefix: None,
+            command: RPL_LUSERCHANNELS_NUM.to_string(),
+            params: vec![vec!["3".to_string()], vec!["channels formed".to_string()]],
+        };
+
+        assert_eq!(lusers_text(&message), "3 channels formed");
+    }
+
+    #[test]
+    fn file_size_is_shown_in_the_biggest_unit_that_fits() {
+        assert_eq!(file_size_text(512), "512 bytes");
+        assert_eq!(file_size_text(1536), "1.5 KB");
+        assert_eq!(file_size_text(5 * 1024 * 1024), "5.0 MB");
+        assert_eq!(
+            file_size_text(3 * 1024 * 1024 * 1024 + 600 * 1024 * 1024),
+            "3.6 GB"
+        );
+    }
+
+    #[test]
+    fn whowas_reply_shows_the_user_that_had_the_nickname() {
+        let message = Message {
+            tags: None,
+            prefix: None,
+            command: RPL_WHOWASUSER_NUM.to_string(),
+            params: vec![
+                vec!["ari".to_string()],
+                vec!["ari".to_string()],
+                vec!["127.0.0.1".to_string()],
+                vec!["*".to_string()],
+                vec!["Ariel Gomez".to_string()],
+            ],
+        };
+        assert_eq!(
+            whowas_text(&message),
+            "ari was online with username 'ari' and realname 'Ariel Gomez', from 127.0.0.1."
+        );
+    }
+
+    #[test]
+    fn timestamps_have_two_digits_for_hour_and_minute() {
+        assert_eq!(timestamped_text(9, 5, "ari: hola"), "[09:05] ari: hola");
+        assert_eq!(timestamped_text(23, 59, "@INFO bye"), "[23:59] @INFO bye");
+    }
+
+    #[test]
+    fn ison_reply_shows_the_users_online_or_that_none_is() {
+        let online = Message {
+            tags: None,
+            prefix: None,
+            command: RPL_ISON_NUM.to_string(),
+            params: vec![vec!["ari marce".to_string()]],
+        };
+        let none_online = Message {
+            tags: None,
+            prefix: None,
+            command: RPL_ISON_NUM.to_string(),
+            params: vec![],
+        };
+
+        assert_eq!(ison_text(&online), "Online: ari, marce");
+        assert_eq!(ison_text(&none_online), "None of them is online");
+    }
+
+    #[test]
+    fn motd_line_is_shown_without_its_dash() {
+        let message = Message {
+            tags: None,
+            prefix: None,
+            command: RPL_MOTD_NUM.to_string(),
+            params: vec![vec!["- Welcome to the server!".to_string()]],
+        };
+
+        assert_eq!(motd_text(&message), "Welcome to the server!");
+    }
+
+    #[test]
+    fn auth_notice_shows_connection_progress_and_other_notices_are_ignored() {
+        let auth_notice = Message {
+            tags: None,
+            prefix: None,
+            command: NOTICE.to_string(),
+            params: vec![
+                vec![NOTICE_AUTH.to_string()],
+                vec!["*** Looking up your connection...".to_string()],
+            ],
+        };
+        let notice = Message {
+            tags: None,
+            prefix: Some("ari".to_string()),
+            command: NOTICE.to_string(),
+            params: vec![vec!["juanireil".to_string()], vec!["hola".to_string()]],
+        };
+
+        assert_eq!(
+            auth_notice_text(&auth_notice),
+            Some("*** Looking up your connection...".to_string())
+        );
+        assert_eq!(auth_notice_text(&notice), None);
+    }
+
+    #[test]
+    fn kicked_user_sees_the_reason_or_the_nickname_kicking() {
+        let mut message = Message {
+            tags: None,
+            prefix: Some("ari".to_string()),
+            command: KICK.to_string(),
+            params: vec![
+                vec!["#canal".to_string()],
+                vec!["juanireil".to_string()],
+                vec!["too much spam".to_string()],
+            ],
+        };
+        assert_eq!(
+            kick_text(&message),
+            "You have been kicked from #canal (too much spam)"
+        );
+
+        message.params.pop();
+        assert_eq!(
+            kick_text(&message),
+            "You have been kicked from #canal (ari)"
+        );
+    }
+
+    #[test]
+    fn notice_is_shown_in_the_chat_of_the_sender_only_if_it_exists() {
+        let notice = Message {
+            tags: None,
+            prefix: Some("ari".to_string()),
+            command: NOTICE.to_string(),
+            params: vec![vec!["juanireil".to_string()], vec!["hola".to_string()]],
+        };
+
+        let users = vec!["ari".to_string()];
+        assert_eq!(
+            notice_chat_and_text(&notice, &users),
+            ("ari".to_string(), "ari: hola".to_string())
+        );
+        assert_eq!(
+            notice_chat_and_text(&notice, &[]),
+            ("You".to_string(), "ari: hola".to_string())
+        );
+    }
+
+    #[test]
+    fn channel_errors_are_shown_in_the_channel_only_if_it_is_open() {
+        let cannot_send = Message {
+            tags: None,
+            prefix: None,
+            command: ERR_CANNOTSENDTOCHAN_NUM.to_string(),
+            params: vec![
+                vec!["#Rust".to_string()],
+                vec![ERR_CANNOTSENDTOCHAN_MSG.to_string()],
+            ],
+        };
+        let chats = vec!["ari".to_string(), "#rust".to_string()];
+        assert_eq!(
+            channel_error_chat_and_text(&cannot_send, ERR_CANNOTSENDTOCHAN_MSG, &chats),
+            (
+                Some("#rust".to_string()),
+                "#Rust: Cannot send to channel".to_string()
+            )
+        );
+        assert_eq!(
+            channel_error_chat_and_text(&cannot_send, ERR_CANNOTSENDTOCHAN_MSG, &[]),
+            (None, "#Rust: Cannot send to channel".to_string())
+        );
+
+        // Parting a channel the user isn't on has the nickname before the channel
+        let not_on_channel = Message {
+            tags: None,
+            prefix: None,
+            command: ERR_NOTONCHANNEL_NUM.to_string(),
+            params: vec![
+                vec!["ari".to_string()],
+                vec!["#rust".to_string()],
+                vec![ERR_NOTONCHANNEL_MSG.to_string()],
+            ],
+        };
+        assert_eq!(
+            channel_error_chat_and_text(&not_on_channel, ERR_NOTONCHANNEL_MSG, &chats),
+            (
+                Some("#rust".to_string()),
+                format!("#rust: {}", ERR_NOTONCHANNEL_MSG)
+            )
+        );
+
+        let user_on_channel = Message {
+            tags: None,
+            prefix: None,
+            command: ERR_USERONCHANNEL_NUM.to_string(),
+            params: vec![
+                vec!["#rust".to_string()],
+                vec!["lucas".to_string()],
+                vec![ERR_USERONCHANNEL_MSG.to_string()],
+            ],
+        };
+        assert_eq!(
+            channel_error_chat_and_text(&user_on_channel, ERR_USERONCHANNEL_MSG, &chats).1,
+            format!("#rust: lucas {}", ERR_USERONCHANNEL_MSG)
+        );
+    }
+
+    #[test]
+    fn operators_messages_show_the_command_and_the_operator_sending() {
+        let wallops = Message {
+            tags: None,
+            prefix: Some("marce".to_string()),
+            command: WALLOPS.to_string(),
+            params: vec![vec!["Reinicio en 5 minutos".to_string()]],
+        };
+        assert_eq!(
+            operators_message_text(&wallops),
+            "WALLOPS from marce: Reinicio en 5 minutos"
+        );
+
+        let wallops = Message {
+            tags: None,
+            prefix: None,
+            command: WALLOPS.to_string(),
+            params: vec![vec!["Reinicio".to_string()]],
+        };
+        assert_eq!(operators_message_text(&wallops), "WALLOPS: Reinicio");
+    }
+
+    #[test]
+    fn participants_show_the_operators_and_not_this_user() {
+        assert_eq!(
+            participants_text("ari", "@lucas ari marce"),
+            "Users in channel: lucas (operator), marce"
+        );
+        assert_eq!(
+            participants_text("ari", "@lucas +marce ari"),
+            "Users in channel: lucas (operator), marce (voice)"
+        );
+        assert_eq!(participants_text("lucas", "@lucas"), "No one is here yet!");
+    }
+
+    #[test]
+    fn names_of_a_channel_without_replies_are_shown_as_not_found() {
+        let end_of_names = |params: Vec<&str>| Message {
+            tags: None,
+            prefix: None,
+            command: "366".to_string(),
+            params: params.iter().map(|param| vec![param.to_string()]).collect(),
+        };
+        let named = vec!["#Rust".to_string()];
+
+        assert_eq!(
+            names_not_found_text(&end_of_names(vec!["#missing", "End of NAMES list"]), &named),
+            Some("Channel not found or empty".to_string())
+        );
+        assert_eq!(
+            names_not_found_text(&end_of_names(vec!["#rust", "End of NAMES list"]), &named),
+            None
+        );
+        assert_eq!(
+            names_not_found_text(&end_of_names(vec!["End of NAMES list"]), &[]),
+            None
+        );
+    }
+
+    #[test]
+    fn bans_of_a_channel_without_replies_are_shown_as_none() {
+        let end_of_bans = |channel: &str| Message {
+            tags: None,
+            prefix: None,
+            command: "368".to_string(),
+            params: vec![
+                vec![channel.to_string()],
+                vec!["End of channel ban list".to_string()],
+            ],
+        };
+        let banning = vec!["#Rust".to_string()];
+
+        assert_eq!(
+            no_bans_text(&end_of_bans("#empty"), &banning),
+            Some("#empty has no bans".to_string())
+        );
+        assert_eq!(no_bans_text(&end_of_bans("#rust"), &banning), None);
+    }
+
+    #[test]
+    fn welcome_replies_are_shown_with_the_creation_time_in_utc() {
+        let text = |line: &str| welcome_text(&parse(format!("{}\r\n", line)).unwrap());
+
+        assert_eq!(
+            text("001 ari :Welcome to the Internet Relay Network ari"),
+            Some("Welcome to the Internet Relay Network ari".to_string())
+        );
+        assert_eq!(
+            text("003 ari 1671234567 :This server was created"),
+            Some("This server was created 2022-12-16 23:49:27 UTC".to_string())
+        );
+        assert_eq!(
+            text("004 ari rust irc-0.1.0 i :Lbefiklmnostv"),
+            Some("rust runs irc-0.1.0, user modes i, channel modes Lbefiklmnostv".to_string())
+        );
+        assert_eq!(text("003 ari :This server was created"), None);
+    }
+
+    #[test]
+    fn who_replies_of_channels_show_the_prefix_and_whether_the_users_are_away_or_far() {
+        let label = |line: &str| who_reply_label(&parse(format!("{}\r\n", line)).unwrap());
+
+        assert_eq!(label("352 ari :WHO reply"), "ari");
+        assert_eq!(
+            label("352 lucas #canal lucas_user localhost rust H@ :0 Lucas Real"),
+            "@lucas"
+        );
+        assert_eq!(
+            label("352 ari #canal ari_user localhost rust G :0 Ari Real"),
+            "ari (away)"
+        );
+        assert_eq!(
+            label("352 marce #canal marce_user localhost other G+ :1 Marce Real"),
+            "+marce (away, on another server)"
+        );
+    }
+
+    #[test]
+    fn channel_members_are_kept_from_names_messages_joins_parts_and_quits() {
+        let message = |prefix: Option<&str>, command: &str, params: Vec<&str>| Message {
+            tags: None,
+            prefix: prefix.map(str::to_string),
+            command: command.to_string(),
+            params: params.iter().map(|param| vec![param.to_string()]).collect(),
+        };
+        let mut channel_members = ChannelMembers::default();
+
+        for received in [
+            message(
+                None,
+                RPL_NAMEREPLY_NUM,
+                vec!["ari", "=", "#canal", "@lucas +juani ari"],
+            ),
+            message(Some("marce"), JOIN, vec!["#canal"]),
+            message(Some("ari"), PART, vec!["#canal"]),
+            message(Some("juani"), PRIVMSG, vec!["#canal", "hola"]),
+            message(Some("lucas"), PRIVMSG, vec!["ari", "hola"]),
+        ] {
+            track_channel_members(&received, &mut channel_members);
+        }
+        assert_eq!(
+            channel_members.get("#canal"),
+            vec!["lucas", "juani", "marce"]
+        );
+
+        let quit = message(Some("lucas"), QUIT, vec!["chau"]);
+        track_channel_members(&quit, &mut channel_members);
+        assert_eq!(channel_members.get("#canal"), vec!["juani", "marce"]);
+    }
+
+    #[test]
+    fn closed_connection_asks_to_reconnect_and_the_new_stream_is_read() {
+        let listener = TcpListener::bind("127.0.0.1:5008").unwrap();
+        let (tx_backend, rx_frontend): (Sender<Message>, Receiver<Message>) = mpsc::channel();
+        let (tx_stream, rx_stream): (Sender<TcpStream>, Receiver<TcpStream>) = mpsc::channel();
+        let _ = thread::spawn(move || {
+            let users_online = Arc::new(Mutex::new(Vec::new()));
+            let raw_mode = Arc::new(Mutex::new(false));
+            let announce_topic_changes = Arc::new(Mutex::new(true));
+            let membership_events = Arc::new(Mutex::new(EVENTS_INLINE.to_string()));
+            let channel_members = Arc::new(Mutex::new(ChannelMembers::default()));
+            let ignored_users = Arc::new(Mutex::new(HashSet::new()));
+            let typing_notifications = Arc::new(Mutex::new(true));
+            ui_updater(
+                tx_backend,
+                rx_stream,
+                mpsc::channel().0,
+                &users_online,
+                &raw_mode,
+                &announce_topic_changes,
+                &membership_events,
+                &channel_members,
+                &ignored_users,
+                &typing_notifications,
+            )
+        });
+
+        // The server closes the first connection
+        tx_stream
+            .send(TcpStream::connect("127.0.0.1:5008").unwrap())
+            .unwrap();
+        drop(listener.accept().unwrap().0);
+        let update = rx_frontend.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(update.command, RECONNECT);
+
+        // Messages of the new connection are handled as before
+        tx_stream
+            .send(TcpStream::connect("127.0.0.1:5008").unwrap())
+            .unwrap();
+        let mut server_side = listener.accept().unwrap().0;
+        server_side.write_all(b"999 :prueba\r\n").unwrap();
+        let update = rx_frontend.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(update.command, RECEIVED_MESSAGE);
+    }
+
+    #[test]
+    fn invalid_utf8_from_the_server_doesnt_close_the_connection() {
+        let listener = TcpListener::bind("127.0.0.1:5015").unwrap();
+        let (tx_backend, rx_frontend): (Sender<Message>, Receiver<Message>) = mpsc::channel();
+        let (tx_stream, rx_stream): (Sender<TcpStream>, Receiver<TcpStream>) = mpsc::channel();
+        let _ = thread::spawn(move || {
+            let users_online = Arc::new(Mutex::new(Vec::new()));
+            let raw_mode = Arc::new(Mutex::new(false));
+            let announce_topic_changes = Arc::new(Mutex::new(true));
+            let membership_events = Arc::new(Mutex::new(EVENTS_INLINE.to_string()));
+            let channel_members = Arc::new(Mutex::new(ChannelMembers::default()));
+            let ignored_users = Arc::new(Mutex::new(HashSet::new()));
+            let typing_notifications = Arc::new(Mutex::new(true));
+            ui_updater(
+                tx_backend,
+                rx_stream,
+                mpsc::channel().0,
+                &users_online,
+                &raw_mode,
+                &announce_topic_changes,
+                &membership_events,
+                &channel_members,
+                &ignored_users,
+                &typing_notifications,
+            )
+        });
+
+        tx_stream
+            .send(TcpStream::connect("127.0.0.1:5015").unwrap())
+            .unwrap();
+        let mut server_side = listener.accept().unwrap().0;
+        server_side.write_all(b"999 :pr\xffueba\r\n").unwrap();
+        server_side.write_all(b"999 :prueba\r\n").unwrap();
+
+        let update = rx_frontend.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(update.command, RECEIVED_MESSAGE);
+        assert_eq!(update.params[0][0], "Server: 999 pr\u{FFFD}ueba");
+        let update = rx_frontend.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(update.command, RECEIVED_MESSAGE);
+        assert_eq!(update.params[0][0], "Server: 999 prueba");
+    }
+
+    #[test]
+    fn typing_notices_dont_open_a_chat_and_are_dropped_when_turned_off() {
+        let listener = TcpListener::bind("127.0.0.1:5016").unwrap();
+        let (tx_backend, rx_frontend): (Sender<Message>, Receiver<Message>) = mpsc::channel();
+        let (tx_stream, rx_stream): (Sender<TcpStream>, Receiver<TcpStream>) = mpsc::channel();
+        let typing_notifications = Arc::new(Mutex::new(true));
+        let typing_notifications_clone = typing_notifications.clone();
+        let _ = thread::spawn(move || {
+            let users_online = Arc::new(Mutex::new(Vec::new()));
+            let raw_mode = Arc::new(Mutex::new(false));
+            let announce_topic_changes = Arc::new(Mutex::new(true));
+            let membership_events = Arc::new(Mutex::new(EVENTS_INLINE.to_string()));
+            let channel_members = Arc::new(Mutex::new(ChannelMembers::default()));
+            let ignored_users = Arc::new(Mutex::new(HashSet::new()));
+            ui_updater(
+                tx_backend,
+                rx_stream,
+                mpsc::channel().0,
+                &users_online,
+                &raw_mode,
+                &announce_topic_changes,
+                &membership_events,
+                &channel_members,
+                &ignored_users,
+                &typing_notifications_clone,
+            )
+        });
+
+        tx_stream
+            .send(TcpStream::connect("127.0.0.1:5016").unwrap())
+            .unwrap();
+        let mut server_side = listener.accept().unwrap().0;
+        server_side
+            .write_all(b":marce NOTICE ari :\x01TYPING active\x01\r\n")
+            .unwrap();
+        let update = rx_frontend.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(update.command, TYPING_NOTIFICATION);
+        assert_eq!(update.prefix, Some("marce".to_string()));
+        assert_eq!(update.params, vec![vec![TYPING_ACTIVE]]);
+
+        // Turned off, the next update is the chat opened by the message
+        *typing_notifications.lock().unwrap() = false;
+        server_side
+            .write_all(b":marce NOTICE ari :\x01TYPING done\x01\r\n:marce PRIVMSG ari :hola\r\n")
+            .unwrap();
+        let update = rx_frontend.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(update.command, ADD_LIST_CHATS);
+        assert_eq!(update.prefix, Some("marce".to_string()));
+    }
+}
@@ -9,10 +9,16 @@ pub const USER: &str = "USER";
 pub const LOGIN: &str = "LOGIN";
 pub const REGISTRATION: &str = "REGISTRATION";
 pub const QUIT: &str = "QUIT";
+pub const PING: &str = "PING";
+pub const PONG: &str = "PONG";
+pub const ERROR: &str = "ERROR";
+// Tells the client that the message with the label given was received
+pub const ACK: &str = "ACK";
 
 // MESSAGES EXCHANGE
 pub const PRIVMSG: &str = "PRIVMSG";
 pub const NOTICE: &str = "NOTICE";
+pub const NOTICE_AUTH: &str = "AUTH";
 
 // CHANNELS
 pub const JOIN: &str = "JOIN";
@@ -23,9 +29,19 @@ pub const INVITE: &str = "INVITE";
 pub const MODE: &str = "MODE";
 pub const KICK: &str = "KICK";
 pub const TOPIC: &str = "TOPIC";
+pub const CLOSE: &str = "CLOSE";
+// Asks the operators of an invite only channel for an invitation
+pub const KNOCK: &str = "KNOCK";
+// Keeps the channel when it is left empty, with the operator as its founder
+pub const REGISTER: &str = "REGISTER";
 
 pub const WHO: &str = "WHO";
 pub const WHOIS: &str = "WHOIS";
+pub const LUSERS: &str = "LUSERS";
+pub const ISON: &str = "ISON";
+pub const WHOWAS: &str = "WHOWAS";
+pub const MOTD: &str = "MOTD";
+pub const SILENCE: &str = "SILENCE";
 
 // modes
 pub const MODE_SET_KEY: &str = "+k";
@@ -49,6 +65,24 @@ pub const MODE_REMOVE_SECRET: &str = "-s";
 pub const MODE_SET_BAN: &str = "+b";
 pub const MODE_REMOVE_BAN: &str = "-b";
 
+pub const MODE_SET_EXCEPTION: &str = "+e";
+pub const MODE_REMOVE_EXCEPTION: &str = "-e";
+
+pub const MODE_SET_FLOOD: &str = "+f";
+pub const MODE_REMOVE_FLOOD: &str = "-f";
+
+pub const MODE_SET_MESSAGE_LENGTH: &str = "+L";
+pub const MODE_REMOVE_MESSAGE_LENGTH: &str = "-L";
+
+pub const MODE_SET_NO_EXTERNAL: &str = "+n";
+pub const MODE_REMOVE_NO_EXTERNAL: &str = "-n";
+
+pub const MODE_SET_MODERATED: &str = "+m";
+pub const MODE_REMOVE_MODERATED: &str = "-m";
+
+pub const MODE_GIVE_VOICE: &str = "+v";
+pub const MODE_TAKE_VOICE: &str = "-v";
+
 // FRONTEND COMMANDS
 pub const RECEIVED_MESSAGE: &str = "RECEIVED_MESSAGE";
 pub const ADD_LIST_CHATS: &str = "ADD_LIST_CHATS";
@@ -61,11 +95,26 @@ pub const CORRECT_REGISTRATION: &str = "CORRECT_REGISTRATION";
 pub const USER_AWAY: &str = "USER_AWAY";
 pub const ERROR_CHANNEL: &str = "ERROR_CHANNEL";
 pub const CONNECTION_ATTEMPT: &str = "CONNECTION_ATTEMPT";
+pub const CONNECTION_PROGRESS: &str = "CONNECTION_PROGRESS";
+// Sent by the UI updater when a user starts or stops typing a message to this user
+pub const TYPING_NOTIFICATION: &str = "TYPING_NOTIFICATION";
+// Sent by the UI updater when the server closes the connection, so the client can connect again
+pub const RECONNECT: &str = "RECONNECT";
+// Sent with CONNECTION_ATTEMPT, says if the connection must be encrypted
+pub const TLS_ON: &str = "TLS";
+pub const TLS_OFF: &str = "PLAIN";
 pub const DCC_CHAT: &str = "DCC_CHAT";
+// Added at the end of a DCC_CHAT offer when the chat is encrypted with a passphrase
+pub const DCC_ENCRYPTED: &str = "encrypted";
 pub const DCC_SEND: &str = "DCC_SEND";
 pub const DCC_CLOSE: &str = "DCC_CLOSE";
+// Typed as /dcc_timeout [seconds], how long a DCC_CHAT offered waits for the other user
+pub const DCC_TIMEOUT: &str = "DCC_TIMEOUT";
 pub const DCC_RESUME: &str = "DCC_RESUME";
+// Accepts a DCC_RESUME, and typed as /dcc_accept [nickname] accepts the file offered by the user
 pub const DCC_ACCEPT: &str = "DCC_ACCEPT";
+// Sent by /dcc_accept to the client, so it accepts the file pending without showing the dialog
+pub const DCC_ACCEPT_OFFER: &str = "DCC_ACCEPT_OFFER";
 pub const PAUSE: &str = "PAUSE";
 // CHANNELS FRONTEND COMMANDS
 pub const PART_CHANNEL: &str = "PART_CHANNEL";
@@ -74,12 +123,56 @@ pub const SERVER: &str = "SERVER";
 pub const AWAY: &str = "AWAY";
 pub const UNAWAY: &str = "UNAWAY";
 pub const OPER: &str = "OPER";
+pub const MUTE: &str = "MUTE";
+pub const UNMUTE: &str = "UNMUTE";
+// Typed as /ignore [nickname], the messages of the user are not shown in this session. Without a
+// nickname it lists the users ignored
+pub const IGNORE: &str = "IGNORE";
+pub const UNIGNORE: &str = "UNIGNORE";
+// Typed as /bans [channel], sent as a MODE +b without masks to list the bans of the channel
+pub const BANS: &str = "BANS";
+pub const RAW: &str = "RAW";
+pub const CONFIRM: &str = "CONFIRM";
+pub const ANNOUNCE_TOPICS: &str = "ANNOUNCE_TOPICS";
+// Typed as /events [mode], chooses how joins, parts and quits are shown
+pub const EVENTS: &str = "EVENTS";
+pub const EVENTS_INLINE: &str = "INLINE";
+pub const EVENTS_HIDDEN: &str = "HIDDEN";
+pub const EVENTS_COLLAPSED: &str = "COLLAPSED";
+// Typed as /max_file_size [megabytes/off], files bigger than it are warned before accepting them
+pub const MAX_FILE_SIZE: &str = "MAX_FILE_SIZE";
+pub const MAX_FILE_SIZE_OFF: &str = "OFF";
+// Typed as /help [command], shows the commands or the syntax of one of them
+pub const HELP: &str = "HELP";
+// Typed as /save [filename], writes the chat shown to a text file
+pub const SAVE: &str = "SAVE";
+// Typed as /rejoin, joins again the last channel parted
+pub const REJOIN: &str = "REJOIN";
+// Typed as /msg [nickname] [message], opens the chat with the user and sends the message
+pub const MSG: &str = "MSG";
+// Typed as /me, sent as a CTCP ACTION inside a PRIVMSG
+pub const ACTION: &str = "ME";
+pub const CTCP_ACTION: &str = "ACTION";
+// Typed as /typing, turns on or off the notifications of the users typing in private chats
+pub const TYPING: &str = "TYPING";
+// Sent inside a NOTICE to tell the other user of a private chat that a message is being written
+pub const CTCP_TYPING: &str = "TYPING";
+pub const CTCP_DELIMITER: char = '\x01';
 
 // SERVERS COMMANDS
 pub const SQUIT: &str = "SQUIT";
+pub const WALLOPS: &str = "WALLOPS";
+// Another name for WALLOPS, the server sends it on as a WALLOPS
+pub const GLOBOPS: &str = "GLOBOPS";
+pub const REHASH: &str = "REHASH";
 
 pub const USERS_INFO: &str = "USERS_INFO";
 pub const CHANNEL_INFO: &str = "CHANNEL_INFO";
 pub const SERVER_EXISTS: &str = "SERVER_EXISTS";
 pub const IS_OPERATOR: &str = "IS_OPERATOR";
+pub const SAVE_CHANNELS: &str = "SAVE_CHANNELS";
+// MODE of a user, like MODE ari +i, sent between the servers so all of them know it
+pub const USER_MODE: &str = "USER_MODE";
+// Sent to the server thread when the process is asked to stop, with Ctrl-C or a SIGTERM
+pub const SHUTDOWN: &str = "SHUTDOWN";
 pub const OPERATOR: &str = "OPER";
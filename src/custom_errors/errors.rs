@@ -5,3 +5,4 @@ pub const SEND_MESSAGE: &str = "Can't send message";
 pub const RECEIVE_MESSAGE: &str = "Can't receive message";
 pub const LOCK_USERS: &str = "Can't lock users";
 pub const LOCK_DCC: &str = "Can't lock DCC Chats";
+pub const MISSING_WIDGET: &str = "Couldn't get widget";
@@ -5,3 +5,4 @@ pub mod message;
 pub mod numeric_reply;
 pub mod parser;
 pub mod server_utils;
+pub mod tls;
@@ -1,9 +1,21 @@
 //!
 //! Message represents the message from client to server or server to server.
+//! Messages can start with IRCv3 tags, like @time=2022-12-16T23:49:27.000Z, that add
+//! information to them. Messages without tags are the same as before
 //!
 
+use std::collections::HashMap;
+
+// Tag with the time the server received the message, in ISO 8601 and UTC
+pub const TIME_TAG: &str = "time";
+// Tag with the number the client gave to the message, answered with an ACK with the same label
+pub const LABEL_TAG: &str = "label";
+// Start of the name of the tags the clients can send, the rest are only set by servers
+pub const CLIENT_ONLY_TAG_PREFIX: char = '+';
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Message {
+    pub tags: Option<HashMap<String, String>>,
     pub prefix: Option<String>,
     pub command: String,
     pub params: Vec<Vec<String>>,
@@ -24,28 +36,88 @@ impl Message {
         count
     }
 
+    ///
+    /// Returns true if every group of parameters has at least the amount given, like [3, 1]
+    /// for a group of three parameters followed by another one
+    ///
+    pub fn has_params(&self, amounts: &[usize]) -> bool {
+        amounts.iter().enumerate().all(|(index, amount)| {
+            matches!(self.params.get(index), Some(param_list) if param_list.len() >= *amount)
+        })
+    }
+
     ///
     /// Returns a new message with the prefix provided and clones the rest
     /// of the attributes
     ///
     pub fn set_prefix(&self, prefix: String) -> Message {
         Message {
+            tags: self.tags.clone(),
             prefix: Some(prefix),
             command: self.command.clone(),
             params: self.params.clone(),
         }
     }
 
+    ///
+    /// Returns the value of the tag given, or None if the message doesn't have it
+    ///
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.as_ref()?.get(name).map(|value| value.as_str())
+    }
+
+    ///
+    /// Returns the time the server received the message, if it told it with the time tag
+    ///
+    pub fn time(&self) -> Option<&str> {
+        self.tag(TIME_TAG)
+    }
+
+    ///
+    /// Returns the number the client gave to the message in its label tag, if it has one
+    ///
+    pub fn label(&self) -> Option<u64> {
+        self.tag(LABEL_TAG)?.parse().ok()
+    }
+
+    ///
+    /// Removes the tags only servers can set, so the time or any other tag received from a
+    /// client is not trusted. Client-only tags, like +typing, are kept
+    ///
+    pub fn remove_server_tags(&mut self) {
+        if let Some(tags) = &mut self.tags {
+            tags.retain(|name, _| name.starts_with(CLIENT_ONLY_TAG_PREFIX));
+            if tags.is_empty() {
+                self.tags = None;
+            }
+        }
+    }
+
     ///
     /// Returns message as string
     ///
     pub fn as_string(&self) -> String {
-        // Obtain prefix
-        let mut message_as_string = match self.prefix.clone() {
-            Some(prefix) => ":".to_string() + &prefix + " ",
-            None => "".to_string(),
+        // Obtain tags, sorted so the same message is always written the same way
+        let mut message_as_string = match &self.tags {
+            Some(tags) if !tags.is_empty() => {
+                let mut tags: Vec<String> = tags
+                    .iter()
+                    .map(|(name, value)| match value.is_empty() {
+                        true => name.clone(),
+                        false => format!("{}={}", name, escape_tag_value(value)),
+                    })
+                    .collect();
+                tags.sort();
+                format!("@{} ", tags.join(";"))
+            }
+            _ => "".to_string(),
         };
 
+        // Obtain prefix
+        if let Some(prefix) = &self.prefix {
+            message_as_string.push_str(&format!(":{} ", prefix));
+        }
+
         // Add command
         message_as_string.push_str(self.command.clone().as_str());
 
@@ -76,14 +148,59 @@ impl Message {
     }
 }
 
+///
+/// Escapes the characters that can't be written in the value of a tag: semicolons, spaces,
+/// backslashes, CR and LF
+///
+pub fn escape_tag_value(value: &str) -> String {
+    let mut escaped = String::new();
+    for character in value.chars() {
+        match character {
+            ';' => escaped.push_str("\\:"),
+            ' ' => escaped.push_str("\\s"),
+            '\\' => escaped.push_str("\\\\"),
+            '\r' => escaped.push_str("\\r"),
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+///
+/// Returns the value of a tag as it was before escaping it. A backslash before any other
+/// character is dropped, and one at the end is ignored
+///
+pub fn unescape_tag_value(value: &str) -> String {
+    let mut unescaped = String::new();
+    let mut characters = value.chars();
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            unescaped.push(character);
+            continue;
+        }
+        match characters.next() {
+            Some(':') => unescaped.push(';'),
+            Some('s') => unescaped.push(' '),
+            Some('r') => unescaped.push('\r'),
+            Some('n') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => {}
+        }
+    }
+    unescaped
+}
+
 /************************************TESTS*******************************************/
 
 #[cfg(test)]
 mod test {
-    use super::Message;
+    use super::{escape_tag_value, unescape_tag_value, Message};
+    use std::collections::HashMap;
 
     fn setup() -> Message {
         Message {
+            tags: None,
             prefix: None,
             command: "COMMAND".to_string(),
             params: vec![
@@ -117,6 +234,16 @@ mod test {
         assert_eq!(message.params_total_count(), 4);
     }
 
+    #[test]
+    fn has_params_checks_the_amount_of_every_group() {
+        let message = setup();
+
+        assert!(message.has_params(&[1, 1, 2]));
+        assert!(message.has_params(&[1]));
+        assert!(!message.has_params(&[2]));
+        assert!(!message.has_params(&[1, 1, 2, 1]));
+    }
+
     #[test]
     fn message_with_prefix_is_correct() {
         let message = setup();
@@ -133,4 +260,50 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn message_with_tags_is_written_with_them_first() {
+        let mut message = setup();
+        message.prefix = Some("prefix".to_string());
+        message.tags = Some(HashMap::from([
+            ("time".to_string(), "2022-12-16T23:49:27.000Z".to_string()),
+            ("note".to_string(), "a b;c".to_string()),
+            ("bot".to_string(), "".to_string()),
+        ]));
+
+        assert_eq!(message.time(), Some("2022-12-16T23:49:27.000Z"));
+        assert_eq!(
+            message.as_string(),
+            "@bot;note=a\\sb\\:c;time=2022-12-16T23:49:27.000Z :prefix COMMAND param param param,param\r\n"
+        );
+        assert_eq!(
+            unescape_tag_value(&escape_tag_value("a b;c\\d")),
+            "a b;c\\d"
+        );
+
+        message.tags = Some(HashMap::new());
+        assert_eq!(
+            message.as_string(),
+            ":prefix COMMAND param param param,param\r\n"
+        );
+    }
+
+    #[test]
+    fn only_client_only_tags_are_kept_when_server_tags_are_removed() {
+        let mut message = setup();
+        message.tags = Some(HashMap::from([
+            ("time".to_string(), "1999-01-01T00:00:00.000Z".to_string()),
+            ("+typing".to_string(), "active".to_string()),
+        ]));
+        message.remove_server_tags();
+        assert_eq!(message.time(), None);
+        assert_eq!(message.tag("+typing"), Some("active"));
+
+        message.tags = Some(HashMap::from([(
+            "time".to_string(),
+            "1999-01-01T00:00:00.000Z".to_string(),
+        )]));
+        message.remove_server_tags();
+        assert_eq!(message.tags, None);
+    }
 }
@@ -32,6 +32,21 @@ pub const RPL_CORRECTLOGIN_MSG: &str = "Login successful";
 pub const RPL_CORRECTREGISTRATION_NUM: &str = "3";
 pub const RPL_CORRECTREGISTRATION_MSG: &str = "Registration successful";
 
+pub const ERR_WEAKPASSWORD_NUM: &str = "10";
+
+// Sent after RPL_CORRECTLOGIN and RPL_CORRECTREGISTRATION, as RFC 2812 welcomes the users.
+// They keep the three digits so they are not taken for the replies above
+pub const RPL_WELCOME_NUM: &str = "001";
+pub const RPL_WELCOME_MSG: &str = "Welcome to the Internet Relay Network";
+
+pub const RPL_YOURHOST_NUM: &str = "002";
+
+// The creation time goes in seconds before the message, the text can't have its colons
+pub const RPL_CREATED_NUM: &str = "003";
+pub const RPL_CREATED_MSG: &str = "This server was created";
+
+pub const RPL_MYINFO_NUM: &str = "004";
+
 pub const ERR_ALREADYREGISTRED_NUM: &str = "462";
 pub const ERR_ALREADYREGISTRED_MSG: &str = "You may not reregister";
 
@@ -39,6 +54,9 @@ pub const ERR_ALREADYREGISTRED_MSG: &str = "You may not reregister";
 pub const ERR_PASSWDMISMATCH_NUM: &str = "464";
 pub const ERR_PASSWDMISMATCH_MSG: &str = "Password incorrect";
 
+pub const ERR_NOOPERHOST_NUM: &str = "491";
+pub const ERR_NOOPERHOST_MSG: &str = "No O-lines for your host";
+
 pub const RPL_YOUREOPER_NUM: &str = "381";
 pub const RPL_YOUREOPER_MSG: &str = "You are now an IRC operator";
 
@@ -55,6 +73,9 @@ pub const ERR_CHANNELISFULL_MSG: &str = "Cannot join channel (+l)";
 pub const ERR_BANNEDFROMCHAN_NUM: &str = "474";
 pub const ERR_BANNEDFROMCHAN_MSG: &str = "Cannot join channel (+b)";
 
+pub const ERR_BANLISTFULL_NUM: &str = "478";
+pub const ERR_BANLISTFULL_MSG: &str = "Channel list is full";
+
 pub const ERR_CHANNELHASKEY_NUM: &str = "476";
 pub const ERR_CHANNELHASKEY_MSG: &str = "The channel has a key";
 
@@ -75,12 +96,32 @@ pub const ERR_INVITEONLYCHAN_MSG: &str = "Cannot join channel (+i)";
 pub const ERR_NOSUCHCHANNEL_NUM: &str = "403";
 pub const ERR_NOSUCHCHANNEL_MSG: &str = "No such channel";
 
+pub const ERR_TOOMANYTARGETS_NUM: &str = "407";
+pub const ERR_TOOMANYTARGETS_MSG: &str = "Too many channels in a single JOIN";
+
 pub const RPL_MODESET_NUM: &str = "9";
 pub const RPL_MODESET_MSG: &str = "Mode was set correctly";
 
+pub const RPL_CHANNELMODEIS_NUM: &str = "324";
+
 pub const ERR_INVALIDLIMIT_NUM: &str = "8";
 pub const ERR_INVALIDLIMIT_MSG: &str = "limit is invalid";
 
+pub const ERR_INVALIDFLOOD_NUM: &str = "11";
+pub const ERR_INVALIDFLOOD_MSG: &str = "flood threshold is invalid";
+
+pub const RPL_CHANNELREGISTERED_NUM: &str = "13";
+pub const RPL_CHANNELREGISTERED_MSG: &str = "Channel registered, it is kept when it is left empty";
+
+pub const ERR_ALREADYFOUNDED_NUM: &str = "14";
+pub const ERR_ALREADYFOUNDED_MSG: &str = "Channel already registered by another user";
+
+pub const ERR_CANNOTSENDTOCHAN_NUM: &str = "404";
+pub const ERR_CANNOTSENDTOCHAN_MSG: &str = "Cannot send to channel";
+
+pub const ERR_MESSAGETOOLONG_NUM: &str = "12";
+pub const ERR_MESSAGETOOLONG_MSG: &str = "Cannot send to channel, message is too long (+L)";
+
 pub const ERR_NOTONCHANNEL_NUM: &str = "442";
 pub const ERR_NOTONCHANNEL_MSG: &str = "You're not on that channel";
 
@@ -93,6 +134,13 @@ pub const RPL_LISTEND_MSG: &str = "End of /LIST";
 pub const RPL_LIST_NUM: &str = "322";
 
 pub const RPL_NAMEREPLY_NUM: &str = "353";
+// Visibility of the channel sent in RPL_NAMEREPLY, as RFC 2812 specifies
+pub const RPL_NAMEREPLY_PUBLIC: &str = "=";
+pub const RPL_NAMEREPLY_SECRET: &str = "@";
+// Goes before the nicknames of the channel operators in RPL_NAMEREPLY
+pub const RPL_NAMEREPLY_OPERATOR: char = '@';
+// Goes before the nicknames of the users with voice in RPL_NAMEREPLY
+pub const RPL_NAMEREPLY_VOICED: char = '+';
 
 pub const RPL_ENDOFNAMES_NUM: &str = "366";
 pub const RPL_ENDOFNAMES_MSG: &str = "End of /NAMES list";
@@ -102,9 +150,33 @@ pub const RPL_TOPIC_NUM: &str = "332";
 pub const RPL_NOTOPIC_NUM: &str = "331";
 pub const RPL_NOTOPIC_MSG: &str = "No topic is set";
 
+pub const RPL_TOPICWHOTIME_NUM: &str = "333";
+
+pub const RPL_CREATIONTIME_NUM: &str = "329";
+
 pub const ERR_UNKNOWNMODE_NUM: &str = "472";
 pub const ERR_UNKNOWNMODE_MSG: &str = "is unknown mode char to me";
 
+pub const RPL_BANLIST_NUM: &str = "367";
+
+pub const RPL_ENDOFBANLIST_NUM: &str = "368";
+pub const RPL_ENDOFBANLIST_MSG: &str = "End of channel ban list";
+
+pub const RPL_EXCEPTLIST_NUM: &str = "348";
+
+pub const RPL_ENDOFEXCEPTLIST_NUM: &str = "349";
+pub const RPL_ENDOFEXCEPTLIST_MSG: &str = "End of channel exception list";
+
+// KNOCK REPLIES
+pub const RPL_KNOCKDLVR_NUM: &str = "711";
+pub const RPL_KNOCKDLVR_MSG: &str = "Your KNOCK has been delivered";
+
+pub const ERR_CHANOPEN_NUM: &str = "713";
+pub const ERR_CHANOPEN_MSG: &str = "Channel is open";
+
+pub const ERR_KNOCKONCHAN_NUM: &str = "714";
+pub const ERR_KNOCKONCHAN_MSG: &str = "You are already on that channel";
+
 //SERVER
 pub const ERR_NORECIPIENT_NUM: &str = "411";
 pub const ERR_NORECIPIENT_MSG: &str = "No recipient given";
@@ -127,12 +199,51 @@ pub const RPL_ENDOFWHOIS_MSG: &str = "End of /WHOIS list";
 pub const RPL_WHOISCHANNELS_NUM: &str = "319";
 pub const RPL_WHOISCHANNELS_MSG: &str = "channel name";
 
+pub const RPL_WHOISIDLE_NUM: &str = "317";
+pub const RPL_WHOISIDLE_MSG: &str = "seconds idle, signon time";
+
+// WHOWAS REPLIES
+pub const RPL_WHOWASUSER_NUM: &str = "314";
+
+pub const RPL_ENDOFWHOWAS_NUM: &str = "369";
+pub const RPL_ENDOFWHOWAS_MSG: &str = "End of WHOWAS";
+
+pub const ERR_WASNOSUCHNICK_NUM: &str = "406";
+pub const ERR_WASNOSUCHNICK_MSG: &str = "There was no such nickname";
+
 // WHO REPLIES
 pub const RPL_ENDOFWHO_NUM: &str = "315";
 pub const RPL_ENDOFWHO_MSG: &str = "End of WHO list";
 
 pub const RPL_WHOREPLY_NUM: &str = "352";
 pub const RPL_WHOREPLY_MSG: &str = "WHO reply";
+// Flags of RPL_WHOREPLY for the members of a channel, they are here or gone (away), followed by
+// their prefix in the channel
+pub const RPL_WHOREPLY_HERE: char = 'H';
+pub const RPL_WHOREPLY_GONE: char = 'G';
+
+// LUSERS REPLIES
+pub const RPL_LUSERCLIENT_NUM: &str = "251";
+
+pub const RPL_LUSERCHANNELS_NUM: &str = "254";
+pub const RPL_LUSERCHANNELS_MSG: &str = "channels formed";
+
+pub const RPL_LUSERME_NUM: &str = "255";
+
+// ISON REPLIES
+pub const RPL_ISON_NUM: &str = "303";
+
+// MOTD REPLIES
+pub const RPL_MOTDSTART_NUM: &str = "375";
+pub const RPL_MOTDSTART_MSG: &str = "Message of the day";
+
+pub const RPL_MOTD_NUM: &str = "372";
+
+pub const RPL_ENDOFMOTD_NUM: &str = "376";
+pub const RPL_ENDOFMOTD_MSG: &str = "End of /MOTD command";
+
+pub const ERR_NOMOTD_NUM: &str = "422";
+pub const ERR_NOMOTD_MSG: &str = "MOTD File is missing";
 
 // AWAY REPLIES
 pub const RPL_UNAWAY_MSG: &str = "You are no longer marked as being away";
@@ -142,12 +253,35 @@ pub const RPL_AWAY_NUM: &str = "301";
 pub const RPL_NOWAWAY_MSG: &str = "You have been marked as being away";
 pub const RPL_NOWAWAY_NUM: &str = "306";
 
+// USER MODE REPLIES
+pub const RPL_UMODEIS_NUM: &str = "221";
+
+pub const ERR_UMODEUNKNOWNFLAG_NUM: &str = "501";
+pub const ERR_UMODEUNKNOWNFLAG_MSG: &str = "Unknown MODE flag";
+
+pub const ERR_USERSDONTMATCH_NUM: &str = "502";
+pub const ERR_USERSDONTMATCH_MSG: &str = "Cannot change mode for other users";
+
+// SILENCE REPLIES
+pub const RPL_SILELIST_NUM: &str = "271";
+
+pub const RPL_ENDOFSILELIST_NUM: &str = "272";
+pub const RPL_ENDOFSILELIST_MSG: &str = "End of Silence List";
+
 // SQUIT REPLIES
 pub const ERR_NOPRIVILEGES_NUM: &str = "481";
 pub const ERR_NOPRIVILEGES_MSG: &str = "Permission Denied- You're not an IRC operator";
 
 pub const ERR_NOSUCHSERVER_NUM: &str = "402";
 pub const ERR_NOSUCHSERVER_MSG: &str = "No such server";
+
+// REHASH REPLIES
+pub const RPL_REHASHING_NUM: &str = "382";
+pub const RPL_REHASHING_MSG: &str = "Rehashing";
+
+pub const ERR_FILEERROR_NUM: &str = "424";
+pub const ERR_FILEERROR_MSG: &str = "File error doing rehash";
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct NumericReply {
     message: String,
@@ -1,10 +1,12 @@
-use crate::message::Message;
-use std::collections::HashSet;
+use crate::message::{unescape_tag_value, Message};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead};
 
 const CR: u8 = 13;
 const LF: u8 = 10;
 const COLON: u8 = 58;
 const SPACE: u8 = 32;
+const AT: u8 = 64;
 
 /// Receives a &str and returns the index of the next whitespace
 pub fn next_whitespace(slice: &str) -> Option<usize> {
@@ -84,7 +86,21 @@ fn param_is_valid(slice: &str) -> bool {
     true
 }
 
-/// Returns a Message struct with the data of the received String, or an error if the String is invalid
+/// Receives the tags of a message, without the '@', and returns them by name. Tags without a
+/// value have an empty one
+fn parse_tags(slice: &str) -> HashMap<String, String> {
+    slice
+        .split(';')
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| match tag.split_once('=') {
+            Some((name, value)) => (name.to_string(), unescape_tag_value(value)),
+            None => (tag.to_string(), String::new()),
+        })
+        .collect()
+}
+
+/// Returns a Message struct with the data of the received String, or an error if the String is invalid.
+/// The tags the message starts with, if it has any, are kept in the tags of the Message
 ///
 /// # Arguments
 ///
@@ -93,10 +109,24 @@ fn param_is_valid(slice: &str) -> bool {
 pub fn parse(message: String) -> Result<Message, String> {
     let mut remaining_message = message.as_str();
 
+    let tags: Option<HashMap<String, String>>;
     let prefix: Option<String>;
     let command: String;
     let mut params: Vec<Vec<String>> = vec![];
 
+    // If the first character is an at sign, the message starts with tags
+    if remaining_message.as_bytes().first() == Some(&AT) {
+        let next_space_index = match next_whitespace(remaining_message) {
+            Some(num) => num,
+            None => return Err("Message is broken".to_string()),
+        };
+
+        tags = Some(parse_tags(&remaining_message[1..next_space_index]));
+        remaining_message = erase_front_whitespaces(&remaining_message[next_space_index..]);
+    } else {
+        tags = None;
+    }
+
     // If the first character is a colon, the message has a prefix
     if remaining_message.as_bytes().first() == Some(&COLON) {
         let next_space_index = match next_whitespace(remaining_message) {
@@ -120,6 +150,7 @@ pub fn parse(message: String) -> Result<Message, String> {
             let index = get_index_of_end_of_message(remaining_message)?;
             command = remaining_message[..index].to_string();
             return Ok(Message {
+                tags,
                 prefix,
                 command,
                 params,
@@ -139,6 +170,7 @@ pub fn parse(message: String) -> Result<Message, String> {
             // Push last param without splitting the remaining message, and skipping ":". Then return the message
             process_last_param(&remaining_message[1..], &mut params, '\n')?;
             return Ok(Message {
+                tags,
                 prefix,
                 command,
                 params,
@@ -151,6 +183,7 @@ pub fn parse(message: String) -> Result<Message, String> {
                 // If there are no more whitespaces, push last param splitted by comma and return the message
                 process_last_param(remaining_message, &mut params, ',')?;
                 return Ok(Message {
+                    tags,
                     prefix,
                     command,
                     params,
@@ -177,9 +210,146 @@ pub fn parse(message: String) -> Result<Message, String> {
     }
 }
 
+///
+/// Returns the name in lowercase following the casemapping of RFC 2812, where the characters
+/// {}|^ are the lowercase of []\~. Nicknames and channel names that only differ in case
+/// are the same for IRC, so they are always compared and stored with this form
+///
+pub fn irc_lowercase(name: &str) -> String {
+    name.chars()
+        .map(|character| match character {
+            '[' => '{',
+            ']' => '}',
+            '\\' => '|',
+            '~' => '^',
+            _ => character.to_ascii_lowercase(),
+        })
+        .collect()
+}
+
+///
+/// Checks if two nicknames or channel names are the same for IRC
+///
+pub fn irc_eq(name: &str, other_name: &str) -> bool {
+    irc_lowercase(name) == irc_lowercase(other_name)
+}
+
+///
+/// Reads a line like read_line does, but the bytes that are not valid UTF-8 are replaced with
+/// U+FFFD instead of failing, so a peer that sends them doesn't end the connection. When the
+/// read times out in the middle of a line its bytes are kept in pending until the rest arrives,
+/// a character cut in two is not replaced
+///
+pub fn read_line_lossy(
+    reader: &mut impl BufRead,
+    pending: &mut Vec<u8>,
+    line: &mut String,
+) -> Result<usize, io::Error> {
+    let bytes_read = reader.read_until(LF, pending)?;
+    line.push_str(&String::from_utf8_lossy(pending));
+    pending.clear();
+    Ok(bytes_read)
+}
+
 #[cfg(test)]
 mod tests {
-    use super::parse;
+    use super::{irc_eq, irc_lowercase, parse, read_line_lossy};
+    use std::io::{self, BufRead, BufReader, ErrorKind, Read};
+
+    #[test]
+    fn names_are_lowercased_with_rfc_casemapping() {
+        assert_eq!(irc_lowercase("Ari"), "ari");
+        assert_eq!(irc_lowercase("#Chan"), "#chan");
+        assert_eq!(irc_lowercase("[Wiz]\\~"), "{wiz}|^");
+        assert_eq!(irc_lowercase("{wiz}|^"), "{wiz}|^");
+    }
+
+    #[test]
+    fn names_that_only_differ_in_case_are_equal() {
+        assert!(irc_eq("Ari", "ari"));
+        assert!(irc_eq("#Chan", "#chan"));
+        assert!(irc_eq("Wiz[]\\", "wiz{}|"));
+        assert!(irc_eq("nick~", "NICK^"));
+        assert!(!irc_eq("ari", "marce"));
+        assert!(!irc_eq("#chan", "&chan"));
+    }
+
+    #[test]
+    fn labeled_message_is_parsed_with_its_label() {
+        let message = parse("@label=12 PRIVMSG ari :hola\r\n".to_string()).unwrap();
+
+        assert_eq!(message.label(), Some(12));
+        assert_eq!(message.command, "PRIVMSG");
+        assert_eq!(message.params, vec![vec!["ari"], vec!["hola"]]);
+        assert_eq!(message.as_string(), "@label=12 PRIVMSG ari hola\r\n");
+
+        let message = parse(":WiZ NICK Kilroy\r\n".to_string()).unwrap();
+        assert_eq!(message.label(), None);
+        assert_eq!(message.prefix, Some("WiZ".to_string()));
+
+        let message = parse("@label=doce PRIVMSG ari :hola\r\n".to_string()).unwrap();
+        assert_eq!(message.label(), None);
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced_and_the_next_lines_are_read() {
+        let mut reader = BufReader::new(&b"PRIVMSG ari :hol\xffa\r\nPRIVMSG ari :chau\r\n"[..]);
+        let mut pending = Vec::new();
+        let mut line = String::new();
+
+        read_line_lossy(&mut reader, &mut pending, &mut line).unwrap();
+        assert_eq!(line, "PRIVMSG ari :hol\u{FFFD}a\r\n");
+        assert_eq!(parse(line.clone()).unwrap().params[1], vec!["hol\u{FFFD}a"]);
+        line.clear();
+
+        read_line_lossy(&mut reader, &mut pending, &mut line).unwrap();
+        assert_eq!(line, "PRIVMSG ari :chau\r\n");
+        line.clear();
+
+        assert_eq!(
+            read_line_lossy(&mut reader, &mut pending, &mut line).unwrap(),
+            0
+        );
+    }
+
+    // Gives the bytes received in parts and times out between them, like a socket with a timeout
+    struct SlowReader {
+        parts: Vec<&'static [u8]>,
+        timed_out: bool,
+    }
+
+    impl Read for SlowReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if !self.timed_out && !self.parts.is_empty() {
+                self.timed_out = true;
+                return Err(io::Error::new(ErrorKind::WouldBlock, "timed out"));
+            }
+            self.timed_out = false;
+            if self.parts.is_empty() {
+                return Ok(0);
+            }
+            let part = self.parts.remove(0);
+            buf[..part.len()].copy_from_slice(part);
+            Ok(part.len())
+        }
+    }
+
+    #[test]
+    fn character_cut_by_a_timeout_is_kept_until_the_rest_arrives() {
+        // ñ is 0xC3 0xB1
+        let mut reader = BufReader::new(SlowReader {
+            parts: vec![b"PRIVMSG ari :ni\xc3", b"\xb1o\r\n"],
+            timed_out: false,
+        });
+        let mut pending = Vec::new();
+        let mut line = String::new();
+
+        while let Err(error) = read_line_lossy(&mut reader, &mut pending, &mut line) {
+            assert_eq!(error.kind(), ErrorKind::WouldBlock);
+        }
+        assert_eq!(line, "PRIVMSG ari :niño\r\n");
+        assert!(reader.fill_buf().unwrap().is_empty());
+    }
 
     #[test]
     fn message_with_prefix_and_one_simple_parameter_is_parsed_correctly() {
@@ -272,4 +442,24 @@ mod tests {
         assert_eq!(message.command, "NAMES");
         assert!(message.params.is_empty());
     }
+
+    #[test]
+    fn message_with_tags_keeps_them_and_is_parsed_like_without_them() {
+        let message_str =
+            "@time=2022-12-16T23:49:27.000Z;note=yes\\:no\\sthanks;bot :ari PRIVMSG #rust :Hello !\r\n";
+        let message = parse(message_str.to_string()).unwrap();
+
+        assert_eq!(message.time(), Some("2022-12-16T23:49:27.000Z"));
+        assert_eq!(message.tag("note"), Some("yes;no thanks"));
+        assert_eq!(message.tag("bot"), Some(""));
+        assert_eq!(message.tag("account"), None);
+
+        let untagged = parse(":ari PRIVMSG #rust :Hello !\r\n".to_string()).unwrap();
+        assert_eq!(untagged.tags, None);
+        assert_eq!(message.prefix, untagged.prefix);
+        assert_eq!(message.command, untagged.command);
+        assert_eq!(message.params, untagged.params);
+
+        assert!(parse("@time=2022-12-16T23:49:27.000Z\r\n".to_string()).is_err());
+    }
 }
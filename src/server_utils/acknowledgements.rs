@@ -0,0 +1,87 @@
+//!
+//! The clients number the messages they send in their label tag. Each labeled message is
+//! answered with an ACK that has the same label, so the client knows it arrived. Clients send
+//! again the messages whose ACK doesn't arrive in time, so a label already received is
+//! acknowledged again but its message is not handled twice
+//!
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{
+    commands::ACK,
+    message::{Message, LABEL_TAG},
+};
+
+#[derive(Debug, Default, Clone)]
+pub struct Acknowledgements {
+    // Every label up to this one was received
+    received_up_to: u64,
+    // Labels received after a label that is still missing, a retransmission can arrive late
+    received_after: BTreeSet<u64>,
+}
+
+impl Acknowledgements {
+    ///
+    /// Saves the label received. Returns false if it was already received, then the message is
+    /// a retransmission and must not be handled again
+    ///
+    pub fn received(&mut self, label: u64) -> bool {
+        if label <= self.received_up_to || !self.received_after.insert(label) {
+            return false;
+        }
+        while self.received_after.remove(&(self.received_up_to + 1)) {
+            self.received_up_to += 1;
+        }
+        true
+    }
+}
+
+///
+/// Returns the ACK that tells the client the message with the label arrived
+///
+pub fn acknowledgement(label: u64) -> String {
+    Message {
+        tags: Some(HashMap::from([(LABEL_TAG.to_string(), label.to_string())])),
+        prefix: None,
+        command: ACK.to_string(),
+        params: vec![],
+    }
+    .as_string()
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::{acknowledgement, Acknowledgements};
+
+    #[test]
+    fn labels_already_received_are_retransmissions() {
+        let mut acknowledgements = Acknowledgements::default();
+
+        assert!(acknowledgements.received(1));
+        assert!(acknowledgements.received(2));
+        assert!(!acknowledgements.received(2));
+        assert!(!acknowledgements.received(1));
+        assert!(acknowledgements.received(5));
+        assert!(!acknowledgements.received(5));
+    }
+
+    #[test]
+    fn label_received_after_a_later_one_is_not_a_retransmission() {
+        let mut acknowledgements = Acknowledgements::default();
+
+        assert!(acknowledgements.received(2));
+        assert!(acknowledgements.received(1));
+        assert!(!acknowledgements.received(1));
+        assert!(!acknowledgements.received(2));
+        assert!(acknowledgements.received(4));
+        assert!(acknowledgements.received(3));
+        assert!(!acknowledgements.received(4));
+    }
+
+    #[test]
+    fn acknowledgement_has_the_label_of_the_message() {
+        assert_eq!(acknowledgement(7), "@label=7 ACK\r\n");
+    }
+}
@@ -3,42 +3,201 @@
 //!
 
 use crate::{
-    commands::{CHANNEL_INFO, MODE_SET_INVITE, MODE_SET_KEY},
+    commands::{CHANNEL_INFO, MODE_SET_INVITE, MODE_SET_KEY, NOTICE, PRIVMSG},
     custom_errors::errors::NONCRITICAL,
-    message::Message,
+    message::{Message, TIME_TAG},
     numeric_reply::{
-        NumericReply, ERR_BADCHANNELKEY_MSG, ERR_BADCHANNELKEY_NUM, ERR_BANNEDFROMCHAN_MSG,
+        NumericReply, ERR_ALREADYFOUNDED_MSG, ERR_ALREADYFOUNDED_NUM, ERR_BADCHANNELKEY_MSG,
+        ERR_BADCHANNELKEY_NUM, ERR_BANLISTFULL_MSG, ERR_BANLISTFULL_NUM, ERR_BANNEDFROMCHAN_MSG,
         ERR_BANNEDFROMCHAN_NUM, ERR_CHANNELHASKEY_MSG, ERR_CHANNELHASKEY_NUM,
         ERR_CHANNELISFULL_MSG, ERR_CHANNELISFULL_NUM, ERR_CHANOPRIVSNEEDED_MSG,
-        ERR_CHANOPRIVSNEEDED_NUM, ERR_INVALIDLIMIT_MSG, ERR_INVALIDLIMIT_NUM,
-        ERR_INVITEONLYCHAN_MSG, ERR_INVITEONLYCHAN_NUM, ERR_KEYSET_MSG, ERR_KEYSET_NUM,
-        ERR_NEEDMOREPARAMS_MSG, ERR_NEEDMOREPARAMS_NUM, ERR_NOSUCHCHANNEL_MSG, ERR_NOSUCHNICK_MSG,
-        ERR_NOSUCHNICK_NUM, ERR_NOTONCHANNEL_MSG, ERR_NOTONCHANNEL_NUM, ERR_TOOMANYCHANNELS_MSG,
-        ERR_TOOMANYCHANNELS_NUM, ERR_USERONCHANNEL_MSG, ERR_USERONCHANNEL_NUM, RPL_NOTOPIC_MSG,
-        RPL_NOTOPIC_NUM, RPL_TOPIC_NUM,
+        ERR_CHANOPRIVSNEEDED_NUM, ERR_INVALIDFLOOD_MSG, ERR_INVALIDFLOOD_NUM, ERR_INVALIDLIMIT_MSG,
+        ERR_INVALIDLIMIT_NUM, ERR_INVITEONLYCHAN_MSG, ERR_INVITEONLYCHAN_NUM, ERR_KEYSET_MSG,
+        ERR_KEYSET_NUM, ERR_NEEDMOREPARAMS_MSG, ERR_NEEDMOREPARAMS_NUM, ERR_NOSUCHCHANNEL_MSG,
+        ERR_NOSUCHNICK_MSG, ERR_NOSUCHNICK_NUM, ERR_NOTONCHANNEL_MSG, ERR_NOTONCHANNEL_NUM,
+        ERR_TOOMANYCHANNELS_MSG, ERR_TOOMANYCHANNELS_NUM, ERR_USERONCHANNEL_MSG,
+        ERR_USERONCHANNEL_NUM, RPL_CHANNELMODEIS_NUM, RPL_CREATIONTIME_NUM, RPL_NAMEREPLY_OPERATOR,
+        RPL_NAMEREPLY_PUBLIC, RPL_NAMEREPLY_SECRET, RPL_NAMEREPLY_VOICED, RPL_NOTOPIC_MSG,
+        RPL_NOTOPIC_NUM, RPL_TOPICWHOTIME_NUM, RPL_TOPIC_NUM, RPL_WHOREPLY_GONE, RPL_WHOREPLY_HERE,
+        RPL_WHOREPLY_NUM,
     },
-    server_utils::user::User,
+    parser::{irc_eq, irc_lowercase},
+    server_utils::{logger::server_time_text, user::User},
 };
 use std::{
-    collections::{HashMap, HashSet},
-    sync::{Arc, Mutex},
+    collections::{HashMap, HashSet, VecDeque},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
+// Time the flood protection stays on when the +f mode doesn't give one
+pub const DEFAULT_FLOOD_COOLDOWN: Duration = Duration::from_secs(60);
+// Masks a channel keeps in its ban list, and in its exception list
+pub const MAX_BANS: usize = 30;
+// Added before the text of the messages replayed to users that join, so they are told apart
+pub const BACKLOG_MARKER: &str = "(backlog)";
+
 use crate::custom_errors::server_error::ServerError;
 
 #[derive(Clone, Debug)]
 pub struct Channel {
     pub name: String,
     pub topic: Option<String>,
-    pub users: HashMap<String, User>,
+    pub topic_set_at: Option<u128>, //milliseconds since epoch when topic was set
+    pub topic_setter: Option<String>, //nickname of the user that set the topic
+    pub created_at: u64,            //seconds since epoch when the channel was created
+    pub users: HashMap<String, User>, //users by their nickname in lowercase
     pub key: Option<String>,
     pub operators: Vec<String>, //nicknames of users given operator privileges
+    pub voiced: Vec<String>,    //nicknames of users that can talk when the channel is moderated
     pub invites: Vec<String>,   //nicknames of users invited
     pub limit: Option<usize>,
     pub enter_mode: Option<String>,
     pub operator_settable_topic: bool,
     pub secret: bool,
     pub banned: HashSet<String>,
+    pub exceptions: HashSet<String>, //masks of users that can join even if they are banned
+    pub flood_protection: Option<FloodProtection>,
+    pub max_message_length: Option<usize>, //characters allowed in the messages sent to the channel
+    pub no_external_messages: bool,        //only the members can send messages to the channel (+n)
+    pub moderated: bool, //only operators and voiced users can send messages to the channel (+m)
+    pub backlog: VecDeque<Message>, //last messages sent to the channel, oldest first
+    pub founder: Option<String>, //nickname of the operator that registered the channel
+}
+
+///
+/// Flood protection of a channel (+f mode). If more than max_events users join in the interval
+/// the channel becomes invite only until the cooldown ends, and if more than max_events messages
+/// are sent only its operators and voiced users can talk until then
+///
+#[derive(Clone, Debug)]
+pub struct FloodProtection {
+    pub max_events: usize,
+    pub interval: Duration,
+    pub cooldown: Duration,
+    joins: VecDeque<Instant>,
+    protected_until: Option<Instant>,
+    messages: VecDeque<Instant>,
+    muted_until: Option<Instant>,
+}
+
+impl FloodProtection {
+    ///
+    /// Returns a FloodProtection that has not seen any join nor message
+    ///
+    pub fn new(max_events: usize, interval: Duration, cooldown: Duration) -> Self {
+        FloodProtection {
+            max_events,
+            interval,
+            cooldown,
+            joins: VecDeque::new(),
+            protected_until: None,
+            messages: VecDeque::new(),
+            muted_until: None,
+        }
+    }
+
+    ///
+    /// Parses a threshold with the form events:seconds or events:seconds:cooldown_seconds
+    ///
+    pub fn from_threshold(threshold: &str) -> Option<Self> {
+        let values: Vec<u64> = threshold
+            .split(':')
+            .map(|value| value.parse::<u64>().ok())
+            .collect::<Option<Vec<u64>>>()?;
+        let (events, seconds, cooldown) = match values.as_slice() {
+            [events, seconds] => (*events, *seconds, DEFAULT_FLOOD_COOLDOWN),
+            [events, seconds, cooldown] => (*events, *seconds, Duration::from_secs(*cooldown)),
+            _ => return None,
+        };
+        if events == 0 || seconds == 0 {
+            return None;
+        }
+        Some(FloodProtection::new(
+            events as usize,
+            Duration::from_secs(seconds),
+            cooldown,
+        ))
+    }
+
+    ///
+    /// Returns the threshold with the form events:seconds:cooldown_seconds
+    ///
+    pub fn threshold(&self) -> String {
+        format!(
+            "{}:{}:{}",
+            self.max_events,
+            self.interval.as_secs(),
+            self.cooldown.as_secs()
+        )
+    }
+
+    ///
+    /// Records a join. Returns true if the join exceeded the threshold and the protection
+    /// was turned on
+    ///
+    pub fn record_join(&mut self, now: Instant) -> bool {
+        let exceeded = record_event(&mut self.joins, self.max_events, self.interval, now);
+        if !exceeded || self.is_protecting(now) {
+            return false;
+        }
+        self.joins.clear();
+        self.protected_until = Some(now + self.cooldown);
+        true
+    }
+
+    ///
+    /// Records a message sent to the channel. Returns true if the message exceeded the
+    /// threshold and the channel was muted
+    ///
+    pub fn record_message(&mut self, now: Instant) -> bool {
+        let exceeded = record_event(&mut self.messages, self.max_events, self.interval, now);
+        if !exceeded || self.is_muting(now) {
+            return false;
+        }
+        self.messages.clear();
+        self.muted_until = Some(now + self.cooldown);
+        true
+    }
+
+    ///
+    /// Checks if the protection is on, it turns off by itself once the cooldown ends
+    ///
+    pub fn is_protecting(&self, now: Instant) -> bool {
+        match self.protected_until {
+            Some(until) => now < until,
+            None => false,
+        }
+    }
+
+    ///
+    /// Checks if only the operators and the voiced users can talk, it ends with the cooldown too
+    ///
+    pub fn is_muting(&self, now: Instant) -> bool {
+        match self.muted_until {
+            Some(until) => now < until,
+            None => false,
+        }
+    }
+}
+
+///
+/// Records the event and checks if there were more than max_events in the interval.
+/// The older ones are forgotten
+///
+fn record_event(
+    events: &mut VecDeque<Instant>,
+    max_events: usize,
+    interval: Duration,
+    now: Instant,
+) -> bool {
+    while let Some(event) = events.front() {
+        if now.duration_since(*event) < interval {
+            break;
+        }
+        events.pop_front();
+    }
+    events.push_back(now);
+    events.len() > max_events
 }
 
 impl Channel {
@@ -46,20 +205,31 @@ impl Channel {
     /// Returns a Channel
     ///
     pub fn new(name: String, operator: &User) -> Channel {
-        let users = HashMap::from([(operator.nickname.clone(), operator.clone())]);
+        let users = HashMap::from([(irc_lowercase(&operator.nickname), operator.clone())]);
 
         Channel {
             name,
             topic: None,
+            topic_set_at: None,
+            topic_setter: None,
+            created_at: now_in_seconds(),
             users,
             key: None,
             operators: vec![operator.nickname.clone()],
+            voiced: Vec::new(),
             invites: Vec::new(),
             limit: None,
             enter_mode: None,
             operator_settable_topic: false,
             secret: false,
             banned: HashSet::new(),
+            exceptions: HashSet::new(),
+            flood_protection: None,
+            max_message_length: None,
+            no_external_messages: false,
+            moderated: false,
+            backlog: VecDeque::new(),
+            founder: None,
         }
     }
 
@@ -83,7 +253,7 @@ impl Channel {
         }
 
         // Check if user is banned
-        if self.is_banned(&user.nickname) {
+        if self.is_banned(&user) {
             return Ok(NumericReply::new(
                 ERR_BANNEDFROMCHAN_NUM,
                 ERR_BANNEDFROMCHAN_MSG,
@@ -112,9 +282,18 @@ impl Channel {
             }
         }
 
+        // While the flood protection is on the channel is invite only
+        if self.is_flood_protected(Instant::now()) && !self.is_user_invited(&user.nickname) {
+            return Ok(NumericReply::new(
+                ERR_INVITEONLYCHAN_NUM,
+                ERR_INVITEONLYCHAN_MSG,
+                Some(vec![self.name.clone()]),
+            ));
+        }
+
         // Check enter mode
         if let Some(mode) = self.enter_mode.clone() {
-            if mode.as_str() == MODE_SET_INVITE && !self.invites.contains(&user.nickname) {
+            if mode.as_str() == MODE_SET_INVITE && !self.is_user_invited(&user.nickname) {
                 return Ok(NumericReply::new(
                     ERR_INVITEONLYCHAN_NUM,
                     ERR_INVITEONLYCHAN_MSG,
@@ -140,11 +319,29 @@ impl Channel {
             }
         }
 
-        self.users.insert(user.nickname.clone(), user);
+        // The founder of a registered channel is its operator again every time it joins
+        if self.is_founder(&user.nickname) && !self.is_operator(&user.nickname) {
+            self.operators.push(user.nickname.clone());
+        }
+        self.users.insert(irc_lowercase(&user.nickname), user);
 
         Ok(self.get_topic_reply())
     }
 
+    ///
+    /// Merges the channel of the same name that another server created, before knowing about
+    /// this one, with the user given as its creator. The creator is a member and an operator,
+    /// like in its own server, and the earliest creation time is kept, so every server ends up
+    /// with the same channel no matter the order in which the creations were received.
+    ///
+    pub fn merge_creation(&mut self, creator: User, created_at: u64) {
+        if !self.is_operator(&creator.nickname) {
+            self.operators.push(creator.nickname.clone());
+        }
+        self.users.insert(irc_lowercase(&creator.nickname), creator);
+        self.created_at = self.created_at.min(created_at);
+    }
+
     /********************************REMOVE USER FUNCTIONS**********************************/
 
     ///
@@ -175,30 +372,42 @@ impl Channel {
 
         // Check if user is operator
         if self.operators.is_empty() {
-            let users_nicks: Vec<String> = self.users.clone().into_keys().collect();
-            let nickname = users_nicks.get(0).unwrap();
-            self.operators.push(nickname.to_string());
+            let user = self.users.values().next().unwrap();
+            self.operators.push(user.nickname.clone());
         }
 
         None
     }
 
+    ///
+    /// Parts every user from the channel, leaving it empty. Returns the nicknames of
+    /// the users that were parted
+    ///
+    pub fn part_all(&mut self) -> Vec<String> {
+        let users: Vec<User> = self.users.values().cloned().collect();
+        for user in users.iter() {
+            self.part(user.clone());
+        }
+        users.into_iter().map(|user| user.nickname).collect()
+    }
+
     ///
     /// Tries to remove user from channel, it returns the user that was removed
     ///
-    pub fn remove_user(&mut self, nickname: &String) -> Option<User> {
+    pub fn remove_user(&mut self, nickname: &str) -> Option<User> {
         // User removed
-        let user = self.users.remove(nickname);
+        let user = self.users.remove(&irc_lowercase(nickname));
 
         if user.is_some() && self.is_operator(nickname) {
             // User removed was oper, removing from operators
             let index = self
                 .operators
                 .iter()
-                .position(|nick| nick == nickname)
+                .position(|nick| irc_eq(nick, nickname))
                 .unwrap();
             self.operators.remove(index);
         }
+        self.voiced.retain(|voiced| !irc_eq(voiced, nickname));
 
         user
     }
@@ -320,6 +529,60 @@ impl Channel {
         Ok(())
     }
 
+    /***************************REGISTRATION FUNCTIONS******************************/
+
+    ///
+    /// Registers the channel with the operator given as its founder. Registered channels
+    /// are kept when they are left empty, and their founder is their operator when it joins
+    /// them again. In case of error could return the following numeric replies:
+    ///
+    /// ERR_NOTONCHANNEL: user registering is not on channel.
+    /// ERR_CHANOPRIVSNEEDED: user registering is not an operator.
+    /// ERR_ALREADYFOUNDED: the channel was registered by another user.
+    ///
+    pub fn register(&mut self, nickname_user_registering: String) -> Result<(), NumericReply> {
+        if let Some(reply) = self.reply_user_using_privileges(&nickname_user_registering) {
+            return Err(reply);
+        }
+        // Other operators can't take the channel from its founder
+        if self.is_registered() && !self.is_founder(&nickname_user_registering) {
+            return Err(NumericReply::new(
+                ERR_ALREADYFOUNDED_NUM,
+                ERR_ALREADYFOUNDED_MSG,
+                Some(vec![self.name.clone()]),
+            ));
+        }
+
+        self.founder = Some(nickname_user_registering);
+        Ok(())
+    }
+
+    ///
+    /// Checks if the channel was registered, so it must not be deleted when it is left empty
+    ///
+    pub fn is_registered(&self) -> bool {
+        self.founder.is_some()
+    }
+
+    ///
+    /// Checks if user with nickname given registered the channel
+    ///
+    pub fn is_founder(&self, nickname: &str) -> bool {
+        match &self.founder {
+            Some(founder) => irc_eq(founder, nickname),
+            None => false,
+        }
+    }
+
+    ///
+    /// The founder keeps the channel after changing its nickname
+    ///
+    pub fn founder_changed_nickname(&mut self, old_nickname: &str, new_nickname: &str) {
+        if self.is_founder(old_nickname) {
+            self.founder = Some(new_nickname.to_string());
+        }
+    }
+
     /***************************INVITE ONLY FUNCTIONS******************************/
 
     ///
@@ -396,8 +659,8 @@ impl Channel {
         None
     }
 
-    pub fn is_user_invited(&self, nickname: &String) -> bool {
-        self.invites.contains(nickname)
+    pub fn is_user_invited(&self, nickname: &str) -> bool {
+        self.invites.iter().any(|invited| irc_eq(invited, nickname))
     }
 
     /*************************OPERATOR PRIVILEGES FUNCTIONS****************************/
@@ -481,7 +744,10 @@ impl Channel {
 
         let nickname_user_taking_privileges_from = &message.params[2][0];
 
-        if &nickname_user_setting_mode == nickname_user_taking_privileges_from {
+        if irc_eq(
+            &nickname_user_setting_mode,
+            nickname_user_taking_privileges_from,
+        ) {
             return Ok(());
         }
 
@@ -500,7 +766,7 @@ impl Channel {
             let index = self
                 .operators
                 .iter()
-                .position(|nick| nick == nickname_user_taking_privileges_from)
+                .position(|nick| irc_eq(nick, nickname_user_taking_privileges_from))
                 .unwrap();
             self.operators.remove(index);
         }
@@ -548,60 +814,19 @@ impl Channel {
         Ok(())
     }
 
-    /*****************************KICK FUNCTIONS********************************/
-
-    ///
-    /// Kicks user with nickname given. If a user is trying to kick itself no action in taken.
-    // In case of error could return the following
-    /// numeric replies:
-    ///
-    /// ERR_NOTONCHANNEL: user trying to set mode is not on channel.
-    /// ERR_CHANOPRIVSNEEDED: user trying to set mode is not an operator.
-    /// ERR_NOSUCHNICK: user being kicked is not on channel.
-    ///
-    pub fn kick(
-        &mut self,
-        nickname_user_getting_kicked: &String,
-        nickname_user_kicking: &String,
-    ) -> Option<NumericReply> {
-        if let Some(reply) = self.reply_user_using_privileges(nickname_user_kicking) {
-            return Some(reply);
-        }
-
-        if !self.is_user_on_channel(nickname_user_getting_kicked) {
-            // User being kicked is not in channel
-            return Some(NumericReply::new(
-                ERR_NOSUCHNICK_NUM,
-                ERR_NOSUCHNICK_MSG,
-                Some(vec![
-                    self.name.clone(),
-                    nickname_user_getting_kicked.clone(),
-                ]),
-            ));
-        }
-
-        if nickname_user_getting_kicked == nickname_user_kicking {
-            return None;
-        }
-
-        let user = self.remove_user(nickname_user_getting_kicked);
-
-        println!("user getting kicked (in kick channel) {:?}", user);
-
-        None
-    }
-
-    /*****************************BAN FUNCTIONS********************************/
+    /*****************************FLOOD FUNCTIONS********************************/
 
     ///
-    /// Sets ban for nicknames specified in messages. In case of error could return the following
-    /// numeric replies:
+    /// Sets the flood protection with the threshold specified in message, which has the form
+    /// joins:seconds or joins:seconds:cooldown_seconds. In case of error could return the
+    /// following numeric replies:
     ///
-    /// ERR_NEEDMOREPARAMS: no users were given.
+    /// ERR_NEEDMOREPARAMS: no threshold was given.
     /// ERR_NOTONCHANNEL: user trying to set mode is not on channel.
     /// ERR_CHANOPRIVSNEEDED: user trying to set mode is not an operator.
+    /// ERR_INVALIDFLOOD: the threshold doesn't have the correct form.
     ///
-    pub fn set_ban(
+    pub fn set_flood_protection(
         &mut self,
         message: Message,
         nickname_user_setting_mode: String,
@@ -618,116 +843,131 @@ impl Channel {
             return Err(reply);
         }
 
-        let nicknames = &message.params[2];
-
-        for nickname in nicknames {
-            if !self.is_banned(nickname) {
-                self.banned.insert(nickname.to_string());
+        let threshold = message.params[2][0].clone();
+        self.flood_protection = match FloodProtection::from_threshold(&threshold) {
+            Some(flood_protection) => Some(flood_protection),
+            None => {
+                return Err(NumericReply::new(
+                    ERR_INVALIDFLOOD_NUM,
+                    ERR_INVALIDFLOOD_MSG,
+                    Some(vec![threshold]),
+                ))
             }
-        }
+        };
 
         Ok(())
     }
 
     ///
-    /// Removes ban from nicknames specified in message. In case of error could return the following
-    /// numeric replies:
+    /// Removes the flood protection. In case of error could return the following numeric replies:
     ///
     /// ERR_NOTONCHANNEL: user trying to set mode is not on channel.
     /// ERR_CHANOPRIVSNEEDED: user trying to set mode is not an operator.
     ///
-    pub fn remove_ban(
+    pub fn remove_flood_protection(
         &mut self,
-        message: Message,
         nickname_user_setting_mode: String,
     ) -> Result<(), NumericReply> {
         if let Some(reply) = self.reply_user_using_privileges(&nickname_user_setting_mode) {
             return Err(reply);
         }
 
-        if message.params_total_count() == 2 {
-            self.banned.clear();
-            return Ok(());
-        }
-
-        let nicknames = &message.params[2];
+        self.flood_protection = None;
+        Ok(())
+    }
 
-        for nickname in nicknames {
-            if self.is_banned(nickname) {
-                self.banned.remove(nickname);
-            }
+    ///
+    /// Records that a user joined the channel. Returns true if the join turned on the flood protection
+    ///
+    pub fn record_join(&mut self, now: Instant) -> bool {
+        match self.flood_protection.as_mut() {
+            Some(flood_protection) => flood_protection.record_join(now),
+            None => false,
         }
+    }
 
-        Ok(())
+    ///
+    /// Checks if the flood protection of the channel is on
+    ///
+    pub fn is_flood_protected(&self, now: Instant) -> bool {
+        match &self.flood_protection {
+            Some(flood_protection) => flood_protection.is_protecting(now),
+            None => false,
+        }
     }
 
-    /*****************************TOPIC FUNCTIONS********************************/
+    ///
+    /// Records that a message was sent to the channel. Returns true if the message made the
+    /// flood protection mute the channel
+    ///
+    pub fn record_sent_message(&mut self, now: Instant) -> bool {
+        match self.flood_protection.as_mut() {
+            Some(flood_protection) => flood_protection.record_message(now),
+            None => false,
+        }
+    }
 
     ///
-    /// Returns RPL_TOPIC or RPL_NOTOPIC accoding to channel topic
+    /// Checks if the flood protection muted the channel because too many messages were sent
     ///
-    pub fn get_topic_reply(&self) -> NumericReply {
-        if self.topic.is_none() {
-            return NumericReply::new(
-                RPL_NOTOPIC_NUM,
-                RPL_NOTOPIC_MSG,
-                Some(vec![self.name.clone()]),
-            );
+    pub fn is_flood_muted(&self, now: Instant) -> bool {
+        match &self.flood_protection {
+            Some(flood_protection) => flood_protection.is_muting(now),
+            None => false,
         }
-        return NumericReply::new(
-            RPL_TOPIC_NUM,
-            self.topic.clone().unwrap().as_str(),
-            Some(vec![self.name.clone()]),
-        );
     }
 
+    /*************************MESSAGE LENGTH FUNCTIONS****************************/
+
     ///
-    /// Sets new topic. If topic is set correctly then RPL_TOPIC is returned. If an
-    /// error was found the following numeric relpies will be returned:
+    /// Sets the amount of characters allowed in the messages sent to the channel. In case of
+    /// error could return the following numeric replies:
     ///
+    /// ERR_NEEDMOREPARAMS: no length was given.
     /// ERR_NOTONCHANNEL: user trying to set mode is not on channel.
-    /// ERR_CHANOPRIVSNEEDED: topic is only settable by operator and user is not an operator.
+    /// ERR_CHANOPRIVSNEEDED: user trying to set mode is not an operator.
+    /// ERR_INVALIDLIMIT: the length is not a number bigger than 0.
     ///
-    pub fn set_topic(
+    pub fn set_max_message_length(
         &mut self,
-        nickname: &String,
-        topic: &str,
-    ) -> Result<NumericReply, NumericReply> {
-        println!("cheking if {} is oper", nickname);
-
-        if !self.is_user_on_channel(nickname) {
+        message: Message,
+        nickname_user_setting_mode: String,
+    ) -> Result<(), NumericReply> {
+        if message.params_total_count() < 3 {
             return Err(NumericReply::new(
-                ERR_NOTONCHANNEL_NUM,
-                ERR_NOTONCHANNEL_MSG,
-                Some(vec![self.name.clone()]),
+                ERR_NEEDMOREPARAMS_NUM,
+                ERR_NEEDMOREPARAMS_MSG,
+                None,
             ));
         }
 
-        if self.operator_settable_topic && !self.is_operator(nickname) {
-            return Err(NumericReply::new(
-                ERR_CHANOPRIVSNEEDED_NUM,
-                ERR_CHANOPRIVSNEEDED_MSG,
-                Some(vec![self.name.clone()]),
-            ));
+        if let Some(reply) = self.reply_user_using_privileges(&nickname_user_setting_mode) {
+            return Err(reply);
         }
 
-        self.topic = Some(topic.to_owned());
+        let length = message.params[2][0].clone();
+        self.max_message_length = match length.parse::<usize>() {
+            Ok(length) if length > 0 => Some(length),
+            _ => {
+                return Err(NumericReply::new(
+                    ERR_INVALIDLIMIT_NUM,
+                    ERR_INVALIDLIMIT_MSG,
+                    Some(vec![length]),
+                ))
+            }
+        };
 
-        Ok(NumericReply::new(
-            RPL_TOPIC_NUM,
-            topic,
-            Some(vec![self.name.clone()]),
-        ))
+        Ok(())
     }
 
     ///
-    /// Sets topic as operator settable only. In case of error could return the following
-    /// numeric replies:
+    /// Removes the limit of characters of the messages. In case of error could return the
+    /// following numeric replies:
     ///
     /// ERR_NOTONCHANNEL: user trying to set mode is not on channel.
     /// ERR_CHANOPRIVSNEEDED: user trying to set mode is not an operator.
     ///
-    pub fn set_operator_settable_topic(
+    pub fn remove_max_message_length(
         &mut self,
         nickname_user_setting_mode: String,
     ) -> Result<(), NumericReply> {
@@ -735,18 +975,30 @@ impl Channel {
             return Err(reply);
         }
 
-        self.operator_settable_topic = true;
+        self.max_message_length = None;
         Ok(())
     }
 
     ///
-    /// Removes topic as operator settable only status. In case of error could return the
+    /// Checks if the text has more characters than the ones allowed in the channel
+    ///
+    pub fn is_message_too_long(&self, text: &str) -> bool {
+        match self.max_message_length {
+            Some(max_message_length) => text.chars().count() > max_message_length,
+            None => false,
+        }
+    }
+
+    /***********************NO EXTERNAL MESSAGES FUNCTIONS*************************/
+
+    ///
+    /// Only the members can send messages to the channel. In case of error could return the
     /// following numeric replies:
     ///
     /// ERR_NOTONCHANNEL: user trying to set mode is not on channel.
     /// ERR_CHANOPRIVSNEEDED: user trying to set mode is not an operator.
     ///
-    pub fn remove_operator_settable_topic(
+    pub fn set_no_external_messages(
         &mut self,
         nickname_user_setting_mode: String,
     ) -> Result<(), NumericReply> {
@@ -754,32 +1006,635 @@ impl Channel {
             return Err(reply);
         }
 
-        self.operator_settable_topic = false;
+        self.no_external_messages = true;
         Ok(())
     }
 
-    pub fn channel_has_topic(&self, topic: &String) -> bool {
-        if self.topic.is_none() {
-            return false;
+    ///
+    /// Anyone can send messages to the channel again. In case of error could return the
+    /// following numeric replies:
+    ///
+    /// ERR_NOTONCHANNEL: user trying to set mode is not on channel.
+    /// ERR_CHANOPRIVSNEEDED: user trying to set mode is not an operator.
+    ///
+    pub fn remove_no_external_messages(
+        &mut self,
+        nickname_user_setting_mode: String,
+    ) -> Result<(), NumericReply> {
+        if let Some(reply) = self.reply_user_using_privileges(&nickname_user_setting_mode) {
+            return Err(reply);
         }
 
-        self.topic.clone().unwrap() == *topic
+        self.no_external_messages = false;
+        Ok(())
     }
 
-    /*****************************STATUS FUNCTIONS********************************/
+    /**************************MODERATED FUNCTIONS*******************************/
 
     ///
-    /// Checks if user with nickname given is an operator
-    ///
-    fn is_operator(&self, nickname: &String) -> bool {
-        self.operators.contains(nickname)
-    }
-
+    /// Only the operators and the users with voice can send messages to the channel. In case
+    /// of error could return the following numeric replies:
     ///
-    /// Checks if user with nickname given is an banned
+    /// ERR_NOTONCHANNEL: user trying to set mode is not on channel.
+    /// ERR_CHANOPRIVSNEEDED: user trying to set mode is not an operator.
     ///
-    fn is_banned(&self, nickname: &String) -> bool {
-        self.banned.contains(nickname)
+    pub fn set_moderated(
+        &mut self,
+        nickname_user_setting_mode: String,
+    ) -> Result<(), NumericReply> {
+        if let Some(reply) = self.reply_user_using_privileges(&nickname_user_setting_mode) {
+            return Err(reply);
+        }
+
+        self.moderated = true;
+        Ok(())
+    }
+
+    ///
+    /// The members can send messages to the channel again. In case of error could return the
+    /// following numeric replies:
+    ///
+    /// ERR_NOTONCHANNEL: user trying to set mode is not on channel.
+    /// ERR_CHANOPRIVSNEEDED: user trying to set mode is not an operator.
+    ///
+    pub fn remove_moderated(
+        &mut self,
+        nickname_user_setting_mode: String,
+    ) -> Result<(), NumericReply> {
+        if let Some(reply) = self.reply_user_using_privileges(&nickname_user_setting_mode) {
+            return Err(reply);
+        }
+
+        self.moderated = false;
+        Ok(())
+    }
+
+    ///
+    /// Gives voice to the nickname specified in message. If the user already has voice no
+    /// action is taken. In case of error could return the following numeric replies:
+    ///
+    /// ERR_NEEDMOREPARAMS: no user was given.
+    /// ERR_NOTONCHANNEL: user trying to set mode is not on channel.
+    /// ERR_CHANOPRIVSNEEDED: user trying to set mode is not an operator.
+    /// ERR_NOSUCHNICK: user getting voice is not on channel.
+    ///
+    pub fn give_voice(
+        &mut self,
+        message: Message,
+        nickname_user_setting_mode: String,
+    ) -> Result<(), NumericReply> {
+        let nickname_user_giving_voice_to =
+            self.user_changing_voice(&message, &nickname_user_setting_mode)?;
+
+        if !self.is_voiced(&nickname_user_giving_voice_to) {
+            self.voiced.push(nickname_user_giving_voice_to);
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Removes voice from the nickname specified in message. If the user has no voice no
+    /// action is taken. In case of error could return the following numeric replies:
+    ///
+    /// ERR_NEEDMOREPARAMS: no user was given.
+    /// ERR_NOTONCHANNEL: user trying to set mode is not on channel.
+    /// ERR_CHANOPRIVSNEEDED: user trying to set mode is not an operator.
+    /// ERR_NOSUCHNICK: user losing voice is not on channel.
+    ///
+    pub fn remove_voice(
+        &mut self,
+        message: Message,
+        nickname_user_setting_mode: String,
+    ) -> Result<(), NumericReply> {
+        let nickname_user_taking_voice_from =
+            self.user_changing_voice(&message, &nickname_user_setting_mode)?;
+
+        self.voiced
+            .retain(|voiced| !irc_eq(voiced, &nickname_user_taking_voice_from));
+
+        Ok(())
+    }
+
+    ///
+    /// Returns the nickname of the user whose voice is given or taken, if the user setting
+    /// the mode can do it and the user is on channel
+    ///
+    fn user_changing_voice(
+        &self,
+        message: &Message,
+        nickname_user_setting_mode: &str,
+    ) -> Result<String, NumericReply> {
+        if message.params_total_count() < 3 {
+            return Err(NumericReply::new(
+                ERR_NEEDMOREPARAMS_NUM,
+                ERR_NEEDMOREPARAMS_MSG,
+                None,
+            ));
+        }
+
+        if let Some(reply) = self.reply_user_using_privileges(nickname_user_setting_mode) {
+            return Err(reply);
+        }
+
+        let nickname = &message.params[2][0];
+        if !self.is_user_on_channel(nickname) {
+            return Err(NumericReply::new(
+                ERR_NOSUCHNICK_NUM,
+                ERR_NOSUCHNICK_MSG,
+                Some(vec![self.name.clone(), nickname.to_string()]),
+            ));
+        }
+
+        Ok(nickname.to_string())
+    }
+
+    ///
+    /// Checks if the user can send messages to the channel. When the channel is moderated or
+    /// muted by the flood protection only the operators and the users with voice can
+    ///
+    pub fn can_send_message(&self, nickname: &str) -> bool {
+        if self.moderated || self.is_flood_muted(Instant::now()) {
+            return self.is_operator(nickname) || self.is_voiced(nickname);
+        }
+        !self.no_external_messages || self.is_user_on_channel(nickname)
+    }
+
+    /*****************************MODES FUNCTIONS********************************/
+
+    ///
+    /// Returns RPL_CHANNELMODEIS with the modes that are on, followed by their parameters,
+    /// like "+klt secret 10". The key is only shown to the members of the channel
+    ///
+    pub fn get_modes_reply(&self, nickname: &str) -> NumericReply {
+        let mut flags = String::from("+");
+        let mut parameters = vec![];
+        if let Some(key) = &self.key {
+            flags.push('k');
+            if self.is_user_on_channel(nickname) {
+                parameters.push(key.clone());
+            }
+        }
+        if let Some(limit) = self.limit {
+            flags.push('l');
+            parameters.push(limit.to_string());
+        }
+        if self.enter_mode == Some(MODE_SET_INVITE.to_string()) {
+            flags.push('i');
+        }
+        if self.operator_settable_topic {
+            flags.push('t');
+        }
+        if self.secret {
+            flags.push('s');
+        }
+        if self.no_external_messages {
+            flags.push('n');
+        }
+        if self.moderated {
+            flags.push('m');
+        }
+        if let Some(flood_protection) = &self.flood_protection {
+            flags.push('f');
+            parameters.push(flood_protection.threshold());
+        }
+        if let Some(max_message_length) = self.max_message_length {
+            flags.push('L');
+            parameters.push(max_message_length.to_string());
+        }
+
+        let modes = std::iter::once(flags)
+            .chain(parameters)
+            .collect::<Vec<String>>()
+            .join(" ");
+        NumericReply::new(RPL_CHANNELMODEIS_NUM, &modes, Some(vec![self.name.clone()]))
+    }
+
+    /*****************************KICK FUNCTIONS********************************/
+
+    ///
+    /// Kicks user with nickname given. If a user is trying to kick itself no action in taken.
+    /// Returns the reason of the kick, which is the comment given or the nickname of the user
+    /// kicking if there is no comment. In case of error could return the following
+    /// numeric replies:
+    ///
+    /// ERR_NOTONCHANNEL: user trying to set mode is not on channel.
+    /// ERR_CHANOPRIVSNEEDED: user trying to set mode is not an operator.
+    /// ERR_NOSUCHNICK: user being kicked is not on channel.
+    ///
+    pub fn kick(
+        &mut self,
+        nickname_user_getting_kicked: &str,
+        nickname_user_kicking: &str,
+        comment: Option<&String>,
+    ) -> Result<String, NumericReply> {
+        if let Some(reply) = self.reply_user_using_privileges(nickname_user_kicking) {
+            return Err(reply);
+        }
+
+        if !self.is_user_on_channel(nickname_user_getting_kicked) {
+            // User being kicked is not in channel
+            return Err(NumericReply::new(
+                ERR_NOSUCHNICK_NUM,
+                ERR_NOSUCHNICK_MSG,
+                Some(vec![
+                    self.name.clone(),
+                    nickname_user_getting_kicked.to_string(),
+                ]),
+            ));
+        }
+
+        let reason = match comment {
+            Some(comment) => comment.clone(),
+            None => nickname_user_kicking.to_string(),
+        };
+
+        if irc_eq(nickname_user_getting_kicked, nickname_user_kicking) {
+            return Ok(reason);
+        }
+
+        let user = self.remove_user(nickname_user_getting_kicked);
+
+        println!("user getting kicked (in kick channel) {:?}", user);
+
+        Ok(reason)
+    }
+
+    /*****************************BAN FUNCTIONS********************************/
+
+    ///
+    /// Sets ban for nicknames or masks specified in messages. A mask has the form nick!user@host
+    /// and can use * and ? as wildcards. In case of error could return the following
+    /// numeric replies:
+    ///
+    /// ERR_NEEDMOREPARAMS: no users were given.
+    /// ERR_NOTONCHANNEL: user trying to set mode is not on channel.
+    /// ERR_CHANOPRIVSNEEDED: user trying to set mode is not an operator.
+    /// ERR_BANLISTFULL: the channel already has MAX_BANS bans, the masks after it are not set.
+    ///
+    pub fn set_ban(
+        &mut self,
+        message: Message,
+        nickname_user_setting_mode: String,
+    ) -> Result<(), NumericReply> {
+        if message.params_total_count() < 3 {
+            return Err(NumericReply::new(
+                ERR_NEEDMOREPARAMS_NUM,
+                ERR_NEEDMOREPARAMS_MSG,
+                None,
+            ));
+        }
+
+        if let Some(reply) = self.reply_user_using_privileges(&nickname_user_setting_mode) {
+            return Err(reply);
+        }
+
+        if !insert_masks(&mut self.banned, &message.params[2]) {
+            return Err(self.ban_list_full_reply("b"));
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Removes ban from nicknames or masks specified in message. In case of error could return the following
+    /// numeric replies:
+    ///
+    /// ERR_NOTONCHANNEL: user trying to set mode is not on channel.
+    /// ERR_CHANOPRIVSNEEDED: user trying to set mode is not an operator.
+    ///
+    pub fn remove_ban(
+        &mut self,
+        message: Message,
+        nickname_user_setting_mode: String,
+    ) -> Result<(), NumericReply> {
+        if let Some(reply) = self.reply_user_using_privileges(&nickname_user_setting_mode) {
+            return Err(reply);
+        }
+
+        if message.params_total_count() == 2 {
+            self.banned.clear();
+            return Ok(());
+        }
+
+        remove_masks(&mut self.banned, &message.params[2]);
+
+        Ok(())
+    }
+
+    ///
+    /// Sets ban exceptions for nicknames or masks specified in message. Users matching an
+    /// exception can join even if they match a ban. In case of error could return the following
+    /// numeric replies:
+    ///
+    /// ERR_NEEDMOREPARAMS: no users were given.
+    /// ERR_NOTONCHANNEL: user trying to set mode is not on channel.
+    /// ERR_CHANOPRIVSNEEDED: user trying to set mode is not an operator.
+    /// ERR_BANLISTFULL: the channel already has MAX_BANS exceptions, the masks after it are not set.
+    ///
+    pub fn set_exception(
+        &mut self,
+        message: Message,
+        nickname_user_setting_mode: String,
+    ) -> Result<(), NumericReply> {
+        if message.params_total_count() < 3 {
+            return Err(NumericReply::new(
+                ERR_NEEDMOREPARAMS_NUM,
+                ERR_NEEDMOREPARAMS_MSG,
+                None,
+            ));
+        }
+
+        if let Some(reply) = self.reply_user_using_privileges(&nickname_user_setting_mode) {
+            return Err(reply);
+        }
+
+        if !insert_masks(&mut self.exceptions, &message.params[2]) {
+            return Err(self.ban_list_full_reply("e"));
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Removes ban exceptions from nicknames or masks specified in message, or all of them if
+    /// none is given. In case of error could return the following numeric replies:
+    ///
+    /// ERR_NOTONCHANNEL: user trying to set mode is not on channel.
+    /// ERR_CHANOPRIVSNEEDED: user trying to set mode is not an operator.
+    ///
+    pub fn remove_exception(
+        &mut self,
+        message: Message,
+        nickname_user_setting_mode: String,
+    ) -> Result<(), NumericReply> {
+        if let Some(reply) = self.reply_user_using_privileges(&nickname_user_setting_mode) {
+            return Err(reply);
+        }
+
+        if message.params_total_count() == 2 {
+            self.exceptions.clear();
+            return Ok(());
+        }
+
+        remove_masks(&mut self.exceptions, &message.params[2]);
+
+        Ok(())
+    }
+
+    ///
+    /// Returns ERR_BANLISTFULL for the list of the mode given
+    ///
+    fn ban_list_full_reply(&self, mode: &str) -> NumericReply {
+        NumericReply::new(
+            ERR_BANLISTFULL_NUM,
+            ERR_BANLISTFULL_MSG,
+            Some(vec![self.name.clone(), mode.to_string()]),
+        )
+    }
+
+    /*****************************BACKLOG FUNCTIONS********************************/
+
+    ///
+    /// Keeps a message sent to the channel, forgetting the oldest ones so at most
+    /// backlog_size are kept. Only PRIVMSGs are kept, with the time they were received in their
+    /// time tag, so the users that join know when they were sent
+    ///
+    pub fn record_message(
+        &mut self,
+        message: &Message,
+        received_at: SystemTime,
+        backlog_size: usize,
+    ) {
+        if message.command != PRIVMSG {
+            return;
+        }
+        let mut message = message.clone();
+        message
+            .tags
+            .get_or_insert_with(HashMap::new)
+            .insert(TIME_TAG.to_string(), server_time_text(received_at));
+        self.backlog.push_back(message);
+        while self.backlog.len() > backlog_size {
+            self.backlog.pop_front();
+        }
+    }
+
+    ///
+    /// Returns the messages kept, oldest first, as the PRIVMSGs replayed to a user that joins.
+    /// Their text starts with BACKLOG_MARKER
+    ///
+    pub fn backlog_messages(&self) -> Vec<Message> {
+        self.backlog
+            .iter()
+            .map(|message| {
+                let mut message = message.clone();
+                if let Some(receiver) = message.params.first_mut().and_then(|p| p.first_mut()) {
+                    *receiver = self.name.clone();
+                }
+                if let Some(text) = message.params.get_mut(1).and_then(|p| p.first_mut()) {
+                    *text = format!("{} {}", BACKLOG_MARKER, text);
+                }
+                message
+            })
+            .collect()
+    }
+
+    /*****************************TOPIC FUNCTIONS********************************/
+
+    ///
+    /// Returns RPL_TOPIC or RPL_NOTOPIC accoding to channel topic
+    ///
+    pub fn get_topic_reply(&self) -> NumericReply {
+        if self.topic.is_none() {
+            return NumericReply::new(
+                RPL_NOTOPIC_NUM,
+                RPL_NOTOPIC_MSG,
+                Some(vec![self.name.clone()]),
+            );
+        }
+        return NumericReply::new(
+            RPL_TOPIC_NUM,
+            self.topic.clone().unwrap().as_str(),
+            Some(vec![self.name.clone()]),
+        );
+    }
+
+    ///
+    /// Returns the replies sent after RPL_TOPIC or RPL_NOTOPIC: RPL_CREATIONTIME with the time
+    /// the channel was created and, if the topic is known, RPL_TOPICWHOTIME with who set it and when
+    ///
+    pub fn get_topic_details_replies(&self) -> Vec<NumericReply> {
+        let mut replies = vec![NumericReply::new(
+            RPL_CREATIONTIME_NUM,
+            "",
+            Some(vec![self.name.clone(), self.created_at.to_string()]),
+        )];
+
+        if let (Some(_), Some(setter), Some(set_at)) =
+            (&self.topic, &self.topic_setter, self.topic_set_at)
+        {
+            replies.push(NumericReply::new(
+                RPL_TOPICWHOTIME_NUM,
+                "",
+                Some(vec![
+                    self.name.clone(),
+                    setter.clone(),
+                    (set_at / 1000).to_string(),
+                ]),
+            ));
+        }
+
+        replies
+    }
+
+    ///
+    /// Sets new topic. If topic is set correctly then RPL_TOPIC is returned. If an
+    /// error was found the following numeric relpies will be returned:
+    ///
+    /// ERR_NOTONCHANNEL: user trying to set mode is not on channel.
+    /// ERR_CHANOPRIVSNEEDED: topic is only settable by operator and user is not an operator.
+    ///
+    pub fn set_topic(
+        &mut self,
+        nickname: &String,
+        topic: &str,
+        set_at: u128,
+    ) -> Result<NumericReply, NumericReply> {
+        println!("cheking if {} is oper", nickname);
+
+        if !self.is_user_on_channel(nickname) {
+            return Err(NumericReply::new(
+                ERR_NOTONCHANNEL_NUM,
+                ERR_NOTONCHANNEL_MSG,
+                Some(vec![self.name.clone()]),
+            ));
+        }
+
+        if self.operator_settable_topic && !self.is_operator(nickname) {
+            return Err(NumericReply::new(
+                ERR_CHANOPRIVSNEEDED_NUM,
+                ERR_CHANOPRIVSNEEDED_MSG,
+                Some(vec![self.name.clone()]),
+            ));
+        }
+
+        self.topic = Some(topic.to_owned());
+        self.topic_set_at = Some(set_at);
+        self.topic_setter = Some(nickname.to_owned());
+
+        Ok(NumericReply::new(
+            RPL_TOPIC_NUM,
+            topic,
+            Some(vec![self.name.clone()]),
+        ))
+    }
+
+    ///
+    /// Sets topic as operator settable only. In case of error could return the following
+    /// numeric replies:
+    ///
+    /// ERR_NOTONCHANNEL: user trying to set mode is not on channel.
+    /// ERR_CHANOPRIVSNEEDED: user trying to set mode is not an operator.
+    ///
+    pub fn set_operator_settable_topic(
+        &mut self,
+        nickname_user_setting_mode: String,
+    ) -> Result<(), NumericReply> {
+        if let Some(reply) = self.reply_user_using_privileges(&nickname_user_setting_mode) {
+            return Err(reply);
+        }
+
+        self.operator_settable_topic = true;
+        Ok(())
+    }
+
+    ///
+    /// Removes topic as operator settable only status. In case of error could return the
+    /// following numeric replies:
+    ///
+    /// ERR_NOTONCHANNEL: user trying to set mode is not on channel.
+    /// ERR_CHANOPRIVSNEEDED: user trying to set mode is not an operator.
+    ///
+    pub fn remove_operator_settable_topic(
+        &mut self,
+        nickname_user_setting_mode: String,
+    ) -> Result<(), NumericReply> {
+        if let Some(reply) = self.reply_user_using_privileges(&nickname_user_setting_mode) {
+            return Err(reply);
+        }
+
+        self.operator_settable_topic = false;
+        Ok(())
+    }
+
+    pub fn channel_has_topic(&self, topic: &String) -> bool {
+        if self.topic.is_none() {
+            return false;
+        }
+
+        self.topic.clone().unwrap() == *topic
+    }
+
+    ///
+    /// Tells whether the topic given, set at the time given, must replace the current topic.
+    /// The most recent topic wins and ties are broken comparing the topics, so every server
+    /// ends up with the same topic no matter the order in which they were received.
+    ///
+    pub fn topic_wins(&self, topic: &str, set_at: u128) -> bool {
+        match (&self.topic, self.topic_set_at) {
+            (Some(current_topic), Some(current_set_at)) => {
+                set_at > current_set_at
+                    || (set_at == current_set_at && topic > current_topic.as_str())
+            }
+            _ => true,
+        }
+    }
+
+    /*****************************STATUS FUNCTIONS********************************/
+
+    ///
+    /// Checks if user with nickname given is an operator
+    ///
+    fn is_operator(&self, nickname: &str) -> bool {
+        self.operators
+            .iter()
+            .any(|operator| irc_eq(operator, nickname))
+    }
+
+    ///
+    /// Checks if user with nickname given has voice
+    ///
+    fn is_voiced(&self, nickname: &str) -> bool {
+        self.voiced.iter().any(|voiced| irc_eq(voiced, nickname))
+    }
+
+    ///
+    /// Returns the bans of the channel sorted
+    ///
+    pub fn get_ban_list(&self) -> Vec<String> {
+        let mut banned: Vec<String> = self.banned.iter().cloned().collect();
+        banned.sort();
+        banned
+    }
+
+    ///
+    /// Returns the ban exceptions of the channel sorted
+    ///
+    pub fn get_exception_list(&self) -> Vec<String> {
+        let mut exceptions: Vec<String> = self.exceptions.iter().cloned().collect();
+        exceptions.sort();
+        exceptions
+    }
+
+    ///
+    /// Checks if user given is banned. A user matching an exception is never banned
+    ///
+    fn is_banned(&self, user: &User) -> bool {
+        if self.exceptions.iter().any(|mask| matches_user(mask, user)) {
+            return false;
+        }
+        self.banned.iter().any(|mask| matches_user(mask, user))
     }
 
     ///
@@ -796,11 +1651,142 @@ impl Channel {
         self.secret
     }
 
+    ///
+    /// Checks if only the users invited can join the channel, because of the +i mode or
+    /// because the flood protection is on
+    ///
+    pub fn is_invite_only(&self, now: Instant) -> bool {
+        self.enter_mode == Some(MODE_SET_INVITE.to_string()) || self.is_flood_protected(now)
+    }
+
+    ///
+    /// Returns the NOTICE sent to each operator of the channel when a user knocks, asking
+    /// for an invitation. The notices come from the channel, so they are shown in its chat.
+    /// The text goes after a dash, a colon inside a parameter would break the message
+    ///
+    pub fn knock_notices(&self, nickname: &str, text: Option<&str>) -> Vec<Message> {
+        let knock_text = match text {
+            Some(text) => format!(
+                "{} is knocking, asking for an invitation - {}",
+                nickname, text
+            ),
+            None => format!("{} is knocking, asking for an invitation", nickname),
+        };
+        self.operators
+            .iter()
+            .map(|operator| Message {
+                tags: None,
+                prefix: Some(self.name.clone()),
+                command: NOTICE.to_string(),
+                params: vec![vec![operator.clone()], vec![knock_text.clone()]],
+            })
+            .collect()
+    }
+
+    ///
+    /// Returns the visibility flag of the channel for RPL_NAMEREPLY
+    ///
+    pub fn visibility_flag(&self) -> &str {
+        match self.secret {
+            true => RPL_NAMEREPLY_SECRET,
+            false => RPL_NAMEREPLY_PUBLIC,
+        }
+    }
+
+    ///
+    /// Returns the nicknames of the users in the channel sorted, operators go first
+    /// and with the operator prefix, then the users with voice and their prefix
+    ///
+    pub fn names(&self) -> Vec<String> {
+        let mut nicknames: Vec<&String> = self.users.values().map(|user| &user.nickname).collect();
+        nicknames.sort();
+        let (operators, others): (Vec<&String>, Vec<&String>) = nicknames
+            .into_iter()
+            .partition(|nickname| self.is_operator(nickname));
+        let (voiced, others): (Vec<&String>, Vec<&String>) = others
+            .into_iter()
+            .partition(|nickname| self.is_voiced(nickname));
+
+        operators
+            .into_iter()
+            .map(|operator| format!("{}{}", RPL_NAMEREPLY_OPERATOR, operator))
+            .chain(
+                voiced
+                    .into_iter()
+                    .map(|voiced| format!("{}{}", RPL_NAMEREPLY_VOICED, voiced)),
+            )
+            .chain(others.into_iter().cloned())
+            .collect()
+    }
+
+    ///
+    /// Returns a RPL_WHOREPLY for every member of the channel sorted by nickname, with the
+    /// nickname, the channel, the username, the host and the server of the member, then its
+    /// flags and the hopcount before the real name. The members are taken from the users given
+    /// to know if they are away, the hopcount is 0 for the members of the server given.
+    /// Users that are not in the channel don't see the invisible members nor the secret channels
+    ///
+    pub fn who_replies(
+        &self,
+        users: &HashMap<String, User>,
+        nickname_asking: &str,
+        server_name: &str,
+    ) -> Vec<NumericReply> {
+        let is_member = self.is_user_on_channel(nickname_asking);
+        if self.secret && !is_member {
+            return vec![];
+        }
+
+        let mut members: Vec<&User> = self
+            .users
+            .values()
+            .map(|member| {
+                users
+                    .get(&irc_lowercase(&member.nickname))
+                    .unwrap_or(member)
+            })
+            .filter(|member| is_member || !member.invisible)
+            .collect();
+        members.sort_by(|a, b| a.nickname.cmp(&b.nickname));
+
+        members
+            .into_iter()
+            .map(|member| {
+                let mut flags = match member.away_message {
+                    Some(_) => RPL_WHOREPLY_GONE.to_string(),
+                    None => RPL_WHOREPLY_HERE.to_string(),
+                };
+                if self.is_operator(&member.nickname) {
+                    flags.push(RPL_NAMEREPLY_OPERATOR);
+                } else if self.is_voiced(&member.nickname) {
+                    flags.push(RPL_NAMEREPLY_VOICED);
+                }
+                let hopcount = match member.server_name == server_name {
+                    true => 0,
+                    false => 1,
+                };
+
+                NumericReply::new(
+                    RPL_WHOREPLY_NUM,
+                    &format!("{} {}", hopcount, member.real_name),
+                    Some(vec![
+                        member.nickname.clone(),
+                        self.name.clone(),
+                        member.username.clone(),
+                        member.address.clone(),
+                        member.server_name.clone(),
+                        flags,
+                    ]),
+                )
+            })
+            .collect()
+    }
+
     ///
     /// Checks if user with the given nickname is on channel
     ///
-    pub fn is_user_on_channel(&self, nickname: &String) -> bool {
-        self.users.get(nickname).is_some()
+    pub fn is_user_on_channel(&self, nickname: &str) -> bool {
+        self.users.contains_key(&irc_lowercase(nickname))
     }
 
     ///
@@ -819,7 +1805,7 @@ impl Channel {
     /// ERR_CHANOPRIVSNEEDED: user is not an operator.
     /// None: non of the above is true.
     ///
-    pub fn reply_user_using_privileges(&self, nickname: &String) -> Option<NumericReply> {
+    pub fn reply_user_using_privileges(&self, nickname: &str) -> Option<NumericReply> {
         if !self.is_user_on_channel(nickname) {
             return Some(NumericReply::new(
                 ERR_NOTONCHANNEL_NUM,
@@ -847,7 +1833,11 @@ impl Channel {
             Some(ref topic) => topic.clone(),
             None => "None".to_string(),
         };
-        let users: Vec<String> = self.users.keys().cloned().collect();
+        let users: Vec<String> = self
+            .users
+            .values()
+            .map(|user| user.nickname.clone())
+            .collect();
         let key = match self.key {
             Some(ref key) => key.clone(),
             None => "None".to_string(),
@@ -860,6 +1850,14 @@ impl Channel {
             Some(ref mode) => mode.clone(),
             None => "None".to_string(),
         };
+        let topic_set_at = match self.topic_set_at {
+            Some(ref set_at) => set_at.to_string(),
+            None => "0".to_string(),
+        };
+        let topic_setter = match self.topic_setter {
+            Some(ref setter) => setter.clone(),
+            None => "None".to_string(),
+        };
         let mut params = vec![vec![
             self.name.clone(),
             topic,
@@ -868,6 +1866,9 @@ impl Channel {
             mode,
             self.operator_settable_topic.to_string(),
             self.secret.to_string(),
+            topic_set_at,
+            topic_setter,
+            self.created_at.to_string(),
         ]];
         params.push(users);
         params.push(self.operators.clone());
@@ -883,8 +1884,37 @@ impl Channel {
         } else {
             params.push(self.banned.iter().cloned().collect());
         }
+        if self.exceptions.is_empty() {
+            params.push(vec!["None".to_string()]);
+        } else {
+            params.push(self.exceptions.iter().cloned().collect());
+        }
+        let flood_protection = match self.flood_protection {
+            Some(ref flood_protection) => flood_protection.threshold(),
+            None => "None".to_string(),
+        };
+        let max_message_length = match self.max_message_length {
+            Some(max_message_length) => max_message_length.to_string(),
+            None => "0".to_string(),
+        };
+        params.push(vec![
+            flood_protection,
+            max_message_length,
+            self.no_external_messages.to_string(),
+            self.moderated.to_string(),
+        ]);
+        if self.voiced.is_empty() {
+            params.push(vec!["None".to_string()]);
+        } else {
+            params.push(self.voiced.clone());
+        }
+        params.push(vec![self
+            .founder
+            .clone()
+            .unwrap_or_else(|| "None".to_string())]);
 
         Message {
+            tags: None,
             prefix: Some(self.clone().name),
             command: CHANNEL_INFO.to_string(),
             params,
@@ -892,16 +1922,18 @@ impl Channel {
     }
 
     ///
-    /// It will return a channel from a message containing all the information of the channels
+    /// It will return a channel from a message containing all the information of the channels.
+    /// The users are already locked by the caller, before the channels
     ///
     pub fn channel_from_message(
         message: Message,
-        users: Arc<Mutex<HashMap<String, User>>>,
+        users: &HashMap<String, User>,
     ) -> Result<Channel, ServerError> {
         let params = message.params;
         let operators = params[2].clone();
         let mut invites = params[3].clone();
         let mut banned: HashSet<String> = params[4].clone().iter().cloned().collect();
+        let mut exceptions: HashSet<String> = params[5].clone().iter().cloned().collect();
         let mut topic = Some(params[0][1].clone());
 
         if params[0][1] == "None" {
@@ -919,24 +1951,54 @@ impl Channel {
         if params[0][4] == "None" {
             mode = None;
         }
+        let topic_set_at = params[0]
+            .get(7)
+            .map(|set_at| set_at.parse::<u128>().unwrap_or(0))
+            .filter(|set_at| *set_at != 0);
+        // Servers that don't send who set the topic and when the channel was created
+        let topic_setter = params[0].get(8).filter(|setter| *setter != "None").cloned();
+        let created_at = params[0]
+            .get(9)
+            .and_then(|created_at| created_at.parse::<u64>().ok())
+            .unwrap_or_else(now_in_seconds);
         if invites.contains(&"None".to_string()) {
             invites.clear();
         }
         if banned.contains(&"None".to_string()) {
             banned.clear();
         }
+        if exceptions.contains("None") {
+            exceptions.clear();
+        }
+        // Servers that don't send the flood protection, the length of the messages, +n, +m
+        // and the voiced users
+        let no_modes = Vec::new();
+        let modes = params.get(6).unwrap_or(&no_modes);
+        let flood_protection = modes
+            .first()
+            .and_then(|threshold| FloodProtection::from_threshold(threshold));
+        let max_message_length = modes
+            .get(1)
+            .and_then(|length| length.parse::<usize>().ok())
+            .filter(|length| *length != 0);
+        let no_external_messages = matches!(modes.get(2).map(String::as_str), Some("true"));
+        let moderated = matches!(modes.get(3).map(String::as_str), Some("true"));
+        let mut voiced = params.get(7).cloned().unwrap_or_default();
+        if voiced.contains(&"None".to_string()) {
+            voiced.clear();
+        }
+        // Servers that don't send the founder of registered channels
+        let founder = params
+            .get(8)
+            .and_then(|founder| founder.first())
+            .filter(|founder| *founder != "None")
+            .cloned();
 
         let nicks_users = params[1].clone();
-        let users = users.lock().map_err(|_| -> ServerError {
-            ServerError {
-                kind: NONCRITICAL.to_string(),
-                message: "Could not access channels".to_string(),
-            }
-        })?;
         let mut channel_users = HashMap::new();
 
         for nick in nicks_users {
-            let user = match users.get(&nick) {
+            let user = match users.get(&irc_lowercase(&nick)) {
                 Some(user) => user,
                 None => {
                     return Err(ServerError {
@@ -946,35 +2008,327 @@ impl Channel {
                 }
             };
 
-            println!("adding user {:?}", user);
+            println!("adding user {:?}", user);
+
+            channel_users.insert(irc_lowercase(&user.nickname), user.clone());
+        }
+
+        Ok(Channel {
+            name: params[0][0].clone(),
+            topic,
+            topic_set_at,
+            topic_setter,
+            created_at,
+            key,
+            limit,
+            enter_mode: mode,
+            operator_settable_topic: params[0][5].parse::<bool>().unwrap(),
+            secret: params[0][6].parse::<bool>().unwrap(),
+            users: channel_users,
+            operators,
+            voiced,
+            invites,
+            banned,
+            exceptions,
+            flood_protection,
+            max_message_length,
+            no_external_messages,
+            moderated,
+            backlog: VecDeque::new(),
+            founder,
+        })
+    }
+}
+
+///
+/// Adds the masks that are not already in the list. Returns false if the list
+/// reached MAX_BANS before a mask could be added
+///
+fn insert_masks(list: &mut HashSet<String>, masks: &[String]) -> bool {
+    for mask in masks {
+        if list.iter().any(|saved| irc_eq(saved, mask)) {
+            continue;
+        }
+        if list.len() >= MAX_BANS {
+            return false;
+        }
+        list.insert(mask.to_string());
+    }
+    true
+}
+
+///
+/// Removes the masks from the list
+///
+fn remove_masks(list: &mut HashSet<String>, masks: &[String]) {
+    for mask in masks {
+        list.retain(|saved| !irc_eq(saved, mask));
+    }
+}
+
+///
+/// Checks if the user matches the ban or exception mask given. Masks without ! or @ are
+/// matched against the nickname, the rest against the full identity of the user:
+/// nickname!username@server_name
+///
+fn matches_user(mask: &str, user: &User) -> bool {
+    if mask.contains('!') || mask.contains('@') {
+        let identity = format!("{}!{}@{}", user.nickname, user.username, user.server_name);
+        matches_mask(mask, &identity)
+    } else {
+        matches_mask(mask, &user.nickname)
+    }
+}
+
+///
+/// Checks if text matches the mask given, where * matches any amount of characters
+/// and ? matches exactly one. Letters are compared without case, like nicknames
+///
+fn matches_mask(mask: &str, text: &str) -> bool {
+    let mask: Vec<char> = irc_lowercase(mask).chars().collect();
+    let text: Vec<char> = irc_lowercase(text).chars().collect();
+    let (mut mask_index, mut text_index) = (0, 0);
+    // Position of the last * and of the text when it was found, to go back if the rest doesn't match
+    let mut last_star: Option<(usize, usize)> = None;
+
+    while text_index < text.len() {
+        if mask_index < mask.len()
+            && (mask[mask_index] == '?' || mask[mask_index] == text[text_index])
+        {
+            mask_index += 1;
+            text_index += 1;
+        } else if mask_index < mask.len() && mask[mask_index] == '*' {
+            last_star = Some((mask_index, text_index));
+            mask_index += 1;
+        } else if let Some((star_index, star_text_index)) = last_star {
+            // The * takes one more character and the rest of the mask is tried again
+            mask_index = star_index + 1;
+            text_index = star_text_index + 1;
+            last_star = Some((star_index, text_index));
+        } else {
+            return false;
+        }
+    }
+
+    mask[mask_index..].iter().all(|character| *character == '*')
+}
+
+///
+/// Returns the seconds since epoch
+///
+pub fn now_in_seconds() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{matches_mask, Channel, FloodProtection, MAX_BANS};
+    use crate::message::Message;
+    use crate::numeric_reply::{
+        NumericReply, ERR_ALREADYFOUNDED_MSG, ERR_ALREADYFOUNDED_NUM, ERR_BANLISTFULL_MSG,
+        ERR_BANLISTFULL_NUM, ERR_BANNEDFROMCHAN_NUM, ERR_CHANOPRIVSNEEDED_MSG,
+        ERR_CHANOPRIVSNEEDED_NUM, ERR_INVALIDLIMIT_NUM, ERR_NOSUCHNICK_NUM, RPL_CHANNELMODEIS_NUM,
+        RPL_NAMEREPLY_PUBLIC, RPL_NAMEREPLY_SECRET,
+    };
+    use crate::server_utils::user::User;
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant, UNIX_EPOCH};
+
+    #[test]
+    fn test_new_channel() {
+        let user = User::new(
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let channel = Channel::new("test".to_string(), &user);
+
+        assert_eq!(channel.name, "test");
+        assert_eq!(channel.topic, None);
+        //assert_eq!(channel.operator, "test");
+        assert_eq!(channel.users.len(), 1);
+        assert!(channel.users.contains_key("test"));
+        assert_eq!(channel.invites.len(), 0);
+    }
+
+    #[test]
+    fn test_join_channel_correctly() {
+        let user = User::new(
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let mut channel = Channel::new("test".to_string(), &user);
+        let another_user = User::new(
+            "test2".to_string(),
+            "test2".to_string(),
+            "test2".to_string(),
+            "test2".to_string(),
+            "test2".to_string(),
+            "password".to_string(),
+        );
+
+        channel.join(another_user, None).unwrap();
+
+        assert_eq!(channel.users.len(), 2);
+        assert!(channel.users.contains_key("test"));
+        //assert_eq!(channel.operator, "test");
+        assert!(channel.users.contains_key("test2"));
+    }
+
+    #[test]
+    fn test_user_already_in_channel() {
+        let user = User::new(
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let mut channel = Channel::new("test".to_string(), &user);
+        let another_user = User::new(
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+
+        channel.join(another_user, None).unwrap();
+
+        assert_eq!(channel.users.len(), 1);
+        assert!(channel.users.contains_key("test"));
+        //assert_eq!(channel.operator, "test");
+    }
+
+    #[test]
+    fn test_invite_user_already_in_channel() {
+        let user = User::new(
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let user_nick = user.nickname.clone();
+        let mut channel = Channel::new("test".to_string(), &user);
+
+        channel.save_invite(&user_nick, user.nickname.clone());
+
+        assert_eq!(channel.invites.len(), 0);
+    }
+
+    #[test]
+    fn test_invite_new_user() {
+        let user = User::new(
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let another_user = User::new(
+            "test2".to_string(),
+            "test2".to_string(),
+            "test2".to_string(),
+            "test2".to_string(),
+            "test2".to_string(),
+            "password".to_string(),
+        );
+        let user_nick = another_user.nickname.clone();
+        let mut channel = Channel::new("test".to_string(), &user);
+
+        channel.save_invite(&user_nick, user.nickname.clone());
 
-            channel_users.insert(user.nickname.clone(), user.clone());
-        }
+        assert_eq!(channel.invites.len(), 1);
+        assert_eq!(channel.invites[0], "test2");
+    }
 
-        Ok(Channel {
-            name: params[0][0].clone(),
-            topic,
-            key,
-            limit,
-            enter_mode: mode,
-            operator_settable_topic: params[0][5].parse::<bool>().unwrap(),
-            secret: params[0][6].parse::<bool>().unwrap(),
-            users: channel_users,
-            operators,
-            invites,
-            banned,
-        })
+    #[test]
+    fn test_topics_set_out_of_order_converge() {
+        let user = User::new(
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let user_nick = user.nickname.clone();
+        // Each server has its own copy of the channel
+        let mut channel_server_1 = Channel::new("#test".to_string(), &user);
+        let mut channel_server_2 = Channel::new("#test".to_string(), &user);
+
+        // Both servers set a topic locally, server 2 does it later
+        channel_server_1
+            .set_topic(&user_nick, "first topic", 100)
+            .unwrap();
+        channel_server_2
+            .set_topic(&user_nick, "second topic", 200)
+            .unwrap();
+
+        // Each server receives the topic set by the other one
+        assert!(channel_server_1.topic_wins("second topic", 200));
+        channel_server_1
+            .set_topic(&user_nick, "second topic", 200)
+            .unwrap();
+        assert!(!channel_server_2.topic_wins("first topic", 100));
+
+        assert_eq!(channel_server_1.topic, channel_server_2.topic);
+        assert_eq!(channel_server_1.topic, Some("second topic".to_string()));
     }
-}
 
-#[cfg(test)]
-mod tests {
+    #[test]
+    fn test_topics_set_at_same_time_converge() {
+        let user = User::new(
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let user_nick = user.nickname.clone();
+        let mut channel_server_1 = Channel::new("#test".to_string(), &user);
+        let mut channel_server_2 = Channel::new("#test".to_string(), &user);
+
+        channel_server_1
+            .set_topic(&user_nick, "topic a", 100)
+            .unwrap();
+        channel_server_2
+            .set_topic(&user_nick, "topic b", 100)
+            .unwrap();
+
+        if channel_server_1.topic_wins("topic b", 100) {
+            channel_server_1
+                .set_topic(&user_nick, "topic b", 100)
+                .unwrap();
+        }
+        if channel_server_2.topic_wins("topic a", 100) {
+            channel_server_2
+                .set_topic(&user_nick, "topic a", 100)
+                .unwrap();
+        }
 
-    use super::Channel;
-    use crate::server_utils::user::User;
+        assert_eq!(channel_server_1.topic, channel_server_2.topic);
+    }
 
     #[test]
-    fn test_new_channel() {
+    fn test_topic_set_time_is_shared_between_servers() {
         let user = User::new(
             "test".to_string(),
             "test".to_string(),
@@ -983,18 +2337,23 @@ mod tests {
             "test".to_string(),
             "password".to_string(),
         );
-        let channel = Channel::new("test".to_string(), &user);
-
-        assert_eq!(channel.name, "test");
-        assert_eq!(channel.topic, None);
-        //assert_eq!(channel.operator, "test");
-        assert_eq!(channel.users.len(), 1);
-        assert!(channel.users.contains_key("test"));
-        assert_eq!(channel.invites.len(), 0);
+        let mut users = HashMap::new();
+        users.insert(user.nickname.clone(), user.clone());
+        let mut channel = Channel::new("#test".to_string(), &user);
+        channel.set_topic(&user.nickname, "topic", 150).unwrap();
+        channel.created_at = 100;
+
+        let received_channel =
+            Channel::channel_from_message(channel.channel_to_message(), &users).unwrap();
+
+        assert_eq!(received_channel.topic, Some("topic".to_string()));
+        assert_eq!(received_channel.topic_set_at, Some(150));
+        assert_eq!(received_channel.topic_setter, Some("test".to_string()));
+        assert_eq!(received_channel.created_at, 100);
     }
 
     #[test]
-    fn test_join_channel_correctly() {
+    fn test_channel_modes_are_shared_between_servers() {
         let user = User::new(
             "test".to_string(),
             "test".to_string(),
@@ -1003,7 +2362,57 @@ mod tests {
             "test".to_string(),
             "password".to_string(),
         );
-        let mut channel = Channel::new("test".to_string(), &user);
+        let mut users = HashMap::new();
+        users.insert(user.nickname.clone(), user.clone());
+        let mut channel = Channel::new("#test".to_string(), &user);
+
+        // A channel without modes stays without them
+        let received_channel =
+            Channel::channel_from_message(channel.channel_to_message(), &users).unwrap();
+        assert!(received_channel.key.is_none());
+        assert!(received_channel.flood_protection.is_none());
+        assert!(received_channel.max_message_length.is_none());
+        assert!(!received_channel.no_external_messages);
+        assert!(!received_channel.moderated);
+        assert!(received_channel.voiced.is_empty());
+        assert!(received_channel.founder.is_none());
+
+        channel.key = Some("pass".to_string());
+        channel.limit = Some(5);
+        channel.flood_protection = FloodProtection::from_threshold("3:10:60");
+        channel.max_message_length = Some(100);
+        channel.no_external_messages = true;
+        channel.moderated = true;
+        channel.voiced.push("ari".to_string());
+        channel.founder = Some("test".to_string());
+
+        let received_channel =
+            Channel::channel_from_message(channel.channel_to_message(), &users).unwrap();
+        assert_eq!(received_channel.key, Some("pass".to_string()));
+        assert_eq!(received_channel.limit, Some(5));
+        assert_eq!(
+            received_channel
+                .flood_protection
+                .map(|flood_protection| flood_protection.threshold()),
+            Some("3:10:60".to_string())
+        );
+        assert_eq!(received_channel.max_message_length, Some(100));
+        assert!(received_channel.no_external_messages);
+        assert!(received_channel.moderated);
+        assert_eq!(received_channel.voiced, vec!["ari".to_string()]);
+        assert_eq!(received_channel.founder, Some("test".to_string()));
+    }
+
+    #[test]
+    fn test_registered_channel_gives_back_operator_to_its_founder() {
+        let founder = User::new(
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
         let another_user = User::new(
             "test2".to_string(),
             "test2".to_string(),
@@ -1012,17 +2421,89 @@ mod tests {
             "test2".to_string(),
             "password".to_string(),
         );
+        let mut channel = Channel::new("#test".to_string(), &founder);
+        channel.join(another_user.clone(), None).unwrap();
 
+        // Only operators can register it
+        assert_eq!(
+            channel.register("test2".to_string()),
+            Err(NumericReply::new(
+                ERR_CHANOPRIVSNEEDED_NUM,
+                ERR_CHANOPRIVSNEEDED_MSG,
+                Some(vec!["#test".to_string()])
+            ))
+        );
+        assert!(!channel.is_registered());
+        channel.register("test".to_string()).unwrap();
+        assert!(channel.is_registered());
+
+        // Another operator can't register it again, the founder can
+        channel.operators.push("test2".to_string());
+        assert_eq!(
+            channel.register("test2".to_string()),
+            Err(NumericReply::new(
+                ERR_ALREADYFOUNDED_NUM,
+                ERR_ALREADYFOUNDED_MSG,
+                Some(vec!["#test".to_string()])
+            ))
+        );
+        assert_eq!(channel.register("test".to_string()), Ok(()));
+        assert_eq!(channel.founder, Some("test".to_string()));
+
+        channel.part(founder.clone());
+        channel.part(another_user.clone());
+        assert!(channel.is_empty());
+        assert!(channel.operators.is_empty());
+
+        // Other users don't get the operator privileges, the founder does
         channel.join(another_user, None).unwrap();
+        assert!(channel.operators.is_empty());
+        channel.join(founder, None).unwrap();
+        assert_eq!(channel.operators, vec!["test".to_string()]);
+
+        // A new nickname of the founder is still the founder
+        channel.founder_changed_nickname("TEST", "test3");
+        assert!(channel.is_founder("test3"));
+        channel.founder_changed_nickname("test2", "test4");
+        assert!(channel.is_founder("test3"));
+    }
 
-        assert_eq!(channel.users.len(), 2);
-        assert!(channel.users.contains_key("test"));
-        //assert_eq!(channel.operator, "test");
-        assert!(channel.users.contains_key("test2"));
+    #[test]
+    fn test_topic_details_have_the_creation_time_and_who_set_the_topic() {
+        let user = User::new(
+            "ari".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let mut channel = Channel::new("#test".to_string(), &user);
+        channel.created_at = 1671234567;
+
+        let details: Vec<String> = channel
+            .get_topic_details_replies()
+            .iter()
+            .map(|reply| reply.as_string())
+            .collect();
+        assert_eq!(details, vec!["329 #test 1671234567 \r\n"]);
+
+        channel
+            .set_topic(&user.nickname, "topic", 1671234599000)
+            .unwrap();
+        let details: Vec<String> = channel
+            .get_topic_details_replies()
+            .iter()
+            .map(|reply| reply.as_string())
+            .collect();
+        assert_eq!(
+            details,
+            vec!["329 #test 1671234567 \r\n", "333 #test ari 1671234599 \r\n"]
+        );
     }
 
     #[test]
-    fn test_user_already_in_channel() {
+    fn test_part_all_empties_channel() {
         let user = User::new(
             "test".to_string(),
             "test".to_string(),
@@ -1033,6 +2514,25 @@ mod tests {
         );
         let mut channel = Channel::new("test".to_string(), &user);
         let another_user = User::new(
+            "test2".to_string(),
+            "test2".to_string(),
+            "test2".to_string(),
+            "test2".to_string(),
+            "test2".to_string(),
+            "password".to_string(),
+        );
+        channel.join(another_user, None).unwrap();
+
+        let mut parted = channel.part_all();
+        parted.sort();
+
+        assert_eq!(parted, vec!["test".to_string(), "test2".to_string()]);
+        assert!(channel.is_empty());
+    }
+
+    #[test]
+    fn test_kick_reason_is_the_comment_or_the_nickname_kicking() {
+        let user = User::new(
             "test".to_string(),
             "test".to_string(),
             "test".to_string(),
@@ -1040,16 +2540,74 @@ mod tests {
             "test".to_string(),
             "password".to_string(),
         );
+        let mut channel = Channel::new("test".to_string(), &user);
+        for nickname in ["test2", "test3"] {
+            let another_user = User::new(
+                nickname.to_string(),
+                nickname.to_string(),
+                nickname.to_string(),
+                nickname.to_string(),
+                nickname.to_string(),
+                "password".to_string(),
+            );
+            channel.join(another_user, None).unwrap();
+        }
 
-        channel.join(another_user, None).unwrap();
+        let comment = "spam".to_string();
+        let reason = channel.kick("test2", "test", Some(&comment));
+        assert_eq!(reason, Ok("spam".to_string()));
+        assert!(!channel.is_user_on_channel("test2"));
 
-        assert_eq!(channel.users.len(), 1);
-        assert!(channel.users.contains_key("test"));
-        //assert_eq!(channel.operator, "test");
+        let reason = channel.kick("test3", "test", None);
+        assert_eq!(reason, Ok("test".to_string()));
+        assert!(!channel.is_user_on_channel("test3"));
     }
 
     #[test]
-    fn test_invite_user_already_in_channel() {
+    fn test_knock_notices_reach_every_operator_of_the_channel() {
+        let user = User::new(
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let mut channel = Channel::new("#test".to_string(), &user);
+        assert!(!channel.is_invite_only(Instant::now()));
+        channel.set_as_invite_only("test".to_string()).unwrap();
+        assert!(channel.is_invite_only(Instant::now()));
+        channel.operators.push("test2".to_string());
+
+        let notices: Vec<String> = channel
+            .knock_notices("test3", Some("let me in"))
+            .iter()
+            .map(Message::as_string)
+            .collect();
+        assert_eq!(
+            notices,
+            vec![
+                ":#test NOTICE test :test3 is knocking, asking for an invitation - let me in\r\n",
+                ":#test NOTICE test2 :test3 is knocking, asking for an invitation - let me in\r\n",
+            ]
+        );
+        assert_eq!(
+            channel.knock_notices("test3", None)[0].params[1][0],
+            "test3 is knocking, asking for an invitation"
+        );
+    }
+
+    #[test]
+    fn test_masks_match_with_wildcards() {
+        assert!(matches_mask("*!*@evil.host", "test!test@evil.host"));
+        assert!(matches_mask("te?t!*@*", "TEST!someone@server"));
+        assert!(matches_mask("test", "test"));
+        assert!(!matches_mask("*!*@evil.host", "test!test@good.host"));
+        assert!(!matches_mask("te?t", "tet"));
+    }
+
+    #[test]
+    fn test_banned_by_mask_or_nickname_cannot_join() {
         let user = User::new(
             "test".to_string(),
             "test".to_string(),
@@ -1058,16 +2616,107 @@ mod tests {
             "test".to_string(),
             "password".to_string(),
         );
-        let user_nick = user.nickname.clone();
         let mut channel = Channel::new("test".to_string(), &user);
+        let ban = Message {
+            tags: None,
+            prefix: None,
+            command: "MODE".to_string(),
+            params: vec![
+                vec!["test".to_string()],
+                vec!["+b".to_string()],
+                vec!["*!*@evil.host".to_string(), "test3".to_string()],
+            ],
+        };
+        channel.set_ban(ban, "test".to_string()).unwrap();
+
+        for (nickname, server_name) in [("test2", "evil.host"), ("test3", "good.host")] {
+            let banned_user = User::new(
+                nickname.to_string(),
+                nickname.to_string(),
+                nickname.to_string(),
+                nickname.to_string(),
+                server_name.to_string(),
+                "password".to_string(),
+            );
+            let reply = channel.join(banned_user, None).unwrap();
+            assert!(reply.has_number(vec![ERR_BANNEDFROMCHAN_NUM]));
+            assert!(!channel.is_user_on_channel(nickname));
+        }
 
-        channel.save_invite(&user_nick, user.nickname.clone());
+        let allowed_user = User::new(
+            "test4".to_string(),
+            "test4".to_string(),
+            "test4".to_string(),
+            "test4".to_string(),
+            "good.host".to_string(),
+            "password".to_string(),
+        );
+        channel.join(allowed_user, None).unwrap();
+        assert!(channel.is_user_on_channel("test4"));
+        assert_eq!(
+            channel.get_ban_list(),
+            vec!["*!*@evil.host".to_string(), "test3".to_string()]
+        );
+    }
 
-        assert_eq!(channel.invites.len(), 0);
+    #[test]
+    fn test_exception_lets_a_banned_user_join() {
+        let user = User::new(
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let mut channel = Channel::new("test".to_string(), &user);
+        let mode = |mode: &str, masks: Vec<&str>| Message {
+            tags: None,
+            prefix: None,
+            command: "MODE".to_string(),
+            params: vec![
+                vec!["test".to_string()],
+                vec![mode.to_string()],
+                masks.iter().map(|mask| mask.to_string()).collect(),
+            ],
+        };
+        channel
+            .set_ban(mode("+b", vec!["*!*@evil.host"]), "test".to_string())
+            .unwrap();
+        channel
+            .set_exception(mode("+e", vec!["friend!*@*"]), "test".to_string())
+            .unwrap();
+        assert!(channel
+            .set_exception(mode("+e", vec!["other"]), "friend".to_string())
+            .is_err());
+
+        let evil_user = |nickname: &str| {
+            User::new(
+                nickname.to_string(),
+                nickname.to_string(),
+                nickname.to_string(),
+                nickname.to_string(),
+                "evil.host".to_string(),
+                "password".to_string(),
+            )
+        };
+        let reply = channel.join(evil_user("enemy"), None).unwrap();
+        assert!(reply.has_number(vec![ERR_BANNEDFROMCHAN_NUM]));
+        channel.join(evil_user("friend"), None).unwrap();
+        assert!(channel.is_user_on_channel("friend"));
+        assert_eq!(channel.get_exception_list(), vec!["friend!*@*".to_string()]);
+
+        // Without the exception the ban applies again
+        channel.remove_user("friend");
+        channel
+            .remove_exception(mode("-e", vec!["FRIEND!*@*"]), "test".to_string())
+            .unwrap();
+        let reply = channel.join(evil_user("friend"), None).unwrap();
+        assert!(reply.has_number(vec![ERR_BANNEDFROMCHAN_NUM]));
     }
 
     #[test]
-    fn test_invite_new_user() {
+    fn test_ban_list_is_limited() {
         let user = User::new(
             "test".to_string(),
             "test".to_string(),
@@ -1076,20 +2725,470 @@ mod tests {
             "test".to_string(),
             "password".to_string(),
         );
-        let another_user = User::new(
-            "test2".to_string(),
-            "test2".to_string(),
-            "test2".to_string(),
-            "test2".to_string(),
-            "test2".to_string(),
+        let mut channel = Channel::new("test".to_string(), &user);
+        let ban = |masks: Vec<String>| Message {
+            tags: None,
+            prefix: None,
+            command: "MODE".to_string(),
+            params: vec![vec!["test".to_string()], vec!["+b".to_string()], masks],
+        };
+        let masks: Vec<String> = (0..MAX_BANS).map(|i| format!("user_{}", i)).collect();
+        channel.set_ban(ban(masks), "test".to_string()).unwrap();
+        assert_eq!(channel.banned.len(), MAX_BANS);
+
+        // A mask already banned is not counted again
+        channel
+            .set_ban(ban(vec!["USER_0".to_string()]), "test".to_string())
+            .unwrap();
+
+        let reply = channel
+            .set_ban(ban(vec!["another".to_string()]), "test".to_string())
+            .unwrap_err();
+        assert_eq!(
+            reply,
+            NumericReply::new(
+                ERR_BANLISTFULL_NUM,
+                ERR_BANLISTFULL_MSG,
+                Some(vec!["test".to_string(), "b".to_string()])
+            )
+        );
+        assert!(!channel.banned.contains("another"));
+    }
+
+    #[test]
+    fn test_backlog_keeps_the_last_messages_with_the_marker() {
+        let user = User::new(
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let mut channel = Channel::new("#Test".to_string(), &user);
+        let message = |command: &str, text: &str| Message {
+            tags: None,
+            prefix: Some("test".to_string()),
+            command: command.to_string(),
+            params: vec![vec!["#test".to_string()], vec![text.to_string()]],
+        };
+        let received_at = UNIX_EPOCH + Duration::from_secs(1671234567);
+        for text in ["uno", "dos"] {
+            channel.record_message(&message("PRIVMSG", text), received_at, 2);
+        }
+        // A time that came with the message is replaced by the one of this server
+        let mut forged = message("PRIVMSG", "tres");
+        forged.tags = Some(HashMap::from([(
+            "time".to_string(),
+            "1999-01-01T00:00:00.000Z".to_string(),
+        )]));
+        channel.record_message(&forged, received_at, 2);
+        channel.record_message(&message("QUIT", "chau"), received_at, 2);
+
+        let tags = Some(HashMap::from([(
+            "time".to_string(),
+            "2022-12-16T23:49:27.000Z".to_string(),
+        )]));
+        assert_eq!(
+            channel.backlog_messages(),
+            vec![
+                Message {
+                    tags: tags.clone(),
+                    prefix: Some("test".to_string()),
+                    command: "PRIVMSG".to_string(),
+                    params: vec![vec!["#Test".to_string()], vec!["(backlog) dos".to_string()]],
+                },
+                Message {
+                    tags,
+                    prefix: Some("test".to_string()),
+                    command: "PRIVMSG".to_string(),
+                    params: vec![
+                        vec!["#Test".to_string()],
+                        vec!["(backlog) tres".to_string()]
+                    ],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_flood_threshold_is_parsed_with_optional_cooldown() {
+        let flood_protection = FloodProtection::from_threshold("5:10").unwrap();
+        assert_eq!(flood_protection.max_events, 5);
+        assert_eq!(flood_protection.interval, Duration::from_secs(10));
+        assert_eq!(flood_protection.cooldown, Duration::from_secs(60));
+
+        let flood_protection = FloodProtection::from_threshold("3:5:30").unwrap();
+        assert_eq!(flood_protection.cooldown, Duration::from_secs(30));
+
+        assert!(FloodProtection::from_threshold("5").is_none());
+        assert!(FloodProtection::from_threshold("0:10").is_none());
+        assert!(FloodProtection::from_threshold("a:10").is_none());
+    }
+
+    #[test]
+    fn test_exceeding_join_threshold_protects_channel_until_cooldown_ends() {
+        let user = User::new(
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
             "password".to_string(),
         );
-        let user_nick = another_user.nickname.clone();
         let mut channel = Channel::new("test".to_string(), &user);
+        channel.flood_protection = Some(FloodProtection::new(
+            2,
+            Duration::from_secs(10),
+            Duration::from_secs(30),
+        ));
+        let start = Instant::now();
+
+        assert!(!channel.record_join(start));
+        assert!(!channel.record_join(start + Duration::from_secs(1)));
+        assert!(!channel.is_flood_protected(start + Duration::from_secs(1)));
+
+        // The third join in less than 10 seconds turns on the protection
+        assert!(channel.record_join(start + Duration::from_secs(2)));
+        assert!(channel.is_flood_protected(start + Duration::from_secs(2)));
+        assert!(channel.is_flood_protected(start + Duration::from_secs(31)));
+
+        // The channel recovers after the cooldown
+        assert!(!channel.is_flood_protected(start + Duration::from_secs(32)));
+        assert!(!channel.record_join(start + Duration::from_secs(33)));
+    }
 
-        channel.save_invite(&user_nick, user.nickname.clone());
+    #[test]
+    fn test_exceeding_message_threshold_mutes_channel_until_cooldown_ends() {
+        let new_user = |nickname: &str| {
+            User::new(
+                nickname.to_string(),
+                "test".to_string(),
+                "test".to_string(),
+                "test".to_string(),
+                "test".to_string(),
+                "password".to_string(),
+            )
+        };
+        let mut channel = Channel::new("#test".to_string(), &new_user("lucas"));
+        channel.join(new_user("ari"), None).unwrap();
+        channel.join(new_user("marce"), None).unwrap();
+        channel.voiced.push("marce".to_string());
+        channel.flood_protection = Some(FloodProtection::new(
+            2,
+            Duration::from_secs(10),
+            Duration::from_secs(30),
+        ));
+        let start = Instant::now();
+
+        assert!(!channel.record_sent_message(start));
+        assert!(!channel.record_sent_message(start + Duration::from_secs(1)));
+        assert!(!channel.is_flood_muted(start + Duration::from_secs(1)));
+
+        // The third message in less than 10 seconds mutes the channel, but not its operators
+        // and voiced users
+        assert!(channel.record_sent_message(start + Duration::from_secs(2)));
+        assert!(channel.is_flood_muted(start + Duration::from_secs(31)));
+        assert!(!channel.can_send_message("ari"));
+        assert!(channel.can_send_message("lucas"));
+        assert!(channel.can_send_message("marce"));
+        // Joins are counted on their own
+        assert!(!channel.is_flood_protected(start + Duration::from_secs(2)));
+
+        assert!(!channel.is_flood_muted(start + Duration::from_secs(32)));
+        assert!(!channel.record_sent_message(start + Duration::from_secs(33)));
+    }
 
-        assert_eq!(channel.invites.len(), 1);
-        assert_eq!(channel.invites[0], "test2");
+    #[test]
+    fn test_joins_spread_over_time_do_not_trigger_flood_protection() {
+        let mut flood_protection =
+            FloodProtection::new(2, Duration::from_secs(10), Duration::from_secs(30));
+        let start = Instant::now();
+
+        for second in [0, 6, 12, 18, 24] {
+            assert!(!flood_protection.record_join(start + Duration::from_secs(second)));
+        }
+    }
+
+    #[test]
+    fn names_mark_the_operators_and_the_secret_channels() {
+        let new_user = |nickname: &str| {
+            User::new(
+                nickname.to_string(),
+                "test".to_string(),
+                "test".to_string(),
+                "test".to_string(),
+                "test".to_string(),
+                "password".to_string(),
+            )
+        };
+        let mut channel = Channel::new("#canal".to_string(), &new_user("lucas"));
+        channel.users.insert("ari".to_string(), new_user("ari"));
+        channel.users.insert("marce".to_string(), new_user("marce"));
+
+        assert_eq!(channel.names(), vec!["@lucas", "ari", "marce"]);
+
+        channel.voiced.push("marce".to_string());
+        channel.voiced.push("lucas".to_string());
+        assert_eq!(channel.names(), vec!["@lucas", "+marce", "ari"]);
+        assert_eq!(channel.visibility_flag(), RPL_NAMEREPLY_PUBLIC);
+
+        channel.secret = true;
+        assert_eq!(channel.visibility_flag(), RPL_NAMEREPLY_SECRET);
+    }
+
+    #[test]
+    fn who_replies_flag_the_members_away_their_prefix_and_the_other_servers() {
+        let new_user = |nickname: &str, server_name: &str| {
+            User::new(
+                nickname.to_string(),
+                "localhost".to_string(),
+                format!("{}_user", nickname),
+                format!("{} real", nickname),
+                server_name.to_string(),
+                "password".to_string(),
+            )
+        };
+        let mut channel = Channel::new("#canal".to_string(), &new_user("lucas", "rust"));
+        channel.join(new_user("ari", "rust"), None).unwrap();
+        channel.join(new_user("marce", "other"), None).unwrap();
+        channel.voiced.push("marce".to_string());
+
+        // The away status is taken from the users, not from the copy of the channel
+        let mut users = HashMap::new();
+        let mut ari = new_user("ari", "rust");
+        ari.away_message = Some("lunch".to_string());
+        users.insert("ari".to_string(), ari);
+
+        let replies: Vec<String> = channel
+            .who_replies(&users, "lucas", "rust")
+            .iter()
+            .map(NumericReply::as_string)
+            .collect();
+        assert_eq!(
+            replies,
+            vec![
+                "352 ari #canal ari_user localhost rust G :0 ari real\r\n",
+                "352 lucas #canal lucas_user localhost rust H@ :0 lucas real\r\n",
+                "352 marce #canal marce_user localhost other H+ :1 marce real\r\n",
+            ]
+        );
+
+        // Outsiders don't see the invisible members nor the secret channels
+        channel.users.get_mut("ari").unwrap().invisible = true;
+        users.get_mut("ari").unwrap().invisible = true;
+        assert_eq!(channel.who_replies(&users, "juani", "rust").len(), 2);
+        assert_eq!(channel.who_replies(&users, "ari", "rust").len(), 3);
+        channel.secret = true;
+        assert!(channel.who_replies(&users, "juani", "rust").is_empty());
+    }
+
+    #[test]
+    fn test_modes_reply_lists_the_modes_on_and_hides_the_key_to_outsiders() {
+        let user = User::new(
+            "lucas".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let mut channel = Channel::new("#canal".to_string(), &user);
+        assert_eq!(
+            channel.get_modes_reply("lucas").as_string(),
+            "324 #canal :+\r\n"
+        );
+
+        channel.key = Some("clave".to_string());
+        channel.limit = Some(10);
+        channel.operator_settable_topic = true;
+        channel.secret = true;
+        channel.flood_protection = FloodProtection::from_threshold("5:10:30");
+        channel.max_message_length = Some(200);
+        channel.no_external_messages = true;
+        channel.moderated = true;
+
+        let reply = channel.get_modes_reply("lucas");
+        assert!(reply.has_number(vec![RPL_CHANNELMODEIS_NUM]));
+        assert_eq!(
+            reply.as_string(),
+            "324 #canal :+kltsnmfL clave 10 5:10:30 200\r\n"
+        );
+        assert_eq!(
+            channel.get_modes_reply("ari").as_string(),
+            "324 #canal :+kltsnmfL 10 5:10:30 200\r\n"
+        );
+    }
+
+    #[test]
+    fn test_operators_can_limit_the_length_of_the_messages() {
+        let new_user = |nickname: &str| {
+            User::new(
+                nickname.to_string(),
+                "test".to_string(),
+                "test".to_string(),
+                "test".to_string(),
+                "test".to_string(),
+                "password".to_string(),
+            )
+        };
+        let mode = |mode: &str, length: &str| Message {
+            tags: None,
+            prefix: None,
+            command: "MODE".to_string(),
+            params: vec![
+                vec!["#canal".to_string()],
+                vec![mode.to_string()],
+                vec![length.to_string()],
+            ],
+        };
+        let mut channel = Channel::new("#canal".to_string(), &new_user("lucas"));
+        channel.join(new_user("ari"), None).unwrap();
+        assert!(!channel.is_message_too_long("Un mensaje sin limite de largo"));
+
+        let reply = channel
+            .set_max_message_length(mode("+L", "5"), "ari".to_string())
+            .unwrap_err();
+        assert!(reply.has_number(vec![ERR_CHANOPRIVSNEEDED_NUM]));
+        for length in ["0", "cinco"] {
+            let reply = channel
+                .set_max_message_length(mode("+L", length), "lucas".to_string())
+                .unwrap_err();
+            assert!(reply.has_number(vec![ERR_INVALIDLIMIT_NUM]));
+        }
+
+        channel
+            .set_max_message_length(mode("+L", "5"), "lucas".to_string())
+            .unwrap();
+        assert!(!channel.is_message_too_long("árbol"));
+        assert!(channel.is_message_too_long("árboles"));
+
+        channel
+            .remove_max_message_length("lucas".to_string())
+            .unwrap();
+        assert!(!channel.is_message_too_long("árboles"));
+    }
+
+    #[test]
+    fn test_channels_created_in_two_servers_are_merged() {
+        let new_user = |nickname: &str| {
+            User::new(
+                nickname.to_string(),
+                "test".to_string(),
+                "test".to_string(),
+                "test".to_string(),
+                "test".to_string(),
+                "password".to_string(),
+            )
+        };
+        let mut channel = Channel::new("#canal".to_string(), &new_user("lucas"));
+        channel.join(new_user("ari"), None).unwrap();
+        channel.created_at = 1671234567;
+
+        channel.merge_creation(new_user("marce"), 1671234560);
+        assert!(channel.is_user_on_channel("marce"));
+        assert_eq!(channel.operators, vec!["lucas", "marce"]);
+        assert_eq!(channel.created_at, 1671234560);
+
+        // The earliest creation is kept and operators are not repeated
+        channel.merge_creation(new_user("Marce"), 1671234599);
+        assert_eq!(channel.operators, vec!["lucas", "marce"]);
+        assert_eq!(channel.users.len(), 3);
+        assert_eq!(channel.created_at, 1671234560);
+    }
+
+    #[test]
+    fn test_only_members_send_messages_to_channels_without_external_messages() {
+        let new_user = |nickname: &str| {
+            User::new(
+                nickname.to_string(),
+                "test".to_string(),
+                "test".to_string(),
+                "test".to_string(),
+                "test".to_string(),
+                "password".to_string(),
+            )
+        };
+        let mut channel = Channel::new("#canal".to_string(), &new_user("lucas"));
+        channel.join(new_user("ari"), None).unwrap();
+        assert!(channel.can_send_message("marce"));
+
+        let reply = channel
+            .set_no_external_messages("ari".to_string())
+            .unwrap_err();
+        assert!(reply.has_number(vec![ERR_CHANOPRIVSNEEDED_NUM]));
+
+        channel
+            .set_no_external_messages("lucas".to_string())
+            .unwrap();
+        assert!(channel.can_send_message("ARI"));
+        assert!(channel.can_send_message("lucas"));
+        assert!(!channel.can_send_message("marce"));
+
+        channel
+            .remove_no_external_messages("lucas".to_string())
+            .unwrap();
+        assert!(channel.can_send_message("marce"));
+    }
+
+    #[test]
+    fn test_only_operators_and_voiced_users_send_messages_to_moderated_channels() {
+        let new_user = |nickname: &str| {
+            User::new(
+                nickname.to_string(),
+                "test".to_string(),
+                "test".to_string(),
+                "test".to_string(),
+                "test".to_string(),
+                "password".to_string(),
+            )
+        };
+        let mode = |mode: &str, nickname: &str| Message {
+            tags: None,
+            prefix: Some("lucas".to_string()),
+            command: "MODE".to_string(),
+            params: vec![
+                vec!["#canal".to_string()],
+                vec![mode.to_string()],
+                vec![nickname.to_string()],
+            ],
+        };
+        let mut channel = Channel::new("#canal".to_string(), &new_user("lucas"));
+        channel.join(new_user("ari"), None).unwrap();
+        channel.join(new_user("marce"), None).unwrap();
+
+        let reply = channel.set_moderated("ari".to_string()).unwrap_err();
+        assert!(reply.has_number(vec![ERR_CHANOPRIVSNEEDED_NUM]));
+        channel.set_moderated("lucas".to_string()).unwrap();
+        assert!(channel.can_send_message("lucas"));
+        assert!(!channel.can_send_message("ari"));
+        assert!(!channel.can_send_message("juani"));
+
+        let reply = channel
+            .give_voice(mode("+v", "juani"), "lucas".to_string())
+            .unwrap_err();
+        assert!(reply.has_number(vec![ERR_NOSUCHNICK_NUM]));
+        let reply = channel
+            .give_voice(mode("+v", "marce"), "ari".to_string())
+            .unwrap_err();
+        assert!(reply.has_number(vec![ERR_CHANOPRIVSNEEDED_NUM]));
+
+        channel
+            .give_voice(mode("+v", "ARI"), "lucas".to_string())
+            .unwrap();
+        channel
+            .give_voice(mode("+v", "ari"), "lucas".to_string())
+            .unwrap();
+        assert_eq!(channel.voiced, vec!["ARI"]);
+        assert!(channel.can_send_message("ari"));
+        assert!(!channel.can_send_message("marce"));
+
+        channel
+            .remove_voice(mode("-v", "ari"), "lucas".to_string())
+            .unwrap();
+        assert!(!channel.can_send_message("ari"));
+
+        channel.remove_moderated("lucas".to_string()).unwrap();
+        assert!(channel.can_send_message("marce"));
     }
 }
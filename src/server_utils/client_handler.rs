@@ -4,50 +4,67 @@
 
 use std::{
     collections::HashMap,
+    io::BufReader,
     io::Write,
-    io::{BufRead, BufReader},
+    net::Shutdown,
     net::TcpStream,
     sync::{mpsc::Receiver, mpsc::Sender, Arc, Mutex},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use crate::{
     commands::{
-        AWAY, CHANNEL_INFO, INVITE, JOIN, KICK, LIST, MODE, NAMES, NICK, NOTICE, OPERATOR, PART,
-        PRIVMSG, QUIT, REGISTRATION, SQUIT, TOPIC, USERS_INFO, WHO, WHOIS,
+        AWAY, CHANNEL_INFO, CLOSE, GLOBOPS, INVITE, ISON, JOIN, KICK, KNOCK, LIST, LUSERS, MODE,
+        MOTD, NAMES, NICK, NOTICE, OPERATOR, PART, PING, PONG, PRIVMSG, QUIT, REGISTER,
+        REGISTRATION, REHASH, SILENCE, SQUIT, TOPIC, USERS_INFO, USER_MODE, WALLOPS, WHO, WHOIS,
+        WHOWAS,
     },
     custom_errors::errors::{CRITICAL, SEND_MESSAGE},
     message::Message,
-    numeric_reply::NumericReply,
-    parser::parse,
+    numeric_reply::{NumericReply, ERR_NICKCOLLISION_NUM},
+    parser::{irc_lowercase, parse, read_line_lossy},
     server_utils::{
+        acknowledgements::{acknowledgement, Acknowledgements},
         channel::Channel,
+        keepalive::{Keepalive, KeepaliveAction},
+        logger::Logger,
         messages_processing_client::{
-            admin_server::handle_quit_server,
-            connection_and_registration::{change_nick, quit, set_operator},
+            admin_server::{handle_quit_server, handle_rehash, handle_wallops},
+            connection_and_registration::{change_nick, handle_pong, motd, quit, set_operator},
             manage_channels::{
-                invite_to_channel, join_channel, kick, list_channels, names, part_channel,
-                set_channel_mode, topic,
+                close_channel, invite_to_channel, join_channel, kick, knock_channel, list_channels,
+                names, part_channel, register_channel, rejoin_channels, set_channel_mode, topic,
             },
             messages_exchange::{notice, private_message},
-            user_information::{handle_away, handle_who, whois},
+            user_information::{
+                handle_away, handle_ison, handle_lusers, handle_silence, handle_who, handle_whowas,
+                set_user_mode, whois,
+            },
         },
         messages_processing_server::{
-            connection_and_registration::{handle_registration_server, handle_users_info},
+            connection_and_registration::{
+                handle_nick_collision_server, handle_registration_server, handle_user_quit_server,
+                handle_users_info,
+            },
             manage_channels::{
-                handle_away_server, handle_channel_info, handle_invite_multiserver,
-                handle_join_server, handle_kick_multiserver, handle_mode_multiserver,
-                handle_part_multiserver, handle_topic,
+                handle_away_server, handle_channel_info, handle_close_multiserver,
+                handle_invite_multiserver, handle_join_server, handle_kick_multiserver,
+                handle_mode_multiserver, handle_part_multiserver, handle_register_multiserver,
+                handle_topic,
             },
-            manage_server::handle_squit,
+            manage_server::{handle_squit, handle_wallops_server},
             message_exchange::handle_privmsg_server,
         },
+        rate_limit::{is_rate_limited, RateLimit, RateLimitAction},
         user::User,
     },
 };
 
 use crate::custom_errors::server_error::ServerError;
 
+// Sent to a user whose messages are being dropped for sending too many
+pub const FLOOD_NOTICE: &str = "You are sending messages too fast, they are being dropped";
+
 pub struct ClientHandler<'a> {
     pub stream: &'a TcpStream,
     pub sender: Sender<Message>,
@@ -57,6 +74,13 @@ pub struct ClientHandler<'a> {
     pub client_name: String, // nickname from user or server name
     pub user: Option<User>,  // If client is a server then user = None
     pub reader: BufReader<TcpStream>,
+    pub max_channels_per_join: usize, //the rest of the channels of a JOIN are rejected
+    pub motd_file_path: String,
+    pub keepalive: Keepalive,
+    pub rate_limit: RateLimit,
+    pub away_on_join: bool, //the users that join a channel are told which members are away
+    pub logger: Logger,
+    pub acknowledgements: Acknowledgements, // labels received, kept from the login
 }
 
 impl ClientHandler<'_> {
@@ -64,7 +88,7 @@ impl ClientHandler<'_> {
     /// Reads messages from the client and carries out the request.
     ///
     pub fn handle_client(&mut self) -> Result<(), ServerError> {
-        println!("handling client");
+        self.logger.info(&self.client_name, "", "Connected");
 
         // Wait until there's data to read
         self.stream.try_clone().map_err(|_| -> ServerError {
@@ -74,21 +98,45 @@ impl ClientHandler<'_> {
             }
         })?;
 
+        // Users receive the message of the day once they are logged in
+        self.send_motd()?;
+
+        // And they are back in the channels they were part of before
+        if let Some(user) = &self.user {
+            rejoin_channels(self.stream, &self.channels, &self.users, &user.nickname)?;
+        }
+
         // Keep reading every message received
         self.read_and_handle_messages()?;
 
         Ok(())
     }
 
+    ///
+    /// Sends the message of the day to the user if the server has a MOTD file
+    ///
+    fn send_motd(&self) -> Result<(), ServerError> {
+        let user = match &self.user {
+            Some(user) if self.motd_file_path != "none" => user,
+            _ => return Ok(()),
+        };
+
+        if let Some(reply) = motd(self.stream, &self.motd_file_path, &user.server_name)? {
+            self.send_reply(reply, self.stream)?;
+        }
+
+        Ok(())
+    }
+
     /* FUNCTIONS TO KEEP ON READING MESSAGES (server socket or thread) */
 
     ///
     /// Reads data from the client and handles it.
     ///
     fn read_and_handle_messages(&mut self) -> Result<(), ServerError> {
-        println!("begining to read and handle messages from client");
-
         let mut data = String::new();
+        // Bytes of a line cut by the timeout
+        let mut pending = Vec::new();
 
         self.stream
             .set_read_timeout(Some(Duration::from_millis(100))) // this is needed so that it doesnt block
@@ -99,16 +147,15 @@ impl ClientHandler<'_> {
                 }
             })?;
 
-        while match self.reader.read_line(&mut data) {
+        while match read_line_lossy(&mut self.reader, &mut pending, &mut data) {
             Ok(_bytes_read) => {
                 // If data was read then handle it
                 if !data.is_empty() {
-                    println!("Message from client read");
                     self.handle_data(&data)?;
                     data.clear();
                     true
                 } else {
-                    println!("No data read");
+                    self.logger.info(&self.client_name, "", "Connection closed");
                     false
                 }
             }
@@ -118,7 +165,13 @@ impl ClientHandler<'_> {
         } {
             // In every execution execute this block
             // Receive from the server channel to write to client
-            self.read_from_server()?
+            if !self.read_from_server()? {
+                break;
+            }
+            // Check that the user is still there
+            if !self.keep_alive()? {
+                break;
+            }
         }
         Ok(())
     }
@@ -127,36 +180,67 @@ impl ClientHandler<'_> {
     /// Handles data read from the client. Parses message and carries out the request.
     ///
     fn handle_data(&mut self, data: &str) -> Result<(), ServerError> {
-        println!("handling data read");
+        self.keepalive.activity(Instant::now());
 
         // Parse messsage
         let mut message = match parse(data.to_owned()) {
-            Ok(msg) => msg,
+            Ok(message) => message,
             Err(_) => {
+                self.logger
+                    .error(&self.client_name, "", "Couldn't parse the message received");
                 return Err(ServerError {
                     kind: CRITICAL.to_string(),
                     message: "Error".to_string(),
-                })
+                });
             }
         };
 
-        println!("message read in client handler {:?}", message);
+        // Labeled messages are acknowledged, the ones received before are not handled again
+        if let Some(label) = message.label() {
+            self.stream
+                .write_all(acknowledgement(label).as_bytes())
+                .map_err(|_| -> ServerError {
+                    ServerError {
+                        kind: CRITICAL.to_string(),
+                        message: "Could not send to client".to_string(),
+                    }
+                })?;
+            if !self.acknowledgements.received(label) {
+                return Ok(());
+            }
+        }
+
+        // Only servers set tags like time, the ones the client made up are dropped
+        message.remove_server_tags();
 
         // Add prefix of sender user to the message
 
         if message.prefix.is_none() && self.user.is_some() {
             message.prefix = Some(self.client_name.clone());
         }
-        println!("message read in client handler with prefix {:?}", message);
+        self.logger
+            .info(&self.client_name, &message.command, "Message read");
+
+        if !self.accept_rate(&message)? {
+            return Ok(());
+        }
+
+        // The answers to the keepalive are sent by the client, not by the user
+        if self.user.is_some() && message.command != PONG {
+            self.mark_user_active();
+        }
 
         // Handle message
+        let command = message.command.clone();
         match self.handle_message(message, self.sender.clone()) {
             Ok(_) => {}
             Err(err) => {
                 // If a critical error was found return error
                 if err.kind == *CRITICAL {
+                    self.logger.error(&self.client_name, &command, &err.message);
                     return Err(err);
                 }
+                self.logger.warn(&self.client_name, &command, &err.message);
             }
         };
 
@@ -164,16 +248,71 @@ impl ClientHandler<'_> {
     }
 
     ///
-    /// Reads message from server and sends it to the client.
+    /// Saves that the user sent a message now, WHOIS shows the time since then as idle time
     ///
-    fn read_from_server(&mut self) -> Result<(), ServerError> {
+    fn mark_user_active(&self) {
+        match self.users.lock() {
+            Ok(mut users) => {
+                if let Some(user) = users.get_mut(&irc_lowercase(&self.client_name)) {
+                    user.mark_active();
+                }
+            }
+            Err(_) => self
+                .logger
+                .warn(&self.client_name, "", "Could not lock users"),
+        }
+    }
+
+    ///
+    /// Checks that the user is not sending too many messages. Returns false if the message has to
+    /// be dropped, the first message dropped in a row is answered with a notice to the user
+    ///
+    fn accept_rate(&mut self, message: &Message) -> Result<bool, ServerError> {
+        if self.user.is_none() || !is_rate_limited(message) {
+            return Ok(true);
+        }
+
+        match self.rate_limit.check(Instant::now()) {
+            RateLimitAction::Accept => Ok(true),
+            RateLimitAction::Drop => Ok(false),
+            RateLimitAction::Warn => {
+                self.logger.warn(
+                    &self.client_name,
+                    &message.command,
+                    "Dropping messages, sent too fast",
+                );
+                let notice = Message {
+                    tags: None,
+                    prefix: None,
+                    command: NOTICE.to_string(),
+                    params: vec![
+                        vec![self.client_name.clone()],
+                        vec![FLOOD_NOTICE.to_string()],
+                    ],
+                };
+                self.stream
+                    .write_all(notice.as_string().as_bytes())
+                    .map_err(|_| -> ServerError {
+                        ServerError {
+                            kind: CRITICAL.to_string(),
+                            message: "Could not send to client".to_string(),
+                        }
+                    })?;
+                Ok(false)
+            }
+        }
+    }
+
+    ///
+    /// Reads message from server and sends it to the client. Returns false if it was the QUIT
+    /// of the server or a nickname collision, then nothing else will be sent
+    ///
+    fn read_from_server(&mut self) -> Result<bool, ServerError> {
         let message = match self.receiver.try_recv() {
             Ok(msg) => msg,
-            Err(_) => return Ok(()),
+            Err(_) => return Ok(true),
         };
 
-        println!("MESSAGE SENT, read from server: {}", message.as_string());
-
         self.stream
             .write_all(message.as_string().as_bytes())
             .map_err(|_| -> ServerError {
@@ -182,7 +321,60 @@ impl ClientHandler<'_> {
                     message: "Could not send to server".to_string(),
                 }
             })?;
-        Ok(())
+        // The user was forgotten because another server has it, its connection is closed
+        if message.command == ERR_NICKCOLLISION_NUM {
+            let _ = self.stream.shutdown(Shutdown::Both);
+            return Ok(false);
+        }
+        Ok(message.command != QUIT || message.prefix.is_some())
+    }
+
+    ///
+    /// Pings the user when it has been inactive for a while. If the PING is not answered
+    /// in time the user is treated as if it sent a QUIT. Returns false once the connection
+    /// is closed. Servers are not pinged.
+    ///
+    fn keep_alive(&mut self) -> Result<bool, ServerError> {
+        let user = match &self.user {
+            Some(user) => user,
+            None => return Ok(true),
+        };
+
+        let reason = match self.keepalive.check(Instant::now()) {
+            KeepaliveAction::Wait => return Ok(true),
+            KeepaliveAction::Ping => {
+                let ping = Message {
+                    tags: None,
+                    prefix: None,
+                    command: PING.to_string(),
+                    params: vec![vec![user.server_name.clone()]],
+                };
+                match self.stream.write_all(ping.as_string().as_bytes()) {
+                    Ok(_) => return Ok(true),
+                    Err(_) => "Connection lost",
+                }
+            }
+            KeepaliveAction::Disconnect => "Ping timeout",
+        };
+
+        self.logger
+            .warn(&self.client_name, "", &format!("Disconnecting: {}", reason));
+        let _ = self.stream.shutdown(Shutdown::Both);
+
+        let quit = Message {
+            tags: None,
+            prefix: Some(self.client_name.clone()),
+            command: QUIT.to_string(),
+            params: vec![vec![reason.to_string()]],
+        };
+        self.sender.send(quit).map_err(|_| -> ServerError {
+            ServerError {
+                kind: CRITICAL.to_string(),
+                message: "Could not send to server".to_string(),
+            }
+        })?;
+
+        Ok(false)
     }
 
     /* FUNCTIONS TO HANDLE MESSAGES */
@@ -223,7 +415,7 @@ impl ClientHandler<'_> {
 
         let command = &message.command;
         let result = match command.as_str() {
-            NICK => change_nick(message, &self.users, user),
+            NICK => change_nick(message, &self.users, &self.channels, user),
             PRIVMSG => private_message(
                 message,
                 self.users.clone(),
@@ -239,6 +431,8 @@ impl ClientHandler<'_> {
                 &self.users.clone(),
                 user,
                 &sender,
+                self.max_channels_per_join,
+                self.away_on_join,
             ),
             NAMES => names(message, self.stream, self.channels.clone()),
             LIST => list_channels(message, &self.channels.clone(), self.stream),
@@ -256,6 +450,11 @@ impl ClientHandler<'_> {
                 self.users.clone(),
                 user,
             ),
+            KNOCK => knock_channel(&sender, message, &self.channels.clone(), user),
+            REGISTER => register_channel(&sender, message, &self.channels.clone(), user),
+            MODE if is_user_mode(&message) => {
+                set_user_mode(message, user, self.users.clone(), &sender)
+            }
             MODE => set_channel_mode(
                 message,
                 &self.channels.clone(),
@@ -268,6 +467,8 @@ impl ClientHandler<'_> {
                 message,
                 self.stream,
                 self.users.clone(),
+                self.channels.clone(),
+                &user.nickname,
                 self.receiver,
                 &sender,
             ),
@@ -280,10 +481,19 @@ impl ClientHandler<'_> {
                 self.channels.clone(),
             ),
             QUIT => quit(message, self.stream, &sender, user),
+            PONG => handle_pong(),
+            LUSERS => handle_lusers(message, &sender),
+            ISON => handle_ison(message, &sender),
+            WHOWAS => handle_whowas(message, &sender),
+            MOTD => motd(self.stream, &self.motd_file_path, &user.server_name),
             AWAY => handle_away(message, user, self.users.clone(), Some(&sender)),
+            SILENCE => handle_silence(message, user, self.users.clone(), self.stream),
             SQUIT => handle_quit_server(message, &sender, self.receiver),
+            WALLOPS | GLOBOPS => handle_wallops(message, &sender, self.receiver),
+            REHASH => handle_rehash(message, &sender, self.receiver),
             KICK => kick(message, user, self.channels.clone(), &sender),
-            TOPIC => topic(message, self.channels.clone(), user, &sender),
+            TOPIC => topic(message, self.channels.clone(), user, &sender, self.stream),
+            CLOSE => close_channel(message, self.channels.clone(), user, &sender, self.receiver),
             _ => return Ok(()),
         };
 
@@ -312,8 +522,10 @@ impl ClientHandler<'_> {
         match command.as_str() {
             JOIN => handle_join_server(message, &sender),
             REGISTRATION => handle_registration_server(message, &self.sender),
+            ERR_NICKCOLLISION_NUM => handle_nick_collision_server(message, &self.sender),
+            QUIT => handle_user_quit_server(message, &self.sender),
             SQUIT => handle_squit(message, &sender, self.receiver, self.stream),
-            PRIVMSG => handle_privmsg_server(message, &sender),
+            PRIVMSG | NOTICE => handle_privmsg_server(message, &sender),
             USERS_INFO => handle_users_info(message, self.users.clone()),
             CHANNEL_INFO => handle_channel_info(message, self.channels.clone(), self.users.clone()),
             KICK => {
@@ -326,6 +538,13 @@ impl ClientHandler<'_> {
                 &self.sender,
                 self.client_name.clone(),
             ),
+            REGISTER => handle_register_multiserver(
+                message,
+                self.channels.clone(),
+                self.users.clone(),
+                &self.sender,
+                self.client_name.clone(),
+            ),
             PART => handle_part_multiserver(
                 message,
                 self.channels.clone(),
@@ -339,7 +558,9 @@ impl ClientHandler<'_> {
                 self.users.clone(),
                 &self.sender,
             ),
-            AWAY => handle_away_server(message, &self.sender),
+            AWAY | USER_MODE => handle_away_server(message, &self.sender),
+            CLOSE => handle_close_multiserver(message, &self.sender),
+            WALLOPS => handle_wallops_server(message, &self.sender),
             _ => return Ok(()),
         }?;
 
@@ -366,3 +587,13 @@ impl ClientHandler<'_> {
         Ok(())
     }
 }
+
+///
+/// Returns true if the MODE is for a user instead of a channel, like MODE ari +i
+///
+fn is_user_mode(message: &Message) -> bool {
+    matches!(
+        message.params.first().and_then(|target| target.first()),
+        Some(target) if !(target.starts_with('#') || target.starts_with('&'))
+    )
+}
@@ -5,29 +5,45 @@
 
 use std::{
     collections::HashMap,
+    io::BufReader,
     io::Write,
-    io::{BufRead, BufReader},
     net::TcpStream,
     sync::{mpsc::Receiver, mpsc::Sender, Arc, Mutex},
+    time::Instant,
 };
 
 use crate::{
-    commands::{LOGIN, SERVER},
+    commands::{LOGIN, NOTICE, NOTICE_AUTH, SERVER},
     custom_errors::errors::{CRITICAL, NONCRITICAL},
     message::Message,
-    numeric_reply::{NumericReply, ERR_ALREADYREGISTRED_MSG, ERR_ALREADYREGISTRED_NUM},
-    parser::parse,
+    numeric_reply::{
+        NumericReply, ERR_ALREADYREGISTRED_MSG, ERR_ALREADYREGISTRED_NUM, ERR_WEAKPASSWORD_NUM,
+    },
+    parser::{irc_lowercase, parse, read_line_lossy},
     server_utils::{
+        acknowledgements::{acknowledgement, Acknowledgements},
         channel::Channel,
         client_handler::ClientHandler,
+        keepalive::Keepalive,
+        logger::Logger,
         messages_processing_client::connection_and_registration::{
-            get_action, get_nickname, get_password, get_user_info, login_user, registrate_user,
+            check_password_strength, get_action, get_nickname, get_password, get_user_info,
+            login_user, registrate_user,
         },
+        rate_limit::{MessageRate, RateLimit},
+        server_data::PasswordPolicy,
         user::User,
+        welcome::Welcome,
     },
 };
 
 use crate::custom_errors::server_error::ServerError;
+
+// Notices sent to a user while the connection goes through login or registration
+pub const AUTH_LOOKING_UP: &str = "*** Looking up your connection...";
+pub const AUTH_CHECKING_LOGIN: &str = "*** Checking your nickname and password...";
+pub const AUTH_CHECKING_REGISTRATION: &str = "*** Checking your registration information...";
+
 pub struct ConnectionHandler {
     pub stream: TcpStream,
     pub sender_to_server: Sender<Message>,
@@ -36,6 +52,15 @@ pub struct ConnectionHandler {
     pub users: Arc<Mutex<HashMap<String, User>>>,
     pub channels: Arc<Mutex<HashMap<String, Channel>>>,
     pub server_name: String,
+    pub max_channels_per_join: usize,
+    pub motd_file_path: String,
+    pub password_policy: PasswordPolicy,
+    pub max_nickname_length: usize,
+    pub message_rate: MessageRate,
+    pub away_on_join: bool,
+    pub welcome: Welcome,
+    pub logger: Logger,
+    pub acknowledgements: Acknowledgements,
 }
 
 impl ConnectionHandler {
@@ -79,6 +104,13 @@ impl ConnectionHandler {
             client_name,
             user,
             reader,
+            max_channels_per_join: self.max_channels_per_join,
+            motd_file_path: self.motd_file_path.clone(),
+            keepalive: Keepalive::default(),
+            rate_limit: RateLimit::new(self.message_rate, Instant::now()),
+            away_on_join: self.away_on_join,
+            logger: self.logger.clone(),
+            acknowledgements: self.acknowledgements.clone(),
         };
 
         handler.handle_client()?;
@@ -112,6 +144,8 @@ impl ConnectionHandler {
             return Ok(None); // Return None because it's a server
         }
 
+        self.send_auth_notice(AUTH_LOOKING_UP)?;
+
         // Create user from data received
         // Handle new client and proceed with login or registration
         let mut user = None;
@@ -151,6 +185,7 @@ impl ConnectionHandler {
         ];
 
         let message = Message {
+            tags: None,
             prefix: Some(new_user.nickname),
             command: action,
             params,
@@ -219,6 +254,7 @@ impl ConnectionHandler {
     ///
     pub fn connect_to_main_server(&mut self) -> Result<(), ServerError> {
         let message = Message {
+            tags: None,
             prefix: None,
             command: SERVER.to_string(),
             params: vec![vec![self.server_name.clone()]],
@@ -243,6 +279,14 @@ impl ConnectionHandler {
             channels: self.channels.clone(),
             client_name: self.server_name.clone(),
             user: None,
+            max_channels_per_join: self.max_channels_per_join,
+            motd_file_path: self.motd_file_path.clone(),
+            keepalive: Keepalive::default(),
+            // Servers forward the messages of every user, they are not rate limited
+            rate_limit: RateLimit::default(),
+            away_on_join: false,
+            logger: self.logger.clone(),
+            acknowledgements: Acknowledgements::default(),
             reader: BufReader::new(self.stream.try_clone().map_err(|_| -> ServerError {
                 ServerError {
                     kind: CRITICAL.to_string(),
@@ -271,7 +315,7 @@ impl ConnectionHandler {
     /// If an error is found a Server error is returned.
     ///
     pub fn handle_login_registration(
-        &self,
+        &mut self,
         reader: &mut BufReader<TcpStream>,
         action: Option<String>,
     ) -> Result<Option<User>, ServerError> {
@@ -310,7 +354,12 @@ impl ConnectionHandler {
         println!("Nick message: {:?}", nick_message);
 
         let nickname;
-        match get_nickname(&nick_message, &action.clone().unwrap(), &self.users) {
+        match get_nickname(
+            &nick_message,
+            &action.clone().unwrap(),
+            self.max_nickname_length,
+            &self.users,
+        ) {
             Ok(result) => {
                 match result {
                     Ok(nick) => nickname = nick,
@@ -343,7 +392,7 @@ impl ConnectionHandler {
             return Ok(None);
         }
 
-        let user = User::new(
+        let mut user = User::new(
             nickname,
             user_info.1,
             user_info.0,
@@ -352,12 +401,20 @@ impl ConnectionHandler {
             password,
         );
 
-        if action.unwrap() == LOGIN {
+        let action = action.unwrap();
+        match action == LOGIN {
+            true => self.send_auth_notice(AUTH_CHECKING_LOGIN)?,
+            false => self.send_auth_notice(AUTH_CHECKING_REGISTRATION)?,
+        }
+
+        if action == LOGIN {
             println!("In login");
             match login_user(&self.users, user.clone(), &self.server_name) {
                 Ok(result) => match result {
                     Ok(reply) => {
                         self.send_reply(reply, &self.stream)?;
+                        user.nickname = self.registered_nickname(&user.nickname)?;
+                        self.send_welcome(&user.nickname)?;
                     }
                     Err(reply) => {
                         self.send_reply(reply, &self.stream)?;
@@ -368,9 +425,17 @@ impl ConnectionHandler {
             }
         } else {
             println!("In registration");
+            // Weak passwords are rejected before the user is created
+            if let Err(reason) = check_password_strength(&user.password, &self.password_policy) {
+                let reply =
+                    NumericReply::new(ERR_WEAKPASSWORD_NUM, &reason, Some(vec![user.nickname]));
+                self.send_reply(reply, &self.stream)?;
+                return Ok(None);
+            }
             match registrate_user(&self.users, user.clone()) {
                 Ok(reply) => {
                     self.send_reply(reply, &self.stream)?;
+                    self.send_welcome(&user.nickname)?;
                 }
                 Err(err) => return Err(err),
             }
@@ -379,36 +444,104 @@ impl ConnectionHandler {
     }
 
     ///
-    /// Read line from reader received, parses data and returns message.
+    /// Welcomes the user that logged in or registered with the information of the server
     ///
-    pub fn process_data(&self, reader: &mut BufReader<TcpStream>) -> Result<Message, ServerError> {
-        let mut data = String::new();
-
-        let received = match reader.read_line(&mut data) {
-            Ok(_) => data.as_mut(),
-            // Failed to read data received
-            Err(_) => {
-                return Err(ServerError {
-                    kind: CRITICAL.to_string(),
-                    message: "Could not read from stream".to_string(),
-                })
+    fn send_welcome(&self, nickname: &str) -> Result<(), ServerError> {
+        for reply in self.welcome.replies(nickname) {
+            self.send_reply(reply, &self.stream)?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Returns the nickname as it was registered, nicknames are compared without case
+    ///
+    fn registered_nickname(&self, nickname: &str) -> Result<String, ServerError> {
+        let users = self.users.lock().map_err(|_| -> ServerError {
+            ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "Could not lock users".to_string(),
             }
-        };
+        })?;
+        Ok(match users.get(&irc_lowercase(nickname)) {
+            Some(user) => user.nickname.clone(),
+            None => nickname.to_string(),
+        })
+    }
+
+    ///
+    /// Read line from reader received, parses data and returns message. Labeled messages are
+    /// acknowledged, and the ones received before are skipped until a new message arrives
+    ///
+    pub fn process_data(
+        &mut self,
+        reader: &mut BufReader<TcpStream>,
+    ) -> Result<Message, ServerError> {
+        loop {
+            let mut data = String::new();
+
+            let received = match read_line_lossy(reader, &mut Vec::new(), &mut data) {
+                Ok(_) => data.as_mut(),
+                // Failed to read data received
+                Err(_) => {
+                    return Err(ServerError {
+                        kind: CRITICAL.to_string(),
+                        message: "Could not read from stream".to_string(),
+                    })
+                }
+            };
 
-        println!("DATA RECEIVED {}", received);
+            println!("DATA RECEIVED {}", received);
 
-        let message = match parse(received.to_string()) {
-            Ok(message) => message,
-            // Failed to parsed data received
-            Err(_) => {
-                return Err(ServerError {
-                    kind: CRITICAL.to_string(),
-                    message: "Could not parse message".to_string(),
-                })
+            let message = match parse(received.to_string()) {
+                Ok(message) => message,
+                // Failed to parsed data received
+                Err(_) => {
+                    return Err(ServerError {
+                        kind: CRITICAL.to_string(),
+                        message: "Could not parse message".to_string(),
+                    })
+                }
+            };
+
+            match message.label() {
+                Some(label) => {
+                    (&self.stream)
+                        .write_all(acknowledgement(label).as_bytes())
+                        .map_err(|_| -> ServerError {
+                            ServerError {
+                                kind: CRITICAL.to_string(),
+                                message: "Could not write".to_string(),
+                            }
+                        })?;
+                    if self.acknowledgements.received(label) {
+                        return Ok(message);
+                    }
+                }
+                None => return Ok(message),
             }
-        };
+        }
+    }
 
-        Ok(message)
+    ///
+    /// Sends a NOTICE AUTH to the client, so it knows how its connection is progressing
+    ///
+    fn send_auth_notice(&self, text: &str) -> Result<(), ServerError> {
+        let notice = Message {
+            tags: None,
+            prefix: None,
+            command: NOTICE.to_string(),
+            params: vec![vec![NOTICE_AUTH.to_string()], vec![text.to_string()]],
+        };
+        (&self.stream)
+            .write_all(notice.as_string().as_bytes())
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: CRITICAL.to_string(),
+                    message: "Could not write".to_string(),
+                }
+            })?;
+        Ok(())
     }
 
     ///
@@ -0,0 +1,201 @@
+//!
+//! The connection limit protects the server from floods of connections. The server counts the
+//! connections open from every ip and closes the new ones of an ip that already has the amount
+//! allowed by the server data file. A connection stops being counted when its thread ends, that
+//! is when the client quits or the connection is lost
+//!
+
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    commands::ERROR,
+    custom_errors::{errors::CRITICAL, server_error::ServerError},
+    message::Message,
+};
+
+///
+/// How many connections an ip can have open at once. Without the line in the server data
+/// file there is no limit
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionLimit {
+    pub max_per_address: Option<usize>,
+    pub exempt_localhost: bool, //the connections from the machine of the server are never closed
+}
+
+impl Default for ConnectionLimit {
+    fn default() -> Self {
+        ConnectionLimit {
+            max_per_address: None,
+            exempt_localhost: true,
+        }
+    }
+}
+
+impl ConnectionLimit {
+    ///
+    /// Returns the connections the ip can have open, None if it has no limit
+    ///
+    fn max_connections(&self, ip: IpAddr) -> Option<usize> {
+        if self.exempt_localhost && ip.is_loopback() {
+            return None;
+        }
+        self.max_per_address
+    }
+}
+
+///
+/// Connections open from every ip. The clones share the count
+///
+#[derive(Debug, Clone, Default)]
+pub struct OpenConnections {
+    counts: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+impl OpenConnections {
+    ///
+    /// Counts a new connection from the ip. Returns None if the ip already has the connections
+    /// allowed, else the connection is counted until the OpenConnection returned is dropped
+    ///
+    pub fn open(
+        &self,
+        ip: IpAddr,
+        limit: ConnectionLimit,
+    ) -> Result<Option<OpenConnection>, ServerError> {
+        let mut counts = self.counts.lock().map_err(|_| -> ServerError {
+            ServerError {
+                kind: CRITICAL.to_string(),
+                message: "Couldn't lock open connections".to_string(),
+            }
+        })?;
+
+        let count = counts.entry(ip).or_insert(0);
+        if let Some(max_connections) = limit.max_connections(ip) {
+            if *count >= max_connections {
+                return Ok(None);
+            }
+        }
+        *count += 1;
+
+        Ok(Some(OpenConnection {
+            ip,
+            counts: self.counts.clone(),
+        }))
+    }
+
+    ///
+    /// Returns how many connections the ip has open
+    ///
+    pub fn count(&self, ip: IpAddr) -> usize {
+        match self.counts.lock() {
+            Ok(counts) => counts.get(&ip).copied().unwrap_or(0),
+            Err(_) => 0,
+        }
+    }
+}
+
+///
+/// A connection counted in the OpenConnections it was opened with
+///
+#[derive(Debug)]
+pub struct OpenConnection {
+    ip: IpAddr,
+    counts: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+impl Drop for OpenConnection {
+    fn drop(&mut self) {
+        if let Ok(mut counts) = self.counts.lock() {
+            if let Some(count) = counts.get_mut(&self.ip) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    counts.remove(&self.ip);
+                }
+            }
+        }
+    }
+}
+
+///
+/// Returns the ERROR sent before closing a connection of an ip that has too many open
+///
+pub fn too_many_connections_message(ip: IpAddr) -> Message {
+    Message {
+        tags: None,
+        prefix: None,
+        command: ERROR.to_string(),
+        params: vec![vec![format!(
+            "Closing link: too many connections from {}",
+            ip
+        )]],
+    }
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::{too_many_connections_message, ConnectionLimit, OpenConnections};
+
+    const REMOTE_IP: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 20));
+    const LOCAL_IP: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    #[test]
+    fn connections_beyond_the_limit_are_refused_until_one_is_closed() {
+        let limit = ConnectionLimit {
+            max_per_address: Some(2),
+            exempt_localhost: false,
+        };
+        let open_connections = OpenConnections::default();
+
+        let first = open_connections.open(REMOTE_IP, limit).unwrap();
+        let second = open_connections.open(REMOTE_IP, limit).unwrap();
+        assert!(first.is_some() && second.is_some());
+        assert!(open_connections.open(REMOTE_IP, limit).unwrap().is_none());
+        assert_eq!(open_connections.count(REMOTE_IP), 2);
+
+        // Other ips have their own count
+        assert!(open_connections.open(LOCAL_IP, limit).unwrap().is_some());
+
+        drop(first);
+        assert_eq!(open_connections.count(REMOTE_IP), 1);
+        assert!(open_connections.open(REMOTE_IP, limit).unwrap().is_some());
+
+        assert_eq!(
+            too_many_connections_message(REMOTE_IP).as_string(),
+            "ERROR :Closing link: too many connections from 192.168.0.20\r\n"
+        );
+    }
+
+    #[test]
+    fn localhost_can_be_exempted_and_without_limit_nothing_is_refused() {
+        let open_connections = OpenConnections::default();
+        let limit = ConnectionLimit {
+            max_per_address: Some(1),
+            exempt_localhost: true,
+        };
+        let local: Vec<_> = (0..3)
+            .map(|_| open_connections.open(LOCAL_IP, limit).unwrap())
+            .collect();
+        assert!(local.iter().all(Option::is_some));
+        assert_eq!(open_connections.count(LOCAL_IP), 3);
+        let remote = open_connections.open(REMOTE_IP, limit).unwrap();
+        assert!(remote.is_some());
+        assert!(open_connections.open(REMOTE_IP, limit).unwrap().is_none());
+
+        let unlimited: Vec<_> = (0..3)
+            .map(|_| {
+                open_connections
+                    .open(REMOTE_IP, ConnectionLimit::default())
+                    .unwrap()
+            })
+            .collect();
+        assert!(unlimited.iter().all(Option::is_some));
+    }
+}
@@ -6,24 +6,76 @@
 
 use super::{channel::Channel, user::User};
 use crate::{
-    commands::{LOGIN, REGISTRATION, SERVER},
+    commands::{ERROR, LOGIN, REGISTRATION, SERVER},
     custom_errors::{
         errors::{CRITICAL, NONCRITICAL},
         server_error::ServerError,
     },
     message::Message,
-    server_utils::connection_handler::ConnectionHandler,
+    parser::irc_lowercase,
+    server_utils::{
+        acknowledgements::Acknowledgements,
+        connection_handler::ConnectionHandler,
+        connection_limit::{
+            too_many_connections_message, ConnectionLimit, OpenConnection, OpenConnections,
+        },
+        logger::Logger,
+        rate_limit::MessageRate,
+        server_data::{PasswordPolicy, ServerData},
+        welcome::Welcome,
+    },
+    tls,
 };
+use rustls::ServerConfig;
 use std::{
     collections::HashMap,
-    net::{TcpListener, TcpStream},
+    io::{ErrorKind, Write},
+    net::{IpAddr, Shutdown, TcpListener, TcpStream},
     sync::{
         mpsc::{self, Receiver, Sender},
         Arc, Mutex,
     },
     thread::{self, JoinHandle},
+    time::Duration,
 };
 
+// Time a refused connection has to finish the TLS handshake to be told why it is closed
+const REFUSED_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+///
+/// Settings of the server data file given to every new connection. They are shared with the
+/// server so a REHASH changes them for the connections that come after it
+///
+#[derive(Debug, Clone)]
+pub struct ConnectionSettings {
+    pub max_channels_per_join: usize,
+    pub motd_file_path: String,
+    pub password_policy: PasswordPolicy,
+    pub max_nickname_length: usize,
+    pub message_rate: MessageRate,
+    pub away_on_join: bool,
+    pub connection_limit: ConnectionLimit,
+    pub welcome: Welcome,
+}
+
+impl ConnectionSettings {
+    ///
+    /// Takes the settings from the server data
+    ///
+    pub fn new(server_data: &ServerData) -> Self {
+        ConnectionSettings {
+            max_channels_per_join: server_data.max_channels_per_join,
+            motd_file_path: server_data.motd_file_path.clone(),
+            password_policy: server_data.password_policy.clone(),
+            max_nickname_length: server_data.max_nickname_length,
+            message_rate: server_data.message_rate,
+            away_on_join: server_data.away_on_join,
+            connection_limit: server_data.connection_limit,
+            welcome: Welcome::new(server_data),
+        }
+    }
+}
+
 pub struct ConnectionListener {
     pub address: String,
     pub users: Arc<Mutex<HashMap<String, User>>>,
@@ -34,20 +86,45 @@ pub struct ConnectionListener {
         Arc<Mutex<HashMap<String, (Option<JoinHandle<Result<(), ServerError>>>, Sender<Message>)>>>,
     pub server_name: String,
     pub sender_to_server: Sender<Message>,
+    pub settings: Arc<Mutex<ConnectionSettings>>,
+    pub open_connections: OpenConnections,
+    pub logger: Logger,
+    pub tls_config: Option<Arc<ServerConfig>>,
 }
 
 impl ConnectionListener {
     ///
-    /// Reads every new connection and handles it
+    /// Binds to the address of the server. If it is not possible the error says why,
+    /// for example when another server already uses the port
     ///
-    pub fn read_new_connections(&mut self) -> Result<(), ServerError> {
-        let listener = TcpListener::bind(self.address.clone()).map_err(|_| -> ServerError {
+    pub fn bind(&self) -> Result<TcpListener, ServerError> {
+        TcpListener::bind(&self.address).map_err(|error| -> ServerError {
+            let message = match error.kind() {
+                ErrorKind::AddrInUse => format!(
+                    "Address {} is already in use, another server may be running on that port",
+                    self.address
+                ),
+                ErrorKind::AddrNotAvailable => format!(
+                    "Address {} is not available, the ip does not belong to this machine",
+                    self.address
+                ),
+                ErrorKind::PermissionDenied => format!(
+                    "Not allowed to listen on {}, ports below 1024 need special permissions",
+                    self.address
+                ),
+                _ => format!("Couldn't bind to address {}: {}", self.address, error),
+            };
             ServerError {
                 kind: CRITICAL.to_string(),
-                message: "Couldnt bind to address".to_string(),
+                message,
             }
-        })?;
+        })
+    }
 
+    ///
+    /// Reads every new connection and handles it
+    ///
+    pub fn read_new_connections(&mut self, listener: TcpListener) -> Result<(), ServerError> {
         for stream in listener.incoming() {
             let stream = match stream {
                 Ok(stream) => stream,
@@ -61,14 +138,57 @@ impl ConnectionListener {
 
             println!("New connection received");
 
+            let settings_clone = self
+                .settings
+                .lock()
+                .map_err(|_| -> ServerError {
+                    ServerError {
+                        kind: CRITICAL.to_string(),
+                        message: "Couldn't lock connection settings".to_string(),
+                    }
+                })?
+                .clone();
+
+            // The connections of an ip with too many open are closed before reading from them
+            let ip = match stream.peer_addr() {
+                Ok(address) => address.ip(),
+                Err(_) => continue,
+            };
+            let open_connection = match self
+                .open_connections
+                .open(ip, settings_clone.connection_limit)?
+            {
+                Some(open_connection) => open_connection,
+                None => {
+                    self.refuse_connection(stream, ip);
+                    continue;
+                }
+            };
+
             let users_clone = self.users.clone();
             let users_clients_clone = self.user_clients.clone();
             let server_clients_clone = self.server_clients.clone();
             let channels_clone = self.channels.clone();
             let sender_to_server_clone = self.sender_to_server.clone();
             let server_name_clone = self.server_name.clone();
+            let logger_clone = self.logger.clone();
+            let tls_config_clone = self.tls_config.clone();
 
             let _ = thread::spawn(move || {
+                // With TLS nothing is read from the connection until the handshake is done
+                let stream = match tls_config_clone {
+                    Some(config) => {
+                        tls::accept(stream, config).map_err(|error| -> ServerError {
+                            println!("TLS handshake failed: {}", error);
+                            ServerError {
+                                kind: NONCRITICAL.to_string(),
+                                message: format!("TLS handshake failed: {}", error),
+                            }
+                        })?
+                    }
+                    None => stream,
+                };
+
                 match Self::handle_connection(
                     stream,
                     users_clone,
@@ -77,6 +197,9 @@ impl ConnectionListener {
                     sender_to_server_clone,
                     users_clients_clone,
                     server_clients_clone,
+                    settings_clone,
+                    open_connection,
+                    logger_clone,
                 ) {
                     Ok(_) => {
                         println!("New connection");
@@ -90,9 +213,42 @@ impl ConnectionListener {
         Ok(())
     }
 
+    ///
+    /// Sends the ERROR that tells the ip has too many connections open and closes the new one.
+    /// With TLS the ERROR is sent after the handshake, in another thread so a flood of
+    /// connections doesn't stop the listener
+    ///
+    fn refuse_connection(&self, stream: TcpStream, ip: IpAddr) {
+        self.logger.warn(
+            "",
+            ERROR,
+            &format!(
+                "Refused a connection from {}, it already has {} open",
+                ip,
+                self.open_connections.count(ip)
+            ),
+        );
+        let tls_config = self.tls_config.clone();
+        let _ = thread::spawn(move || {
+            let mut stream = match tls_config {
+                Some(config) => {
+                    let _ = stream.set_read_timeout(Some(REFUSED_HANDSHAKE_TIMEOUT));
+                    match tls::accept(stream, config) {
+                        Ok(stream) => stream,
+                        Err(_) => return,
+                    }
+                }
+                None => stream,
+            };
+            let _ = stream.write_all(too_many_connections_message(ip).as_string().as_bytes());
+            let _ = stream.shutdown(Shutdown::Both);
+        });
+    }
+
     ///
     /// Handles specific connection
     ///
+    #[allow(clippy::too_many_arguments)]
     fn handle_connection(
         stream: TcpStream,
         users: Arc<Mutex<HashMap<String, User>>>,
@@ -105,6 +261,9 @@ impl ConnectionListener {
         server_clients: Arc<
             Mutex<HashMap<String, (Option<JoinHandle<Result<(), ServerError>>>, Sender<Message>)>>,
         >,
+        settings: ConnectionSettings,
+        open_connection: OpenConnection,
+        logger: Logger,
     ) -> Result<(), ServerError> {
         println!("Handling connection in another thread");
         let (sender_read_new_connections, receiver_from_connection_hanlder): (
@@ -122,10 +281,21 @@ impl ConnectionListener {
             receiver: rx_user,
             channels,
             server_name,
+            max_channels_per_join: settings.max_channels_per_join,
+            motd_file_path: settings.motd_file_path,
+            password_policy: settings.password_policy,
+            max_nickname_length: settings.max_nickname_length,
+            message_rate: settings.message_rate,
+            away_on_join: settings.away_on_join,
+            welcome: settings.welcome,
+            logger,
+            acknowledgements: Acknowledgements::default(),
         };
 
         // Spawn new thread for new client
         let handle = thread::spawn(move || {
+            // The connection is counted until the client quits or the connection is lost
+            let _open_connection = open_connection;
             // connection succeeded
             match connection_handler.handle_client() {
                 Ok(_) => {
@@ -201,7 +371,10 @@ impl ConnectionListener {
                 message: "Couldnt lock users clients".to_string(),
             }
         })?;
-        users_clients.insert(nickname, (Some(handle), sender_to_client_handler));
+        users_clients.insert(
+            irc_lowercase(&nickname),
+            (Some(handle), sender_to_client_handler),
+        );
         Ok(())
     }
 
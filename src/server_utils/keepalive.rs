@@ -0,0 +1,127 @@
+//!
+//! The keepalive checks that a user is still connected. If the user sends nothing for a while
+//! the server sends a PING and, if nothing arrives before the timeout, the user is disconnected
+//!
+
+use std::time::{Duration, Instant};
+
+// Time without activity from the user after which a PING is sent
+pub const PING_INTERVAL: Duration = Duration::from_secs(60);
+// Time the user has to answer the PING before being disconnected
+pub const PING_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum KeepaliveAction {
+    Wait,
+    Ping,
+    Disconnect,
+}
+
+#[derive(Debug)]
+pub struct Keepalive {
+    interval: Duration,
+    timeout: Duration,
+    last_activity: Instant,
+    ping_sent_at: Option<Instant>,
+}
+
+impl Default for Keepalive {
+    fn default() -> Self {
+        Keepalive::new(PING_INTERVAL, PING_TIMEOUT)
+    }
+}
+
+impl Keepalive {
+    ///
+    /// Creates a new Keepalive that pings after interval without activity and waits timeout for an answer
+    ///
+    pub fn new(interval: Duration, timeout: Duration) -> Self {
+        Keepalive {
+            interval,
+            timeout,
+            last_activity: Instant::now(),
+            ping_sent_at: None,
+        }
+    }
+
+    ///
+    /// Records that something was received from the user, a PONG or any other message
+    ///
+    pub fn activity(&mut self, now: Instant) {
+        self.last_activity = now;
+        self.ping_sent_at = None;
+    }
+
+    ///
+    /// Returns what has to be done with the connection. Ping is returned once per interval
+    /// without activity and Disconnect once the PING was not answered in time
+    ///
+    pub fn check(&mut self, now: Instant) -> KeepaliveAction {
+        match self.ping_sent_at {
+            Some(ping_sent_at) if now.duration_since(ping_sent_at) >= self.timeout => {
+                KeepaliveAction::Disconnect
+            }
+            Some(_) => KeepaliveAction::Wait,
+            None if now.duration_since(self.last_activity) >= self.interval => {
+                self.ping_sent_at = Some(now);
+                KeepaliveAction::Ping
+            }
+            None => KeepaliveAction::Wait,
+        }
+    }
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::{Keepalive, KeepaliveAction};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn keepalive_pings_after_interval_and_disconnects_after_timeout() {
+        let mut keepalive = Keepalive::new(Duration::from_secs(60), Duration::from_secs(30));
+        let start = Instant::now();
+        keepalive.activity(start);
+
+        assert_eq!(
+            keepalive.check(start + Duration::from_secs(59)),
+            KeepaliveAction::Wait
+        );
+        assert_eq!(
+            keepalive.check(start + Duration::from_secs(60)),
+            KeepaliveAction::Ping
+        );
+        // The PING is sent only once while waiting for the answer
+        assert_eq!(
+            keepalive.check(start + Duration::from_secs(89)),
+            KeepaliveAction::Wait
+        );
+        assert_eq!(
+            keepalive.check(start + Duration::from_secs(90)),
+            KeepaliveAction::Disconnect
+        );
+    }
+
+    #[test]
+    fn keepalive_answered_ping_keeps_connection() {
+        let mut keepalive = Keepalive::new(Duration::from_secs(60), Duration::from_secs(30));
+        let start = Instant::now();
+        keepalive.activity(start);
+
+        assert_eq!(
+            keepalive.check(start + Duration::from_secs(60)),
+            KeepaliveAction::Ping
+        );
+        keepalive.activity(start + Duration::from_secs(70));
+
+        assert_eq!(
+            keepalive.check(start + Duration::from_secs(100)),
+            KeepaliveAction::Wait
+        );
+        assert_eq!(
+            keepalive.check(start + Duration::from_secs(130)),
+            KeepaliveAction::Ping
+        );
+    }
+}
@@ -0,0 +1,300 @@
+//!
+//! The logger writes what the server does to its log file, a line for each event with the time,
+//! the level, the nickname and the command that caused it. Events below the level chosen in the
+//! server data file are not written. Without a log file the lines are printed
+//!
+
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::custom_errors::{errors::CRITICAL, server_error::ServerError};
+
+// Written instead of the nickname or the command when the event has none
+const NO_FIELD: &str = "-";
+
+const SECONDS_PER_DAY: u64 = 86400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    ///
+    /// Returns the level with the name given, like the ones in the server data file
+    ///
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "info" => Some(LogLevel::Info),
+            "warn" => Some(LogLevel::Warn),
+            "error" => Some(LogLevel::Error),
+            _ => None,
+        }
+    }
+
+    ///
+    /// Returns the name written in the log lines
+    ///
+    pub fn name(&self) -> &str {
+        match self {
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+        }
+    }
+}
+
+///
+/// Writes the events of the server. It can be cloned to be used by every thread, all the clones
+/// write to the same file
+///
+#[derive(Debug, Clone)]
+pub struct Logger {
+    level: LogLevel,
+    file: Option<Arc<Mutex<File>>>,
+}
+
+impl Default for Logger {
+    ///
+    /// Prints every event
+    ///
+    fn default() -> Self {
+        Logger {
+            level: LogLevel::Info,
+            file: None,
+        }
+    }
+}
+
+impl Logger {
+    ///
+    /// Creates a logger that writes the events of the level given or higher. The lines are
+    /// added at the end of the file, which is created if it doesn't exist
+    ///
+    pub fn new(file_path: Option<&str>, level: LogLevel) -> Result<Self, ServerError> {
+        let file = match file_path {
+            Some(file_path) => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(file_path)
+                    .map_err(|error| -> ServerError {
+                        ServerError {
+                            kind: CRITICAL.to_string(),
+                            message: format!("Couldn't open log file {}: {}", file_path, error),
+                        }
+                    })?;
+                Some(Arc::new(Mutex::new(file)))
+            }
+            None => None,
+        };
+
+        Ok(Logger { level, file })
+    }
+
+    ///
+    /// Writes an event of level INFO
+    ///
+    pub fn info(&self, nickname: &str, command: &str, text: &str) {
+        self.log(LogLevel::Info, nickname, command, text);
+    }
+
+    ///
+    /// Writes an event of level WARN
+    ///
+    pub fn warn(&self, nickname: &str, command: &str, text: &str) {
+        self.log(LogLevel::Warn, nickname, command, text);
+    }
+
+    ///
+    /// Writes an event of level ERROR
+    ///
+    pub fn error(&self, nickname: &str, command: &str, text: &str) {
+        self.log(LogLevel::Error, nickname, command, text);
+    }
+
+    ///
+    /// Writes the event if its level is not silenced. A log that can't be written doesn't stop
+    /// the server, so errors are ignored
+    ///
+    fn log(&self, level: LogLevel, nickname: &str, command: &str, text: &str) {
+        if level < self.level {
+            return;
+        }
+
+        let line = log_line(SystemTime::now(), level, nickname, command, text);
+        match &self.file {
+            Some(file) => {
+                if let Ok(mut file) = file.lock() {
+                    let _ = writeln!(file, "{}", line);
+                }
+            }
+            None => println!("{}", line),
+        }
+    }
+}
+
+///
+/// Returns the line of an event, like "[2022-12-16 23:49:27] INFO ari PRIVMSG: Message received".
+/// The time is in UTC
+///
+pub fn log_line(
+    time: SystemTime,
+    level: LogLevel,
+    nickname: &str,
+    command: &str,
+    text: &str,
+) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let field = |field: &str| match field.is_empty() {
+        true => NO_FIELD.to_string(),
+        false => field.to_string(),
+    };
+
+    format!(
+        "[{}] {} {} {}: {}",
+        timestamp_text(seconds),
+        level.name(),
+        field(nickname),
+        field(command),
+        text
+    )
+}
+
+///
+/// Returns the date and time of the seconds since epoch given, as YYYY-MM-DD HH:MM:SS
+///
+pub fn timestamp_text(seconds: u64) -> String {
+    let (year, month, day) = civil_date(seconds / SECONDS_PER_DAY);
+    let seconds_of_day = seconds % SECONDS_PER_DAY;
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    )
+}
+
+///
+/// Returns the time given as the time tag of IRCv3 messages, in ISO 8601 with milliseconds and
+/// UTC, like 2022-12-16T23:49:27.000Z
+///
+pub fn server_time_text(time: SystemTime) -> String {
+    let duration = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = duration.as_secs();
+    let (year, month, day) = civil_date(seconds / SECONDS_PER_DAY);
+    let seconds_of_day = seconds % SECONDS_PER_DAY;
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60,
+        duration.subsec_millis()
+    )
+}
+
+///
+/// Returns the year, month and day of the days since epoch given. Years are counted in eras
+/// of 400 years starting on March, so the leap day is the last one of each year
+///
+fn civil_date(days: u64) -> (u64, u64, u64) {
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = match month_from_march < 10 {
+        true => month_from_march + 3,
+        false => month_from_march - 9,
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as u64;
+
+    (year, month, day)
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use super::{log_line, server_time_text, timestamp_text, LogLevel, Logger};
+
+    #[test]
+    fn timestamps_are_the_utc_date_and_time() {
+        assert_eq!(timestamp_text(0), "1970-01-01 00:00:00");
+        assert_eq!(timestamp_text(951782400), "2000-02-29 00:00:00");
+        assert_eq!(timestamp_text(1671234567), "2022-12-16 23:49:27");
+    }
+
+    #[test]
+    fn server_time_is_in_iso_8601_with_milliseconds() {
+        let time = UNIX_EPOCH + Duration::from_millis(1671234567089);
+        assert_eq!(server_time_text(time), "2022-12-16T23:49:27.089Z");
+        assert_eq!(server_time_text(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+    }
+
+    #[test]
+    fn log_line_has_time_level_nickname_and_command() {
+        let time = UNIX_EPOCH + Duration::from_secs(1671234567);
+        assert_eq!(
+            log_line(time, LogLevel::Warn, "ari", "PRIVMSG", "Sent too fast"),
+            "[2022-12-16 23:49:27] WARN ari PRIVMSG: Sent too fast"
+        );
+        assert_eq!(
+            log_line(time, LogLevel::Info, "", "", "Server running"),
+            "[2022-12-16 23:49:27] INFO - -: Server running"
+        );
+    }
+
+    #[test]
+    fn events_below_the_level_are_not_written() {
+        let path = std::env::temp_dir().join("logger_test.log");
+        let _ = fs::remove_file(&path);
+        let logger = Logger::new(path.to_str(), LogLevel::Warn).unwrap();
+
+        logger.info("ari", "JOIN", "Joined");
+        logger.clone().warn("ari", "PRIVMSG", "Sent too fast");
+        logger.error("", "", "Couldn't get lock");
+
+        let lines: Vec<String> = fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| line.split_once("] ").unwrap().1.to_string())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "WARN ari PRIVMSG: Sent too fast".to_string(),
+                "ERROR - -: Couldn't get lock".to_string()
+            ]
+        );
+        assert_eq!(LogLevel::from_name("ERROR"), Some(LogLevel::Error));
+        assert_eq!(LogLevel::from_name("debug"), None);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -57,6 +57,7 @@ impl MainServer {
                 vec![user.real_name.clone()],
             ];
             let message = Message {
+                tags: None,
                 prefix: Some(user.clone().nickname),
                 command: USERS_INFO.to_string(),
                 params,
@@ -119,6 +120,7 @@ impl ServerRol for MainServer {
             println!("Server already exists");
             sender
                 .send(Message {
+                    tags: None,
                     prefix: None,
                     command: SERVER.to_string(),
                     params: vec![vec![]],
@@ -213,6 +215,20 @@ impl ServerRol for MainServer {
         Ok(())
     }
 
+    ///
+    /// Returns the amount of secondary servers connected
+    ///
+    fn servers_count(&self) -> usize {
+        self.servers.len()
+    }
+
+    ///
+    /// Returns true if the secondary server whose name was received is connected
+    ///
+    fn is_server_connected(&self, server_name: &str) -> bool {
+        self.servers.contains_key(server_name)
+    }
+
     ///
     /// Sends a message to all the servers except the one whose name was received.
     ///
@@ -1,10 +1,12 @@
 use std::sync::mpsc::{Receiver, Sender};
 
 use crate::{
+    commands::{IS_OPERATOR, WALLOPS},
     custom_errors::{errors::CRITICAL, server_error::ServerError},
     numeric_reply::{
-        NumericReply, ERR_NOPRIVILEGES_MSG, ERR_NOPRIVILEGES_NUM, ERR_NOSUCHSERVER_MSG,
-        ERR_NOSUCHSERVER_NUM,
+        NumericReply, ERR_FILEERROR_MSG, ERR_FILEERROR_NUM, ERR_NEEDMOREPARAMS_MSG,
+        ERR_NEEDMOREPARAMS_NUM, ERR_NOPRIVILEGES_MSG, ERR_NOPRIVILEGES_NUM, ERR_NOSUCHSERVER_MSG,
+        ERR_NOSUCHSERVER_NUM, RPL_REHASHING_MSG, RPL_REHASHING_NUM,
     },
 };
 
@@ -45,23 +47,108 @@ pub fn handle_quit_server(
     Ok(None)
 }
 
+///
+/// Sends a WALLOPS or GLOBOPS to the server so that the text reaches every operator.
+/// Only the operator of the server can send it, the rest receive ERR_NOPRIVILEGES.
+/// Both are the same message, a GLOBOPS goes on as a WALLOPS
+///
+pub fn handle_wallops(
+    message: Message,
+    sender: &Sender<Message>,
+    receiver: &Receiver<Message>,
+) -> Result<Option<NumericReply>, ServerError> {
+    if message.params_total_count() == 0 {
+        let reply = NumericReply::new(ERR_NEEDMOREPARAMS_NUM, ERR_NEEDMOREPARAMS_MSG, None);
+        return Ok(Some(reply));
+    }
+
+    let mut request = message.clone();
+    request.command = IS_OPERATOR.to_string();
+    sender.send(request).map_err(|_| -> ServerError {
+        ServerError {
+            kind: CRITICAL.to_string(),
+            message: "Could not send message".to_string(),
+        }
+    })?;
+    let answer = receiver.recv().map_err(|_| -> ServerError {
+        ServerError {
+            kind: CRITICAL.to_string(),
+            message: "Could not receive message".to_string(),
+        }
+    })?;
+    if answer.params_total_count() == 0 || answer.params[0][0] != "You are an operator" {
+        let reply = NumericReply::new(ERR_NOPRIVILEGES_NUM, ERR_NOPRIVILEGES_MSG, None);
+        return Ok(Some(reply));
+    }
+
+    let mut message = message;
+    message.command = WALLOPS.to_string();
+    sender.send(message).map_err(|_| -> ServerError {
+        ServerError {
+            kind: CRITICAL.to_string(),
+            message: "Could not send message".to_string(),
+        }
+    })?;
+    Ok(None)
+}
+
+///
+/// Asks the server to read its server data file again. Only the operator of the server can
+/// do it, the rest receive ERR_NOPRIVILEGES. The user is answered with RPL_REHASHING, or
+/// ERR_FILEERROR if the file couldn't be read and the server kept its settings
+///
+pub fn handle_rehash(
+    message: Message,
+    sender: &Sender<Message>,
+    receiver: &Receiver<Message>,
+) -> Result<Option<NumericReply>, ServerError> {
+    sender.send(message).map_err(|_| -> ServerError {
+        ServerError {
+            kind: CRITICAL.to_string(),
+            message: "Could not send message".to_string(),
+        }
+    })?;
+    let answer = receiver.recv().map_err(|_| -> ServerError {
+        ServerError {
+            kind: CRITICAL.to_string(),
+            message: "Could not receive message".to_string(),
+        }
+    })?;
+
+    let file_path = answer.params.get(1).cloned().unwrap_or_default();
+    let reply = match answer.params.first().and_then(|text| text.first()) {
+        Some(text) if text == "Rehashing" => {
+            NumericReply::new(RPL_REHASHING_NUM, RPL_REHASHING_MSG, Some(file_path))
+        }
+        Some(text) if text == "Could not read the server data file" => {
+            NumericReply::new(ERR_FILEERROR_NUM, ERR_FILEERROR_MSG, Some(file_path))
+        }
+        _ => NumericReply::new(ERR_NOPRIVILEGES_NUM, ERR_NOPRIVILEGES_MSG, None),
+    };
+    Ok(Some(reply))
+}
+
 /**************************************TESTS**************************************/
 #[cfg(test)]
 mod tests {
-    use crate::commands::QUIT;
+    use crate::commands::{IS_OPERATOR, QUIT, REHASH, WALLOPS};
     use crate::custom_errors::errors::CRITICAL;
     use crate::custom_errors::server_error::ServerError;
     use crate::message::Message;
     use crate::numeric_reply::{
-        NumericReply, ERR_NOPRIVILEGES_MSG, ERR_NOPRIVILEGES_NUM, ERR_NOSUCHSERVER_MSG,
-        ERR_NOSUCHSERVER_NUM,
+        NumericReply, ERR_FILEERROR_MSG, ERR_FILEERROR_NUM, ERR_NEEDMOREPARAMS_MSG,
+        ERR_NEEDMOREPARAMS_NUM, ERR_NOPRIVILEGES_MSG, ERR_NOPRIVILEGES_NUM, ERR_NOSUCHSERVER_MSG,
+        ERR_NOSUCHSERVER_NUM, RPL_REHASHING_MSG, RPL_REHASHING_NUM,
+    };
+    use crate::server_utils::messages_processing_client::admin_server::{
+        handle_quit_server, handle_rehash, handle_wallops,
     };
-    use crate::server_utils::messages_processing_client::admin_server::handle_quit_server;
     use std::sync::mpsc;
     #[test]
     fn test_quit_non_existant_server() {
         let (sender, receiver) = mpsc::channel();
         let message = Message {
+            tags: None,
             command: QUIT.to_string(),
             params: vec![vec!["non_existant_server".to_string()]],
             prefix: None,
@@ -83,6 +170,7 @@ mod tests {
     fn test_quit_need_more_params() {
         let (sender, receiver) = mpsc::channel();
         let message = Message {
+            tags: None,
             command: QUIT.to_string(),
             params: vec![vec![]],
             prefix: None,
@@ -102,6 +190,7 @@ mod tests {
     fn test_not_oper() {
         let (sender, receiver) = mpsc::channel();
         let message = Message {
+            tags: None,
             command: QUIT.to_string(),
             params: vec![vec!["some_server".to_string()]],
             prefix: Some("not_oper".to_string()),
@@ -131,6 +220,7 @@ mod tests {
     fn test_squit_correct() {
         let (sender, receiver) = mpsc::channel();
         let message = Message {
+            tags: None,
             command: QUIT.to_string(),
             params: vec![vec!["some_server".to_string()]],
             prefix: Some("not_oper".to_string()),
@@ -150,4 +240,109 @@ mod tests {
         let reply = result.unwrap();
         assert!(reply.is_none());
     }
+
+    #[test]
+    fn test_wallops_need_more_params() {
+        let (sender, receiver) = mpsc::channel();
+        let message = Message {
+            tags: None,
+            command: WALLOPS.to_string(),
+            params: vec![],
+            prefix: Some("ari".to_string()),
+        };
+        let result = handle_wallops(message, &sender, &receiver);
+        assert_eq!(
+            result.unwrap(),
+            Some(NumericReply::new(
+                ERR_NEEDMOREPARAMS_NUM,
+                ERR_NEEDMOREPARAMS_MSG,
+                None
+            ))
+        );
+    }
+
+    #[test]
+    fn test_wallops_not_oper() {
+        let (sender, receiver) = mpsc::channel();
+        let message = Message {
+            tags: None,
+            command: WALLOPS.to_string(),
+            params: vec![vec!["Restarting".to_string()]],
+            prefix: Some("not_oper".to_string()),
+        };
+        let mut answer = message.clone();
+        answer.params = vec![];
+        sender.send(answer).unwrap();
+        let result = handle_wallops(message, &sender, &receiver);
+        assert_eq!(
+            result.unwrap(),
+            Some(NumericReply::new(
+                ERR_NOPRIVILEGES_NUM,
+                ERR_NOPRIVILEGES_MSG,
+                None
+            ))
+        );
+        // Only the operator check reached the server
+        assert_eq!(receiver.recv().unwrap().command, IS_OPERATOR);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_wallops_from_oper_is_sent_to_server() {
+        let (sender, receiver) = mpsc::channel();
+        let message = Message {
+            tags: None,
+            command: WALLOPS.to_string(),
+            params: vec![vec!["Restarting".to_string()]],
+            prefix: Some("ari".to_string()),
+        };
+        let mut answer = message.clone();
+        answer.params = vec![vec!["You are an operator".to_string()]];
+        sender.send(answer).unwrap();
+        let result = handle_wallops(message.clone(), &sender, &receiver);
+        assert_eq!(result.unwrap(), None);
+        assert_eq!(receiver.recv().unwrap().command, IS_OPERATOR);
+        assert_eq!(receiver.recv().unwrap(), message);
+    }
+
+    #[test]
+    fn test_rehash_answers() {
+        let file_path = "saved_files/server_data.txt".to_string();
+        for (answer_text, reply) in [
+            (
+                "Rehashing",
+                NumericReply::new(
+                    RPL_REHASHING_NUM,
+                    RPL_REHASHING_MSG,
+                    Some(vec![file_path.clone()]),
+                ),
+            ),
+            (
+                "Could not read the server data file",
+                NumericReply::new(
+                    ERR_FILEERROR_NUM,
+                    ERR_FILEERROR_MSG,
+                    Some(vec![file_path.clone()]),
+                ),
+            ),
+            (
+                "You are not an operator",
+                NumericReply::new(ERR_NOPRIVILEGES_NUM, ERR_NOPRIVILEGES_MSG, None),
+            ),
+        ] {
+            let (sender, receiver) = mpsc::channel();
+            let message = Message {
+                tags: None,
+                command: REHASH.to_string(),
+                params: vec![],
+                prefix: Some("ari".to_string()),
+            };
+            let mut answer = message.clone();
+            answer.params = vec![vec![answer_text.to_string()], vec![file_path.clone()]];
+            sender.send(answer).unwrap();
+            let result = handle_rehash(message.clone(), &sender, &receiver);
+            assert_eq!(result.unwrap(), Some(reply));
+            assert_eq!(receiver.recv().unwrap(), message);
+        }
+    }
 }
@@ -6,6 +6,8 @@
 
 use std::{
     collections::HashMap,
+    fs,
+    io::Write,
     net::TcpStream,
     result::Result,
     string::String,
@@ -23,17 +25,22 @@ use crate::{
     },
     message::Message,
     numeric_reply::{
-        NumericReply, ERR_ERRONEUSNICKNAME_MSG, ERR_ERRONEUSNICKNAME_NUM, ERR_INVALIDLOGIN_MSG,
-        ERR_INVALIDLOGIN_NUM, ERR_NEEDMOREPARAMS_MSG, ERR_NEEDMOREPARAMS_NUM,
-        ERR_NICKCOLLISION_MSG, ERR_NICKCOLLISION_NUM, ERR_NICKNAMEINUSE_MSG, ERR_NICKNAMEINUSE_NUM,
-        ERR_NONICKNAMEGIVEN_MSG, ERR_NONICKNAMEGIVEN_NUM, ERR_PASSWDMISMATCH_MSG,
-        ERR_PASSWDMISMATCH_NUM, RPL_CORRECTLOGIN_MSG, RPL_CORRECTLOGIN_NUM,
-        RPL_CORRECTREGISTRATION_MSG, RPL_CORRECTREGISTRATION_NUM, RPL_YOUREOPER_MSG,
-        RPL_YOUREOPER_NUM,
+        NumericReply, ERR_ERRONEUSNICKNAME_NUM, ERR_INVALIDLOGIN_MSG, ERR_INVALIDLOGIN_NUM,
+        ERR_NEEDMOREPARAMS_MSG, ERR_NEEDMOREPARAMS_NUM, ERR_NICKCOLLISION_MSG,
+        ERR_NICKCOLLISION_NUM, ERR_NICKNAMEINUSE_MSG, ERR_NICKNAMEINUSE_NUM, ERR_NOMOTD_MSG,
+        ERR_NOMOTD_NUM, ERR_NONICKNAMEGIVEN_MSG, ERR_NONICKNAMEGIVEN_NUM, ERR_NOOPERHOST_MSG,
+        ERR_NOOPERHOST_NUM, ERR_PASSWDMISMATCH_MSG, ERR_PASSWDMISMATCH_NUM, RPL_CORRECTLOGIN_MSG,
+        RPL_CORRECTLOGIN_NUM, RPL_CORRECTREGISTRATION_MSG, RPL_CORRECTREGISTRATION_NUM,
+        RPL_ENDOFMOTD_MSG, RPL_ENDOFMOTD_NUM, RPL_MOTDSTART_MSG, RPL_MOTDSTART_NUM, RPL_MOTD_NUM,
+        RPL_YOUREOPER_MSG, RPL_YOUREOPER_NUM,
     },
-    server_utils::user::User,
+    parser::irc_lowercase,
+    server_utils::{channel::Channel, server_data::PasswordPolicy, user::User},
 };
 
+// Characters allowed in nicknames besides letters, digits and '-', as in RFC 2812
+const NICKNAME_SPECIAL_CHARACTERS: &str = "[]\\`_^{|}";
+
 /********************************PASS MESSAGE*************************************/
 
 ///
@@ -69,9 +76,12 @@ pub fn get_password(message: &Message) -> Result<String, NumericReply> {
 /// ERR_ERRONEUSNICKNAME: nickname contains invalid characters.
 /// ERR_NICKCOLLISION: registered of a NICK that already exists by another server.
 ///
+/// Nicknames longer than the maximum length given can't be registered.
+///
 pub fn get_nickname(
     message: &Message,
     action: &String,
+    max_length: usize,
     users: &Arc<Mutex<HashMap<String, User>>>,
 ) -> Result<Result<String, NumericReply>, ServerError> {
     if message.command != *NICK {
@@ -91,7 +101,7 @@ pub fn get_nickname(
     let nickname = message.params[0][0].to_string();
 
     if action == REGISTRATION {
-        let result = check_registration_nick(&nickname, users)?;
+        let result = check_registration_nick(&nickname, max_length, users)?;
 
         if let Some(reply) = result {
             return Ok(Err(reply));
@@ -106,17 +116,18 @@ pub fn get_nickname(
 /// numeric reply:
 ///
 /// ERR_NICKCOLLISION: registered of a NICK that already exists by another server.
-/// ERR_ERRONEUSNICKNAME: nickname contains invalid characters.
+/// ERR_ERRONEUSNICKNAME: nickname is too long or has invalid characters, the message says why.
 ///  
 pub fn check_registration_nick(
     nickname: &String,
+    max_length: usize,
     users: &Arc<Mutex<HashMap<String, User>>>,
 ) -> Result<Option<NumericReply>, ServerError> {
     // Check if nickname is valid
-    if !nickname_is_valid(nickname) {
+    if let Err(reason) = check_nickname(nickname, max_length) {
         return Ok(Some(NumericReply::new(
             ERR_ERRONEUSNICKNAME_NUM,
-            ERR_ERRONEUSNICKNAME_MSG,
+            &reason,
             Some(vec![nickname.to_string()]),
         )));
     }
@@ -139,9 +150,12 @@ pub fn check_registration_nick(
 ///
 /// ERR_NICKNAMEINUSE: attempt to change to a currently existing nickname
 ///
+/// The channels registered by the user keep it as their founder with the new nickname.
+///
 pub fn change_nick(
     message: Message,
     users: &Arc<Mutex<HashMap<String, User>>>,
+    channels: &Arc<Mutex<HashMap<String, Channel>>>,
     user: &mut User,
 ) -> Result<Option<NumericReply>, ServerError> {
     if message.params_total_count() == 0 {
@@ -168,11 +182,22 @@ pub fn change_nick(
             message: "Couldn't get lock".to_string(),
         }
     })?;
-    let user_to_modify = match users.get_mut(&new_nickname) {
+    let user_to_modify = match users.get_mut(&irc_lowercase(&new_nickname)) {
         Some(user) => user,
         None => return Ok(None),
     };
     user_to_modify.nickname = new_nickname.clone();
+
+    // The users are still locked, they are always locked before the channels
+    let mut channels = channels.lock().map_err(|_| -> ServerError {
+        ServerError {
+            kind: NONCRITICAL.to_string(),
+            message: "Couldn't get lock".to_string(),
+        }
+    })?;
+    for channel in channels.values_mut() {
+        channel.founder_changed_nickname(&user.nickname, &new_nickname);
+    }
     user.nickname = new_nickname;
 
     Ok(None)
@@ -193,8 +218,8 @@ pub fn get_user_info(message: &Message) -> Result<(String, String, String, Strin
         panic!("user information needed");
     }
 
-    // if less than 4 params are given then ERR_NEEDMOREPARAMS
-    if message.params_total_count() < 4 {
+    // if the realname doesn't follow the other 3 params then ERR_NEEDMOREPARAMS
+    if !message.has_params(&[3, 1]) {
         return Err(NumericReply::new(
             ERR_NEEDMOREPARAMS_NUM,
             ERR_NEEDMOREPARAMS_MSG,
@@ -239,7 +264,7 @@ pub fn login_user(
     user: User,
     server_name: &String,
 ) -> Result<Result<NumericReply, NumericReply>, ServerError> {
-    let nickname = user.nickname.clone();
+    let mut nickname = user.nickname.clone();
     let password = user.password;
 
     if !check_login(&nickname, &password, users, server_name)? {
@@ -252,6 +277,18 @@ pub fn login_user(
     };
 
     println!("Login correct");
+    let mut users = users.lock().map_err(|_| -> ServerError {
+        ServerError {
+            kind: NONCRITICAL.to_string(),
+            message: "Could not lock users".to_string(),
+        }
+    })?;
+    if let Some(user) = users.get_mut(&irc_lowercase(&nickname)) {
+        user.sign_on();
+        // The nickname is answered as it was registered, whatever case was used to login
+        nickname = user.nickname.clone();
+    }
+
     Ok(Ok(NumericReply::new(
         RPL_CORRECTLOGIN_NUM,
         RPL_CORRECTLOGIN_MSG,
@@ -259,6 +296,31 @@ pub fn login_user(
     )))
 }
 
+///
+/// Checks that the password meets the policy given. If it doesn't, the reason is returned
+///
+pub fn check_password_strength(pass: &str, policy: &PasswordPolicy) -> Result<(), String> {
+    if pass.chars().count() < policy.min_length {
+        return Err(format!(
+            "Password must have at least {} characters",
+            policy.min_length
+        ));
+    }
+    if policy.require_letter && !pass.chars().any(|c| c.is_alphabetic()) {
+        return Err("Password must have a letter".to_string());
+    }
+    if policy.require_uppercase && !pass.chars().any(|c| c.is_uppercase()) {
+        return Err("Password must have an uppercase letter".to_string());
+    }
+    if policy.require_digit && !pass.chars().any(|c| c.is_ascii_digit()) {
+        return Err("Password must have a digit".to_string());
+    }
+    if policy.require_symbol && pass.chars().all(|c| c.is_alphanumeric()) {
+        return Err("Password must have a symbol".to_string());
+    }
+    Ok(())
+}
+
 ///
 /// Registrates user. Saves the user information in the server data file.
 /// Returns the following numeric reply:
@@ -267,7 +329,7 @@ pub fn login_user(
 ///
 pub fn registrate_user(
     users: &Arc<Mutex<HashMap<String, User>>>,
-    new_user: User,
+    mut new_user: User,
 ) -> Result<NumericReply, ServerError> {
     let nickname = new_user.nickname.clone();
 
@@ -279,7 +341,8 @@ pub fn registrate_user(
         }
     })?;
 
-    users.insert(nickname.clone(), new_user);
+    new_user.sign_on();
+    users.insert(irc_lowercase(&nickname), new_user);
 
     println!("Registration correct");
     Ok(NumericReply::new(
@@ -315,13 +378,76 @@ pub fn quit(
     Ok(None)
 }
 
+///
+/// Handles the answer of the user to a PING. The keepalive was already updated when the
+/// message was read, so there is nothing else to do
+///
+pub fn handle_pong() -> Result<Option<NumericReply>, ServerError> {
+    Ok(None)
+}
+
+/********************************MOTD MESSAGE*************************************/
+
+///
+/// Sends the message of the day of the server, RPL_MOTDSTART followed by a RPL_MOTD
+/// for each line of the MOTD file, and returns RPL_ENDOFMOTD. Could return the following
+/// numeric replies:
+///
+/// ERR_NOMOTD: the MOTD file could not be read.
+///
+pub fn motd(
+    mut stream: &TcpStream,
+    motd_file_path: &str,
+    server_name: &str,
+) -> Result<Option<NumericReply>, ServerError> {
+    let motd = match fs::read_to_string(motd_file_path) {
+        Ok(motd) => motd,
+        Err(_) => {
+            return Ok(Some(NumericReply::new(
+                ERR_NOMOTD_NUM,
+                ERR_NOMOTD_MSG,
+                None,
+            )))
+        }
+    };
+
+    //RPL_MOTDSTART
+    let mut replies = NumericReply::new(
+        RPL_MOTDSTART_NUM,
+        &format!("- {} {} -", server_name, RPL_MOTDSTART_MSG),
+        None,
+    )
+    .as_string();
+
+    //RPL_MOTD
+    for line in motd.lines() {
+        let reply = NumericReply::new(RPL_MOTD_NUM, &format!("- {}", line), None);
+        replies.push_str(&reply.as_string());
+    }
+
+    stream
+        .write_all(replies.as_bytes())
+        .map_err(|_| -> ServerError {
+            ServerError {
+                kind: CRITICAL.to_string(),
+                message: "Could not write on stream".to_string(),
+            }
+        })?;
+
+    Ok(Some(NumericReply::new(
+        RPL_ENDOFMOTD_NUM,
+        RPL_ENDOFMOTD_MSG,
+        None,
+    )))
+}
+
 /********************************AUX FUNCTIONS*************************************/
 
 ///
 /// Checks if there's nickname collision (another user with the nickname provided)
 ///
 fn check_nickname_collision(
-    nickname: &String,
+    nickname: &str,
     users: &Arc<Mutex<HashMap<String, User>>>,
 ) -> Result<bool, ServerError> {
     let users = users.lock().map_err(|_| -> ServerError {
@@ -331,14 +457,14 @@ fn check_nickname_collision(
         }
     })?;
 
-    Ok(users.get(nickname).is_some())
+    Ok(users.get(&irc_lowercase(nickname)).is_some())
 }
 
 ///
 /// Checks if there's a user in users with the password and nickname of the user suplied
 ///
 fn check_login(
-    nickname: &String,
+    nickname: &str,
     password: &String,
     users: &Arc<Mutex<HashMap<String, User>>>,
     server_name: &String,
@@ -352,7 +478,7 @@ fn check_login(
     println!("In check login, with servername: {}", server_name);
 
     // check if user and password are correct
-    match users.get(nickname) {
+    match users.get(&irc_lowercase(nickname)) {
         Some(user) => {
             if user.server_name != *server_name {
                 println!("Server names do not match");
@@ -368,19 +494,46 @@ fn check_login(
 }
 
 ///
-/// Checks that nickname doesnt contains invalid characters and
-/// has less than 9 characters
+/// Checks that the nickname follows RFC 2812: up to the maximum length given, made of letters,
+/// digits, '-' and the characters []\`_^{|}, and not starting with a digit or '-'.
+/// If it doesn't, the reason is returned
 ///
-fn nickname_is_valid(nickname: &String) -> bool {
-    if nickname.len() > 9 {
-        return false;
+pub fn check_nickname(nickname: &str, max_length: usize) -> Result<(), String> {
+    if nickname.chars().count() > max_length {
+        return Err(format!(
+            "Nickname must have at most {} characters",
+            max_length
+        ));
     }
+    if let Some(character) = nickname
+        .chars()
+        .find(|character| !is_nickname_character(*character))
+    {
+        return Err(format!("Nickname cannot have the character {}", character));
+    }
+    match nickname.chars().next() {
+        None => Err("Nickname cannot be empty".to_string()),
+        Some(first) if first.is_ascii_digit() => {
+            Err("Nickname cannot start with a digit".to_string())
+        }
+        Some('-') => Err("Nickname cannot start with -".to_string()),
+        Some(_) => Ok(()),
+    }
+}
 
-    !(nickname.starts_with('#') || nickname.starts_with('&') || nickname.starts_with(':'))
+fn is_nickname_character(character: char) -> bool {
+    character.is_ascii_alphanumeric()
+        || character == '-'
+        || NICKNAME_SPECIAL_CHARACTERS.contains(character)
 }
 
 ///
-/// Sets user as operator
+/// Sets user as operator if the name and password given are the ones of an operator in the
+/// operators file of the server. In case of error could return the following numeric replies:
+///
+/// ERR_NEEDMOREPARAMS: the name or the password are missing.
+/// ERR_PASSWDMISMATCH: the password is not the one of the operator.
+/// ERR_NOOPERHOST: the name is not in the operators file.
 ///
 pub fn set_operator(
     message: Message,
@@ -414,6 +567,13 @@ pub fn set_operator(
             None,
         )));
     }
+    if answer.params[0][0] == "Unknown operator" {
+        return Ok(Some(NumericReply::new(
+            ERR_NOOPERHOST_NUM,
+            ERR_NOOPERHOST_MSG,
+            None,
+        )));
+    }
 
     Ok(Some(NumericReply::new(
         RPL_YOUREOPER_NUM,
@@ -427,20 +587,26 @@ pub fn set_operator(
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
+    use std::fs;
+    use std::io::{BufRead, BufReader};
+    use std::net::{TcpListener, TcpStream};
     use std::sync::{mpsc, Arc, Mutex};
 
     use crate::commands::{LOGIN, NICK, OPER, PASS, REGISTRATION, USER};
     use crate::message::Message;
     use crate::numeric_reply::{
-        NumericReply, ERR_ERRONEUSNICKNAME_MSG, ERR_ERRONEUSNICKNAME_NUM, ERR_INVALIDLOGIN_MSG,
-        ERR_INVALIDLOGIN_NUM, ERR_NEEDMOREPARAMS_MSG, ERR_NEEDMOREPARAMS_NUM,
-        ERR_NICKCOLLISION_MSG, ERR_NICKCOLLISION_NUM, ERR_NONICKNAMEGIVEN_MSG,
-        ERR_NONICKNAMEGIVEN_NUM, ERR_PASSWDMISMATCH_MSG, ERR_PASSWDMISMATCH_NUM,
-        RPL_CORRECTLOGIN_MSG, RPL_CORRECTLOGIN_NUM, RPL_YOUREOPER_MSG, RPL_YOUREOPER_NUM,
+        NumericReply, ERR_ERRONEUSNICKNAME_NUM, ERR_INVALIDLOGIN_MSG, ERR_INVALIDLOGIN_NUM,
+        ERR_NEEDMOREPARAMS_MSG, ERR_NEEDMOREPARAMS_NUM, ERR_NICKCOLLISION_MSG,
+        ERR_NICKCOLLISION_NUM, ERR_NOMOTD_MSG, ERR_NOMOTD_NUM, ERR_NONICKNAMEGIVEN_MSG,
+        ERR_NONICKNAMEGIVEN_NUM, ERR_NOOPERHOST_MSG, ERR_NOOPERHOST_NUM, ERR_PASSWDMISMATCH_MSG,
+        ERR_PASSWDMISMATCH_NUM, RPL_CORRECTLOGIN_MSG, RPL_CORRECTLOGIN_NUM, RPL_ENDOFMOTD_MSG,
+        RPL_ENDOFMOTD_NUM, RPL_YOUREOPER_MSG, RPL_YOUREOPER_NUM,
     };
     use crate::server_utils::messages_processing_client::connection_and_registration::{
-        check_registration_nick, get_nickname, get_password, get_user_info, set_operator,
+        check_nickname, check_password_strength, check_registration_nick, get_nickname,
+        get_password, get_user_info, motd, set_operator,
     };
+    use crate::server_utils::server_data::{PasswordPolicy, DEFAULT_MAX_NICKNAME_LENGTH};
     use crate::server_utils::user::User;
 
     use super::login_user;
@@ -451,6 +617,7 @@ mod tests {
     fn get_password_returns_correct_password() {
         let password_expected = "password".to_string();
         let message = Message {
+            tags: None,
             prefix: None,
             command: PASS.to_string(),
             params: vec![vec![password_expected.clone()]],
@@ -467,6 +634,7 @@ mod tests {
     #[test]
     fn get_password_returns_whitout_password_return_corerct_numeric_reply() {
         let message = Message {
+            tags: None,
             prefix: None,
             command: PASS.to_string(),
             params: vec![],
@@ -489,12 +657,18 @@ mod tests {
     fn get_nickname_while_registrating_returns_correct_nickname() {
         let nickname_expected = "nickname".to_string();
         let message = Message {
+            tags: None,
             prefix: None,
             command: NICK.to_string(),
             params: vec![vec![nickname_expected.clone()]],
         };
         let users = Arc::new(Mutex::new(HashMap::new()));
-        let nickname = get_nickname(&message, &REGISTRATION.to_string(), &users);
+        let nickname = get_nickname(
+            &message,
+            &REGISTRATION.to_string(),
+            DEFAULT_MAX_NICKNAME_LENGTH,
+            &users,
+        );
 
         assert!(nickname.is_ok());
 
@@ -517,6 +691,7 @@ mod tests {
         );
 
         let message = Message {
+            tags: None,
             prefix: None,
             command: NICK.to_string(),
             params: vec![vec![nickname_expected.clone()]],
@@ -525,7 +700,12 @@ mod tests {
             nickname_expected.clone(),
             user,
         )])));
-        let nickname = get_nickname(&message, &LOGIN.to_string(), &users);
+        let nickname = get_nickname(
+            &message,
+            &LOGIN.to_string(),
+            DEFAULT_MAX_NICKNAME_LENGTH,
+            &users,
+        );
 
         assert!(nickname.is_ok());
 
@@ -537,12 +717,18 @@ mod tests {
     #[test]
     fn get_nickname_without_nickname_correct_numeric_reply() {
         let message = Message {
+            tags: None,
             prefix: None,
             command: NICK.to_string(),
             params: vec![],
         };
         let users = Arc::new(Mutex::new(HashMap::new()));
-        let reply = get_nickname(&message, &LOGIN.to_string(), &users);
+        let reply = get_nickname(
+            &message,
+            &LOGIN.to_string(),
+            DEFAULT_MAX_NICKNAME_LENGTH,
+            &users,
+        );
 
         assert!(!reply.is_err());
         let reply = reply.unwrap().err().unwrap();
@@ -567,6 +753,7 @@ mod tests {
         );
 
         let message = Message {
+            tags: None,
             prefix: None,
             command: NICK.to_string(),
             params: vec![vec![nickname_expected.clone()]],
@@ -575,7 +762,12 @@ mod tests {
             nickname_expected.clone(),
             user,
         )])));
-        let reply = get_nickname(&message, &REGISTRATION.to_string(), &users);
+        let reply = get_nickname(
+            &message,
+            &REGISTRATION.to_string(),
+            DEFAULT_MAX_NICKNAME_LENGTH,
+            &users,
+        );
 
         assert!(!reply.is_err());
 
@@ -585,7 +777,7 @@ mod tests {
             reply,
             NumericReply::new(
                 ERR_ERRONEUSNICKNAME_NUM,
-                ERR_ERRONEUSNICKNAME_MSG,
+                "Nickname cannot have the character #",
                 Some(vec![nickname_expected.clone()])
             )
         )
@@ -605,6 +797,7 @@ mod tests {
         );
 
         let message = Message {
+            tags: None,
             prefix: None,
             command: NICK.to_string(),
             params: vec![vec![nickname_expected.clone()]],
@@ -613,7 +806,12 @@ mod tests {
             nickname_expected.clone(),
             user,
         )])));
-        let reply = get_nickname(&message, &REGISTRATION.to_string(), &users);
+        let reply = get_nickname(
+            &message,
+            &REGISTRATION.to_string(),
+            DEFAULT_MAX_NICKNAME_LENGTH,
+            &users,
+        );
         assert!(!reply.is_err());
 
         let reply = reply.unwrap().err().unwrap();
@@ -638,6 +836,7 @@ mod tests {
             vec!["real_name".to_string()],
         ];
         let message = Message {
+            tags: None,
             prefix: None,
             command: USER.to_string(),
             params,
@@ -662,6 +861,7 @@ mod tests {
             vec!["real_name".to_string()],
         ];
         let message = Message {
+            tags: None,
             prefix: None,
             command: USER.to_string(),
             params,
@@ -678,6 +878,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn get_user_info_without_realname_returns_correct_numeric_reply() {
+        for params in [
+            vec![vec![
+                "username".to_string(),
+                "hostname".to_string(),
+                "server_name".to_string(),
+                "real_name".to_string(),
+            ]],
+            vec![],
+        ] {
+            let message = Message {
+                tags: None,
+                prefix: None,
+                command: USER.to_string(),
+                params,
+            };
+
+            assert_eq!(
+                get_user_info(&message),
+                Err(NumericReply::new(
+                    ERR_NEEDMOREPARAMS_NUM,
+                    ERR_NEEDMOREPARAMS_MSG,
+                    None
+                ))
+            );
+        }
+    }
+
     // Test login user
 
     #[test]
@@ -772,7 +1001,9 @@ mod tests {
             user_expected,
         )])));
 
-        let reply = check_registration_nick(&nickname_expected, &users).unwrap();
+        let reply =
+            check_registration_nick(&nickname_expected, DEFAULT_MAX_NICKNAME_LENGTH, &users)
+                .unwrap();
         assert_eq!(
             reply.unwrap(),
             NumericReply::new(ERR_NICKCOLLISION_NUM, ERR_NICKCOLLISION_MSG, None)
@@ -796,19 +1027,51 @@ mod tests {
             user_expected,
         )])));
 
-        let reply = check_registration_nick(&"#juani".to_string(), &users).unwrap();
+        let reply =
+            check_registration_nick(&"#juani".to_string(), DEFAULT_MAX_NICKNAME_LENGTH, &users)
+                .unwrap();
         assert_eq!(
             reply.unwrap(),
             NumericReply::new(
                 ERR_ERRONEUSNICKNAME_NUM,
-                ERR_ERRONEUSNICKNAME_MSG,
+                "Nickname cannot have the character #",
                 Some(vec!["#juani".to_string()])
             )
         );
     }
+
+    #[test]
+    fn check_nickname_follows_rfc_2812() {
+        assert!(check_nickname("juanireil", DEFAULT_MAX_NICKNAME_LENGTH).is_ok());
+        assert!(check_nickname("[ari]_^{|}", 10).is_ok());
+        assert!(check_nickname("a-1`\\", DEFAULT_MAX_NICKNAME_LENGTH).is_ok());
+
+        assert_eq!(
+            check_nickname("juanireil2", DEFAULT_MAX_NICKNAME_LENGTH),
+            Err("Nickname must have at most 9 characters".to_string())
+        );
+        assert!(check_nickname("juanireil2", 12).is_ok());
+        assert_eq!(
+            check_nickname("1ari", DEFAULT_MAX_NICKNAME_LENGTH),
+            Err("Nickname cannot start with a digit".to_string())
+        );
+        assert_eq!(
+            check_nickname("-ari", DEFAULT_MAX_NICKNAME_LENGTH),
+            Err("Nickname cannot start with -".to_string())
+        );
+        assert_eq!(
+            check_nickname("ari.g", DEFAULT_MAX_NICKNAME_LENGTH),
+            Err("Nickname cannot have the character .".to_string())
+        );
+        assert_eq!(
+            check_nickname("", DEFAULT_MAX_NICKNAME_LENGTH),
+            Err("Nickname cannot be empty".to_string())
+        );
+    }
     #[test]
     fn test_set_operator_need_more_params() {
         let message = Message {
+            tags: None,
             prefix: None,
             command: OPER.to_string(),
             params: vec![vec![]],
@@ -827,6 +1090,7 @@ mod tests {
         let nickname_expected = "nickname".to_string();
 
         let message = Message {
+            tags: None,
             prefix: None,
             command: OPER.to_string(),
             params: vec![
@@ -848,6 +1112,7 @@ mod tests {
         let nickname_expected = "nickname".to_string();
 
         let message = Message {
+            tags: None,
             prefix: None,
             command: OPER.to_string(),
             params: vec![
@@ -866,4 +1131,117 @@ mod tests {
             NumericReply::new(ERR_PASSWDMISMATCH_NUM, ERR_PASSWDMISMATCH_MSG, None)
         );
     }
+
+    #[test]
+    fn test_set_operator_unknown_name() {
+        let message = Message {
+            tags: None,
+            prefix: None,
+            command: OPER.to_string(),
+            params: vec![vec!["unknown".to_string()], vec!["password".to_string()]],
+        };
+        let (sender1, _receiver1) = mpsc::channel();
+        let (sender2, receiver2) = mpsc::channel();
+        let mut answer = message.clone();
+        answer.params = vec![vec!["Unknown operator".to_string()]];
+        sender2.send(answer).unwrap();
+        let reply = set_operator(message, &sender1, &receiver2).unwrap();
+        assert_eq!(
+            reply.unwrap(),
+            NumericReply::new(ERR_NOOPERHOST_NUM, ERR_NOOPERHOST_MSG, None)
+        );
+    }
+
+    // Tests motd message
+
+    #[test]
+    fn motd_sends_every_line_of_the_motd_file() {
+        let path = std::env::temp_dir().join("irc_motd_test.txt");
+        let path = path.to_string_lossy().to_string();
+        fs::write(&path, "Welcome!\nBe nice").unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:5005").unwrap();
+        let stream = TcpStream::connect("127.0.0.1:5005").unwrap();
+        let (received_stream, _) = listener.accept().unwrap();
+
+        let reply = motd(&stream, &path, "test_server").unwrap();
+        drop(stream);
+
+        assert_eq!(
+            reply,
+            Some(NumericReply::new(
+                RPL_ENDOFMOTD_NUM,
+                RPL_ENDOFMOTD_MSG,
+                None
+            ))
+        );
+
+        let lines: Vec<String> = BufReader::new(received_stream)
+            .lines()
+            .map(|line| line.unwrap())
+            .collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "375 :- test_server Message of the day -",
+                "372 :- Welcome!",
+                "372 :- Be nice"
+            ]
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn motd_without_motd_file_returns_err_nomotd() {
+        let listener = TcpListener::bind("127.0.0.1:5006").unwrap();
+        let stream = TcpStream::connect("127.0.0.1:5006").unwrap();
+        let (_received_stream, _) = listener.accept().unwrap();
+
+        let reply = motd(&stream, "saved_files/missing_motd.txt", "test_server").unwrap();
+
+        assert_eq!(
+            reply,
+            Some(NumericReply::new(ERR_NOMOTD_NUM, ERR_NOMOTD_MSG, None))
+        );
+    }
+
+    // Tests password strength
+
+    #[test]
+    fn check_password_strength_rejects_too_short_password() {
+        let result = check_password_strength("abc123", &PasswordPolicy::default());
+
+        assert_eq!(
+            result,
+            Err("Password must have at least 8 characters".to_string())
+        );
+    }
+
+    #[test]
+    fn check_password_strength_rejects_password_missing_a_class() {
+        let result = check_password_strength("onlyletters", &PasswordPolicy::default());
+        assert_eq!(result, Err("Password must have a digit".to_string()));
+
+        let policy = PasswordPolicy {
+            min_length: 4,
+            require_letter: false,
+            require_uppercase: true,
+            require_digit: false,
+            require_symbol: true,
+        };
+        let result = check_password_strength("Password", &policy);
+        assert_eq!(result, Err("Password must have a symbol".to_string()));
+        let result = check_password_strength("pass!", &policy);
+        assert_eq!(
+            result,
+            Err("Password must have an uppercase letter".to_string())
+        );
+    }
+
+    #[test]
+    fn check_password_strength_accepts_strong_password() {
+        assert!(check_password_strength("password123", &PasswordPolicy::default()).is_ok());
+    }
 }
@@ -7,28 +7,36 @@ use std::{
     collections::HashMap,
     io::Write,
     net::TcpStream,
-    sync::{mpsc::Sender, Arc, Mutex},
+    sync::{
+        mpsc::{Receiver, Sender},
+        Arc, Mutex,
+    },
+    time::{Instant, SystemTime, UNIX_EPOCH},
 };
 
 use crate::custom_errors::server_error::ServerError;
 use crate::message::Message;
 use crate::numeric_reply::*;
+use crate::parser::{irc_eq, irc_lowercase};
 use crate::server_utils::{channel::Channel, user::User};
 use crate::{
     commands::{
-        JOIN, MODE_GIVE_OP_PRIVILEGES, MODE_REMOVE_BAN, MODE_REMOVE_INVITE, MODE_REMOVE_KEY,
-        MODE_REMOVE_LIMIT, MODE_REMOVE_OP_TOPIC, MODE_REMOVE_SECRET, MODE_SET_BAN, MODE_SET_INVITE,
-        MODE_SET_KEY, MODE_SET_LIMIT, MODE_SET_OP_TOPIC, MODE_SET_SECRET, MODE_TAKE_OP_PRIVILEGES,
-        PART,
+        IS_OPERATOR, JOIN, MODE_GIVE_OP_PRIVILEGES, MODE_GIVE_VOICE, MODE_REMOVE_BAN,
+        MODE_REMOVE_EXCEPTION, MODE_REMOVE_FLOOD, MODE_REMOVE_INVITE, MODE_REMOVE_KEY,
+        MODE_REMOVE_LIMIT, MODE_REMOVE_MESSAGE_LENGTH, MODE_REMOVE_MODERATED,
+        MODE_REMOVE_NO_EXTERNAL, MODE_REMOVE_OP_TOPIC, MODE_REMOVE_SECRET, MODE_SET_BAN,
+        MODE_SET_EXCEPTION, MODE_SET_FLOOD, MODE_SET_INVITE, MODE_SET_KEY, MODE_SET_LIMIT,
+        MODE_SET_MESSAGE_LENGTH, MODE_SET_MODERATED, MODE_SET_NO_EXTERNAL, MODE_SET_OP_TOPIC,
+        MODE_SET_SECRET, MODE_TAKE_OP_PRIVILEGES, MODE_TAKE_VOICE, NOTICE, PART, SAVE_CHANNELS,
     },
     custom_errors::errors::{CRITICAL, NONCRITICAL},
     numeric_reply::{
         NumericReply, ERR_NEEDMOREPARAMS_MSG, ERR_NEEDMOREPARAMS_NUM, ERR_NOSUCHCHANNEL_MSG,
         ERR_NOSUCHCHANNEL_NUM, ERR_NOSUCHNICK_MSG, ERR_NOSUCHNICK_NUM, ERR_NOTONCHANNEL_MSG,
-        ERR_NOTONCHANNEL_NUM, ERR_UNKNOWNMODE_MSG, ERR_UNKNOWNMODE_NUM, RPL_ENDOFNAMES_MSG,
-        RPL_ENDOFNAMES_NUM, RPL_INVITING_NUM, RPL_LISTEND_MSG, RPL_LISTEND_NUM, RPL_LISTSTART_MSG,
-        RPL_LISTSTART_NUM, RPL_LIST_NUM, RPL_NAMEREPLY_NUM, RPL_NOTOPIC_MSG, RPL_NOTOPIC_NUM,
-        RPL_TOPIC_NUM,
+        ERR_NOTONCHANNEL_NUM, ERR_TOOMANYTARGETS_MSG, ERR_TOOMANYTARGETS_NUM, ERR_UNKNOWNMODE_MSG,
+        ERR_UNKNOWNMODE_NUM, RPL_ENDOFNAMES_MSG, RPL_ENDOFNAMES_NUM, RPL_INVITING_NUM,
+        RPL_LISTEND_MSG, RPL_LISTEND_NUM, RPL_LISTSTART_MSG, RPL_LISTSTART_NUM, RPL_LIST_NUM,
+        RPL_NAMEREPLY_NUM, RPL_NOTOPIC_MSG, RPL_NOTOPIC_NUM, RPL_TOPIC_NUM,
     },
 };
 
@@ -43,7 +51,12 @@ use crate::{
 /// ERR_BADCHANNELKEY: user trying to join with incorrect key.
 /// ERR_CHANNELISFULL: channel has a limit of participants and reached it.               
 /// ERR_TOOMANYCHANNELS: user already joined 10 channels, cant join another one.
+/// ERR_TOOMANYTARGETS: the channel exceeds the amount of channels allowed in one JOIN.
+///
+/// With away_on_join the user gets RPL_AWAY for each member of the channel that is away.
+/// Only the first max_channels_per_join channels are processed, the server data file sets it.
 ///
+#[allow(clippy::too_many_arguments)]
 pub fn join_channel(
     mut stream: &TcpStream,
     message: Message,
@@ -51,18 +64,24 @@ pub fn join_channel(
     users: &Arc<Mutex<HashMap<String, User>>>,
     user: &User,
     sender: &Sender<Message>,
+    max_channels_per_join: usize,
+    away_on_join: bool,
 ) -> Result<Option<NumericReply>, ServerError> {
     println!("In Join a channel! Message: {:?}", message);
 
     let mut passwords: Vec<String> = vec![];
-    let channels_names = message.params[0].clone();
+    let mut channels_names = message.params[0].clone();
     let mut binding = users.lock().map_err(|_| -> ServerError {
         ServerError {
             kind: NONCRITICAL.to_string(),
             message: "Could not lock users".to_string(),
         }
     })?;
-    let user = match binding.get_mut(&user.nickname) {
+    let users_away = match away_on_join {
+        true => away_messages(&binding),
+        false => HashMap::new(),
+    };
+    let user = match binding.get_mut(&irc_lowercase(&user.nickname)) {
         Some(u) => u,
         None => {
             return Err(ServerError {
@@ -78,10 +97,12 @@ pub fn join_channel(
             None,
         )));
     }
-    if message.params_total_count() > 1 {
+    if message.params.len() > 1 {
         passwords = message.params[1].clone();
     }
 
+    // The channels stay locked until the user joined all of them, so the checks of the channel,
+    // like its limit, and the insertion of the user can't be interleaved with another join
     let mut channels = channels.lock().map_err(|_| -> ServerError {
         ServerError {
             kind: NONCRITICAL.to_string(),
@@ -89,8 +110,17 @@ pub fn join_channel(
         }
     })?;
 
+    // Channels only known by this server are saved by the server when created
+    let mut channel_created = false;
+
+    // Only the first channels are processed, the rest are rejected
+    let excess_channels = match channels_names.len() > max_channels_per_join {
+        true => channels_names.split_off(max_channels_per_join),
+        false => vec![],
+    };
+
     for (i, channel_name) in channels_names.into_iter().enumerate() {
-        match channels.get_mut(&channel_name) {
+        match channels.get_mut(&irc_lowercase(&channel_name)) {
             //Check if the channel exists
             Some(channel) => {
                 // If exists joins
@@ -101,12 +131,24 @@ pub fn join_channel(
                     password = Some(passwords[i].clone())
                 }
 
+                let already_on_channel = channel.is_user_on_channel(&user.nickname);
                 let reply = channel.join(user.clone(), password)?;
+                if reply.has_number(vec![RPL_TOPIC_NUM, RPL_NOTOPIC_NUM]) {
+                    user.add_channel(&channel_name);
+                }
+
+                if !already_on_channel
+                    && reply.has_number(vec![RPL_TOPIC_NUM, RPL_NOTOPIC_NUM])
+                    && channel.record_join(Instant::now())
+                {
+                    notify_flood_protection(channel, sender, "users joined", "it is invite only")?;
+                }
 
                 if channel.is_multiserver()
                     && reply.has_number(vec![RPL_TOPIC_NUM, RPL_NOTOPIC_NUM])
                 {
                     let new_user_message = Message {
+                        tags: None,
                         prefix: Some(user.nickname.clone()),
                         command: JOIN.to_string(),
                         params: vec![vec![channel_name.clone()]],
@@ -128,6 +170,15 @@ pub fn join_channel(
                             message: "Could not send to server".to_string(),
                         }
                     })?;
+                if reply.has_number(vec![RPL_TOPIC_NUM, RPL_NOTOPIC_NUM]) {
+                    send_topic_details(channel, stream)?;
+                }
+
+                // Users that just joined get the last messages, so they know what is being talked about
+                if !already_on_channel && reply.has_number(vec![RPL_TOPIC_NUM, RPL_NOTOPIC_NUM]) {
+                    send_backlog(channel, stream)?;
+                    send_members_away(channel, &users_away, &user.nickname, stream)?;
+                }
             }
             None => {
                 // If it doesn't exist create one
@@ -136,13 +187,18 @@ pub fn join_channel(
                 let channel = Channel::new(channel_name.clone(), &user.clone());
 
                 // If channel is multiserver notify server soit notifies
-                // all server a new channel was created
+                // all server a new channel was created. The creation time tells them it was
+                // created here, so it can be merged if another server created it too
                 if channel.is_multiserver() {
                     println!("Channel is multiserver notify server");
                     let message_new_channel = Message {
+                        tags: None,
                         prefix: Some(user.nickname.clone()),
                         command: JOIN.to_string(),
-                        params: vec![vec![channel_name.clone()]],
+                        params: vec![
+                            vec![channel_name.clone()],
+                            vec![channel.created_at.to_string()],
+                        ],
                     };
 
                     sender
@@ -153,10 +209,10 @@ pub fn join_channel(
                                 message: "Could not send to server".to_string(),
                             }
                         })?;
+                } else {
+                    channel_created = true;
                 }
 
-                channels.insert(channel_name.to_string(), channel);
-
                 //channels.insert(channel_name.clone(), channel);
                 let reply = NumericReply::new(
                     RPL_NOTOPIC_NUM,
@@ -172,19 +228,167 @@ pub fn join_channel(
                             message: "Could not write in stream".to_string(),
                         }
                     })?;
+                send_topic_details(&channel, stream)?;
+
+                channels.insert(irc_lowercase(&channel_name), channel);
+                user.add_channel(&channel_name);
             }
         }
-        user.add_channel(&channel_name);
+    }
+
+    for channel_name in excess_channels {
+        let reply = NumericReply::new(
+            ERR_TOOMANYTARGETS_NUM,
+            ERR_TOOMANYTARGETS_MSG,
+            Some(vec![channel_name]),
+        );
+        stream
+            .write_all(reply.as_string().as_bytes())
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: CRITICAL.to_string(),
+                    message: "Could not write in stream".to_string(),
+                }
+            })?;
+    }
+
+    if channel_created {
+        request_channels_save(sender)?;
     }
     Ok(None)
 }
 
+///
+/// Joins the user that just logged in to the channels it was part of before. For each of them
+/// the topic, the users and the last messages are sent, like when joining, so the client opens
+/// its chats again. Channels that no longer exist, or that the user is not part of anymore,
+/// are forgotten.
+///
+pub fn rejoin_channels(
+    mut stream: &TcpStream,
+    channels: &Arc<Mutex<HashMap<String, Channel>>>,
+    users: &Arc<Mutex<HashMap<String, User>>>,
+    nickname: &str,
+) -> Result<(), ServerError> {
+    let mut users = users.lock().map_err(|_| -> ServerError {
+        ServerError {
+            kind: NONCRITICAL.to_string(),
+            message: "Could not lock users".to_string(),
+        }
+    })?;
+    let user = match users.get_mut(&irc_lowercase(nickname)) {
+        Some(user) => user,
+        None => return Ok(()),
+    };
+    let channels = channels.lock().map_err(|_| -> ServerError {
+        ServerError {
+            kind: NONCRITICAL.to_string(),
+            message: "Could not lock channels".to_string(),
+        }
+    })?;
+
+    let mut channels_names: Vec<String> = user.channels.iter().cloned().collect();
+    channels_names.sort();
+    for channel_name in channels_names {
+        let channel = match channels.get(&irc_lowercase(&channel_name)) {
+            Some(channel) if channel.is_user_on_channel(&user.nickname) => channel,
+            _ => {
+                user.remove_channel(&channel_name);
+                continue;
+            }
+        };
+
+        let mut replies = channel.get_topic_reply().as_string();
+        for reply in channel.get_topic_details_replies() {
+            replies.push_str(&reply.as_string());
+        }
+        replies.push_str(&names_reply(channel, &user.nickname).as_string());
+        replies
+            .push_str(&NumericReply::new(RPL_ENDOFNAMES_NUM, RPL_ENDOFNAMES_MSG, None).as_string());
+        stream
+            .write_all(replies.as_bytes())
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: CRITICAL.to_string(),
+                    message: "Could not write on stream".to_string(),
+                }
+            })?;
+        send_backlog(channel, stream)?;
+    }
+
+    Ok(())
+}
+
 /********************************LIST MESSAGE*************************************/
 
+///
+/// Amount of users a channel must have to be listed, as in the LIST filters ">3" (more than
+/// 3 users) and "<10" (less than 10 users)
+///
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct UsersCountFilter {
+    more_than: Option<usize>,
+    less_than: Option<usize>,
+}
+
+impl UsersCountFilter {
+    ///
+    /// Adds the filter given, returns false if it is not a users count filter
+    ///
+    fn add(&mut self, filter: &str) -> bool {
+        let (bound, count) = match (filter.get(..1), filter.get(1..)) {
+            (Some(bound), Some(count)) => (bound, count),
+            _ => return false,
+        };
+        let count = match count.parse::<usize>() {
+            Ok(count) => count,
+            Err(_) => return false,
+        };
+        match bound {
+            ">" => self.more_than = Some(count),
+            "<" => self.less_than = Some(count),
+            _ => return false,
+        }
+        true
+    }
+
+    ///
+    /// Returns true if a channel with the amount of users given must be listed
+    ///
+    pub fn matches(&self, users_count: usize) -> bool {
+        let has_more = match self.more_than {
+            Some(count) => users_count > count,
+            None => true,
+        };
+        let has_less = match self.less_than {
+            Some(count) => users_count < count,
+            None => true,
+        };
+        has_more && has_less
+    }
+}
+
+///
+/// Separates the parameters of a LIST message in the names of the channels asked for
+/// and the users count filters
+///
+pub fn parse_list_params(message: &Message) -> (Vec<String>, UsersCountFilter) {
+    let mut filter = UsersCountFilter::default();
+    let channel_names = message
+        .params
+        .iter()
+        .flatten()
+        .filter(|param| !filter.add(param))
+        .cloned()
+        .collect();
+    (channel_names, filter)
+}
+
 ///
 /// This function is called when a user sends a LIST message to the server
 /// with a specific name or list of names. It will return a list of the servers
-/// that match with a name received
+/// that match with a name received. Filters like ">3" or "<10" only list the channels
+/// with more or less users than the amount given
 ///
 pub fn list_channels(
     message: Message,
@@ -208,12 +412,13 @@ pub fn list_channels(
             }
         })?;
 
-    if message.params_total_count() == 0 {
+    let (channel_names, filter) = parse_list_params(&message);
+    if channel_names.is_empty() {
         // Return all channels
-        list_all_channels(channels, stream)?;
+        list_all_channels(channels, &filter, stream)?;
     } else {
         // Return channels received in message
-        list_some_channels(message, channels, stream)?;
+        list_some_channels(channel_names, &filter, channels, stream)?;
     }
 
     //RPL_LISTEND
@@ -232,10 +437,12 @@ pub fn list_channels(
 
 ///
 /// This function is called to list all channels known by the server if it is
-/// possible
+/// possible. Channels are listed from the most popular to the least popular,
+/// channels with the same amount of users are listed by name.
 ///
 pub fn list_all_channels(
     channels: &Arc<Mutex<HashMap<String, Channel>>>,
+    filter: &UsersCountFilter,
     mut stream: &TcpStream,
 ) -> Result<(), ServerError> {
     println!("Return all channels");
@@ -247,8 +454,16 @@ pub fn list_all_channels(
         }
     })?;
 
-    for channel in channels.values() {
-        if channel.is_secret() {
+    let mut channels_to_list: Vec<&Channel> = channels.values().collect();
+    channels_to_list.sort_by(|a, b| {
+        b.users
+            .len()
+            .cmp(&a.users.len())
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    for channel in channels_to_list {
+        if channel.is_secret() || !filter.matches(channel.users.len()) {
             continue;
         }
 
@@ -277,11 +492,11 @@ pub fn list_all_channels(
 /// LISTS channels received in message
 ///
 pub fn list_some_channels(
-    message: Message,
+    channels_to_show: Vec<String>,
+    filter: &UsersCountFilter,
     channels: &Arc<Mutex<HashMap<String, Channel>>>,
     mut stream: &TcpStream,
 ) -> Result<(), ServerError> {
-    let channels_to_show = message.params[0].clone();
     let channels_existing = channels.lock().map_err(|_| -> ServerError {
         ServerError {
             kind: NONCRITICAL.to_string(),
@@ -290,8 +505,8 @@ pub fn list_some_channels(
     })?;
 
     for channel in channels_to_show.into_iter() {
-        if let Some(channel_act) = channels_existing.get(&channel) {
-            if channel_act.is_secret() {
+        if let Some(channel_act) = channels_existing.get(&irc_lowercase(&channel)) {
+            if channel_act.is_secret() || !filter.matches(channel_act.users.len()) {
                 continue;
             }
 
@@ -348,11 +563,12 @@ pub fn part_channel(
     let mut empty_channels = vec![];
 
     for channel in channels_to_leave.into_iter() {
-        match channels.get_mut(&channel) {
+        match channels.get_mut(&irc_lowercase(&channel)) {
             Some(channel_act) => {
                 let reply = channel_act.part(user.clone());
 
-                if channel_act.is_empty() {
+                // Registered channels are kept, their founder can join them again
+                if channel_act.is_empty() && !channel_act.is_registered() {
                     empty_channels.push(channel.clone());
                 }
 
@@ -367,6 +583,7 @@ pub fn part_channel(
                         })?;
                 } else if channel_act.is_multiserver() {
                     let part_message = Message {
+                        tags: None,
                         prefix: Some(user.nickname.clone()),
                         command: PART.to_string(),
                         params: vec![vec![channel_act.name.clone()]],
@@ -394,8 +611,13 @@ pub fn part_channel(
         };
     }
 
+    let channels_deleted = !empty_channels.is_empty();
     for channel in empty_channels {
-        channels.remove(&channel);
+        channels.remove(&irc_lowercase(&channel));
+    }
+
+    if channels_deleted {
+        request_channels_save(sender)?;
     }
 
     Ok(None)
@@ -428,8 +650,10 @@ pub fn part_channel(
 ///
 /// RPL_UMODEIS:
 /// RPL_BANLIST:
-/// RPL_CHANNELMODEIS:
+/// RPL_CHANNELMODEIS: modes of the channel, when no mode is given. DONE
 /// RPL_ENDOFBANLIST:
+/// RPL_EXCEPTLIST:
+/// RPL_ENDOFEXCEPTLIST:
 ///
 pub fn set_channel_mode(
     message: Message,
@@ -439,6 +663,11 @@ pub fn set_channel_mode(
     stream: &TcpStream,
 ) -> Result<Option<NumericReply>, ServerError> {
     println!("Set channel mode function");
+    // Without a mode the user is asking for the modes of the channel
+    if message.params_total_count() == 1 {
+        return query_channel_modes(&message.params[0][0], channels, &user.nickname);
+    }
+
     // Check if channel and mode were given
     if message.params_total_count() < 2 {
         return Ok(Some(NumericReply::new(
@@ -459,7 +688,7 @@ pub fn set_channel_mode(
         }
     })?;
 
-    return match channels.get_mut(channel_name) {
+    return match channels.get_mut(&irc_lowercase(channel_name)) {
         Some(channel) => Ok(handle_mode(
             channel,
             message,
@@ -475,6 +704,33 @@ pub fn set_channel_mode(
     };
 }
 
+///
+/// Returns RPL_CHANNELMODEIS with the modes of the channel, nothing is changed.
+///
+/// ERR_NOSUCHCHANNEL: non existing channel name provided.
+///
+fn query_channel_modes(
+    channel_name: &str,
+    channels: &Arc<Mutex<HashMap<String, Channel>>>,
+    nickname: &str,
+) -> Result<Option<NumericReply>, ServerError> {
+    let channels = channels.lock().map_err(|_| -> ServerError {
+        ServerError {
+            kind: NONCRITICAL.to_string(),
+            message: "Could not access channels".to_string(),
+        }
+    })?;
+
+    Ok(Some(match channels.get(&irc_lowercase(channel_name)) {
+        Some(channel) => channel.get_modes_reply(nickname),
+        None => NumericReply::new(
+            ERR_NOSUCHCHANNEL_NUM,
+            ERR_NOSUCHCHANNEL_MSG,
+            Some(vec![channel_name.to_string()]),
+        ),
+    }))
+}
+
 ///
 /// This function is used to handle the mode command.
 ///
@@ -496,6 +752,17 @@ fn handle_mode(
 
     let mode = &message.params[1][0].clone();
     println!("Process mode {}", mode);
+
+    // Setting a ban without masks lists the bans of the channel
+    if mode == MODE_SET_BAN && message.params_total_count() == 2 {
+        return Ok(Some(send_ban_list(channel, stream)?));
+    }
+
+    // Same for the exceptions
+    if mode == MODE_SET_EXCEPTION && message.params_total_count() == 2 {
+        return Ok(Some(send_exception_list(channel, stream)?));
+    }
+
     let message_clone = message.clone();
 
     let result = match mode.to_string().as_str() {
@@ -517,6 +784,20 @@ fn handle_mode(
         MODE_REMOVE_SECRET => channel.remove_secret_status(nickname_user_setting_mode),
         MODE_SET_BAN => channel.set_ban(message, nickname_user_setting_mode),
         MODE_REMOVE_BAN => channel.remove_ban(message, nickname_user_setting_mode),
+        MODE_SET_EXCEPTION => channel.set_exception(message, nickname_user_setting_mode),
+        MODE_REMOVE_EXCEPTION => channel.remove_exception(message, nickname_user_setting_mode),
+        MODE_SET_FLOOD => channel.set_flood_protection(message, nickname_user_setting_mode),
+        MODE_REMOVE_FLOOD => channel.remove_flood_protection(nickname_user_setting_mode),
+        MODE_SET_MESSAGE_LENGTH => {
+            channel.set_max_message_length(message, nickname_user_setting_mode)
+        }
+        MODE_REMOVE_MESSAGE_LENGTH => channel.remove_max_message_length(nickname_user_setting_mode),
+        MODE_SET_NO_EXTERNAL => channel.set_no_external_messages(nickname_user_setting_mode),
+        MODE_REMOVE_NO_EXTERNAL => channel.remove_no_external_messages(nickname_user_setting_mode),
+        MODE_SET_MODERATED => channel.set_moderated(nickname_user_setting_mode),
+        MODE_REMOVE_MODERATED => channel.remove_moderated(nickname_user_setting_mode),
+        MODE_GIVE_VOICE => channel.give_voice(message, nickname_user_setting_mode),
+        MODE_TAKE_VOICE => channel.remove_voice(message, nickname_user_setting_mode),
         &_ => Err(NumericReply::new(
             ERR_UNKNOWNMODE_NUM,
             ERR_UNKNOWNMODE_MSG,
@@ -533,6 +814,8 @@ fn handle_mode(
                         message: "Could not send to server".to_string(),
                     }
                 })?;
+            } else {
+                request_channels_save(sender)?;
             }
             stream
                 .write_all(
@@ -557,6 +840,187 @@ fn handle_mode(
     }
 }
 
+///
+/// Writes the replies that follow RPL_TOPIC or RPL_NOTOPIC, with the time the channel was
+/// created and who set the topic
+///
+fn send_topic_details(channel: &Channel, mut stream: &TcpStream) -> Result<(), ServerError> {
+    for reply in channel.get_topic_details_replies() {
+        stream
+            .write_all(reply.as_string().as_bytes())
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: CRITICAL.to_string(),
+                    message: "Could not write on stream".to_string(),
+                }
+            })?;
+    }
+
+    Ok(())
+}
+
+///
+/// Writes the messages of the backlog of the channel, oldest first
+///
+fn send_backlog(channel: &Channel, mut stream: &TcpStream) -> Result<(), ServerError> {
+    for message in channel.backlog_messages() {
+        stream
+            .write_all(message.as_string().as_bytes())
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: CRITICAL.to_string(),
+                    message: "Could not write on stream".to_string(),
+                }
+            })?;
+    }
+
+    Ok(())
+}
+
+///
+/// Returns the nickname and the away message of the users away, by their nickname in lowercase
+///
+fn away_messages(users: &HashMap<String, User>) -> HashMap<String, (String, String)> {
+    users
+        .iter()
+        .filter_map(|(key, user)| {
+            user.away_message
+                .as_ref()
+                .map(|away_message| (key.clone(), (user.nickname.clone(), away_message.clone())))
+        })
+        .collect()
+}
+
+///
+/// Writes RPL_AWAY for each member of the channel that is away, but the user that joined
+///
+fn send_members_away(
+    channel: &Channel,
+    users_away: &HashMap<String, (String, String)>,
+    nickname: &str,
+    mut stream: &TcpStream,
+) -> Result<(), ServerError> {
+    let mut members: Vec<&String> = channel.users.keys().collect();
+    members.sort();
+    for member in members {
+        match users_away.get(member) {
+            Some((away_nickname, away_message)) if !irc_eq(away_nickname, nickname) => {
+                let reply = NumericReply::new(
+                    RPL_AWAY_NUM,
+                    away_message,
+                    Some(vec![away_nickname.clone()]),
+                );
+                stream
+                    .write_all(reply.as_string().as_bytes())
+                    .map_err(|_| -> ServerError {
+                        ServerError {
+                            kind: CRITICAL.to_string(),
+                            message: "Could not write on stream".to_string(),
+                        }
+                    })?;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+///
+/// Writes RPL_BANLIST for each ban of the channel and returns RPL_ENDOFBANLIST
+///
+fn send_ban_list(channel: &Channel, stream: &TcpStream) -> Result<NumericReply, ServerError> {
+    send_masks(channel, channel.get_ban_list(), RPL_BANLIST_NUM, stream)?;
+
+    Ok(NumericReply::new(
+        RPL_ENDOFBANLIST_NUM,
+        RPL_ENDOFBANLIST_MSG,
+        Some(vec![channel.name.clone()]),
+    ))
+}
+
+///
+/// Writes RPL_EXCEPTLIST for each ban exception of the channel and returns RPL_ENDOFEXCEPTLIST
+///
+fn send_exception_list(channel: &Channel, stream: &TcpStream) -> Result<NumericReply, ServerError> {
+    send_masks(
+        channel,
+        channel.get_exception_list(),
+        RPL_EXCEPTLIST_NUM,
+        stream,
+    )?;
+
+    Ok(NumericReply::new(
+        RPL_ENDOFEXCEPTLIST_NUM,
+        RPL_ENDOFEXCEPTLIST_MSG,
+        Some(vec![channel.name.clone()]),
+    ))
+}
+
+///
+/// Writes a reply with the number given for each mask
+///
+fn send_masks(
+    channel: &Channel,
+    masks: Vec<String>,
+    number: &str,
+    mut stream: &TcpStream,
+) -> Result<(), ServerError> {
+    for mask in masks {
+        let reply = NumericReply::new(number, "", Some(vec![channel.name.clone(), mask]));
+        stream
+            .write_all(reply.as_string().as_bytes())
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: CRITICAL.to_string(),
+                    message: "Could not write on stream".to_string(),
+                }
+            })?;
+    }
+
+    Ok(())
+}
+
+///
+/// Sends a notice to the operators in the channel telling them that the flood protection was turned on,
+/// with the flood detected and what the channel does until the cooldown ends
+///
+pub fn notify_flood_protection(
+    channel: &Channel,
+    sender: &Sender<Message>,
+    flood: &str,
+    protection: &str,
+) -> Result<(), ServerError> {
+    let cooldown = match &channel.flood_protection {
+        Some(flood_protection) => flood_protection.cooldown.as_secs(),
+        None => return Ok(()),
+    };
+    let text = format!(
+        "Too many {} {}, {} for {} seconds",
+        flood, channel.name, protection, cooldown
+    );
+
+    for operator in &channel.operators {
+        if !channel.is_user_on_channel(operator) {
+            continue;
+        }
+        let notice = Message {
+            tags: None,
+            prefix: None,
+            command: NOTICE.to_string(),
+            params: vec![vec![operator.clone()], vec![text.clone()]],
+        };
+        sender.send(notice).map_err(|_| -> ServerError {
+            ServerError {
+                kind: CRITICAL.to_string(),
+                message: "Could not send to server".to_string(),
+            }
+        })?;
+    }
+
+    Ok(())
+}
+
 /********************************INVITE MESSAGE*************************************/
 
 ///
@@ -589,22 +1053,23 @@ pub fn invite_to_channel(
 
     let nick_user_to_invite = message.params[0][0].clone();
     let channel_name = &message.params[1][0];
-    let mut channels = channels.lock().map_err(|_| -> ServerError {
+    // Users are locked before channels, like in JOIN, so they can't wait for each other
+    let users = users.lock().map_err(|_| -> ServerError {
         ServerError {
             kind: NONCRITICAL.to_string(),
-            message: "Could not access channels".to_string(),
+            message: "Could not lock users".to_string(),
         }
     })?;
-    let users = users.lock().map_err(|_| -> ServerError {
+    let mut channels = channels.lock().map_err(|_| -> ServerError {
         ServerError {
             kind: NONCRITICAL.to_string(),
-            message: "Could not lock users".to_string(),
+            message: "Could not access channels".to_string(),
         }
     })?;
 
     println!("Invite user {}", nick_user_to_invite);
 
-    if users.get(&nick_user_to_invite).is_none() {
+    if users.get(&irc_lowercase(&nick_user_to_invite)).is_none() {
         return Ok(Some(NumericReply::new(
             ERR_NOSUCHNICK_NUM,
             ERR_NOSUCHNICK_MSG,
@@ -612,7 +1077,7 @@ pub fn invite_to_channel(
         )));
     }
 
-    return match channels.get_mut(channel_name) {
+    return match channels.get_mut(&irc_lowercase(channel_name)) {
         Some(channel) => {
             // Channel found, try to invite user
 
@@ -651,73 +1116,205 @@ pub fn invite_to_channel(
     };
 }
 
-/********************************NAMES MESSAGE*************************************/
+/********************************KNOCK MESSAGE*************************************/
 
 ///
-/// This function will receive a message with NAMES command and will
-/// notify with RPL_NAMREPLY every user in the channel specified in message.
-/// When all users where listed it will send RPL_ENDOFNAMES.
-/// If no channel is specified it will list all users in all channels.
+/// Asks the operators of an invite only channel for an invitation, with the text of the
+/// message if one was given. If the knock is passed onto the server to notify the operators
+/// returns RPL_KNOCKDLVR. In cases of error could return the following numeric replies:
 ///
-pub fn names(
+/// ERR_NEEDMOREPARAMS: channel name not suplied.
+/// ERR_NOSUCHCHANNEL: channel does not exist.
+/// ERR_KNOCKONCHAN: user knocking is already on channel.
+/// ERR_CHANOPEN: channel is not invite only, so the user can join it.
+///
+pub fn knock_channel(
+    sender: &Sender<Message>,
     message: Message,
-    mut stream: &TcpStream,
-    channels: Arc<Mutex<HashMap<String, Channel>>>,
+    channels: &Arc<Mutex<HashMap<String, Channel>>>,
+    user: &User,
 ) -> Result<Option<NumericReply>, ServerError> {
-    println!("Names!, with message: {:?}", message);
-    let user_nickname = message.clone().prefix.expect("No prefix in message");
+    let channel_name = match message.params.first().and_then(|param| param.first()) {
+        Some(channel_name) => channel_name.clone(),
+        None => {
+            return Ok(Some(NumericReply::new(
+                ERR_NEEDMOREPARAMS_NUM,
+                ERR_NEEDMOREPARAMS_MSG,
+                None,
+            )))
+        }
+    };
     let channels = channels.lock().map_err(|_| -> ServerError {
         ServerError {
             kind: NONCRITICAL.to_string(),
             message: "Could not access channels".to_string(),
         }
     })?;
-    if message.params_total_count() < 1 {
-        for channel in channels.values() {
-            let users = &channel.users;
-            let users = users.keys().cloned().collect::<Vec<String>>();
-            let message_to_send = users.join(" ");
-            let answer = NumericReply::new(
-                RPL_NAMEREPLY_NUM,
-                &message_to_send,
-                Some(vec![user_nickname.clone(), channel.name.clone()]),
-            );
-            stream
-                .write_all(answer.as_string().as_bytes())
-                .map_err(|_| -> ServerError {
-                    ServerError {
-                        kind: CRITICAL.to_string(),
-                        message: "Could not write to stream".to_string(),
-                    }
-                })?;
+
+    let channel = match channels.get(&irc_lowercase(&channel_name)) {
+        Some(channel) => channel,
+        None => {
+            return Ok(Some(NumericReply::new(
+                ERR_NOSUCHCHANNEL_NUM,
+                ERR_NOSUCHCHANNEL_MSG,
+                Some(vec![channel_name]),
+            )))
         }
-    } else {
-        let channels_to_send = message.params[0].clone();
-        for channel in channels_to_send {
-            let channel = match channels.get(&channel) {
-                Some(channel) => channel,
-                None => continue,
-            };
-            let users = &channel.users;
-            let users = users.keys().cloned().collect::<Vec<String>>();
-            let message = users.join(" ");
-            let answer = NumericReply::new(
-                RPL_NAMEREPLY_NUM,
-                &message,
-                Some(vec![user_nickname.clone(), channel.name.clone()]),
-            );
-            stream
-                .write_all(answer.as_string().as_bytes())
-                .map_err(|_| -> ServerError {
-                    ServerError {
-                        kind: CRITICAL.to_string(),
-                        message: "Could not write to stream".to_string(),
-                    }
-                })?;
+    };
+    if channel.is_user_on_channel(&user.nickname) {
+        return Ok(Some(NumericReply::new(
+            ERR_KNOCKONCHAN_NUM,
+            ERR_KNOCKONCHAN_MSG,
+            Some(vec![channel.name.clone()]),
+        )));
+    }
+    if !channel.is_invite_only(Instant::now()) {
+        return Ok(Some(NumericReply::new(
+            ERR_CHANOPEN_NUM,
+            ERR_CHANOPEN_MSG,
+            Some(vec![channel.name.clone()]),
+        )));
+    }
+
+    // Notify server to send the knock to the operators
+    sender
+        .send(message.set_prefix(user.nickname.clone()))
+        .map_err(|_| -> ServerError {
+            ServerError {
+                kind: CRITICAL.to_string(),
+                message: "Could not send to server".to_string(),
+            }
+        })?;
+
+    Ok(Some(NumericReply::new(
+        RPL_KNOCKDLVR_NUM,
+        RPL_KNOCKDLVR_MSG,
+        Some(vec![channel.name.clone()]),
+    )))
+}
+
+/********************************REGISTER MESSAGE*************************************/
+
+///
+/// Registers the channel with the operator that sent the message as its founder, so the
+/// channel is kept when it is left empty and across restarts. If the channel was registered
+/// returns RPL_CHANNELREGISTERED. In cases of error could return the following numeric replies:
+///
+/// ERR_NEEDMOREPARAMS: channel name not suplied.
+/// ERR_NOSUCHCHANNEL: channel does not exist.
+/// ERR_NOTONCHANNEL: user registering is not on channel.
+/// ERR_CHANOPRIVSNEEDED: non operator trying to register.
+///
+pub fn register_channel(
+    sender: &Sender<Message>,
+    message: Message,
+    channels: &Arc<Mutex<HashMap<String, Channel>>>,
+    user: &User,
+) -> Result<Option<NumericReply>, ServerError> {
+    let channel_name = match message.params.first().and_then(|param| param.first()) {
+        Some(channel_name) => channel_name.clone(),
+        None => {
+            return Ok(Some(NumericReply::new(
+                ERR_NEEDMOREPARAMS_NUM,
+                ERR_NEEDMOREPARAMS_MSG,
+                None,
+            )))
+        }
+    };
+    let mut channels = channels.lock().map_err(|_| -> ServerError {
+        ServerError {
+            kind: NONCRITICAL.to_string(),
+            message: "Could not access channels".to_string(),
         }
+    })?;
+
+    let channel = match channels.get_mut(&irc_lowercase(&channel_name)) {
+        Some(channel) => channel,
+        None => {
+            return Ok(Some(NumericReply::new(
+                ERR_NOSUCHCHANNEL_NUM,
+                ERR_NOSUCHCHANNEL_MSG,
+                Some(vec![channel_name]),
+            )))
+        }
+    };
+    if let Err(reply) = channel.register(user.nickname.clone()) {
+        return Ok(Some(reply));
+    }
+
+    // The server saves the channel and tells the other servers who the founder is
+    if channel.is_multiserver() {
+        sender
+            .send(message.set_prefix(user.nickname.clone()))
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: CRITICAL.to_string(),
+                    message: "Could not send to server".to_string(),
+                }
+            })?;
+    } else {
+        request_channels_save(sender)?;
     }
 
-    let answer = NumericReply::new(RPL_ENDOFNAMES_NUM, RPL_ENDOFNAMES_MSG, None);
+    Ok(Some(NumericReply::new(
+        RPL_CHANNELREGISTERED_NUM,
+        RPL_CHANNELREGISTERED_MSG,
+        Some(vec![channel.name.clone()]),
+    )))
+}
+
+/********************************NAMES MESSAGE*************************************/
+
+///
+/// This function will receive a message with NAMES command and will
+/// notify with RPL_NAMREPLY every user in the channel specified in message.
+/// When all users where listed it will send RPL_ENDOFNAMES, with the name of the channel
+/// if only one was asked, so the client knows which one was not found.
+/// If no channel is specified it will list all users in all channels.
+/// Secret channels are only listed to their users
+///
+pub fn names(
+    message: Message,
+    mut stream: &TcpStream,
+    channels: Arc<Mutex<HashMap<String, Channel>>>,
+) -> Result<Option<NumericReply>, ServerError> {
+    println!("Names!, with message: {:?}", message);
+    let user_nickname = message.clone().prefix.expect("No prefix in message");
+    let channels = channels.lock().map_err(|_| -> ServerError {
+        ServerError {
+            kind: NONCRITICAL.to_string(),
+            message: "Could not access channels".to_string(),
+        }
+    })?;
+    let channels_to_send: Vec<&Channel> = if message.params_total_count() < 1 {
+        channels.values().collect()
+    } else {
+        message.params[0]
+            .iter()
+            .filter_map(|channel| channels.get(&irc_lowercase(channel)))
+            .collect()
+    };
+
+    for channel in channels_to_send {
+        if channel.is_secret() && !channel.is_user_on_channel(&user_nickname) {
+            continue;
+        }
+        let answer = names_reply(channel, &user_nickname);
+        stream
+            .write_all(answer.as_string().as_bytes())
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: CRITICAL.to_string(),
+                    message: "Could not write to stream".to_string(),
+                }
+            })?;
+    }
+
+    let channel_asked = match message.params.first() {
+        Some(channels_asked) if channels_asked.len() == 1 => Some(channels_asked.clone()),
+        _ => None,
+    };
+    let answer = NumericReply::new(RPL_ENDOFNAMES_NUM, RPL_ENDOFNAMES_MSG, channel_asked);
     stream
         .write_all(answer.as_string().as_bytes())
         .map_err(|_| -> ServerError {
@@ -729,10 +1326,26 @@ pub fn names(
     Ok(None)
 }
 
+///
+/// Returns the RPL_NAMREPLY with the users of the channel, for the user given
+///
+fn names_reply(channel: &Channel, nickname: &str) -> NumericReply {
+    NumericReply::new(
+        RPL_NAMEREPLY_NUM,
+        &channel.names().join(" "),
+        Some(vec![
+            nickname.to_string(),
+            channel.visibility_flag().to_string(),
+            channel.name.clone(),
+        ]),
+    )
+}
+
 /**********************************KICK MESSAGE***********************************/
 
 ///
-/// Kics a user from the channel received in message.
+/// Kics a user from the channel received in message. The KICK sent to the server carries
+/// the comment, or the nickname of the user kicking if no comment was given.
 ///
 /// Command: KICK
 /// Parameters: <channel> <user> [<comment>]
@@ -766,8 +1379,9 @@ pub fn kick(
     let channel_name = &message.params[0][0];
     let nickname_user_getting_kicked = &message.params[1][0];
     let nickname_user_kicking = &user.nickname;
+    let comment = message.params.get(2).and_then(|comment| comment.first());
 
-    let channel = match channels.get_mut(channel_name) {
+    let channel = match channels.get_mut(&irc_lowercase(channel_name)) {
         Some(channel) => channel,
         None => {
             return Ok(Some(NumericReply::new(
@@ -778,9 +1392,19 @@ pub fn kick(
         }
     };
 
-    match channel.kick(nickname_user_getting_kicked, nickname_user_kicking) {
-        Some(reply) => Ok(Some(reply)),
-        None => {
+    match channel.kick(nickname_user_getting_kicked, nickname_user_kicking, comment) {
+        Err(reply) => Ok(Some(reply)),
+        Ok(reason) => {
+            let message = Message {
+                tags: None,
+                prefix: message.prefix.clone(),
+                command: message.command.clone(),
+                params: vec![
+                    vec![channel_name.clone()],
+                    vec![nickname_user_getting_kicked.clone()],
+                    vec![reason],
+                ],
+            };
             sender.send(message).map_err(|_| -> ServerError {
                 ServerError {
                     kind: CRITICAL.to_string(),
@@ -807,7 +1431,7 @@ pub fn kick(
 /// ERR_NEEDMOREPARAMS
 /// ERR_NOTONCHANNEL
 /// RPL_NOTOPIC
-/// RPL_TOPIC
+/// RPL_TOPIC, followed by RPL_CREATIONTIME and RPL_TOPICWHOTIME when the topic is asked
 /// ERR_CHANOPRIVSNEEDED
 /// ERR_NOSUCHCHANNEL.
 ///
@@ -816,6 +1440,7 @@ pub fn topic(
     channels: Arc<Mutex<HashMap<String, Channel>>>,
     user: &mut User,
     sender: &Sender<Message>,
+    mut stream: &TcpStream,
 ) -> Result<Option<NumericReply>, ServerError> {
     if message.params_total_count() == 0 {
         return Ok(Some(NumericReply::new(
@@ -834,7 +1459,7 @@ pub fn topic(
         }
     })?;
 
-    let channel = match channels.get_mut(channel_name) {
+    let channel = match channels.get_mut(&irc_lowercase(channel_name)) {
         Some(channel) => channel,
         None => {
             return Ok(Some(NumericReply::new(
@@ -846,19 +1471,42 @@ pub fn topic(
     };
 
     if message.params_total_count() == 1 {
-        Ok(Some(channel.get_topic_reply()))
+        stream
+            .write_all(channel.get_topic_reply().as_string().as_bytes())
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: CRITICAL.to_string(),
+                    message: "Could not write on stream".to_string(),
+                }
+            })?;
+        send_topic_details(channel, stream)?;
+        Ok(None)
     } else {
         let topic = &message.params[1][0];
-        match channel.set_topic(&nickname, topic) {
+        let set_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_millis())
+            .unwrap_or(0);
+        match channel.set_topic(&nickname, topic, set_at) {
             Ok(reply) => {
-                if channel.is_multiserver() {
-                    sender.send(message).map_err(|_| -> ServerError {
-                        ServerError {
-                            kind: CRITICAL.to_string(),
-                            message: "Broken channel connection".to_string(),
-                        }
-                    })?;
+                // The server tells the members and saves the channels. The time the topic was
+                // set is sent so that servers can solve conflicts
+                let message = Message {
+                    tags: None,
+                    prefix: message.prefix.clone(),
+                    command: message.command.clone(),
+                    params: vec![
+                        message.params[0].clone(),
+                        vec![set_at.to_string()],
+                        message.params[1].clone(),
+                    ],
                 };
+                sender.send(message).map_err(|_| -> ServerError {
+                    ServerError {
+                        kind: CRITICAL.to_string(),
+                        message: "Broken channel connection".to_string(),
+                    }
+                })?;
                 Ok(Some(reply))
             }
             Err(reply) => Ok(Some(reply)),
@@ -866,24 +1514,139 @@ pub fn topic(
     }
 }
 
+/******************************SAVE CHANNELS MESSAGE********************************/
+
+///
+/// Asks the server to rewrite the channels file. Changes in multiserver channels are
+/// already sent to the server, so this is only needed for channels of this server.
+///
+fn request_channels_save(sender: &Sender<Message>) -> Result<(), ServerError> {
+    let message = Message {
+        tags: None,
+        prefix: None,
+        command: SAVE_CHANNELS.to_string(),
+        params: vec![],
+    };
+
+    sender.send(message).map_err(|_| -> ServerError {
+        ServerError {
+            kind: CRITICAL.to_string(),
+            message: "Could not send to server".to_string(),
+        }
+    })
+}
+
+/**********************************CLOSE MESSAGE***********************************/
+
+///
+/// Closes a channel. Every member is notified and parted from the channel and then the
+/// channel is deleted in every server. Only channel operators and the server operator can
+/// close a channel.
+///
+/// Command: CLOSE
+/// Parameters: <channel> [<comment>]
+///
+/// ERR_NEEDMOREPARAMS: no channel was given.
+/// ERR_NOSUCHCHANNEL: non existing channel name provided.
+/// ERR_NOTONCHANNEL: user is not on channel and is not the server operator.
+/// ERR_CHANOPRIVSNEEDED: user is not channel operator nor the server operator.
+///
+pub fn close_channel(
+    message: Message,
+    channels: Arc<Mutex<HashMap<String, Channel>>>,
+    user: &User,
+    sender: &Sender<Message>,
+    receiver: &Receiver<Message>,
+) -> Result<Option<NumericReply>, ServerError> {
+    if message.params_total_count() == 0 {
+        return Ok(Some(NumericReply::new(
+            ERR_NEEDMOREPARAMS_NUM,
+            ERR_NEEDMOREPARAMS_MSG,
+            None,
+        )));
+    }
+
+    let channel_name = &message.params[0][0];
+    let channels = channels.lock().map_err(|_| -> ServerError {
+        ServerError {
+            kind: NONCRITICAL.to_string(),
+            message: "Could not access channels".to_string(),
+        }
+    })?;
+    let privileges_reply = match channels.get(&irc_lowercase(channel_name)) {
+        Some(channel) => channel.reply_user_using_privileges(&user.nickname),
+        None => {
+            return Ok(Some(NumericReply::new(
+                ERR_NOSUCHCHANNEL_NUM,
+                ERR_NOSUCHCHANNEL_MSG,
+                None,
+            )))
+        }
+    };
+    // The server must be able to access the channels while answering
+    drop(channels);
+
+    if let Some(reply) = privileges_reply {
+        // Only the server operator can close channels without being channel operator
+        let mut request = message.clone();
+        request.command = IS_OPERATOR.to_string();
+        sender.send(request).map_err(|_| -> ServerError {
+            ServerError {
+                kind: CRITICAL.to_string(),
+                message: "Could not send to server".to_string(),
+            }
+        })?;
+        let answer = receiver.recv().map_err(|_| -> ServerError {
+            ServerError {
+                kind: CRITICAL.to_string(),
+                message: "Could not receive from server".to_string(),
+            }
+        })?;
+        if answer.params_total_count() == 0 || answer.params[0][0] != "You are an operator" {
+            return Ok(Some(reply));
+        }
+    }
+
+    // The server notifies every member and removes the channel
+    sender.send(message).map_err(|_| -> ServerError {
+        ServerError {
+            kind: CRITICAL.to_string(),
+            message: "Broken channel connection".to_string(),
+        }
+    })?;
+
+    Ok(None)
+}
+
 /**************************************TESTS**************************************/
 
 #[cfg(test)]
 mod tests {
-    use crate::commands::{INVITE, JOIN};
+    use crate::commands::{
+        CLOSE, INVITE, IS_OPERATOR, JOIN, LIST, MODE_SET_KEY, NAMES, PART, REGISTER, SAVE_CHANNELS,
+    };
     use crate::message::Message;
     use crate::numeric_reply::{
-        NumericReply, ERR_NEEDMOREPARAMS_MSG, ERR_NEEDMOREPARAMS_NUM, ERR_NOSUCHNICK_MSG,
-        ERR_NOSUCHNICK_NUM, RPL_INVITING_NUM,
+        NumericReply, ERR_CHANNELHASKEY_NUM, ERR_CHANOPRIVSNEEDED_MSG, ERR_CHANOPRIVSNEEDED_NUM,
+        ERR_NEEDMOREPARAMS_MSG, ERR_NEEDMOREPARAMS_NUM, ERR_NOSUCHNICK_MSG, ERR_NOSUCHNICK_NUM,
+        ERR_TOOMANYTARGETS_NUM, RPL_AWAY_NUM, RPL_CHANNELREGISTERED_MSG, RPL_CHANNELREGISTERED_NUM,
+        RPL_CREATIONTIME_NUM, RPL_ENDOFNAMES_MSG, RPL_ENDOFNAMES_NUM, RPL_INVITING_NUM,
+        RPL_LIST_NUM, RPL_NAMEREPLY_NUM, RPL_NAMEREPLY_PUBLIC, RPL_NOTOPIC_MSG, RPL_NOTOPIC_NUM,
     };
     use crate::server_utils::channel::Channel;
+    use crate::server_utils::server_data::DEFAULT_MAX_CHANNELS_PER_JOIN;
     use crate::server_utils::user::User;
     use std::collections::HashMap;
+    use std::io::{BufRead, BufReader};
     use std::net::{TcpListener, TcpStream};
     use std::sync::mpsc::{self, Receiver, Sender};
-    use std::sync::{Arc, Mutex};
+    use std::sync::{Arc, Barrier, Mutex};
+    use std::thread;
 
-    use super::{invite_to_channel, join_channel};
+    use super::{
+        close_channel, invite_to_channel, join_channel, list_all_channels, list_channels, names,
+        part_channel, register_channel, rejoin_channels, UsersCountFilter,
+    };
 
     #[test]
     fn test_join_channel_creates_new_channel_correctly() {
@@ -904,12 +1667,22 @@ mod tests {
         let _other = aux.incoming();
         let stream = TcpStream::connect("127.0.0.1:5000").unwrap();
         let message = Message {
+            tags: None,
             prefix: Some("test_user".to_string()),
             command: JOIN.to_string(),
             params: vec![vec!["#test_channel".to_string()]],
         };
 
-        let reply = join_channel(&stream, message, &channels, &users, &user, &sender);
+        let reply = join_channel(
+            &stream,
+            message,
+            &channels,
+            &users,
+            &user,
+            &sender,
+            DEFAULT_MAX_CHANNELS_PER_JOIN,
+            false,
+        );
         assert!(reply.is_ok());
         assert!(reply.unwrap().is_none());
         assert!(channels.lock().unwrap().contains_key("#test_channel"));
@@ -934,12 +1707,22 @@ mod tests {
         let _other = aux.incoming();
         let stream = TcpStream::connect("127.0.0.1:5001").unwrap();
         let message = Message {
+            tags: None,
             prefix: Some("test_user".to_string()),
             command: JOIN.to_string(),
             params: vec![vec![]],
         };
 
-        let reply = join_channel(&stream, message, &channels, &users, &user, &sender);
+        let reply = join_channel(
+            &stream,
+            message,
+            &channels,
+            &users,
+            &user,
+            &sender,
+            DEFAULT_MAX_CHANNELS_PER_JOIN,
+            false,
+        );
         assert!(reply.is_ok());
         assert_eq!(
             reply.unwrap().unwrap(),
@@ -976,6 +1759,7 @@ mod tests {
         let users: Arc<Mutex<HashMap<String, User>>> = Arc::new(Mutex::new(users));
 
         let message = Message {
+            tags: None,
             prefix: Some("test_user".to_string()),
             command: INVITE.to_string(),
             params: vec![vec![]],
@@ -1018,6 +1802,7 @@ mod tests {
         let users: Arc<Mutex<HashMap<String, User>>> = Arc::new(Mutex::new(users));
 
         let message = Message {
+            tags: None,
             prefix: Some("test_user".to_string()),
             command: INVITE.to_string(),
             params: vec![
@@ -1075,6 +1860,7 @@ mod tests {
         let users: Arc<Mutex<HashMap<String, User>>> = Arc::new(Mutex::new(users));
 
         let message = Message {
+            tags: None,
             prefix: Some("test_user".to_string()),
             command: INVITE.to_string(),
             params: vec![
@@ -1100,4 +1886,639 @@ mod tests {
     //kick
     //mode
     fn test() {}
+
+    #[test]
+    fn test_list_all_channels_sorted_by_popularity() {
+        let users: Vec<User> = ["user_1", "user_2", "user_3"]
+            .iter()
+            .map(|nickname| {
+                User::new(
+                    nickname.to_string(),
+                    "test".to_string(),
+                    "test".to_string(),
+                    "test".to_string(),
+                    "test".to_string(),
+                    "password".to_string(),
+                )
+            })
+            .collect();
+
+        // #small has one user, #big three and #medium_a and #medium_b two
+        let small = Channel::new("#small".to_string(), &users[0]);
+        let mut big = Channel::new("#big".to_string(), &users[0]);
+        big.join(users[1].clone(), None).unwrap();
+        big.join(users[2].clone(), None).unwrap();
+        let mut medium_b = Channel::new("#medium_b".to_string(), &users[0]);
+        medium_b.join(users[1].clone(), None).unwrap();
+        let mut medium_a = Channel::new("#medium_a".to_string(), &users[0]);
+        medium_a.join(users[2].clone(), None).unwrap();
+
+        let mut channels = HashMap::new();
+        for channel in [small, big, medium_b, medium_a] {
+            channels.insert(channel.name.clone(), channel);
+        }
+        let channels: Arc<Mutex<HashMap<String, Channel>>> = Arc::new(Mutex::new(channels));
+
+        let listener = TcpListener::bind("127.0.0.1:5002").unwrap();
+        let stream = TcpStream::connect("127.0.0.1:5002").unwrap();
+        let (received_stream, _) = listener.accept().unwrap();
+
+        list_all_channels(&channels, &UsersCountFilter::default(), &stream).unwrap();
+        drop(stream);
+
+        let listed: Vec<String> = BufReader::new(received_stream)
+            .lines()
+            .map(|line| line.unwrap().split(' ').nth(1).unwrap().to_string())
+            .collect();
+
+        assert_eq!(listed, vec!["#big", "#medium_a", "#medium_b", "#small"]);
+    }
+
+    #[test]
+    fn test_list_with_users_count_filter_only_lists_populated_channels() {
+        let users: Vec<User> = ["user_1", "user_2", "user_3"]
+            .iter()
+            .map(|nickname| {
+                User::new(
+                    nickname.to_string(),
+                    "test".to_string(),
+                    "test".to_string(),
+                    "test".to_string(),
+                    "test".to_string(),
+                    "password".to_string(),
+                )
+            })
+            .collect();
+
+        // #alone has one user, #pair two and #crowd and #secret three
+        let alone = Channel::new("#alone".to_string(), &users[0]);
+        let mut pair = Channel::new("#pair".to_string(), &users[0]);
+        pair.join(users[1].clone(), None).unwrap();
+        let mut crowd = Channel::new("#crowd".to_string(), &users[0]);
+        crowd.join(users[1].clone(), None).unwrap();
+        crowd.join(users[2].clone(), None).unwrap();
+        let mut secret = Channel::new("#secret".to_string(), &users[0]);
+        secret.join(users[1].clone(), None).unwrap();
+        secret.join(users[2].clone(), None).unwrap();
+        secret.set_as_secret("user_1".to_string()).unwrap();
+
+        let mut channels = HashMap::new();
+        for channel in [alone, pair, crowd, secret] {
+            channels.insert(channel.name.clone(), channel);
+        }
+        let channels: Arc<Mutex<HashMap<String, Channel>>> = Arc::new(Mutex::new(channels));
+
+        let listener = TcpListener::bind("127.0.0.1:5009").unwrap();
+        let stream = TcpStream::connect("127.0.0.1:5009").unwrap();
+        let (received_stream, _) = listener.accept().unwrap();
+
+        let list = |params: Vec<Vec<String>>| {
+            let message = Message {
+                tags: None,
+                prefix: Some("user_1".to_string()),
+                command: LIST.to_string(),
+                params,
+            };
+            list_channels(message, &channels, &stream).unwrap();
+        };
+        list(vec![vec![">1".to_string()]]);
+        list(vec![vec![">1".to_string()], vec!["<3".to_string()]]);
+        list(vec![
+            vec!["#alone".to_string(), "#crowd".to_string()],
+            vec![">1".to_string()],
+        ]);
+        drop(stream);
+
+        // The start and end of each list are left out
+        let listed: Vec<String> = BufReader::new(received_stream)
+            .lines()
+            .map(|line| line.unwrap())
+            .filter(|line| line.starts_with(RPL_LIST_NUM))
+            .map(|line| line.split(' ').nth(1).unwrap().to_string())
+            .collect();
+
+        assert_eq!(listed, vec!["#crowd", "#pair", "#pair", "#crowd"]);
+    }
+
+    #[test]
+    fn test_join_channel_tells_the_members_away_only_when_asked() {
+        let new_user = |nickname: &str| {
+            User::new(
+                nickname.to_string(),
+                "test".to_string(),
+                "test".to_string(),
+                "test".to_string(),
+                "test".to_string(),
+                "password".to_string(),
+            )
+        };
+        let (sender, _receiver): (Sender<Message>, Receiver<Message>) = mpsc::channel();
+        let mut ari = new_user("Ari");
+        ari.away_message = Some("Durmiendo".to_string());
+        let mut channel = Channel::new("#rust".to_string(), &ari);
+        channel.join(new_user("juani"), None).unwrap();
+        let channels = Arc::new(Mutex::new(HashMap::from([("#rust".to_string(), channel)])));
+        let users = Arc::new(Mutex::new(HashMap::from([
+            ("ari".to_string(), ari),
+            ("juani".to_string(), new_user("juani")),
+            ("lucas".to_string(), new_user("lucas")),
+            ("marce".to_string(), new_user("marce")),
+        ])));
+
+        let listener = TcpListener::bind("127.0.0.1:5014").unwrap();
+        let stream = TcpStream::connect("127.0.0.1:5014").unwrap();
+        let (received_stream, _) = listener.accept().unwrap();
+        for (nickname, away_on_join) in [("lucas", true), ("marce", false)] {
+            let message = Message {
+                tags: None,
+                prefix: Some(nickname.to_string()),
+                command: JOIN.to_string(),
+                params: vec![vec!["#rust".to_string()]],
+            };
+            let reply = join_channel(
+                &stream,
+                message,
+                &channels,
+                &users,
+                &new_user(nickname),
+                &sender,
+                DEFAULT_MAX_CHANNELS_PER_JOIN,
+                away_on_join,
+            );
+            assert!(reply.unwrap().is_none());
+        }
+        drop(stream);
+
+        let away_replies: Vec<String> = BufReader::new(received_stream)
+            .lines()
+            .map(|line| line.unwrap())
+            .filter(|line| line.starts_with(RPL_AWAY_NUM))
+            .collect();
+        assert_eq!(away_replies, vec!["301 Ari :Durmiendo"]);
+    }
+
+    #[test]
+    fn test_join_channel_processes_up_to_channels_limit() {
+        let (sender, _receiver): (Sender<Message>, Receiver<Message>) = mpsc::channel();
+        let channels: Arc<Mutex<HashMap<String, Channel>>> = Arc::new(Mutex::new(HashMap::new()));
+        let user = User::new(
+            "test_user".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let mut users = HashMap::new();
+        users.insert(user.nickname.clone(), user.clone());
+        let users: Arc<Mutex<HashMap<String, User>>> = Arc::new(Mutex::new(users));
+
+        let listener = TcpListener::bind("127.0.0.1:5003").unwrap();
+        let stream = TcpStream::connect("127.0.0.1:5003").unwrap();
+        let (received_stream, _) = listener.accept().unwrap();
+
+        let channels_names: Vec<String> = (0..DEFAULT_MAX_CHANNELS_PER_JOIN + 2)
+            .map(|i| format!("&channel_{}", i))
+            .collect();
+        let message = Message {
+            tags: None,
+            prefix: Some("test_user".to_string()),
+            command: JOIN.to_string(),
+            params: vec![channels_names.clone()],
+        };
+
+        let reply = join_channel(
+            &stream,
+            message,
+            &channels,
+            &users,
+            &user,
+            &sender,
+            DEFAULT_MAX_CHANNELS_PER_JOIN,
+            false,
+        );
+        assert!(reply.unwrap().is_none());
+        drop(stream);
+
+        // Only the channels up to the limit were created
+        let channels = channels.lock().unwrap();
+        assert_eq!(channels.len(), DEFAULT_MAX_CHANNELS_PER_JOIN);
+        for channel_name in &channels_names[..DEFAULT_MAX_CHANNELS_PER_JOIN] {
+            assert!(channels.contains_key(channel_name));
+        }
+
+        // The rest were reported to the user
+        let replies: Vec<String> = BufReader::new(received_stream)
+            .lines()
+            .map(|line| line.unwrap())
+            .collect();
+        let joined = replies
+            .iter()
+            .filter(|reply| reply.starts_with(RPL_NOTOPIC_NUM))
+            .count();
+        let rejected: Vec<&String> = replies
+            .iter()
+            .filter(|reply| reply.starts_with(ERR_TOOMANYTARGETS_NUM))
+            .collect();
+        assert_eq!(joined, DEFAULT_MAX_CHANNELS_PER_JOIN);
+        assert_eq!(rejected.len(), 2);
+        assert!(rejected[0].contains(&channels_names[DEFAULT_MAX_CHANNELS_PER_JOIN]));
+        assert!(rejected[1].contains(&channels_names[DEFAULT_MAX_CHANNELS_PER_JOIN + 1]));
+    }
+
+    #[test]
+    fn test_simultaneous_joins_never_exceed_the_limit_of_the_channel() {
+        const USERS_JOINING: usize = 20;
+        let (sender, _receiver): (Sender<Message>, Receiver<Message>) = mpsc::channel();
+        let users: Vec<User> = (0..USERS_JOINING)
+            .map(|i| {
+                User::new(
+                    format!("user_{}", i),
+                    "test".to_string(),
+                    "test".to_string(),
+                    "test".to_string(),
+                    "test".to_string(),
+                    "password".to_string(),
+                )
+            })
+            .collect();
+
+        // The channel is left empty by its operator, with room for one user
+        let mut channel = Channel::new("#limited".to_string(), &users[0]);
+        channel.remove_user(&users[0].nickname);
+        channel.limit = Some(1);
+        let channels: Arc<Mutex<HashMap<String, Channel>>> =
+            Arc::new(Mutex::new(HashMap::from([(channel.name.clone(), channel)])));
+        let users_by_nickname: Arc<Mutex<HashMap<String, User>>> = Arc::new(Mutex::new(
+            users
+                .iter()
+                .map(|user| (user.nickname.clone(), user.clone()))
+                .collect(),
+        ));
+
+        let listener = TcpListener::bind("127.0.0.1:5017").unwrap();
+        let barrier = Arc::new(Barrier::new(USERS_JOINING));
+        let handles: Vec<_> = users
+            .into_iter()
+            .map(|user| {
+                let stream = TcpStream::connect("127.0.0.1:5017").unwrap();
+                let (sender, channels, users, barrier) = (
+                    sender.clone(),
+                    channels.clone(),
+                    users_by_nickname.clone(),
+                    barrier.clone(),
+                );
+                thread::spawn(move || {
+                    let message = Message {
+                        tags: None,
+                        prefix: Some(user.nickname.clone()),
+                        command: JOIN.to_string(),
+                        params: vec![vec!["#limited".to_string()]],
+                    };
+                    barrier.wait();
+                    join_channel(
+                        &stream,
+                        message,
+                        &channels,
+                        &users,
+                        &user,
+                        &sender,
+                        DEFAULT_MAX_CHANNELS_PER_JOIN,
+                        false,
+                    )
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert!(handle.join().unwrap().unwrap().is_none());
+        }
+        drop(listener);
+
+        // Only one user got in, and only that one has the channel in its list
+        assert_eq!(channels.lock().unwrap()["#limited"].users.len(), 1);
+        let users_with_channel = users_by_nickname
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|user| user.channels.contains("#limited"))
+            .count();
+        assert_eq!(users_with_channel, 1);
+    }
+
+    #[test]
+    fn test_join_channel_pairs_each_key_with_its_channel() {
+        let (sender, _receiver): (Sender<Message>, Receiver<Message>) = mpsc::channel();
+        let owner = User::new(
+            "owner".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let mut channels = HashMap::new();
+        for name in ["#a", "#b"] {
+            let mut channel = Channel::new(name.to_string(), &owner);
+            channel.enter_mode = Some(MODE_SET_KEY.to_string());
+            channel.key = Some("clave".to_string());
+            channels.insert(name.to_string(), channel);
+        }
+        let channels: Arc<Mutex<HashMap<String, Channel>>> = Arc::new(Mutex::new(channels));
+        let user = User::new(
+            "test_user".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let mut users = HashMap::new();
+        users.insert(user.nickname.clone(), user.clone());
+        let users: Arc<Mutex<HashMap<String, User>>> = Arc::new(Mutex::new(users));
+
+        let listener = TcpListener::bind("127.0.0.1:5007").unwrap();
+        let stream = TcpStream::connect("127.0.0.1:5007").unwrap();
+        let (received_stream, _) = listener.accept().unwrap();
+
+        // The only key given is for the first channel
+        let message = Message {
+            tags: None,
+            prefix: Some("test_user".to_string()),
+            command: JOIN.to_string(),
+            params: vec![
+                vec!["#a".to_string(), "#b".to_string()],
+                vec!["clave".to_string()],
+            ],
+        };
+
+        let reply = join_channel(
+            &stream,
+            message,
+            &channels,
+            &users,
+            &user,
+            &sender,
+            DEFAULT_MAX_CHANNELS_PER_JOIN,
+            false,
+        );
+        assert!(reply.unwrap().is_none());
+        drop(stream);
+
+        let channels = channels.lock().unwrap();
+        assert!(channels["#a"].is_user_on_channel("test_user"));
+        assert!(!channels["#b"].is_user_on_channel("test_user"));
+
+        let replies: Vec<String> = BufReader::new(received_stream)
+            .lines()
+            .map(|line| line.unwrap())
+            .collect();
+        assert_eq!(replies.len(), 3);
+        assert!(replies[0].starts_with(RPL_NOTOPIC_NUM));
+        assert!(replies[1].starts_with(RPL_CREATIONTIME_NUM));
+        assert!(replies[2].starts_with(ERR_CHANNELHASKEY_NUM));
+    }
+
+    #[test]
+    fn test_names_of_a_single_channel_ends_with_its_name() {
+        let operator = User::new(
+            "operator".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let channels: Arc<Mutex<HashMap<String, Channel>>> =
+            Arc::new(Mutex::new(HashMap::from([(
+                "#rust".to_string(),
+                Channel::new("#Rust".to_string(), &operator),
+            )])));
+
+        let listener = TcpListener::bind("127.0.0.1:5012").unwrap();
+        let stream = TcpStream::connect("127.0.0.1:5012").unwrap();
+        let (received_stream, _) = listener.accept().unwrap();
+
+        for channels_asked in [vec!["#rust"], vec!["#missing"], vec!["#rust", "#missing"]] {
+            let message = Message {
+                tags: None,
+                prefix: Some("operator".to_string()),
+                command: NAMES.to_string(),
+                params: vec![channels_asked
+                    .iter()
+                    .map(|channel| channel.to_string())
+                    .collect()],
+            };
+            assert!(names(message, &stream, channels.clone()).unwrap().is_none());
+        }
+        drop(stream);
+
+        let replies: Vec<String> = BufReader::new(received_stream)
+            .lines()
+            .map(|line| line.unwrap())
+            .collect();
+        assert_eq!(
+            replies,
+            vec![
+                format!(
+                    "{} operator {} #Rust :@operator",
+                    RPL_NAMEREPLY_NUM, RPL_NAMEREPLY_PUBLIC
+                ),
+                format!("{} #rust :{}", RPL_ENDOFNAMES_NUM, RPL_ENDOFNAMES_MSG),
+                format!("{} #missing :{}", RPL_ENDOFNAMES_NUM, RPL_ENDOFNAMES_MSG),
+                format!(
+                    "{} operator {} #Rust :@operator",
+                    RPL_NAMEREPLY_NUM, RPL_NAMEREPLY_PUBLIC
+                ),
+                format!("{} :{}", RPL_ENDOFNAMES_NUM, RPL_ENDOFNAMES_MSG),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_close_channel_by_channel_operator_is_sent_to_server() {
+        let (sender, receiver): (Sender<Message>, Receiver<Message>) = mpsc::channel();
+        let operator = User::new(
+            "operator".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let channel = Channel::new("#channel".to_string(), &operator);
+        let channels = Arc::new(Mutex::new(HashMap::from([(channel.name.clone(), channel)])));
+        let message = Message {
+            tags: None,
+            prefix: Some("operator".to_string()),
+            command: CLOSE.to_string(),
+            params: vec![vec!["#channel".to_string()], vec!["Bye".to_string()]],
+        };
+
+        let reply = close_channel(message.clone(), channels, &operator, &sender, &receiver);
+
+        assert!(reply.unwrap().is_none());
+        assert_eq!(receiver.try_recv().unwrap(), message);
+    }
+
+    #[test]
+    fn test_close_channel_by_non_operator_is_rejected() {
+        let (sender, receiver): (Sender<Message>, Receiver<Message>) = mpsc::channel();
+        let operator = User::new(
+            "operator".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let user = User::new(
+            "test_user".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let mut channel = Channel::new("#channel".to_string(), &operator);
+        channel.join(user.clone(), None).unwrap();
+        let channels = Arc::new(Mutex::new(HashMap::from([(channel.name.clone(), channel)])));
+        let message = Message {
+            tags: None,
+            prefix: Some("test_user".to_string()),
+            command: CLOSE.to_string(),
+            params: vec![vec!["#channel".to_string()]],
+        };
+
+        // Answer of the server, the user is not the server operator
+        let mut answer = message.clone();
+        answer.command = IS_OPERATOR.to_string();
+        answer.params = vec![vec![]];
+        sender.send(answer).unwrap();
+
+        let reply = close_channel(message, channels.clone(), &user, &sender, &receiver);
+
+        assert_eq!(
+            reply.unwrap(),
+            Some(NumericReply::new(
+                ERR_CHANOPRIVSNEEDED_NUM,
+                ERR_CHANOPRIVSNEEDED_MSG,
+                Some(vec!["#channel".to_string()])
+            ))
+        );
+        // Only the operator request was sent, the channel is still there
+        assert_eq!(receiver.try_recv().unwrap().command, IS_OPERATOR);
+        assert!(receiver.try_recv().is_err());
+        assert!(channels.lock().unwrap().contains_key("#channel"));
+    }
+
+    #[test]
+    fn test_registered_channel_is_kept_when_its_last_user_parts() {
+        let (sender, receiver): (Sender<Message>, Receiver<Message>) = mpsc::channel();
+        let operator = User::new(
+            "operator".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let channel = Channel::new("&channel".to_string(), &operator);
+        let channels = Arc::new(Mutex::new(HashMap::from([(channel.name.clone(), channel)])));
+        let register = Message {
+            tags: None,
+            prefix: None,
+            command: REGISTER.to_string(),
+            params: vec![vec!["&channel".to_string()]],
+        };
+
+        let reply = register_channel(&sender, register, &channels, &operator);
+
+        assert_eq!(
+            reply.unwrap(),
+            Some(NumericReply::new(
+                RPL_CHANNELREGISTERED_NUM,
+                RPL_CHANNELREGISTERED_MSG,
+                Some(vec!["&channel".to_string()])
+            ))
+        );
+        assert_eq!(receiver.try_recv().unwrap().command, SAVE_CHANNELS);
+
+        let _listener = TcpListener::bind("127.0.0.1:5018").unwrap();
+        let stream = TcpStream::connect("127.0.0.1:5018").unwrap();
+        let part = Message {
+            tags: None,
+            prefix: Some("operator".to_string()),
+            command: PART.to_string(),
+            params: vec![vec!["&channel".to_string()]],
+        };
+        part_channel(part, &channels, &operator, &stream, &sender).unwrap();
+
+        let channels = channels.lock().unwrap();
+        let channel = channels.get("&channel").unwrap();
+        assert!(channel.is_empty());
+        assert_eq!(channel.founder, Some("operator".to_string()));
+    }
+
+    #[test]
+    fn test_rejoin_channels_sends_the_channels_of_the_user_and_forgets_the_rest() {
+        let operator = User::new(
+            "operator".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let mut user = User::new(
+            "test_user".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        // The user left #parted without the server noticing and #closed doesn't exist anymore
+        for channel_name in ["#Joined", "#parted", "#closed"] {
+            user.add_channel(&channel_name.to_string());
+        }
+        let mut joined = Channel::new("#Joined".to_string(), &operator);
+        joined.join(user.clone(), None).unwrap();
+        joined.created_at = 1000;
+        let parted = Channel::new("#parted".to_string(), &operator);
+        let channels: Arc<Mutex<HashMap<String, Channel>>> = Arc::new(Mutex::new(HashMap::from([
+            ("#joined".to_string(), joined),
+            ("#parted".to_string(), parted),
+        ])));
+        let users: Arc<Mutex<HashMap<String, User>>> =
+            Arc::new(Mutex::new(HashMap::from([("test_user".to_string(), user)])));
+
+        let listener = TcpListener::bind("127.0.0.1:5010").unwrap();
+        let stream = TcpStream::connect("127.0.0.1:5010").unwrap();
+        let (received_stream, _) = listener.accept().unwrap();
+
+        rejoin_channels(&stream, &channels, &users, "TEST_USER").unwrap();
+        drop(stream);
+
+        let received: Vec<String> = BufReader::new(received_stream)
+            .lines()
+            .map(|line| line.unwrap())
+            .collect();
+        assert_eq!(
+            received,
+            vec![
+                format!("{} #Joined :{}", RPL_NOTOPIC_NUM, RPL_NOTOPIC_MSG),
+                format!("{} #Joined 1000 ", RPL_CREATIONTIME_NUM),
+                format!(
+                    "{} test_user {} #Joined :@operator test_user",
+                    RPL_NAMEREPLY_NUM, RPL_NAMEREPLY_PUBLIC
+                ),
+                format!("{} :{}", RPL_ENDOFNAMES_NUM, RPL_ENDOFNAMES_MSG),
+            ]
+        );
+        let channels_of_user = users.lock().unwrap()["test_user"].channels.clone();
+        assert_eq!(
+            channels_of_user.into_iter().collect::<Vec<String>>(),
+            vec!["#Joined"]
+        );
+    }
 }
@@ -12,6 +12,7 @@ use std::{
         mpsc::Sender,
         {Arc, Mutex},
     },
+    time::Instant,
 };
 
 use crate::{
@@ -20,11 +21,15 @@ use crate::{
         server_error::ServerError,
     },
     numeric_reply::{
-        NumericReply, ERR_NORECIPIENT_MSG, ERR_NORECIPIENT_NUM, ERR_NOSUCHNICK_MSG,
-        ERR_NOSUCHNICK_NUM, ERR_NOTEXTTOSEND_MSG, ERR_NOTEXTTOSEND_NUM, ERR_NOTONCHANNEL_MSG,
-        ERR_NOTONCHANNEL_NUM, RPL_AWAY_NUM,
+        NumericReply, ERR_CANNOTSENDTOCHAN_MSG, ERR_CANNOTSENDTOCHAN_NUM, ERR_MESSAGETOOLONG_MSG,
+        ERR_MESSAGETOOLONG_NUM, ERR_NORECIPIENT_MSG, ERR_NORECIPIENT_NUM, ERR_NOSUCHNICK_MSG,
+        ERR_NOSUCHNICK_NUM, ERR_NOTEXTTOSEND_MSG, ERR_NOTEXTTOSEND_NUM,
+    },
+    parser::irc_lowercase,
+    server_utils::{
+        channel::Channel, messages_processing_client::manage_channels::notify_flood_protection,
+        user::User,
     },
-    server_utils::{channel::Channel, user::User},
 };
 
 use crate::message::Message;
@@ -37,7 +42,7 @@ use crate::message::Message;
 /// of error could return the following numeric replies:
 ///  
 /// ERR_NORECIPIENT: no recipient was given.
-/// ERR_NOTEXTTOSEND: no text to send was given.
+/// ERR_NOTEXTTOSEND: no text to send was given, or it is empty.
 /// ERR_NOSUCHNICK: no channel or user found with given nick.
 ///
 pub fn private_message(
@@ -49,8 +54,9 @@ pub fn private_message(
 ) -> Result<Option<NumericReply>, ServerError> {
     println!("Send a private message!");
 
-    let data = message.params[1].clone();
-    let receivers = message.params[0].clone();
+    // A missing text, like in "PRIVMSG ari", is the same as an empty one
+    let data = message.params.get(1).cloned().unwrap_or_default();
+    let receivers = message.params.first().cloned().unwrap_or_default();
 
     // Check if there are receiver, if not ERR_NORECIPIENT
     if receivers.is_empty() || receivers[0].is_empty() {
@@ -142,21 +148,12 @@ fn send_message_to_user(
             message: "Could not lock users".to_string(),
         }
     })?;
-    return match users.get(receiver) {
-        Some(user) => match &user.away_message {
-            Some(away_message) => {
-                let reply = NumericReply::new(
-                    RPL_AWAY_NUM,
-                    away_message,
-                    Some(vec![user.nickname.clone()]),
-                );
-                Ok(Some(reply))
-            }
-            None => {
-                notify_server_to_send_message(message, receiver, sender)?;
-                Ok(None)
-            }
-        },
+    // Messages to away users are delivered too, the server tells the sender they are away
+    return match users.get(&irc_lowercase(receiver)) {
+        Some(_) => {
+            notify_server_to_send_message(message, receiver, sender)?;
+            Ok(None)
+        }
         None => {
             println!("Client not found!");
             Ok(Some(NumericReply::new(
@@ -171,6 +168,8 @@ fn send_message_to_user(
 ///
 /// Notifies server that a private message should be sent to the specified channel be sending the
 /// proper message. If the recipient is not found the numeric reply ERR_NOSUCHNICK ir returned.
+/// Too many messages in a channel with flood protection mute it for a while.
+/// Messages longer than the limit of the channel are not sent and ERR_MESSAGETOOLONG is returned.
 ///
 fn send_message_to_channel(
     channel_name: &String,
@@ -178,23 +177,43 @@ fn send_message_to_channel(
     sender: &Sender<Message>,
     channels: Arc<Mutex<HashMap<String, Channel>>>,
 ) -> Result<Option<NumericReply>, ServerError> {
-    let channels = channels.lock().map_err(|_| -> ServerError {
+    let mut channels = channels.lock().map_err(|_| -> ServerError {
         ServerError {
             kind: NONCRITICAL.to_string(),
             message: "Could not access channels".to_string(),
         }
     })?;
 
-    match channels.get(channel_name) {
+    match channels.get_mut(&irc_lowercase(channel_name)) {
         Some(channel) => {
-            if !channel.is_user_on_channel(&message.prefix.clone().expect("No prefix found")) {
+            if !channel.can_send_message(&message.prefix.clone().expect("No prefix found")) {
                 return Ok(Some(NumericReply::new(
-                    ERR_NOTONCHANNEL_NUM,
-                    ERR_NOTONCHANNEL_MSG,
-                    Some(vec![channel_name.to_string()]),
+                    ERR_CANNOTSENDTOCHAN_NUM,
+                    ERR_CANNOTSENDTOCHAN_MSG,
+                    Some(vec![channel.name.clone()]),
                 )));
             }
+            if let Some(text) = message.params.get(1).and_then(|param| param.first()) {
+                if channel.is_message_too_long(text) {
+                    return Ok(Some(NumericReply::new(
+                        ERR_MESSAGETOOLONG_NUM,
+                        ERR_MESSAGETOOLONG_MSG,
+                        Some(vec![
+                            channel.name.clone(),
+                            channel.max_message_length.unwrap_or_default().to_string(),
+                        ]),
+                    )));
+                }
+            }
             notify_server_to_send_message(message, channel_name, sender)?;
+            if channel.record_sent_message(Instant::now()) {
+                notify_flood_protection(
+                    channel,
+                    sender,
+                    "messages were sent to",
+                    "only operators and voiced users can talk",
+                )?;
+            }
             Ok(None)
         }
         None => {
@@ -239,6 +258,7 @@ mod tests {
         NumericReply, ERR_NORECIPIENT_MSG, ERR_NORECIPIENT_NUM, ERR_NOTEXTTOSEND_MSG,
         ERR_NOTEXTTOSEND_NUM,
     };
+    use crate::parser::parse;
     use crate::server_utils::channel::Channel;
     use crate::server_utils::messages_processing_client::messages_exchange::private_message;
     use crate::server_utils::user::User;
@@ -272,6 +292,7 @@ mod tests {
         let stream = TcpStream::connect("127.0.0.1:3000").unwrap();
 
         let message = Message {
+            tags: None,
             prefix: None,
             command: PRIVMSG.to_string(),
             params: vec![vec![], vec!["test message\r\n".to_string()]],
@@ -321,6 +342,7 @@ mod tests {
         let stream = TcpStream::connect("127.0.0.1:3001").unwrap();
 
         let message = Message {
+            tags: None,
             prefix: None,
             command: PRIVMSG.to_string(),
             params: vec![vec![user_2.nickname.clone()], vec![]],
@@ -341,6 +363,32 @@ mod tests {
         )
     }
 
+    #[test]
+    fn send_private_message_with_empty_or_missing_text_is_not_delivered() {
+        let users = Arc::new(Mutex::new(HashMap::new()));
+        let channels = Arc::new(Mutex::new(HashMap::new()));
+        let (sender, receiver): (Sender<Message>, Receiver<Message>) = mpsc::channel();
+
+        for line in [
+            "PRIVMSG ari :\r\n",
+            "PRIVMSG ari\r\n",
+            "PRIVMSG #canal :\r\n",
+        ] {
+            let message = parse(line.to_string()).unwrap();
+            let reply =
+                private_message(message, users.clone(), &sender, channels.clone(), None).unwrap();
+            assert_eq!(
+                reply,
+                Some(NumericReply::new(
+                    ERR_NOTEXTTOSEND_NUM,
+                    ERR_NOTEXTTOSEND_MSG,
+                    None
+                ))
+            );
+        }
+        assert!(receiver.try_recv().is_err());
+    }
+
     #[test]
     fn send_private_message_with_non_existing_recipient_returns_correct_numeric_reply() {
         let _user = User::new(
@@ -365,6 +413,7 @@ mod tests {
         let stream = TcpStream::connect("127.0.0.1:3002").unwrap();
 
         let message = Message {
+            tags: None,
             prefix: None,
             command: PRIVMSG.to_string(),
             params: vec![
@@ -408,6 +457,7 @@ mod tests {
         let stream = TcpStream::connect("127.0.0.1:3003").unwrap();
 
         let message = Message {
+            tags: None,
             prefix: None,
             command: PRIVMSG.to_string(),
             params: vec![
@@ -452,6 +502,7 @@ mod tests {
         let channel = Channel::new("test_name".to_string(), &user);
 
         let message = Message {
+            tags: None,
             prefix: None,
             command: PRIVMSG.to_string(),
             params: vec![
@@ -497,6 +548,7 @@ mod tests {
         let stream = TcpStream::connect("127.0.0.1:3005").unwrap();
 
         let message = Message {
+            tags: None,
             prefix: None,
             command: PRIVMSG.to_string(),
             params: vec![
@@ -540,6 +592,7 @@ mod tests {
         let stream = TcpStream::connect("127.0.0.1:3006").unwrap();
 
         let mut message = Message {
+            tags: None,
             prefix: Some("test_user".to_string()),
             command: PRIVMSG.to_string(),
             params: vec![
@@ -595,6 +648,7 @@ mod tests {
         let stream = TcpStream::connect("127.0.0.1:3008").unwrap();
 
         let mut message = Message {
+            tags: None,
             prefix: Some("test_user".to_string()),
             command: PRIVMSG.to_string(),
             params: vec![
@@ -645,6 +699,7 @@ mod tests {
         let channel = Channel::new("test_name".to_string(), &user);
 
         let message = Message {
+            tags: None,
             prefix: Some("test_user".to_string()),
             command: PRIVMSG.to_string(),
             params: vec![
@@ -693,6 +748,7 @@ mod tests {
         let channel = Channel::new("test_name".to_string(), &user);
 
         let mut message = Message {
+            tags: None,
             prefix: Some("test_user".to_string()),
             command: PRIVMSG.to_string(),
             params: vec![
@@ -745,6 +801,7 @@ mod tests {
         let channel_2 = Channel::new("test_name_2".to_string(), &user);
 
         let mut message = Message {
+            tags: None,
             prefix: Some("test_user".to_string()),
             command: PRIVMSG.to_string(),
             params: vec![
@@ -5,17 +5,22 @@
 
 use crate::custom_errors::server_error::ServerError;
 use crate::{
-    commands::IS_OPERATOR,
+    commands::{IS_OPERATOR, USER_MODE},
     custom_errors::errors::{CRITICAL, NONCRITICAL},
     message::Message,
     numeric_reply::{
-        NumericReply, ERR_NONICKNAMEGIVEN_MSG, ERR_NONICKNAMEGIVEN_NUM, ERR_NOSUCHNICK_MSG,
-        ERR_NOSUCHNICK_NUM, ERR_NOSUCHSERVER_MSG, ERR_NOSUCHSERVER_NUM, RPL_ENDOFWHOIS_MSG,
-        RPL_ENDOFWHOIS_NUM, RPL_ENDOFWHO_MSG, RPL_ENDOFWHO_NUM, RPL_NOWAWAY_MSG, RPL_NOWAWAY_NUM,
+        NumericReply, ERR_NEEDMOREPARAMS_MSG, ERR_NEEDMOREPARAMS_NUM, ERR_NONICKNAMEGIVEN_MSG,
+        ERR_NONICKNAMEGIVEN_NUM, ERR_NOSUCHNICK_MSG, ERR_NOSUCHNICK_NUM, ERR_NOSUCHSERVER_MSG,
+        ERR_NOSUCHSERVER_NUM, ERR_UMODEUNKNOWNFLAG_MSG, ERR_UMODEUNKNOWNFLAG_NUM,
+        ERR_USERSDONTMATCH_MSG, ERR_USERSDONTMATCH_NUM, RPL_ENDOFSILELIST_MSG,
+        RPL_ENDOFSILELIST_NUM, RPL_ENDOFWHOIS_MSG, RPL_ENDOFWHOIS_NUM, RPL_ENDOFWHO_MSG,
+        RPL_ENDOFWHO_NUM, RPL_NOWAWAY_MSG, RPL_NOWAWAY_NUM, RPL_SILELIST_NUM, RPL_UMODEIS_NUM,
         RPL_UNAWAY_MSG, RPL_UNAWAY_NUM, RPL_WHOISCHANNELS_MSG, RPL_WHOISCHANNELS_NUM,
-        RPL_WHOISOPERATOR_MSG, RPL_WHOISOPERATOR_NUM, RPL_WHOISSERVER_MSG, RPL_WHOISSERVER_NUM,
-        RPL_WHOISUSER_NUM, RPL_WHOREPLY_MSG, RPL_WHOREPLY_NUM,
+        RPL_WHOISIDLE_MSG, RPL_WHOISIDLE_NUM, RPL_WHOISOPERATOR_MSG, RPL_WHOISOPERATOR_NUM,
+        RPL_WHOISSERVER_MSG, RPL_WHOISSERVER_NUM, RPL_WHOISUSER_NUM, RPL_WHOREPLY_MSG,
+        RPL_WHOREPLY_NUM,
     },
+    parser::{irc_eq, irc_lowercase},
     server_utils::{channel::Channel, user::User},
 };
 use std::{
@@ -31,12 +36,17 @@ use std::{
 
 ///
 /// Handles WHO command, will check what it has to answer with
-/// if not parameter is received in message then it returns all users
+/// if not parameter is received in message then it returns all users.
+/// Invisible users are not listed unless they share a channel with the user asking.
+/// WHO #channel lists the members of the channel with their flags, and the end of the
+/// list has the name of the channel
 ///
 pub fn handle_who(
     message: Message,
     mut stream: &TcpStream,
     users: Arc<Mutex<HashMap<String, User>>>,
+    channels: Arc<Mutex<HashMap<String, Channel>>>,
+    nickname: &str,
     receiver: &Receiver<Message>,
     sender: &Sender<Message>,
 ) -> Result<Option<NumericReply>, ServerError> {
@@ -45,11 +55,12 @@ pub fn handle_who(
         message.params
     );
 
+    let mut end_params = None;
     if message.params_total_count() == 1 && message.params[0][0] == "0"
         || message.params_total_count() == 0
     {
         let users_list = get_all_users(users)?;
-        send_response(users_list, stream)?;
+        send_response(visible_users(users_list, nickname, channels)?, stream)?;
     } else if message.params[0].contains(&"o".to_string()) {
         sender.send(message).map_err(|_| -> ServerError {
             ServerError {
@@ -65,12 +76,24 @@ pub fn handle_who(
         })?;
 
         if msg.command != *"OPERATOR_NOT_FOUND" {
-            let names = &msg.params[0];
-            for user in names {
+            // Invisible operators are left out like any other invisible user
+            let operators = {
+                let users = users.lock().map_err(|_| -> ServerError {
+                    ServerError {
+                        kind: NONCRITICAL.to_string(),
+                        message: "Could not lock users".to_string(),
+                    }
+                })?;
+                msg.params[0]
+                    .iter()
+                    .filter_map(|name| users.get(&irc_lowercase(name)).cloned())
+                    .collect::<Vec<User>>()
+            };
+            for user in visible_users(operators, nickname, channels)? {
                 let answer = NumericReply::new(
                     RPL_WHOREPLY_NUM,
                     RPL_WHOREPLY_MSG,
-                    Some(vec![user.to_string()]),
+                    Some(vec![user.nickname]),
                 );
                 stream
                     .write_all(answer.as_string().as_bytes())
@@ -82,12 +105,15 @@ pub fn handle_who(
                     })?;
             }
         }
+    } else if is_channel_name(&message.params[0][0]) {
+        send_channel_members(&message.params[0][0], users, channels, nickname, stream)?;
+        end_params = Some(vec![message.params[0][0].clone()]);
     } else {
         let users_list = get_users_with(message.params[0][0].clone(), users)?;
-        send_response(users_list, stream)?;
+        send_response(visible_users(users_list, nickname, channels)?, stream)?;
     }
 
-    let end = NumericReply::new(RPL_ENDOFWHO_NUM, RPL_ENDOFWHO_MSG, None);
+    let end = NumericReply::new(RPL_ENDOFWHO_NUM, RPL_ENDOFWHO_MSG, end_params);
     stream
         .write_all(end.as_string().as_bytes())
         .map_err(|_| -> ServerError {
@@ -145,7 +171,7 @@ pub fn whois(
         }
     })?;
 
-    let user = match users.get(&nick) {
+    let user = match users.get(&irc_lowercase(&nick)) {
         Some(user) => user,
         None => {
             return Ok(Some(NumericReply::new(
@@ -202,6 +228,21 @@ pub fn whois(
             }
         })?;
 
+    // The idle and signon times are only known by the server the user is connected to
+    if let Some(signon) = user.signon {
+        let idle = user.idle_seconds().unwrap_or_default();
+        let args = vec![user.nickname.clone(), idle.to_string(), signon.to_string()];
+        let reply = NumericReply::new(RPL_WHOISIDLE_NUM, RPL_WHOISIDLE_MSG, Some(args));
+        stream
+            .write_all(reply.as_string().as_bytes())
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: CRITICAL.to_string(),
+                    message: "Could not send to server".to_string(),
+                }
+            })?;
+    }
+
     let mut request = message;
     request.command = IS_OPERATOR.to_string();
     sender.send(request).map_err(|_| -> ServerError {
@@ -308,6 +349,88 @@ pub fn get_users_with(
     Ok(list_of_users)
 }
 
+///
+/// Leaves out the invisible users (+i) that don't share a channel with the user asking,
+/// the user asking is always kept
+///
+pub fn visible_users(
+    users: Vec<User>,
+    nickname: &str,
+    channels: Arc<Mutex<HashMap<String, Channel>>>,
+) -> Result<Vec<User>, ServerError> {
+    let channels = channels.lock().map_err(|_| -> ServerError {
+        ServerError {
+            kind: NONCRITICAL.to_string(),
+            message: "Could not lock channels".to_string(),
+        }
+    })?;
+
+    Ok(users
+        .into_iter()
+        .filter(|user| {
+            !user.invisible
+                || irc_eq(&user.nickname, nickname)
+                || channels.values().any(|channel| {
+                    channel.is_user_on_channel(&user.nickname)
+                        && channel.is_user_on_channel(nickname)
+                })
+        })
+        .collect())
+}
+
+///
+/// Checks if the name given is the name of a channel
+///
+fn is_channel_name(name: &str) -> bool {
+    name.starts_with('#') || name.starts_with('&')
+}
+
+///
+/// Sends a RPL_WHOREPLY for every member of the channel that the user asking can see,
+/// nothing if the channel doesn't exist
+///
+pub fn send_channel_members(
+    channel_name: &str,
+    users: Arc<Mutex<HashMap<String, User>>>,
+    channels: Arc<Mutex<HashMap<String, Channel>>>,
+    nickname: &str,
+    mut stream: &TcpStream,
+) -> Result<(), ServerError> {
+    let users = users.lock().map_err(|_| -> ServerError {
+        ServerError {
+            kind: NONCRITICAL.to_string(),
+            message: "Could not lock users".to_string(),
+        }
+    })?;
+    let server_name = match users.get(&irc_lowercase(nickname)) {
+        Some(user) => user.server_name.clone(),
+        None => return Ok(()),
+    };
+    let channels = channels.lock().map_err(|_| -> ServerError {
+        ServerError {
+            kind: NONCRITICAL.to_string(),
+            message: "Could not lock channels".to_string(),
+        }
+    })?;
+    let replies = match channels.get(&irc_lowercase(channel_name)) {
+        Some(channel) => channel.who_replies(&users, nickname, &server_name),
+        None => vec![],
+    };
+
+    for reply in replies {
+        stream
+            .write_all(reply.as_string().as_bytes())
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: CRITICAL.to_string(),
+                    message: "Could not send to server".to_string(),
+                }
+            })?;
+    }
+
+    Ok(())
+}
+
 ///
 /// Sends the response to the client, it will send NumericReply::RPL_WHOREPLY first and then the users nick
 ///
@@ -344,7 +467,7 @@ pub fn handle_away(
                 message: "Could not lock users".to_string(),
             }
         })?;
-        let user_data = users.get_mut(&user.nickname).unwrap();
+        let user_data = users.get_mut(&irc_lowercase(&user.nickname)).unwrap();
         user_data.away_message = None;
         user.away_message = None;
         let answer = NumericReply::new(RPL_UNAWAY_NUM, RPL_UNAWAY_MSG, None);
@@ -356,7 +479,7 @@ pub fn handle_away(
                 message: "Could not lock users".to_string(),
             }
         })?;
-        let user_data = users.get_mut(&user.nickname).unwrap();
+        let user_data = users.get_mut(&irc_lowercase(&user.nickname)).unwrap();
         user_data.away_message = Some(message.params[0][0].clone());
         user.away_message = Some(message.params[0][0].clone());
 
@@ -374,12 +497,234 @@ pub fn handle_away(
     }
 }
 
+/*******************************USER MODE MESSAGE***********************************/
+
+///
+/// Handles MODE command when the target is a nickname. Users can only change their own
+/// modes, the only one known is +i (invisible): invisible users are not listed by WHO to
+/// the users that don't share a channel with them. The change is sent to the server so
+/// the other servers know it. Answers with RPL_UMODEIS, also when no mode is given.
+///
+/// ERR_USERSDONTMATCH: the nickname is not the one of the user.
+/// ERR_UMODEUNKNOWNFLAG: the mode is not +i or -i.
+///
+/// Command: MODE
+/// Parameters: <nickname> [( "+" / "-" ) "i"]
+///
+pub fn set_user_mode(
+    message: Message,
+    user: &mut User,
+    users: Arc<Mutex<HashMap<String, User>>>,
+    sender: &Sender<Message>,
+) -> Result<Option<NumericReply>, ServerError> {
+    if !irc_eq(&message.params[0][0], &user.nickname) {
+        return Ok(Some(NumericReply::new(
+            ERR_USERSDONTMATCH_NUM,
+            ERR_USERSDONTMATCH_MSG,
+            None,
+        )));
+    }
+
+    if message.params_total_count() > 1 {
+        let mode = message.params[1][0].clone();
+        let invisible = match mode.as_str() {
+            "+i" => true,
+            "-i" => false,
+            _ => {
+                return Ok(Some(NumericReply::new(
+                    ERR_UMODEUNKNOWNFLAG_NUM,
+                    ERR_UMODEUNKNOWNFLAG_MSG,
+                    None,
+                )))
+            }
+        };
+
+        let mut users = users.lock().map_err(|_| -> ServerError {
+            ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "Could not lock users".to_string(),
+            }
+        })?;
+        if let Some(user_data) = users.get_mut(&irc_lowercase(&user.nickname)) {
+            user_data.invisible = invisible;
+        }
+        user.invisible = invisible;
+
+        let user_mode = Message {
+            tags: None,
+            prefix: Some(user.nickname.clone()),
+            command: USER_MODE.to_string(),
+            params: vec![vec![user.nickname.clone()], vec![mode]],
+        };
+        sender.send(user_mode).map_err(|_| -> ServerError {
+            ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "Could not send".to_string(),
+            }
+        })?;
+    }
+
+    Ok(Some(NumericReply::new(
+        RPL_UMODEIS_NUM,
+        "",
+        Some(vec![user.modes()]),
+    )))
+}
+
+/*******************************SILENCE MESSAGE***********************************/
+
+///
+/// Handles SILENCE command. Each parameter adds (+nickname) or removes (-nickname) a user
+/// from the silence list, the server won't deliver messages from silenced users.
+/// If no parameter is received the silence list is sent with RPL_SILELIST for each
+/// silenced user and RPL_ENDOFSILELIST.
+///
+/// Command: SILENCE
+/// Parameters: [(+|-)<nickname> *( "," (+|-)<nickname> )]
+///
+pub fn handle_silence(
+    message: Message,
+    user: &mut User,
+    users: Arc<Mutex<HashMap<String, User>>>,
+    mut stream: &TcpStream,
+) -> Result<Option<NumericReply>, ServerError> {
+    let mut users = users.lock().map_err(|_| -> ServerError {
+        ServerError {
+            kind: NONCRITICAL.to_string(),
+            message: "Could not lock users".to_string(),
+        }
+    })?;
+    let user_data = match users.get_mut(&irc_lowercase(&user.nickname)) {
+        Some(user_data) => user_data,
+        None => {
+            return Err(ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "Could not found user".to_string(),
+            })
+        }
+    };
+
+    if message.params_total_count() == 0 {
+        let mut silenced: Vec<&String> = user_data.silenced.iter().collect();
+        silenced.sort();
+        for nickname in silenced {
+            let args = vec![user.nickname.clone(), nickname.clone()];
+            let reply = NumericReply::new(RPL_SILELIST_NUM, "", Some(args));
+            stream
+                .write_all(reply.as_string().as_bytes())
+                .map_err(|_| -> ServerError {
+                    ServerError {
+                        kind: CRITICAL.to_string(),
+                        message: "Could not write to stream".to_string(),
+                    }
+                })?;
+        }
+        let args = vec![user.nickname.clone()];
+        let answer = NumericReply::new(RPL_ENDOFSILELIST_NUM, RPL_ENDOFSILELIST_MSG, Some(args));
+        return Ok(Some(answer));
+    }
+
+    for param in message.params[0].iter() {
+        match param.strip_prefix('-') {
+            Some(nickname) => user_data.unsilence(nickname),
+            None => user_data.silence(param.strip_prefix('+').unwrap_or(param)),
+        }
+    }
+    user.silenced = user_data.silenced.clone();
+
+    Ok(None)
+}
+
+/*******************************LUSERS MESSAGE***********************************/
+
+///
+/// Handles LUSERS command. The server is the one that knows how many users, channels
+/// and servers there are, so it answers with RPL_LUSERCLIENT, RPL_LUSERCHANNELS and
+/// RPL_LUSERME.
+///
+/// Command: LUSERS
+///
+pub fn handle_lusers(
+    message: Message,
+    sender: &Sender<Message>,
+) -> Result<Option<NumericReply>, ServerError> {
+    sender.send(message).map_err(|_| -> ServerError {
+        ServerError {
+            kind: CRITICAL.to_string(),
+            message: "Could not send to server".to_string(),
+        }
+    })?;
+
+    Ok(None)
+}
+
+/*******************************ISON MESSAGE***********************************/
+
+///
+/// Handles ISON command. The server is the one that knows which users are connected, so it
+/// answers with RPL_ISON. If no nickname is given ERR_NEEDMOREPARAMS is returned.
+///
+/// Command: ISON
+/// Parameters: <nickname> *( SPACE <nickname> )
+///
+pub fn handle_ison(
+    message: Message,
+    sender: &Sender<Message>,
+) -> Result<Option<NumericReply>, ServerError> {
+    if message.params_total_count() == 0 {
+        return Ok(Some(NumericReply::new(
+            ERR_NEEDMOREPARAMS_NUM,
+            ERR_NEEDMOREPARAMS_MSG,
+            Some(vec![message.command]),
+        )));
+    }
+
+    sender.send(message).map_err(|_| -> ServerError {
+        ServerError {
+            kind: CRITICAL.to_string(),
+            message: "Could not send to server".to_string(),
+        }
+    })?;
+
+    Ok(None)
+}
+
+///
+/// Handles WHOWAS command. Only the server keeps the users that quit, so it is the one that
+/// answers. If no nickname is given ERR_NONICKNAMEGIVEN is returned.
+///
+/// Command: WHOWAS
+/// Parameters: <nickname>
+///
+pub fn handle_whowas(
+    message: Message,
+    sender: &Sender<Message>,
+) -> Result<Option<NumericReply>, ServerError> {
+    if message.params_total_count() == 0 {
+        return Ok(Some(NumericReply::new(
+            ERR_NONICKNAMEGIVEN_NUM,
+            ERR_NONICKNAMEGIVEN_MSG,
+            None,
+        )));
+    }
+
+    sender.send(message).map_err(|_| -> ServerError {
+        ServerError {
+            kind: CRITICAL.to_string(),
+            message: "Could not send to server".to_string(),
+        }
+    })?;
+
+    Ok(None)
+}
+
 /************************************TESTS*******************************************/
 
 #[cfg(test)]
 mod tests {
     use std::{
         collections::HashMap,
+        io::{BufRead, BufReader},
         net::{TcpListener, TcpStream},
         sync::{
             mpsc::{self},
@@ -388,17 +733,87 @@ mod tests {
     };
 
     use crate::{
-        commands::NICK,
+        commands::{ISON, MODE, NICK, SILENCE, USER_MODE, WHO, WHOWAS},
         message::Message,
         numeric_reply::{
-            NumericReply, ERR_NONICKNAMEGIVEN_MSG, ERR_NONICKNAMEGIVEN_NUM, ERR_NOSUCHNICK_MSG,
-            ERR_NOSUCHNICK_NUM, ERR_NOSUCHSERVER_MSG, ERR_NOSUCHSERVER_NUM, RPL_NOWAWAY_MSG,
-            RPL_NOWAWAY_NUM, RPL_UNAWAY_MSG, RPL_UNAWAY_NUM,
+            NumericReply, ERR_NEEDMOREPARAMS_MSG, ERR_NEEDMOREPARAMS_NUM, ERR_NONICKNAMEGIVEN_MSG,
+            ERR_NONICKNAMEGIVEN_NUM, ERR_NOSUCHNICK_MSG, ERR_NOSUCHNICK_NUM, ERR_NOSUCHSERVER_MSG,
+            ERR_NOSUCHSERVER_NUM, ERR_UMODEUNKNOWNFLAG_MSG, ERR_UMODEUNKNOWNFLAG_NUM,
+            ERR_USERSDONTMATCH_MSG, ERR_USERSDONTMATCH_NUM, RPL_ENDOFSILELIST_MSG,
+            RPL_ENDOFSILELIST_NUM, RPL_ENDOFWHO_NUM, RPL_NOWAWAY_MSG, RPL_NOWAWAY_NUM,
+            RPL_SILELIST_NUM, RPL_UMODEIS_NUM, RPL_UNAWAY_MSG, RPL_UNAWAY_NUM,
         },
-        server_utils::user::User,
+        server_utils::{channel::Channel, user::User},
+    };
+
+    use super::{
+        handle_away, handle_ison, handle_silence, handle_who, handle_whowas, set_user_mode,
+        visible_users, whois,
     };
 
-    use super::{handle_away, whois};
+    #[test]
+    fn test_handle_ison_without_nicknames_needs_more_params() {
+        let (sender, receiver) = mpsc::channel();
+        let message = Message {
+            tags: None,
+            prefix: Some("test".to_string()),
+            command: ISON.to_string(),
+            params: vec![],
+        };
+
+        let reply = handle_ison(message, &sender).unwrap();
+        assert_eq!(
+            reply,
+            Some(NumericReply::new(
+                ERR_NEEDMOREPARAMS_NUM,
+                ERR_NEEDMOREPARAMS_MSG,
+                Some(vec![ISON.to_string()])
+            ))
+        );
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_handle_ison_is_answered_by_the_server() {
+        let (sender, receiver) = mpsc::channel();
+        let message = Message {
+            tags: None,
+            prefix: Some("test".to_string()),
+            command: ISON.to_string(),
+            params: vec![vec!["ari".to_string()], vec!["marce".to_string()]],
+        };
+
+        let reply = handle_ison(message.clone(), &sender).unwrap();
+        assert!(reply.is_none());
+        assert_eq!(receiver.try_recv().unwrap(), message);
+    }
+
+    #[test]
+    fn test_handle_whowas_needs_a_nickname_and_is_answered_by_the_server() {
+        let (sender, receiver) = mpsc::channel();
+        let mut message = Message {
+            tags: None,
+            prefix: Some("test".to_string()),
+            command: WHOWAS.to_string(),
+            params: vec![],
+        };
+
+        let reply = handle_whowas(message.clone(), &sender).unwrap();
+        assert_eq!(
+            reply,
+            Some(NumericReply::new(
+                ERR_NONICKNAMEGIVEN_NUM,
+                ERR_NONICKNAMEGIVEN_MSG,
+                None
+            ))
+        );
+        assert!(receiver.try_recv().is_err());
+
+        message.params = vec![vec!["ari".to_string()]];
+        let reply = handle_whowas(message.clone(), &sender).unwrap();
+        assert!(reply.is_none());
+        assert_eq!(receiver.try_recv().unwrap(), message);
+    }
 
     #[test]
     fn test_handle_away_no_message() {
@@ -413,6 +828,7 @@ mod tests {
         );
 
         let message = Message {
+            tags: None,
             prefix: None,
             command: NICK.to_string(),
             params: vec![vec![]],
@@ -444,6 +860,7 @@ mod tests {
         );
 
         let message = Message {
+            tags: None,
             prefix: None,
             command: NICK.to_string(),
             params: vec![vec!["I went to sleep".to_string()]],
@@ -476,6 +893,7 @@ mod tests {
         );
 
         let message = Message {
+            tags: None,
             prefix: None,
             command: NICK.to_string(),
             params: vec![vec![]],
@@ -512,6 +930,7 @@ mod tests {
         );
 
         let message = Message {
+            tags: None,
             prefix: None,
             command: NICK.to_string(),
             params: vec![vec!["hola".to_string()]],
@@ -552,6 +971,7 @@ mod tests {
         );
 
         let message = Message {
+            tags: None,
             prefix: None,
             command: NICK.to_string(),
             params: vec![vec!["server".to_string()], vec![nickname_expected.clone()]],
@@ -594,6 +1014,7 @@ mod tests {
         );
 
         let message = Message {
+            tags: None,
             prefix: None,
             command: NICK.to_string(),
             params: vec![vec![nickname_expected.clone()]],
@@ -614,4 +1035,250 @@ mod tests {
         let reply = whois(message, &stream, users, &sender, &receiver, channels).unwrap();
         assert!(reply.is_none());
     }
+
+    #[test]
+    fn test_whois_shows_the_idle_and_signon_times_of_users_connected_here() {
+        let mut user = User::new(
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        user.sign_on();
+        let signon = user.signon.unwrap();
+        let users = Arc::new(Mutex::new(HashMap::from([("test".to_string(), user)])));
+        let (sender, receiver) = mpsc::channel();
+
+        let listener = TcpListener::bind("127.0.0.1:5013").unwrap();
+        let stream = TcpStream::connect("127.0.0.1:5013").unwrap();
+        let (received_stream, _) = listener.accept().unwrap();
+        let message = Message {
+            tags: None,
+            prefix: None,
+            command: NICK.to_string(),
+            params: vec![vec!["test".to_string()]],
+        };
+        sender.send(message.clone()).unwrap();
+        sender.send(message.clone()).unwrap();
+        let channels = Arc::new(Mutex::new(HashMap::new()));
+        let reply = whois(message, &stream, users, &sender, &receiver, channels).unwrap();
+        assert!(reply.is_none());
+        drop(stream);
+
+        let replies: Vec<String> = BufReader::new(received_stream)
+            .lines()
+            .map(|line| line.unwrap())
+            .collect();
+        assert!(replies.contains(&format!("317 test 0 {} :seconds idle, signon time", signon)));
+    }
+
+    #[test]
+    fn test_handle_silence_adds_removes_and_lists_silenced_users() {
+        let nickname_expected = "test".to_string();
+        let mut user = User::new(
+            nickname_expected.clone(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "test".to_string(),
+            "password".to_string(),
+        );
+        let users = Arc::new(Mutex::new(HashMap::from([(
+            nickname_expected.clone(),
+            user.clone(),
+        )])));
+        let listener = TcpListener::bind("127.0.0.1:5004").unwrap();
+        let stream = TcpStream::connect("127.0.0.1:5004").unwrap();
+        let (received_stream, _) = listener.accept().unwrap();
+
+        let message = Message {
+            tags: None,
+            prefix: None,
+            command: SILENCE.to_string(),
+            params: vec![vec![
+                "+spammer".to_string(),
+                "flooder".to_string(),
+                "+friend".to_string(),
+            ]],
+        };
+        let reply = handle_silence(message, &mut user, users.clone(), &stream).unwrap();
+        assert!(reply.is_none());
+
+        let message = Message {
+            tags: None,
+            prefix: None,
+            command: SILENCE.to_string(),
+            params: vec![vec!["-friend".to_string()]],
+        };
+        let reply = handle_silence(message, &mut user, users.clone(), &stream).unwrap();
+        assert!(reply.is_none());
+
+        // The silence list is updated in the server users too
+        let silenced = users.lock().unwrap().get("test").unwrap().silenced.clone();
+        assert_eq!(silenced, user.silenced);
+        assert!(user.has_silenced("spammer"));
+        assert!(user.has_silenced("flooder"));
+        assert!(!user.has_silenced("friend"));
+
+        let message = Message {
+            tags: None,
+            prefix: None,
+            command: SILENCE.to_string(),
+            params: vec![],
+        };
+        let reply = handle_silence(message, &mut user, users, &stream).unwrap();
+        assert_eq!(
+            reply,
+            Some(NumericReply::new(
+                RPL_ENDOFSILELIST_NUM,
+                RPL_ENDOFSILELIST_MSG,
+                Some(vec![nickname_expected.clone()])
+            ))
+        );
+        drop(stream);
+
+        let replies: Vec<String> = BufReader::new(received_stream)
+            .lines()
+            .map(|line| line.unwrap() + "\r\n")
+            .collect();
+        let expected: Vec<String> = ["flooder", "spammer"]
+            .iter()
+            .map(|nickname| {
+                NumericReply::new(
+                    RPL_SILELIST_NUM,
+                    "",
+                    Some(vec![nickname_expected.clone(), nickname.to_string()]),
+                )
+                .as_string()
+            })
+            .collect();
+        assert_eq!(replies, expected);
+    }
+
+    fn user(nickname: &str) -> User {
+        User::new(
+            nickname.to_string(),
+            "127.0.0.1".to_string(),
+            nickname.to_string(),
+            nickname.to_string(),
+            "test_server".to_string(),
+            "password".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_set_user_mode_only_changes_the_modes_of_the_user() {
+        let mut ari = user("ari");
+        let users = Arc::new(Mutex::new(HashMap::from([(
+            "ari".to_string(),
+            ari.clone(),
+        )])));
+        let (sender, receiver) = mpsc::channel();
+        let mode = |params: Vec<&str>| Message {
+            tags: None,
+            prefix: None,
+            command: MODE.to_string(),
+            params: params.iter().map(|param| vec![param.to_string()]).collect(),
+        };
+
+        let reply = set_user_mode(mode(vec!["lucas", "+i"]), &mut ari, users.clone(), &sender);
+        assert_eq!(
+            reply.unwrap(),
+            Some(NumericReply::new(
+                ERR_USERSDONTMATCH_NUM,
+                ERR_USERSDONTMATCH_MSG,
+                None
+            ))
+        );
+        let reply = set_user_mode(mode(vec!["ARI", "+x"]), &mut ari, users.clone(), &sender);
+        assert_eq!(
+            reply.unwrap(),
+            Some(NumericReply::new(
+                ERR_UMODEUNKNOWNFLAG_NUM,
+                ERR_UMODEUNKNOWNFLAG_MSG,
+                None
+            ))
+        );
+        assert!(receiver.try_recv().is_err());
+
+        let reply = set_user_mode(mode(vec!["ari", "+i"]), &mut ari, users.clone(), &sender);
+        let umodeis = Some(NumericReply::new(
+            RPL_UMODEIS_NUM,
+            "",
+            Some(vec!["+i".to_string()]),
+        ));
+        assert_eq!(reply.unwrap(), umodeis);
+        assert!(ari.invisible);
+        assert!(users.lock().unwrap().get("ari").unwrap().invisible);
+        let user_mode = receiver.try_recv().unwrap();
+        assert_eq!(user_mode.command, USER_MODE);
+        assert_eq!(
+            user_mode.params,
+            vec![vec!["ari".to_string()], vec!["+i".to_string()]]
+        );
+
+        // Without a mode the modes set are answered
+        let reply = set_user_mode(mode(vec!["ari"]), &mut ari, users, &sender);
+        assert_eq!(reply.unwrap(), umodeis);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_invisible_users_are_only_seen_by_users_sharing_a_channel() {
+        let mut ari = user("ari");
+        ari.invisible = true;
+        let mut marce = user("marce");
+        marce.invisible = true;
+        let lucas = user("lucas");
+
+        let mut channel = Channel::new("#rust".to_string(), &ari);
+        channel.join(lucas.clone(), None).unwrap();
+        let channels = Arc::new(Mutex::new(HashMap::from([("#rust".to_string(), channel)])));
+
+        let seen_by = |nickname: &str| -> Vec<String> {
+            let users = vec![ari.clone(), lucas.clone(), marce.clone()];
+            visible_users(users, nickname, channels.clone())
+                .unwrap()
+                .into_iter()
+                .map(|user| user.nickname)
+                .collect()
+        };
+
+        assert_eq!(seen_by("lucas"), vec!["ari", "lucas"]);
+        assert_eq!(seen_by("juanireil"), vec!["lucas"]);
+        assert_eq!(seen_by("Marce"), vec!["lucas", "marce"]);
+    }
+    #[test]
+    fn test_who_operators_leaves_out_an_invisible_operator() {
+        let mut ari = user("ari");
+        ari.invisible = true;
+        let users = Arc::new(Mutex::new(HashMap::from([("ari".to_string(), ari)])));
+        let channels = Arc::new(Mutex::new(HashMap::new()));
+        let message = Message {
+            tags: None,
+            prefix: Some("lucas".to_string()),
+            command: WHO.to_string(),
+            params: vec![vec!["o".to_string()]],
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:4004").unwrap();
+        let stream = TcpStream::connect("127.0.0.1:4004").unwrap();
+        let mut reader = BufReader::new(listener.accept().unwrap().0);
+        let (sender, receiver) = mpsc::channel();
+        // The server answers with the nickname of its operator
+        let mut answer = message.clone();
+        answer.params = vec![vec!["ari".to_string()]];
+        sender.send(answer).unwrap();
+
+        let reply = handle_who(
+            message, &stream, users, channels, "lucas", &receiver, &sender,
+        );
+        assert!(reply.unwrap().is_none());
+
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert!(line.starts_with(RPL_ENDOFWHO_NUM));
+    }
 }
@@ -6,6 +6,7 @@ use crate::custom_errors::server_error::ServerError;
 use crate::{
     custom_errors::errors::{CRITICAL, NONCRITICAL},
     message::Message,
+    parser::irc_lowercase,
     server_utils::user::User,
 };
 use std::{
@@ -32,6 +33,43 @@ pub fn handle_registration_server(
     Ok(())
 }
 
+///
+/// This function is called when a server receives the ERR_NICKCOLLISION of a user registered
+/// with a nickname that was already in use. The server is notified so it tells the user
+///
+pub fn handle_nick_collision_server(
+    message: Message,
+    sender: &Sender<Message>,
+) -> Result<(), ServerError> {
+    sender.send(message).map_err(|_| -> ServerError {
+        ServerError {
+            kind: CRITICAL.to_string(),
+            message: "Couldn't send".to_string(),
+        }
+    })?;
+
+    Ok(())
+}
+
+///
+/// This function is called when a server receives the QUIT of a user of another server, like the
+/// ones the main server sends for the users of a server that left. The server is notified so
+/// the members of the channels of the user are told
+///
+pub fn handle_user_quit_server(
+    message: Message,
+    sender: &Sender<Message>,
+) -> Result<(), ServerError> {
+    sender.send(message).map_err(|_| -> ServerError {
+        ServerError {
+            kind: CRITICAL.to_string(),
+            message: "Couldn't send".to_string(),
+        }
+    })?;
+
+    Ok(())
+}
+
 ///
 /// This function is called when a server receives information about a new registered user
 ///
@@ -49,7 +87,7 @@ pub fn handle_users_info(
         .prefix
         .clone()
         .expect("No prefix in registration message");
-    if !users.contains_key(&nickname) {
+    if !users.contains_key(&irc_lowercase(&nickname)) {
         add_new_user(message, users)?;
     }
     Ok(())
@@ -62,6 +100,12 @@ fn add_new_user(
     message: Message,
     mut users: MutexGuard<HashMap<String, User>>,
 ) -> Result<(), ServerError> {
+    if !message.has_params(&[5, 1]) {
+        return Err(ServerError {
+            kind: NONCRITICAL.to_string(),
+            message: "User information is missing parameters".to_string(),
+        });
+    }
     let user_data = message.params;
     //vec![vec![user.nickname.clone(), user.address.clone(), user.username.clone(), user.server_name.clone(), user.password.clone()], vec![user.real_name.clone()]];
     let user = User::new(
@@ -73,7 +117,7 @@ fn add_new_user(
         user_data[0][4].clone(),
     );
     println!("New user saved {:?}", user);
-    users.insert(user.nickname.clone(), user);
+    users.insert(irc_lowercase(&user.nickname), user);
     Ok(())
 }
 
@@ -98,6 +142,7 @@ mod tests {
         let users = Arc::new(Mutex::new(HashMap::new()));
 
         let message = Message {
+            tags: None,
             prefix: Some("nickname".to_string()),
             command: USERS_INFO.to_string(),
             params: vec![
@@ -120,6 +165,7 @@ mod tests {
         let users = Arc::new(Mutex::new(HashMap::new()));
 
         let message = Message {
+            tags: None,
             prefix: Some("nickname".to_string()),
             command: USERS_INFO.to_string(),
             params: vec![
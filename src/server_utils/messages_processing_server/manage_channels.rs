@@ -5,12 +5,17 @@
 use crate::custom_errors::server_error::ServerError;
 use crate::{
     commands::{
-        MODE_GIVE_OP_PRIVILEGES, MODE_REMOVE_BAN, MODE_REMOVE_INVITE, MODE_REMOVE_KEY,
-        MODE_REMOVE_LIMIT, MODE_REMOVE_OP_TOPIC, MODE_REMOVE_SECRET, MODE_SET_BAN, MODE_SET_INVITE,
-        MODE_SET_KEY, MODE_SET_LIMIT, MODE_SET_OP_TOPIC, MODE_SET_SECRET, MODE_TAKE_OP_PRIVILEGES,
+        MODE_GIVE_OP_PRIVILEGES, MODE_GIVE_VOICE, MODE_REMOVE_BAN, MODE_REMOVE_EXCEPTION,
+        MODE_REMOVE_FLOOD, MODE_REMOVE_INVITE, MODE_REMOVE_KEY, MODE_REMOVE_LIMIT,
+        MODE_REMOVE_MESSAGE_LENGTH, MODE_REMOVE_MODERATED, MODE_REMOVE_NO_EXTERNAL,
+        MODE_REMOVE_OP_TOPIC, MODE_REMOVE_SECRET, MODE_SET_BAN, MODE_SET_EXCEPTION, MODE_SET_FLOOD,
+        MODE_SET_INVITE, MODE_SET_KEY, MODE_SET_LIMIT, MODE_SET_MESSAGE_LENGTH, MODE_SET_MODERATED,
+        MODE_SET_NO_EXTERNAL, MODE_SET_OP_TOPIC, MODE_SET_SECRET, MODE_TAKE_OP_PRIVILEGES,
+        MODE_TAKE_VOICE,
     },
     custom_errors::errors::{CRITICAL, NONCRITICAL},
     message::Message,
+    parser::irc_lowercase,
     server_utils::{channel::Channel, user::User},
 };
 use std::{
@@ -71,7 +76,7 @@ pub fn handle_kick_multiserver(
             message: "Could not access channels".to_string(),
         }
     })?;
-    let channel = match channels.get_mut(channel_name) {
+    let channel = match channels.get_mut(&irc_lowercase(channel_name)) {
         Some(channel) => channel,
         None => {
             return Err(ServerError {
@@ -118,22 +123,29 @@ pub fn handle_channel_info(
 ) -> Result<(), ServerError> {
     println!("Received message with channel info: {:?}", message);
     let channel_name = message.params[0][0].clone();
+    // Users are locked before channels, like in JOIN, so they can't wait for each other
+    let users = users.lock().map_err(|_| -> ServerError {
+        ServerError {
+            kind: NONCRITICAL.to_string(),
+            message: "Could not lock users".to_string(),
+        }
+    })?;
     let mut channels = channels.lock().map_err(|_| -> ServerError {
         ServerError {
             kind: NONCRITICAL.to_string(),
             message: "Could not lock channels".to_string(),
         }
     })?;
-    let channel = match channels.get(&channel_name) {
+    let channel = match channels.get(&irc_lowercase(&channel_name)) {
         Some(channel) => channel.clone(),
         None => {
             println!("Server does not have channel, adding it");
-            Channel::channel_from_message(message, users)?
+            Channel::channel_from_message(message, &users)?
         }
     };
 
     println!("Adding channel: {:?}", channel);
-    channels.insert(channel.name.clone(), channel);
+    channels.insert(irc_lowercase(&channel.name), channel);
 
     Ok(())
 }
@@ -158,7 +170,7 @@ pub fn handle_mode_multiserver(
         }
     })?;
 
-    let user_setting_mode = match users.get_mut(&nickname_user_setting_mode) {
+    let user_setting_mode = match users.get_mut(&irc_lowercase(&nickname_user_setting_mode)) {
         Some(user) => user,
         None => {
             return Err(ServerError {
@@ -182,7 +194,7 @@ pub fn handle_mode_multiserver(
             message: "Could not access channels".to_string(),
         }
     })?;
-    let channel = match channels.get_mut(channel_name) {
+    let channel = match channels.get_mut(&irc_lowercase(channel_name)) {
         Some(channel) => channel,
         None => {
             return Err(ServerError {
@@ -211,6 +223,20 @@ pub fn handle_mode_multiserver(
         MODE_REMOVE_SECRET => channel.remove_secret_status(nickname_user_setting_mode),
         MODE_SET_BAN => channel.set_ban(message, nickname_user_setting_mode),
         MODE_REMOVE_BAN => channel.remove_ban(message, nickname_user_setting_mode),
+        MODE_SET_EXCEPTION => channel.set_exception(message, nickname_user_setting_mode),
+        MODE_REMOVE_EXCEPTION => channel.remove_exception(message, nickname_user_setting_mode),
+        MODE_SET_FLOOD => channel.set_flood_protection(message, nickname_user_setting_mode),
+        MODE_REMOVE_FLOOD => channel.remove_flood_protection(nickname_user_setting_mode),
+        MODE_SET_MESSAGE_LENGTH => {
+            channel.set_max_message_length(message, nickname_user_setting_mode)
+        }
+        MODE_REMOVE_MESSAGE_LENGTH => channel.remove_max_message_length(nickname_user_setting_mode),
+        MODE_SET_NO_EXTERNAL => channel.set_no_external_messages(nickname_user_setting_mode),
+        MODE_REMOVE_NO_EXTERNAL => channel.remove_no_external_messages(nickname_user_setting_mode),
+        MODE_SET_MODERATED => channel.set_moderated(nickname_user_setting_mode),
+        MODE_REMOVE_MODERATED => channel.remove_moderated(nickname_user_setting_mode),
+        MODE_GIVE_VOICE => channel.give_voice(message, nickname_user_setting_mode),
+        MODE_TAKE_VOICE => channel.remove_voice(message, nickname_user_setting_mode),
         &_ => {
             return Err(ServerError {
                 kind: CRITICAL.to_string(),
@@ -229,6 +255,88 @@ pub fn handle_mode_multiserver(
     Ok(())
 }
 
+///
+/// This function is called when a server receives the registration of a multiserver
+/// channel, the server of the operator already checked it so the founder is only saved.
+/// A channel registered by another user keeps its founder.
+///
+pub fn handle_register_multiserver(
+    message: Message,
+    channels: Arc<Mutex<HashMap<String, Channel>>>,
+    users: Arc<Mutex<HashMap<String, User>>>,
+    sender: &Sender<Message>,
+    server_name: String,
+) -> Result<(), ServerError> {
+    let nickname_user_registering = match &message.prefix {
+        Some(nickname) => nickname.clone(),
+        None => {
+            return Err(ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "No prefix in register message".to_string(),
+            })
+        }
+    };
+    let channel_name = match message.params.first().and_then(|param| param.first()) {
+        Some(channel_name) => channel_name.clone(),
+        None => {
+            return Err(ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "No channel in register message".to_string(),
+            })
+        }
+    };
+    let users = users.lock().map_err(|_| -> ServerError {
+        ServerError {
+            kind: NONCRITICAL.to_string(),
+            message: "Could not access users".to_string(),
+        }
+    })?;
+
+    match users.get(&irc_lowercase(&nickname_user_registering)) {
+        Some(user) if user.server_name == server_name => {
+            // channel already registered
+            return Ok(());
+        }
+        Some(_) => {}
+        None => {
+            return Err(ServerError {
+                kind: CRITICAL.to_string(),
+                message: "user not found".to_string(),
+            })
+        }
+    }
+
+    let mut channels = channels.lock().map_err(|_| -> ServerError {
+        ServerError {
+            kind: NONCRITICAL.to_string(),
+            message: "Could not access channels".to_string(),
+        }
+    })?;
+    match channels.get_mut(&irc_lowercase(&channel_name)) {
+        Some(channel)
+            if channel.is_registered() && !channel.is_founder(&nickname_user_registering) =>
+        {
+            return Ok(())
+        }
+        Some(channel) => channel.founder = Some(nickname_user_registering),
+        None => {
+            return Err(ServerError {
+                kind: CRITICAL.to_string(),
+                message: "channel not found".to_string(),
+            })
+        }
+    }
+
+    sender.send(message).map_err(|_| -> ServerError {
+        ServerError {
+            kind: CRITICAL.to_string(),
+            message: "Could not send message".to_string(),
+        }
+    })?;
+
+    Ok(())
+}
+
 ///
 /// This function is called when a server receives a part from another server.
 ///
@@ -266,6 +374,24 @@ pub fn handle_topic(
     Ok(())
 }
 
+///
+/// This function is called when a server receives that a channel was closed in another server.
+/// It will send it to the main thread so that the channel is removed.
+///
+pub fn handle_close_multiserver(
+    message: Message,
+    sender: &Sender<Message>,
+) -> Result<(), ServerError> {
+    sender.send(message).map_err(|_| -> ServerError {
+        ServerError {
+            kind: CRITICAL.to_string(),
+            message: "Broken channel connection".to_string(),
+        }
+    })?;
+
+    Ok(())
+}
+
 ///
 /// This is called when a server receives an invite message from another server, it will check  and send it to the main thread.
 ///
@@ -282,7 +408,7 @@ pub fn handle_invite_multiserver(
             message: "Could not access channels".to_string(),
         }
     })?;
-    let channel = match channels.get_mut(channel_name) {
+    let channel = match channels.get_mut(&irc_lowercase(channel_name)) {
         Some(channel) => channel,
         None => {
             return Err(ServerError {
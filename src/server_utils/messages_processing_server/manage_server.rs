@@ -47,3 +47,20 @@ pub fn handle_squit(
     }
     Ok(())
 }
+
+///
+/// This function is called when a server receives a WALLOPS from another server.
+/// It will send it to the main thread so that it reaches the operator of this server.
+///
+pub fn handle_wallops_server(
+    message: Message,
+    sender: &Sender<Message>,
+) -> Result<(), ServerError> {
+    sender.send(message).map_err(|_| -> ServerError {
+        ServerError {
+            kind: CRITICAL.to_string(),
+            message: "Could not send message".to_string(),
+        }
+    })?;
+    Ok(())
+}
@@ -0,0 +1,185 @@
+//!
+//! The rate limit protects the server from users that send too many messages. Every user has a
+//! bucket with a message for each token, the bucket refills with time and the messages that
+//! arrive when it is empty are dropped
+//!
+
+use std::time::Instant;
+
+use crate::commands::{DCC_ACCEPT, DCC_CHAT, DCC_CLOSE, DCC_RESUME, DCC_SEND, PING, PONG, PRIVMSG};
+use crate::message::Message;
+
+// Messages a user can send at once when the server data file has no rate limit
+pub const DEFAULT_MESSAGES_BURST: u32 = 20;
+// Messages per second that refill the bucket when the server data file has no rate limit
+pub const DEFAULT_MESSAGES_PER_SECOND: u32 = 5;
+
+// Requests sent inside a PRIVMSG to negotiate a DCC connection
+const DCC_COMMANDS: [&str; 5] = [DCC_CHAT, DCC_SEND, DCC_RESUME, DCC_ACCEPT, DCC_CLOSE];
+
+///
+/// How many messages a user can send at once and how many per second after that
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageRate {
+    pub burst: u32,
+    pub per_second: u32,
+}
+
+impl Default for MessageRate {
+    fn default() -> Self {
+        MessageRate {
+            burst: DEFAULT_MESSAGES_BURST,
+            per_second: DEFAULT_MESSAGES_PER_SECOND,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RateLimitAction {
+    Accept,
+    // The message is dropped and the user must be told, only the first one dropped in a row is warned
+    Warn,
+    Drop,
+}
+
+#[derive(Debug)]
+pub struct RateLimit {
+    rate: MessageRate,
+    tokens: f64,
+    last_refill: Instant,
+    warned: bool,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        RateLimit::new(MessageRate::default(), Instant::now())
+    }
+}
+
+impl RateLimit {
+    ///
+    /// Creates a new RateLimit with the bucket full
+    ///
+    pub fn new(rate: MessageRate, now: Instant) -> Self {
+        RateLimit {
+            rate,
+            tokens: rate.burst as f64,
+            last_refill: now,
+            warned: false,
+        }
+    }
+
+    ///
+    /// Takes a token for a message received and returns what has to be done with it
+    ///
+    pub fn check(&mut self, now: Instant) -> RateLimitAction {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * self.rate.per_second as f64).min(self.rate.burst as f64);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            self.warned = false;
+            return RateLimitAction::Accept;
+        }
+
+        match self.warned {
+            true => RateLimitAction::Drop,
+            false => {
+                self.warned = true;
+                RateLimitAction::Warn
+            }
+        }
+    }
+}
+
+///
+/// Returns true if the message counts against the rate limit. Keepalive messages and the
+/// negotiation of DCC connections go through even if the user is sending too many messages
+///
+pub fn is_rate_limited(message: &Message) -> bool {
+    match message.command.as_str() {
+        PING | PONG => false,
+        PRIVMSG => !is_dcc_negotiation(message),
+        _ => true,
+    }
+}
+
+fn is_dcc_negotiation(message: &Message) -> bool {
+    let text = match message.params.get(1).and_then(|text| text.first()) {
+        Some(text) => text,
+        None => return false,
+    };
+    let request = text.split_whitespace().next().unwrap_or_default();
+    DCC_COMMANDS.contains(&request)
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::{is_rate_limited, MessageRate, RateLimit, RateLimitAction};
+    use crate::commands::{PONG, PRIVMSG};
+    use crate::message::Message;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn rate_limit_drops_messages_over_the_burst_and_warns_once() {
+        let start = Instant::now();
+        let rate = MessageRate {
+            burst: 3,
+            per_second: 1,
+        };
+        let mut rate_limit = RateLimit::new(rate, start);
+
+        for _ in 0..3 {
+            assert_eq!(rate_limit.check(start), RateLimitAction::Accept);
+        }
+        assert_eq!(rate_limit.check(start), RateLimitAction::Warn);
+        assert_eq!(rate_limit.check(start), RateLimitAction::Drop);
+
+        // A second later there is a token again, and a new flood is warned again
+        let later = start + Duration::from_secs(1);
+        assert_eq!(rate_limit.check(later), RateLimitAction::Accept);
+        assert_eq!(rate_limit.check(later), RateLimitAction::Warn);
+    }
+
+    #[test]
+    fn rate_limit_never_drops_messages_at_typing_speed() {
+        let start = Instant::now();
+        let mut rate_limit = RateLimit::new(MessageRate::default(), start);
+
+        for second in 0..120 {
+            assert_eq!(
+                rate_limit.check(start + Duration::from_secs(second)),
+                RateLimitAction::Accept
+            );
+        }
+    }
+
+    #[test]
+    fn dcc_negotiation_and_pongs_are_not_rate_limited() {
+        let privmsg = |text: &str| Message {
+            tags: None,
+            prefix: None,
+            command: PRIVMSG.to_string(),
+            params: vec![vec!["ari".to_string()], vec![text.to_string()]],
+        };
+        let pong = Message {
+            tags: None,
+            prefix: None,
+            command: PONG.to_string(),
+            params: vec![vec!["main_server".to_string()]],
+        };
+
+        assert!(is_rate_limited(&privmsg("hola")));
+        assert!(!is_rate_limited(&privmsg(
+            "DCC_SEND photo.png 127.0.0.1 5000 10"
+        )));
+        assert!(!is_rate_limited(&pong));
+    }
+}
@@ -4,8 +4,11 @@
 //!
 
 use crate::{
-    commands::SERVER_EXISTS, custom_errors::errors::CRITICAL, message::Message,
-    server_utils::connection_handler::ConnectionHandler,
+    commands::SERVER_EXISTS,
+    custom_errors::errors::CRITICAL,
+    message::Message,
+    server_utils::{connection_handler::ConnectionHandler, server_data::parse_bind_address},
+    tls,
 };
 use std::{
     collections::HashMap,
@@ -17,7 +20,16 @@ use std::{
     thread::{self, JoinHandle},
 };
 
-use super::{channel::Channel, server_rol::ServerRol, user::User};
+use super::{
+    acknowledgements::Acknowledgements,
+    channel::{now_in_seconds, Channel},
+    logger::Logger,
+    rate_limit::MessageRate,
+    server_data::{PasswordPolicy, DEFAULT_MAX_CHANNELS_PER_JOIN, DEFAULT_MAX_NICKNAME_LENGTH},
+    server_rol::ServerRol,
+    user::User,
+    welcome::{Welcome, SERVER_VERSION},
+};
 use crate::custom_errors::server_error::ServerError;
 
 pub struct SecondaryServer {
@@ -28,7 +40,7 @@ pub struct SecondaryServer {
 
 impl SecondaryServer {
     ///
-    /// Creates a Secondary Server. Connects to main server
+    /// Creates a Secondary Server. Connects to main server, with TLS if use_tls is true
     ///
     pub fn new(
         users: Arc<Mutex<HashMap<String, User>>>,
@@ -36,6 +48,8 @@ impl SecondaryServer {
         channels: Arc<Mutex<HashMap<String, Channel>>>,
         server_name: String,
         main_server_data: (String, String),
+        use_tls: bool,
+        logger: Logger,
     ) -> Result<Self, ServerError> {
         let main_server_data = connect_to_main_server(
             users,
@@ -43,6 +57,8 @@ impl SecondaryServer {
             channels,
             server_name,
             main_server_data,
+            use_tls,
+            logger,
         )?;
 
         let secondary_server = SecondaryServer {
@@ -112,6 +128,21 @@ impl ServerRol for SecondaryServer {
         Ok(())
     }
 
+    ///
+    /// Returns the amount of servers connected, a secondary server is only connected to the main one
+    ///
+    fn servers_count(&self) -> usize {
+        1
+    }
+
+    ///
+    /// Returns true for every server, the other servers are reached through the main one and
+    /// only the main server knows which of them are connected
+    ///
+    fn is_server_connected(&self, _server_name: &str) -> bool {
+        true
+    }
+
     ///
     /// Will send the message to the main server unless it is the server that is told to skip
     ///
@@ -141,18 +172,24 @@ fn connect_to_main_server(
     channels: Arc<Mutex<HashMap<String, Channel>>>,
     server_name: String,
     main_server_data: (String, String),
+    use_tls: bool,
+    logger: Logger,
 ) -> Result<(String, JoinHandle<()>, Sender<Message>), ServerError> {
     println!("Connecting to main server{:?}", main_server_data);
     let address = main_server_data.1;
     println!("Connecting to main server in addres:{:?}", address);
 
     // Connect to address
-    let stream = TcpStream::connect(address).map_err(|_| -> ServerError {
+    let stream = TcpStream::connect(&address).map_err(|_| -> ServerError {
         ServerError {
             kind: CRITICAL.to_string(),
             message: "Couldn't connect to main server".to_string(),
         }
     })?;
+    let stream = match use_tls {
+        true => connect_with_tls(stream, &address)?,
+        false => stream,
+    };
 
     // Communication channel from secondary server to main server handler thread
     let (sender_to_handler, receiver_from_server): (Sender<Message>, Receiver<Message>) =
@@ -165,8 +202,22 @@ fn connect_to_main_server(
         sender_to_server,
         receiver: receiver_from_server,
         channels,
-        server_name,
         sender_to_read_new_connections: None,
+        max_channels_per_join: DEFAULT_MAX_CHANNELS_PER_JOIN,
+        motd_file_path: "none".to_string(),
+        password_policy: PasswordPolicy::default(),
+        max_nickname_length: DEFAULT_MAX_NICKNAME_LENGTH,
+        message_rate: MessageRate::default(),
+        away_on_join: false,
+        // Users don't login through the connection to the main server
+        welcome: Welcome {
+            server_name: server_name.clone(),
+            version: SERVER_VERSION.to_string(),
+            created_at: now_in_seconds(),
+        },
+        server_name,
+        logger,
+        acknowledgements: Acknowledgements::default(),
     };
 
     let handle = thread::spawn(move || {
@@ -181,3 +232,18 @@ fn connect_to_main_server(
 
     Ok((main_server_data.0, handle, sender_to_handler))
 }
+
+///
+/// Does the TLS handshake with the main server, its certificate must be valid for its ip
+///
+fn connect_with_tls(stream: TcpStream, address: &str) -> Result<TcpStream, ServerError> {
+    let host = parse_bind_address(address)?.ip().to_string();
+    tls::client_config()
+        .and_then(|config| tls::connect(stream, &host, config))
+        .map_err(|error| -> ServerError {
+            ServerError {
+                kind: CRITICAL.to_string(),
+                message: format!("Couldn't connect to main server with TLS: {}", error),
+            }
+        })
+}
@@ -5,14 +5,15 @@
 //!
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     result::Result,
     string::String,
     sync::{
-        mpsc::{self, Receiver, Sender},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
         MutexGuard, {Arc, Mutex},
     },
     thread::{self, JoinHandle},
+    time::SystemTime,
 };
 
 use super::user::User;
@@ -21,19 +22,90 @@ use super::{
     server_rol::ServerRol,
 };
 use crate::message::Message;
+use crate::parser::{irc_eq, irc_lowercase};
+use crate::tls;
 use crate::{
     commands::AWAY,
     custom_errors::server_error::ServerError,
-    server_utils::{connection_listener::ConnectionListener, server_data::add_user},
+    server_utils::{
+        connection_limit::OpenConnections,
+        connection_listener::{ConnectionListener, ConnectionSettings},
+        logger::Logger,
+        server_data::{oper_password, save_channels, PendingUsers, USERS_FLUSH_INTERVAL},
+        watchdog::{Heartbeat, Watchdog, WATCHDOG_THRESHOLD},
+    },
 };
 use crate::{
     commands::{
-        INVITE, IS_OPERATOR, JOIN, KICK, MODE, NOTICE, OPERATOR, PART, PRIVMSG, QUIT, REGISTRATION,
-        SERVER, SERVER_EXISTS, SQUIT, TOPIC, USERS_INFO, WHO, WHOIS,
+        CLOSE, INVITE, ISON, IS_OPERATOR, JOIN, KICK, KNOCK, LUSERS, MODE, NOTICE, OPERATOR, PART,
+        PRIVMSG, QUIT, REGISTER, REGISTRATION, REHASH, SAVE_CHANNELS, SERVER, SERVER_EXISTS,
+        SHUTDOWN, SQUIT, TOPIC, USERS_INFO, USER_MODE, WALLOPS, WHO, WHOIS, WHOWAS,
     },
     custom_errors::errors::{CRITICAL, NONCRITICAL},
+    numeric_reply::{
+        ERR_NICKCOLLISION_MSG, ERR_NICKCOLLISION_NUM, ERR_NOSUCHNICK_MSG, ERR_NOSUCHNICK_NUM,
+        ERR_WASNOSUCHNICK_MSG, ERR_WASNOSUCHNICK_NUM, RPL_AWAY_NUM, RPL_ENDOFWHOWAS_MSG,
+        RPL_ENDOFWHOWAS_NUM, RPL_ISON_NUM, RPL_LUSERCHANNELS_MSG, RPL_LUSERCHANNELS_NUM,
+        RPL_LUSERCLIENT_NUM, RPL_LUSERME_NUM, RPL_WHOWASUSER_NUM,
+    },
     server_utils::channel::Channel,
 };
+use rustls::ServerConfig;
+
+// Reason of the QUIT sent to the users when the server stops
+pub const SHUTDOWN_MESSAGE: &str = "Server shutting down";
+
+///
+/// The last users that quit, kept for WHOWAS. When it is full the oldest user is forgotten
+///
+#[derive(Debug)]
+pub struct WhowasHistory {
+    capacity: usize,
+    users: VecDeque<User>,
+}
+
+impl WhowasHistory {
+    ///
+    /// Creates an empty history that keeps at most capacity users
+    ///
+    pub fn new(capacity: usize) -> Self {
+        WhowasHistory {
+            capacity,
+            users: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    ///
+    /// Adds a user that quit, forgetting the oldest ones if there is no room for it
+    ///
+    pub fn record(&mut self, user: User) {
+        while self.users.len() >= self.capacity.max(1) {
+            self.users.pop_front();
+        }
+        self.users.push_back(user);
+    }
+
+    ///
+    /// Changes the amount of users kept, forgetting the oldest ones if there are more
+    ///
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.users.len() > self.capacity.max(1) {
+            self.users.pop_front();
+        }
+    }
+
+    ///
+    /// Returns the users that used the nickname, from the newest to the oldest
+    ///
+    pub fn find(&self, nickname: &str) -> Vec<&User> {
+        self.users
+            .iter()
+            .rev()
+            .filter(|user| irc_eq(&user.nickname, nickname))
+            .collect()
+    }
+}
 
 pub struct Server {
     // initial data from server
@@ -55,6 +127,16 @@ pub struct Server {
     channels: Arc<Mutex<HashMap<String, Channel>>>,
     // server users
     users: Arc<Mutex<HashMap<String, User>>>,
+    // registered users not yet saved in the users file
+    pending_users: PendingUsers,
+    // bumped every time messages are checked, so the watchdog knows the server is not stuck
+    heartbeat: Heartbeat,
+    // users that quit, for WHOWAS
+    whowas_history: WhowasHistory,
+    // writes what the server does to the log file
+    logger: Logger,
+    // settings given to the new connections, changed by REHASH
+    connection_settings: Arc<Mutex<ConnectionSettings>>,
 }
 
 impl Server {
@@ -62,24 +144,32 @@ impl Server {
     /// Creates a new Server with a received configuration. Initializes the channels and clients connected.
     ///
     pub fn new(server_data: ServerData) -> Result<Self, ServerError> {
+        // The address is checked before anything else so a malformed one is reported clearly
+        server_data.bind_address()?;
+
         // Communication channel from client handler thread to server thread
         let (sender_to_server, receiver_from_handler): (Sender<Message>, Receiver<Message>) =
             mpsc::channel();
         let users = Arc::new(Mutex::new(server_data.users.clone()));
-        let channels = Arc::new(Mutex::new(HashMap::new()));
+        let channels = Arc::new(Mutex::new(server_data.channels.clone()));
         let users_clients = Arc::new(Mutex::new(HashMap::new()));
         let server_clients = Arc::new(Mutex::new(HashMap::new()));
+        let pending_users = PendingUsers::new(server_data.users_file_path.clone());
+        let whowas_history = WhowasHistory::new(server_data.whowas_history_size);
+        let logger = Logger::new(server_data.log_file_path.as_deref(), server_data.log_level)?;
+        let connection_settings = Arc::new(Mutex::new(ConnectionSettings::new(&server_data)));
 
         let server_rol: Box<dyn ServerRol> = if server_data.is_main() {
             Box::new(MainServer::new())
         } else {
-            println!("{:?}", server_data);
             let secondary_server = SecondaryServer::new(
                 users.clone(),
                 sender_to_server.clone(),
                 channels.clone(),
                 server_data.server_name.clone(),
                 server_data.main.clone().unwrap(),
+                server_data.tls_files.is_some(),
+                logger.clone(),
             )?;
             Box::new(secondary_server)
         };
@@ -94,6 +184,11 @@ impl Server {
             receiver_from_handler,
             operator: "".to_string(),
             server_rol,
+            pending_users,
+            heartbeat: Heartbeat::new(),
+            whowas_history,
+            logger,
+            connection_settings,
         })
     }
 
@@ -102,7 +197,14 @@ impl Server {
     /// For each new connection it launches a new thread and saves the user and the channel sender in a vector
     ///
     pub fn run(mut self) -> Result<(), ServerError> {
-        println!("Server running");
+        self.logger.info(
+            "",
+            "",
+            &format!(
+                "Server {} running on {}",
+                self.server_data.server_name, self.server_data.server_address
+            ),
+        );
 
         let mut connection_listener = ConnectionListener {
             address: self.server_data.server_address.clone(),
@@ -112,58 +214,164 @@ impl Server {
             server_clients: self.server_clients.clone(),
             server_name: self.server_data.server_name.clone(),
             sender_to_server: self.sender_to_server.clone(),
+            settings: self.connection_settings.clone(),
+            open_connections: OpenConnections::default(),
+            tls_config: self.tls_config()?,
+            logger: self.logger.clone(),
         };
 
-        let _ = thread::spawn(move || match connection_listener.read_new_connections() {
-            Ok(_) => {
-                println!("No new connections");
-                Ok(())
-            }
-            Err(err) => Err(err),
-        });
+        // Binding here lets the error reach the caller instead of getting lost in the thread
+        let listener = connection_listener.bind()?;
+
+        let _ = thread::spawn(
+            move || match connection_listener.read_new_connections(listener) {
+                Ok(_) => {
+                    println!("No new connections");
+                    Ok(())
+                }
+                Err(err) => Err(err),
+            },
+        );
+
+        let watchdog = Watchdog::new(&self.heartbeat, WATCHDOG_THRESHOLD)
+            .watch_lock("users", &self.users)
+            .watch_lock("channels", &self.channels)
+            .watch_lock("users clients", &self.users_clients)
+            .watch_lock("server clients", &self.server_clients);
+        let _ = thread::spawn(move || watchdog.run());
+
+        self.handle_stop_signals();
 
         loop {
             if let Err(err) = self.check_messages() {
                 if err.kind == CRITICAL {
+                    self.logger.error("", "", &err.message);
+                    self.save_pending_users().ok();
                     return Err(err);
                 }
+                // The users were told and their threads finished, nothing is left to do
+                if err.kind == SHUTDOWN {
+                    return Ok(());
+                }
             }
         }
     }
 
+    ///
+    /// Makes Ctrl-C and SIGTERM send a SHUTDOWN to the server thread, so the users are told
+    /// before the process exits. Only one server of the process can handle them, the rest
+    /// just stop with the process
+    ///
+    fn handle_stop_signals(&self) {
+        let sender_to_server = self.sender_to_server.clone();
+        let result = ctrlc::set_handler(move || {
+            sender_to_server
+                .send(Message {
+                    tags: None,
+                    prefix: None,
+                    command: SHUTDOWN.to_string(),
+                    params: vec![],
+                })
+                .ok();
+        });
+        if let Err(error) = result {
+            self.logger.warn(
+                "",
+                SHUTDOWN,
+                &format!("Couldn't handle the signals to stop: {}", error),
+            );
+        }
+    }
+
+    ///
+    /// Returns the TLS configuration if the server data has a certificate and a private key.
+    /// Without them connections are not encrypted, which is only safe inside the same machine
+    ///
+    fn tls_config(&self) -> Result<Option<Arc<ServerConfig>>, ServerError> {
+        let (certificate_path, key_path) = match &self.server_data.tls_files {
+            Some(tls_files) => tls_files,
+            None => {
+                self.logger.warn(
+                    "",
+                    "",
+                    &format!(
+                        "TLS is not configured, passwords are sent to {} without encryption",
+                        self.server_data.server_address
+                    ),
+                );
+                return Ok(None);
+            }
+        };
+
+        let config =
+            tls::server_config(certificate_path, key_path).map_err(|error| -> ServerError {
+                ServerError {
+                    kind: CRITICAL.to_string(),
+                    message: format!("Couldn't load the TLS certificate and key: {}", error),
+                }
+            })?;
+        Ok(Some(config))
+    }
+
     /// This function checks if there are messages from the clients and if there are
     /// it will check what to do with them. Right now it is a send so it sends it to
     /// the client
     fn check_messages(&mut self) -> Result<(), ServerError> {
-        let message = self.receiver_from_handler.recv().map_err(|_| -> ServerError {
-            ServerError {
-                kind: CRITICAL.to_string(),
-                message: "Could not receive from handler".to_string(),
+        self.heartbeat.beat();
+
+        let message = match self
+            .receiver_from_handler
+            .recv_timeout(USERS_FLUSH_INTERVAL)
+        {
+            Ok(message) => message,
+            // Registered users are saved even if no other message arrives
+            Err(RecvTimeoutError::Timeout) => return self.save_pending_users_if_due(),
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(ServerError {
+                    kind: CRITICAL.to_string(),
+                    message: "Could not receive from handler".to_string(),
+                })
             }
-        })?;
+        };
 
-        println!("Received message in SERVER: {:?}", message);
+        let command = message.command.clone();
+        self.logger.info(
+            message.prefix.as_deref().unwrap_or_default(),
+            &command,
+            "Message received",
+        );
 
-        match message.command.as_str() {
+        match command.as_str() {
             REGISTRATION => self.handle_registration(message),
             SERVER => self.handle_server(message),
             PRIVMSG => self.handle_private_message(message),
             NOTICE => self.handle_notice(message),
             JOIN => self.handle_join(message),
             INVITE => self.handle_invite(message),
+            KNOCK => self.handle_knock(message),
             OPERATOR => self.handle_operator(message),
             WHO => self.handle_who(message),
             QUIT => self.handle_quit(message),
             WHOIS => self.handle_whois(message),
+            LUSERS => self.handle_lusers(message),
+            ISON => self.handle_ison(message),
+            WHOWAS => self.handle_whowas(message),
             SQUIT => self.handle_squit(message),
             KICK => self.handle_kick(message),
             USERS_INFO => self.handle_users_info(message),
             SERVER_EXISTS => self.handle_server_exists(message),
             IS_OPERATOR => self.handle_is_operator(message),
-            MODE => self.handle_mode(message),
+            MODE | REGISTER => self.handle_mode(message),
             PART => self.handle_part(message),
             TOPIC => self.handle_topic(message),
             AWAY => self.handle_away(message),
+            USER_MODE => self.handle_user_mode(message),
+            CLOSE => self.handle_close(message),
+            WALLOPS => self.handle_wallops(message),
+            REHASH => self.handle_rehash(message),
+            ERR_NICKCOLLISION_NUM => self.handle_nick_collision(message),
+            SAVE_CHANNELS => Ok(()),
+            SHUTDOWN => self.handle_shutdown(),
             &_ => {
                 return Err(ServerError {
                     kind: "Message".to_string(),
@@ -171,6 +379,23 @@ impl Server {
                 })
             }
         }?;
+
+        // Channels file is rewritten after every command that could change a channel
+        if [
+            JOIN,
+            MODE,
+            TOPIC,
+            PART,
+            KICK,
+            INVITE,
+            CLOSE,
+            REGISTER,
+            SAVE_CHANNELS,
+        ]
+        .contains(&command.as_str())
+        {
+            self.save_channels()?;
+        }
         Ok(())
     }
 
@@ -192,7 +417,7 @@ impl Server {
             }
         })?;
 
-        let user = match users.get_mut(nickname) {
+        let user = match users.get_mut(&irc_lowercase(nickname)) {
             Some(user) => user,
             None => {
                 return Err(ServerError {
@@ -231,6 +456,44 @@ impl Server {
         Ok(())
     }
 
+    ///
+    /// Handles the modes of a user. If the user is connected to this server the mode was
+    /// already set by its client handler, so all servers are notified. Otherwise it comes
+    /// from another server and the mode is set, the main server notifies the rest of them
+    ///
+    fn handle_user_mode(&mut self, message: Message) -> Result<(), ServerError> {
+        let nickname = &message.params[0][0];
+        let invisible = message.params[1][0] == "+i";
+        let mut users = self.users.lock().map_err(|_| -> ServerError {
+            ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "Could not access users".to_string(),
+            }
+        })?;
+
+        let user = match users.get_mut(&irc_lowercase(nickname)) {
+            Some(user) => user,
+            None => {
+                return Err(ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "user not found".to_string(),
+                })
+            }
+        };
+
+        if user.server_name == self.server_data.server_name {
+            self.server_rol.notify_all_but(message, &user.server_name)?;
+            return Ok(());
+        }
+        user.invisible = invisible;
+
+        if self.server_data.is_main() {
+            self.server_rol.notify_all_but(message, &user.server_name)?;
+        }
+
+        Ok(())
+    }
+
     ///
     /// This function handles a topic message, it will set the topic of the channel if it is possible
     ///
@@ -242,7 +505,7 @@ impl Server {
                 message: "Could not access channels".to_string(),
             }
         })?;
-        let user_setting_topic = match users.get(nickname) {
+        let user_setting_topic = match users.get(&irc_lowercase(nickname)) {
             Some(user) => user,
             None => {
                 return Err(ServerError {
@@ -259,7 +522,7 @@ impl Server {
                 message: "Could not access channels".to_string(),
             }
         })?;
-        let channel = match channels.get_mut(channel_name) {
+        let channel = match channels.get_mut(&irc_lowercase(channel_name)) {
             Some(channel) => channel,
             None => {
                 return Err(ServerError {
@@ -269,18 +532,70 @@ impl Server {
             }
         };
 
-        let topic = &message.params[1][0];
+        // The message could come without the time the topic was set
+        let (topic, set_at) = match message.params.len() >= 3 {
+            true => (
+                &message.params[2][0],
+                message.params[1][0].parse::<u128>().unwrap_or(0),
+            ),
+            false => (&message.params[1][0], 0),
+        };
 
-        if !channel.channel_has_topic(topic) {
-            let _result = channel.set_topic(&message.prefix.clone().unwrap(), topic);
+        // Members of this server are told the new topic, the setter already got RPL_TOPIC
+        let topic_change = Message {
+            tags: None,
+            prefix: Some(nickname.clone()),
+            command: TOPIC.to_string(),
+            params: vec![vec![channel.name.clone()], vec![topic.clone()]],
+        };
+        let members: Vec<String> = channel
+            .users
+            .keys()
+            .filter(|member| !irc_eq(member, nickname))
+            .cloned()
+            .collect();
+
+        if channel.channel_has_topic(topic) && channel.topic_set_at == Some(set_at) {
+            // Topic was set on this server, the rest of the servers are notified
+            if channel.is_multiserver() {
+                self.server_rol.notify(message)?;
+            }
+        } else if channel.topic_wins(topic, set_at) {
+            let _result = channel.set_topic(nickname, topic, set_at);
             if self.server_data.is_main() {
                 self.server_rol
                     .notify_all_but(message, &user_setting_topic.server_name)?;
             }
         } else {
-            self.server_rol.notify(message)?;
+            // The topic is older than the current one, so it's ignored
+            return Ok(());
         }
+        drop(channels);
+        drop(users);
+
+        self.send_topic_change(&topic_change, &members)
+    }
 
+    ///
+    /// Sends the topic change to the members connected to this server. Members of other
+    /// servers are told by their server when the topic arrives there
+    ///
+    fn send_topic_change(&self, message: &Message, members: &[String]) -> Result<(), ServerError> {
+        for member in members {
+            let is_in_server = self
+                .users_clients
+                .lock()
+                .map_err(|_| -> ServerError {
+                    ServerError {
+                        kind: NONCRITICAL.to_string(),
+                        message: "Could not lock user clients".to_string(),
+                    }
+                })?
+                .contains_key(&irc_lowercase(member));
+            if is_in_server {
+                self.send_message_to_receiver(message, member)?;
+            }
+        }
         Ok(())
     }
 
@@ -289,9 +604,10 @@ impl Server {
     /// but if the user isnt an operator it will send an error message to the user
     ///
     fn handle_squit(&mut self, message: Message) -> Result<(), ServerError> {
-        println!("Received SQUIT message");
         let user = match message.clone().prefix {
             Some(user) => user,
+            // A secondary server is announcing that it left
+            None if self.server_data.is_main() => return self.handle_server_left(message),
             None => {
                 return Err(ServerError {
                     kind: CRITICAL.to_string(),
@@ -309,61 +625,27 @@ impl Server {
                 comment = &message.params[1][0];
             }
             println!("message: {:?}", message);
-            if self.operator == user {
+            if irc_eq(&self.operator, &user) {
                 self.send_message_to_receiver(&message, &message.prefix.clone().unwrap())?; //to notify the thread that the squit was received succesfully
-                let quit_message = Message {
-                    prefix: None,
-                    command: QUIT.to_string(),
-                    params: vec![vec![comment.clone()]],
-                };
-                println!(
-                    "Sending quit message to all users: {:?}",
-                    self.users_clients
-                );
-                let users_clients = self.users_clients.lock().map_err(|_| -> ServerError {
-                    ServerError {
-                        kind: NONCRITICAL.to_string(),
-                        message: "Could not access user clients".to_string(),
-                    }
-                })?;
-                for client in users_clients.values() {
-                    println!("sending quit message to client");
-                    println!("quit message: {:?}", quit_message);
-                    client
-                        .1
-                        .send(quit_message.clone())
-                        .map_err(|_| -> ServerError {
-                            ServerError {
-                                kind: CRITICAL.to_string(),
-                                message: "Couldn't set as non blocking".to_string(),
-                            }
-                        })?;
-                    println!("message sent");
-                }
+
+                // Registered users must not be lost when the server stops
+                self.save_pending_users()?;
                 println!("sending quit message to all servers");
                 let mut message_notice = message.clone();
                 message_notice.prefix = None;
                 self.server_rol.notify(message_notice)?;
-                // Waiting for all threads to finish before exiting
-                for client in self.users_clients.lock().unwrap().iter_mut() {
-                    println!("Waiting for client to disconnect");
-                    let data = client.1;
-                    if let Some(handler) = data.0.take() {
-                        handler.join().map_err(|_| -> ServerError {
-                            ServerError {
-                                kind: CRITICAL.to_string(),
-                                message: "Couldn't set as non blocking".to_string(),
-                            }
-                        })??;
-                    }
-                }
+                self.disconnect_users(comment)?;
                 // Returns this error to inform that it must stop running, and sends the comment so that it can be shown
                 return Err(ServerError {
                     kind: "SQUIT".to_string(),
                     message: comment.to_string(),
                 });
             } else {
-                println!("User {} is not an operator", user);
+                self.logger.warn(
+                    &user,
+                    SQUIT,
+                    "Tried to disconnect a server without being an operator",
+                );
                 let mut answer_message = message.clone();
                 answer_message.params = vec![vec!["You are not an operator".to_string()]];
                 self.send_message_to_receiver(&answer_message, &user)?
@@ -388,6 +670,209 @@ impl Server {
         Ok(())
     }
 
+    ///
+    /// Handles the SHUTDOWN sent when the process is asked to stop. Like a SQUIT of the
+    /// operator, the users are saved, the other servers notified and the users told before
+    /// their threads finish
+    ///
+    fn handle_shutdown(&mut self) -> Result<(), ServerError> {
+        self.logger.warn("", SHUTDOWN, SHUTDOWN_MESSAGE);
+        self.save_pending_users()?;
+        self.server_rol.notify(Message {
+            tags: None,
+            prefix: None,
+            command: SQUIT.to_string(),
+            params: vec![
+                vec![self.server_data.server_name.clone()],
+                vec![SHUTDOWN_MESSAGE.to_string()],
+            ],
+        })?;
+        self.disconnect_users(SHUTDOWN_MESSAGE)?;
+
+        // Returns this error to inform that it must stop running
+        Err(ServerError {
+            kind: SHUTDOWN.to_string(),
+            message: SHUTDOWN_MESSAGE.to_string(),
+        })
+    }
+
+    ///
+    /// Sends a QUIT with the comment to every user connected to this server and waits for
+    /// their threads to finish
+    ///
+    fn disconnect_users(&self, comment: &str) -> Result<(), ServerError> {
+        let quit_message = Message {
+            tags: None,
+            prefix: None,
+            command: QUIT.to_string(),
+            params: vec![vec![comment.to_string()]],
+        };
+        println!(
+            "Sending quit message to all users: {:?}",
+            self.users_clients
+        );
+        let mut users_clients = self.users_clients.lock().map_err(|_| -> ServerError {
+            ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "Could not access user clients".to_string(),
+            }
+        })?;
+        for client in users_clients.values() {
+            println!("quit message: {:?}", quit_message);
+            client
+                .1
+                .send(quit_message.clone())
+                .map_err(|_| -> ServerError {
+                    ServerError {
+                        kind: CRITICAL.to_string(),
+                        message: "Couldn't set as non blocking".to_string(),
+                    }
+                })?;
+        }
+        // Waiting for all threads to finish before exiting
+        for client in users_clients.iter_mut() {
+            println!("Waiting for client to disconnect");
+            let data = client.1;
+            if let Some(handler) = data.0.take() {
+                handler.join().map_err(|_| -> ServerError {
+                    ServerError {
+                        kind: CRITICAL.to_string(),
+                        message: "Couldn't set as non blocking".to_string(),
+                    }
+                })??;
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Handles the SQUIT a secondary server sends before leaving. Its users can't be reached
+    /// anymore, so they are removed and the members of their channels in this server receive
+    /// a QUIT for each of them. The handler of the server is answered so it stops waiting
+    ///
+    fn handle_server_left(&mut self, message: Message) -> Result<(), ServerError> {
+        let server_name = message.params[0][0].clone();
+        self.logger
+            .info("", SQUIT, &format!("Server {} left", server_name));
+
+        let users_left: Vec<User> = {
+            let mut users = self.users.lock().map_err(|_| -> ServerError {
+                ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "Couldn't get lock".to_string(),
+                }
+            })?;
+            let nicknames: Vec<String> = users
+                .iter()
+                .filter(|(_, user)| user.server_name == server_name)
+                .map(|(nickname, _)| nickname.clone())
+                .collect();
+            nicknames
+                .iter()
+                .filter_map(|nickname| users.remove(nickname))
+                .collect()
+        };
+
+        for user in users_left {
+            let quit = Message {
+                tags: None,
+                prefix: Some(user.nickname.clone()),
+                command: QUIT.to_string(),
+                params: vec![vec![format!("{} left the network", server_name)]],
+            };
+            self.send_quit_to_members(&user, &quit)?;
+            // The other secondary servers tell the members of the channels they have
+            self.server_rol.notify_all_but(quit, &server_name)?;
+            self.whowas_history.record(user);
+        }
+
+        // The server may have closed its connection already, then nobody is waiting
+        self.server_rol
+            .send_message_to_server(message, server_name)
+            .ok();
+        Ok(())
+    }
+
+    ///
+    /// Removes the user from its channels and sends the QUIT to the members of them that are
+    /// connected to this server
+    ///
+    fn send_quit_to_members(&self, user: &User, quit: &Message) -> Result<(), ServerError> {
+        for channel in &user.channels {
+            for member in self.remove_from_channel(channel, &user.nickname)? {
+                let is_in_server = self
+                    .users_clients
+                    .lock()
+                    .map_err(|_| -> ServerError {
+                        ServerError {
+                            kind: NONCRITICAL.to_string(),
+                            message: "Could not lock user clients".to_string(),
+                        }
+                    })?
+                    .contains_key(&member);
+                if is_in_server {
+                    self.send_message_to_receiver(quit, &member)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Handles the QUIT of a user connected to another server, sent by that server or by the
+    /// main server when the server of the user left. Only the members connected to this server
+    /// are told, the other servers receive it from the server that sent it
+    ///
+    fn handle_remote_quit(&mut self, nickname: &str, message: &Message) -> Result<(), ServerError> {
+        let user = match self
+            .users
+            .lock()
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "Couldn't get lock".to_string(),
+                }
+            })?
+            .get_mut(&irc_lowercase(nickname))
+        {
+            Some(user) => {
+                let quitting = user.clone();
+                // The same QUIT may arrive again, then it reaches nobody
+                user.channels.clear();
+                user.sign_off();
+                quitting
+            }
+            None => return Ok(()),
+        };
+
+        self.send_quit_to_members(&user, message)?;
+        self.whowas_history.record(user);
+        Ok(())
+    }
+
+    ///
+    /// Removes the user from the channel and returns the nicknames of the members left
+    ///
+    fn remove_from_channel(
+        &self,
+        channel_name: &str,
+        nickname: &str,
+    ) -> Result<Vec<String>, ServerError> {
+        let mut channels = self.channels.lock().map_err(|_| -> ServerError {
+            ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "Couldn't get lock".to_string(),
+            }
+        })?;
+        Ok(match channels.get_mut(&irc_lowercase(channel_name)) {
+            Some(channel) => {
+                channel.remove_user(nickname);
+                channel.users.keys().cloned().collect()
+            }
+            None => vec![],
+        })
+    }
+
     ///
     /// Handles the server message. It is used when a new connection arrives or to notify a server of a new one
     ///
@@ -421,7 +906,7 @@ impl Server {
                 }
             })?;
         let nickname = message.prefix.clone().unwrap();
-        if !users.contains_key(&nickname) {
+        if !users.contains_key(&irc_lowercase(&nickname)) {
             println!("new user");
             self.add_new_user(message, users)?;
         }
@@ -446,7 +931,7 @@ impl Server {
         let nickname = message.prefix.clone().unwrap();
         let user;
 
-        if !users.contains_key(&nickname) {
+        if !users.contains_key(&irc_lowercase(&nickname)) {
             println!("Adding new user");
             user = self.add_new_user(message.clone(), users)?;
 
@@ -455,7 +940,13 @@ impl Server {
                 self.server_rol.notify_all_but(message, &user.server_name)?;
             };
         } else {
-            user = users.get(&nickname).unwrap().clone(); //this wont fail
+            user = users.get(&irc_lowercase(&nickname)).unwrap().clone(); //this wont fail
+
+            // The user was registered again by another server, while a client is using it here
+            if self.is_nickname_collision(&user, &message)? {
+                drop(users);
+                return self.reject_registration(message);
+            }
 
             if user.server_name == self.server_data.server_name {
                 println!("Notifying servers of message: {:?}", message);
@@ -464,57 +955,208 @@ impl Server {
         };
 
         if self.server_data.is_main() {
-            add_user(&user, self.server_data.users_file_path.clone()).map_err(
-                |_| -> ServerError {
-                    ServerError {
-                        kind: CRITICAL.to_string(),
-                        message: "Could not save user in data file".to_string(),
-                    }
-                },
-            )?;
+            self.pending_users.push(user).map_err(|_| -> ServerError {
+                ServerError {
+                    kind: CRITICAL.to_string(),
+                    message: "Could not save user in data file".to_string(),
+                }
+            })?;
         }
 
         Ok(())
     }
 
     ///
-    /// This function handles the registration of a new user in the server. It will save it in the users dictionaty
+    /// Checks if the registration received claims the nickname of a user that is connected to
+    /// this server. The same user propagated again between servers comes with the server it
+    /// was registered in, so it is not a collision
     ///
-    fn add_new_user(
-        &self,
-        message: Message,
-        mut users: MutexGuard<HashMap<String, User>>,
-    ) -> Result<User, ServerError> {
-        let user_data = message.params;
-        println!("user_data: {:?}", user_data);
-        let user = User::new(
-            user_data[0][0].clone(),
-            user_data[0][1].clone(),
-            user_data[0][2].clone(),
-            user_data[1][0].clone(),
-            user_data[0][3].clone(),
-            user_data[0][4].clone(),
+    fn is_nickname_collision(&self, user: &User, message: &Message) -> Result<bool, ServerError> {
+        let server_name = &message.params[0][3];
+        if user.server_name == *server_name {
+            return Ok(false);
+        }
+
+        let users_clients = self.users_clients.lock().map_err(|_| -> ServerError {
+            ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "Could not lock user clients".to_string(),
+            }
+        })?;
+        Ok(users_clients.contains_key(&irc_lowercase(&user.nickname)))
+    }
+
+    ///
+    /// Answers ERR_NICKCOLLISION to the server where the user was registered, the user is not
+    /// saved nor propagated. The reply carries the name of that server so the main server can
+    /// send it there
+    ///
+    fn reject_registration(&mut self, message: Message) -> Result<(), ServerError> {
+        let nickname = message.prefix.clone().unwrap();
+        let server_name = message.params[0][3].clone();
+        self.logger.warn(
+            &nickname,
+            REGISTRATION,
+            &format!("Nickname in use, registration of {} rejected", server_name),
         );
 
-        users.insert(user.nickname.clone(), user.clone());
-        println!("new user saved {:?}", user);
-        Ok(user)
+        let collision = Message {
+            tags: None,
+            prefix: None,
+            command: ERR_NICKCOLLISION_NUM.to_string(),
+            params: vec![
+                vec![nickname, server_name.clone()],
+                vec![ERR_NICKCOLLISION_MSG.to_string()],
+            ],
+        };
+        self.server_rol
+            .send_message_to_server(collision, server_name)
     }
 
     ///
-    /// This function will take the received invite messsage and ask to resend it to the correct user.
+    /// Receives the ERR_NICKCOLLISION of a user registered in another server. If the user was
+    /// registered here its client is told and disconnected, like after a QUIT, and the user is
+    /// forgotten so it can register again with another nickname. Otherwise the reply is sent
+    /// to the server of the user
     ///
-    fn handle_invite(&mut self, message: Message) -> Result<(), ServerError> {
-        let receiver = &message.params.clone()[0][0];
+    fn handle_nick_collision(&mut self, message: Message) -> Result<(), ServerError> {
+        let nickname = message.params[0][0].clone();
+        let server_name = message.params[0][1].clone();
+        if server_name != self.server_data.server_name {
+            return self.server_rol.send_message_to_server(message, server_name);
+        }
 
-        let users = self.users.lock().map_err(|_| -> ServerError {
-            ServerError {
-                kind: NONCRITICAL.to_string(),
-                message: "Could not access channels".to_string(),
-            }
-        })?;
-        let user_inviting = match users.get(receiver) {
-            Some(user) => user,
+        // The handler closes the connection once it writes the reply
+        if let Some((_, sender)) = self
+            .users_clients
+            .lock()
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "Could not lock user clients".to_string(),
+                }
+            })?
+            .get(&irc_lowercase(&nickname))
+        {
+            sender.send(message).map_err(|_| -> ServerError {
+                ServerError {
+                    kind: CRITICAL.to_string(),
+                    message: "Couldn't send".to_string(),
+                }
+            })?;
+        }
+        self.close_user_client(&nickname)?;
+
+        self.users
+            .lock()
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "Couldn't get lock".to_string(),
+                }
+            })?
+            .remove(&irc_lowercase(&nickname));
+        self.logger
+            .warn(&nickname, REGISTRATION, "Nickname collision, user removed");
+        Ok(())
+    }
+
+    ///
+    /// Writes every registered user not yet saved to the users file
+    ///
+    fn save_pending_users(&mut self) -> Result<(), ServerError> {
+        if !self.server_data.is_main() {
+            return Ok(());
+        }
+        self.pending_users.flush().map_err(|_| -> ServerError {
+            ServerError {
+                kind: CRITICAL.to_string(),
+                message: "Could not save users in data file".to_string(),
+            }
+        })
+    }
+
+    ///
+    /// Rewrites the channels file with the current channels of the server
+    ///
+    fn save_channels(&self) -> Result<(), ServerError> {
+        let channels = self.channels.lock().map_err(|_| -> ServerError {
+            ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "Could not access channels".to_string(),
+            }
+        })?;
+        save_channels(&channels, self.server_data.channels_file_path.clone()).map_err(
+            |_| -> ServerError {
+                ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "Could not save channels in data file".to_string(),
+                }
+            },
+        )
+    }
+
+    ///
+    /// Writes the registered users not yet saved if they waited long enough
+    ///
+    fn save_pending_users_if_due(&mut self) -> Result<(), ServerError> {
+        if !self.server_data.is_main() {
+            return Ok(());
+        }
+        self.pending_users
+            .flush_if_due()
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: CRITICAL.to_string(),
+                    message: "Could not save users in data file".to_string(),
+                }
+            })
+    }
+
+    ///
+    /// This function handles the registration of a new user in the server. It will save it in the users dictionaty
+    ///
+    fn add_new_user(
+        &self,
+        message: Message,
+        mut users: MutexGuard<HashMap<String, User>>,
+    ) -> Result<User, ServerError> {
+        if !message.has_params(&[5, 1]) {
+            return Err(ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "Registration message is missing parameters".to_string(),
+            });
+        }
+        let user_data = message.params;
+        let user = User::new(
+            user_data[0][0].clone(),
+            user_data[0][1].clone(),
+            user_data[0][2].clone(),
+            user_data[1][0].clone(),
+            user_data[0][3].clone(),
+            user_data[0][4].clone(),
+        );
+
+        users.insert(irc_lowercase(&user.nickname), user.clone());
+        self.logger
+            .info(&user.nickname, REGISTRATION, "New user saved");
+        Ok(user)
+    }
+
+    ///
+    /// This function will take the received invite messsage and ask to resend it to the correct user.
+    ///
+    fn handle_invite(&mut self, message: Message) -> Result<(), ServerError> {
+        let receiver = &message.params.clone()[0][0];
+
+        let users = self.users.lock().map_err(|_| -> ServerError {
+            ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "Could not access channels".to_string(),
+            }
+        })?;
+        let user_inviting = match users.get(&irc_lowercase(receiver)) {
+            Some(user) => user,
             None => {
                 return Err(ServerError {
                     kind: CRITICAL.to_string(),
@@ -530,7 +1172,7 @@ impl Server {
                     message: "Could not lock user clients".to_string(),
                 }
             })?;
-            let sender = match users_clients.get(receiver) {
+            let sender = match users_clients.get(&irc_lowercase(receiver)) {
                 Some((_, sender)) => sender,
                 None => {
                     return Err(ServerError {
@@ -553,6 +1195,40 @@ impl Server {
         Ok(())
     }
 
+    ///
+    /// Receives a KNOCK checked by the client handler and notifies every operator of the
+    /// channel with a NOTICE, the ones of other servers get it through their server
+    ///
+    fn handle_knock(&mut self, message: Message) -> Result<(), ServerError> {
+        let nickname_knocking = message.prefix.clone().unwrap_or_default();
+        let channel_name = &message.params[0][0];
+        let text = message.params.get(1).map(|text| text.join(" "));
+
+        let notices = {
+            let channels = self.channels.lock().map_err(|_| -> ServerError {
+                ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "Could not access channels".to_string(),
+                }
+            })?;
+            match channels.get(&irc_lowercase(channel_name)) {
+                Some(channel) => channel.knock_notices(&nickname_knocking, text.as_deref()),
+                None => {
+                    return Err(ServerError {
+                        kind: NONCRITICAL.to_string(),
+                        message: "channel not found".to_string(),
+                    })
+                }
+            }
+        };
+
+        for notice in notices {
+            let operator = notice.params[0][0].clone();
+            self.send_message_to_receiver(&notice, &operator)?;
+        }
+        Ok(())
+    }
+
     ///
     /// Checks ig a server exists, and will send a message to the asking thread with the answer
     ///
@@ -580,7 +1256,7 @@ impl Server {
     ///
     fn handle_is_operator(&mut self, message: Message) -> Result<(), ServerError> {
         let receiver = message.prefix.clone().unwrap();
-        if self.operator == *receiver {
+        if irc_eq(&self.operator, &receiver) {
             let mut answer = message;
             answer.params = vec![vec!["You are an operator".to_string()]];
             self.send_message_to_receiver(&answer, &receiver)?;
@@ -624,41 +1300,323 @@ impl Server {
     }
 
     ///
-    /// This function receives the operator message
-    /// if the password received is correct it will set the operator
+    /// Handles a LUSERS message, answers with the amount of users, channels and servers known.
+    /// The main server knows every user, multiserver channel and server, so its counts are the
+    /// ones of the whole network
     ///
-    fn handle_operator(&mut self, message: Message) -> Result<(), ServerError> {
-        let operator = message.params[0][0].clone();
-        let password = message.params[1][0].clone();
-        println!("operator: {}", operator);
-        println!("password: {}", password);
+    fn handle_lusers(&mut self, message: Message) -> Result<(), ServerError> {
+        let nickname = message.prefix.clone().unwrap();
+        let users_count = self
+            .users
+            .lock()
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "Could not access users".to_string(),
+                }
+            })?
+            .len();
+        let channels_count = self
+            .channels
+            .lock()
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "Could not access channels".to_string(),
+                }
+            })?
+            .len();
+        let clients_count = self
+            .users_clients
+            .lock()
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "Could not lock user clients".to_string(),
+                }
+            })?
+            .len();
+        let servers_count = self.server_rol.servers_count();
+
+        let replies = vec![
+            Message {
+                tags: None,
+                prefix: None,
+                command: RPL_LUSERCLIENT_NUM.to_string(),
+                params: vec![vec![format!(
+                    "There are {} users and 0 services on {} servers",
+                    users_count,
+                    servers_count + 1
+                )]],
+            },
+            Message {
+                tags: None,
+                prefix: None,
+                command: RPL_LUSERCHANNELS_NUM.to_string(),
+                params: vec![
+                    vec![channels_count.to_string()],
+                    vec![RPL_LUSERCHANNELS_MSG.to_string()],
+                ],
+            },
+            Message {
+                tags: None,
+                prefix: None,
+                command: RPL_LUSERME_NUM.to_string(),
+                params: vec![vec![format!(
+                    "I have {} clients and {} servers",
+                    clients_count, servers_count
+                )]],
+            },
+        ];
+        for reply in replies {
+            self.send_message_to_receiver(&reply, &nickname)?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Handles an ISON message, answers with the nicknames asked that are present on the network.
+    /// Users of this server are present if they have a client here. Users of other servers are
+    /// present while their server is connected, as only their own server knows their clients
+    ///
+    fn handle_ison(&mut self, message: Message) -> Result<(), ServerError> {
+        let nickname = message.prefix.clone().unwrap();
+        let users_clients = self.users_clients.lock().map_err(|_| -> ServerError {
+            ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "Could not lock user clients".to_string(),
+            }
+        })?;
         let users = self.users.lock().map_err(|_| -> ServerError {
             ServerError {
                 kind: NONCRITICAL.to_string(),
-                message: "Couldn't get lock".to_string(),
+                message: "Could not access users".to_string(),
             }
         })?;
-        for user in users.values() {
-            if user.nickname == operator {
-                if user.password == password {
-                    self.operator = operator.clone();
-                    let mut answer = message;
-                    answer.params = vec![vec!["You are now an operator".to_string()]];
-                    self.send_message_to_receiver(&answer, &operator)?;
-                    println!("new operator: {}", self.operator);
-                    return Ok(());
-                } else {
-                    let mut answer = message.clone();
-                    answer.params = vec![vec!["Wrong password".to_string()]];
-                    self.send_message_to_receiver(&answer, &operator)?;
+
+        let mut present: Vec<String> = vec![];
+        for asked in message
+            .params
+            .iter()
+            .flatten()
+            .flat_map(|param| param.split_whitespace())
+        {
+            let key = irc_lowercase(asked);
+            let user = match users.get(&key) {
+                Some(user) => user,
+                None => continue,
+            };
+            let is_present = users_clients.contains_key(&key)
+                || (user.server_name != self.server_data.server_name
+                    && self.server_rol.is_server_connected(&user.server_name));
+            if is_present && !present.contains(&user.nickname) {
+                present.push(user.nickname.clone());
+            }
+        }
+        drop(users);
+        drop(users_clients);
+
+        let reply = Message {
+            tags: None,
+            prefix: None,
+            command: RPL_ISON_NUM.to_string(),
+            params: vec![vec![present.join(" ")]],
+        };
+        self.send_message_to_receiver(&reply, &nickname)
+    }
+
+    ///
+    /// Handles a WHOWAS message, answers with a RPL_WHOWASUSER for every time the nickname
+    /// asked is in the history of users that quit, or with ERR_WASNOSUCHNICK if it is not there
+    ///
+    fn handle_whowas(&mut self, message: Message) -> Result<(), ServerError> {
+        let nickname = message.prefix.clone().unwrap();
+        let asked = message.params[0][0].clone();
+
+        let mut replies: Vec<Message> = self
+            .whowas_history
+            .find(&asked)
+            .into_iter()
+            .map(|user| Message {
+                tags: None,
+                prefix: None,
+                command: RPL_WHOWASUSER_NUM.to_string(),
+                params: vec![
+                    vec![user.nickname.clone()],
+                    vec![user.username.clone()],
+                    vec![user.address.clone()],
+                    vec!["*".to_string()],
+                    vec![user.real_name.clone()],
+                ],
+            })
+            .collect();
+        if replies.is_empty() {
+            replies.push(Message {
+                tags: None,
+                prefix: None,
+                command: ERR_WASNOSUCHNICK_NUM.to_string(),
+                params: vec![vec![asked.clone()], vec![ERR_WASNOSUCHNICK_MSG.to_string()]],
+            });
+        }
+        replies.push(Message {
+            tags: None,
+            prefix: None,
+            command: RPL_ENDOFWHOWAS_NUM.to_string(),
+            params: vec![vec![asked], vec![RPL_ENDOFWHOWAS_MSG.to_string()]],
+        });
+
+        for reply in replies {
+            self.send_message_to_receiver(&reply, &nickname)?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// This function receives the operator message
+    /// if the name and password received are the ones of an operator in the operators file
+    /// it will set the user that sent it as the operator
+    ///
+    fn handle_operator(&mut self, message: Message) -> Result<(), ServerError> {
+        let nickname = message.prefix.clone().unwrap_or_default();
+        let name = &message.params[0][0];
+        let password = &message.params[1][0];
+
+        let answer_text = match oper_password(&self.server_data.opers_file_path, name) {
+            Some(oper_password) if oper_password == *password => {
+                self.operator = nickname.clone();
+                self.logger.info(&nickname, OPERATOR, "Is now an operator");
+                "You are now an operator"
+            }
+            Some(_) => "Wrong password",
+            None => "Unknown operator",
+        };
+
+        let mut answer = message;
+        answer.params = vec![vec![answer_text.to_string()]];
+        self.send_message_to_receiver(&answer, &nickname)
+    }
+
+    ///
+    /// Handles the REHASH of the operator. The server data file is read again and the
+    /// settings found are used without dropping the users connected, the connections that
+    /// were already open keep the ones they started with. The user is answered with the
+    /// path of the file
+    ///
+    fn handle_rehash(&mut self, message: Message) -> Result<(), ServerError> {
+        let nickname = message.prefix.clone().unwrap_or_default();
+        let file_path = self.server_data.file_path.clone();
+
+        let answer_text = if !irc_eq(&self.operator, &nickname) {
+            self.logger.warn(
+                &nickname,
+                REHASH,
+                "Tried to rehash without being an operator",
+            );
+            "You are not an operator"
+        } else {
+            match ServerData::new(file_path.clone()) {
+                Ok(server_data) => {
+                    self.reload_server_data(server_data)?;
+                    self.logger
+                        .info(&nickname, REHASH, &format!("Reloaded {}", file_path));
+                    "Rehashing"
+                }
+                Err(error) => {
+                    self.logger.error(
+                        &nickname,
+                        REHASH,
+                        &format!("Couldn't reload {}: {}", file_path, error),
+                    );
+                    "Could not read the server data file"
                 }
             }
+        };
+
+        let mut answer = message;
+        answer.params = vec![vec![answer_text.to_string()], vec![file_path]];
+        self.send_message_to_receiver(&answer, &nickname)
+    }
+
+    ///
+    /// Takes the settings of the server data read again. The address, the name, the main
+    /// server, the files of users and channels, the log and TLS are only used when the server
+    /// starts, so their changes are ignored with a warning. The users and channels are the
+    /// ones in memory, not the ones read from the files
+    ///
+    fn reload_server_data(&mut self, server_data: ServerData) -> Result<(), ServerError> {
+        let ignored = [
+            (
+                "address",
+                self.server_data.server_address != server_data.server_address,
+            ),
+            (
+                "name",
+                self.server_data.server_name != server_data.server_name,
+            ),
+            ("main server", self.server_data.main != server_data.main),
+            (
+                "users file",
+                self.server_data.users_file_path != server_data.users_file_path,
+            ),
+            (
+                "channels file",
+                self.server_data.channels_file_path != server_data.channels_file_path,
+            ),
+            (
+                "log",
+                self.server_data.log_file_path != server_data.log_file_path
+                    || self.server_data.log_level != server_data.log_level,
+            ),
+            (
+                "TLS files",
+                self.server_data.tls_files != server_data.tls_files,
+            ),
+        ];
+        for (setting, changed) in ignored {
+            if changed {
+                self.logger.warn(
+                    "",
+                    REHASH,
+                    &format!(
+                        "The {} can't change while the server runs, restart it to use the new one",
+                        setting
+                    ),
+                );
+            }
         }
+
+        self.server_data.admin_nick = server_data.admin_nick;
+        self.server_data.admin_password = server_data.admin_password;
+        self.server_data.motd_file_path = server_data.motd_file_path;
+        self.server_data.opers_file_path = server_data.opers_file_path;
+        self.server_data.password_policy = server_data.password_policy;
+        self.server_data.max_nickname_length = server_data.max_nickname_length;
+        self.server_data.message_rate = server_data.message_rate;
+        self.server_data.connection_limit = server_data.connection_limit;
+        self.server_data.whowas_history_size = server_data.whowas_history_size;
+        self.server_data.channel_backlog_size = server_data.channel_backlog_size;
+        self.server_data.away_on_join = server_data.away_on_join;
+        self.server_data.max_channels_per_join = server_data.max_channels_per_join;
+        self.whowas_history
+            .set_capacity(self.server_data.whowas_history_size);
+
+        let mut connection_settings =
+            self.connection_settings
+                .lock()
+                .map_err(|_| -> ServerError {
+                    ServerError {
+                        kind: NONCRITICAL.to_string(),
+                        message: "Could not lock connection settings".to_string(),
+                    }
+                })?;
+        *connection_settings = ConnectionSettings::new(&self.server_data);
         Ok(())
     }
 
     ///
-    /// This function is called when a mode message is received, it will notify all servers of the new mode
+    /// This function is called when a mode message is received, it will notify all servers of the new mode.
+    /// Registrations of channels are notified the same way
     ///
     fn handle_mode(&mut self, message: Message) -> Result<(), ServerError> {
         let nickname_setting_mode = message.prefix.clone().unwrap();
@@ -669,7 +1627,7 @@ impl Server {
                 message: "Could not access channels".to_string(),
             }
         })?;
-        let user_setting_mode = match users.get(&nickname_setting_mode) {
+        let user_setting_mode = match users.get(&irc_lowercase(&nickname_setting_mode)) {
             Some(user) => user,
             None => {
                 return Err(ServerError {
@@ -701,7 +1659,7 @@ impl Server {
                 message: "Could not access channels".to_string(),
             }
         })?;
-        let user_parting = match users.get_mut(&nickname_parting) {
+        let user_parting = match users.get_mut(&irc_lowercase(&nickname_parting)) {
             Some(user) => user,
             None => {
                 return Err(ServerError {
@@ -718,7 +1676,7 @@ impl Server {
                 message: "Could not access channels".to_string(),
             }
         })?;
-        let channel = match channels.get_mut(channel_name) {
+        let channel = match channels.get_mut(&irc_lowercase(channel_name)) {
             Some(channel) => channel,
             None => {
                 return Err(ServerError {
@@ -743,6 +1701,148 @@ impl Server {
         Ok(())
     }
 
+    ///
+    /// Handles the close of a channel. Parts every member, notifies the ones connected to
+    /// this server and removes the channel. The rest of the servers are notified so that
+    /// the channel is removed in the whole network.
+    ///
+    fn handle_close(&mut self, message: Message) -> Result<(), ServerError> {
+        let nickname_closing = message.prefix.clone().unwrap();
+        let channel_name = &message.params[0][0];
+
+        let mut channels = self.channels.lock().map_err(|_| -> ServerError {
+            ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "Could not access channels".to_string(),
+            }
+        })?;
+        let mut channel = match channels.remove(&irc_lowercase(channel_name)) {
+            Some(channel) => channel,
+            // Channel was already closed
+            None => return Ok(()),
+        };
+        drop(channels);
+
+        let mut users = self.users.lock().map_err(|_| -> ServerError {
+            ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "Could not access users".to_string(),
+            }
+        })?;
+        let mut nicknames_to_notify = channel.part_all();
+        for nickname in nicknames_to_notify.iter() {
+            if let Some(user) = users.get_mut(&irc_lowercase(nickname)) {
+                user.remove_channel(channel_name);
+            }
+        }
+        if !nicknames_to_notify
+            .iter()
+            .any(|nickname| irc_eq(nickname, &nickname_closing))
+        {
+            nicknames_to_notify.push(nickname_closing.clone());
+        }
+
+        // Only members connected to this server are notified, other servers notify theirs
+        let users_clients = self.users_clients.lock().map_err(|_| -> ServerError {
+            ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "Could not lock user clients".to_string(),
+            }
+        })?;
+        for nickname in nicknames_to_notify {
+            if let Some((_, sender)) = users_clients.get(&irc_lowercase(&nickname)) {
+                sender.send(message.clone()).map_err(|_| -> ServerError {
+                    ServerError {
+                        kind: CRITICAL.to_string(),
+                        message: "Couldn't send".to_string(),
+                    }
+                })?;
+            }
+        }
+        drop(users_clients);
+
+        let server_closing = match users.get(&irc_lowercase(&nickname_closing)) {
+            Some(user) => user.server_name.clone(),
+            None => {
+                return Err(ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "user not found".to_string(),
+                })
+            }
+        };
+        drop(users);
+
+        if server_closing == self.server_data.server_name {
+            self.server_rol.notify(message)?;
+        } else if self.server_data.is_main() {
+            self.server_rol.notify_all_but(message, &server_closing)?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Handles a WALLOPS message, also sent as GLOBOPS. The text is only delivered to the operator
+    /// of this server, and the rest of the servers are notified so that each one delivers it to
+    /// its operator.
+    ///
+    fn handle_wallops(&mut self, message: Message) -> Result<(), ServerError> {
+        let nickname_sending = message.prefix.clone().unwrap();
+
+        let users = self.users.lock().map_err(|_| -> ServerError {
+            ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "Could not access users".to_string(),
+            }
+        })?;
+        let server_sending = match users.get(&irc_lowercase(&nickname_sending)) {
+            Some(user) => user.server_name.clone(),
+            None => {
+                return Err(ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "user not found".to_string(),
+                })
+            }
+        };
+        drop(users);
+
+        // Users of other servers were already checked by their server
+        if server_sending == self.server_data.server_name
+            && !irc_eq(&nickname_sending, &self.operator)
+        {
+            return Err(ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "You are not an operator".to_string(),
+            });
+        }
+
+        let users_clients = self.users_clients.lock().map_err(|_| -> ServerError {
+            ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "Could not lock user clients".to_string(),
+            }
+        })?;
+        for (nickname, (_, sender)) in users_clients.iter() {
+            if irc_eq(nickname, &self.operator) {
+                sender.send(message.clone()).map_err(|_| -> ServerError {
+                    ServerError {
+                        kind: CRITICAL.to_string(),
+                        message: "Couldn't send".to_string(),
+                    }
+                })?;
+            }
+        }
+        drop(users_clients);
+
+        if server_sending == self.server_data.server_name {
+            self.server_rol.notify(message)?;
+        } else if self.server_data.is_main() {
+            self.server_rol.notify_all_but(message, &server_sending)?;
+        }
+
+        Ok(())
+    }
+
     ///
     /// This function is called when the thread receives a who message
     /// requesting for operators data, it will either return all operators,
@@ -768,7 +1868,7 @@ impl Server {
                     message: "Couldn't get lock 4".to_string(),
                 }
             })?;
-            let user = match users.get(&self.operator.clone()) {
+            let user = match users.get(&irc_lowercase(&self.operator)) {
                 Some(user) => user,
                 None => {
                     return Err(ServerError {
@@ -806,12 +1906,87 @@ impl Server {
         if receiver.contains('#') || receiver.contains('&') {
             // Send message to channel
             self.send_message_to_channel(receiver, &nick, &message)?;
+            self.record_in_backlog(receiver, &message)?;
+        } else if irc_eq(receiver, &nick) {
+            // It would come back to its sender and open a chat with itself
+            self.logger
+                .info(&nick, &message.command, "Message to itself dropped");
         } else {
             // Send message to receiver
             self.send_message_to_receiver(&message, receiver)?;
+            self.reply_if_away(&nick, receiver)?;
         }
 
-        Ok(())
+        Ok(())
+    }
+
+    ///
+    /// Keeps the message in the backlog of the channel, so it is replayed to the users that join
+    ///
+    fn record_in_backlog(&self, channel_name: &str, message: &Message) -> Result<(), ServerError> {
+        let backlog_size = self.server_data.channel_backlog_size;
+        if backlog_size == 0 {
+            return Ok(());
+        }
+        if let Some(channel) = self
+            .channels
+            .lock()
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "Couldn't get lock".to_string(),
+                }
+            })?
+            .get_mut(&irc_lowercase(channel_name))
+        {
+            channel.record_message(message, SystemTime::now(), backlog_size);
+        }
+        Ok(())
+    }
+
+    ///
+    /// Sends RPL_AWAY with the away message of the receiver to the sender, if the receiver is away.
+    /// Only the server the sender is connected to answers, the away status is known by every server
+    ///
+    fn reply_if_away(&self, nickname_sender: &str, receiver: &str) -> Result<(), ServerError> {
+        let is_sender_here = self
+            .users_clients
+            .lock()
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "Could not lock user clients".to_string(),
+                }
+            })?
+            .contains_key(&irc_lowercase(nickname_sender));
+        if !is_sender_here {
+            return Ok(());
+        }
+
+        let reply = match self
+            .users
+            .lock()
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "Couldn't get lock".to_string(),
+                }
+            })?
+            .get(&irc_lowercase(receiver))
+        {
+            Some(user) => match &user.away_message {
+                Some(away_message) => Message {
+                    tags: None,
+                    prefix: None,
+                    command: RPL_AWAY_NUM.to_string(),
+                    params: vec![vec![user.nickname.clone()], vec![away_message.clone()]],
+                },
+                None => return Ok(()),
+            },
+            None => return Ok(()),
+        };
+
+        self.send_message_to_receiver(&reply, &nickname_sender.to_string())
     }
 
     ///
@@ -830,13 +2005,26 @@ impl Server {
     /// of the client is running is joined and the client information is removed from server.
     ///
     fn handle_quit(&mut self, message: Message) -> Result<(), ServerError> {
-        println!("Handling QUIT in server {}", self.server_data.server_name);
-
         let nickname = match message.prefix.clone() {
             Some(nickname) => nickname,
             None => "".to_string(),
         };
 
+        // Only the users connected here have a client, the others quit in another server
+        let is_client_here = self
+            .users_clients
+            .lock()
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "Could not lock user clients".to_string(),
+                }
+            })?
+            .contains_key(&irc_lowercase(&nickname));
+        if !is_client_here {
+            return self.handle_remote_quit(&nickname, &message);
+        }
+
         if message.params_total_count() == 1 {
             let users = self.users.lock().map_err(|_| -> ServerError {
                 ServerError {
@@ -844,18 +2032,52 @@ impl Server {
                     message: "Couldn't get lock".to_string(),
                 }
             })?;
-            let user = users.get(&nickname).unwrap(); // If it is here then the user exists so it cant fail
-            let channels = &user.channels;
+            let user = users.get(&irc_lowercase(&nickname)).unwrap(); // If it is here then the user exists so it cant fail
+            let channels = user.channels.clone();
+            // The users are taken again to find the members of the channels
+            drop(users);
 
-            for channel in channels {
+            for channel in &channels {
                 println!("Sending message to channel {}", channel);
                 self.send_message_to_channel(channel, &nickname, &message)?;
             }
         }
 
-        if let Some((_, (handler, _))) = self.users_clients.lock().unwrap().remove_entry(&nickname)
+        if let Some(user) = self
+            .users
+            .lock()
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "Couldn't get lock".to_string(),
+                }
+            })?
+            .get_mut(&irc_lowercase(&nickname))
         {
-            handler.unwrap().join().map_err(|_| -> ServerError {
+            user.sign_off();
+            self.whowas_history.record(user.clone());
+        }
+
+        self.close_user_client(&nickname)
+    }
+
+    ///
+    /// Forgets the client of the user and waits until its handler ends, so no connection is
+    /// left open after the user is gone
+    ///
+    fn close_user_client(&mut self, nickname: &str) -> Result<(), ServerError> {
+        let client = self
+            .users_clients
+            .lock()
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "Could not lock user clients".to_string(),
+                }
+            })?
+            .remove(&irc_lowercase(nickname));
+        if let Some((Some(handler), _)) = client {
+            handler.join().map_err(|_| -> ServerError {
                 ServerError {
                     kind: NONCRITICAL.to_string(),
                     message: "Couldn't get lock".to_string(),
@@ -869,11 +2091,26 @@ impl Server {
     ///
     /// Handles multiserver kick. If server has already kicked the user from channel
     /// then it notifies other server so they kick it and user getting kicked.
+    /// The KICK delivered always has a reason, the nickname of the user kicking
+    /// if no comment was given.
     ///
-    fn handle_kick(&mut self, message: Message) -> Result<(), ServerError> {
+    fn handle_kick(&mut self, mut message: Message) -> Result<(), ServerError> {
+        if message.params.len() < 3 {
+            let nickname_user_kicking = message.prefix.clone().unwrap();
+            message.params.push(vec![nickname_user_kicking]);
+        }
+
         let channel_name = &message.params[0][0];
         let nickname_user_getting_kicked = &message.params[1][0];
 
+        // Users are locked before channels, like in JOIN
+        let users = self.users.lock().map_err(|_| -> ServerError {
+            ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "Could not access users".to_string(),
+            }
+        })?;
+
         let mut channels = self.channels.lock().map_err(|_| -> ServerError {
             ServerError {
                 kind: NONCRITICAL.to_string(),
@@ -881,7 +2118,7 @@ impl Server {
             }
         })?;
 
-        let channel = match channels.get_mut(channel_name) {
+        let channel = match channels.get_mut(&irc_lowercase(channel_name)) {
             Some(channel) => channel,
             None => {
                 return Err(ServerError {
@@ -891,14 +2128,7 @@ impl Server {
             }
         };
 
-        let users = self.users.lock().map_err(|_| -> ServerError {
-            ServerError {
-                kind: NONCRITICAL.to_string(),
-                message: "Could not access channels".to_string(),
-            }
-        })?;
-
-        let user_kicking = match users.get(&message.prefix.clone().unwrap()) {
+        let user_kicking = match users.get(&irc_lowercase(&message.prefix.clone().unwrap())) {
             Some(user) => user,
             None => {
                 return Err(ServerError {
@@ -919,7 +2149,7 @@ impl Server {
                     message: "Could not lock user clients".to_string(),
                 }
             })?;
-            let sender = users_clients.get_mut(nickname_user_getting_kicked);
+            let sender = users_clients.get_mut(&irc_lowercase(nickname_user_getting_kicked));
 
             if let Some((_, sender_user)) = sender {
                 sender_user.send(message).map_err(|_| -> ServerError {
@@ -938,26 +2168,35 @@ impl Server {
     /// Receives a message with join command. If the server does not have the channel
     /// then gets created. In other case all the correspoding servers get the message
     /// so they can check if they have the channel and all users.
+    /// The join of who created the channel has its creation time. If the channel was created
+    /// here too before knowing about the other one, both are merged
     ///
     fn handle_join(&mut self, message: Message) -> Result<(), ServerError> {
         println!("JOIN handling join in server");
 
         let channel_name = &message.params[0][0];
-        let mut channels = self.channels.lock().map_err(|_| -> ServerError {
+        let nickname_user_joining = &message.prefix.clone().unwrap();
+        let created_at = message
+            .params
+            .get(1)
+            .and_then(|param| param.first())
+            .and_then(|created_at| created_at.parse::<u64>().ok());
+
+        // Users are locked before channels, like the client handlers do when their users join,
+        // else both could wait forever for the lock the other one holds
+        let users = self.users.lock().map_err(|_| -> ServerError {
             ServerError {
                 kind: NONCRITICAL.to_string(),
-                message: "Couldn't get lock".to_string(),
+                message: "Could not access users".to_string(),
             }
         })?;
-
-        let nickname_user_joining = &message.prefix.clone().unwrap();
-        let users = self.users.lock().map_err(|_| -> ServerError {
+        let mut channels = self.channels.lock().map_err(|_| -> ServerError {
             ServerError {
                 kind: NONCRITICAL.to_string(),
                 message: "Could not access channels".to_string(),
             }
         })?;
-        let user_joining = match users.get(nickname_user_joining) {
+        let user_joining = match users.get(&irc_lowercase(nickname_user_joining)) {
             Some(user) => user,
             None => {
                 return Err(ServerError {
@@ -967,14 +2206,17 @@ impl Server {
             }
         };
 
-        if !channels.contains_key(channel_name) {
+        if !channels.contains_key(&irc_lowercase(channel_name)) {
             println!("Channel not found, creating channel");
             // If server does not have channel then create it
             let operator_nick = &message.prefix.clone().unwrap(); // This unwrap is safe because the server will always send the message with a prefix
-            let operator = users.get(operator_nick).unwrap();
-            let channel = Channel::new(channel_name.clone(), operator);
+            let operator = users.get(&irc_lowercase(operator_nick)).unwrap();
+            let mut channel = Channel::new(channel_name.clone(), operator);
+            if let Some(created_at) = created_at {
+                channel.created_at = created_at;
+            }
 
-            channels.insert(channel_name.to_string(), channel);
+            channels.insert(irc_lowercase(&channel.name), channel);
 
             if self.server_data.is_main() {
                 self.server_rol
@@ -982,7 +2224,7 @@ impl Server {
             }
         } else {
             println!("Channel found");
-            let channel = match channels.get_mut(channel_name) {
+            let channel = match channels.get_mut(&irc_lowercase(channel_name)) {
                 Some(channel) => channel,
                 None => {
                     return Err(ServerError {
@@ -994,9 +2236,14 @@ impl Server {
 
             if !channel.is_user_on_channel(nickname_user_joining) {
                 println!("Adding user to channel");
-                channel
-                    .users
-                    .insert(nickname_user_joining.to_string(), user_joining.clone());
+                match created_at {
+                    Some(created_at) => channel.merge_creation(user_joining.clone(), created_at),
+                    None => {
+                        channel
+                            .users
+                            .insert(irc_lowercase(nickname_user_joining), user_joining.clone());
+                    }
+                }
                 if self.server_data.is_main() {
                     self.server_rol
                         .notify_all_but(message, &user_joining.server_name)?;
@@ -1021,118 +2268,969 @@ impl Server {
         message: &Message,
         receiver: &String,
     ) -> Result<(), ServerError> {
+        if self.is_silenced(message, receiver)? {
+            self.logger.info(
+                receiver,
+                &message.command,
+                "Silenced the sender, message dropped",
+            );
+            return Ok(());
+        }
         println!("Sent message: {:?} to {}", message, receiver);
-        let users_clients = self.users_clients.lock().map_err(|_| -> ServerError {
-            ServerError {
-                kind: NONCRITICAL.to_string(),
-                message: "Could not lock user clients".to_string(),
-            }
-        })?;
-        let sender = match users_clients.get(receiver) {
-            Some((_, sender)) => sender,
-            None => {
-                let users = self.users.lock().map_err(|_| -> ServerError {
+        // The locks are released before answering, the answer is sent with this same function
+        let server_name = {
+            let users_clients = self.users_clients.lock().map_err(|_| -> ServerError {
+                ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "Could not lock user clients".to_string(),
+                }
+            })?;
+            if let Some((_, sender)) = users_clients.get(&irc_lowercase(receiver)) {
+                sender.send(message.clone()).map_err(|_| -> ServerError {
                     ServerError {
-                        kind: NONCRITICAL.to_string(),
-                        message: "Couldn't get lock".to_string(),
+                        kind: CRITICAL.to_string(),
+                        message: "Couldn't send".to_string(),
                     }
                 })?;
-                let user = users.get(receiver).unwrap();
-                let server_name = user.server_name.clone();
-                self.server_rol
-                    .send_message_to_server(message.clone(), server_name)?;
-
                 return Ok(());
             }
+            let users = self.users.lock().map_err(|_| -> ServerError {
+                ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "Couldn't get lock".to_string(),
+                }
+            })?;
+            users
+                .get(&irc_lowercase(receiver))
+                .map(|user| user.server_name.clone())
+        };
+
+        match server_name {
+            Some(server_name) => self
+                .server_rol
+                .send_message_to_server(message.clone(), server_name),
+            None => self.reply_no_such_nick(message, receiver),
+        }
+    }
+
+    ///
+    /// The receiver of the message doesn't exist, it could have quit after the message was
+    /// checked. The sender of a private message is answered with ERR_NOSUCHNICK if it is
+    /// connected here, other messages are dropped, a NOTICE is never answered
+    ///
+    fn reply_no_such_nick(&self, message: &Message, receiver: &str) -> Result<(), ServerError> {
+        self.logger.info(
+            receiver,
+            &message.command,
+            "Receiver not found, message dropped",
+        );
+        let nickname_sender = match &message.prefix {
+            Some(nickname) if message.command == PRIVMSG && !irc_eq(nickname, receiver) => nickname,
+            _ => return Ok(()),
+        };
+        let is_sender_here = self
+            .users_clients
+            .lock()
+            .map_err(|_| -> ServerError {
+                ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "Could not lock user clients".to_string(),
+                }
+            })?
+            .contains_key(&irc_lowercase(nickname_sender));
+        if !is_sender_here {
+            return Ok(());
+        }
+
+        let reply = Message {
+            tags: None,
+            prefix: None,
+            command: ERR_NOSUCHNICK_NUM.to_string(),
+            params: vec![
+                vec![receiver.to_string()],
+                vec![ERR_NOSUCHNICK_MSG.to_string()],
+            ],
         };
+        self.send_message_to_receiver(&reply, nickname_sender)
+    }
 
-        sender.send(message.clone()).map_err(|_| -> ServerError {
+    ///
+    /// Returns true if the message is a private message or notice from a user that the
+    /// receiver silenced. Receivers of other servers are checked by their own server
+    ///
+    fn is_silenced(&self, message: &Message, receiver: &str) -> Result<bool, ServerError> {
+        let users = self.users.lock().map_err(|_| -> ServerError {
             ServerError {
-                kind: CRITICAL.to_string(),
-                message: "Couldn't send".to_string(),
+                kind: NONCRITICAL.to_string(),
+                message: "Couldn't get lock".to_string(),
             }
         })?;
-
-        Ok(())
+        Ok(has_silenced_sender(message, receiver, &users))
     }
 
     ///
-    /// This function will Send a Message to every member of the channel
+    /// This function will Send a Message to every member of the channel.
+    /// Where it goes for each member is found first, taking the channels, the users and
+    /// their clients once, and the message is sent after they are released
     ///
     fn send_message_to_channel(
         &self,
         channel_name: &String,
-        nickname_sender: &String,
+        nickname_sender: &str,
         message: &Message,
     ) -> Result<(), ServerError> {
         println!("Send message to channel: {}", channel_name);
-        let channels = self.channels.lock().map_err(|_| -> ServerError {
-            ServerError {
-                kind: NONCRITICAL.to_string(),
-                message: "Couldn't get lock".to_string(),
-            }
-        })?;
-        match channels.get(channel_name) {
-            Some(channel) => {
-                for nickname in channel.users.keys() {
-                    let is_in_server = self.users_clients.lock().unwrap().contains_key(nickname);
-                    if self.send_priv_msg(nickname_sender, nickname, is_in_server)?
-                        && nickname != nickname_sender
-                    {
-                        self.send_message_to_receiver(message, nickname)?;
-                    }
+        let (message, members) = {
+            let channels = self.channels.lock().map_err(|_| -> ServerError {
+                ServerError {
+                    kind: NONCRITICAL.to_string(),
+                    message: "Couldn't get lock".to_string(),
+                }
+            })?;
+            let channel = match channels.get(&irc_lowercase(channel_name)) {
+                Some(channel) => channel,
+                None => {
+                    return Err(ServerError {
+                        kind: CRITICAL.to_string(),
+                        message: "Couldn't get channel".to_string(),
+                    });
+                }
+            };
+
+            // Members receive the channel name as it was created, whatever case the sender used
+            let mut message = message.clone();
+            if let Some(receiver) = message.params.first_mut().and_then(|p| p.first_mut()) {
+                if irc_eq(receiver, &channel.name) {
+                    *receiver = channel.name.clone();
                 }
             }
-            None => {
-                return Err(ServerError {
-                    kind: CRITICAL.to_string(),
-                    message: "Couldn't get channel".to_string(),
-                });
-            }
-        }
-        Ok(())
+            let members: Vec<String> = channel.users.keys().cloned().collect();
+            (message, members)
+        };
+
+        let recipients = self.channel_recipients(&message, nickname_sender, &members)?;
+        self.send_to_recipients(&message, recipients)
     }
 
-    fn send_priv_msg(
+    ///
+    /// Returns where the message has to be sent for each member given, except its sender.
+    /// Members of other servers are reached through their server, unless the sender is from
+    /// their same server, which already sent it to them. The members that silenced the sender
+    /// are left out
+    ///
+    fn channel_recipients(
         &self,
+        message: &Message,
         nickname_sender: &str,
-        nickname_receiver: &str,
-        is_in_server: bool,
-    ) -> Result<bool, ServerError> {
-        if is_in_server {
-            println!("Sending message client in main");
-            return Ok(true);
-        }
+        members: &[String],
+    ) -> Result<Vec<Recipient>, ServerError> {
+        let users_clients = self.users_clients.lock().map_err(|_| -> ServerError {
+            ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "Could not lock user clients".to_string(),
+            }
+        })?;
         let users = self.users.lock().map_err(|_| -> ServerError {
             ServerError {
                 kind: NONCRITICAL.to_string(),
                 message: "Couldn't lock users".to_string(),
             }
         })?;
-        let user = match users.get(nickname_sender) {
-            Some(user) => user,
-            None => {
-                return Err(ServerError {
-                    kind: NONCRITICAL.to_string(),
-                    message: "User doesn't exist".to_string(),
-                })
+        let user_doesnt_exist = || -> ServerError {
+            ServerError {
+                kind: NONCRITICAL.to_string(),
+                message: "User doesn't exist".to_string(),
             }
         };
-        let user_receiver = match users.get(nickname_receiver) {
-            Some(user) => user,
-            None => {
-                return Err(ServerError {
-                    kind: NONCRITICAL.to_string(),
-                    message: "User doesn't exist".to_string(),
-                })
+
+        let mut recipients = vec![];
+        let mut silenced = vec![];
+        for nickname in members {
+            if irc_eq(nickname, nickname_sender) {
+                continue;
             }
+            let recipient = match users_clients.get(&irc_lowercase(nickname)) {
+                Some((_, sender)) => Recipient::Client(sender.clone()),
+                None => {
+                    let sender_server = &users
+                        .get(&irc_lowercase(nickname_sender))
+                        .ok_or_else(user_doesnt_exist)?
+                        .server_name;
+                    let receiver_server = &users
+                        .get(&irc_lowercase(nickname))
+                        .ok_or_else(user_doesnt_exist)?
+                        .server_name;
+                    if sender_server == receiver_server {
+                        continue;
+                    }
+                    Recipient::Server(receiver_server.clone())
+                }
+            };
+
+            if has_silenced_sender(message, nickname, &users) {
+                silenced.push(nickname);
+                continue;
+            }
+            recipients.push(recipient);
+        }
+        drop(users);
+        drop(users_clients);
+
+        // Logged once the locks are released, so the other threads don't wait on the output
+        for nickname in silenced {
+            self.logger.info(
+                nickname,
+                &message.command,
+                "Silenced the sender, message dropped",
+            );
+        }
+        Ok(recipients)
+    }
+
+    ///
+    /// Sends the message to each recipient, without taking any lock of the server
+    ///
+    fn send_to_recipients(
+        &self,
+        message: &Message,
+        recipients: Vec<Recipient>,
+    ) -> Result<(), ServerError> {
+        for recipient in recipients {
+            match recipient {
+                Recipient::Client(sender) => {
+                    sender.send(message.clone()).map_err(|_| -> ServerError {
+                        ServerError {
+                            kind: CRITICAL.to_string(),
+                            message: "Couldn't send".to_string(),
+                        }
+                    })?
+                }
+                Recipient::Server(server_name) => self
+                    .server_rol
+                    .send_message_to_server(message.clone(), server_name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+///
+/// Where a message for a member of a channel is sent: to its client if it is connected to
+/// this server, or to the server it is connected to
+///
+enum Recipient {
+    Client(Sender<Message>),
+    Server(String),
+}
+
+///
+/// Returns true if the message is a private message or notice from a user that the
+/// receiver silenced
+///
+fn has_silenced_sender(message: &Message, receiver: &str, users: &HashMap<String, User>) -> bool {
+    if message.command != PRIVMSG && message.command != NOTICE {
+        return false;
+    }
+    match (&message.prefix, users.get(&irc_lowercase(receiver))) {
+        (Some(nickname_sender), Some(user)) => user.has_silenced(nickname_sender),
+        _ => false,
+    }
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::{Server, WhowasHistory, SHUTDOWN_MESSAGE};
+    use crate::{
+        commands::{
+            NOTICE, OPERATOR, PRIVMSG, QUIT, REGISTRATION, REHASH, SAVE_CHANNELS, SERVER, SHUTDOWN,
+            SQUIT, TOPIC, USER_MODE,
+        },
+        custom_errors::errors::NONCRITICAL,
+        message::Message,
+        numeric_reply::{
+            ERR_NICKCOLLISION_MSG, ERR_NICKCOLLISION_NUM, ERR_NOSUCHNICK_MSG, ERR_NOSUCHNICK_NUM,
+        },
+        server_utils::{channel::Channel, server_data::ServerData, user::User},
+    };
+    use std::{fs, sync::mpsc, thread};
+
+    fn user(nickname: &str, real_name: &str) -> User {
+        User::new(
+            nickname.to_string(),
+            "127.0.0.1".to_string(),
+            nickname.to_string(),
+            real_name.to_string(),
+            "main_server".to_string(),
+            "password123".to_string(),
+        )
+    }
+
+    #[test]
+    fn whowas_history_finds_the_newest_users_first_and_forgets_the_oldest() {
+        let mut history = WhowasHistory::new(3);
+        history.record(user("ari", "Ariel"));
+        history.record(user("lucas", "Lucas"));
+        history.record(user("Ari", "Ariel again"));
+
+        let found: Vec<&str> = history
+            .find("ARI")
+            .iter()
+            .map(|user| user.real_name.as_str())
+            .collect();
+        assert_eq!(found, vec!["Ariel again", "Ariel"]);
+
+        history.record(user("marce", "Marcelo"));
+        assert_eq!(history.find("ari").len(), 1);
+        assert_eq!(history.find("marce").len(), 1);
+        assert!(history.find("juanireil").is_empty());
+    }
+
+    #[test]
+    fn users_of_a_server_that_left_are_removed_and_their_channels_notified() {
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        let mut server = Server::new(server_data).unwrap();
+
+        let juani = user("juani", "Juan");
+        let mut ari = user("ari", "Ariel");
+        ari.server_name = "secondary_server".to_string();
+        ari.add_channel(&"#rust".to_string());
+        let mut channel = Channel::new("#rust".to_string(), &juani);
+        channel.join(ari.clone(), None).unwrap();
+
+        server
+            .users
+            .lock()
+            .unwrap()
+            .extend([("juani".to_string(), juani), ("ari".to_string(), ari)]);
+        server
+            .channels
+            .lock()
+            .unwrap()
+            .insert("#rust".to_string(), channel);
+        let (sender_to_juani, receiver_juani) = mpsc::channel();
+        server
+            .users_clients
+            .lock()
+            .unwrap()
+            .insert("juani".to_string(), (None, sender_to_juani));
+
+        server
+            .sender_to_server
+            .send(Message {
+                tags: None,
+                prefix: None,
+                command: SQUIT.to_string(),
+                params: vec![
+                    vec!["secondary_server".to_string()],
+                    vec!["bye".to_string()],
+                ],
+            })
+            .unwrap();
+        server.check_messages().unwrap();
+
+        let users = server.users.lock().unwrap();
+        assert!(!users.contains_key("ari"));
+        assert!(users.contains_key("juani"));
+        let channels = server.channels.lock().unwrap();
+        assert!(!channels["#rust"].users.contains_key("ari"));
+
+        let quit = receiver_juani.try_recv().unwrap();
+        assert_eq!(quit.command, QUIT);
+        assert_eq!(quit.prefix, Some("ari".to_string()));
+        assert_eq!(server.whowas_history.find("ari").len(), 1);
+    }
+
+    #[test]
+    fn topic_changes_are_sent_to_the_members_of_this_server_but_the_setter() {
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        let mut server = Server::new(server_data).unwrap();
+
+        let juani = user("juani", "Juan");
+        let ari = user("ari", "Ariel");
+        let mut channel = Channel::new("&Rust".to_string(), &juani);
+        channel.join(ari.clone(), None).unwrap();
+        channel
+            .set_topic(&"juani".to_string(), "Rust talk", 10)
+            .unwrap();
+        server
+            .users
+            .lock()
+            .unwrap()
+            .extend([("juani".to_string(), juani), ("ari".to_string(), ari)]);
+        server
+            .channels
+            .lock()
+            .unwrap()
+            .insert("&rust".to_string(), channel);
+        let (sender_to_juani, receiver_juani) = mpsc::channel();
+        let (sender_to_ari, receiver_ari) = mpsc::channel();
+        server.users_clients.lock().unwrap().extend([
+            ("juani".to_string(), (None, sender_to_juani)),
+            ("ari".to_string(), (None, sender_to_ari)),
+        ]);
+
+        server
+            .sender_to_server
+            .send(Message {
+                tags: None,
+                prefix: Some("juani".to_string()),
+                command: TOPIC.to_string(),
+                params: vec![
+                    vec!["&rust".to_string()],
+                    vec!["10".to_string()],
+                    vec!["Rust talk".to_string()],
+                ],
+            })
+            .unwrap();
+        server.check_messages().unwrap();
+
+        let topic_change = receiver_ari.try_recv().unwrap();
+        assert_eq!(topic_change.command, TOPIC);
+        assert_eq!(topic_change.prefix, Some("juani".to_string()));
+        assert_eq!(
+            topic_change.params,
+            vec![vec!["&Rust".to_string()], vec!["Rust talk".to_string()]]
+        );
+        assert!(receiver_juani.try_recv().is_err());
+    }
+
+    #[test]
+    fn messages_to_a_channel_are_sent_after_releasing_the_users() {
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        let server = Server::new(server_data).unwrap();
+
+        let members: Vec<User> = (0..500)
+            .map(|number| user(&format!("member{}", number), "Member"))
+            .collect();
+        let mut channel = Channel::new("#crowd".to_string(), &members[0]);
+        let mut receivers = vec![];
+        for member in &members {
+            channel.join(member.clone(), None).unwrap();
+            let (sender, receiver) = mpsc::channel();
+            receivers.push(receiver);
+            server
+                .users_clients
+                .lock()
+                .unwrap()
+                .insert(member.nickname.clone(), (None, sender));
+            server
+                .users
+                .lock()
+                .unwrap()
+                .insert(member.nickname.clone(), member.clone());
+        }
+        server
+            .channels
+            .lock()
+            .unwrap()
+            .insert("#crowd".to_string(), channel);
+
+        let message = Message {
+            tags: None,
+            prefix: Some("member0".to_string()),
+            command: PRIVMSG.to_string(),
+            params: vec![vec!["#crowd".to_string()], vec!["hola".to_string()]],
         };
+        let members: Vec<String> = members.into_iter().map(|user| user.nickname).collect();
+        let recipients = server
+            .channel_recipients(&message, "member0", &members)
+            .unwrap();
+        assert_eq!(recipients.len(), 499);
+
+        // The users are taken once to find the recipients, none is needed to send to them
+        let _users_clients = server.users_clients.lock().unwrap();
+        let _users = server.users.lock().unwrap();
+        server.send_to_recipients(&message, recipients).unwrap();
+
+        assert!(receivers[0].try_recv().is_err());
+        for receiver in &receivers[1..] {
+            assert_eq!(receiver.try_recv().unwrap().params[1], vec!["hola"]);
+            assert!(receiver.try_recv().is_err());
+        }
+    }
 
-        let sender_server = &user.server_name;
-        let receiver_server = &user_receiver.server_name;
-        if sender_server == receiver_server {
-            return Ok(false);
+    #[test]
+    fn private_messages_to_the_own_nickname_are_dropped() {
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        let mut server = Server::new(server_data).unwrap();
+
+        server
+            .users
+            .lock()
+            .unwrap()
+            .insert("ari".to_string(), user("ari", "Ariel"));
+        let (sender_to_ari, receiver_ari) = mpsc::channel();
+        server
+            .users_clients
+            .lock()
+            .unwrap()
+            .insert("ari".to_string(), (None, sender_to_ari));
+
+        server
+            .sender_to_server
+            .send(Message {
+                tags: None,
+                prefix: Some("ari".to_string()),
+                command: PRIVMSG.to_string(),
+                params: vec![vec!["ARI".to_string()], vec!["hola".to_string()]],
+            })
+            .unwrap();
+        server.check_messages().unwrap();
+
+        assert!(receiver_ari.try_recv().is_err());
+    }
+
+    #[test]
+    fn private_messages_to_a_nickname_that_doesnt_exist_are_answered_with_no_such_nick() {
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        let mut server = Server::new(server_data).unwrap();
+
+        server
+            .users
+            .lock()
+            .unwrap()
+            .insert("ari".to_string(), user("ari", "Ariel"));
+        let (sender_to_ari, receiver_ari) = mpsc::channel();
+        server
+            .users_clients
+            .lock()
+            .unwrap()
+            .insert("ari".to_string(), (None, sender_to_ari));
+
+        for command in [PRIVMSG, NOTICE] {
+            server
+                .sender_to_server
+                .send(Message {
+                    tags: None,
+                    prefix: Some("ari".to_string()),
+                    command: command.to_string(),
+                    params: vec![vec!["nadie".to_string()], vec!["hola".to_string()]],
+                })
+                .unwrap();
+            server.check_messages().unwrap();
+        }
+
+        let reply = receiver_ari.try_recv().unwrap();
+        assert_eq!(
+            reply.as_string(),
+            format!("{} nadie :{}\r\n", ERR_NOSUCHNICK_NUM, ERR_NOSUCHNICK_MSG)
+        );
+        // The notice is not answered
+        assert!(receiver_ari.try_recv().is_err());
+    }
+
+    #[test]
+    fn shutdown_tells_the_users_and_waits_for_their_threads() {
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        let mut server = Server::new(server_data).unwrap();
+        let (sender_to_ari, receiver_ari) = mpsc::channel();
+        let handler = thread::spawn(move || {
+            // Like a client handler, it finishes after the QUIT of the server
+            let quit: Message = receiver_ari.recv().unwrap();
+            assert_eq!(quit.command, QUIT);
+            assert_eq!(quit.params, vec![vec![SHUTDOWN_MESSAGE.to_string()]]);
+            Ok(())
+        });
+        server
+            .users_clients
+            .lock()
+            .unwrap()
+            .insert("ari".to_string(), (Some(handler), sender_to_ari));
+
+        server
+            .sender_to_server
+            .send(Message {
+                tags: None,
+                prefix: None,
+                command: SHUTDOWN.to_string(),
+                params: vec![],
+            })
+            .unwrap();
+        let result = server.check_messages();
+
+        assert_eq!(result.unwrap_err().kind, SHUTDOWN);
+        assert!(server.users_clients.lock().unwrap()["ari"].0.is_none());
+    }
+
+    #[test]
+    fn user_modes_from_other_servers_are_set() {
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        let mut server = Server::new(server_data).unwrap();
+        server
+            .users
+            .lock()
+            .unwrap()
+            .insert("ari".to_string(), user("ari", "Ariel"));
+
+        for (mode, invisible) in [("+i", true), ("-i", false)] {
+            server
+                .sender_to_server
+                .send(Message {
+                    tags: None,
+                    prefix: Some("ari".to_string()),
+                    command: USER_MODE.to_string(),
+                    params: vec![vec!["ARI".to_string()], vec![mode.to_string()]],
+                })
+                .unwrap();
+            server.check_messages().unwrap();
+
+            assert_eq!(server.users.lock().unwrap()["ari"].invisible, invisible);
+        }
+    }
+
+    #[test]
+    fn operators_are_checked_against_the_opers_file() {
+        // juanireil:clave_de_oper
+
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        let mut server = Server::new(server_data).unwrap();
+        let (sender_to_ari, receiver_ari) = mpsc::channel();
+        server
+            .users_clients
+            .lock()
+            .unwrap()
+            .insert("ari".to_string(), (None, sender_to_ari));
+
+        for (name, password, answer) in [
+            ("lucas", "clave_de_oper", "Unknown operator"),
+            ("juanireil", "password123", "Wrong password"),
+            ("juanireil", "clave_de_oper", "You are now an operator"),
+        ] {
+            server
+                .sender_to_server
+                .send(Message {
+                    tags: None,
+                    prefix: Some("ari".to_string()),
+                    command: OPERATOR.to_string(),
+                    params: vec![vec![name.to_string()], vec![password.to_string()]],
+                })
+                .unwrap();
+            server.check_messages().unwrap();
+
+            assert_eq!(receiver_ari.try_recv().unwrap().params, vec![vec![answer]]);
+        }
+        assert_eq!(server.operator, "ari");
+    }
+
+    #[test]
+    fn rehash_of_the_operator_reloads_the_settings_but_not_the_address() {
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        let mut server = Server::new(server_data).unwrap();
+        let path = std::env::temp_dir().join("irc_rehash_test.txt");
+        fs::write(
+            &path,
+            "S;test_server;127.0.0.1:4000;none;saved_files/users_test.txt;\n\
+             A;contrasena;juanireil\nW;2\nN;15\nJ;false\nX;3\n",
+        )
+        .unwrap();
+        server.server_data.file_path = path.to_string_lossy().to_string();
+        server.operator = "ari".to_string();
+        let (sender_to_ari, receiver_ari) = mpsc::channel();
+        let (sender_to_lucas, receiver_lucas) = mpsc::channel();
+        server.users_clients.lock().unwrap().extend([
+            ("ari".to_string(), (None, sender_to_ari)),
+            ("lucas".to_string(), (None, sender_to_lucas)),
+        ]);
+        let rehash = |nickname: &str| Message {
+            tags: None,
+            prefix: Some(nickname.to_string()),
+            command: REHASH.to_string(),
+            params: vec![],
+        };
+
+        server.sender_to_server.send(rehash("lucas")).unwrap();
+        server.check_messages().unwrap();
+        assert_eq!(
+            receiver_lucas.try_recv().unwrap().params[0],
+            vec!["You are not an operator"]
+        );
+        assert_eq!(server.server_data.max_nickname_length, 12);
+
+        server.sender_to_server.send(rehash("ari")).unwrap();
+        server.check_messages().unwrap();
+        assert_eq!(
+            receiver_ari.try_recv().unwrap().params[0],
+            vec!["Rehashing"]
+        );
+        assert_eq!(server.server_data.max_nickname_length, 15);
+        assert_eq!(server.server_data.whowas_history_size, 2);
+        assert_eq!(server.server_data.server_address, "127.0.0.1:3000");
+        let settings = server.connection_settings.lock().unwrap().clone();
+        assert_eq!(settings.max_nickname_length, 15);
+        assert!(!settings.away_on_join);
+        assert_eq!(settings.max_channels_per_join, 3);
+
+        // Without the file the settings are kept
+        fs::remove_file(&path).unwrap();
+        server.sender_to_server.send(rehash("ari")).unwrap();
+        server.check_messages().unwrap();
+        assert_eq!(
+            receiver_ari.try_recv().unwrap().params[0],
+            vec!["Could not read the server data file"]
+        );
+        assert_eq!(server.server_data.max_nickname_length, 15);
+    }
+
+    fn registration(nickname: &str, server_name: &str, real_name: &str) -> Message {
+        Message {
+            tags: None,
+            prefix: Some(nickname.to_string()),
+            command: REGISTRATION.to_string(),
+            params: vec![
+                vec![
+                    nickname.to_string(),
+                    "127.0.0.1".to_string(),
+                    nickname.to_string(),
+                    server_name.to_string(),
+                    "password123".to_string(),
+                ],
+                vec![real_name.to_string()],
+            ],
+        }
+    }
+
+    #[test]
+    fn truncated_registration_is_an_error_and_the_user_is_not_added() {
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        let mut server = Server::new(server_data).unwrap();
+        let mut message = registration("pedro", "test_server", "Pedro");
+        message.params.truncate(1);
+        server.sender_to_server.send(message).unwrap();
+
+        let err = server.check_messages().unwrap_err();
+        assert_eq!(err.kind, NONCRITICAL);
+        assert!(!server.users.lock().unwrap().contains_key("pedro"));
+    }
+
+    #[test]
+    fn registration_of_a_nickname_connected_here_is_answered_with_nick_collision() {
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        let mut server = Server::new(server_data).unwrap();
+        let mut ari = user("ari", "Ariel");
+        ari.server_name = "test_server".to_string();
+        server.users.lock().unwrap().insert("ari".to_string(), ari);
+        let (sender_to_ari, _receiver_ari) = mpsc::channel();
+        server
+            .users_clients
+            .lock()
+            .unwrap()
+            .insert("ari".to_string(), (None, sender_to_ari));
+        let (sender_to_secondary, receiver_secondary) = mpsc::channel();
+        server
+            .server_rol
+            .handle_server(
+                Message {
+                    tags: None,
+                    prefix: None,
+                    command: SERVER.to_string(),
+                    params: vec![vec!["secondary_server".to_string()]],
+                },
+                thread::spawn(|| Ok(())),
+                sender_to_secondary,
+                "main_server".to_string(),
+                server.users.clone(),
+                server.channels.clone(),
+            )
+            .unwrap();
+
+        server
+            .sender_to_server
+            .send(registration("ARI", "secondary_server", "Another Ari"))
+            .unwrap();
+        server.check_messages().unwrap();
+
+        // The user connected here is kept and the other server is told
+        let users = server.users.lock().unwrap();
+        assert_eq!(users["ari"].real_name, "Ariel");
+        assert_eq!(users["ari"].server_name, "test_server");
+        let collision = receiver_secondary
+            .try_iter()
+            .find(|message| message.command == ERR_NICKCOLLISION_NUM)
+            .unwrap();
+        assert_eq!(
+            collision.params[0],
+            vec!["ARI".to_string(), "secondary_server".to_string()]
+        );
+    }
+
+    #[test]
+    fn nick_collision_of_a_user_registered_here_closes_its_client() {
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        let mut server = Server::new(server_data).unwrap();
+        let mut ari = user("ari", "Ariel");
+        ari.server_name = "test_server".to_string();
+        server.users.lock().unwrap().insert("ari".to_string(), ari);
+        // The handler ends after telling the client, like the one of a connection does
+        let (sender_to_ari, receiver_ari) = mpsc::channel::<Message>();
+        let handler = thread::spawn(move || {
+            assert_eq!(receiver_ari.recv().unwrap().command, ERR_NICKCOLLISION_NUM);
+            Ok(())
+        });
+        server
+            .users_clients
+            .lock()
+            .unwrap()
+            .insert("ari".to_string(), (Some(handler), sender_to_ari));
+
+        server
+            .sender_to_server
+            .send(Message {
+                tags: None,
+                prefix: None,
+                command: ERR_NICKCOLLISION_NUM.to_string(),
+                params: vec![vec!["ari".to_string(), "test_server".to_string()]],
+            })
+            .unwrap();
+        server.check_messages().unwrap();
+
+        assert!(!server.users_clients.lock().unwrap().contains_key("ari"));
+        assert!(!server.users.lock().unwrap().contains_key("ari"));
+    }
+
+    #[test]
+    fn user_propagated_again_between_servers_is_reused() {
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        let mut server = Server::new(server_data).unwrap();
+        let mut lucas = user("lucas", "Lucas");
+        lucas.server_name = "secondary_server".to_string();
+        server
+            .users
+            .lock()
+            .unwrap()
+            .insert("lucas".to_string(), lucas);
+
+        server
+            .sender_to_server
+            .send(registration("lucas", "secondary_server", "Lucas again"))
+            .unwrap();
+        assert!(server.check_messages().is_ok());
+
+        let users = server.users.lock().unwrap();
+        assert_eq!(users["lucas"].real_name, "Lucas");
+        assert_eq!(users["lucas"].server_name, "secondary_server");
+    }
+
+    #[test]
+    fn user_registered_here_with_a_nickname_in_use_is_removed_and_told() {
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        let mut server = Server::new(server_data).unwrap();
+        server
+            .users
+            .lock()
+            .unwrap()
+            .insert("ari".to_string(), user("ari", "Ariel"));
+        let (sender_to_ari, receiver_ari) = mpsc::channel();
+        server
+            .users_clients
+            .lock()
+            .unwrap()
+            .insert("ari".to_string(), (None, sender_to_ari));
+
+        server
+            .sender_to_server
+            .send(Message {
+                tags: None,
+                prefix: None,
+                command: ERR_NICKCOLLISION_NUM.to_string(),
+                params: vec![
+                    vec!["ari".to_string(), "test_server".to_string()],
+                    vec![ERR_NICKCOLLISION_MSG.to_string()],
+                ],
+            })
+            .unwrap();
+        server.check_messages().unwrap();
+
+        assert!(!server.users.lock().unwrap().contains_key("ari"));
+        assert_eq!(
+            receiver_ari.try_recv().unwrap().command,
+            ERR_NICKCOLLISION_NUM
+        );
+    }
+
+    #[test]
+    fn quit_of_a_user_of_another_server_reaches_the_members_connected_here_once() {
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        let mut server = Server::new(server_data).unwrap();
+
+        let juani = user("juani", "Juan");
+        let mut ari = user("ari", "Ariel");
+        ari.server_name = "secondary_server".to_string();
+        ari.add_channel(&"#rust".to_string());
+        let mut channel = Channel::new("#rust".to_string(), &juani);
+        channel.join(ari.clone(), None).unwrap();
+
+        server
+            .users
+            .lock()
+            .unwrap()
+            .extend([("juani".to_string(), juani), ("ari".to_string(), ari)]);
+        server
+            .channels
+            .lock()
+            .unwrap()
+            .insert("#rust".to_string(), channel);
+        let (sender_to_juani, receiver_juani) = mpsc::channel();
+        server
+            .users_clients
+            .lock()
+            .unwrap()
+            .insert("juani".to_string(), (None, sender_to_juani));
+
+        let quit = Message {
+            tags: None,
+            prefix: Some("ari".to_string()),
+            command: QUIT.to_string(),
+            params: vec![vec!["secondary_server left the network".to_string()]],
+        };
+        server.sender_to_server.send(quit.clone()).unwrap();
+        server.sender_to_server.send(quit).unwrap();
+        server.check_messages().unwrap();
+        server.check_messages().unwrap();
+
+        assert_eq!(receiver_juani.try_recv().unwrap().command, QUIT);
+        assert!(receiver_juani.try_recv().is_err());
+        let channels = server.channels.lock().unwrap();
+        assert!(!channels["#rust"].users.contains_key("ari"));
+        assert!(server.users.lock().unwrap()["ari"].channels.is_empty());
+    }
+
+    #[test]
+    fn heartbeat_increments_for_every_message_processed() {
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        let mut server = Server::new(server_data).unwrap();
+        let heartbeat = server.heartbeat.clone();
+        let message = Message {
+            tags: None,
+            prefix: None,
+            command: SAVE_CHANNELS.to_string(),
+            params: vec![],
+        };
+
+        for _ in 0..3 {
+            server.sender_to_server.send(message.clone()).unwrap();
+        }
+
+        for processed in 1..=3 {
+            server.check_messages().unwrap();
+            assert_eq!(heartbeat.count(), processed);
         }
-        Ok(true)
     }
 }
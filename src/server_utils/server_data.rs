@@ -3,17 +3,70 @@
 //!
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet, VecDeque},
     fs::{File, OpenOptions},
     io::{BufRead, BufReader, Error, ErrorKind, Write},
+    net::{IpAddr, SocketAddr},
+    time::{Duration, Instant},
 };
 
-use super::channel::Channel;
+use super::{
+    channel::{now_in_seconds, Channel, FloodProtection},
+    connection_limit::ConnectionLimit,
+    logger::LogLevel,
+    rate_limit::MessageRate,
+};
+use crate::custom_errors::{errors::CRITICAL, server_error::ServerError};
+use crate::parser::irc_lowercase;
 use crate::server_utils::user::User;
 
 const SERVER_IDENTIFIER: &str = "S";
 const USER_IDENTIFIER: &str = "U";
 const ADMIN_IDENTIFIER: &str = "A";
+const CHANNELS_PER_JOIN_IDENTIFIER: &str = "X";
+const CHANNEL_IDENTIFIER: &str = "C";
+// Line of the channels file with the founder of a registered channel
+const CHANNEL_FOUNDER_IDENTIFIER: &str = "F";
+const MOTD_IDENTIFIER: &str = "M";
+const PASSWORD_POLICY_IDENTIFIER: &str = "P";
+const TLS_IDENTIFIER: &str = "T";
+const RATE_LIMIT_IDENTIFIER: &str = "R";
+const WHOWAS_IDENTIFIER: &str = "W";
+const BACKLOG_IDENTIFIER: &str = "B";
+const LOG_IDENTIFIER: &str = "L";
+const NICKNAME_IDENTIFIER: &str = "N";
+const OPERS_IDENTIFIER: &str = "O";
+const AWAY_ON_JOIN_IDENTIFIER: &str = "J";
+const CONNECTION_LIMIT_IDENTIFIER: &str = "I";
+
+// Separates the name of an operator from its password in the operators file
+const OPER_SEPARATOR: char = ':';
+
+// Characters that set which classes a password policy requires
+const POLICY_LETTER: char = 'l';
+const POLICY_UPPERCASE: char = 'u';
+const POLICY_DIGIT: char = 'd';
+const POLICY_SYMBOL: char = 's';
+
+// Minimum length of passwords when the server data file has no password policy
+pub const DEFAULT_PASSWORD_MIN_LENGTH: usize = 8;
+
+// Users that quit kept for WHOWAS when the server data file has no history size
+pub const DEFAULT_WHOWAS_HISTORY_SIZE: usize = 100;
+// Channels processed in a single JOIN when the server data file has no amount, the rest are rejected
+pub const DEFAULT_MAX_CHANNELS_PER_JOIN: usize = 5;
+// Messages of each channel replayed to users that join when the server data file has no backlog size
+pub const DEFAULT_CHANNEL_BACKLOG_SIZE: usize = 0;
+// Longest nickname new users can register when the server data file has no nickname length, as in RFC 2812
+pub const DEFAULT_MAX_NICKNAME_LENGTH: usize = 9;
+
+// Separates the nicknames of a list inside a channel line
+const NICKNAMES_SEPARATOR: char = ',';
+
+// Registered users kept in memory before writing them all to the users file
+pub const USERS_BATCH_SIZE: usize = 10;
+// Longest time registered users wait in memory before being written
+pub const USERS_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
 
 #[derive(Debug)]
 pub struct ServerData {
@@ -26,6 +79,48 @@ pub struct ServerData {
     pub channels: HashMap<String, Channel>,
     pub main: Option<(String, String)>,
     pub users_file_path: String,
+    pub max_channels_per_join: usize,
+    pub channels_file_path: String,
+    pub motd_file_path: String,
+    pub opers_file_path: String,
+    pub password_policy: PasswordPolicy,
+    pub max_nickname_length: usize,
+    pub message_rate: MessageRate,
+    pub connection_limit: ConnectionLimit,
+    pub whowas_history_size: usize,
+    pub channel_backlog_size: usize,
+    pub away_on_join: bool, //tells the users that join a channel which members are away
+    pub log_file_path: Option<String>, //None if the log lines are printed
+    pub log_level: LogLevel,
+    pub tls_files: Option<(String, String)>, //certificate and private key paths, None if TLS is off
+    pub created_at: u64, //seconds since epoch when the server started, a rehash keeps it
+}
+
+///
+/// Requirements that the password of a new user must meet
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_letter: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+}
+
+impl Default for PasswordPolicy {
+    ///
+    /// Passwords must have at least 8 characters, a letter and a digit
+    ///
+    fn default() -> Self {
+        PasswordPolicy {
+            min_length: DEFAULT_PASSWORD_MIN_LENGTH,
+            require_letter: true,
+            require_uppercase: false,
+            require_digit: true,
+            require_symbol: false,
+        }
+    }
 }
 
 impl ServerData {
@@ -46,6 +141,21 @@ impl ServerData {
             channels: HashMap::new(),
             main: None,
             users_file_path: none.clone(),
+            max_channels_per_join: DEFAULT_MAX_CHANNELS_PER_JOIN,
+            channels_file_path: none.clone(),
+            motd_file_path: none.clone(),
+            opers_file_path: none.clone(),
+            password_policy: PasswordPolicy::default(),
+            max_nickname_length: DEFAULT_MAX_NICKNAME_LENGTH,
+            message_rate: MessageRate::default(),
+            connection_limit: ConnectionLimit::default(),
+            whowas_history_size: DEFAULT_WHOWAS_HISTORY_SIZE,
+            channel_backlog_size: DEFAULT_CHANNEL_BACKLOG_SIZE,
+            away_on_join: false,
+            log_file_path: None,
+            log_level: LogLevel::Info,
+            tls_files: None,
+            created_at: now_in_seconds(),
         };
 
         set_server_data(&mut server_data, path)?;
@@ -69,11 +179,13 @@ impl ServerData {
         name: String,
         main: Option<(String, String)>,
         users_file_path: String,
+        channels_file_path: String,
     ) {
         self.server_address = addres;
         self.server_name = name;
         self.main = main;
         self.users_file_path = users_file_path;
+        self.channels_file_path = channels_file_path;
     }
 
     ///
@@ -88,7 +200,7 @@ impl ServerData {
     /// This will add a user only if server is main
     ///
     pub fn add_user(&mut self, user: User) {
-        self.users.insert(user.nickname.clone(), user);
+        self.users.insert(irc_lowercase(&user.nickname), user);
     }
 
     ///
@@ -97,6 +209,47 @@ impl ServerData {
     pub fn is_main(&self) -> bool {
         self.main.is_none()
     }
+
+    ///
+    /// Returns the address the server listens to, or an error if it is malformed
+    ///
+    pub fn bind_address(&self) -> Result<SocketAddr, ServerError> {
+        parse_bind_address(&self.server_address)
+    }
+}
+
+/******************************SERVER ADDRESS**********************************/
+
+///
+/// Parses the address the server listens to, an ip and a port like 127.0.0.1:3000.
+/// The ip 0.0.0.0 listens on every network interface, so users in the LAN can connect
+///
+pub fn parse_bind_address(address: &str) -> Result<SocketAddr, ServerError> {
+    let (ip, port) = match address.rsplit_once(':') {
+        Some((ip, port)) => (ip, port),
+        None => return Err(invalid_address(address, "expected ip:port")),
+    };
+
+    let ip = ip
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+        .map_err(|_| invalid_address(address, "the ip is not valid"))?;
+
+    match port.parse::<u16>() {
+        Ok(port) if port != 0 => Ok(SocketAddr::new(ip, port)),
+        _ => Err(invalid_address(
+            address,
+            "the port must be a number between 1 and 65535",
+        )),
+    }
+}
+
+fn invalid_address(address: &str, reason: &str) -> ServerError {
+    ServerError {
+        kind: CRITICAL.to_string(),
+        message: format!("Invalid server address {}: {}", address, reason),
+    }
 }
 
 /******************************READ SERVER DATA FILE**********************************/
@@ -112,6 +265,8 @@ fn set_server_data(server_data: &mut ServerData, path: String) -> Result<(), Err
         read_file_and_set_info(server_data, server_data.users_file_path.to_string())?;
     }
 
+    server_data.channels = load_channels(server_data.channels_file_path.to_string())?;
+
     Ok(())
 }
 
@@ -141,6 +296,18 @@ fn parse_line(line: Vec<&str>, server_data: &mut ServerData) {
         SERVER_IDENTIFIER => parse_and_set_server_data(line, server_data),
         USER_IDENTIFIER => parse_and_set_user(line, server_data),
         ADMIN_IDENTIFIER => parse_and_set_admin(line, server_data),
+        CHANNELS_PER_JOIN_IDENTIFIER => parse_and_set_max_channels_per_join(line, server_data),
+        MOTD_IDENTIFIER => parse_and_set_motd(line, server_data),
+        OPERS_IDENTIFIER => parse_and_set_opers(line, server_data),
+        PASSWORD_POLICY_IDENTIFIER => parse_and_set_password_policy(line, server_data),
+        TLS_IDENTIFIER => parse_and_set_tls(line, server_data),
+        RATE_LIMIT_IDENTIFIER => parse_and_set_rate_limit(line, server_data),
+        WHOWAS_IDENTIFIER => parse_and_set_whowas_history(line, server_data),
+        BACKLOG_IDENTIFIER => parse_and_set_channel_backlog(line, server_data),
+        AWAY_ON_JOIN_IDENTIFIER => parse_and_set_away_on_join(line, server_data),
+        CONNECTION_LIMIT_IDENTIFIER => parse_and_set_connection_limit(line, server_data),
+        LOG_IDENTIFIER => parse_and_set_log(line, server_data),
+        NICKNAME_IDENTIFIER => parse_and_set_max_nickname_length(line, server_data),
         &_ => println!("Invalid config file line [{:?}]", line),
     }
 }
@@ -177,7 +344,8 @@ fn parse_and_set_user(line: Vec<&str>, server_data: &mut ServerData) {
 }
 
 ///
-/// This will parse line and set server data in ServerData
+/// This will parse line and set server data in ServerData. The channels file path
+/// is optional and goes after the rest of the server information
 ///
 fn parse_and_set_server_data(line: Vec<&str>, server_data: &mut ServerData) {
     let name = line[1];
@@ -185,6 +353,10 @@ fn parse_and_set_server_data(line: Vec<&str>, server_data: &mut ServerData) {
     let main_name = line[3];
     let mut main_data = None;
     let mut user_file_path = "none".to_string();
+    let channels_file_path = match line.get(5) {
+        Some(path) if !path.is_empty() => path.to_string(),
+        _ => "none".to_string(),
+    };
 
     if main_name != "none" {
         main_data = Some((main_name.to_string(), line[4].to_string()));
@@ -197,9 +369,112 @@ fn parse_and_set_server_data(line: Vec<&str>, server_data: &mut ServerData) {
         name.to_string(),
         main_data,
         user_file_path,
+        channels_file_path,
     );
 }
 
+///
+/// Reads a line from the channels file and returns the channel it represents.
+/// Channels are saved without users, so they are empty until someone joins again
+///
+fn parse_channel_line(line: Vec<&str>) -> Channel {
+    Channel {
+        name: line[1].to_string(),
+        key: optional_field(line[2]),
+        limit: line[3].parse::<usize>().ok(),
+        enter_mode: optional_field(line[4]),
+        operator_settable_topic: line[5] == "true",
+        secret: line[6] == "true",
+        topic_set_at: line[7].parse::<u128>().ok(),
+        operators: nicknames_field(line[8]),
+        invites: nicknames_field(line[9]),
+        banned: nicknames_field(line[10])
+            .into_iter()
+            .collect::<HashSet<String>>(),
+        flood_protection: FloodProtection::from_threshold(line[11]),
+        exceptions: nicknames_field(line[12])
+            .into_iter()
+            .collect::<HashSet<String>>(),
+        max_message_length: line[13].parse::<usize>().ok(),
+        topic_setter: optional_field(line[14]),
+        created_at: line[15].parse::<u64>().unwrap_or_else(|_| now_in_seconds()),
+        no_external_messages: line[16] == "true",
+        moderated: line[17] == "true",
+        voiced: nicknames_field(line[18]),
+        // The topic is the last field because it can contain the separator
+        topic: optional_field(&line[19..].join(";")),
+        users: HashMap::new(),
+        backlog: VecDeque::new(),
+        // The founder is saved in its own line
+        founder: None,
+    }
+}
+
+///
+/// Returns None if the field of the line was saved empty
+///
+fn optional_field(field: &str) -> Option<String> {
+    match field.is_empty() {
+        true => None,
+        false => Some(field.to_string()),
+    }
+}
+
+///
+/// Returns the nicknames saved in a field of a channel line
+///
+fn nicknames_field(field: &str) -> Vec<String> {
+    field
+        .split(NICKNAMES_SEPARATOR)
+        .filter(|nickname| !nickname.is_empty())
+        .map(|nickname| nickname.to_string())
+        .collect()
+}
+
+///
+/// Reads the channels file and returns every channel saved, with the founders of the
+/// registered ones. If the server has no channels file or it was not created yet there
+/// are no channels to load
+///
+pub fn load_channels(path: String) -> Result<HashMap<String, Channel>, Error> {
+    let mut channels = HashMap::new();
+
+    if path == "none" {
+        return Ok(channels);
+    }
+
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(channels),
+        Err(error) => return Err(error),
+    };
+    let reader = BufReader::new(file);
+    let mut founders = vec![];
+
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.split(';').collect::<Vec<&str>>();
+        if line[0] == CHANNEL_FOUNDER_IDENTIFIER && line.len() == 3 {
+            founders.push((irc_lowercase(line[1]), line[2].to_string()));
+            continue;
+        }
+        if line[0] != CHANNEL_IDENTIFIER || line.len() < 20 {
+            println!("Invalid channels file line [{:?}]", line);
+            continue;
+        }
+        let channel = parse_channel_line(line);
+        channels.insert(irc_lowercase(&channel.name), channel);
+    }
+
+    for (channel_name, founder) in founders {
+        if let Some(channel) = channels.get_mut(&channel_name) {
+            channel.founder = Some(founder);
+        }
+    }
+
+    Ok(channels)
+}
+
 ///
 /// This will parse line and set admin in ServerData
 ///
@@ -209,12 +484,212 @@ fn parse_and_set_admin(line: Vec<&str>, server_data: &mut ServerData) {
     server_data.set_admin_data(nickname.to_string(), password.to_string());
 }
 
+///
+/// This will parse line and set how many channels are processed in a single JOIN.
+/// If the amount is missing or is not a positive number the default one is kept
+///
+fn parse_and_set_max_channels_per_join(line: Vec<&str>, server_data: &mut ServerData) {
+    match line.get(1).and_then(|amount| amount.parse::<usize>().ok()) {
+        Some(amount) if amount > 0 => server_data.max_channels_per_join = amount,
+        _ => println!("Invalid channels per join line [{:?}]", line),
+    }
+}
+
+///
+/// This will parse line and set the path of the file with the message of the day
+///
+fn parse_and_set_motd(line: Vec<&str>, server_data: &mut ServerData) {
+    if let Some(path) = line.get(1) {
+        if !path.is_empty() {
+            server_data.motd_file_path = path.to_string();
+        }
+    }
+}
+
+///
+/// This will parse line and set the path of the file with the credentials of the operators
+///
+fn parse_and_set_opers(line: Vec<&str>, server_data: &mut ServerData) {
+    if let Some(path) = line.get(1) {
+        if !path.is_empty() {
+            server_data.opers_file_path = path.to_string();
+        }
+    }
+}
+
+///
+/// Returns the password of the operator with the name given, reading the operators file, which
+/// has a `name:password` line for each operator. None if the name is not in the file or the
+/// file can't be read
+///
+pub fn oper_password(opers_file_path: &str, name: &str) -> Option<String> {
+    let file = File::open(opers_file_path).ok()?;
+
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| {
+            line.split_once(OPER_SEPARATOR)
+                .map(|(oper_name, password)| (oper_name.to_string(), password.to_string()))
+        })
+        .find(|(oper_name, _)| oper_name == name)
+        .map(|(_, password)| password)
+}
+
+///
+/// This will parse line and set the certificate and private key used for TLS.
+/// If any of them is missing TLS stays off
+///
+fn parse_and_set_tls(line: Vec<&str>, server_data: &mut ServerData) {
+    match (line.get(1), line.get(2)) {
+        (Some(certificate), Some(key)) if !certificate.is_empty() && !key.is_empty() => {
+            server_data.tls_files = Some((certificate.to_string(), key.to_string()));
+        }
+        _ => println!("Invalid TLS line [{:?}]", line),
+    }
+}
+
+///
+/// This will parse line and set the password policy. The line has the minimum length and
+/// the classes of characters required: l (letter), u (uppercase), d (digit) and s (symbol).
+/// If the length is invalid the default policy is kept
+///
+fn parse_and_set_password_policy(line: Vec<&str>, server_data: &mut ServerData) {
+    let min_length = match line.get(1).and_then(|length| length.parse::<usize>().ok()) {
+        Some(min_length) => min_length,
+        None => return,
+    };
+    let classes = line.get(2).unwrap_or(&"");
+
+    server_data.password_policy = PasswordPolicy {
+        min_length,
+        require_letter: classes.contains(POLICY_LETTER),
+        require_uppercase: classes.contains(POLICY_UPPERCASE),
+        require_digit: classes.contains(POLICY_DIGIT),
+        require_symbol: classes.contains(POLICY_SYMBOL),
+    };
+}
+
+///
+/// This will parse line and set how many messages users can send at once and per second.
+/// If any of them is missing or is not a positive number the default rate is kept
+///
+fn parse_and_set_rate_limit(line: Vec<&str>, server_data: &mut ServerData) {
+    let number = |index: usize| {
+        line.get(index)
+            .and_then(|number| number.parse::<u32>().ok())
+            .filter(|number| *number > 0)
+    };
+    match (number(1), number(2)) {
+        (Some(burst), Some(per_second)) => {
+            server_data.message_rate = MessageRate { burst, per_second }
+        }
+        _ => println!("Invalid rate limit line [{:?}]", line),
+    }
+}
+
+///
+/// This will parse line and set how many users that quit are kept for WHOWAS.
+/// If the size is missing or is not a positive number the default one is kept
+///
+fn parse_and_set_whowas_history(line: Vec<&str>, server_data: &mut ServerData) {
+    match line.get(1).and_then(|size| size.parse::<usize>().ok()) {
+        Some(size) if size > 0 => server_data.whowas_history_size = size,
+        _ => println!("Invalid whowas line [{:?}]", line),
+    }
+}
+
+///
+/// This will parse line and set how many messages of each channel are replayed to users
+/// that join. A size of 0 turns the backlog off. If the size is not a number the default one is kept
+///
+fn parse_and_set_channel_backlog(line: Vec<&str>, server_data: &mut ServerData) {
+    match line.get(1).and_then(|size| size.parse::<usize>().ok()) {
+        Some(size) => server_data.channel_backlog_size = size,
+        None => println!("Invalid backlog line [{:?}]", line),
+    }
+}
+
+///
+/// This will parse line and set if the users that join a channel are told which members are
+/// away. If the value is not true or false it stays off
+///
+fn parse_and_set_away_on_join(line: Vec<&str>, server_data: &mut ServerData) {
+    match line.get(1).and_then(|value| value.parse::<bool>().ok()) {
+        Some(away_on_join) => server_data.away_on_join = away_on_join,
+        None => println!("Invalid away on join line [{:?}]", line),
+    }
+}
+
+///
+/// This will parse line and set how many connections an ip can have open at once and if the
+/// connections from the machine of the server are exempted, they are unless it is false.
+/// If the amount is missing or is not a positive number there is no limit
+///
+fn parse_and_set_connection_limit(line: Vec<&str>, server_data: &mut ServerData) {
+    match line.get(1).and_then(|amount| amount.parse::<usize>().ok()) {
+        Some(amount) if amount > 0 => {
+            server_data.connection_limit = ConnectionLimit {
+                max_per_address: Some(amount),
+                exempt_localhost: line.get(2) != Some(&"false"),
+            }
+        }
+        _ => println!("Invalid connection limit line [{:?}]", line),
+    }
+}
+
+///
+/// This will parse line and set the log file and the lowest level of the events written.
+/// Without a path the log lines are printed, and with an invalid level every event is written
+///
+fn parse_and_set_log(line: Vec<&str>, server_data: &mut ServerData) {
+    server_data.log_file_path = line
+        .get(1)
+        .filter(|path| !path.is_empty())
+        .map(|path| path.to_string());
+    if let Some(level) = line.get(2) {
+        match LogLevel::from_name(level) {
+            Some(level) => server_data.log_level = level,
+            None => println!("Invalid log level [{:?}]", line),
+        }
+    }
+}
+
+///
+/// This will parse line and set the longest nickname new users can register.
+/// If the length is missing or is not a positive number the default one is kept
+///
+fn parse_and_set_max_nickname_length(line: Vec<&str>, server_data: &mut ServerData) {
+    match line.get(1).and_then(|length| length.parse::<usize>().ok()) {
+        Some(length) if length > 0 => server_data.max_nickname_length = length,
+        _ => println!("Invalid nickname line [{:?}]", line),
+    }
+}
+
 /******************************WRITE ON SERVER DATA FILE********************************/
 
 ///
-/// This function add a user to the server data file
+/// This function adds every user received to the server data file with a single write
 ///
-pub fn add_user(user: &User, path: String) -> Result<(), std::io::Error> {
+pub fn add_users(users: &[User], path: String) -> Result<(), std::io::Error> {
+    let mut buf = String::new();
+
+    for user in users {
+        buf.push_str(&user_line(user));
+    }
+
+    println!("adding users {} in FILE {}", buf, path);
+
+    let mut file = OpenOptions::new().append(true).open(path)?;
+    file.write_all(buf.as_bytes())?;
+
+    Ok(())
+}
+
+///
+/// Returns the line that represents the user in the server data file
+///
+fn user_line(user: &User) -> String {
     let mut buf = format!("{};", USER_IDENTIFIER);
 
     buf.push_str(push_char(&user.nickname, ';').as_str());
@@ -225,14 +700,157 @@ pub fn add_user(user: &User, path: String) -> Result<(), std::io::Error> {
     buf.push_str(&user.password);
     buf.push('\n');
 
-    println!("adding user {} in FILE {}", buf, path);
+    buf
+}
 
-    let mut file = OpenOptions::new().append(true).open(path)?;
+///
+/// Rewrites the channels file with every channel received, so channels deleted
+/// are removed from the file too
+///
+pub fn save_channels(
+    channels: &HashMap<String, Channel>,
+    path: String,
+) -> Result<(), std::io::Error> {
+    if path == "none" {
+        return Ok(());
+    }
+
+    let mut buf = String::new();
+
+    for channel in channels.values() {
+        buf.push_str(&channel_line(channel));
+        if let Some(founder) = &channel.founder {
+            buf.push_str(&format!(
+                "{};{};{}\n",
+                CHANNEL_FOUNDER_IDENTIFIER, channel.name, founder
+            ));
+        }
+    }
+
+    let mut file = File::create(path)?;
     file.write_all(buf.as_bytes())?;
 
     Ok(())
 }
 
+///
+/// Returns the line that represents the channel in the channels file
+///
+fn channel_line(channel: &Channel) -> String {
+    let none = String::new();
+    let banned = channel.get_ban_list();
+    let exceptions = channel.get_exception_list();
+
+    let mut buf = format!("{};", CHANNEL_IDENTIFIER);
+
+    buf.push_str(push_char(&channel.name, ';').as_str());
+    buf.push_str(push_char(channel.key.as_ref().unwrap_or(&none), ';').as_str());
+    buf.push_str(push_char(&option_to_string(channel.limit), ';').as_str());
+    buf.push_str(push_char(channel.enter_mode.as_ref().unwrap_or(&none), ';').as_str());
+    buf.push_str(push_char(&channel.operator_settable_topic.to_string(), ';').as_str());
+    buf.push_str(push_char(&channel.secret.to_string(), ';').as_str());
+    buf.push_str(push_char(&option_to_string(channel.topic_set_at), ';').as_str());
+    buf.push_str(push_char(&join_nicknames(&channel.operators), ';').as_str());
+    buf.push_str(push_char(&join_nicknames(&channel.invites), ';').as_str());
+    buf.push_str(push_char(&join_nicknames(&banned), ';').as_str());
+    let flood_threshold = match &channel.flood_protection {
+        Some(flood_protection) => flood_protection.threshold(),
+        None => none.clone(),
+    };
+    buf.push_str(push_char(&flood_threshold, ';').as_str());
+    buf.push_str(push_char(&join_nicknames(&exceptions), ';').as_str());
+    buf.push_str(push_char(&option_to_string(channel.max_message_length), ';').as_str());
+    buf.push_str(push_char(channel.topic_setter.as_ref().unwrap_or(&none), ';').as_str());
+    buf.push_str(push_char(&channel.created_at.to_string(), ';').as_str());
+    buf.push_str(push_char(&channel.no_external_messages.to_string(), ';').as_str());
+    buf.push_str(push_char(&channel.moderated.to_string(), ';').as_str());
+    buf.push_str(push_char(&join_nicknames(&channel.voiced), ';').as_str());
+    buf.push_str(channel.topic.as_ref().unwrap_or(&none));
+    buf.push('\n');
+
+    buf
+}
+
+///
+/// Returns the value as a String, or an empty String if there is no value
+///
+fn option_to_string<T: ToString>(value: Option<T>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => String::new(),
+    }
+}
+
+///
+/// Joins the nicknames so they can be saved in a single field of a channel line
+///
+fn join_nicknames(nicknames: &[String]) -> String {
+    nicknames.join(&NICKNAMES_SEPARATOR.to_string())
+}
+
+///
+/// Registered users waiting to be written to the users file. They are written together
+/// once USERS_BATCH_SIZE users are waiting or USERS_FLUSH_INTERVAL passed since the last
+/// write, and when the server stops. The users of the server are always updated, so only
+/// the file is behind: if the server dies between writes, the waiting users are lost.
+///
+#[derive(Debug)]
+pub struct PendingUsers {
+    users: Vec<User>,
+    path: String,
+    last_flush: Instant,
+}
+
+impl PendingUsers {
+    ///
+    /// Creates an empty batch of users for the users file in path
+    ///
+    pub fn new(path: String) -> Self {
+        PendingUsers {
+            users: vec![],
+            path,
+            last_flush: Instant::now(),
+        }
+    }
+
+    ///
+    /// Adds a user to the batch, and writes the batch if it is full or it waited too long
+    ///
+    pub fn push(&mut self, user: User) -> Result<(), std::io::Error> {
+        self.users.push(user);
+
+        if self.users.len() >= USERS_BATCH_SIZE {
+            return self.flush();
+        }
+        self.flush_if_due()
+    }
+
+    ///
+    /// Writes the batch only if USERS_FLUSH_INTERVAL passed since the last write
+    ///
+    pub fn flush_if_due(&mut self) -> Result<(), std::io::Error> {
+        if self.last_flush.elapsed() >= USERS_FLUSH_INTERVAL {
+            return self.flush();
+        }
+        Ok(())
+    }
+
+    ///
+    /// Writes every waiting user to the users file
+    ///
+    pub fn flush(&mut self) -> Result<(), std::io::Error> {
+        self.last_flush = Instant::now();
+        if self.users.is_empty() {
+            return Ok(());
+        }
+
+        add_users(&self.users, self.path.clone())?;
+        self.users.clear();
+
+        Ok(())
+    }
+}
+
 ///
 /// Pushes a char into a String
 ///
@@ -247,8 +865,19 @@ fn push_char(string: &String, end_string: char) -> String {
 #[cfg(test)]
 mod tests {
 
-    use super::ServerData;
-    use crate::server_utils::user::User;
+    use super::{
+        load_channels, oper_password, parse_bind_address, save_channels, PasswordPolicy,
+        PendingUsers, ServerData, DEFAULT_CHANNEL_BACKLOG_SIZE, DEFAULT_MAX_CHANNELS_PER_JOIN,
+        DEFAULT_MAX_NICKNAME_LENGTH, DEFAULT_WHOWAS_HISTORY_SIZE, USERS_BATCH_SIZE,
+    };
+    use crate::server_utils::{
+        channel::{Channel, FloodProtection},
+        connection_limit::ConnectionLimit,
+        logger::LogLevel,
+        rate_limit::MessageRate,
+        user::User,
+    };
+    use std::{collections::HashMap, fs, time::Duration};
 
     #[test]
     fn create_server_data_from_file_sets_admin_information_correctly() {
@@ -293,6 +922,238 @@ mod tests {
         );
     }
 
+    #[test]
+    fn create_server_data_sets_max_channels_per_join_or_default_one() {
+        // X;8
+
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        assert_eq!(server_data.max_channels_per_join, 8);
+
+        let server_data =
+            ServerData::new("saved_files/secondary_server_data_test.txt".to_string()).unwrap();
+        assert_eq!(
+            server_data.max_channels_per_join,
+            DEFAULT_MAX_CHANNELS_PER_JOIN
+        );
+    }
+
+    #[test]
+    fn create_server_data_sets_password_policy_or_default_one() {
+        // P;10;lud
+
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        let policy = PasswordPolicy {
+            min_length: 10,
+            require_letter: true,
+            require_uppercase: true,
+            require_digit: true,
+            require_symbol: false,
+        };
+        assert_eq!(server_data.password_policy, policy);
+
+        let server_data =
+            ServerData::new("saved_files/secondary_server_data_test.txt".to_string()).unwrap();
+        assert_eq!(server_data.password_policy, PasswordPolicy::default());
+    }
+
+    #[test]
+    fn create_server_data_sets_rate_limit_or_default_one() {
+        // R;30;3
+
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        let rate = MessageRate {
+            burst: 30,
+            per_second: 3,
+        };
+        assert_eq!(server_data.message_rate, rate);
+
+        let server_data =
+            ServerData::new("saved_files/secondary_server_data_test.txt".to_string()).unwrap();
+        assert_eq!(server_data.message_rate, MessageRate::default());
+    }
+
+    #[test]
+    fn create_server_data_sets_connection_limit_or_no_limit() {
+        // I;3;false
+
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        let limit = ConnectionLimit {
+            max_per_address: Some(3),
+            exempt_localhost: false,
+        };
+        assert_eq!(server_data.connection_limit, limit);
+
+        let server_data =
+            ServerData::new("saved_files/secondary_server_data_test.txt".to_string()).unwrap();
+        assert_eq!(server_data.connection_limit, ConnectionLimit::default());
+    }
+
+    #[test]
+    fn create_server_data_sets_whowas_history_size_or_default_one() {
+        // W;50
+
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        assert_eq!(server_data.whowas_history_size, 50);
+
+        let server_data =
+            ServerData::new("saved_files/secondary_server_data_test.txt".to_string()).unwrap();
+        assert_eq!(server_data.whowas_history_size, DEFAULT_WHOWAS_HISTORY_SIZE);
+    }
+
+    #[test]
+    fn create_server_data_sets_channel_backlog_size_or_default_one() {
+        // B;15
+
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        assert_eq!(server_data.channel_backlog_size, 15);
+
+        let server_data =
+            ServerData::new("saved_files/secondary_server_data_test.txt".to_string()).unwrap();
+        assert_eq!(
+            server_data.channel_backlog_size,
+            DEFAULT_CHANNEL_BACKLOG_SIZE
+        );
+    }
+
+    #[test]
+    fn create_server_data_sets_away_on_join_or_leaves_it_off() {
+        // J;true
+
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        assert!(server_data.away_on_join);
+
+        let server_data =
+            ServerData::new("saved_files/secondary_server_data_test.txt".to_string()).unwrap();
+        assert!(!server_data.away_on_join);
+    }
+
+    #[test]
+    fn create_server_data_sets_log_file_and_level_or_prints_everything() {
+        // L;;warn
+
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        assert_eq!(server_data.log_file_path, None);
+        assert_eq!(server_data.log_level, LogLevel::Warn);
+
+        let server_data =
+            ServerData::new("saved_files/secondary_server_data_test.txt".to_string()).unwrap();
+        assert_eq!(server_data.log_file_path, None);
+        assert_eq!(server_data.log_level, LogLevel::Info);
+    }
+
+    #[test]
+    fn create_server_data_sets_max_nickname_length_or_default_one() {
+        // N;12
+
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        assert_eq!(server_data.max_nickname_length, 12);
+
+        let server_data =
+            ServerData::new("saved_files/secondary_server_data_test.txt".to_string()).unwrap();
+        assert_eq!(server_data.max_nickname_length, DEFAULT_MAX_NICKNAME_LENGTH);
+    }
+
+    #[test]
+    fn create_server_data_sets_tls_files_only_if_configured() {
+        // T;saved_files/certificate_test.pem;saved_files/key_test.pem
+
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        assert_eq!(
+            server_data.tls_files,
+            Some((
+                "saved_files/certificate_test.pem".to_string(),
+                "saved_files/key_test.pem".to_string()
+            ))
+        );
+
+        let server_data =
+            ServerData::new("saved_files/secondary_server_data_test.txt".to_string()).unwrap();
+        assert_eq!(server_data.tls_files, None);
+    }
+
+    #[test]
+    fn create_server_data_sets_opers_file_only_if_configured() {
+        // O;saved_files/opers_test.txt
+
+        let server_data =
+            ServerData::new("saved_files/main_server_data_test.txt".to_string()).unwrap();
+        assert_eq!(
+            server_data.opers_file_path,
+            "saved_files/opers_test.txt".to_string()
+        );
+
+        let server_data =
+            ServerData::new("saved_files/secondary_server_data_test.txt".to_string()).unwrap();
+        assert_eq!(server_data.opers_file_path, "none".to_string());
+    }
+
+    #[test]
+    fn oper_passwords_are_read_from_the_opers_file() {
+        // juanireil:clave_de_oper
+        // ari:dos:puntos
+
+        let path = "saved_files/opers_test.txt";
+        assert_eq!(
+            oper_password(path, "juanireil"),
+            Some("clave_de_oper".to_string())
+        );
+        assert_eq!(oper_password(path, "ari"), Some("dos:puntos".to_string()));
+        assert_eq!(oper_password(path, "lucas"), None);
+        assert_eq!(oper_password("none", "juanireil"), None);
+    }
+
+    #[test]
+    fn bind_address_accepts_ip_and_port_including_every_interface() {
+        assert_eq!(
+            parse_bind_address("127.0.0.1:3000").unwrap().to_string(),
+            "127.0.0.1:3000"
+        );
+        assert_eq!(
+            parse_bind_address("0.0.0.0:6667").unwrap().to_string(),
+            "0.0.0.0:6667"
+        );
+        assert_eq!(
+            parse_bind_address("[::1]:3000").unwrap().to_string(),
+            "[::1]:3000"
+        );
+    }
+
+    #[test]
+    fn malformed_bind_address_returns_error_with_the_reason() {
+        let error = parse_bind_address("127.0.0.1").unwrap_err();
+        assert_eq!(
+            error.message,
+            "Invalid server address 127.0.0.1: expected ip:port"
+        );
+
+        let error = parse_bind_address("localhost:3000").unwrap_err();
+        assert_eq!(
+            error.message,
+            "Invalid server address localhost:3000: the ip is not valid"
+        );
+
+        for address in ["127.0.0.1:", "127.0.0.1:0", "127.0.0.1:70000"] {
+            let error = parse_bind_address(address).unwrap_err();
+            assert_eq!(
+                error.message,
+                format!(
+                    "Invalid server address {}: the port must be a number between 1 and 65535",
+                    address
+                )
+            );
+        }
+    }
+
     #[test]
     fn create_server_data_for_main_server_sets_users_correctly() {
         //U;juanireil;127.0.0.1;juani;Juan Reil;test_server;password123
@@ -352,4 +1213,139 @@ mod tests {
         assert_eq!(marce.server_name, "secondary_server".to_string());
         assert_eq!(marce.password, "password123".to_string());
     }
+
+    #[test]
+    fn rapid_registrations_are_written_in_a_single_batch() {
+        let path = std::env::temp_dir().join("irc_pending_users_test.txt");
+        fs::write(&path, "").unwrap();
+        let mut pending_users = PendingUsers::new(path.to_string_lossy().to_string());
+
+        for number in 0..USERS_BATCH_SIZE - 1 {
+            let nickname = format!("user{}", number);
+            let user = User::new(
+                nickname.clone(),
+                "127.0.0.1".to_string(),
+                nickname,
+                "Real Name".to_string(),
+                "test_server".to_string(),
+                "password123".to_string(),
+            );
+            pending_users.push(user).unwrap();
+        }
+
+        // Nothing is written until the batch is full
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+
+        let user = User::new(
+            "last".to_string(),
+            "127.0.0.1".to_string(),
+            "last".to_string(),
+            "Real Name".to_string(),
+            "test_server".to_string(),
+            "password123".to_string(),
+        );
+        pending_users.push(user).unwrap();
+
+        let written = fs::read_to_string(&path).unwrap();
+        assert_eq!(written.lines().count(), USERS_BATCH_SIZE);
+        assert!(written.starts_with("U;user0;127.0.0.1;user0;Real Name;test_server;password123\n"));
+        assert!(written.ends_with("U;last;127.0.0.1;last;Real Name;test_server;password123\n"));
+
+        // The users already written are not written again
+        pending_users.flush().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), written);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn saved_channels_are_loaded_with_the_same_state() {
+        let path = std::env::temp_dir().join("irc_channels_test.txt");
+        let path = path.to_string_lossy().to_string();
+        let operator = User::new(
+            "ari".to_string(),
+            "127.0.0.1".to_string(),
+            "arisalese".to_string(),
+            "Ariana Salese".to_string(),
+            "test_server".to_string(),
+            "password123".to_string(),
+        );
+
+        let mut channel = Channel::new("#rust".to_string(), &operator);
+        channel.topic = Some("crates; traits and lifetimes".to_string());
+        channel.topic_set_at = Some(1000);
+        channel.topic_setter = Some("ari".to_string());
+        channel.created_at = 500;
+        channel.key = Some("secret_key".to_string());
+        channel.limit = Some(10);
+        channel.enter_mode = Some("+i".to_string());
+        channel.invites.push("marce".to_string());
+        channel.banned.insert("lucas".to_string());
+        channel.exceptions.insert("lucas!*@main_server".to_string());
+        channel.secret = true;
+        channel.flood_protection = Some(FloodProtection::new(
+            5,
+            Duration::from_secs(10),
+            Duration::from_secs(30),
+        ));
+        channel.max_message_length = Some(200);
+        channel.no_external_messages = true;
+        channel.moderated = true;
+        channel.voiced.push("marce".to_string());
+        channel.founder = Some("ari".to_string());
+        let empty_channel = Channel::new("&local".to_string(), &operator);
+
+        let mut channels = HashMap::new();
+        channels.insert(channel.name.clone(), channel);
+        channels.insert(empty_channel.name.clone(), empty_channel);
+        save_channels(&channels, path.clone()).unwrap();
+
+        let loaded = load_channels(path.clone()).unwrap();
+        let rust = loaded.get("#rust").unwrap();
+
+        assert_eq!(rust.topic, Some("crates; traits and lifetimes".to_string()));
+        assert_eq!(rust.topic_set_at, Some(1000));
+        assert_eq!(rust.topic_setter, Some("ari".to_string()));
+        assert_eq!(rust.created_at, 500);
+        assert_eq!(rust.key, Some("secret_key".to_string()));
+        assert_eq!(rust.limit, Some(10));
+        assert_eq!(rust.enter_mode, Some("+i".to_string()));
+        assert_eq!(rust.operators, vec!["ari".to_string()]);
+        assert_eq!(rust.invites, vec!["marce".to_string()]);
+        assert!(rust.banned.contains("lucas"));
+        assert!(rust.exceptions.contains("lucas!*@main_server"));
+        assert!(rust.secret);
+        assert_eq!(
+            rust.flood_protection.as_ref().unwrap().threshold(),
+            "5:10:30"
+        );
+        assert_eq!(rust.max_message_length, Some(200));
+        assert!(rust.no_external_messages);
+        assert!(rust.moderated);
+        assert_eq!(rust.voiced, vec!["marce".to_string()]);
+        assert!(rust.users.is_empty());
+        assert_eq!(rust.founder, Some("ari".to_string()));
+
+        let local = loaded.get("&local").unwrap();
+        assert_eq!(local.topic, None);
+        assert_eq!(local.topic_setter, None);
+        assert_eq!(local.key, None);
+        assert_eq!(local.limit, None);
+        assert!(local.invites.is_empty());
+        assert!(local.banned.is_empty());
+        assert!(local.flood_protection.is_none());
+        assert!(local.exceptions.is_empty());
+        assert_eq!(local.max_message_length, None);
+        assert!(!local.no_external_messages);
+        assert!(!local.moderated);
+        assert!(local.voiced.is_empty());
+        assert_eq!(local.founder, None);
+
+        // Deleted channels are removed from the file
+        channels.remove("&local");
+        save_channels(&channels, path.clone()).unwrap();
+        assert!(!load_channels(path.clone()).unwrap().contains_key("&local"));
+
+        fs::remove_file(&path).unwrap();
+    }
 }
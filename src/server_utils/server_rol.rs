@@ -30,4 +30,6 @@ pub trait ServerRol {
     fn notify(&self, message: Message) -> Result<(), ServerError>;
     fn notify_all_but(&mut self, message: Message, server_name: &str) -> Result<(), ServerError>;
     fn check_server_existance(&mut self, message: Message) -> Result<(), ServerError>;
+    fn servers_count(&self) -> usize;
+    fn is_server_connected(&self, server_name: &str) -> bool;
 }
@@ -2,7 +2,12 @@
 //! User saves all the important data of a user
 //!
 
-use std::collections::HashSet;
+use std::{
+    collections::HashSet,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::parser::irc_eq;
 
 #[derive(Clone, Debug)]
 pub struct User {
@@ -14,6 +19,10 @@ pub struct User {
     pub password: String,
     pub channels: HashSet<String>,
     pub away_message: Option<String>,
+    pub silenced: HashSet<String>, //nicknames of users whose messages are not delivered
+    pub signon: Option<u64>,       //seconds since epoch, only while connected to this server
+    pub invisible: bool,           //hidden from WHO to users that don't share a channel with them
+    pub last_activity: Option<Instant>, //last message sent, only while connected to this server
 }
 
 impl User {
@@ -37,6 +46,10 @@ impl User {
             password,
             channels: HashSet::new(),
             away_message: None,
+            silenced: HashSet::new(),
+            signon: None,
+            invisible: false,
+            last_activity: None,
         }
     }
 
@@ -44,7 +57,7 @@ impl User {
     /// Returns true if the user has any atribute that equals the name received
     ///
     pub fn has_atribute_name(&self, name: &str) -> bool {
-        self.nickname == name
+        irc_eq(&self.nickname, name)
             || self.username == name
             || self.real_name == name
             || self.address == name
@@ -55,14 +68,16 @@ impl User {
     /// Add a channel to the user, it means that the user is in that channel
     ///
     pub fn add_channel(&mut self, channel_name: &String) {
-        self.channels.insert(channel_name.to_string());
+        if !self.channels.iter().any(|name| irc_eq(name, channel_name)) {
+            self.channels.insert(channel_name.to_string());
+        }
     }
 
     ///
     /// When a user exits a channel, it is removed from the channels list
     ///
-    pub fn remove_channel(&mut self, channel_name: &String) {
-        self.channels.remove(channel_name);
+    pub fn remove_channel(&mut self, channel_name: &str) {
+        self.channels.retain(|name| !irc_eq(name, channel_name));
     }
 
     ///
@@ -89,4 +104,77 @@ impl User {
 
         false
     }
+
+    ///
+    /// Silences a user, their messages will no longer be delivered to this user
+    ///
+    pub fn silence(&mut self, nickname: &str) {
+        if !self.has_silenced(nickname) {
+            self.silenced.insert(nickname.to_string());
+        }
+    }
+
+    ///
+    /// Removes a user from the silence list
+    ///
+    pub fn unsilence(&mut self, nickname: &str) {
+        self.silenced.retain(|silenced| !irc_eq(silenced, nickname));
+    }
+
+    ///
+    /// Checks if the user silenced the nickname received
+    ///
+    pub fn has_silenced(&self, nickname: &str) -> bool {
+        self.silenced
+            .iter()
+            .any(|silenced| irc_eq(silenced, nickname))
+    }
+
+    ///
+    /// Saves the current time as the moment the user connected
+    ///
+    pub fn sign_on(&mut self) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or(0);
+        self.signon = Some(now);
+        self.last_activity = Some(Instant::now());
+    }
+
+    ///
+    /// Saves the current time as the moment the user sent its last message
+    ///
+    pub fn mark_active(&mut self) {
+        self.last_activity = Some(Instant::now());
+    }
+
+    ///
+    /// Returns the seconds since the user sent its last message, None if it is not connected
+    /// to this server
+    ///
+    pub fn idle_seconds(&self) -> Option<u64> {
+        self.last_activity
+            .map(|last_activity| last_activity.elapsed().as_secs())
+    }
+
+    ///
+    /// The user is no longer connected, the modes of the connection are forgotten
+    ///
+    pub fn sign_off(&mut self) {
+        self.signon = None;
+        self.last_activity = None;
+        self.invisible = false;
+    }
+
+    ///
+    /// Returns the modes of the user as in RPL_UMODEIS, like "+i"
+    ///
+    pub fn modes(&self) -> String {
+        if self.invisible {
+            "+i".to_string()
+        } else {
+            "+".to_string()
+        }
+    }
 }
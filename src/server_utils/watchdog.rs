@@ -0,0 +1,192 @@
+//!
+//! The watchdog watches the loop of the server that processes messages. If the loop
+//! stops making progress, usually because a handler is waiting for a lock that is never
+//! released, it warns about it and shows which locks are held
+//!
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, TryLockError, Weak,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+// Time without progress after which the server is considered stuck
+pub const WATCHDOG_THRESHOLD: Duration = Duration::from_secs(30);
+// Time between checks of the heartbeat
+const WATCHDOG_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+///
+/// Counter bumped by the server every time it goes through its loop
+///
+#[derive(Debug, Clone, Default)]
+pub struct Heartbeat {
+    beats: Arc<AtomicUsize>,
+}
+
+impl Heartbeat {
+    ///
+    /// Creates a new Heartbeat that has not beaten yet
+    ///
+    pub fn new() -> Self {
+        Heartbeat {
+            beats: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    ///
+    /// Records that the server made progress
+    ///
+    pub fn beat(&self) {
+        self.beats.fetch_add(1, Ordering::Relaxed);
+    }
+
+    ///
+    /// Returns how many times the heartbeat has beaten
+    ///
+    pub fn count(&self) -> usize {
+        self.beats.load(Ordering::Relaxed)
+    }
+}
+
+type LockCheck = Box<dyn Fn() -> bool + Send>;
+
+pub struct Watchdog {
+    // The watchdog stops once the server drops its heartbeat
+    beats: Weak<AtomicUsize>,
+    threshold: Duration,
+    locks: Vec<(String, LockCheck)>,
+    last_count: usize,
+    last_progress: Instant,
+    warned: bool,
+}
+
+impl Watchdog {
+    ///
+    /// Creates a new Watchdog that warns if the heartbeat does not change for longer than threshold
+    ///
+    pub fn new(heartbeat: &Heartbeat, threshold: Duration) -> Self {
+        Watchdog {
+            beats: Arc::downgrade(&heartbeat.beats),
+            threshold,
+            locks: vec![],
+            last_count: heartbeat.count(),
+            last_progress: Instant::now(),
+            warned: false,
+        }
+    }
+
+    ///
+    /// Adds a lock to the ones shown as held when the server is stuck
+    ///
+    pub fn watch_lock<T: Send + 'static>(mut self, name: &str, lock: &Arc<Mutex<T>>) -> Self {
+        let lock = lock.clone();
+        let is_held = move || matches!(lock.try_lock(), Err(TryLockError::WouldBlock));
+        self.locks.push((name.to_string(), Box::new(is_held)));
+        self
+    }
+
+    ///
+    /// Checks the heartbeat until the server stops, printing a warning every time it gets stuck
+    ///
+    pub fn run(mut self) {
+        loop {
+            thread::sleep(WATCHDOG_CHECK_INTERVAL);
+            let count = match self.beats.upgrade() {
+                Some(beats) => beats.load(Ordering::Relaxed),
+                None => return,
+            };
+            if let Some(warning) = self.check(count, Instant::now()) {
+                println!("{}", warning);
+            }
+        }
+    }
+
+    ///
+    /// Returns a warning if the count did not change for longer than the threshold.
+    /// The warning is returned once for every time the server gets stuck
+    ///
+    fn check(&mut self, count: usize, now: Instant) -> Option<String> {
+        if count != self.last_count {
+            self.last_count = count;
+            self.last_progress = now;
+            self.warned = false;
+            return None;
+        }
+
+        let stuck_for = now.duration_since(self.last_progress);
+        if self.warned || stuck_for < self.threshold {
+            return None;
+        }
+        self.warned = true;
+
+        let held_locks: Vec<&str> = self
+            .locks
+            .iter()
+            .filter(|(_, is_held)| is_held())
+            .map(|(name, _)| name.as_str())
+            .collect();
+        let held_locks = match held_locks.is_empty() {
+            true => "none".to_string(),
+            false => held_locks.join(", "),
+        };
+
+        Some(format!(
+            "WARNING! Server made no progress for {} seconds, it could be deadlocked. Locks held: {}",
+            stuck_for.as_secs(),
+            held_locks
+        ))
+    }
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::{Heartbeat, Watchdog};
+    use std::{
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    #[test]
+    fn watchdog_warns_once_when_heartbeat_stops_and_shows_held_locks() {
+        let heartbeat = Heartbeat::new();
+        let users = Arc::new(Mutex::new(0));
+        let channels = Arc::new(Mutex::new(0));
+        let mut watchdog = Watchdog::new(&heartbeat, Duration::from_secs(30))
+            .watch_lock("users", &users)
+            .watch_lock("channels", &channels);
+        let start = Instant::now();
+
+        heartbeat.beat();
+        assert!(watchdog.check(heartbeat.count(), start).is_none());
+        assert!(watchdog
+            .check(heartbeat.count(), start + Duration::from_secs(29))
+            .is_none());
+
+        let _channels_guard = channels.lock().unwrap();
+        let warning = watchdog.check(heartbeat.count(), start + Duration::from_secs(30));
+        assert_eq!(
+            warning,
+            Some(
+                "WARNING! Server made no progress for 30 seconds, it could be deadlocked. Locks held: channels"
+                    .to_string()
+            )
+        );
+
+        // It is not repeated until the server gets stuck again
+        assert!(watchdog
+            .check(heartbeat.count(), start + Duration::from_secs(60))
+            .is_none());
+        heartbeat.beat();
+        assert!(watchdog
+            .check(heartbeat.count(), start + Duration::from_secs(61))
+            .is_none());
+        assert!(watchdog
+            .check(heartbeat.count(), start + Duration::from_secs(91))
+            .is_some());
+    }
+}
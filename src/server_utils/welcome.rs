@@ -0,0 +1,116 @@
+//!
+//! Users that login or register are welcomed as RFC 2812 says, with RPL_WELCOME, RPL_YOURHOST,
+//! RPL_CREATED and RPL_MYINFO. They are sent after RPL_CORRECTLOGIN or RPL_CORRECTREGISTRATION,
+//! which the clients of this server still wait for to know the login ended
+//!
+
+use crate::{
+    numeric_reply::{
+        NumericReply, RPL_CREATED_MSG, RPL_CREATED_NUM, RPL_MYINFO_NUM, RPL_WELCOME_MSG,
+        RPL_WELCOME_NUM, RPL_YOURHOST_NUM,
+    },
+    server_utils::server_data::ServerData,
+};
+
+// Version of the server sent in RPL_YOURHOST and RPL_MYINFO
+pub const SERVER_VERSION: &str = concat!("irc-", env!("CARGO_PKG_VERSION"));
+
+// Modes the users can set to themselves
+const USER_MODES: &str = "i";
+// Modes the operators can set to their channels
+const CHANNEL_MODES: &str = "Lbefiklmnostv";
+
+///
+/// What the server tells about itself to the users it welcomes
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Welcome {
+    pub server_name: String,
+    pub version: String,
+    pub created_at: u64, //seconds since epoch
+}
+
+impl Welcome {
+    ///
+    /// Takes the name and the creation time of the server from the server data
+    ///
+    pub fn new(server_data: &ServerData) -> Self {
+        Welcome {
+            server_name: server_data.server_name.clone(),
+            version: SERVER_VERSION.to_string(),
+            created_at: server_data.created_at,
+        }
+    }
+
+    ///
+    /// Returns the replies that welcome the user, in the order they are sent
+    ///
+    pub fn replies(&self, nickname: &str) -> Vec<NumericReply> {
+        vec![
+            NumericReply::new(
+                RPL_WELCOME_NUM,
+                &format!("{} {}", RPL_WELCOME_MSG, nickname),
+                Some(vec![nickname.to_string()]),
+            ),
+            NumericReply::new(
+                RPL_YOURHOST_NUM,
+                &format!(
+                    "Your host is {}, running version {}",
+                    self.server_name, self.version
+                ),
+                Some(vec![nickname.to_string()]),
+            ),
+            NumericReply::new(
+                RPL_CREATED_NUM,
+                RPL_CREATED_MSG,
+                Some(vec![nickname.to_string(), self.created_at.to_string()]),
+            ),
+            // The channel modes are the trailing parameter, so no empty one is written after them
+            NumericReply::new(
+                RPL_MYINFO_NUM,
+                CHANNEL_MODES,
+                Some(vec![
+                    nickname.to_string(),
+                    self.server_name.clone(),
+                    self.version.clone(),
+                    USER_MODES.to_string(),
+                ]),
+            ),
+        ]
+    }
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::{Welcome, SERVER_VERSION};
+    use crate::numeric_reply::NumericReply;
+
+    #[test]
+    fn users_are_welcomed_with_the_information_of_the_server() {
+        let welcome = Welcome {
+            server_name: "rust".to_string(),
+            version: SERVER_VERSION.to_string(),
+            created_at: 1671234567,
+        };
+
+        let replies: Vec<String> = welcome
+            .replies("ari")
+            .iter()
+            .map(NumericReply::as_string)
+            .collect();
+        assert_eq!(
+            replies,
+            vec![
+                "001 ari :Welcome to the Internet Relay Network ari\r\n".to_string(),
+                format!(
+                    "002 ari :Your host is rust, running version {}\r\n",
+                    SERVER_VERSION
+                ),
+                "003 ari 1671234567 :This server was created\r\n".to_string(),
+                format!("004 ari rust {} i :Lbefiklmnostv\r\n", SERVER_VERSION),
+            ]
+        );
+    }
+}
@@ -0,0 +1,361 @@
+//!
+//! TLS encrypts the connections between clients and servers. Once the handshake is done the rest
+//! of the application keeps using a TcpStream, connected to a relay that encrypts what is written
+//! in it and decrypts what arrives from the other side
+//!
+
+use std::{
+    fs::File,
+    io::{self, BufReader, ErrorKind, Read, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::{Arc, Mutex, MutexGuard},
+    thread,
+};
+
+use rustls::{
+    Certificate, ClientConfig, ClientConnection, Connection, OwnedTrustAnchor, PrivateKey,
+    RootCertStore, ServerConfig, ServerConnection, ServerName,
+};
+use rustls_pemfile::Item;
+
+// Certificates trusted besides the public authorities, for servers with their own certificate
+pub const TRUSTED_CERTIFICATES_PATH: &str = "saved_files/trusted_certificates.pem";
+// Size of the chunks read from the streams by the relay
+const RELAY_BUFFER_SIZE: usize = 4096;
+
+///
+/// Reads the certificate chain and the private key of a server and returns the configuration
+/// used to accept connections
+///
+pub fn server_config(
+    certificate_path: &str,
+    key_path: &str,
+) -> Result<Arc<ServerConfig>, io::Error> {
+    let certificates = read_certificates(certificate_path)?;
+    let key = read_private_key(key_path)?;
+
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certificates, key)
+        .map_err(|error| invalid_data(&format!("Invalid certificate or key: {}", error)))?;
+
+    Ok(Arc::new(config))
+}
+
+///
+/// Returns the configuration used to connect to servers. A server is trusted if its certificate
+/// was signed by a public authority or is in the trusted certificates file
+///
+pub fn client_config() -> Result<Arc<ClientConfig>, io::Error> {
+    let mut roots = RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+        OwnedTrustAnchor::from_subject_spki_name_constraints(
+            anchor.subject,
+            anchor.spki,
+            anchor.name_constraints,
+        )
+    }));
+
+    match read_certificates(TRUSTED_CERTIFICATES_PATH) {
+        Ok(certificates) => {
+            for certificate in certificates {
+                roots.add(&certificate).map_err(|error| {
+                    invalid_data(&format!("Invalid trusted certificate: {}", error))
+                })?;
+            }
+        }
+        // Without the file only the public authorities are trusted
+        Err(error) if error.kind() == ErrorKind::NotFound => {}
+        Err(error) => return Err(error),
+    }
+
+    let config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    Ok(Arc::new(config))
+}
+
+///
+/// Does the handshake with a client that has just connected and returns the stream to talk with it
+///
+pub fn accept(stream: TcpStream, config: Arc<ServerConfig>) -> Result<TcpStream, io::Error> {
+    let connection = ServerConnection::new(config).map_err(tls_error)?;
+    relay(stream, Connection::from(connection))
+}
+
+///
+/// Does the handshake with the server in host, checking its certificate, and returns the stream
+/// to talk with it
+///
+pub fn connect(
+    stream: TcpStream,
+    host: &str,
+    config: Arc<ClientConfig>,
+) -> Result<TcpStream, io::Error> {
+    let server_name = ServerName::try_from(host)
+        .map_err(|_| invalid_data(&format!("{} is not a valid server name", host)))?;
+    let connection = ClientConnection::new(config, server_name).map_err(tls_error)?;
+    relay(stream, Connection::from(connection))
+}
+
+///
+/// Completes the handshake and starts the threads that encrypt and decrypt the data.
+/// The stream returned is closed when the other side closes the connection, and closing
+/// it closes the connection
+///
+fn relay(mut socket: TcpStream, mut connection: Connection) -> Result<TcpStream, io::Error> {
+    // Nothing is sent until the handshake is done, so a failure reaches the caller
+    while connection.is_handshaking() {
+        connection.complete_io(&mut socket)?;
+    }
+
+    let (stream, mut plain) = local_pair()?;
+    let connection = Arc::new(Mutex::new(connection));
+
+    let mut socket_reader = socket.try_clone()?;
+    let mut plain_writer = plain.try_clone()?;
+    let connection_clone = connection.clone();
+    let _ = thread::spawn(move || {
+        if let Err(error) = decrypt(&mut socket_reader, &mut plain_writer, &connection_clone) {
+            println!("TLS connection closed: {}", error);
+        }
+        let _ = plain_writer.shutdown(Shutdown::Write);
+    });
+    let _ = thread::spawn(move || {
+        if let Err(error) = encrypt(&mut plain, &mut socket, &connection) {
+            println!("TLS connection closed: {}", error);
+        }
+        let _ = socket.shutdown(Shutdown::Both);
+    });
+
+    Ok(stream)
+}
+
+///
+/// Decrypts what arrives from the other side and writes it in the local stream,
+/// until the other side closes the connection
+///
+fn decrypt(
+    socket: &mut TcpStream,
+    plain: &mut TcpStream,
+    connection: &Mutex<Connection>,
+) -> Result<(), io::Error> {
+    let mut buffer = [0; RELAY_BUFFER_SIZE];
+    loop {
+        // Data could have arrived with the end of the handshake, so it is taken before reading
+        let (plaintext, closed) = take_plaintext(&mut lock(connection)?)?;
+        plain.write_all(&plaintext)?;
+        if closed {
+            return Ok(());
+        }
+
+        let size = socket.read(&mut buffer)?;
+        if size == 0 {
+            return Ok(());
+        }
+
+        let mut connection = lock(connection)?;
+        let mut received = &buffer[..size];
+        while !received.is_empty() {
+            connection.read_tls(&mut received)?;
+            connection.process_new_packets().map_err(tls_error)?;
+        }
+        // Alerts and key updates are answered right away
+        while connection.wants_write() {
+            connection.write_tls(socket)?;
+        }
+    }
+}
+
+///
+/// Encrypts what the application writes in the local stream and sends it to the other side.
+/// When the application closes its stream the other side is told that the connection ends
+///
+fn encrypt(
+    plain: &mut TcpStream,
+    socket: &mut TcpStream,
+    connection: &Mutex<Connection>,
+) -> Result<(), io::Error> {
+    let mut buffer = [0; RELAY_BUFFER_SIZE];
+    loop {
+        let size = plain.read(&mut buffer)?;
+
+        let mut connection = lock(connection)?;
+        if size == 0 {
+            connection.send_close_notify();
+        } else {
+            connection.writer().write_all(&buffer[..size])?;
+        }
+        while connection.wants_write() {
+            connection.write_tls(socket)?;
+        }
+
+        if size == 0 {
+            return Ok(());
+        }
+    }
+}
+
+///
+/// Returns the data already decrypted and whether the other side closed the connection
+///
+fn take_plaintext(connection: &mut Connection) -> Result<(Vec<u8>, bool), io::Error> {
+    let mut plaintext = vec![];
+    let mut buffer = [0; RELAY_BUFFER_SIZE];
+    loop {
+        match connection.reader().read(&mut buffer) {
+            Ok(0) => return Ok((plaintext, true)),
+            Ok(size) => plaintext.extend_from_slice(&buffer[..size]),
+            Err(error) if error.kind() == ErrorKind::WouldBlock => return Ok((plaintext, false)),
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+///
+/// Returns two connected streams, what is written in one is read from the other
+///
+fn local_pair() -> Result<(TcpStream, TcpStream), io::Error> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let stream = TcpStream::connect(listener.local_addr()?)?;
+    loop {
+        let (other, address) = listener.accept()?;
+        // Any other local process that connects in the meantime is left out
+        if address == stream.local_addr()? {
+            return Ok((stream, other));
+        }
+    }
+}
+
+///
+/// Reads every certificate of a PEM file
+///
+fn read_certificates(path: &str) -> Result<Vec<Certificate>, io::Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let certificates = rustls_pemfile::certs(&mut reader)?;
+    if certificates.is_empty() {
+        return Err(invalid_data(&format!("No certificates found in {}", path)));
+    }
+
+    Ok(certificates.into_iter().map(Certificate).collect())
+}
+
+///
+/// Reads the first private key of a PEM file
+///
+fn read_private_key(path: &str) -> Result<PrivateKey, io::Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    loop {
+        match rustls_pemfile::read_one(&mut reader)? {
+            Some(Item::RSAKey(key)) | Some(Item::PKCS8Key(key)) | Some(Item::ECKey(key)) => {
+                return Ok(PrivateKey(key))
+            }
+            Some(_) => continue,
+            None => return Err(invalid_data(&format!("No private key found in {}", path))),
+        }
+    }
+}
+
+fn lock(connection: &Mutex<Connection>) -> Result<MutexGuard<Connection>, io::Error> {
+    connection
+        .lock()
+        .map_err(|_| io::Error::new(ErrorKind::Other, "Couldn't lock the TLS connection"))
+}
+
+fn tls_error(error: rustls::Error) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, error)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+/************************************TESTS*******************************************/
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::{TcpListener, TcpStream},
+        sync::Arc,
+        thread,
+    };
+
+    use rustls::{ClientConfig, RootCertStore};
+
+    use super::{accept, connect, read_certificates, server_config};
+
+    // Self signed certificate for 127.0.0.1 and localhost, with its key
+    const CERTIFICATE_PATH: &str = "saved_files/certificate_test.pem";
+    const KEY_PATH: &str = "saved_files/key_test.pem";
+
+    ///
+    /// Returns a client configuration that only trusts the test certificate
+    ///
+    fn test_client_config() -> Arc<ClientConfig> {
+        let mut roots = RootCertStore::empty();
+        for certificate in read_certificates(CERTIFICATE_PATH).unwrap() {
+            roots.add(&certificate).unwrap();
+        }
+        let config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Arc::new(config)
+    }
+
+    #[test]
+    fn lines_written_after_the_handshake_arrive_decrypted_on_both_sides() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let config = server_config(CERTIFICATE_PATH, KEY_PATH).unwrap();
+        // The server answers the first line it receives
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut stream = accept(stream, config).unwrap();
+            let mut line = String::new();
+            BufReader::new(stream.try_clone().unwrap())
+                .read_line(&mut line)
+                .unwrap();
+            stream
+                .write_all(format!("received {}", line).as_bytes())
+                .unwrap();
+            line
+        });
+
+        let stream = TcpStream::connect(address).unwrap();
+        let mut stream = connect(stream, "127.0.0.1", test_client_config()).unwrap();
+        stream.write_all(b"PASS contrasena\r\n").unwrap();
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).unwrap();
+
+        assert_eq!(server.join().unwrap(), "PASS contrasena\r\n");
+        assert_eq!(line, "received PASS contrasena\r\n");
+    }
+
+    #[test]
+    fn certificates_not_trusted_are_refused_in_the_handshake() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let config = server_config(CERTIFICATE_PATH, KEY_PATH).unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            accept(stream, config).is_err()
+        });
+
+        // A client that doesn't trust the test certificate
+        let config = Arc::new(
+            ClientConfig::builder()
+                .with_safe_defaults()
+                .with_root_certificates(RootCertStore::empty())
+                .with_no_client_auth(),
+        );
+        let stream = TcpStream::connect(address).unwrap();
+
+        assert!(connect(stream, "127.0.0.1", config).is_err());
+        assert!(server.join().unwrap());
+    }
+}
@@ -3,21 +3,41 @@
 //!
 
 use std::{
+    env, fs,
     io::{BufRead, BufReader, Write},
-    net::TcpStream,
+    net::{TcpListener, TcpStream},
+    thread,
+    time::Duration,
 };
 
 use irc::{
-    numeric_reply::{NumericReply, RPL_CORRECTLOGIN_MSG, RPL_CORRECTLOGIN_NUM},
-    server_utils::{server::Server, server_data::ServerData},
+    commands::{NOTICE, NOTICE_AUTH},
+    message::Message,
+    numeric_reply::{
+        NumericReply, RPL_CORRECTLOGIN_MSG, RPL_CORRECTLOGIN_NUM, RPL_CREATED_NUM,
+        RPL_CREATIONTIME_NUM, RPL_MYINFO_NUM, RPL_TOPICWHOTIME_NUM, RPL_WELCOME_NUM,
+        RPL_YOURHOST_NUM,
+    },
+    server_utils::{
+        connection_handler::{AUTH_CHECKING_LOGIN, AUTH_LOOKING_UP},
+        server::Server,
+        server_data::ServerData,
+    },
 };
 
 ///
 /// Runs a test server
 ///
 pub fn run_server() -> Result<(), String> {
+    run_server_with_data("tests/common/server_data_test.txt".to_string())
+}
+
+///
+/// Runs a server with the server data file received
+///
+pub fn run_server_with_data(server_data_path: String) -> Result<(), String> {
     // Read server data file of server to obtain ServerData
-    let server_data = match ServerData::new("tests/common/server_data_test.txt".to_string()) {
+    let server_data = match ServerData::new(server_data_path) {
         Ok(server_data) => server_data,
         Err(error) => {
             println!("aca toy");
@@ -46,51 +66,126 @@ pub fn run_server() -> Result<(), String> {
     }
 }
 
+/*******************************MULTISERVER HELPERS*********************************/
+
 ///
-/// Logs in user with nickname ari in file users_test.txt
+/// Returns an address of a port that is free to use, so multiserver tests do not
+/// depend on fixed ports
 ///
-pub fn login_ari() -> TcpStream {
-    let address = "127.0.0.1:3000";
-    let mut socket = TcpStream::connect(address).unwrap();
+pub fn free_address() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    drop(listener);
+    address
+}
 
-    let action = "LOGIN\r\n";
-    let result = socket.write_all(action.as_bytes());
-    assert!(result.is_ok());
+///
+/// Writes a server data file in the temporary directory and returns its path.
+/// If main is None the server is a main server that uses the users in users_test.txt,
+/// else it is a secondary server of the main server (name, address) received.
+/// The operators of every server are the ones in opers_test.txt
+///
+pub fn write_server_data(
+    server_name: &str,
+    address: &str,
+    admin_nick: &str,
+    main: Option<(&str, &str)>,
+) -> String {
+    let server_line = match main {
+        Some((main_name, main_address)) => format!(
+            "S;{};{};{};{}",
+            server_name, address, main_name, main_address
+        ),
+        None => format!(
+            "S;{};{};none;tests/common/users_test.txt",
+            server_name, address
+        ),
+    };
+    let content = format!(
+        "{}\nA;contrasena;{}\nO;tests/common/opers_test.txt",
+        server_line, admin_nick
+    );
 
-    let pass_message = "PASS password123\r\n";
-    let result = socket.write_all(pass_message.as_bytes());
-    assert!(result.is_ok());
+    let port = address.split(':').next_back().unwrap();
+    let path = env::temp_dir().join(format!("{}_{}.txt", server_name, port));
+    fs::write(&path, content).unwrap();
+    path.to_string_lossy().to_string()
+}
 
-    let nick_message = "NICK ari\r\n";
-    let result = socket.write_all(nick_message.as_bytes());
-    assert!(result.is_ok());
+///
+/// Boots a main server (main_server) and a secondary server (secondary_server_1) linked to it,
+/// both on free ports. Returns the addresses of the main and the secondary server
+///
+pub fn run_multiserver() -> (String, String) {
+    let main_address = run_main_server();
+    let secondary_address = run_secondary_server(&main_address);
 
-    let user_message = "USER arisalese,127.0.0.1,main_server :Ariana Salese\r\n";
-    let result = socket.write_all(user_message.as_bytes());
-    assert!(result.is_ok());
+    (main_address, secondary_address)
+}
 
-    let mut reader = BufReader::new(socket.try_clone().unwrap());
+///
+/// Boots a main server (main_server) on a free port and returns its address
+///
+pub fn run_main_server() -> String {
+    let main_address = free_address();
+    let main_data = write_server_data("main_server", &main_address, "juanireil", None);
+    let _main_handle = thread::spawn(move || {
+        let result = run_server_with_data(main_data);
+        assert!(result.is_ok());
+    });
 
-    let mut data = String::new();
-    let result = reader.read_line(&mut data);
-    assert!(result.is_ok());
+    // The secondary server connects to the main one when created, so main must be running
+    std::thread::sleep(Duration::new(2, 0));
 
-    let reply = NumericReply::new(
-        RPL_CORRECTLOGIN_NUM,
-        RPL_CORRECTLOGIN_MSG,
-        Some(vec!["ari".to_string()]),
-    )
-    .as_string();
-    assert_eq!(reply, data);
+    main_address
+}
 
-    socket
+///
+/// Boots a secondary server (secondary_server_1) on a free port, linked to the main server
+/// with the address received. Returns its address
+///
+pub fn run_secondary_server(main_address: &str) -> String {
+    run_secondary_server_named(main_address, "secondary_server_1", "marce")
 }
 
 ///
-/// Logs in user with nickname juanireil in file users_test.txt
+/// Boots a secondary server with the name and the admin received on a free port, linked to the
+/// main server with the address received. Returns its address
 ///
-pub fn login_juani() -> TcpStream {
-    let address = "127.0.0.1:3000";
+pub fn run_secondary_server_named(
+    main_address: &str,
+    server_name: &str,
+    admin_nick: &str,
+) -> String {
+    let secondary_address = free_address();
+    let secondary_data = write_server_data(
+        server_name,
+        &secondary_address,
+        admin_nick,
+        Some(("main_server", main_address)),
+    );
+    let _secondary_handle = thread::spawn(move || {
+        let result = run_server_with_data(secondary_data);
+        assert!(result.is_ok());
+    });
+
+    // Wait until the secondary server receives the users and channels
+    std::thread::sleep(Duration::new(2, 0));
+
+    secondary_address
+}
+
+///
+/// Logs in a user of users_test.txt in the server with the address received.
+/// The user must belong to that server
+///
+pub fn login(
+    address: &str,
+    nickname: &str,
+    username: &str,
+    real_name: &str,
+    server_name: &str,
+) -> TcpStream {
     let mut socket = TcpStream::connect(address).unwrap();
 
     let action = "LOGIN\r\n";
@@ -101,27 +196,110 @@ pub fn login_juani() -> TcpStream {
     let result = socket.write_all(pass_message.as_bytes());
     assert!(result.is_ok());
 
-    let nick_message = "NICK juanireil\r\n";
+    let nick_message = format!("NICK {}\r\n", nickname);
     let result = socket.write_all(nick_message.as_bytes());
     assert!(result.is_ok());
 
-    let user_message = "USER juanireil,127.0.0.1,main_server :Juani Reil\r\n";
+    let user_message = format!(
+        "USER {},127.0.0.1,{} :{}\r\n",
+        username, server_name, real_name
+    );
     let result = socket.write_all(user_message.as_bytes());
     assert!(result.is_ok());
 
     let mut reader = BufReader::new(socket.try_clone().unwrap());
 
+    // The server tells how the connection is progressing before answering
     let mut data = String::new();
+    for progress in [AUTH_LOOKING_UP, AUTH_CHECKING_LOGIN] {
+        let result = reader.read_line(&mut data);
+        assert!(result.is_ok());
+        assert_eq!(data, auth_notice(progress));
+        data.clear();
+    }
+
     let result = reader.read_line(&mut data);
     assert!(result.is_ok());
 
     let reply = NumericReply::new(
         RPL_CORRECTLOGIN_NUM,
         RPL_CORRECTLOGIN_MSG,
-        Some(vec!["juanireil".to_string()]),
+        Some(vec![nickname.to_string()]),
     )
     .as_string();
     assert_eq!(reply, data);
 
+    // Then the server welcomes the user
+    for welcome in [
+        RPL_WELCOME_NUM,
+        RPL_YOURHOST_NUM,
+        RPL_CREATED_NUM,
+        RPL_MYINFO_NUM,
+    ] {
+        data.clear();
+        let result = reader.read_line(&mut data);
+        assert!(result.is_ok());
+        assert!(data.starts_with(&format!("{} {} ", welcome, nickname)));
+    }
+
     socket
 }
+
+///
+/// Returns the NOTICE AUTH sent by the server while a connection logs in or registers
+///
+pub fn auth_notice(text: &str) -> String {
+    Message {
+        tags: None,
+        prefix: None,
+        command: NOTICE.to_string(),
+        params: vec![vec![NOTICE_AUTH.to_string()], vec![text.to_string()]],
+    }
+    .as_string()
+}
+
+///
+/// Logs in user with nickname ari in file users_test.txt
+///
+pub fn login_ari() -> TcpStream {
+    login(
+        "127.0.0.1:3000",
+        "ari",
+        "arisalese",
+        "Ariana Salese",
+        "main_server",
+    )
+}
+
+///
+/// Logs in user with nickname juanireil in file users_test.txt
+///
+pub fn login_juani() -> TcpStream {
+    login(
+        "127.0.0.1:3000",
+        "juanireil",
+        "juanireil",
+        "Juani Reil",
+        "main_server",
+    )
+}
+
+///
+/// Reads the RPL_CREATIONTIME sent after the topic of the channel. The time changes in every run
+/// so only the start of the reply is checked, and the time is returned
+///
+pub fn read_creation_time(reader: &mut BufReader<TcpStream>, channel: &str) -> u64 {
+    let mut data = String::new();
+    reader.read_line(&mut data).unwrap();
+    assert!(data.starts_with(&format!("{} {} ", RPL_CREATIONTIME_NUM, channel)));
+    data.split_whitespace().nth(2).unwrap().parse().unwrap()
+}
+
+///
+/// Reads the RPL_TOPICWHOTIME sent after the creation time of the channel, if it has a topic
+///
+pub fn read_topic_who_time(reader: &mut BufReader<TcpStream>, channel: &str, setter: &str) {
+    let mut data = String::new();
+    reader.read_line(&mut data).unwrap();
+    assert!(data.starts_with(&format!("{} {} {} ", RPL_TOPICWHOTIME_NUM, channel, setter)));
+}
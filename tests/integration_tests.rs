@@ -5,17 +5,39 @@
 //!
 
 use irc::{
-    commands::PRIVMSG,
+    client_utils::headless::HeadlessClient,
+    commands::{
+        ADD_LIST_CHATS, AWAY, CLOSE, ISON, KICK, MODE, NAMES, NOTICE, PRIVMSG, QUIT, SEARCH_USERS,
+        WALLOPS, WHO, WHOWAS,
+    },
     message::Message,
     numeric_reply::{
-        NumericReply, ERR_BADCHANNELKEY_MSG, ERR_BADCHANNELKEY_NUM, ERR_CHANNELHASKEY_MSG,
-        ERR_CHANNELHASKEY_NUM, ERR_CHANOPRIVSNEEDED_MSG, ERR_CHANOPRIVSNEEDED_NUM,
-        ERR_INVITEONLYCHAN_MSG, ERR_INVITEONLYCHAN_NUM, RPL_INVITING_NUM, RPL_MODESET_MSG,
-        RPL_MODESET_NUM, RPL_NOTOPIC_MSG, RPL_NOTOPIC_NUM, RPL_TOPIC_NUM,
+        NumericReply, ERR_BADCHANNELKEY_MSG, ERR_BADCHANNELKEY_NUM, ERR_BANNEDFROMCHAN_MSG,
+        ERR_BANNEDFROMCHAN_NUM, ERR_CHANNELHASKEY_MSG, ERR_CHANNELHASKEY_NUM, ERR_CHANOPEN_MSG,
+        ERR_CHANOPEN_NUM, ERR_CHANOPRIVSNEEDED_MSG, ERR_CHANOPRIVSNEEDED_NUM,
+        ERR_ERRONEUSNICKNAME_NUM, ERR_INVALIDLOGIN_MSG, ERR_INVALIDLOGIN_NUM,
+        ERR_INVITEONLYCHAN_MSG, ERR_INVITEONLYCHAN_NUM, ERR_NOMOTD_MSG, ERR_NOMOTD_NUM,
+        ERR_NOPRIVILEGES_MSG, ERR_NOPRIVILEGES_NUM, ERR_NOSUCHCHANNEL_MSG, ERR_NOSUCHCHANNEL_NUM,
+        ERR_PASSWDMISMATCH_MSG, ERR_PASSWDMISMATCH_NUM, ERR_WASNOSUCHNICK_MSG,
+        ERR_WEAKPASSWORD_NUM, RPL_BANLIST_NUM, RPL_CHANNELMODEIS_NUM, RPL_CORRECTLOGIN_MSG,
+        RPL_CORRECTLOGIN_NUM, RPL_CREATED_NUM, RPL_ENDOFBANLIST_MSG, RPL_ENDOFBANLIST_NUM,
+        RPL_ENDOFEXCEPTLIST_MSG, RPL_ENDOFEXCEPTLIST_NUM, RPL_ENDOFSILELIST_MSG,
+        RPL_ENDOFSILELIST_NUM, RPL_EXCEPTLIST_NUM, RPL_INVITING_NUM, RPL_KNOCKDLVR_MSG,
+        RPL_KNOCKDLVR_NUM, RPL_LUSERCHANNELS_MSG, RPL_LUSERCHANNELS_NUM, RPL_LUSERCLIENT_NUM,
+        RPL_LUSERME_NUM, RPL_MODESET_MSG, RPL_MODESET_NUM, RPL_MYINFO_NUM, RPL_NAMEREPLY_NUM,
+        RPL_NOTOPIC_MSG, RPL_NOTOPIC_NUM, RPL_SILELIST_NUM, RPL_TOPIC_NUM, RPL_WELCOME_NUM,
+        RPL_YOUREOPER_MSG, RPL_YOUREOPER_NUM, RPL_YOURHOST_NUM,
+    },
+    server_utils::{
+        client_handler::FLOOD_NOTICE,
+        connection_handler::{AUTH_CHECKING_LOGIN, AUTH_CHECKING_REGISTRATION, AUTH_LOOKING_UP},
+        rate_limit::DEFAULT_MESSAGES_BURST,
     },
 };
 use std::{
+    fs::OpenOptions,
     io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
     thread,
     time::Duration,
 };
@@ -35,6 +57,87 @@ fn user_can_login_correctly() {
     login_ari();
 }
 
+#[test]
+fn login_handshake_sends_progress_notices_in_order() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+
+    let mut socket = TcpStream::connect("127.0.0.1:3000").unwrap();
+    let handshake = "LOGIN\r\nPASS wrong_password\r\nNICK ari\r\nUSER arisalese,127.0.0.1,main_server :Ariana Salese\r\n";
+    let result = socket.write_all(handshake.as_bytes());
+    assert!(result.is_ok());
+
+    let reader = BufReader::new(socket);
+    let received: Vec<String> = reader
+        .lines()
+        .take(3)
+        .map(|line| format!("{}\r\n", line.unwrap()))
+        .collect();
+
+    let invalid_login = NumericReply::new(
+        ERR_INVALIDLOGIN_NUM,
+        ERR_INVALIDLOGIN_MSG,
+        Some(vec!["ari".to_string(), "wrong_password".to_string()]),
+    )
+    .as_string();
+    assert_eq!(
+        received,
+        vec![
+            auth_notice(AUTH_LOOKING_UP),
+            auth_notice(AUTH_CHECKING_LOGIN),
+            invalid_login
+        ]
+    );
+}
+
+#[test]
+fn login_is_followed_by_the_welcome_of_the_server() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+
+    let mut socket = TcpStream::connect("127.0.0.1:3000").unwrap();
+    let handshake = "LOGIN\r\nPASS password123\r\nNICK ari\r\nUSER arisalese,127.0.0.1,main_server :Ariana Salese\r\n";
+    let result = socket.write_all(handshake.as_bytes());
+    assert!(result.is_ok());
+
+    let reader = BufReader::new(socket);
+    let received: Vec<String> = reader
+        .lines()
+        .skip(2)
+        .take(5)
+        .map(|line| format!("{}\r\n", line.unwrap()))
+        .collect();
+
+    let correct_login = NumericReply::new(
+        RPL_CORRECTLOGIN_NUM,
+        RPL_CORRECTLOGIN_MSG,
+        Some(vec!["ari".to_string()]),
+    )
+    .as_string();
+    assert_eq!(received[0], correct_login);
+    assert_eq!(
+        received[1],
+        format!(
+            "{} ari :Welcome to the Internet Relay Network ari\r\n",
+            RPL_WELCOME_NUM
+        )
+    );
+    assert!(received[2].starts_with(&format!(
+        "{} ari :Your host is main_server",
+        RPL_YOURHOST_NUM
+    )));
+    assert!(received[3].starts_with(&format!("{} ari ", RPL_CREATED_NUM)));
+    assert!(received[4].starts_with(&format!("{} ari main_server ", RPL_MYINFO_NUM)));
+}
+
 #[test]
 fn user_can_send_private_message_correctly() {
     let _handle = thread::spawn(move || {
@@ -61,6 +164,7 @@ fn user_can_send_private_message_correctly() {
     assert!(result.is_ok());
 
     let message = Message {
+        tags: None,
         prefix: Some("ari".to_string()),
         command: PRIVMSG.to_string(),
         params: vec![
@@ -104,6 +208,7 @@ fn user_can_join_and_communicate_in_channels_correctly() {
     let result = reader_ari.read_line(&mut data);
     assert!(result.is_ok());
     assert_eq!(data, reply);
+    read_creation_time(&mut reader_ari, "#canal");
     data.clear();
 
     let join_message = "JOIN #canal\r\n";
@@ -113,6 +218,7 @@ fn user_can_join_and_communicate_in_channels_correctly() {
     let result = reader_juani.read_line(&mut data);
     assert!(result.is_ok());
     assert_eq!(data, reply);
+    read_creation_time(&mut reader_juani, "#canal");
     data.clear();
 
     let priv_msg_message = "PRIVMSG #canal :Hola grupo\r\n";
@@ -123,6 +229,7 @@ fn user_can_join_and_communicate_in_channels_correctly() {
     assert!(result.is_ok());
 
     let message = Message {
+        tags: None,
         prefix: Some("juanireil".to_string()),
         command: PRIVMSG.to_string(),
         params: vec![vec!["#canal".to_string()], vec!["Hola grupo".to_string()]],
@@ -163,6 +270,7 @@ fn user_can_join_and_set_mode_to_invite_and_other_user_cant_join_without_invitat
     let result = reader_ari.read_line(&mut data);
     assert!(result.is_ok());
     assert_eq!(data, no_topic_reply);
+    read_creation_time(&mut reader_ari, "#canal");
     data.clear();
 
     let mode_message = "MODE #canal +i\r\n";
@@ -229,6 +337,7 @@ fn user_can_join_and_set_mode_to_invite_and_can_invite_other_user_and_it_can_joi
     let result = reader_ari.read_line(&mut data);
     assert!(result.is_ok());
     assert_eq!(data, no_topic_reply);
+    read_creation_time(&mut reader_ari, "#canal");
     data.clear();
 
     let mode_message = "MODE #canal +i\r\n";
@@ -270,6 +379,7 @@ fn user_can_join_and_set_mode_to_invite_and_can_invite_other_user_and_it_can_joi
     let result = reader_juani.read_line(&mut data);
     assert!(result.is_ok());
     assert_eq!(data, no_topic_reply);
+    read_creation_time(&mut reader_juani, "#canal");
 }
 
 #[test]
@@ -304,6 +414,7 @@ fn user_can_join_and_set_key_and_other_users_cant_join_without_it() {
     let result = reader_ari.read_line(&mut data);
     assert!(result.is_ok());
     assert_eq!(data, no_topic_reply);
+    read_creation_time(&mut reader_ari, "#canal");
     data.clear();
 
     let mode_message = "MODE #canal +k pass\r\n";
@@ -382,6 +493,7 @@ fn user_can_join_and_set_key_and_other_users_can_join_with_it_correctly() {
     let result = reader_ari.read_line(&mut data);
     assert!(result.is_ok());
     assert_eq!(data, no_topic_reply);
+    read_creation_time(&mut reader_ari, "#canal");
     data.clear();
 
     let mode_message = "MODE #canal +k pass\r\n";
@@ -407,6 +519,7 @@ fn user_can_join_and_set_key_and_other_users_can_join_with_it_correctly() {
     let result = reader_juani.read_line(&mut data);
     assert!(result.is_ok());
     assert_eq!(data, no_topic_reply);
+    read_creation_time(&mut reader_juani, "#canal");
 }
 
 #[test]
@@ -441,6 +554,7 @@ fn user_can_set_and_ask_for_channel_topic_correctly_with_mode_topic_or_not() {
     let result = reader_ari.read_line(&mut data);
     assert!(result.is_ok());
     assert_eq!(data, reply);
+    read_creation_time(&mut reader_ari, "#canal");
     data.clear();
 
     let join_message = "JOIN #canal\r\n";
@@ -450,6 +564,7 @@ fn user_can_set_and_ask_for_channel_topic_correctly_with_mode_topic_or_not() {
     let result = reader_juani.read_line(&mut data);
     assert!(result.is_ok());
     assert_eq!(data, reply);
+    read_creation_time(&mut reader_juani, "#canal");
     data.clear();
 
     let topic_message = "TOPIC #canal :Nuevo topic de juani\r\n";
@@ -468,6 +583,12 @@ fn user_can_set_and_ask_for_channel_topic_correctly_with_mode_topic_or_not() {
     assert_eq!(data, topic_reply);
     data.clear();
 
+    // The other members are told who changed the topic
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(data, ":juanireil TOPIC #canal :Nuevo topic de juani\r\n");
+    data.clear();
+
     let topic_message = "TOPIC #canal\r\n";
     let result = socket_ari.write_all(topic_message.as_bytes());
     assert!(result.is_ok());
@@ -475,6 +596,8 @@ fn user_can_set_and_ask_for_channel_topic_correctly_with_mode_topic_or_not() {
     let result = reader_ari.read_line(&mut data);
     assert!(result.is_ok());
     assert_eq!(data, topic_reply);
+    read_creation_time(&mut reader_ari, "#canal");
+    read_topic_who_time(&mut reader_ari, "#canal", "juanireil");
     data.clear();
 
     let mode_t_message = "MODE #canal +t\r\n";
@@ -516,5 +639,1846 @@ fn user_can_set_and_ask_for_channel_topic_correctly_with_mode_topic_or_not() {
     let result = reader_ari.read_line(&mut data);
     assert!(result.is_ok());
     assert_eq!(data, topic_reply);
+    read_creation_time(&mut reader_ari, "#canal");
+    read_topic_who_time(&mut reader_ari, "#canal", "juanireil");
+    data.clear();
+}
+
+#[test]
+fn operator_can_close_channel_and_members_are_notified() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+
+    let mut socket_ari = login_ari();
+    let mut socket_juani = login_juani();
+
+    let mut data = String::new();
+    let mut reader_ari = BufReader::new(socket_ari.try_clone().unwrap());
+    let mut reader_juani = BufReader::new(socket_juani.try_clone().unwrap());
+
+    let no_topic_reply = NumericReply::new(
+        RPL_NOTOPIC_NUM,
+        RPL_NOTOPIC_MSG,
+        Some(vec!["#canal".to_string()]),
+    )
+    .as_string();
+
+    std::thread::sleep(Duration::new(5, 0));
+
+    let join_message = "JOIN #canal\r\n";
+    let result = socket_ari.write_all(join_message.as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(data, no_topic_reply);
+    read_creation_time(&mut reader_ari, "#canal");
+    data.clear();
+
+    let result = socket_juani.write_all(join_message.as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_juani.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(data, no_topic_reply);
+    read_creation_time(&mut reader_juani, "#canal");
+    data.clear();
+
+    let close_message = "CLOSE #canal :Cerrando el canal\r\n";
+    let result = socket_ari.write_all(close_message.as_bytes());
+    assert!(result.is_ok());
+
+    let message = Message {
+        tags: None,
+        prefix: Some("ari".to_string()),
+        command: CLOSE.to_string(),
+        params: vec![
+            vec!["#canal".to_string()],
+            vec!["Cerrando el canal".to_string()],
+        ],
+    };
+
+    // Every member is notified
+    let result = reader_juani.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(data, message.as_string());
+    data.clear();
+
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(data, message.as_string());
+    data.clear();
+
+    // The channel no longer exists
+    let topic_message = "TOPIC #canal\r\n";
+    let result = socket_juani.write_all(topic_message.as_bytes());
+    assert!(result.is_ok());
+
+    let no_channel_err =
+        NumericReply::new(ERR_NOSUCHCHANNEL_NUM, ERR_NOSUCHCHANNEL_MSG, None).as_string();
+
+    let result = reader_juani.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(data, no_channel_err);
+}
+
+#[test]
+fn users_in_different_servers_can_send_private_messages() {
+    let (main_address, secondary_address) = run_multiserver();
+
+    let mut socket_ari = login(
+        &main_address,
+        "ari",
+        "arisalese",
+        "Ariana Salese",
+        "main_server",
+    );
+    let mut socket_marce = login(
+        &secondary_address,
+        "marce",
+        "marce",
+        "Marcelo Rondan",
+        "secondary_server_1",
+    );
+
+    let mut data = String::new();
+    let mut reader_ari = BufReader::new(socket_ari.try_clone().unwrap());
+    let mut reader_marce = BufReader::new(socket_marce.try_clone().unwrap());
+
+    std::thread::sleep(Duration::new(2, 0));
+
+    let priv_msg_message = "PRIVMSG marce :Hola marce\r\n";
+    let result = socket_ari.write_all(priv_msg_message.as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_marce.read_line(&mut data);
+    assert!(result.is_ok());
+
+    let message = Message {
+        tags: None,
+        prefix: Some("ari".to_string()),
+        command: PRIVMSG.to_string(),
+        params: vec![vec!["marce".to_string()], vec!["Hola marce".to_string()]],
+    };
+    assert_eq!(message.as_string(), data);
+    data.clear();
+
+    let priv_msg_message = "PRIVMSG ari :Hola ari\r\n";
+    let result = socket_marce.write_all(priv_msg_message.as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+
+    let message = Message {
+        tags: None,
+        prefix: Some("marce".to_string()),
+        command: PRIVMSG.to_string(),
+        params: vec![vec!["ari".to_string()], vec!["Hola ari".to_string()]],
+    };
+    assert_eq!(message.as_string(), data);
+}
+
+#[test]
+fn users_in_different_servers_can_communicate_in_channels() {
+    let (main_address, secondary_address) = run_multiserver();
+
+    let mut socket_ari = login(
+        &main_address,
+        "ari",
+        "arisalese",
+        "Ariana Salese",
+        "main_server",
+    );
+    let mut socket_marce = login(
+        &secondary_address,
+        "marce",
+        "marce",
+        "Marcelo Rondan",
+        "secondary_server_1",
+    );
+
+    let mut data = String::new();
+    let mut reader_ari = BufReader::new(socket_ari.try_clone().unwrap());
+    let mut reader_marce = BufReader::new(socket_marce.try_clone().unwrap());
+
+    let no_topic_reply = NumericReply::new(
+        RPL_NOTOPIC_NUM,
+        RPL_NOTOPIC_MSG,
+        Some(vec!["#canal".to_string()]),
+    )
+    .as_string();
+
+    std::thread::sleep(Duration::new(2, 0));
+
+    let join_message = "JOIN #canal\r\n";
+    let result = socket_ari.write_all(join_message.as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(data, no_topic_reply);
+    read_creation_time(&mut reader_ari, "#canal");
+    data.clear();
+
+    // Wait until the channel reaches the secondary server
+    std::thread::sleep(Duration::new(2, 0));
+
+    let result = socket_marce.write_all(join_message.as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_marce.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(data, no_topic_reply);
+    read_creation_time(&mut reader_marce, "#canal");
+    data.clear();
+
+    std::thread::sleep(Duration::new(2, 0));
+
+    let priv_msg_message = "PRIVMSG #canal :Hola desde el otro servidor\r\n";
+    let result = socket_marce.write_all(priv_msg_message.as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+
+    let message = Message {
+        tags: None,
+        prefix: Some("marce".to_string()),
+        command: PRIVMSG.to_string(),
+        params: vec![
+            vec!["#canal".to_string()],
+            vec!["Hola desde el otro servidor".to_string()],
+        ],
+    };
+    assert_eq!(message.as_string(), data);
+}
+
+#[test]
+fn knock_on_an_invite_only_channel_reaches_its_operators_in_other_servers() {
+    let (main_address, secondary_address) = run_multiserver();
+
+    let mut socket_ari = login(
+        &main_address,
+        "ari",
+        "arisalese",
+        "Ariana Salese",
+        "main_server",
+    );
+    let mut socket_marce = login(
+        &secondary_address,
+        "marce",
+        "marce",
+        "Marcelo Rondan",
+        "secondary_server_1",
+    );
+
+    let mut data = String::new();
+    let mut reader_ari = BufReader::new(socket_ari.try_clone().unwrap());
+    let mut reader_marce = BufReader::new(socket_marce.try_clone().unwrap());
+
+    std::thread::sleep(Duration::new(2, 0));
+
+    let result = socket_ari.write_all("JOIN #canal\r\n".as_bytes());
+    assert!(result.is_ok());
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    read_creation_time(&mut reader_ari, "#canal");
+    data.clear();
+
+    // Wait until the channel reaches the secondary server
+    std::thread::sleep(Duration::new(2, 0));
+
+    // Channels that are not invite only can be joined without knocking
+    let knock_message = "KNOCK #canal :Quiero entrar\r\n";
+    let result = socket_marce.write_all(knock_message.as_bytes());
+    assert!(result.is_ok());
+    let result = reader_marce.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(
+        data,
+        NumericReply::new(
+            ERR_CHANOPEN_NUM,
+            ERR_CHANOPEN_MSG,
+            Some(vec!["#canal".to_string()])
+        )
+        .as_string()
+    );
+    data.clear();
+
+    let result = socket_ari.write_all("MODE #canal +i\r\n".as_bytes());
+    assert!(result.is_ok());
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(
+        data,
+        NumericReply::new(
+            RPL_MODESET_NUM,
+            RPL_MODESET_MSG,
+            Some(vec!["#canal".to_string(), "+i".to_string()]),
+        )
+        .as_string()
+    );
+    data.clear();
+
+    std::thread::sleep(Duration::new(2, 0));
+
+    let result = socket_marce.write_all(knock_message.as_bytes());
+    assert!(result.is_ok());
+    let result = reader_marce.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(
+        data,
+        NumericReply::new(
+            RPL_KNOCKDLVR_NUM,
+            RPL_KNOCKDLVR_MSG,
+            Some(vec!["#canal".to_string()])
+        )
+        .as_string()
+    );
+    data.clear();
+
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    let knock_notice = Message {
+        tags: None,
+        prefix: Some("#canal".to_string()),
+        command: NOTICE.to_string(),
+        params: vec![
+            vec!["ari".to_string()],
+            vec!["marce is knocking, asking for an invitation - Quiero entrar".to_string()],
+        ],
+    };
+    assert_eq!(data, knock_notice.as_string());
+}
+
+#[test]
+fn channel_keyed_before_a_secondary_server_connects_stays_keyed_on_it() {
+    let main_address = run_main_server();
+
+    let mut socket_ari = login(
+        &main_address,
+        "ari",
+        "arisalese",
+        "Ariana Salese",
+        "main_server",
+    );
+
+    let mut data = String::new();
+    let mut reader_ari = BufReader::new(socket_ari.try_clone().unwrap());
+
+    let no_topic_reply = NumericReply::new(
+        RPL_NOTOPIC_NUM,
+        RPL_NOTOPIC_MSG,
+        Some(vec!["#canal".to_string()]),
+    )
+    .as_string();
+
+    std::thread::sleep(Duration::new(2, 0));
+
+    let result = socket_ari.write_all("JOIN #canal\r\n".as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(data, no_topic_reply);
+    read_creation_time(&mut reader_ari, "#canal");
     data.clear();
+
+    let result = socket_ari.write_all("MODE #canal +k pass\r\n".as_bytes());
+    assert!(result.is_ok());
+
+    let mode_set_reply = NumericReply::new(
+        RPL_MODESET_NUM,
+        RPL_MODESET_MSG,
+        Some(vec!["#canal".to_string(), "+k".to_string()]),
+    )
+    .as_string();
+
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(data, mode_set_reply);
+    data.clear();
+
+    // The secondary server only knows the key from the channels sent when it connects
+    let secondary_address = run_secondary_server(&main_address);
+
+    let mut socket_marce = login(
+        &secondary_address,
+        "marce",
+        "marce",
+        "Marcelo Rondan",
+        "secondary_server_1",
+    );
+    let mut reader_marce = BufReader::new(socket_marce.try_clone().unwrap());
+
+    std::thread::sleep(Duration::new(2, 0));
+
+    let result = socket_marce.write_all("JOIN #canal\r\n".as_bytes());
+    assert!(result.is_ok());
+
+    let key_set_err =
+        NumericReply::new(ERR_CHANNELHASKEY_NUM, ERR_CHANNELHASKEY_MSG, None).as_string();
+
+    let result = reader_marce.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(data, key_set_err);
+    data.clear();
+
+    let result = socket_marce.write_all("JOIN #canal pass\r\n".as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_marce.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(data, no_topic_reply);
+}
+
+#[test]
+fn silenced_user_messages_are_not_delivered() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+
+    let mut socket_ari = login_ari();
+    let mut socket_juani = login_juani();
+    let mut socket_lucas = login("127.0.0.1:3000", "lucas", "lu", "Lucas Bilo", "main_server");
+
+    let mut data = String::new();
+    let mut reader_juani = BufReader::new(socket_juani.try_clone().unwrap());
+    let mut reader_lucas = BufReader::new(socket_lucas.try_clone().unwrap());
+
+    std::thread::sleep(Duration::new(2, 0));
+
+    let silence_message = "SILENCE +ari\r\n";
+    let result = socket_juani.write_all(silence_message.as_bytes());
+    assert!(result.is_ok());
+
+    std::thread::sleep(Duration::new(1, 0));
+
+    let priv_msg_message = "PRIVMSG juanireil :No deberias leer esto\r\n";
+    let result = socket_ari.write_all(priv_msg_message.as_bytes());
+    assert!(result.is_ok());
+
+    // Ari can still talk to users that did not silence her
+    let priv_msg_message = "PRIVMSG lucas :Hola lucas\r\n";
+    let result = socket_ari.write_all(priv_msg_message.as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_lucas.read_line(&mut data);
+    assert!(result.is_ok());
+    let message = Message {
+        tags: None,
+        prefix: Some("ari".to_string()),
+        command: PRIVMSG.to_string(),
+        params: vec![vec!["lucas".to_string()], vec!["Hola lucas".to_string()]],
+    };
+    assert_eq!(message.as_string(), data);
+    data.clear();
+
+    let priv_msg_message = "PRIVMSG juanireil :Hola juani\r\n";
+    let result = socket_lucas.write_all(priv_msg_message.as_bytes());
+    assert!(result.is_ok());
+
+    // The first message juani receives is the one from lucas
+    let result = reader_juani.read_line(&mut data);
+    assert!(result.is_ok());
+    let message = Message {
+        tags: None,
+        prefix: Some("lucas".to_string()),
+        command: PRIVMSG.to_string(),
+        params: vec![
+            vec!["juanireil".to_string()],
+            vec!["Hola juani".to_string()],
+        ],
+    };
+    assert_eq!(message.as_string(), data);
+    data.clear();
+
+    let result = socket_juani.write_all("SILENCE\r\n".as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_juani.read_line(&mut data);
+    assert!(result.is_ok());
+    let silenced_reply = NumericReply::new(
+        RPL_SILELIST_NUM,
+        "",
+        Some(vec!["juanireil".to_string(), "ari".to_string()]),
+    )
+    .as_string();
+    assert_eq!(data, silenced_reply);
+    data.clear();
+
+    let result = reader_juani.read_line(&mut data);
+    assert!(result.is_ok());
+    let end_reply = NumericReply::new(
+        RPL_ENDOFSILELIST_NUM,
+        RPL_ENDOFSILELIST_MSG,
+        Some(vec!["juanireil".to_string()]),
+    )
+    .as_string();
+    assert_eq!(data, end_reply);
+}
+
+#[test]
+fn wallops_is_only_delivered_to_operators() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+
+    let mut socket_ari = login_ari();
+    let mut socket_juani = login_juani();
+
+    let mut data = String::new();
+    let mut reader_ari = BufReader::new(socket_ari.try_clone().unwrap());
+    let mut reader_juani = BufReader::new(socket_juani.try_clone().unwrap());
+
+    std::thread::sleep(Duration::new(2, 0));
+
+    // The password of the account is not the one of the operator
+    let result = socket_ari.write_all("OPER ari password123\r\n".as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(
+        data,
+        NumericReply::new(ERR_PASSWDMISMATCH_NUM, ERR_PASSWDMISMATCH_MSG, None).as_string()
+    );
+    data.clear();
+
+    let oper_message = "OPER ari clave_de_oper\r\n";
+    let result = socket_ari.write_all(oper_message.as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(
+        data,
+        NumericReply::new(RPL_YOUREOPER_NUM, RPL_YOUREOPER_MSG, None).as_string()
+    );
+    data.clear();
+
+    // Users that are not operators can't send it
+    let wallops_message = "WALLOPS :Reinicio en 5 minutos\r\n";
+    let result = socket_juani.write_all(wallops_message.as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_juani.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(
+        data,
+        NumericReply::new(ERR_NOPRIVILEGES_NUM, ERR_NOPRIVILEGES_MSG, None).as_string()
+    );
+    data.clear();
+
+    let result = socket_ari.write_all(wallops_message.as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    let message = Message {
+        tags: None,
+        prefix: Some("ari".to_string()),
+        command: WALLOPS.to_string(),
+        params: vec![vec!["Reinicio en 5 minutos".to_string()]],
+    };
+    assert_eq!(data, message.as_string());
+    data.clear();
+
+    // Juani is not an operator, so the next line received is the private message
+    let priv_msg_message = "PRIVMSG juanireil :Hola juani\r\n";
+    let result = socket_ari.write_all(priv_msg_message.as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_juani.read_line(&mut data);
+    assert!(result.is_ok());
+    let message = Message {
+        tags: None,
+        prefix: Some("ari".to_string()),
+        command: PRIVMSG.to_string(),
+        params: vec![
+            vec!["juanireil".to_string()],
+            vec!["Hola juani".to_string()],
+        ],
+    };
+    assert_eq!(data, message.as_string());
+}
+
+#[test]
+fn globops_reaches_the_operators_of_every_server() {
+    let (main_address, secondary_address) = run_multiserver();
+
+    let mut socket_ari = login(
+        &main_address,
+        "ari",
+        "arisalese",
+        "Ariana Salese",
+        "main_server",
+    );
+    let mut socket_juani = login(
+        &main_address,
+        "juanireil",
+        "juanireil",
+        "Juani Reil",
+        "main_server",
+    );
+    let mut socket_marce = login(
+        &secondary_address,
+        "marce",
+        "marce",
+        "Marcelo Rondan",
+        "secondary_server_1",
+    );
+
+    let mut data = String::new();
+    let mut reader_ari = BufReader::new(socket_ari.try_clone().unwrap());
+    let mut reader_juani = BufReader::new(socket_juani.try_clone().unwrap());
+    let mut reader_marce = BufReader::new(socket_marce.try_clone().unwrap());
+
+    std::thread::sleep(Duration::new(2, 0));
+
+    let oper_reply = NumericReply::new(RPL_YOUREOPER_NUM, RPL_YOUREOPER_MSG, None).as_string();
+    let result = socket_ari.write_all("OPER ari clave_de_oper\r\n".as_bytes());
+    assert!(result.is_ok());
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(data, oper_reply);
+    data.clear();
+
+    let result = socket_marce.write_all("OPER marce clave_de_oper\r\n".as_bytes());
+    assert!(result.is_ok());
+    let result = reader_marce.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(data, oper_reply);
+    data.clear();
+
+    // Users that are not operators can't send it
+    let globops_message = "GLOBOPS :Reinicio en 5 minutos\r\n";
+    let result = socket_juani.write_all(globops_message.as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_juani.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(
+        data,
+        NumericReply::new(ERR_NOPRIVILEGES_NUM, ERR_NOPRIVILEGES_MSG, None).as_string()
+    );
+    data.clear();
+
+    // The operator of the secondary server reaches the one of the main server, and itself,
+    // with the WALLOPS that GLOBOPS is another name for
+    let result = socket_marce.write_all(globops_message.as_bytes());
+    assert!(result.is_ok());
+
+    let message = Message {
+        tags: None,
+        prefix: Some("marce".to_string()),
+        command: WALLOPS.to_string(),
+        params: vec![vec!["Reinicio en 5 minutos".to_string()]],
+    };
+    let result = reader_marce.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(data, message.as_string());
+    data.clear();
+
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(data, message.as_string());
+    data.clear();
+
+    // Juani is not an operator, so the next line received is the private message
+    let result = socket_ari.write_all("PRIVMSG juanireil :Hola juani\r\n".as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_juani.read_line(&mut data);
+    assert!(result.is_ok());
+    let message = Message {
+        tags: None,
+        prefix: Some("ari".to_string()),
+        command: PRIVMSG.to_string(),
+        params: vec![
+            vec!["juanireil".to_string()],
+            vec!["Hola juani".to_string()],
+        ],
+    };
+    assert_eq!(data, message.as_string());
+}
+
+#[test]
+fn lusers_shows_how_many_users_channels_and_servers_there_are() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+
+    let mut socket_ari = login_ari();
+    let _socket_juani = login_juani();
+
+    let mut data = String::new();
+    let mut reader_ari = BufReader::new(socket_ari.try_clone().unwrap());
+
+    std::thread::sleep(Duration::new(2, 0));
+
+    let join_message = "JOIN #canal\r\n";
+    let result = socket_ari.write_all(join_message.as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    data.clear();
+    read_creation_time(&mut reader_ari, "#canal");
+
+    let registered_users = std::fs::read_to_string("tests/common/users_test.txt")
+        .unwrap()
+        .lines()
+        .filter(|line| line.starts_with("U;"))
+        .count();
+
+    let lusers_message = "LUSERS\r\n";
+    let result = socket_ari.write_all(lusers_message.as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    let message = Message {
+        tags: None,
+        prefix: None,
+        command: RPL_LUSERCLIENT_NUM.to_string(),
+        params: vec![vec![format!(
+            "There are {} users and 0 services on 1 servers",
+            registered_users
+        )]],
+    };
+    assert_eq!(data, message.as_string());
+    data.clear();
+
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    let message = Message {
+        tags: None,
+        prefix: None,
+        command: RPL_LUSERCHANNELS_NUM.to_string(),
+        params: vec![
+            vec!["1".to_string()],
+            vec![RPL_LUSERCHANNELS_MSG.to_string()],
+        ],
+    };
+    assert_eq!(data, message.as_string());
+    data.clear();
+
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    let message = Message {
+        tags: None,
+        prefix: None,
+        command: RPL_LUSERME_NUM.to_string(),
+        params: vec![vec!["I have 2 clients and 0 servers".to_string()]],
+    };
+    assert_eq!(data, message.as_string());
+}
+
+#[test]
+fn motd_without_motd_file_returns_err_nomotd() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+
+    let mut socket_ari = login_ari();
+
+    let mut data = String::new();
+    let mut reader_ari = BufReader::new(socket_ari.try_clone().unwrap());
+
+    std::thread::sleep(Duration::new(2, 0));
+
+    let motd_message = "MOTD\r\n";
+    let result = socket_ari.write_all(motd_message.as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(
+        data,
+        NumericReply::new(ERR_NOMOTD_NUM, ERR_NOMOTD_MSG, None).as_string()
+    );
+}
+
+#[test]
+fn kicked_user_receives_the_comment_of_the_kick() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+
+    let mut socket_ari = login_ari();
+    let mut socket_juani = login_juani();
+
+    let mut data = String::new();
+    let mut reader_ari = BufReader::new(socket_ari.try_clone().unwrap());
+    let mut reader_juani = BufReader::new(socket_juani.try_clone().unwrap());
+
+    std::thread::sleep(Duration::new(2, 0));
+
+    let join_message = "JOIN #canal\r\n";
+    let result = socket_ari.write_all(join_message.as_bytes());
+    assert!(result.is_ok());
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    data.clear();
+    read_creation_time(&mut reader_ari, "#canal");
+
+    let result = socket_juani.write_all(join_message.as_bytes());
+    assert!(result.is_ok());
+    let result = reader_juani.read_line(&mut data);
+    assert!(result.is_ok());
+    data.clear();
+    read_creation_time(&mut reader_juani, "#canal");
+
+    let kick_message = "KICK #canal juanireil :too much spam\r\n";
+    let result = socket_ari.write_all(kick_message.as_bytes());
+    assert!(result.is_ok());
+
+    let result = reader_juani.read_line(&mut data);
+    assert!(result.is_ok());
+    let message = Message {
+        tags: None,
+        prefix: Some("ari".to_string()),
+        command: KICK.to_string(),
+        params: vec![
+            vec!["#canal".to_string()],
+            vec!["juanireil".to_string()],
+            vec!["too much spam".to_string()],
+        ],
+    };
+    assert_eq!(data, message.as_string());
+}
+
+#[test]
+fn user_banned_by_mask_cant_join_and_bans_are_listed() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+
+    let mut socket_ari = login_ari();
+    let mut socket_juani = login_juani();
+
+    let mut data = String::new();
+    let mut reader_ari = BufReader::new(socket_ari.try_clone().unwrap());
+    let mut reader_juani = BufReader::new(socket_juani.try_clone().unwrap());
+
+    std::thread::sleep(Duration::new(2, 0));
+
+    let join_message = "JOIN #canal\r\n";
+    let result = socket_ari.write_all(join_message.as_bytes());
+    assert!(result.is_ok());
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    data.clear();
+    read_creation_time(&mut reader_ari, "#canal");
+
+    let mode_message = "MODE #canal +b juani*!*@*\r\n";
+    let result = socket_ari.write_all(mode_message.as_bytes());
+    assert!(result.is_ok());
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    data.clear();
+
+    let result = socket_juani.write_all(join_message.as_bytes());
+    assert!(result.is_ok());
+    let result = reader_juani.read_line(&mut data);
+    assert!(result.is_ok());
+    let banned_err = NumericReply::new(
+        ERR_BANNEDFROMCHAN_NUM,
+        ERR_BANNEDFROMCHAN_MSG,
+        Some(vec!["#canal".to_string()]),
+    )
+    .as_string();
+    assert_eq!(data, banned_err);
+    data.clear();
+
+    let mode_message = "MODE #canal +b\r\n";
+    let result = socket_ari.write_all(mode_message.as_bytes());
+    assert!(result.is_ok());
+
+    let ban_list_reply = NumericReply::new(
+        RPL_BANLIST_NUM,
+        "",
+        Some(vec!["#canal".to_string(), "juani*!*@*".to_string()]),
+    )
+    .as_string();
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(data, ban_list_reply);
+    data.clear();
+
+    let end_of_ban_list_reply = NumericReply::new(
+        RPL_ENDOFBANLIST_NUM,
+        RPL_ENDOFBANLIST_MSG,
+        Some(vec!["#canal".to_string()]),
+    )
+    .as_string();
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(data, end_of_ban_list_reply);
+}
+
+#[test]
+fn user_with_ban_exception_can_join_and_exceptions_are_listed() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+
+    let mut socket_ari = login_ari();
+    let mut socket_juani = login_juani();
+
+    let mut data = String::new();
+    let mut reader_ari = BufReader::new(socket_ari.try_clone().unwrap());
+    let mut reader_juani = BufReader::new(socket_juani.try_clone().unwrap());
+
+    std::thread::sleep(Duration::new(2, 0));
+
+    let join_message = "JOIN #excepciones\r\n";
+    let result = socket_ari.write_all(join_message.as_bytes());
+    assert!(result.is_ok());
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    data.clear();
+    read_creation_time(&mut reader_ari, "#excepciones");
+
+    for mode_message in [
+        "MODE #excepciones +b juani*\r\n",
+        "MODE #excepciones +e juanireil!*@*\r\n",
+    ] {
+        let result = socket_ari.write_all(mode_message.as_bytes());
+        assert!(result.is_ok());
+        let result = reader_ari.read_line(&mut data);
+        assert!(result.is_ok());
+        data.clear();
+    }
+
+    let result = socket_juani.write_all(join_message.as_bytes());
+    assert!(result.is_ok());
+    let result = reader_juani.read_line(&mut data);
+    assert!(result.is_ok());
+    assert!(!data.starts_with(ERR_BANNEDFROMCHAN_NUM));
+    data.clear();
+    read_creation_time(&mut reader_juani, "#excepciones");
+
+    let mode_message = "MODE #excepciones +e\r\n";
+    let result = socket_ari.write_all(mode_message.as_bytes());
+    assert!(result.is_ok());
+
+    let exception_list_reply = NumericReply::new(
+        RPL_EXCEPTLIST_NUM,
+        "",
+        Some(vec![
+            "#excepciones".to_string(),
+            "juanireil!*@*".to_string(),
+        ]),
+    )
+    .as_string();
+    let end_of_exception_list_reply = NumericReply::new(
+        RPL_ENDOFEXCEPTLIST_NUM,
+        RPL_ENDOFEXCEPTLIST_MSG,
+        Some(vec!["#excepciones".to_string()]),
+    )
+    .as_string();
+    for expected in [exception_list_reply, end_of_exception_list_reply] {
+        // Ari is told that juani joined before the list arrives
+        loop {
+            let result = reader_ari.read_line(&mut data);
+            assert!(result.is_ok());
+            if !data.contains(" JOIN ") {
+                break;
+            }
+            data.clear();
+        }
+        assert_eq!(data, expected);
+        data.clear();
+    }
+}
+
+#[test]
+fn registration_with_weak_password_is_rejected() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+
+    let mut socket = TcpStream::connect("127.0.0.1:3000").unwrap();
+    let handshake = "REGISTRATION\r\nPASS abc\r\nNICK nuevo\r\nUSER nuevo,127.0.0.1,main_server :Usuario Nuevo\r\n";
+    let result = socket.write_all(handshake.as_bytes());
+    assert!(result.is_ok());
+
+    let reader = BufReader::new(socket);
+    let received: Vec<String> = reader
+        .lines()
+        .take(3)
+        .map(|line| format!("{}\r\n", line.unwrap()))
+        .collect();
+
+    let weak_password = NumericReply::new(
+        ERR_WEAKPASSWORD_NUM,
+        "Password must have at least 8 characters",
+        Some(vec!["nuevo".to_string()]),
+    )
+    .as_string();
+    assert_eq!(
+        received,
+        vec![
+            auth_notice(AUTH_LOOKING_UP),
+            auth_notice(AUTH_CHECKING_REGISTRATION),
+            weak_password
+        ]
+    );
+}
+
+#[test]
+fn registration_with_invalid_nickname_says_why() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+
+    let mut socket = TcpStream::connect("127.0.0.1:3000").unwrap();
+    let handshake = "REGISTRATION\r\nPASS password123\r\nNICK 9nuevo\r\nUSER nuevo,127.0.0.1,main_server :Usuario Nuevo\r\n";
+    let result = socket.write_all(handshake.as_bytes());
+    assert!(result.is_ok());
+
+    let reader = BufReader::new(socket);
+    let received: Vec<String> = reader
+        .lines()
+        .take(2)
+        .map(|line| format!("{}\r\n", line.unwrap()))
+        .collect();
+
+    let erroneous_nickname = NumericReply::new(
+        ERR_ERRONEUSNICKNAME_NUM,
+        "Nickname cannot start with a digit",
+        Some(vec!["9nuevo".to_string()]),
+    )
+    .as_string();
+    assert_eq!(
+        received,
+        vec![auth_notice(AUTH_LOOKING_UP), erroneous_nickname]
+    );
+}
+
+#[test]
+fn headless_clients_can_login_join_and_talk_in_a_channel() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+    let timeout = Duration::from_secs(5);
+
+    let ari = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(ari.login("ari", "password123", timeout).is_ok());
+    ari.join("#canal").unwrap();
+    let channel_chat = ari.wait_for(timeout, |update| {
+        update.command == *ADD_LIST_CHATS && update.prefix == Some("#canal".to_string())
+    });
+    assert!(channel_chat.is_some());
+    assert!(ari.chats().contains(&"#canal".to_string()));
+
+    let juani = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(juani.login("juanireil", "password123", timeout).is_ok());
+    juani.join("#canal").unwrap();
+    juani.privmsg("#canal", "Hola grupo").unwrap();
+
+    assert_eq!(
+        ari.wait_for_text("Hola grupo", timeout),
+        Some(("#canal".to_string(), "juanireil: Hola grupo".to_string()))
+    );
+}
+
+#[test]
+fn users_joining_a_channel_get_its_last_messages() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+    let timeout = Duration::from_secs(5);
+
+    let juani = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(juani.login("juanireil", "password123", timeout).is_ok());
+    juani.join("#backlog").unwrap();
+    juani.privmsg("#backlog", "Primer mensaje").unwrap();
+    juani.privmsg("#backlog", "Segundo mensaje").unwrap();
+    std::thread::sleep(Duration::from_secs(1));
+
+    let ari = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(ari.login("ari", "password123", timeout).is_ok());
+    ari.join("#backlog").unwrap();
+    assert_eq!(
+        ari.wait_for_text("Primer mensaje", timeout),
+        Some((
+            "#backlog".to_string(),
+            "juanireil: (backlog) Primer mensaje".to_string()
+        ))
+    );
+    assert!(ari
+        .wait_for_text("(backlog) Segundo mensaje", timeout)
+        .is_some());
+
+    // Joining again doesn't replay the backlog to a user already there
+    ari.join("#backlog").unwrap();
+    assert!(ari
+        .wait_for_text("(backlog)", Duration::from_secs(2))
+        .is_none());
+}
+
+#[test]
+fn server_reports_address_in_use_and_malformed_addresses() {
+    let address = free_address();
+    let _listener = TcpListener::bind(&address).unwrap();
+    let server_data = write_server_data("busy_server", &address, "juanireil", None);
+
+    assert_eq!(
+        run_server_with_data(server_data),
+        Err(format!(
+            "Address {} is already in use, another server may be running on that port",
+            address
+        ))
+    );
+
+    let server_data = write_server_data("malformed_server", "127.0.0.1:70000", "juanireil", None);
+    assert_eq!(
+        run_server_with_data(server_data),
+        Err(
+            "Invalid server address 127.0.0.1:70000: the port must be a number between 1 and 65535"
+                .to_string()
+        )
+    );
+}
+
+#[test]
+fn connections_beyond_the_limit_of_an_address_are_closed_until_one_quits() {
+    let address = free_address();
+    let server_data = write_server_data("main_server", &address, "juanireil", None);
+    // Two connections per ip, localhost included
+    let mut file = OpenOptions::new().append(true).open(&server_data).unwrap();
+    file.write_all(b"\nI;2;false").unwrap();
+    let _handle = thread::spawn(move || {
+        let result = run_server_with_data(server_data);
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(2, 0));
+
+    let mut socket_ari = login(&address, "ari", "arisalese", "Ariana Salese", "main_server");
+    let _socket_waiting = TcpStream::connect(&address).unwrap();
+
+    let refused_socket = TcpStream::connect(&address).unwrap();
+    let mut reader_refused = BufReader::new(refused_socket);
+    let mut data = String::new();
+    let result = reader_refused.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(
+        data,
+        "ERROR :Closing link: too many connections from 127.0.0.1\r\n"
+    );
+    data.clear();
+    // The server closed it
+    assert_eq!(reader_refused.read_line(&mut data).unwrap(), 0);
+
+    let result = socket_ari.write_all(format!("{}\r\n", QUIT).as_bytes());
+    assert!(result.is_ok());
+
+    std::thread::sleep(Duration::new(2, 0));
+
+    // The connection of the user that quit is not counted anymore
+    let _socket_juani = login(
+        &address,
+        "juanireil",
+        "juanireil",
+        "Juani Reil",
+        "main_server",
+    );
+}
+
+#[test]
+fn nicknames_and_channel_names_are_compared_without_case() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+    let timeout = Duration::from_secs(5);
+
+    let ari = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(ari.login("ari", "password123", timeout).is_ok());
+    ari.join("#Canal").unwrap();
+
+    let juani = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(juani.login("JuaniReil", "password123", timeout).is_ok());
+    juani.join("#canal").unwrap();
+    juani.privmsg("#CANAL", "Hola grupo").unwrap();
+
+    // Messages are shown with the names as they were registered and created
+    assert_eq!(
+        ari.wait_for_text("Hola grupo", timeout),
+        Some(("#Canal".to_string(), "juanireil: Hola grupo".to_string()))
+    );
+
+    juani.privmsg("ARI", "Hola ari").unwrap();
+    assert!(ari.wait_for_text("Hola ari", timeout).is_some());
+}
+
+#[test]
+fn ison_answers_only_the_users_connected() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+    let timeout = Duration::from_secs(5);
+
+    let ari = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(ari.login("ari", "password123", timeout).is_ok());
+    let juani = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(juani.login("juanireil", "password123", timeout).is_ok());
+
+    // lucas is registered but not connected, marce is from a server that is not connected
+    // and nobody is called pepe
+    ari.send(Message {
+        tags: None,
+        prefix: None,
+        command: ISON.to_string(),
+        params: vec![
+            vec!["JUANIREIL".to_string()],
+            vec!["lucas".to_string()],
+            vec!["marce".to_string()],
+            vec!["pepe".to_string()],
+        ],
+    })
+    .unwrap();
+    assert_eq!(
+        ari.wait_for_text("Online", timeout),
+        Some(("You".to_string(), "Online: juanireil".to_string()))
+    );
+
+    ari.send(Message {
+        tags: None,
+        prefix: None,
+        command: ISON.to_string(),
+        params: vec![vec!["marce".to_string()]],
+    })
+    .unwrap();
+    assert!(ari
+        .wait_for_text("None of them is online", timeout)
+        .is_some());
+}
+
+#[test]
+fn whowas_shows_the_users_that_quit() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+    let timeout = Duration::from_secs(5);
+
+    let ari = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(ari.login("ari", "password123", timeout).is_ok());
+    let juani = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(juani.login("juanireil", "password123", timeout).is_ok());
+
+    // Nobody called pepe ever connected, so only ari is in the history after quitting
+    ari.quit().unwrap();
+    std::thread::sleep(Duration::from_secs(1));
+
+    let whowas = |nickname: &str| Message {
+        tags: None,
+        prefix: None,
+        command: WHOWAS.to_string(),
+        params: vec![vec![nickname.to_string()]],
+    };
+    juani.send(whowas("ARI")).unwrap();
+    let (_, text) = juani.wait_for_text("was online", timeout).unwrap();
+    assert!(text.starts_with("ari was online with username"));
+
+    juani.send(whowas("pepe")).unwrap();
+    assert!(juani
+        .wait_for_text(ERR_WASNOSUCHNICK_MSG, timeout)
+        .is_some());
+}
+
+#[test]
+fn invisible_users_are_only_found_with_who_by_users_in_their_channels() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+    let timeout = Duration::from_secs(5);
+
+    let ari = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(ari.login("ari", "password123", timeout).is_ok());
+    let juani = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(juani.login("juanireil", "password123", timeout).is_ok());
+    let lucas = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(lucas.login("lucas", "password123", timeout).is_ok());
+
+    ari.send(Message {
+        tags: None,
+        prefix: None,
+        command: MODE.to_string(),
+        params: vec![vec!["ari".to_string()], vec!["+i".to_string()]],
+    })
+    .unwrap();
+    assert!(ari.wait_for_text("Your modes: +i", timeout).is_some());
+
+    let who_finds_ari = |client: &HeadlessClient| -> bool {
+        client
+            .send(Message {
+                tags: None,
+                prefix: None,
+                command: WHO.to_string(),
+                params: vec![vec!["ari".to_string()]],
+            })
+            .unwrap();
+        let found = client
+            .wait_for(timeout, |update| update.command == *SEARCH_USERS)
+            .unwrap();
+        found.params[0].contains(&"ari".to_string())
+    };
+    assert!(!who_finds_ari(&juani));
+
+    ari.join("#canal").unwrap();
+    juani.join("#canal").unwrap();
+    std::thread::sleep(Duration::new(1, 0));
+    assert!(who_finds_ari(&juani));
+    assert!(!who_finds_ari(&lucas));
+}
+
+#[test]
+fn who_of_a_channel_lists_its_members_with_their_flags() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+    let timeout = Duration::from_secs(5);
+
+    let ari = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(ari.login("ari", "password123", timeout).is_ok());
+    let juani = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(juani.login("juanireil", "password123", timeout).is_ok());
+
+    ari.join("#canal").unwrap();
+    std::thread::sleep(Duration::new(1, 0));
+    juani.join("#canal").unwrap();
+    ari.send(Message {
+        tags: None,
+        prefix: None,
+        command: AWAY.to_string(),
+        params: vec![vec!["Volvemos en 5".to_string()]],
+    })
+    .unwrap();
+    std::thread::sleep(Duration::new(1, 0));
+
+    juani
+        .send(Message {
+            tags: None,
+            prefix: None,
+            command: WHO.to_string(),
+            params: vec![vec!["#canal".to_string()]],
+        })
+        .unwrap();
+    let found = juani
+        .wait_for(timeout, |update| update.command == *SEARCH_USERS)
+        .unwrap();
+    assert_eq!(
+        found.params,
+        vec![
+            vec!["ari".to_string(), "juanireil".to_string()],
+            vec!["@ari (away)".to_string(), "juanireil".to_string()]
+        ]
+    );
+}
+
+#[test]
+fn private_message_to_away_user_is_delivered_and_sender_is_told() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+    let timeout = Duration::from_secs(5);
+
+    let ari = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(ari.login("ari", "password123", timeout).is_ok());
+    let juani = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(juani.login("juanireil", "password123", timeout).is_ok());
+
+    ari.send(Message {
+        tags: None,
+        prefix: None,
+        command: AWAY.to_string(),
+        params: vec![vec!["Volvemos en 5".to_string()]],
+    })
+    .unwrap();
+    std::thread::sleep(Duration::new(1, 0));
+
+    juani.privmsg("ari", "Hola ari").unwrap();
+    assert_eq!(
+        juani.wait_for_text("is away", timeout),
+        Some(("ari".to_string(), "ari is away 'Volvemos en 5'".to_string()))
+    );
+    assert!(ari.wait_for_text("Hola ari", timeout).is_some());
+
+    // Channel messages don't answer with the away message
+    ari.join("#canal").unwrap();
+    juani.join("#canal").unwrap();
+    std::thread::sleep(Duration::new(1, 0));
+    juani.privmsg("#canal", "Hola grupo").unwrap();
+    assert!(ari.wait_for_text("Hola grupo", timeout).is_some());
+    assert!(juani.wait_for_text("is away", timeout).is_none());
+}
+
+#[test]
+fn messages_sent_too_fast_are_dropped_and_the_sender_is_told() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+
+    let mut socket_ari = login_ari();
+    let socket_juani = login_juani();
+
+    std::thread::sleep(Duration::new(2, 0));
+
+    let flood = "PRIVMSG juanireil :spam\r\n".repeat(2 * DEFAULT_MESSAGES_BURST as usize);
+    let result = socket_ari.write_all(flood.as_bytes());
+    assert!(result.is_ok());
+
+    let mut reader_ari = BufReader::new(socket_ari.try_clone().unwrap());
+    let mut data = String::new();
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    let notice = Message {
+        tags: None,
+        prefix: None,
+        command: NOTICE.to_string(),
+        params: vec![vec!["ari".to_string()], vec![FLOOD_NOTICE.to_string()]],
+    };
+    assert_eq!(data, notice.as_string());
+
+    // Only the messages that fit in the burst, and the few refilled meanwhile, are delivered
+    socket_juani
+        .set_read_timeout(Some(Duration::new(2, 0)))
+        .unwrap();
+    let mut reader_juani = BufReader::new(socket_juani);
+    let mut delivered = 0;
+    data.clear();
+    while reader_juani.read_line(&mut data).is_ok() && !data.is_empty() {
+        delivered += 1;
+        data.clear();
+    }
+    assert!(delivered >= DEFAULT_MESSAGES_BURST as usize);
+    assert!(delivered < 2 * DEFAULT_MESSAGES_BURST as usize);
+}
+
+#[test]
+fn names_marks_the_channel_operators() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+    let timeout = Duration::from_secs(5);
+
+    let ari = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(ari.login("ari", "password123", timeout).is_ok());
+    let juani = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(juani.login("juanireil", "password123", timeout).is_ok());
+
+    ari.join("#canal").unwrap();
+    std::thread::sleep(Duration::new(1, 0));
+    juani.join("#canal").unwrap();
+    std::thread::sleep(Duration::new(1, 0));
+
+    juani
+        .send(Message {
+            tags: None,
+            prefix: None,
+            command: NAMES.to_string(),
+            params: vec![vec!["#canal".to_string()]],
+        })
+        .unwrap();
+    assert_eq!(
+        juani.wait_for_text("Users in channel", timeout),
+        Some((
+            "#canal".to_string(),
+            "Users in channel: ari (operator)".to_string()
+        ))
+    );
+}
+
+#[test]
+fn messages_longer_than_the_channel_limit_are_not_sent() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+    let timeout = Duration::from_secs(5);
+
+    let ari = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(ari.login("ari", "password123", timeout).is_ok());
+    let juani = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(juani.login("juanireil", "password123", timeout).is_ok());
+
+    ari.join("#corto").unwrap();
+    std::thread::sleep(Duration::new(1, 0));
+    juani.join("#corto").unwrap();
+    std::thread::sleep(Duration::new(1, 0));
+
+    ari.send(Message {
+        tags: None,
+        prefix: None,
+        command: MODE.to_string(),
+        params: vec![
+            vec!["#corto".to_string()],
+            vec!["+L".to_string()],
+            vec!["10".to_string()],
+        ],
+    })
+    .unwrap();
+    assert!(ari.wait_for_text(RPL_MODESET_MSG, timeout).is_some());
+
+    juani
+        .privmsg("#corto", "Este mensaje es muy largo")
+        .unwrap();
+    assert_eq!(
+        juani.wait_for_text("wasn't sent", timeout),
+        Some((
+            "#corto".to_string(),
+            "The message wasn't sent, the channel allows up to 10 characters".to_string()
+        ))
+    );
+    juani.privmsg("#corto", "Hola").unwrap();
+    assert_eq!(
+        ari.wait_for_text("Hola", timeout),
+        Some(("#corto".to_string(), "juanireil: Hola".to_string()))
+    );
+    assert!(ari
+        .wait_for_text("muy largo", Duration::from_secs(1))
+        .is_none());
+}
+
+#[test]
+fn clients_know_the_users_of_their_channels_to_complete_nicknames() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+    let timeout = Duration::from_secs(5);
+
+    let ari = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(ari.login("ari", "password123", timeout).is_ok());
+    let juani = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(juani.login("juanireil", "password123", timeout).is_ok());
+
+    ari.join("#nombres").unwrap();
+    std::thread::sleep(Duration::new(1, 0));
+    juani.join("#nombres").unwrap();
+    std::thread::sleep(Duration::new(1, 0));
+    juani.privmsg("#nombres", "Hola").unwrap();
+    assert!(ari.wait_for_text("Hola", timeout).is_some());
+    assert_eq!(
+        ari.channel_members("#nombres"),
+        vec!["juanireil".to_string()]
+    );
+
+    juani
+        .send(Message {
+            tags: None,
+            prefix: None,
+            command: NAMES.to_string(),
+            params: vec![vec!["#nombres".to_string()]],
+        })
+        .unwrap();
+    assert!(juani.wait_for_text("Users in channel", timeout).is_some());
+    let mut members = juani.channel_members("#NOMBRES");
+    members.sort();
+    assert_eq!(members, vec!["ari".to_string(), "juanireil".to_string()]);
+}
+
+#[test]
+fn users_are_back_in_their_channels_after_login_again() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+    let timeout = Duration::from_secs(5);
+
+    let ari = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(ari.login("ari", "password123", timeout).is_ok());
+    let juani = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(juani.login("juanireil", "password123", timeout).is_ok());
+
+    ari.join("#vuelta").unwrap();
+    std::thread::sleep(Duration::new(1, 0));
+    juani.join("#vuelta").unwrap();
+    std::thread::sleep(Duration::new(1, 0));
+    ari.quit().unwrap();
+    std::thread::sleep(Duration::new(1, 0));
+
+    let ari = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(ari.login("ari", "password123", timeout).is_ok());
+    let channel_chat = ari.wait_for(timeout, |update| {
+        update.command == *ADD_LIST_CHATS && update.prefix == Some("#vuelta".to_string())
+    });
+    assert!(channel_chat.is_some());
+    assert!(ari.wait_for_text("Users in channel", timeout).is_some());
+    let mut members = ari.channel_members("#vuelta");
+    members.sort();
+    assert_eq!(members, vec!["ari".to_string(), "juanireil".to_string()]);
+
+    juani.privmsg("#vuelta", "Volviste").unwrap();
+    assert!(ari.wait_for_text("Volviste", timeout).is_some());
+}
+
+#[test]
+fn messages_of_ignored_users_are_not_shown() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+    let timeout = Duration::from_secs(5);
+
+    let ari = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(ari.login("ari", "password123", timeout).is_ok());
+    let juani = HeadlessClient::connect("127.0.0.1:3000").unwrap();
+    assert!(juani.login("juanireil", "password123", timeout).is_ok());
+
+    ari.ignore("JuaniReil", true);
+    juani.privmsg("ari", "Mensaje ignorado").unwrap();
+    assert!(ari.wait_for_text("Mensaje ignorado", timeout).is_none());
+    // No chat was opened for the ignored user
+    assert!(!ari.chats().contains(&"juanireil".to_string()));
+
+    ari.ignore("juanireil", false);
+    juani.privmsg("ari", "Mensaje visible").unwrap();
+    assert!(ari.wait_for_text("Mensaje visible", timeout).is_some());
+}
+
+#[test]
+fn mode_without_a_mode_shows_the_modes_of_the_channel() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+
+    let mut socket_ari = login_ari();
+    let mut reader_ari = BufReader::new(socket_ari.try_clone().unwrap());
+    let mut data = String::new();
+
+    std::thread::sleep(Duration::new(2, 0));
+
+    let result = socket_ari.write_all("JOIN #modos\r\n".as_bytes());
+    assert!(result.is_ok());
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    data.clear();
+    read_creation_time(&mut reader_ari, "#modos");
+
+    let result = socket_ari.write_all("MODE #modos +l 5\r\n".as_bytes());
+    assert!(result.is_ok());
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    data.clear();
+
+    let result = socket_ari.write_all("MODE #modos\r\n".as_bytes());
+    assert!(result.is_ok());
+    let result = reader_ari.read_line(&mut data);
+    assert!(result.is_ok());
+    assert_eq!(
+        data,
+        NumericReply::new(
+            RPL_CHANNELMODEIS_NUM,
+            "+l 5",
+            Some(vec!["#modos".to_string()])
+        )
+        .as_string()
+    );
+}
+
+#[test]
+fn labeled_messages_are_acknowledged_and_retransmissions_are_not_handled_again() {
+    let _handle = thread::spawn(move || {
+        let result = run_server();
+        assert!(result.is_ok());
+    });
+
+    std::thread::sleep(Duration::new(5, 0));
+
+    let mut socket_ari = login_ari();
+    let socket_juani = login_juani();
+
+    std::thread::sleep(Duration::new(5, 0));
+
+    // The second message is sent again because its ACK didn't arrive
+    let messages = "@label=1 PRIVMSG juanireil :Hola juani\r\n@label=1 PRIVMSG juanireil :Hola juani\r\n@label=2 PRIVMSG juanireil :Chau juani\r\n";
+    let result = socket_ari.write_all(messages.as_bytes());
+    assert!(result.is_ok());
+
+    let mut reader_ari = BufReader::new(socket_ari);
+    let mut data = String::new();
+    for label in ["1", "1", "2"] {
+        let result = reader_ari.read_line(&mut data);
+        assert!(result.is_ok());
+        assert_eq!(data, format!("@label={} ACK\r\n", label));
+        data.clear();
+    }
+
+    let mut reader_juani = BufReader::new(socket_juani);
+    for text in ["Hola juani", "Chau juani"] {
+        let result = reader_juani.read_line(&mut data);
+        assert!(result.is_ok());
+        let message = Message {
+            tags: None,
+            prefix: Some("ari".to_string()),
+            command: PRIVMSG.to_string(),
+            params: vec![vec!["juanireil".to_string()], vec![text.to_string()]],
+        };
+        assert_eq!(message.as_string(), data);
+        data.clear();
+    }
+}
+
+#[test]
+fn channel_created_at_the_same_time_in_two_servers_is_merged() {
+    let main_address = run_main_server();
+    let first_address = run_secondary_server(&main_address);
+    let second_address = run_secondary_server_named(&main_address, "secondary_server_2", "nico");
+
+    let mut socket_marce = login(
+        &first_address,
+        "marce",
+        "marce",
+        "Marcelo Rondan",
+        "secondary_server_1",
+    );
+    let mut socket_nico = login(
+        &second_address,
+        "nico",
+        "nico",
+        "Nicolas Gomez",
+        "secondary_server_2",
+    );
+
+    let mut data = String::new();
+    let mut reader_marce = BufReader::new(socket_marce.try_clone().unwrap());
+    let mut reader_nico = BufReader::new(socket_nico.try_clone().unwrap());
+
+    // Both join before the other server can know about the channel
+    let join_message = "JOIN #canal\r\n";
+    let result = socket_marce.write_all(join_message.as_bytes());
+    assert!(result.is_ok());
+    let result = socket_nico.write_all(join_message.as_bytes());
+    assert!(result.is_ok());
+
+    let mut created_at = vec![];
+    for reader in [&mut reader_marce, &mut reader_nico] {
+        let result = reader.read_line(&mut data);
+        assert!(result.is_ok());
+        created_at.push(read_creation_time(reader, "#canal"));
+        data.clear();
+    }
+
+    // Wait until the joins reach every server
+    std::thread::sleep(Duration::new(3, 0));
+
+    let names_message = "NAMES #canal\r\n";
+    let mut names = vec![];
+    for (socket, reader) in [
+        (&mut socket_marce, &mut reader_marce),
+        (&mut socket_nico, &mut reader_nico),
+    ] {
+        let result = socket.write_all(names_message.as_bytes());
+        assert!(result.is_ok());
+        let result = reader.read_line(&mut data);
+        assert!(result.is_ok());
+        assert!(data.starts_with(RPL_NAMEREPLY_NUM));
+        names.push(data.split_once(" :").unwrap().1.to_string());
+        data.clear();
+    }
+
+    // Both servers have the same members and operators, whichever created the channel first
+    assert_eq!(names[0], names[1]);
+    let members: Vec<&str> = names[0].split_whitespace().collect();
+    assert!(members.contains(&"@marce"));
+    assert!(members.contains(&"@nico"));
+
+    // And both keep the earliest creation time
+    let topic_message = "TOPIC #canal\r\n";
+    for (socket, reader) in [
+        (&mut socket_marce, &mut reader_marce),
+        (&mut socket_nico, &mut reader_nico),
+    ] {
+        let result = socket.write_all(topic_message.as_bytes());
+        assert!(result.is_ok());
+        // The end of the names is still waiting to be read
+        while !data.starts_with(RPL_NOTOPIC_NUM) {
+            data.clear();
+            let result = reader.read_line(&mut data);
+            assert!(result.is_ok());
+        }
+        data.clear();
+        assert_eq!(
+            read_creation_time(reader, "#canal"),
+            *created_at.iter().min().unwrap()
+        );
+    }
 }